@@ -0,0 +1,73 @@
+// Hex encoding on the seal path: every frame gets a frame hash, a chain link
+// and usually an HMAC, each hex-encoded. Compares the generic `hex` crate
+// with the SIMD encoder the core crate uses.
+//
+//   cargo bench --bench encoding
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sha2::{Digest, Sha256};
+
+use immutable_encryption::{crypto, FrameMetadata, VideoFrame};
+use immutable_encryption_core::hash::{self, GENESIS_HASH};
+
+fn hex_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hex_encode");
+    // A digest, and a 4 KiB buffer to show the throughput ceiling
+    for len in [32usize, 4096] {
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::new("hex", len), &data, |b, data| {
+            b.iter(|| hex::encode(black_box(data)))
+        });
+        group.bench_with_input(BenchmarkId::new("simd", len), &data, |b, data| {
+            b.iter(|| hash::to_hex(black_box(data)))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("hex_decode");
+    let digest = hash::sha256_hex(b"frame");
+    group.bench_function("hex", |b| b.iter(|| hex::decode(black_box(&digest))));
+    group.bench_function("simd", |b| b.iter(|| hash::from_hex(black_box(&digest))));
+    group.finish();
+}
+
+// Everything the seal path hex-encodes for one frame
+fn frame_digests(c: &mut Criterion) {
+    let frame = VideoFrame {
+        timestamp: 1_700_000_000,
+        sequence: 42,
+        data: vec![0xa5; 256 * 1024].into(),
+        metadata: FrameMetadata {
+            device_id: "bench-cam-0".to_string(),
+            location: None,
+            resolution: (1920, 1080),
+            fps: 30,
+            codec: "MJPEG".to_string(),
+            telemetry: None,
+        },
+        signature: None,
+    };
+    let key = [7u8; 32];
+
+    c.bench_function("frame_digests_1080p", |b| {
+        b.iter(|| {
+            let frame_hash = hash::frame_hash(black_box(&frame)).unwrap();
+            let link = hash::chain_link(&frame_hash, GENESIS_HASH, frame.sequence);
+            crypto::hmac_sha256_hex(&key, link.as_bytes())
+        })
+    });
+
+    // The hex part alone, so its share of the total is visible
+    let digest = Sha256::digest(b"frame");
+    c.bench_function("frame_digests_hex_only", |b| {
+        b.iter(|| {
+            for _ in 0..3 {
+                black_box(hash::to_hex(black_box(&digest)));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, hex_encoding, frame_digests);
+criterion_main!(benches);
@@ -0,0 +1,342 @@
+// End-to-end pipeline benchmark: synthetic camera streams are submitted to a
+// real node and go through ingest, encryption, anchoring and RocksDB storage.
+// Anchoring talks to a local mock Ethereum RPC, so the chain's latency is
+// simulated but the anchoring code path is the one the node runs.
+//
+//   cargo bench --features video --bench pipeline [-- 1080p]
+//
+// Environment:
+//   PIPELINE_BENCH_FRAMES              frames per camera (default 120)
+//   PIPELINE_BENCH_CAMERAS             concurrent cameras (default 4)
+//   PIPELINE_BENCH_ANCHOR_LATENCY_MS   mock RPC response time (default 20)
+//   PIPELINE_BENCH_MIN_FPS_<LOAD>      fail when sustained FPS is lower
+//   PIPELINE_BENCH_MAX_P99_MS_<LOAD>   fail when p99 seal latency is higher
+// where <LOAD> is 1080P or 4K.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tempfile::TempDir;
+use warp::Filter;
+
+use immutable_encryption::{
+    blockchain::BlockchainConfig,
+    crypto::CryptoConfig,
+    devices::{AnchorPolicy, DevicePolicies, DevicePolicy},
+    storage::StorageConfig,
+    verification::VerificationConfig,
+    video::{RealTimeEncryptionNode, SubmitOutcome},
+    FrameMetadata, VideoFrame,
+};
+
+const SEAL_WAIT: Duration = Duration::from_secs(10);
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+struct Load {
+    name: &'static str,
+    resolution: (u32, u32),
+    fps: u32,
+    frame_bytes: usize, // sized like an MJPEG frame at this resolution
+}
+
+const LOADS: [Load; 2] = [
+    Load {
+        name: "1080p",
+        resolution: (1920, 1080),
+        fps: 30,
+        frame_bytes: 256 * 1024,
+    },
+    Load {
+        name: "4k",
+        resolution: (3840, 2160),
+        fps: 30,
+        frame_bytes: 1024 * 1024,
+    },
+];
+
+struct Report {
+    frames: usize,
+    stored: u64,
+    elapsed: Duration,
+    latencies: Vec<Duration>, // submit to sealed, sorted
+}
+
+impl Report {
+    fn fps(&self) -> f64 {
+        self.stored as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn percentile(&self, p: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = ((self.latencies.len() as f64 * p).ceil() as usize).max(1);
+        self.latencies[rank.min(self.latencies.len()) - 1]
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // `cargo bench` passes `--bench`; any other argument filters loads by name
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    let frames = env_number("PIPELINE_BENCH_FRAMES").unwrap_or(120.0) as usize;
+    let cameras = env_number("PIPELINE_BENCH_CAMERAS").unwrap_or(4.0) as usize;
+    let anchor_latency = Duration::from_millis(
+        env_number("PIPELINE_BENCH_ANCHOR_LATENCY_MS").unwrap_or(20.0) as u64,
+    );
+
+    let rpc = spawn_mock_rpc(anchor_latency);
+    let mut failures = Vec::new();
+
+    for load in LOADS.iter() {
+        if !filters.is_empty() && !filters.iter().any(|f| load.name.contains(f.as_str())) {
+            continue;
+        }
+
+        let report = match run_load(load, cameras, frames, rpc).await {
+            Ok(report) => report,
+            Err(e) => {
+                failures.push(format!("{}: {}", load.name, e));
+                continue;
+            }
+        };
+
+        let p99 = report.percentile(0.99);
+        println!(
+            "{:>6}  {} cameras x {} frames of {} KiB: {:.1} fps sustained ({:.1} MiB/s), \
+             seal latency p50 {:.1} ms, p99 {:.1} ms",
+            load.name,
+            cameras,
+            frames,
+            load.frame_bytes / 1024,
+            report.fps(),
+            report.fps() * load.frame_bytes as f64 / (1024.0 * 1024.0),
+            report.percentile(0.5).as_secs_f64() * 1000.0,
+            p99.as_secs_f64() * 1000.0
+        );
+
+        if report.stored != report.frames as u64 {
+            failures.push(format!(
+                "{}: {} of {} frames stored",
+                load.name, report.stored, report.frames
+            ));
+        }
+        let key = load.name.to_uppercase();
+        if let Some(min) = env_number(&format!("PIPELINE_BENCH_MIN_FPS_{}", key)) {
+            if report.fps() < min {
+                failures.push(format!(
+                    "{}: {:.1} fps is below the {} fps gate",
+                    load.name,
+                    report.fps(),
+                    min
+                ));
+            }
+        }
+        if let Some(max) = env_number(&format!("PIPELINE_BENCH_MAX_P99_MS_{}", key)) {
+            if p99.as_secs_f64() * 1000.0 > max {
+                failures.push(format!(
+                    "{}: p99 seal latency {:.1} ms is above the {} ms gate",
+                    load.name,
+                    p99.as_secs_f64() * 1000.0,
+                    max
+                ));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    for failure in &failures {
+        eprintln!("FAILED {}", failure);
+    }
+    ExitCode::FAILURE
+}
+
+// Each camera submits its next frame as soon as the last one is sealed, like
+// a client of `POST /frames`; the run ends once every frame is stored
+async fn run_load(
+    load: &Load,
+    cameras: usize,
+    frames: usize,
+    rpc: SocketAddr,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let node = Arc::new(build_node(&temp_dir, rpc).await?);
+    let (sender, _verified) = node.start_processing().await?;
+
+    // Frames are keyed by sequence and timestamp, so cameras get distinct timestamps
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+    let started = Instant::now();
+    let mut tasks = Vec::new();
+    for camera in 0..cameras {
+        let node = node.clone();
+        let sender = sender.clone();
+        let device_id = format!("bench-cam-{}", camera);
+        let payload = synthetic_payload(load.frame_bytes, camera as u64);
+        let (resolution, fps) = (load.resolution, load.fps);
+        let timestamp = now.as_secs() + camera as u64;
+
+        tasks.push(tokio::spawn(async move {
+            let mut latencies = Vec::with_capacity(frames);
+            for _ in 0..frames {
+                let frame = VideoFrame {
+                    timestamp,
+                    sequence: 0, // assigned by the node
+                    data: payload.clone(),
+                    metadata: FrameMetadata {
+                        device_id: device_id.clone(),
+                        location: None,
+                        resolution,
+                        fps,
+                        codec: "MJPEG".to_string(),
+                        telemetry: None,
+                    },
+                    signature: None,
+                };
+                let submitted = Instant::now();
+                match node.submit_frame(&sender, frame, None, SEAL_WAIT).await? {
+                    SubmitOutcome::Sealed(_) => latencies.push(submitted.elapsed()),
+                    SubmitOutcome::Pending { .. } => latencies.push(SEAL_WAIT),
+                }
+            }
+            Ok::<_, immutable_encryption::error::ImmutableEncryptionError>(latencies)
+        }));
+    }
+
+    let mut latencies = Vec::with_capacity(cameras * frames);
+    for task in tasks {
+        latencies.extend(task.await??);
+    }
+    drop(sender);
+
+    // Shutting down flushes the last anchor batch and waits for storage
+    node.shutdown(DRAIN_TIMEOUT).await?;
+    let elapsed = started.elapsed();
+    let stored = node.status().await.frames_stored;
+
+    latencies.sort();
+    Ok(Report {
+        frames: cameras * frames,
+        stored,
+        elapsed,
+        latencies,
+    })
+}
+
+async fn build_node(
+    temp_dir: &TempDir,
+    rpc: SocketAddr,
+) -> Result<RealTimeEncryptionNode, Box<dyn std::error::Error>> {
+    let rpc_url = format!("http://{}", rpc);
+    let node = RealTimeEncryptionNode::new(
+        CryptoConfig::software(vec![7u8; 32], 3600),
+        BlockchainConfig {
+            ethereum_rpc_url: rpc_url.clone(),
+            bitcoin_rpc_url: rpc_url.clone(),
+            private_chain_rpc: rpc_url.clone(),
+            opentimestamps_url: rpc_url,
+        },
+        StorageConfig {
+            database_path: temp_dir.path().to_string_lossy().to_string(),
+            ipfs_enabled: false,
+            ipfs_api_url: String::new(),
+            backup_enabled: false,
+            backup_path: String::new(),
+            compression_enabled: false,
+            frame_encoding: Default::default(),
+            backup_io_uring: false,
+        },
+        VerificationConfig {
+            strict_mode: true,
+            quantum_verification: false,
+            hardware_attestation: false,
+            min_confirmations: HashMap::new(),
+            attestation_key: None,
+            device_keys: HashMap::new(),
+        },
+    )
+    .await?;
+
+    // Bitcoin anchoring waits for a real confirmation, so only the EVM path
+    // is exercised; every frame is anchored
+    let policies = DevicePolicies::new(
+        DevicePolicy {
+            anchoring: AnchorPolicy {
+                chains: vec!["ethereum".to_string()],
+                every: 1,
+            },
+            ..DevicePolicy::default()
+        },
+        HashMap::new(),
+    );
+    Ok(node.with_device_policies(policies))
+}
+
+// Answers the JSON-RPC calls an Ethereum anchor makes after `latency`
+fn spawn_mock_rpc(latency: Duration) -> SocketAddr {
+    let rpc = warp::post().and(warp::body::json()).and_then(
+        move |request: serde_json::Value| async move {
+            tokio::time::sleep(latency).await;
+            let result = match request["method"].as_str() {
+                Some("eth_getTransactionReceipt") => mock_receipt(&request["params"][0]),
+                _ => json!("0x1"),
+            };
+            Ok::<_, Infallible>(warp::reply::json(&json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": result,
+            })))
+        },
+    );
+
+    let (addr, server) = warp::serve(rpc).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    addr
+}
+
+fn mock_receipt(transaction_hash: &serde_json::Value) -> serde_json::Value {
+    let zero_hash = format!("0x{}", "0".repeat(64));
+    json!({
+        "transactionHash": transaction_hash,
+        "transactionIndex": "0x0",
+        "blockHash": zero_hash,
+        "blockNumber": "0x1",
+        "from": format!("0x{}", "0".repeat(40)),
+        "to": "0x1234567890123456789012345678901234567890",
+        "cumulativeGasUsed": "0xc350",
+        "gasUsed": "0xc350",
+        "contractAddress": null,
+        "logs": [],
+        "status": "0x1",
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "type": "0x2",
+        "effectiveGasPrice": "0x1",
+    })
+}
+
+// Incompressible bytes, different per camera, shared by all of its frames
+fn synthetic_payload(len: usize, seed: u64) -> bytes::Bytes {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+fn env_number(name: &str) -> Option<f64> {
+    std::env::var(name).ok()?.parse().ok()
+}
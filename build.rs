@@ -0,0 +1,20 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::configure()
+        .build_server(true)
+        .build_client(true)
+        // Frame payloads decode as `Bytes` so they reach the pipeline uncopied
+        .bytes([
+            ".immutable_encryption.v1.VideoFrame.data",
+            ".immutable_encryption.v1.SubmitFrameRequest.data",
+        ])
+        .compile(
+            &[
+                "proto/immutable_encryption/v1/formats.proto",
+                "proto/immutable_encryption/v1/evidence.proto",
+            ],
+            &["proto"],
+        )?;
+
+    println!("cargo:rerun-if-changed=proto");
+    Ok(())
+}
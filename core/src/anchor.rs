@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::merkle::InclusionProof;
+use crate::types::BlockchainAnchor;
+
+// A proof file written by `blockchain-anchor batch`: one hash's path to the
+// Merkle root that was anchored, with the root's anchors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchoredProof {
+    pub proof: InclusionProof,
+    #[serde(default)]
+    pub anchors: Vec<BlockchainAnchor>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProofFile {
+    Anchored(AnchoredProof),
+    Bare(InclusionProof),
+}
+
+// Accepts a proof file or a bare inclusion proof
+pub fn parse_proof(json: &str) -> Result<AnchoredProof> {
+    match serde_json::from_str(json) {
+        Ok(ProofFile::Anchored(anchored)) => Ok(anchored),
+        Ok(ProofFile::Bare(proof)) => Ok(AnchoredProof {
+            proof,
+            anchors: Vec::new(),
+        }),
+        Err(e) => Err(Error::Verification(format!(
+            "Not an inclusion proof: {}",
+            e
+        ))),
+    }
+}
+
+// An anchor's proof names the transaction and block recorded beside it, as
+// `{chain}-proof:{transaction}:{block}`. Whether that transaction really
+// carries the hash can only be checked against the chain itself.
+pub fn check_anchor(anchor: &BlockchainAnchor) -> Result<()> {
+    let mismatch = || {
+        Error::Verification(format!(
+            "{} anchor {} has proof {:?}",
+            anchor.chain, anchor.transaction_hash, anchor.proof
+        ))
+    };
+    let rest = anchor
+        .proof
+        .strip_prefix(&format!("{}-proof:", anchor.chain))
+        .ok_or_else(mismatch)?;
+    let (transaction, block) = rest.rsplit_once(':').ok_or_else(mismatch)?;
+    if block != anchor.block_number.to_string()
+        || !same_transaction(transaction, &anchor.transaction_hash)
+    {
+        return Err(mismatch());
+    }
+    Ok(())
+}
+
+// Hashes compare without `0x` and case. Older Ethereum proofs abbreviate the
+// hash as `0x1234…abcd`, which matches on its two ends.
+fn same_transaction(proof: &str, recorded: &str) -> bool {
+    let normalize = |hash: &str| hash.trim_start_matches("0x").to_ascii_lowercase();
+    let (proof, recorded) = (normalize(proof), normalize(recorded));
+    match proof.split_once('…') {
+        Some((start, end)) => {
+            !start.is_empty()
+                && !end.is_empty()
+                && recorded.starts_with(start)
+                && recorded.ends_with(end)
+        }
+        None => proof == recorded,
+    }
+}
+
+// `hash` is a leaf of the anchored root and every anchor is consistent
+pub fn check_anchored_proof(hash: &str, anchored: &AnchoredProof) -> Result<()> {
+    if anchored.proof.leaf != hash {
+        return Err(Error::Verification(format!(
+            "Proof is for {}, not {}",
+            anchored.proof.leaf, hash
+        )));
+    }
+    if !anchored.proof.verify() {
+        return Err(Error::Verification(format!(
+            "Inclusion proof for {} does not lead to root {}",
+            hash, anchored.proof.root
+        )));
+    }
+    anchored.anchors.iter().try_for_each(check_anchor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+
+    #[test]
+    fn test_anchored_proof_checks() -> Result<()> {
+        let leaves = vec!["aa".to_string(), "bb".to_string(), "cc".to_string()];
+        let tree = MerkleTree::new(leaves)?;
+        let anchor = BlockchainAnchor {
+            chain: "bitcoin".to_string(),
+            transaction_hash: "f00d".to_string(),
+            block_number: 800_000,
+            timestamp: 1000,
+            proof: "bitcoin-proof:f00d:800000".to_string(),
+        };
+        let json = serde_json::json!({ "proof": tree.proof(1), "anchors": [&anchor] }).to_string();
+
+        let anchored = parse_proof(&json)?;
+        check_anchored_proof("bb", &anchored)?;
+        assert!(check_anchored_proof("cc", &anchored).is_err());
+
+        let bare = serde_json::to_string(&tree.proof(2))?;
+        assert!(parse_proof(&bare)?.anchors.is_empty());
+        assert!(parse_proof("{}").is_err());
+
+        let mut ethereum = anchor.clone();
+        ethereum.chain = "ethereum".to_string();
+        ethereum.transaction_hash = format!("12ab{}cd34", "0".repeat(56));
+        ethereum.proof = "ethereum-proof:0x12ab…cd34:800000".to_string();
+        check_anchor(&ethereum)?;
+
+        let mut forged = anchored.clone();
+        forged.anchors[0].block_number = 800_001;
+        assert!(check_anchored_proof("bb", &forged).is_err());
+        forged.anchors.clear();
+        forged.proof.root = "00".repeat(32);
+        assert!(check_anchored_proof("bb", &forged).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use crate::error::{Error, Result};
+use crate::types::{Cipher, EncryptedFrame};
+
+// Sequences follow on, each frame names its predecessor's hash and timestamps
+// advance. Fails with the first break.
+pub fn check_links(frames: &[EncryptedFrame]) -> Result<()> {
+    for window in frames.windows(2) {
+        let current = &window[0];
+        let next = &window[1];
+
+        if next.sequence != current.sequence + 1 {
+            return Err(Error::InvalidSequence(next.sequence));
+        }
+        if next.previous_hash != current.hash {
+            return Err(Error::HashChainViolation);
+        }
+        if next.timestamp <= current.timestamp {
+            return Err(Error::EvidenceTampered(format!(
+                "Timestamp of frame {} does not advance past frame {}",
+                next.sequence, current.sequence
+            )));
+        }
+    }
+    Ok(())
+}
+
+// What the per-frame checks read: everything but the payload bytes, so an
+// archived frame can be checked in place without decoding it
+pub trait ChainLink {
+    fn sequence(&self) -> u64;
+    fn timestamp(&self) -> u64;
+    fn hash(&self) -> &str;
+    fn previous_hash(&self) -> &str;
+    fn cipher(&self) -> Cipher;
+    fn nonce_len(&self) -> usize;
+    fn ciphertext_len(&self) -> usize;
+    fn attestation(&self) -> Option<(&[u8], &[u8])>; // (quote, signature)
+    fn device_id(&self) -> &str;
+    fn device_signature(&self) -> Option<(&str, &[u8])>; // (payload hash, signature)
+}
+
+impl ChainLink for EncryptedFrame {
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+    fn hash(&self) -> &str {
+        &self.hash
+    }
+    fn previous_hash(&self) -> &str {
+        &self.previous_hash
+    }
+    fn cipher(&self) -> Cipher {
+        self.cipher
+    }
+    fn nonce_len(&self) -> usize {
+        self.nonce.len()
+    }
+    fn ciphertext_len(&self) -> usize {
+        self.ciphertext.len()
+    }
+    fn attestation(&self) -> Option<(&[u8], &[u8])> {
+        self.attestation
+            .as_ref()
+            .map(|a| (a.quote.as_slice(), a.signature.as_slice()))
+    }
+    fn device_id(&self) -> &str {
+        self.device_id.as_str()
+    }
+    fn device_signature(&self) -> Option<(&str, &[u8])> {
+        self.device_signature
+            .as_ref()
+            .map(|s| (s.payload_hash.as_str(), s.signature.as_slice()))
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl ChainLink for crate::types::ArchivedEncryptedFrame {
+    fn sequence(&self) -> u64 {
+        self.sequence.to_native()
+    }
+    fn timestamp(&self) -> u64 {
+        self.timestamp.to_native()
+    }
+    fn hash(&self) -> &str {
+        self.hash.as_str()
+    }
+    fn previous_hash(&self) -> &str {
+        self.previous_hash.as_str()
+    }
+    fn cipher(&self) -> Cipher {
+        use crate::types::ArchivedCipher;
+        match self.cipher {
+            ArchivedCipher::Aes256Gcm => Cipher::Aes256Gcm,
+            ArchivedCipher::ChaCha20Poly1305 => Cipher::ChaCha20Poly1305,
+            ArchivedCipher::XChaCha20Poly1305 => Cipher::XChaCha20Poly1305,
+            ArchivedCipher::Aes256GcmSiv => Cipher::Aes256GcmSiv,
+        }
+    }
+    fn nonce_len(&self) -> usize {
+        self.nonce.len()
+    }
+    fn ciphertext_len(&self) -> usize {
+        self.ciphertext.len()
+    }
+    fn attestation(&self) -> Option<(&[u8], &[u8])> {
+        self.attestation
+            .as_ref()
+            .map(|a| (a.quote.as_slice(), a.signature.as_slice()))
+    }
+    fn device_id(&self) -> &str {
+        self.device_id.as_str()
+    }
+    fn device_signature(&self) -> Option<(&str, &[u8])> {
+        self.device_signature
+            .as_ref()
+            .map(|s| (s.payload_hash.as_str(), s.signature.as_slice()))
+    }
+}
+
+// A 64-hex-digit hash, a nonce of its cipher's length and a non-empty
+// ciphertext
+pub fn is_well_formed<F: ChainLink + ?Sized>(frame: &F) -> bool {
+    frame.hash().len() == 64
+        && frame.hash().chars().all(|c| c.is_ascii_hexdigit())
+        && frame.nonce_len() == frame.cipher().nonce_len()
+        && frame.ciphertext_len() > 0
+}
+
+// The checks `check_links` makes across a slice, applied to one frame and its
+// predecessor so long evidence sets can be checked as a stream. Every problem
+// is reported rather than only the first.
+pub fn frame_anomalies<F: ChainLink + ?Sized>(previous: Option<&F>, frame: &F) -> Vec<String> {
+    let mut anomalies = Vec::new();
+    let sequence = frame.sequence();
+
+    if !is_well_formed(frame) {
+        anomalies.push(format!("Frame {} is malformed", sequence));
+    }
+
+    if let Some(previous) = previous {
+        let previous_sequence = previous.sequence();
+        if sequence != previous_sequence + 1 {
+            anomalies.push(format!(
+                "Sequence gap detected: frame {} to {} (expected {})",
+                previous_sequence,
+                sequence,
+                previous_sequence + 1
+            ));
+        }
+        if frame.previous_hash() != previous.hash() {
+            anomalies.push(format!(
+                "Hash chain break between frame {} and {}",
+                previous_sequence, sequence
+            ));
+        }
+        if frame.timestamp() <= previous.timestamp() {
+            anomalies.push(format!(
+                "Timestamp of frame {} does not advance past frame {}",
+                sequence, previous_sequence
+            ));
+        }
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(sequence: u64, hash: char, previous: char, timestamp: u64) -> EncryptedFrame {
+        EncryptedFrame {
+            sequence,
+            device_id: "cam_1".to_string(),
+            ciphertext: vec![1],
+            hash: hash.to_string().repeat(64),
+            previous_hash: previous.to_string().repeat(64),
+            nonce: vec![0; 12],
+            timestamp,
+            blockchain_anchors: Vec::new(),
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        }
+    }
+
+    #[test]
+    fn test_chain_breaks_are_found() {
+        let chain = [frame(1, 'a', '0', 10), frame(2, 'b', 'a', 11)];
+        assert!(check_links(&chain).is_ok());
+        assert!(frame_anomalies(Some(&chain[0]), &chain[1]).is_empty());
+
+        let gap = [frame(1, 'a', '0', 10), frame(3, 'b', 'a', 11)];
+        assert!(matches!(check_links(&gap), Err(Error::InvalidSequence(3))));
+
+        let broken = [frame(1, 'a', '0', 10), frame(2, 'b', 'c', 11)];
+        assert!(matches!(
+            check_links(&broken),
+            Err(Error::HashChainViolation)
+        ));
+
+        let stale = frame(2, 'b', 'c', 10);
+        assert_eq!(frame_anomalies(Some(&chain[0]), &stale).len(), 2);
+
+        let mut malformed = frame(1, 'a', '0', 10);
+        malformed.nonce.clear();
+        assert!(!is_well_formed(&malformed));
+
+        // Extended nonces only pass for the cipher that uses them
+        let mut extended = frame(1, 'a', '0', 10);
+        extended.nonce = vec![0; 24];
+        assert!(!is_well_formed(&extended));
+        extended.cipher = Cipher::XChaCha20Poly1305;
+        assert!(is_well_formed(&extended));
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn test_archived_frames_are_checked_in_place() {
+        use crate::types::ArchivedEncryptedFrame;
+
+        let archive = |frame: &EncryptedFrame| {
+            rkyv::to_bytes::<rkyv::rancor::Error>(frame)
+                .unwrap()
+                .to_vec()
+        };
+        let first = archive(&frame(1, 'a', '0', 10));
+        // Unaligned archives can be read at any offset, as in a tagged record
+        let stale = [&[0u8][..], &archive(&frame(2, 'b', 'c', 10))].concat();
+
+        let access =
+            |bytes| rkyv::access::<ArchivedEncryptedFrame, rkyv::rancor::Error>(bytes).unwrap();
+        let (first, stale) = (access(&first[..]), access(&stale[1..]));
+        assert_eq!(first.hash(), "a".repeat(64));
+        assert_eq!(frame_anomalies(Some(first), stale).len(), 2);
+    }
+}
@@ -0,0 +1,25 @@
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+// Messages match the node's errors for the same failures
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Invalid frame sequence: {0}")]
+    InvalidSequence(u64),
+
+    #[error("Hash chain integrity violation")]
+    HashChainViolation,
+
+    #[error("Evidence tampered: {0}")]
+    EvidenceTampered(String),
+
+    #[error("Verification error: {0}")]
+    Verification(String),
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Verification(format!("JSON serialization error: {}", err))
+    }
+}
@@ -0,0 +1,104 @@
+use blake3::Hasher;
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+use crate::types::VideoFrame;
+
+// `previous_hash` of the first frame in a device's chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// BLAKE3 over SHA-256 of the frame's sequence, timestamp, payload and metadata
+pub fn frame_hash(frame: &VideoFrame) -> Result<String> {
+    let mut sha256 = Sha256::new();
+    sha256.update(frame.sequence.to_be_bytes());
+    sha256.update(frame.timestamp.to_be_bytes());
+    sha256.update(&frame.data);
+    sha256.update(serde_json::to_string(&frame.metadata)?.as_bytes());
+    let sha_result = sha256.finalize();
+
+    let mut blake3 = Hasher::new();
+    blake3.update(&sha_result);
+    Ok(to_hex(blake3.finalize().as_bytes()))
+}
+
+// The chain hash stored on a sealed frame, binding it to its predecessor
+pub fn chain_link(frame_hash: &str, previous_hash: &str, sequence: u64) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(frame_hash.as_bytes());
+    hasher.update(previous_hash.as_bytes());
+    hasher.update(sequence.to_be_bytes());
+    to_hex(&hasher.finalize())
+}
+
+// What a capturing device signs with its identity key: the frame's place in
+// its chain and SHA-256 of the payload as captured
+pub fn capture_message(
+    device_id: &str,
+    sequence: u64,
+    timestamp: u64,
+    payload_hash: &str,
+) -> Vec<u8> {
+    format!(
+        "capture:{}:{}:{}:{}",
+        device_id, sequence, timestamp, payload_hash
+    )
+    .into_bytes()
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+pub fn blake3_hex(data: &[u8]) -> String {
+    to_hex(blake3::hash(data).as_bytes())
+}
+
+// Lowercase hex, SIMD-accelerated where the CPU supports it. Every sealed
+// frame goes through this several times, so it sits on the ingest hot path.
+pub fn to_hex(data: &[u8]) -> String {
+    faster_hex::hex_string(data)
+}
+
+// Accepts either case; None for odd lengths or non-hex digits
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let mut decoded = vec![0; hex.len() / 2];
+    faster_hex::hex_decode(hex.as_bytes(), &mut decoded).ok()?;
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameMetadata;
+
+    #[test]
+    fn test_chain_link_binds_predecessor_and_sequence() {
+        let frame = VideoFrame {
+            timestamp: 1000,
+            sequence: 1,
+            data: vec![1, 2, 3].into(),
+            metadata: FrameMetadata {
+                device_id: "cam_1".to_string(),
+                location: None,
+                resolution: (640, 480),
+                fps: 15,
+                codec: "MJPEG".to_string(),
+                telemetry: None,
+            },
+            signature: None,
+        };
+        let hash = frame_hash(&frame).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, frame_hash(&frame.clone()).unwrap());
+
+        let link = chain_link(&hash, GENESIS_HASH, 1);
+        assert_ne!(link, chain_link(&hash, GENESIS_HASH, 2));
+        assert_ne!(link, chain_link(&hash, &link, 1));
+        assert_eq!(GENESIS_HASH, "0".repeat(64));
+
+        assert_eq!(from_hex(&hash).map(|bytes| to_hex(&bytes)), Some(hash));
+        assert_eq!(from_hex("00FFab"), Some(vec![0x00, 0xff, 0xab]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
@@ -0,0 +1,14 @@
+// Types, hashing and proof checks shared by the node and by anything that only
+// needs to verify what the node produced. Nothing here can decrypt a frame.
+pub mod anchor;
+pub mod chain;
+pub mod error;
+pub mod hash;
+pub mod merkle;
+pub mod types;
+
+pub use error::{Error, Result};
+pub use types::{
+    BlockchainAnchor, Cipher, CustodyEntry, DeviceSignature, EncryptedFrame, FrameMetadata,
+    HardwareAttestation, LegalCompliance, Telemetry, VideoFrame,
+};
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::hash::{from_hex, to_hex};
+
+// Leaves and interior nodes hash under different prefixes so a leaf can never
+// be passed off as a subtree
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub side: Side, // where the sibling sits relative to the running hash
+    pub hash: String,
+}
+
+// Shows that `leaf` is the `index`th input under `root`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub leaf: String,
+    pub index: usize,
+    pub root: String,
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    pub fn verify(&self) -> bool {
+        let mut hash = leaf_hash(&self.leaf);
+        for step in &self.path {
+            let sibling = match from_hex(&step.hash) {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+            hash = match step.side {
+                Side::Left => node_hash(&sibling, &hash),
+                Side::Right => node_hash(&hash, &sibling),
+            };
+        }
+        to_hex(&hash) == self.root
+    }
+}
+
+// A level-by-level tree over string leaves. An odd node at the end of a level
+// is carried up unchanged rather than paired with itself.
+pub struct MerkleTree {
+    leaves: Vec<String>,
+    levels: Vec<Vec<Vec<u8>>>, // levels[0] holds the leaf hashes
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<String>) -> Result<Self> {
+        if leaves.is_empty() {
+            return Err(Error::Verification(
+                "A Merkle tree needs at least one leaf".to_string(),
+            ));
+        }
+
+        let mut levels = vec![leaves
+            .iter()
+            .map(|leaf| leaf_hash(leaf))
+            .collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    _ => pair[0].clone(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(Self { leaves, levels })
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn root(&self) -> String {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .map(|hash| to_hex(hash))
+            .unwrap_or_default()
+    }
+
+    pub fn proof(&self, index: usize) -> Option<InclusionProof> {
+        let leaf = self.leaves.get(index)?.clone();
+        let mut path = Vec::new();
+        let mut position = index;
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                path.push(ProofStep {
+                    side: if sibling < position {
+                        Side::Left
+                    } else {
+                        Side::Right
+                    },
+                    hash: to_hex(hash),
+                });
+            }
+            position /= 2;
+        }
+
+        Some(InclusionProof {
+            leaf,
+            index,
+            root: self.root(),
+            path,
+        })
+    }
+}
+
+fn leaf_hash(leaf: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(leaf.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_leaf_proves_inclusion() -> Result<()> {
+        for count in 1..=9 {
+            let leaves: Vec<String> = (0..count).map(|i| format!("{:064x}", i)).collect();
+            let tree = MerkleTree::new(leaves)?;
+
+            for index in 0..count {
+                let proof = tree.proof(index).expect("leaf exists");
+                assert!(proof.verify(), "leaf {} of {}", index, count);
+
+                let mut forged = proof.clone();
+                forged.leaf = format!("{:064x}", 99);
+                assert!(!forged.verify());
+            }
+            assert!(tree.proof(count).is_none());
+        }
+        assert!(MerkleTree::new(Vec::new()).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoFrame {
+    pub timestamp: u64,
+    pub sequence: u64,
+    // Shared rather than copied as the frame moves through the pipeline
+    pub data: Bytes,
+    pub metadata: FrameMetadata,
+    // The capturing device's Ed25519 signature over `hash::capture_message`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameMetadata {
+    pub device_id: String,
+    pub location: Option<(f64, f64)>,
+    pub resolution: (u32, u32),
+    pub fps: u32,
+    pub codec: String,
+    #[serde(default)]
+    pub telemetry: Option<Telemetry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Telemetry {
+    pub source: String,
+    pub sampled_at_ms: u64,
+    pub altitude_m: Option<f64>,
+    pub speed_mps: Option<f64>,
+    pub heading_deg: Option<f64>,
+    pub roll_deg: Option<f64>,
+    pub pitch_deg: Option<f64>,
+    pub yaw_deg: Option<f64>,
+    pub fix_quality: Option<u8>,
+    pub satellites: Option<u8>,
+}
+
+// Frame ciphers; all take the same 256-bit scheduled keys. XChaCha20's
+// 192-bit nonces are safe to draw at random for any number of frames under
+// one key, where 96-bit ones aren't in long-running sessions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub enum Cipher {
+    #[default]
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-poly1305")]
+    ChaCha20Poly1305, // faster than AES on devices without AES instructions
+    #[serde(rename = "xchacha20-poly1305")]
+    XChaCha20Poly1305,
+    #[serde(rename = "aes-256-gcm-siv")]
+    Aes256GcmSiv, // a repeated nonce, e.g. after a clock reset, only leaks equal frames
+}
+
+impl Cipher {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            Cipher::Aes256Gcm | Cipher::ChaCha20Poly1305 | Cipher::Aes256GcmSiv => 12,
+            Cipher::XChaCha20Poly1305 => 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct EncryptedFrame {
+    pub sequence: u64,
+    pub device_id: String,
+    pub ciphertext: Vec<u8>,
+    pub hash: String,
+    pub previous_hash: String,
+    pub nonce: Vec<u8>,
+    pub timestamp: u64,
+    pub blockchain_anchors: Vec<BlockchainAnchor>,
+    // These are omitted at their defaults so older frames serialize unchanged
+    #[serde(default, skip_serializing_if = "Cipher::is_default")]
+    pub cipher: Cipher,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compressed: bool, // payload was zstd-compressed before sealing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<HardwareAttestation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_signature: Option<DeviceSignature>,
+}
+
+// A TPM 2.0 quote whose qualifying data is SHA-256 of the frame's hash, so
+// through the hash chain it vouches for every earlier frame too. Like
+// anchors, it is added after sealing and isn't part of the hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct HardwareAttestation {
+    pub quote: Vec<u8>,     // marshalled TPMS_ATTEST
+    pub signature: Vec<u8>, // ECDSA P-256 over `quote`, r || s
+}
+
+// What the capturing device signed with its identity key. The payload hash
+// is of the data as captured, so the signature can be checked without
+// decrypting the frame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct DeviceSignature {
+    pub payload_hash: String, // hex SHA-256
+    pub signature: Vec<u8>,   // Ed25519 over `hash::capture_message`
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct BlockchainAnchor {
+    pub chain: String,
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub proof: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodyEntry {
+    pub timestamp: u64,
+    pub actor: String,
+    pub action: String,
+    pub signature: String,
+    pub blockchain_reference: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegalCompliance {
+    pub standards_met: Vec<String>,
+    pub certifications: Vec<String>,
+    pub jurisdiction_compliance: Vec<String>,
+}
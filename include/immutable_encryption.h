@@ -0,0 +1,110 @@
+/*
+ * C API for sealing video frames on the camera itself.
+ *
+ * Link against the cdylib built by `cargo build --release --lib`
+ * (libimmutable_encryption.so / .dylib / immutable_encryption.dll).
+ *
+ * A sealer holds one device's hash chain and is not thread-safe: use it from
+ * one thread at a time. Strings returned through `char **` are owned by the
+ * caller and must be released with ie_string_free().
+ */
+#ifndef IMMUTABLE_ENCRYPTION_H
+#define IMMUTABLE_ENCRYPTION_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define IE_CIPHER_AES_256_GCM 0u
+#define IE_CIPHER_CHACHA20_POLY1305 1u /* faster on cores without AES instructions */
+#define IE_CIPHER_XCHACHA20_POLY1305 2u /* 192-bit random nonces for long-running sessions */
+#define IE_CIPHER_AES_256_GCM_SIV 3u /* survives a repeated nonce, e.g. after a clock reset */
+
+/* Hex SHA-256 chain tip plus the terminating NUL */
+#define IE_CHAIN_TIP_LEN 65
+
+/* An Ed25519 identity key's seed and public key */
+#define IE_IDENTITY_KEY_LEN 32
+
+typedef enum ie_status {
+    IE_OK = 0,
+    IE_ERR_INVALID_ARGUMENT = 1, /* NULL pointer, bad UTF-8, non-increasing sequence, ... */
+    IE_ERR_CRYPTO = 2,           /* bad key length, RNG or cipher failure */
+    IE_ERR_BUFFER_TOO_SMALL = 3,
+    IE_ERR_INTERNAL = 4
+} ie_status;
+
+typedef struct ie_sealer ie_sealer;
+
+typedef struct ie_frame {
+    const uint8_t *data;
+    size_t data_len;
+    uint64_t timestamp;
+    uint64_t sequence; /* 0 takes the next one; otherwise must exceed the last */
+    uint32_t width;
+    uint32_t height;
+    uint32_t fps;
+    const char *codec; /* may be NULL */
+} ie_frame;
+
+/*
+ * Message for the last failed call on this thread, or NULL. Valid until the
+ * next failing call on the same thread; do not free it.
+ */
+const char *ie_last_error(void);
+
+/*
+ * Creates a sealer for `device_id` (NUL-terminated UTF-8) with a 32-byte key
+ * provisioned by the node. Frame keys are derived from it per
+ * `key_rotation_interval` seconds, which must be the node's
+ * encryption.key_rotation_interval_seconds. The chain starts at the all-zero
+ * hash. Returns NULL on error; see ie_last_error().
+ */
+ie_sealer *ie_sealer_new(const uint8_t *key, size_t key_len, const char *device_id,
+                         uint32_t cipher, uint64_t key_rotation_interval);
+
+/* Frees a sealer; NULL is ignored */
+void ie_sealer_free(ie_sealer *sealer);
+
+/*
+ * Continues a chain persisted before a reboot: `chain_tip` is the 64-character
+ * hex tip from ie_chain_tip() and `last_sequence` the last sequence sealed.
+ */
+ie_status ie_sealer_resume(ie_sealer *sealer, const char *chain_tip, uint64_t last_sequence);
+
+/*
+ * Signs every frame sealed from now on with the device's Ed25519 identity key,
+ * given as its IE_IDENTITY_KEY_LEN-byte seed. Unless NULL, `out_public_key`
+ * receives the IE_IDENTITY_KEY_LEN-byte public key to register with the node
+ * under verification.device_keys.
+ */
+ie_status ie_sealer_set_identity_key(ie_sealer *sealer, const uint8_t *seed, size_t seed_len,
+                                     uint8_t *out_public_key);
+
+/*
+ * Hashes, chains and encrypts one frame. On success `*out_json` holds the
+ * sealed frame as JSON, in the format the node stores and ingests.
+ */
+ie_status ie_seal_frame(ie_sealer *sealer, const ie_frame *frame, char **out_json);
+
+/* Copies the current chain tip into `out`, which needs IE_CHAIN_TIP_LEN bytes */
+ie_status ie_chain_tip(const ie_sealer *sealer, char *out, size_t out_len);
+
+/*
+ * Signed proof over the frames sealed since the previous export: their
+ * sequence range, the tips before and after, and a Merkle root over their
+ * chain hashes. `*out_json` is set to NULL when nothing was sealed since.
+ */
+ie_status ie_export_proof(ie_sealer *sealer, char **out_json);
+
+/* Frees a string returned by this library; NULL is ignored */
+void ie_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* IMMUTABLE_ENCRYPTION_H */
@@ -1,24 +1,26 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgGroup, ArgMatches, Command};
 use std::collections::HashMap;
 use std::fs;
+use std::io::BufRead;
+use std::path::Path;
 use tracing::{error, info, warn};
-use tracing_subscriber;
 
 use immutable_encryption::{
-    blockchain::{BlockchainConfig, MultiChainAnchor},
-    config::Config,
-    FrameMetadata,
+    anchor_history::AnchorHistory,
+    blockchain::{AnchorObservation, BlockchainConfig, InclusionState, MultiChainAnchor},
+    completions,
+    config::{Config, LoadOptions},
+    merkle::MerkleTree,
+    session,
+    storage::DistributedStorage,
+    tenant::{self, tenant_storage_config, DEFAULT_TENANT},
+    trace, FrameMetadata,
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
-
     // Parse command line arguments
-    let matches = Command::new("blockchain-anchor")
+    let cli = Command::new("blockchain-anchor")
         .version("0.1.0")
         .about("Standalone blockchain anchoring tool")
         .arg(
@@ -28,13 +30,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .value_name("FILE")
                 .help("Configuration file path"),
         )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["toml", "yaml", "json"])
+                .help("Config file format (default: by extension)"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Overlay config.<NAME>.toml on the config file (default: $IE_PROFILE)"),
+        )
         .arg(
             Arg::new("hash")
                 .short('h')
                 .long("hash")
                 .value_name("HASH")
                 .help("Hash to anchor to blockchain")
-                .required(true),
+                .required_unless_present_any(["verify", "hashes-file"]),
+        )
+        .arg(
+            Arg::new("hashes-file")
+                .long("hashes-file")
+                .value_name("FILE")
+                .conflicts_with("hash")
+                .help(
+                    "Anchor one Merkle root over the hashes in FILE, one per line ('-' for stdin)",
+                ),
+        )
+        .arg(
+            Arg::new("proofs-dir")
+                .long("proofs-dir")
+                .value_name("DIR")
+                .default_value("anchor_proofs")
+                .help("Where batch mode writes per-hash inclusion proofs"),
         )
         .arg(
             Arg::new("metadata")
@@ -64,43 +95,136 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .value_name("ANCHOR_FILE")
                 .help("Verify existing anchor from JSON file"),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("status")
+                .about("Check where existing anchors stand and record it in their history")
+                .arg(
+                    Arg::new("tx")
+                        .long("tx")
+                        .value_name("HASH")
+                        .help("Anchor transaction to check"),
+                )
+                .arg(
+                    Arg::new("chain")
+                        .long("chain")
+                        .value_name("CHAIN")
+                        .value_parser(["ethereum", "bitcoin"])
+                        .requires("tx")
+                        .help("Chain --tx is on; every configured chain is asked if omitted"),
+                )
+                .arg(
+                    Arg::new("evidence-id")
+                        .long("evidence-id")
+                        .value_name("ID")
+                        .help("Check the anchors of a sealed session in the local database"),
+                )
+                .group(
+                    ArgGroup::new("target")
+                        .args(["tx", "evidence-id"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .default_value(DEFAULT_TENANT)
+                        .help("Tenant that owns the evidence"),
+                )
+                .arg(
+                    Arg::new("history-dir")
+                        .long("history-dir")
+                        .value_name("DIR")
+                        .default_value("anchor_history")
+                        .help("Where each transaction's timestamped status history is kept"),
+                ),
+        )
+        .subcommand(
+            Command::new("estimate")
+                .about("Quote the current cost of anchoring from live fee markets")
+                .arg(
+                    Arg::new("chains")
+                        .long("chains")
+                        .value_name("CHAINS")
+                        .help("Comma-separated list of chains (ethereum,bitcoin,private)")
+                        .default_value("ethereum,bitcoin"),
+                )
+                .arg(
+                    Arg::new("count")
+                        .long("count")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("1")
+                        .help("Hashes to anchor, one at a time or as one Merkle batch"),
+                ),
+        )
+        .subcommand_negates_reqs(true)
+        .subcommand(completions::command());
+    let matches = cli.clone().get_matches();
+    if let Some(("completions", args)) = matches.subcommand() {
+        return Ok(completions::generate(cli, args, &mut std::io::stdout())?);
+    }
 
     // Load configuration
-    let config = if let Some(config_path) = matches.get_one::<String>("config") {
-        Config::load_from_file(config_path)?
-    } else {
-        Config::load()?
-    };
+    let config = Config::load_with(load_options(&matches)?)?
+        .resolve_secrets()
+        .await?;
 
-    // Validate configuration
+    // Validate configuration, then log as it says
     config.validate()?;
+    let _tracing = trace::init(&config.logging)?;
 
     // Initialize blockchain anchor
     let blockchain_config = config.get_blockchain_config();
     let anchor = MultiChainAnchor::new(blockchain_config).await?;
 
+    if let Some(("estimate", args)) = matches.subcommand() {
+        return estimate_costs(&anchor, args).await;
+    }
+
+    if let Some(("status", args)) = matches.subcommand() {
+        let settled = anchor_status(&config, &anchor, args).await?;
+        std::process::exit(if settled { 0 } else { 1 });
+    }
+
     if let Some(anchor_file) = matches.get_one::<String>("verify") {
         // Verify mode
         verify_anchor(&anchor, anchor_file).await?;
+        return Ok(());
+    }
+
+    // Load or create metadata
+    let metadata = if let Some(metadata_file) = matches.get_one::<String>("metadata") {
+        load_metadata_from_file(metadata_file)?
+    } else {
+        let device_id = matches.get_one::<String>("device-id").unwrap();
+        create_default_metadata(device_id)
+    };
+
+    if let Some(hashes_file) = matches.get_one::<String>("hashes-file") {
+        // Batch mode
+        let proofs_dir = matches.get_one::<String>("proofs-dir").unwrap();
+        anchor_batch(&anchor, hashes_file, proofs_dir, &metadata).await?;
     } else {
         // Anchor mode
         let hash = matches.get_one::<String>("hash").unwrap();
-
-        // Load or create metadata
-        let metadata = if let Some(metadata_file) = matches.get_one::<String>("metadata") {
-            load_metadata_from_file(metadata_file)?
-        } else {
-            let device_id = matches.get_one::<String>("device-id").unwrap();
-            create_default_metadata(device_id)
-        };
-
         anchor_hash(&anchor, hash, &metadata).await?;
     }
 
     Ok(())
 }
 
+// The --config, --profile and --format flags
+fn load_options(matches: &ArgMatches) -> Result<LoadOptions<'_>, Box<dyn std::error::Error>> {
+    Ok(LoadOptions {
+        path: matches.get_one::<String>("config").map(String::as_str),
+        profile: matches.get_one::<String>("profile").map(String::as_str),
+        format: matches
+            .get_one::<String>("format")
+            .map(|f| f.parse())
+            .transpose()?,
+    })
+}
+
 async fn anchor_hash(
     anchor: &MultiChainAnchor,
     hash: &str,
@@ -129,6 +253,83 @@ async fn anchor_hash(
     Ok(())
 }
 
+// Anchors a single Merkle root over every hash in the file, then writes one
+// inclusion proof per hash next to the root's anchors
+async fn anchor_batch(
+    anchor: &MultiChainAnchor,
+    hashes_file: &str,
+    proofs_dir: &str,
+    metadata: &FrameMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hashes = read_hashes(hashes_file)?;
+    let tree = MerkleTree::new(hashes)?;
+    let root = tree.root();
+    info!("Anchoring Merkle root {} over {} hashes", root, tree.len());
+
+    let anchors = anchor.anchor_to_all_chains(&root, metadata).await?;
+    for anchor_result in &anchors {
+        println!(
+            "Chain: {} Transaction Hash: {}",
+            anchor_result.chain, anchor_result.transaction_hash
+        );
+    }
+
+    fs::create_dir_all(proofs_dir)?;
+    let summary = serde_json::json!({
+        "merkle_root": root,
+        "leaf_count": tree.len(),
+        "anchors": anchors
+    });
+    fs::write(
+        Path::new(proofs_dir).join("root.json"),
+        serde_json::to_string_pretty(&summary)?,
+    )?;
+    for index in 0..tree.len() {
+        let proof = tree.proof(index).ok_or("Merkle proof index out of range")?;
+        let output = serde_json::json!({ "proof": &proof, "anchors": &anchors });
+        fs::write(
+            Path::new(proofs_dir).join(format!("{}.json", proof.leaf)),
+            serde_json::to_string_pretty(&output)?,
+        )?;
+    }
+    info!("Wrote {} inclusion proofs to {}", tree.len(), proofs_dir);
+
+    Ok(())
+}
+
+// One hex hash per line; blank lines are skipped and repeats anchored once.
+// Hashes double as proof file names, so anything else is rejected.
+fn read_hashes(path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let reader: Box<dyn BufRead> = if path == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(std::io::BufReader::new(fs::File::open(path)?))
+    };
+
+    let mut hashes = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let hash = line.trim().to_ascii_lowercase();
+        if hash.is_empty() {
+            continue;
+        }
+        if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Line {} is not a hex hash: {}", number + 1, line).into());
+        }
+        if seen.insert(hash.clone()) {
+            hashes.push(hash);
+        } else {
+            warn!("Skipping repeated hash {}", hash);
+        }
+    }
+
+    if hashes.is_empty() {
+        return Err(format!("No hashes found in {}", path).into());
+    }
+    Ok(hashes)
+}
+
 async fn verify_anchor(
     anchor: &MultiChainAnchor,
     anchor_file: &str,
@@ -175,6 +376,159 @@ async fn verify_anchor(
     Ok(())
 }
 
+// Anchoring N hashes one by one costs N transactions; a Merkle batch costs one
+// whatever its size, so both are shown side by side
+async fn estimate_costs(
+    anchor: &MultiChainAnchor,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let count = *args.get_one::<u64>("count").unwrap();
+    let chains = args.get_one::<String>("chains").unwrap();
+
+    let mut quotes = Vec::new();
+    for chain in chains.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        let quote = anchor.fee_quote(chain).await?;
+        println!("Chain: {}", quote.chain);
+        println!(
+            "Fee Rate: {:.2} {} ({} per anchor)",
+            quote.fee_rate,
+            quote.fee_rate_unit,
+            match quote.chain.as_str() {
+                "bitcoin" => format!("{} vbytes", quote.units_per_anchor),
+                _ => format!("{} gas", quote.units_per_anchor),
+            }
+        );
+        println!("Per Anchor: {:.8} {}", quote.cost_per_anchor, quote.coin);
+        println!(
+            "{} Individual Anchors: {:.8} {}",
+            count,
+            quote.cost_per_anchor * count as f64,
+            quote.coin
+        );
+        println!(
+            "One Merkle Batch of {}: {:.8} {} ({:.8} per hash)",
+            count,
+            quote.cost_per_anchor,
+            quote.coin,
+            quote.cost_per_anchor / count as f64
+        );
+        println!("---");
+        quotes.push(quote);
+    }
+
+    let output_file = "anchor_estimate.json";
+    let summary = serde_json::json!({ "count": count, "quotes": quotes });
+    fs::write(output_file, serde_json::to_string_pretty(&summary)?)?;
+    info!("Estimate saved to: {}", output_file);
+    Ok(())
+}
+
+// Queries each anchor's chain, appends the result to the anchor's history
+// and prints it. Returns false if any anchor was reorganized out of
+// its block or can't be found.
+async fn anchor_status(
+    config: &Config,
+    anchor: &MultiChainAnchor,
+    args: &ArgMatches,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let history_dir = args.get_one::<String>("history-dir").unwrap();
+    let targets = status_targets(config, anchor, args).await?;
+
+    let mut settled = true;
+    for (chain, transaction_hash, recorded_block) in targets {
+        let observation = anchor.observe(&chain, &transaction_hash).await?;
+        let mut history =
+            AnchorHistory::load(history_dir, &chain, &transaction_hash, recorded_block)?;
+        let observation = history.record(observation).clone();
+        let path = history.save(history_dir)?;
+
+        if observation.reorged {
+            warn!(
+                "{} anchor {} has been reorganized out of its block",
+                chain, transaction_hash
+            );
+        }
+        settled &= !observation.reorged && observation.state != InclusionState::Missing;
+        print_observation(&observation, history.observations.len());
+        info!("History saved to: {}", path.display());
+    }
+
+    Ok(settled)
+}
+
+// (chain, transaction, block recorded at anchoring) for each anchor to check
+async fn status_targets(
+    config: &Config,
+    anchor: &MultiChainAnchor,
+    args: &ArgMatches,
+) -> Result<Vec<(String, String, Option<u64>)>, Box<dyn std::error::Error>> {
+    if let Some(evidence_id) = args.get_one::<String>("evidence-id") {
+        let tenant_id = args.get_one::<String>("tenant").unwrap();
+        tenant::validate_tenant_id(tenant_id)?;
+        let storage = DistributedStorage::new(tenant_storage_config(
+            &config.get_storage_config(),
+            &config.storage.tenants_path,
+            tenant_id,
+        ))
+        .await?;
+        let manifest = session::load_manifest(&storage, evidence_id)
+            .await?
+            .ok_or_else(|| format!("Session {} is not sealed or does not exist", evidence_id))?;
+        if manifest.anchors.is_empty() {
+            return Err(format!("Session {} has no anchors", evidence_id).into());
+        }
+        return Ok(manifest
+            .anchors
+            .iter()
+            .map(|a| {
+                let recorded = Some(a.block_number).filter(|&b| b > 0);
+                (a.chain.clone(), a.transaction_hash.clone(), recorded)
+            })
+            .collect());
+    }
+
+    let tx = args.get_one::<String>("tx").unwrap();
+    if let Some(chain) = args.get_one::<String>("chain") {
+        return Ok(vec![(chain.clone(), tx.clone(), None)]);
+    }
+
+    // Without --chain, keep the chains that know the transaction
+    let mut found = Vec::new();
+    for chain in anchor.status_chains() {
+        match anchor.observe(chain, tx).await {
+            Ok(o) if o.state != InclusionState::Missing => {
+                found.push((chain.to_string(), tx.clone(), None))
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Could not check {} on {}: {}", tx, chain, e),
+        }
+    }
+    if found.is_empty() {
+        return Err(format!("No configured chain knows transaction {}", tx).into());
+    }
+    Ok(found)
+}
+
+fn print_observation(observation: &AnchorObservation, checks: usize) {
+    println!("Chain: {}", observation.chain);
+    println!("Transaction Hash: {}", observation.transaction_hash);
+    println!("State: {:?}", observation.state);
+    if let Some(block_number) = observation.block_number {
+        println!(
+            "Block: {} ({})",
+            block_number,
+            observation.block_hash.as_deref().unwrap_or("hash unknown")
+        );
+    }
+    println!("Confirmations: {}", observation.confirmations);
+    println!(
+        "Reorganized: {}",
+        if observation.reorged { "yes" } else { "no" }
+    );
+    println!("Checks recorded: {}", checks);
+    println!("---");
+}
+
 fn load_metadata_from_file(file_path: &str) -> Result<FrameMetadata, Box<dyn std::error::Error>> {
     let content = fs::read_to_string(file_path)?;
     let metadata: FrameMetadata = serde_json::from_str(&content)?;
@@ -188,5 +542,6 @@ fn create_default_metadata(device_id: &str) -> FrameMetadata {
         resolution: (1920, 1080),
         fps: 30,
         codec: "H.264".to_string(),
+        telemetry: None,
     }
 }
@@ -1,20 +1,68 @@
-use clap::{Arg, Command};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{error, info, warn};
-use tracing_subscriber;
 
-use immutable_encryption::{config::Config, FrameMetadata, RealTimeEncryptionNode, VideoFrame};
+use immutable_encryption::{
+    admin::{LegalHoldRelease, LegalHoldRequest},
+    aff4,
+    api_keys::ApiKeyRequest,
+    approval::ApprovalAction,
+    auth::{JwtAuthenticator, Principal, RequestAuthenticator, Role},
+    bundle::{if_range_matches, parse_range, verify_bundle, EvidenceBundle, BUNDLE_CONTENT_TYPE},
+    case::CaseRequest,
+    clip::SharedClip,
+    cluster::{self, LeaseKeeper, LeaseStore},
+    completions,
+    config::{Config, ConfigFormat, LoadOptions, TlsConfig},
+    content_credentials::ContentCredentials,
+    cose::{self, ENVELOPE_CONTENT_TYPE},
+    crypto::{self, EncryptionEngine},
+    device_auth::{ClientAuthConfig, ClientCertificate, DeviceCertificateRegistry},
+    devices::DevicePolicies,
+    edge::{EdgeSync, SyncBatch},
+    enrollment::{DeviceIssuer, EnrollmentRequest},
+    error::ImmutableEncryptionError,
+    evidence::{EvidenceQuery, FrameQuery},
+    export,
+    ffi::DeviceSegment,
+    grpc::EvidenceGrpcService,
+    health::{self, HealthReport, HealthState},
+    keystore::{self, Keystore},
+    metrics, mp4,
+    notifications::{build_sinks, EventBus},
+    playback::{
+        FrameSelector, PlaybackQuery, PlaybackRequest, PlaybackService, SnapshotFormat,
+        MJPEG_BOUNDARY,
+    },
+    rate_limit::RateLimiter,
+    recovery::{self, CrashState},
+    redaction::RedactionRequest,
+    rendition::{hash_rendition_upload, TranscodeProfile},
+    report::ReportFormat,
+    sensors::{spawn_sensor_feed, TelemetryMerger},
+    stats::NodeStatus,
+    storage::{self, DistributedStorage},
+    tenant::{self, tenant_crypto_config, tenant_storage_config, TenantDirectory, DEFAULT_TENANT},
+    timesync::{spawn_time_sync, TimeMonitor},
+    trace::{self, REQUEST_ID_HEADER},
+    verification::VerificationEngine,
+    verify_jobs::VerificationProgress,
+    video::SubmitOutcome,
+    FrameMetadata, RealTimeEncryptionNode, VideoFrame,
+};
+use std::sync::Arc;
+
+const DEMO_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+const DEMO_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+const IMPORT_SEAL_WAIT: Duration = Duration::from_secs(30);
+const IMPORT_DRAIN_TIMEOUT: Duration = Duration::from_secs(120);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
-
     // Parse command line arguments
-    let matches = Command::new("encryption-node")
+    let cli = Command::new("encryption-node")
         .version("0.1.0")
         .about("Real-time immutable video encryption and blockchain anchoring")
         .arg(
@@ -22,8 +70,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .short('c')
                 .long("config")
                 .value_name("FILE")
+                .global(true)
                 .help("Configuration file path"),
         )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["toml", "yaml", "json"])
+                .global(true)
+                .help("Config file format (default: by extension)"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .global(true)
+                .help("Overlay config.<NAME>.toml on the config file (default: $IE_PROFILE)"),
+        )
         .arg(
             Arg::new("demo")
                 .short('d')
@@ -37,14 +101,442 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .value_name("PORT")
                 .help("Server port"),
         )
-        .get_matches();
+        .arg(
+            Arg::new("print-config")
+                .long("print-config")
+                .action(ArgAction::SetTrue)
+                .help("Print the effective configuration with secrets redacted and exit"),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Verify evidence offline and print the result as JSON")
+                .arg(
+                    Arg::new("bundle")
+                        .long("bundle")
+                        .value_name("PATH")
+                        .help("Evidence bundle, export archive or AFF4 container"),
+                )
+                .arg(
+                    Arg::new("evidence-id")
+                        .long("evidence-id")
+                        .value_name("ID")
+                        .help("Session in the local database; the node must not be running"),
+                )
+                .arg(
+                    Arg::new("clip")
+                        .long("clip")
+                        .value_name("PATH")
+                        .help("Shared clip from POST /evidence/{id}/clips"),
+                )
+                .arg(
+                    Arg::new("public-key")
+                        .long("public-key")
+                        .value_name("HEX")
+                        .requires("clip")
+                        .help("Node's envelope key for --clip; defaults to the tenant's own"),
+                )
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .default_value(DEFAULT_TENANT)
+                        .help("Tenant whose key sealed the evidence"),
+                )
+                .group(
+                    ArgGroup::new("source")
+                        .args(["bundle", "evidence-id", "clip"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about(
+                    "Write a sealed session's signed bundle to a tar.zst archive or AFF4 container",
+                )
+                .arg(
+                    Arg::new("evidence-id")
+                        .long("evidence-id")
+                        .value_name("ID")
+                        .required(true)
+                        .help("Session in the local database; the node must not be running"),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("Archive to write, e.g. bundle.tar.zst or evidence.aff4"),
+                )
+                .arg(
+                    Arg::new("container")
+                        .long("container")
+                        .value_name("FORMAT")
+                        .value_parser(["tar", "aff4"])
+                        .default_value("tar")
+                        .help("tar: zstd-compressed tar; aff4: AFF4 logical container"),
+                )
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .default_value(DEFAULT_TENANT)
+                        .help("Tenant that owns the evidence"),
+                )
+                .arg(
+                    Arg::new("authorization-key")
+                        .long("authorization-key")
+                        .value_name("API_KEY")
+                        .help("Auditor or prosecutor API key; adds decrypted media to the archive"),
+                )
+                .arg(
+                    Arg::new("content-credentials")
+                        .long("content-credentials")
+                        .action(ArgAction::SetTrue)
+                        .requires("authorization-key")
+                        .help("Embed C2PA Content Credentials in exported stills"),
+                )
+                .arg(
+                    Arg::new("approval")
+                        .long("approval")
+                        .value_name("REQUEST_ID")
+                        .requires("authorization-key")
+                        .help("Approved request to decrypt the session; see [approvals.decrypt]"),
+                ),
+        )
+        .subcommand(
+            Command::new("decrypt")
+                .about("Write decrypted frames to disk, recording the decryption in custody")
+                .arg(
+                    Arg::new("frame-id")
+                        .long("frame-id")
+                        .value_name("ID")
+                        .action(ArgAction::Append)
+                        .help("Frame to decrypt; repeat for several"),
+                )
+                .arg(
+                    Arg::new("device")
+                        .long("device")
+                        .value_name("DEVICE")
+                        .requires_all(["from", "to"])
+                        .help("Decrypt this device's frames between --from and --to"),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_name("UNIX_SECS")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("UNIX_SECS")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("approval")
+                        .long("approval")
+                        .value_name("REQUEST_ID")
+                        .help("Decrypt the session of this approved request"),
+                )
+                .group(
+                    ArgGroup::new("selector")
+                        .args(["frame-id", "device", "approval"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("DIR")
+                        .required(true)
+                        .help("Directory to write decrypted frames to"),
+                )
+                .arg(
+                    Arg::new("container")
+                        .long("container")
+                        .value_name("FORMAT")
+                        .value_parser(["raw", "mjpeg"])
+                        .default_value("raw")
+                        .help("raw: one file per frame; mjpeg: one stream per device"),
+                )
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .default_value(DEFAULT_TENANT)
+                        .help("Tenant that owns the frames"),
+                )
+                .arg(
+                    Arg::new("authorization-key")
+                        .long("authorization-key")
+                        .value_name("API_KEY")
+                        .required(true)
+                        .help("Auditor or prosecutor API key"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Seal and anchor an existing MP4 recording as a new session")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("MP4 file; each video sample becomes one sealed frame"),
+                )
+                .arg(
+                    Arg::new("device-id")
+                        .long("device-id")
+                        .value_name("ID")
+                        .required(true)
+                        .help("Device that recorded the footage"),
+                )
+                .arg(
+                    Arg::new("case")
+                        .long("case")
+                        .value_name("CASE_ID")
+                        .help("Case to file the session under"),
+                )
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .default_value(DEFAULT_TENANT)
+                        .help("Tenant that owns the footage"),
+                ),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Rewrite a tenant's database in the current on-disk format")
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .default_value(DEFAULT_TENANT)
+                        .help("Tenant whose database to migrate; the node must not be running"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Report what would be upgraded without writing"),
+                ),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Create and check configuration files")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("init")
+                        .about(
+                            "Write a validated config.toml, prompting for endpoints on a terminal",
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE")
+                                .default_value("config.toml")
+                                .help("Where to write the configuration"),
+                        )
+                        .arg(
+                            Arg::new("data-dir")
+                                .long("data-dir")
+                                .value_name("DIR")
+                                .default_value(".")
+                                .help("Directory for keys, database, backups and logs"),
+                        )
+                        .arg(Arg::new("port").long("port").value_name("PORT"))
+                        .arg(
+                            Arg::new("ethereum-rpc")
+                                .long("ethereum-rpc")
+                                .value_name("URL"),
+                        )
+                        .arg(
+                            Arg::new("bitcoin-rpc")
+                                .long("bitcoin-rpc")
+                                .value_name("URL"),
+                        )
+                        .arg(
+                            Arg::new("private-chain-rpc")
+                                .long("private-chain-rpc")
+                                .value_name("URL"),
+                        )
+                        .arg(Arg::new("ipfs-api").long("ipfs-api").value_name("URL"))
+                        .arg(
+                            Arg::new("no-ipfs")
+                                .long("no-ipfs")
+                                .action(ArgAction::SetTrue)
+                                .conflicts_with("ipfs-api")
+                                .help("Disable IPFS replication"),
+                        )
+                        .arg(
+                            Arg::new("non-interactive")
+                                .long("non-interactive")
+                                .action(ArgAction::SetTrue)
+                                .help("Never prompt; take values from flags and defaults"),
+                        )
+                        .arg(
+                            Arg::new("skip-checks")
+                                .long("skip-checks")
+                                .action(ArgAction::SetTrue)
+                                .help("Don't probe the RPC and IPFS endpoints"),
+                        )
+                        .arg(
+                            Arg::new("allow-unreachable")
+                                .long("allow-unreachable")
+                                .action(ArgAction::SetTrue)
+                                .help("Save even if an endpoint can't be reached"),
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .long("force")
+                                .action(ArgAction::SetTrue)
+                                .help("Overwrite an existing file"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("validate")
+                        .about("Check every config field and list all problems with their paths")
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .value_name("FORMAT")
+                                .value_parser(["text", "json"])
+                                .default_value("text")
+                                .help("Print a list or a JSON report"),
+                        ),
+                )
+                .subcommand(Command::new("seal").about(
+                    "Seal a value read from stdin with the keystore, for pasting into the config",
+                )),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check the config, keystore, endpoints and database and print the results")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FORMAT")
+                        .value_parser(["text", "json"])
+                        .default_value("text")
+                        .help("Print a table or a JSON health report"),
+                ),
+        )
+        .subcommand(
+            Command::new("keys")
+                .about("Create and manage the keystore at encryption.primary_key_path")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("keygen")
+                        .about("Create a new keystore")
+                        .arg(force_arg()),
+                )
+                .subcommand(
+                    Command::new("rotate")
+                        .about("Replace the primary key, retiring the current one"),
+                )
+                .subcommand(
+                    Command::new("export-public").about("Print the public signing and KEM keys"),
+                )
+                .subcommand(
+                    Command::new("shard")
+                        .about("Split the keystore into recovery shares")
+                        .arg(
+                            Arg::new("threshold")
+                                .long("threshold")
+                                .value_name("N")
+                                .value_parser(clap::value_parser!(u8))
+                                .default_value("3")
+                                .help("Shares needed to recover"),
+                        )
+                        .arg(
+                            Arg::new("shares")
+                                .long("shares")
+                                .value_name("N")
+                                .value_parser(clap::value_parser!(u8))
+                                .default_value("5")
+                                .help("Shares to produce"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("recover")
+                        .about("Rebuild the keystore from recovery shares")
+                        .arg(
+                            Arg::new("share")
+                                .long("share")
+                                .value_name("SHARE")
+                                .action(ArgAction::Append)
+                                .required(true)
+                                .help("A share printed by `keys shard`; repeat per share"),
+                        )
+                        .arg(force_arg()),
+                )
+                .subcommand(
+                    Command::new("verification-key")
+                        .about("Print the key the browser verifier checks signatures with")
+                        .arg(
+                            Arg::new("tenant")
+                                .long("tenant")
+                                .value_name("TENANT")
+                                .default_value(DEFAULT_TENANT)
+                                .help("Tenant whose signatures will be checked"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("envelope-key")
+                        .about("Print the public key that verifies COSE frame envelopes")
+                        .arg(
+                            Arg::new("tenant")
+                                .long("tenant")
+                                .value_name("TENANT")
+                                .default_value(DEFAULT_TENANT)
+                                .help("Tenant whose envelopes will be checked"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("attestation-key")
+                        .about("Print the TPM key that verifies frame attestations"),
+                ),
+        )
+        .subcommand(completions::command());
+    let matches = cli.clone().get_matches();
+    if let Some(("completions", args)) = matches.subcommand() {
+        return Ok(completions::generate(cli, args, &mut std::io::stdout())?);
+    }
+
+    // On a terminal, a keystore passphrase in neither the config nor the
+    // environment is asked for instead
+    if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        keystore::set_passphrase_prompt(prompt_passphrase);
+    }
+
+    // Scaffolding writes a config, so it mustn't depend on loading one
+    if let Some(("config", args)) = matches.subcommand() {
+        return match args.subcommand() {
+            Some(("init", args)) => init_config(args).await,
+            Some(("validate", args)) => {
+                let valid = validate_config(&matches, args).await?;
+                std::process::exit(if valid { 0 } else { 1 });
+            }
+            Some(("seal", _)) => seal_config_value(&matches),
+            _ => Err("Unknown config command".into()),
+        };
+    }
+
+    // The doctor reports a broken config as a failed check rather than erroring
+    if let Some(("doctor", args)) = matches.subcommand() {
+        let healthy = run_doctor(&matches, args).await?;
+        std::process::exit(if healthy { 0 } else { 1 });
+    }
 
     // Load configuration
-    let config = if let Some(config_path) = matches.get_one::<String>("config") {
-        Config::load_from_file(config_path)?
-    } else {
-        Config::load()?
-    };
+    let loaded = Config::load_sealed_with(load_options(&matches)?)?;
+    let config = loaded
+        .clone()
+        .open_sealed_values()?
+        .resolve_secrets()
+        .await?;
 
     // Override port if provided
     let mut config = config;
@@ -52,175 +544,3822 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         config.server.port = port.parse().map_err(|e| format!("Invalid port: {}", e))?;
     }
 
+    // Shows what the node would run with after profiles, IE__ variables,
+    // secrets and flags; exits non-zero if that config is invalid
+    if matches.get_flag("print-config") {
+        let format = match matches.get_one::<String>("format") {
+            Some(format) => format.parse()?,
+            None => ConfigFormat::from_path(
+                matches
+                    .get_one::<String>("config")
+                    .map_or("config.toml", String::as_str),
+            ),
+        };
+        print!("{}", format.serialize(&config.redacted(&loaded)?)?);
+        let report = config.validation_report();
+        if !report.is_valid() {
+            eprintln!("{}", report);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Offline verification runs before logging starts, so stdout carries only
+    // the result; invalid evidence exits non-zero
+    match matches.subcommand() {
+        Some(("verify", args)) => {
+            let is_valid = verify_offline(&config, args).await?;
+            std::process::exit(if is_valid { 0 } else { 1 });
+        }
+        Some(("export", args)) => return export_evidence(&config, args).await,
+        Some(("decrypt", args)) => return decrypt_frames(&config, args).await,
+        Some(("import", args)) => return import_video(&config, args).await,
+        Some(("migrate", args)) => return migrate_storage(&config, args).await,
+        Some(("keys", args)) => return manage_keys(&config, args),
+        _ => {}
+    }
+
+    // Validate configuration and create the directories it writes into,
+    // including the log file's
+    config.validate()?;
+    config.create_directories()?;
+
+    // Initialize logging and span export; flushes outstanding spans on exit
+    let _tracing = trace::init(&config.logging)?;
+
     info!(
         "Starting Immutable Encryption Node on port {}",
         config.server.port
     );
 
-    // Validate configuration
-    config.validate()?;
+    // Deliver pipeline events to the configured sinks
+    let events = if config.notifications.enabled {
+        let events = EventBus::new(config.notifications.queue_capacity);
+        for sink in build_sinks(&config.notifications).await? {
+            info!("Delivering notifications to {}", sink.name());
+            events.attach(sink);
+        }
+        Some(events)
+    } else {
+        None
+    };
 
-    // Initialize the encryption node
-    let node = RealTimeEncryptionNode::new(
-        config.get_crypto_config(),
-        config.get_blockchain_config(),
-        config.get_storage_config(),
-        config.get_verification_config(),
-    )
-    .await?;
+    // Merge external GPS/IMU telemetry into frame metadata if configured
+    let telemetry = if config.sensors.enabled {
+        let merger = Arc::new(tokio::sync::RwLock::new(TelemetryMerger::new(
+            &config.sensors,
+        )));
+        spawn_sensor_feed(config.sensors.clone(), merger.clone());
+        Some(merger)
+    } else {
+        None
+    };
+
+    // Check the clock frame timestamps come from, and how far to trust it
+    let time_sync = if config.time_sync.enabled {
+        let monitor = Arc::new(TimeMonitor::new(config.time_sync.clone()));
+        spawn_time_sync(monitor.clone());
+        Some(monitor)
+    } else {
+        warn!("Time sync checks are disabled; frame timestamps come from an unverified clock");
+        None
+    };
+
+    // Config changes show up in each node's audit log as a new fingerprint
+    let config_fingerprint = crypto::sha256_hex(&serde_json::to_vec(&config.redacted(&loaded)?)?);
+
+    // Clustered nodes share the data volume; only the leader opens the
+    // databases, so standbys wait here until its lease lapses or is released
+    let leadership = if config.cluster.enabled {
+        let store = Arc::new(LeaseStore::new(config.cluster.clone()));
+        let leadership = cluster::await_leadership(&store).await?;
+        info!(
+            "{} leads the cluster at epoch {}",
+            leadership.lease.node_id, leadership.lease.epoch
+        );
+        Some((store, leadership))
+    } else {
+        None
+    };
+
+    // Each tenant gets its own node: a key derived for it, its own database
+    // and its own pipeline
+    let mut runtimes = std::collections::HashMap::new();
+    let mut crash_targets = Vec::new();
+    let mut cluster_states = Vec::new();
+    for tenant_id in tenant::tenant_ids(&config.tenants)? {
+        let mut registry = DeviceCertificateRegistry::new(config.server.tls.client_auth.as_ref());
+        if config.enrollment.required {
+            registry = registry.with_enrollment_required();
+        }
+        let mut node = RealTimeEncryptionNode::new(
+            tenant_crypto_config(&config.get_crypto_config()?, &tenant_id)?,
+            config.get_blockchain_config(),
+            tenant_storage_config(
+                &config.get_storage_config(),
+                &config.storage.tenants_path,
+                &tenant_id,
+            ),
+            config.get_verification_config(),
+        )
+        .await?
+        .with_watermark(config.watermark.clone())
+        .with_ingest_config(config.ingest.clone())
+        .with_pipeline_config(config.pipeline.clone())
+        .with_device_policies(DevicePolicies::from_config(&config))
+        .with_device_registry(registry);
+        if config.enrollment.enabled {
+            node = node.with_enrollment(DeviceIssuer::load(&config.enrollment)?);
+        }
+        if config.edge.enabled || !config.edge.trusted_nodes.is_empty() {
+            node = node.with_edge(EdgeSync::new(&config.edge)?);
+        }
+        node = node.with_approvals(config.approvals.clone());
+        if let Some(events) = &events {
+            node = node.with_events(events.clone());
+        }
+        if let Some(merger) = &telemetry {
+            node = node.with_telemetry(merger.clone());
+        }
+        if let Some(monitor) = &time_sync {
+            node = node.with_time_sync(monitor.clone());
+        }
+        // Resume the chain and account for frames an earlier crash cut off,
+        // or the previous leader left in flight
+        let crash_dir =
+            tenant::tenant_dir(std::path::Path::new(&config.storage.crash_dir), &tenant_id);
+        if let Some((_, leadership)) = &leadership {
+            leadership.restore_checkpoint(&tenant_id, &crash_dir)?;
+        }
+        for report in node.recover_from_crash(&crash_dir).await? {
+            warn!(
+                "Recovered from {} ({}): {} frame(s) lost, {} left unanchored",
+                report.snapshot,
+                report.reason,
+                report.lost_frames.len(),
+                report.unanchored_frames.len()
+            );
+            for frame in &report.lost_frames {
+                warn!(
+                    "Lost in the crash: {} sequence {} ({})",
+                    frame.device_id, frame.sequence, frame.hash
+                );
+            }
+        }
+        crash_targets.push((crash_dir, node.crash_state()));
+        if let Some((_, leadership)) = leadership.as_ref().filter(|(_, l)| l.previous.is_some()) {
+            let handoff = node.record_handoff(leadership).await?;
+            info!(
+                "Recorded {} for tenant {} at {}",
+                handoff.describe(),
+                tenant_id,
+                handoff.describe_tips()
+            );
+        }
+        cluster_states.push((tenant_id.clone(), node.crash_state()));
+
+        // Devices enrolled or revoked in earlier runs
+        let enrolled = node.load_enrollments().await?;
+        if config.enrollment.enabled {
+            info!("{} device(s) enrolled for tenant {}", enrolled, tenant_id);
+        }
+
+        if node.record_config(&config_fingerprint).await? {
+            info!("Recorded config {} in the audit log", config_fingerprint);
+        }
+        if config.audit.anchor_interval_secs > 0 {
+            node.spawn_audit_anchoring(Duration::from_secs(config.audit.anchor_interval_secs));
+        }
+        if config.edge.enabled {
+            info!(
+                "Edge mode: anchoring is deferred to {}",
+                config.edge.central_url
+            );
+            node.spawn_edge_sync(Duration::from_secs(config.edge.sync_interval_secs));
+        }
 
-    // Start the processing pipeline
-    let (frame_sender, encrypted_receiver) = node.start_processing().await?;
+        // Start the processing pipeline
+        let (frame_sender, _) = node.start_processing().await?;
+        let playback = Arc::new(node.playback_service(config.playback.clone()));
+        if tenant_id != DEFAULT_TENANT {
+            info!("Serving tenant {}", tenant_id);
+        }
+        runtimes.insert(
+            tenant_id,
+            TenantRuntime {
+                node,
+                frame_sender,
+                playback,
+            },
+        );
+    }
+    let tenants = Arc::new(TenantDirectory::new(&config.tenants, runtimes)?);
+    recovery::install_panic_hook(crash_targets.clone());
+    let default = tenants
+        .get(DEFAULT_TENANT)
+        .ok_or("The default tenant is not being served")?;
 
     // Start demo mode if requested
     if matches.get_flag("demo") {
         info!("Starting demo mode with simulated video frames");
+        let demo_sender = default.frame_sender.clone();
+        let demo_node = default.node.clone();
+        tokio::spawn(async move {
+            demo_video_generation(demo_sender, demo_node).await;
+        });
+    }
+
+    // SIGTERM/SIGINT flips this so every server and the pipeline can drain
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let shutdown_tx = Arc::new(shutdown_tx);
+    {
+        let shutdown_tx = shutdown_tx.clone();
+        tokio::spawn(async move {
+            match shutdown_signal().await {
+                Ok(signal) => info!("Received {}, shutting down", signal),
+                Err(e) => error!("Failed to listen for shutdown signals: {}", e),
+            }
+            let _ = shutdown_tx.send(true);
+        });
+    }
+
+    // A leader that can no longer renew its lease stops, since a standby is
+    // about to take over the chain
+    let lease_keeper = leadership.map(|(store, leadership)| {
+        let shutdown_tx = shutdown_tx.clone();
+        LeaseKeeper::spawn(store, leadership.lease, cluster_states, move |_| {
+            let _ = shutdown_tx.send(true);
+        })
+    });
+    let grace = Duration::from_secs(config.server.shutdown_timeout_secs);
+
+    // Start the gRPC API alongside HTTP for non-Rust integrations. Its callers
+    // are identified by device certificate only, so it serves the default tenant.
+    if let Some(grpc_port) = config.server.grpc_port {
+        let addr = std::net::SocketAddr::new(config.server.host.parse()?, grpc_port);
+        let router = EvidenceGrpcService::new(default.node.clone(), default.frame_sender.clone())
+            .into_router(grpc_tls_config(&config.server.tls)?)?;
+        info!("Starting gRPC server on {}", addr);
+        let shutdown = wait_for_shutdown(shutdown_rx.clone());
         tokio::spawn(async move {
-            demo_video_generation(frame_sender).await;
+            if let Err(e) = router.serve_with_shutdown(addr, shutdown).await {
+                error!("gRPC server failed: {}", e);
+            }
         });
     }
 
-    // Start HTTP server for API endpoints
-    start_http_server(config, node).await?;
+    // Start HTTP server for API endpoints. After a signal, in-flight requests
+    // get the grace period; long-lived streams are cut off once it expires.
+    let server = start_http_server(config, tenants.clone(), shutdown_rx.clone());
+    tokio::pin!(server);
+    let deadline = tokio::select! {
+        result = &mut server => {
+            snapshot_on_error(&crash_targets, result)?;
+            tokio::time::Instant::now() + grace
+        }
+        _ = wait_for_shutdown(shutdown_rx) => {
+            let deadline = tokio::time::Instant::now() + grace;
+            match tokio::time::timeout_at(deadline, &mut server).await {
+                Ok(result) => snapshot_on_error(&crash_targets, result)?,
+                Err(_) => warn!("HTTP connections still open after {:?}; closing them", grace),
+            }
+            deadline
+        }
+    };
+
+    // Seal, anchor and store whatever is still queued before exiting
+    info!("Draining the encryption pipelines");
+    let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+    let drained = futures::future::join_all(
+        tenants
+            .iter()
+            .map(|(_, tenant)| tenant.node.shutdown(remaining)),
+    )
+    .await;
+    if let Some(Err(e)) = drained.into_iter().find(|result| result.is_err()) {
+        error!("Shutdown incomplete: {}", e);
+        return snapshot_on_error(&crash_targets, Err(e.into()));
+    }
+    info!("Pipelines drained, exiting");
+
+    // Hand the drained chains straight to a standby
+    if let Some(keeper) = lease_keeper {
+        keeper.release().await?;
+        info!("Released cluster leadership");
+    }
+
+    // A panic the node survived left a snapshot that is now stale; a final one
+    // lets the next start resume from where the chain really ended
+    for (dir, state) in &crash_targets {
+        if !recovery::pending_snapshots(dir)?.is_empty() {
+            recovery::write_snapshot(dir, state, "shutdown after a survived panic");
+        }
+    }
 
     Ok(())
 }
 
-async fn demo_video_generation(sender: immutable_encryption::FrameSender) {
-    let mut sequence = 0;
-    let mut interval = tokio::time::interval(Duration::from_millis(33)); // ~30 FPS
+// Leaves a crash snapshot for every tenant when the node is exiting on an
+// error, so the next start can account for what was still in flight
+fn snapshot_on_error<T, E: std::fmt::Display>(
+    targets: &[(std::path::PathBuf, Arc<CrashState>)],
+    result: Result<T, E>,
+) -> Result<T, E> {
+    if let Err(e) = &result {
+        let reason = format!("fatal error: {}", e);
+        for (dir, state) in targets {
+            recovery::write_snapshot(dir, state, &reason);
+        }
+    }
+    result
+}
 
-    loop {
-        interval.tick().await;
+// The --config, --profile and --format flags
+fn load_options(matches: &ArgMatches) -> Result<LoadOptions<'_>, Box<dyn std::error::Error>> {
+    Ok(LoadOptions {
+        path: matches.get_one::<String>("config").map(String::as_str),
+        profile: matches.get_one::<String>("profile").map(String::as_str),
+        format: matches
+            .get_one::<String>("format")
+            .map(|f| f.parse())
+            .transpose()?,
+    })
+}
 
-        sequence += 1;
+// `encryption-node config init`: fills a default config from flags (and, on a
+// terminal, prompts), validates it and checks its endpoints before saving
+async fn init_config(args: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::IsTerminal;
 
-        // Simulate video frame data
-        let frame_data = vec![0u8; 1024 * 1024]; // 1MB frame
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    let output = args
+        .get_one::<String>("output")
+        .map(String::as_str)
+        .unwrap_or("config.toml");
+    if std::path::Path::new(output).exists() && !args.get_flag("force") {
+        return Err(format!("{} already exists; pass --force to replace it", output).into());
+    }
+    let interactive = !args.get_flag("non-interactive") && std::io::stdin().is_terminal();
+    let value = |name: &str, label: &str, default: &str| -> std::io::Result<String> {
+        let default = args.get_one::<String>(name).map_or(default, String::as_str);
+        if interactive {
+            prompt(label, default)
+        } else {
+            Ok(default.to_string())
+        }
+    };
 
-        let frame = VideoFrame {
-            timestamp,
-            sequence,
-            data: frame_data,
-            metadata: FrameMetadata {
-                device_id: "demo_drone_001".to_string(),
-                location: Some((40.7128 + (sequence as f64 * 0.0001), -74.0060)), // Moving coordinates
-                resolution: (1920, 1080),
-                fps: 30,
-                codec: "H.264".to_string(),
-            },
-        };
+    let mut config = Config::default();
+    // Paths stay relative to the data root, which is pinned down absolutely
+    let data_dir = std::env::current_dir()?.join(value("data-dir", "Data directory", ".")?);
+    config.storage.data_dir = Some(data_dir.to_string_lossy().to_string());
 
-        if let Err(e) = sender.send(frame) {
-            error!("Failed to send demo frame: {}", e);
-            break;
+    config.server.port = value("port", "HTTP port", &config.server.port.to_string())?
+        .parse()
+        .map_err(|e| format!("Invalid port: {}", e))?;
+    config.blockchain.ethereum.rpc_url = value(
+        "ethereum-rpc",
+        "Ethereum RPC URL",
+        &config.blockchain.ethereum.rpc_url,
+    )?;
+    config.blockchain.bitcoin.rpc_url = value(
+        "bitcoin-rpc",
+        "Bitcoin API URL",
+        &config.blockchain.bitcoin.rpc_url,
+    )?;
+    config.blockchain.private_chain.rpc_url = value(
+        "private-chain-rpc",
+        "Private chain RPC URL",
+        &config.blockchain.private_chain.rpc_url,
+    )?;
+    if args.get_flag("no-ipfs") {
+        config.storage.ipfs.enabled = false;
+    } else {
+        config.storage.ipfs.api_url =
+            value("ipfs-api", "IPFS API URL", &config.storage.ipfs.api_url)?;
+    }
+    // The file keeps relative paths; checks run against where they resolve to
+    let mut resolved = config.clone();
+    resolved.resolve_paths(&data_dir);
+    resolved.validate()?;
+
+    if !args.get_flag("skip-checks") {
+        let dependencies = health::probe_endpoints(&resolved).await?;
+        for dependency in &dependencies {
+            match &dependency.error {
+                None => eprintln!("ok      {} ({}ms)", dependency.name, dependency.latency_ms),
+                Some(e) => eprintln!("FAILED  {}: {}", dependency.name, e),
+            }
         }
+        let unreachable = dependencies.iter().filter(|d| !d.healthy).count();
+        let proceed = unreachable == 0
+            || args.get_flag("allow-unreachable")
+            || (interactive && prompt("Some endpoints are unreachable. Save anyway?", "n")? == "y");
+        if !proceed {
+            return Err(format!(
+                "{} endpoint(s) unreachable; fix them or pass --allow-unreachable",
+                unreachable
+            )
+            .into());
+        }
+    }
 
-        if sequence % 100 == 0 {
-            info!("Generated {} demo frames", sequence);
+    resolved.create_directories()?;
+    config.save_to_file(output)?;
+    eprintln!(
+        "Wrote {}. Create its keystore with: encryption-node --config {} keys keygen",
+        output, output
+    );
+    Ok(())
+}
+
+// Asks on stderr so stdout stays clean; an empty answer keeps the default
+fn prompt(label: &str, default: &str) -> std::io::Result<String> {
+    use std::io::Write;
+
+    eprint!("{} [{}]: ", label, default);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+// Read from the terminal without echoing it
+fn prompt_passphrase() -> std::io::Result<String> {
+    rpassword::prompt_password("Keystore passphrase: ")
+}
+
+// A mistyped passphrase would seal a new keystore nobody can open
+fn confirm_new_passphrase(passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if keystore::passphrase_was_prompted()
+        && rpassword::prompt_password("Repeat the passphrase: ")? != passphrase
+    {
+        return Err("Passphrases don't match".into());
+    }
+    Ok(())
+}
+
+// `encryption-node config seal`: reads the value from stdin so it stays out of
+// shell history, and prints the `enc:v1:` form
+fn seal_config_value(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load_sealed_with(load_options(matches)?)?;
+    let keystore = Keystore::load(
+        &config.encryption.primary_key_path,
+        &config.keystore_passphrase()?,
+    )?;
+
+    let mut value = String::new();
+    std::io::stdin().read_line(&mut value)?;
+    let value = value.trim_end_matches(['\r', '\n']);
+    if value.is_empty() {
+        return Err("No value on stdin to seal".into());
+    }
+    println!("{}", keystore.seal_value(value)?);
+    Ok(())
+}
+
+// Prints every violation in the config rather than stopping at the first
+async fn validate_config(
+    matches: &ArgMatches,
+    args: &ArgMatches,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let config = Config::load_with(load_options(matches)?)?
+        .resolve_secrets()
+        .await?;
+    let report = config.validation_report();
+
+    if args.get_one::<String>("output").map(String::as_str) == Some("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if report.is_valid() {
+        println!("Config is valid");
+    } else {
+        println!("{}", report);
+    }
+    Ok(report.is_valid())
+}
+
+// Checks everything a node needs before it can start and prints one row per
+// check. Later checks are skipped when the config itself doesn't load.
+async fn run_doctor(
+    matches: &ArgMatches,
+    args: &ArgMatches,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let loaded = Config::load_with(load_options(&matches)?);
+    let loaded = match loaded {
+        Ok(config) => config.resolve_secrets().await,
+        Err(e) => Err(e),
+    };
+    let health_config = loaded
+        .as_ref()
+        .map(|config| config.health.clone())
+        .unwrap_or_default();
+
+    let mut checks = Vec::new();
+    let config = match loaded {
+        Ok(config) => {
+            checks.push(
+                health::probe(&health_config, "config", "config", async {
+                    config.validate()
+                })
+                .await,
+            );
+            Some(config)
+        }
+        Err(e) => {
+            checks.push(health::probe(&health_config, "config", "config", async { Err(e) }).await);
+            None
         }
+    };
 
-        // Stop after 10 minutes for demo
-        if sequence >= 18000 {
-            info!("Demo completed after {} frames", sequence);
-            break;
+    if let Some(config) = &config {
+        // Unsealing the keystore is deliberately slow, so keep it off the runtime
+        let path = config.encryption.primary_key_path.clone();
+        let passphrase = config.keystore_passphrase();
+        checks.push(
+            health::probe(
+                &health_config,
+                health::KEYSTORE,
+                health::KEYSTORE,
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        Keystore::load(&path, &passphrase?)?
+                            .public_keys()
+                            .map(|_| ())
+                    })
+                    .await?
+                },
+            )
+            .await,
+        );
+
+        match health::probe_endpoints(config).await {
+            Ok(endpoints) => checks.extend(endpoints),
+            Err(e) => checks.push(
+                health::probe(
+                    &health_config,
+                    health::BLOCKCHAIN,
+                    health::BLOCKCHAIN,
+                    async { Err(e) },
+                )
+                .await,
+            ),
+        }
+
+        // Opening a database a running node holds fails on its lock
+        for tenant_id in tenant::tenant_ids(&config.tenants)? {
+            let storage = tenant_storage_config(
+                &config.get_storage_config(),
+                &config.storage.tenants_path,
+                &tenant_id,
+            );
+            let name = if tenant_id == DEFAULT_TENANT {
+                health::ROCKSDB.to_string()
+            } else {
+                format!("{}:{}", health::ROCKSDB, tenant_id)
+            };
+            checks.push(
+                health::probe(&health_config, &name, health::ROCKSDB, async {
+                    DistributedStorage::new(storage)
+                        .await?
+                        .probe_primary()
+                        .await
+                })
+                .await,
+            );
+        }
+    }
+
+    let report = HealthReport::new(checks);
+    let failed = report.dependencies.iter().filter(|d| !d.healthy).count();
+    if args.get_one::<String>("output").map(String::as_str) == Some("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "{:<24} {:<11} {:<6} {:>8}  DETAIL",
+            "CHECK", "KIND", "RESULT", "LATENCY"
+        );
+        for check in &report.dependencies {
+            println!(
+                "{:<24} {:<11} {:<6} {:>6}ms  {}",
+                check.name,
+                check.kind,
+                if check.healthy { "PASS" } else { "FAIL" },
+                check.latency_ms,
+                check.error.as_deref().unwrap_or("")
+            );
         }
+        println!(
+            "\n{} of {} checks passed",
+            report.dependencies.len() - failed,
+            report.dependencies.len()
+        );
     }
+    Ok(failed == 0)
 }
 
-async fn start_http_server(
-    config: Config,
-    node: RealTimeEncryptionNode,
-) -> Result<(), Box<dyn std::error::Error>> {
-    use warp::Filter;
+fn force_arg() -> Arg {
+    Arg::new("force")
+        .long("force")
+        .action(ArgAction::SetTrue)
+        .help("Overwrite an existing keystore")
+}
 
-    info!(
-        "Starting HTTP server on {}:{}",
-        config.server.host, config.server.port
-    );
+// Keystore lifecycle. The keystore is sealed with encryption.keystore_passphrase,
+// IMMUTABLE_KEYSTORE_PASSPHRASE or, on a terminal, a prompted passphrase;
+// with [encryption.hsm] or [encryption.kms], new primary keys are wrapped by
+// the HSM or KMS key, and with hardware_backed sealed to the TPM, before they
+// are saved. Results are printed as JSON.
+fn manage_keys(config: &Config, args: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.encryption.primary_key_path.as_str();
+    let passphrase = config.keystore_passphrase()?;
+    let interval = config.encryption.key_rotation_interval_seconds;
+    let wrap = |keystore: &mut Keystore| -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(provider) = config.key_provider()? {
+            keystore.wrap_primary_key(provider.as_ref())?;
+        }
+        if let Some(hardware) = config.hardware_keys()? {
+            keystore.seal_primary_key(hardware.as_ref())?;
+        }
+        Ok(())
+    };
+    let refuse_overwrite = |args: &ArgMatches| {
+        if std::path::Path::new(path).exists() && !args.get_flag("force") {
+            Err(format!(
+                "Keystore {} already exists; pass --force to replace it",
+                path
+            ))
+        } else {
+            Ok(())
+        }
+    };
 
-    // Health check endpoint
-    let health = warp::path("health").and(warp::get()).map(|| {
-        warp::reply::json(&serde_json::json!({
-            "status": "healthy",
-            "timestamp": std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        }))
-    });
+    let output = match args.subcommand() {
+        Some(("keygen", args)) => {
+            refuse_overwrite(args)?;
+            confirm_new_passphrase(&passphrase)?;
+            let mut keystore = Keystore::generate()?;
+            keystore.record_key_rotation_interval(interval)?;
+            wrap(&mut keystore)?;
+            keystore.save(path, &passphrase)?;
+            serde_json::json!({ "keystore": path, "public_keys": keystore.public_keys()? })
+        }
+        Some(("rotate", _)) => {
+            let mut keystore = Keystore::load(path, &passphrase)?;
+            keystore.record_key_rotation_interval(interval)?;
+            keystore.rotate()?;
+            wrap(&mut keystore)?;
+            keystore.save(path, &passphrase)?;
+            serde_json::json!({
+                "keystore": path,
+                "rotated_at": keystore.rotated_at,
+                "retired_keys": keystore.retired_keys.len()
+            })
+        }
+        Some(("export-public", _)) => {
+            serde_json::to_value(Keystore::load(path, &passphrase)?.public_keys()?)?
+        }
+        Some(("shard", args)) => {
+            let threshold = args.get_one::<u8>("threshold").copied().unwrap_or(3);
+            let count = args.get_one::<u8>("shares").copied().unwrap_or(5);
+            let shares = Keystore::load(path, &passphrase)?.to_shares(threshold, count)?;
+            serde_json::json!({ "threshold": threshold, "shares": shares })
+        }
+        Some(("recover", args)) => {
+            refuse_overwrite(args)?;
+            let shares: Vec<String> = args
+                .get_many::<String>("share")
+                .map(|shares| shares.cloned().collect())
+                .unwrap_or_default();
+            let keystore = Keystore::from_shares(&shares)?;
+            confirm_new_passphrase(&passphrase)?;
+            keystore.save(path, &passphrase)?;
+            serde_json::json!({ "keystore": path, "public_keys": keystore.public_keys()? })
+        }
+        Some(("verification-key", args)) => {
+            let tenant_id = offline_tenant(args)?;
+            let engine = EncryptionEngine::new(tenant_crypto_config(
+                &config.get_crypto_config()?,
+                tenant_id,
+            )?)?;
+            serde_json::json!({
+                "tenant": tenant_id,
+                "verification_key": engine.verification_key()
+            })
+        }
+        Some(("envelope-key", args)) => {
+            let tenant_id = offline_tenant(args)?;
+            let engine = EncryptionEngine::new(tenant_crypto_config(
+                &config.get_crypto_config()?,
+                tenant_id,
+            )?)?;
+            let public_key = engine.envelope_public_key()?;
+            serde_json::json!({
+                "tenant": tenant_id,
+                "algorithm": "EdDSA",
+                "public_key": hex::encode(&public_key),
+                "key_id": hex::encode(cose::key_id(&public_key))
+            })
+        }
+        Some(("attestation-key", _)) => {
+            let hardware = config
+                .hardware_keys()?
+                .ok_or("encryption.hardware_backed is off; frames are not attested")?;
+            serde_json::json!({
+                "algorithm": "ES256",
+                "attestation_key": hex::encode(hardware.attestation_key()?)
+            })
+        }
+        _ => return Err("Unknown keys command".into()),
+    };
 
-    // Status endpoint
-    let node_clone = node.clone();
-    let status = warp::path("status").and(warp::get()).map(move || {
-        warp::reply::json(&serde_json::json!({
-            "node": "running",
-            "timestamp": std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-        }))
-    });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
 
-    // Verify evidence endpoint
-    let node_clone = node.clone();
-    let verify = warp::path("verify")
-        .and(warp::path::param::<String>())
-        .and(warp::get())
-        .and_then(move |evidence_id: String| {
-            let node = node_clone.clone();
-            async move {
-                match node.verify_evidence(&[evidence_id]).await {
-                    Ok(result) => Ok(warp::reply::json(&result)),
-                    Err(e) => {
-                        error!("Verification failed: {}", e);
-                        Ok(warp::reply::json(&serde_json::json!({
-                            "error": e.to_string()
-                        })))
-                    }
-                }
-            }
-        });
+// Opens a tenant's database and keys directly, for commands that run
+// without the HTTP server
+async fn open_offline_node(
+    config: &Config,
+    args: &ArgMatches,
+) -> Result<RealTimeEncryptionNode, Box<dyn std::error::Error>> {
+    let tenant_id = offline_tenant(args)?;
+    Ok(RealTimeEncryptionNode::new(
+        tenant_crypto_config(&config.get_crypto_config()?, tenant_id)?,
+        config.get_blockchain_config(),
+        tenant_storage_config(
+            &config.get_storage_config(),
+            &config.storage.tenants_path,
+            tenant_id,
+        ),
+        config.get_verification_config(),
+    )
+    .await?
+    .with_approvals(config.approvals.clone()))
+}
 
-    // Generate court report endpoint
-    let node_clone = node.clone();
-    let court_report = warp::path("court-report")
-        .and(warp::path::param::<String>())
-        .and(warp::get())
-        .and_then(move |evidence_id: String| {
-            let node = node_clone.clone();
-            async move {
-                match node.generate_court_report(&evidence_id).await {
-                    Ok(report) => Ok(warp::reply::json(&report)),
-                    Err(e) => {
-                        error!("Court report generation failed: {}", e);
-                        Ok(warp::reply::json(&serde_json::json!({
-                            "error": e.to_string()
-                        })))
-                    }
-                }
-            }
+fn offline_tenant(args: &ArgMatches) -> Result<&str, Box<dyn std::error::Error>> {
+    let tenant_id = args
+        .get_one::<String>("tenant")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_TENANT);
+    tenant::validate_tenant_id(tenant_id)?;
+    Ok(tenant_id)
+}
+
+// Runs the verification engine against an exported bundle or archive, a
+// shared clip, or the local database, without starting any server
+async fn verify_offline(
+    config: &Config,
+    args: &ArgMatches,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if let Some(path) = args.get_one::<String>("clip") {
+        let clip: SharedClip =
+            serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(path)?))?;
+        let public_key = match args.get_one::<String>("public-key") {
+            Some(key) => hex::decode(key)?,
+            None => EncryptionEngine::new(tenant_crypto_config(
+                &config.get_crypto_config()?,
+                offline_tenant(args)?,
+            )?)?
+            .envelope_public_key()?,
+        };
+
+        let error = clip.verify(&public_key).err();
+        let is_valid = error.is_none();
+        let report = serde_json::json!({
+            "clip_id": clip.clip_id,
+            "evidence_id": clip.evidence_id,
+            "frames": clip.frames.len(),
+            "is_valid": is_valid,
+            "error": error.map(|e| e.to_string()),
         });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(is_valid);
+    }
 
-    // Combine all routes
-    let routes = health
-        .or(status)
-        .or(verify)
-        .or(court_report)
-        .with(warp::cors().allow_any_origin())
-        .with(warp::log("api"));
+    if let Some(path) = args.get_one::<String>("bundle") {
+        let crypto_config =
+            tenant_crypto_config(&config.get_crypto_config()?, offline_tenant(args)?)?;
+        let engine = EncryptionEngine::new(crypto_config)?;
+        let verifier = VerificationEngine::new(config.get_verification_config());
 
-    // Start server
-    warp::serve(routes)
-        .run((
-            config.server.host.parse::<std::net::IpAddr>()?,
-            config.server.port,
-        ))
-        .await;
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let prefix = std::io::BufRead::fill_buf(&mut reader)?;
+        let (archive, container) = (export::is_archive(prefix), aff4::is_container(prefix));
+        let result = if archive {
+            export::verify_archive(reader, &engine, &verifier)?
+        } else if container {
+            export::verify_aff4(reader, &engine, &verifier)?
+        } else {
+            verify_bundle(reader, &engine, &verifier)?
+        };
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(result.is_valid);
+    }
 
-    Ok(())
+    let evidence_id = args
+        .get_one::<String>("evidence-id")
+        .ok_or("verify needs --bundle, --clip or --evidence-id")?;
+    let node = open_offline_node(config, args).await?;
+
+    // A forged manifest is a verification failure, not an operational one
+    let (is_valid, report) = match node.verify_session(evidence_id).await {
+        Ok(result) => (result.is_valid, serde_json::to_value(&result)?),
+        Err(e) => (
+            false,
+            serde_json::json!({
+                "evidence_id": evidence_id,
+                "is_valid": false,
+                "error": e.to_string(),
+                "code": e.code()
+            }),
+        ),
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(is_valid)
+}
+
+// Writes a session's bundle, and decrypted media for an authorized key, to a
+// tar.zst archive. Prints a summary as JSON.
+async fn export_evidence(
+    config: &Config,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let evidence_id = args
+        .get_one::<String>("evidence-id")
+        .ok_or("export needs --evidence-id")?;
+    let out = args.get_one::<String>("out").ok_or("export needs --out")?;
+    let aff4 = args.get_one::<String>("container").map(String::as_str) == Some("aff4");
+    let credentials = if args.get_flag("content-credentials") {
+        if aff4 {
+            return Err("--content-credentials applies to tar exports only".into());
+        }
+        if !config.content_credentials.enabled {
+            return Err("--content-credentials needs [content_credentials] enabled".into());
+        }
+        Some(ContentCredentials::from_config(
+            &config.content_credentials,
+        )?)
+    } else {
+        None
+    };
+
+    let node = open_offline_node(config, args).await?;
+
+    // Metadata-only exports need no key but are still audited
+    let (exporter, include_media) = match args.get_one::<String>("authorization-key") {
+        Some(key) => {
+            let action = format!("export_media:{}", evidence_id);
+            let exporter = authorize_plaintext(&node, args, key, &action).await?;
+            let decrypt = ApprovalAction::Decrypt {
+                evidence_id: evidence_id.clone(),
+            };
+            let approval = args.get_one::<String>("approval").map(String::as_str);
+            node.require_approval(&decrypt, approval, &exporter).await?;
+            (exporter, true)
+        }
+        None => (
+            Principal {
+                subject: "cli".to_string(),
+                roles: Vec::new(),
+                tenant: offline_tenant(args)?.to_string(),
+                source: Some("cli".to_string()),
+                issued_by: None,
+            },
+            false,
+        ),
+    };
+
+    let bundle = node
+        .evidence_bundle(evidence_id)
+        .await?
+        .ok_or_else(|| format!("Session {} is not sealed or does not exist", evidence_id))?;
+    // Forensic suites get the signed court report as the custody record
+    let court_report = if aff4 {
+        node.court_report_document(evidence_id, ReportFormat::Json)
+            .await?
+            .body
+    } else {
+        Vec::new()
+    };
+    let examiner = exporter.subject.clone();
+    let file = std::fs::File::create(out)?;
+    let handle = tokio::runtime::Handle::current();
+    let written = tokio::task::spawn_blocking(move || {
+        let out = std::io::BufWriter::new(file);
+        if aff4 {
+            export::write_aff4(
+                &handle,
+                bundle,
+                include_media,
+                &court_report,
+                &examiner,
+                out,
+            )
+        } else {
+            export::write_archive(&handle, bundle, include_media, credentials.as_ref(), out)
+        }
+    })
+    .await?;
+    let summary = match written {
+        Ok(summary) => summary,
+        Err(e) => {
+            // A truncated archive must not be mistaken for an export
+            let _ = std::fs::remove_file(out);
+            return Err(e.into());
+        }
+    };
+    let action = if include_media {
+        "export_media"
+    } else {
+        "export"
+    };
+    node.audit(&exporter, action, Some(evidence_id)).await?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({ "archive": out, "export": summary }))?
+    );
+    Ok(())
+}
+
+// Writes decrypted frames selected by ID or device range to a directory,
+// one file per frame or an MJPEG stream per device. The decryption is recorded
+// in each device's chain of custody first.
+async fn decrypt_frames(
+    config: &Config,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let out = std::path::PathBuf::from(args.get_one::<String>("out").ok_or("decrypt needs --out")?);
+    let mjpeg = args.get_one::<String>("container").map(String::as_str) == Some("mjpeg");
+    let key = args
+        .get_one::<String>("authorization-key")
+        .ok_or("decrypt needs --authorization-key")?;
+
+    let node = open_offline_node(config, args).await?;
+    let principal = authorize_plaintext(&node, args, key, "decrypt").await?;
+    let selector = match args.get_one::<String>("approval") {
+        // The approved session's device over the time it recorded
+        Some(request_id) => {
+            let request = node
+                .approval_request(request_id)
+                .await?
+                .ok_or_else(|| format!("Unknown approval request {}", request_id))?;
+            if !matches!(request.action, ApprovalAction::Decrypt { .. }) {
+                return Err(format!("Request {} does not approve decryption", request_id).into());
+            }
+            node.require_approval(&request.action, Some(request_id), &principal)
+                .await?;
+            let manifest = node
+                .session_manifest(request.action.evidence_id())
+                .await?
+                .ok_or("The approved session has no manifest")?;
+            FrameSelector::Range {
+                device_id: manifest.device_id,
+                from: manifest.first_frame_timestamp.unwrap_or(0),
+                to: manifest.last_frame_timestamp.unwrap_or(0),
+            }
+        }
+        None => {
+            if config.approvals.decrypt.is_some() {
+                return Err("[approvals.decrypt] is set; decrypt with --approval".into());
+            }
+            match args.get_many::<String>("frame-id") {
+                Some(frame_ids) => FrameSelector::Frames(frame_ids.cloned().collect()),
+                None => FrameSelector::Range {
+                    device_id: args
+                        .get_one::<String>("device")
+                        .cloned()
+                        .ok_or("decrypt needs --frame-id or --device")?,
+                    from: *args.get_one::<u64>("from").ok_or("--device needs --from")?,
+                    to: *args.get_one::<u64>("to").ok_or("--device needs --to")?,
+                },
+            }
+        }
+    };
+    let investigator = principal.subject;
+    let playback = node.playback_service(config.playback.clone());
+    let frame_ids = playback
+        .authorize_decryption(&investigator, &selector)
+        .await?;
+
+    std::fs::create_dir_all(&out)?;
+    let mut streams: std::collections::HashMap<String, std::fs::File> = Default::default();
+    let mut written = Vec::new();
+    for frame_id in &frame_ids {
+        let (frame, data) = playback.decrypt_frame(frame_id).await?;
+        let path = if mjpeg {
+            if SnapshotFormat::detect(&data) != Some(SnapshotFormat::Jpeg) {
+                return Err(format!("Frame {} is not JPEG; use --container raw", frame_id).into());
+            }
+            let path = out.join(format!("{}.mjpeg", frame.device_id));
+            let stream = match streams.entry(frame.device_id.clone()) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(std::fs::File::create(&path)?)
+                }
+            };
+            std::io::Write::write_all(stream, &data)?;
+            path
+        } else {
+            let extension = match SnapshotFormat::detect(&data) {
+                Some(SnapshotFormat::Jpeg) => "jpg",
+                Some(SnapshotFormat::Png) => "png",
+                None => "bin",
+            };
+            let path = out.join(format!(
+                "{}_{:010}.{}",
+                frame.device_id, frame.sequence, extension
+            ));
+            std::fs::write(&path, &data)?;
+            path
+        };
+
+        written.push(serde_json::json!({
+            "frame_id": frame_id,
+            "device_id": frame.device_id,
+            "sequence": frame.sequence,
+            "timestamp": frame.timestamp,
+            "path": path,
+            "sha256": immutable_encryption::crypto::sha256_hex(&data)
+        }));
+    }
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "investigator": investigator,
+            "container": if mjpeg { "mjpeg" } else { "raw" },
+            "frames": written
+        }))?
+    );
+    Ok(())
+}
+
+// Upgrades every record an older build wrote and prints what changed as
+// JSON. Records that can't be upgraded are listed and exit non-zero.
+async fn migrate_storage(
+    config: &Config,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tenant_id = offline_tenant(args)?;
+    let storage = DistributedStorage::new(tenant_storage_config(
+        &config.get_storage_config(),
+        &config.storage.tenants_path,
+        tenant_id,
+    ))
+    .await?;
+    let report = storage.migrate(args.get_flag("dry-run")).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.failed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Runs a recording through the same pipeline as live frames: one session per
+// file, each video sample sealed as a frame, then the session manifest sealed
+// and anchored. Timestamps come from the file's creation time when it has one.
+async fn import_video(
+    config: &Config,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = args
+        .get_one::<String>("file")
+        .ok_or("import needs a file")?;
+    let device_id = args
+        .get_one::<String>("device-id")
+        .ok_or("import needs --device-id")?;
+
+    // Parse before opening a session so a bad file leaves nothing behind
+    let mut file = std::fs::File::open(path)?;
+    let track = mp4::VideoTrack::parse(&mut file)?;
+    if track.samples.is_empty() {
+        return Err(format!("{} has no video samples", path).into());
+    }
+    let source_sha256 = {
+        use sha2::Digest;
+        let mut hasher = sha2::Sha256::new();
+        std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        hex::encode(hasher.finalize())
+    };
+    let started_at = match track.created_at {
+        Some(created_at) => created_at,
+        None => std::fs::metadata(path)?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs(),
+    };
+
+    let node = open_offline_node(config, args)
+        .await?
+        .with_ingest_config(config.ingest.clone())
+        .with_pipeline_config(config.pipeline.clone())
+        .with_device_policies(DevicePolicies::from_config(config));
+    let (sender, _) = node.start_processing().await?;
+    let session = node
+        .start_session(device_id, args.get_one::<String>("case").cloned())
+        .await?;
+
+    let metadata = FrameMetadata {
+        device_id: device_id.clone(),
+        location: None,
+        resolution: (track.width, track.height),
+        fps: track.fps(),
+        codec: track.codec.clone(),
+        telemetry: None,
+    };
+    for sample in &track.samples {
+        let frame = VideoFrame {
+            timestamp: started_at + sample.decode_time / track.timescale.max(1) as u64,
+            sequence: 0, // assigned per device
+            data: track.read_sample(&mut file, sample)?.into(),
+            metadata: metadata.clone(),
+            signature: None,
+        };
+        // Waiting for each seal keeps at most one sample in memory
+        node.submit_frame(&sender, frame, None, IMPORT_SEAL_WAIT)
+            .await?;
+    }
+
+    // Draining anchors the last batch; the manifest covers what was sealed
+    drop(sender);
+    node.shutdown(IMPORT_DRAIN_TIMEOUT).await?;
+    let manifest = node.stop_session(&session.session_id).await?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "evidence_id": manifest.session_id,
+            "manifest_hash": manifest.manifest_hash,
+            "frame_count": manifest.frame_count,
+            "anchors": manifest.anchors,
+            "source": {
+                "path": path,
+                "sha256": source_sha256,
+                "codec": track.codec,
+                "resolution": [track.width, track.height],
+                "samples": track.samples.len()
+            }
+        }))?
+    );
+    Ok(())
+}
+
+// Plaintext leaves the node only for an auditor or prosecutor of the tenant
+// being read; the key's audit log records the use
+async fn authorize_plaintext(
+    node: &RealTimeEncryptionNode,
+    args: &ArgMatches,
+    key: &str,
+    action: &str,
+) -> Result<Principal, Box<dyn std::error::Error>> {
+    let mut principal = node.api_key_manager().authenticate(key, action).await?;
+    principal.source = Some("cli".to_string());
+    principal.require_any(&[Role::Auditor, Role::Prosecutor])?;
+    if principal.tenant != offline_tenant(args)? {
+        return Err(format!("API key belongs to tenant {}", principal.tenant).into());
+    }
+    Ok(principal)
+}
+
+// Resolves with the name of the first termination signal received
+async fn shutdown_signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT"),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map(|_| "Ctrl-C")
+    }
+}
+
+async fn wait_for_shutdown(mut shutdown: tokio::sync::watch::Receiver<bool>) {
+    // A dropped sender also counts as shutdown
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+// Besides feeding frames, the demo reports pipeline throughput as it runs and
+// a summary at the end, so it doubles as a smoke test on new hardware
+async fn demo_video_generation(
+    sender: immutable_encryption::FrameSender,
+    node: RealTimeEncryptionNode,
+) {
+    let mut sequence = 0;
+    let mut interval = tokio::time::interval(Duration::from_millis(33)); // ~30 FPS
+
+    let started = std::time::Instant::now();
+    let baseline = node.pipeline_stats().await;
+    let (mut last, mut last_at) = (baseline.clone(), started);
+    let mut report = tokio::time::interval(DEMO_REPORT_INTERVAL);
+    report.tick().await; // the first tick is immediate
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = report.tick() => {
+                let current = node.pipeline_stats().await;
+                log_throughput(&last, &current, last_at.elapsed());
+                (last, last_at) = (current, std::time::Instant::now());
+                continue;
+            }
+        }
+
+        sequence += 1;
+
+        // Simulate video frame data
+        let frame_data = vec![0u8; 1024 * 1024]; // 1MB frame
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let frame = VideoFrame {
+            timestamp,
+            sequence,
+            data: frame_data.into(),
+            metadata: FrameMetadata {
+                device_id: "demo_drone_001".to_string(),
+                location: Some((40.7128 + (sequence as f64 * 0.0001), -74.0060)), // Moving coordinates
+                resolution: (1920, 1080),
+                fps: 30,
+                codec: "H.264".to_string(),
+                telemetry: None,
+            },
+            signature: None,
+        };
+
+        if let Err(e) = sender.send(frame) {
+            error!("Failed to send demo frame: {}", e);
+            break;
+        }
+
+        if sequence % 100 == 0 {
+            info!("Generated {} demo frames", sequence);
+        }
+
+        // Stop after 10 minutes for demo
+        if sequence >= 18000 {
+            info!("Demo completed after {} frames", sequence);
+            break;
+        }
+    }
+
+    // Let the pipeline catch up so the summary covers every generated frame
+    let settled = baseline.frames_processed + baseline.frames_failed + sequence;
+    let deadline = std::time::Instant::now() + DEMO_DRAIN_TIMEOUT;
+    let mut finished = node.pipeline_stats().await;
+    while finished.frames_processed + finished.frames_failed < settled
+        && std::time::Instant::now() < deadline
+    {
+        sleep(Duration::from_millis(250)).await;
+        finished = node.pipeline_stats().await;
+    }
+
+    let elapsed = started.elapsed();
+    let (frames_per_sec, mb_per_sec) = throughput(&baseline, &finished, elapsed);
+    info!(
+        "Demo summary: {} frames generated, {} sealed, {} failed in {:.1}s; \
+         {:.1} frames/s, {:.1} MB/s encrypted; {} batches anchored, \
+         mean anchor batch latency {}ms",
+        sequence,
+        finished.frames_processed - baseline.frames_processed,
+        finished.frames_failed - baseline.frames_failed,
+        elapsed.as_secs_f64(),
+        frames_per_sec,
+        mb_per_sec,
+        finished.batches_anchored - baseline.batches_anchored,
+        finished.mean_batch_latency_ms
+    );
+}
+
+fn log_throughput(last: &NodeStatus, current: &NodeStatus, elapsed: Duration) {
+    let (frames_per_sec, mb_per_sec) = throughput(last, current, elapsed);
+    info!(
+        "Demo throughput: {:.1} frames/s, {:.1} MB/s encrypted, \
+         anchor batch latency {}ms (mean {}ms), {} queued",
+        frames_per_sec,
+        mb_per_sec,
+        current.last_batch_latency_ms,
+        current.mean_batch_latency_ms,
+        current.batch_queue_depth
+    );
+}
+
+// Sealed frames and ciphertext megabytes per second between two snapshots
+fn throughput(from: &NodeStatus, to: &NodeStatus, elapsed: Duration) -> (f64, f64) {
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    let frames = to.frames_processed.saturating_sub(from.frames_processed) as f64;
+    let bytes = to.bytes_encrypted.saturating_sub(from.bytes_encrypted) as f64;
+    (frames / secs, bytes / 1_000_000.0 / secs)
+}
+
+// Evidence, keys and pipeline belonging to one tenant
+struct TenantRuntime {
+    node: RealTimeEncryptionNode,
+    frame_sender: immutable_encryption::FrameSender,
+    playback: Arc<PlaybackService>,
+}
+
+type Tenants = TenantDirectory<TenantRuntime>;
+
+async fn start_http_server(
+    config: Config,
+    tenants: Arc<Tenants>,
+    shutdown: tokio::sync::watch::Receiver<bool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use warp::{Filter, Reply};
+
+    info!(
+        "Starting HTTP server on {}:{}",
+        config.server.host, config.server.port
+    );
+
+    // Health check endpoint: probes every tenant's database and keys, plus the
+    // shared IPFS and blockchain endpoints. 503 when a critical one fails.
+    let health_config = Arc::new(config.health.clone());
+    let health_tenants = tenants.clone();
+    let health = warp::path("health").and(warp::get()).then(move || {
+        let config = health_config.clone();
+        let tenants = health_tenants.clone();
+        async move {
+            let mut runtimes: Vec<_> = tenants.iter().collect();
+            runtimes.sort_by(|a, b| a.0.cmp(b.0));
+
+            let mut dependencies = Vec::new();
+            for (tenant_id, runtime) in runtimes {
+                for mut dependency in runtime.node.probe_local(&config).await {
+                    if tenant_id != DEFAULT_TENANT {
+                        dependency.name = format!("{}:{}", dependency.name, tenant_id);
+                    }
+                    dependencies.push(dependency);
+                }
+            }
+            if let Some(default) = tenants.get(DEFAULT_TENANT) {
+                dependencies.extend(default.node.probe_external(&config).await);
+            }
+
+            let report = HealthReport::new(dependencies);
+            let status = if report.status == HealthState::Unhealthy {
+                warn!("Health check failed: {:?}", report.dependencies);
+                warp::http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                warp::http::StatusCode::OK
+            };
+            warp::reply::with_status(warp::reply::json(&report), status)
+        }
+    });
+
+    // API keys live in the default tenant's database; each key records the
+    // tenant it acts for
+    let platform = tenants
+        .get(DEFAULT_TENANT)
+        .ok_or("The default tenant is not being served")?;
+    let auth = Arc::new(RequestAuthenticator::new(
+        JwtAuthenticator::new(&config.auth)?,
+        Arc::new(platform.node.api_key_manager()),
+    ));
+    if !auth.jwt_enabled() {
+        warn!("JWT authentication is disabled; callers without an API key have every role");
+    }
+
+    // Status endpoint
+    let status = warp::path("status")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Admin],
+        ))
+        .and_then(
+            move |_principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(warp::reply::json(&node.status().await))
+            },
+        );
+
+    // Prometheus scrape endpoint for the per-module operation metrics
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(require_roles(
+            auth.clone(),
+            &[Role::Operator, Role::Auditor, Role::Admin],
+        ))
+        .map(|_principal: Principal| match metrics::global().encode() {
+            Ok(text) => warp::reply::with_header(text, "content-type", "text/plain; version=0.0.4")
+                .into_response(),
+            Err(e) => json_error(&e),
+        });
+
+    // Verify evidence endpoint
+    let verify = warp::path("verify")
+        .and(warp::path::param::<String>())
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |evidence_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    match node.verify_evidence(&[evidence_id]).await {
+                        Ok(result) => Ok(ok_reply(&result)),
+                        Err(e) => {
+                            error!("Verification failed: {}", e);
+                            Ok(error_reply(&e))
+                        }
+                    }
+                }
+            },
+        );
+
+    // Verify a device's frames over a time range, resolved via the device index
+    let verify_range = warp::path!("verify")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Auditor, Role::Prosecutor],
+        ))
+        .and(warp::query::<RangeQuery>())
+        .and_then(
+            move |_principal: Principal, node: RealTimeEncryptionNode, query: RangeQuery| {
+                async move {
+                    let to = query.to.unwrap_or(u64::MAX);
+                    let result = node.verify_range(&query.device, query.from, to).await;
+                    let reply = match result {
+                        Ok(report) => ok_reply(&report),
+                        Err(e) => {
+                            error!("Range verification failed: {}", e);
+                            error_reply(&e)
+                        }
+                    };
+                    Ok::<_, warp::Rejection>(reply)
+                }
+            },
+        );
+
+    // Court report download; `Accept` selects JSON, HTML or PDF
+    let court_report = warp::path("court-report")
+        .and(warp::path::param::<String>())
+        .and(warp::get())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Auditor]))
+        .and(warp::header::optional::<String>("accept"))
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  accept: Option<String>| {
+                async move {
+                    let format = match ReportFormat::from_accept(accept.as_deref()) {
+                        Some(format) => format,
+                        None => {
+                            let error = ImmutableEncryptionError::invalid_request(
+                                "court reports are available as application/json, text/html or application/pdf",
+                            );
+                            return warp::http::Response::builder()
+                                .status(warp::http::StatusCode::NOT_ACCEPTABLE)
+                                .header("content-type", "application/json")
+                                .body(serde_json::to_vec(&error.body()).unwrap_or_default())
+                                .map_err(|e| {
+                                    warp::reject::custom(ApiRejection::from(
+                                        ImmutableEncryptionError::internal(&e.to_string()),
+                                    ))
+                                });
+                        }
+                    };
+
+                    let document = node
+                        .court_report_document(&evidence_id, format)
+                        .await
+                        .map_err(|e| {
+                            error!("Court report generation failed: {}", e);
+                            warp::reject::custom(ApiRejection::from(e))
+                        })?;
+                    info!(
+                        "{} downloaded the {} court report for {}",
+                        principal.subject,
+                        format.extension(),
+                        evidence_id
+                    );
+
+                    warp::http::Response::builder()
+                        .header("content-type", format.content_type())
+                        .header(
+                            "content-disposition",
+                            format!(
+                                "attachment; filename=\"court-report-{}.{}\"",
+                                evidence_id,
+                                format.extension()
+                            ),
+                        )
+                        .header("x-report-sha256", document.sha256)
+                        .header("x-report-signature", document.signature)
+                        .body(document.body)
+                        .map_err(|e| {
+                            warp::reject::custom(ApiRejection::from(
+                                ImmutableEncryptionError::internal(&e.to_string()),
+                            ))
+                        })
+                }
+            },
+        );
+
+    // Recording session endpoints
+    let start_session =
+        warp::path!("sessions")
+            .and(warp::post())
+            .and(tenant_node(
+                auth.clone(),
+                tenants.clone(),
+                &[Role::Operator],
+            ))
+            .and(warp::body::json::<serde_json::Value>())
+            .and_then(
+                move |_principal: Principal,
+                      node: RealTimeEncryptionNode,
+                      body: serde_json::Value| {
+                    async move {
+                        let device_id = body["device_id"].as_str().unwrap_or_default();
+                        let case_id = body["case_id"].as_str().map(|s| s.to_string());
+
+                        match node.start_session(device_id, case_id).await {
+                            Ok(session) => Ok::<_, warp::Rejection>(ok_reply(&session)),
+                            Err(e) => {
+                                error!("Failed to start session: {}", e);
+                                Ok(error_reply(&e))
+                            }
+                        }
+                    }
+                },
+            );
+
+    let stop_session = warp::path!("sessions" / String / "stop")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Operator]))
+        .and_then(
+            move |session_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    match node.stop_session(&session_id).await {
+                        Ok(manifest) => Ok::<_, warp::Rejection>(ok_reply(&manifest)),
+                        Err(e) => {
+                            error!("Failed to stop session: {}", e);
+                            Ok(error_reply(&e))
+                        }
+                    }
+                }
+            },
+        );
+
+    let session_manifest = warp::path!("sessions" / String)
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |session_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    match node.session_manifest(&session_id).await {
+                        Ok(Some(manifest)) => Ok::<_, warp::Rejection>(ok_reply(&manifest)),
+                        Ok(None) => Ok(error_reply(&ImmutableEncryptionError::NotFound(
+                            format!("No manifest for session {}", session_id),
+                        ))),
+                        Err(e) => Ok(error_reply(&e)),
+                    }
+                }
+            },
+        );
+
+    let verify_session = warp::path!("sessions" / String / "verify")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |session_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    match node.verify_session(&session_id).await {
+                        Ok(result) => Ok::<_, warp::Rejection>(ok_reply(&result)),
+                        Err(e) => {
+                            error!("Session verification failed: {}", e);
+                            Ok(error_reply(&e))
+                        }
+                    }
+                }
+            },
+        );
+
+    // Background verification of a sealed session; progress is available as a
+    // snapshot or as server-sent events until the job finishes
+    let start_verification =
+        warp::path!("verifications")
+            .and(warp::post())
+            .and(tenant_node(
+                auth.clone(),
+                tenants.clone(),
+                &[Role::Operator, Role::Auditor, Role::Prosecutor],
+            ))
+            .and(warp::body::json::<serde_json::Value>())
+            .and_then(
+                move |_principal: Principal,
+                      node: RealTimeEncryptionNode,
+                      body: serde_json::Value| {
+                    async move {
+                        let evidence_id = body["evidence_id"].as_str().unwrap_or_default();
+                        let reply = match node.start_verification_job(evidence_id).await {
+                            Ok(progress) => warp::reply::with_status(
+                                warp::reply::json(&progress),
+                                warp::http::StatusCode::ACCEPTED,
+                            ),
+                            Err(e) => {
+                                error!("Failed to start verification: {}", e);
+                                error_reply(&e)
+                            }
+                        };
+                        Ok::<_, warp::Rejection>(reply)
+                    }
+                },
+            );
+
+    let verification_progress = warp::path!("verifications" / String)
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |job_id: String, _principal: Principal, node: RealTimeEncryptionNode| async move {
+                let progress = node.verification_progress(&job_id);
+                let reply = match progress {
+                    Some(rx) => ok_reply(&*rx.borrow()),
+                    None => error_reply(&ImmutableEncryptionError::NotFound(format!(
+                        "Unknown verification job {}",
+                        job_id
+                    ))),
+                };
+                Ok::<_, warp::Rejection>(reply)
+            },
+        );
+
+    let verification_events = warp::path!("verifications" / String / "events")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |job_id: String, _principal: Principal, node: RealTimeEncryptionNode| async move {
+                match node.verification_progress(&job_id) {
+                    Some(rx) => Ok(warp::sse::reply(
+                        warp::sse::keep_alive().stream(progress_events(rx)),
+                    )),
+                    None => Err(warp::reject::custom(ApiRejection {
+                        status: warp::http::StatusCode::NOT_FOUND,
+                        error: ImmutableEncryptionError::NotFound(format!(
+                            "Unknown verification job {}",
+                            job_id
+                        )),
+                    })),
+                }
+            },
+        );
+
+    // Browse recorded evidence by device, time range and anchor status
+    let list_evidence = warp::path!("evidence")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and(warp::query::<EvidenceQuery>())
+        .and_then(
+            move |_principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  query: EvidenceQuery| {
+                async move {
+                    Ok::<_, warp::Rejection>(listing_reply(node.list_evidence(&query).await))
+                }
+            },
+        );
+
+    let evidence_frames = warp::path!("evidence" / String / "frames")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and(warp::query::<FrameQuery>())
+        .and_then(
+            move |evidence_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  query: FrameQuery| {
+                async move {
+                    match node.evidence_frames(&evidence_id, &query).await {
+                        Ok(None) => Ok::<_, warp::Rejection>(error_reply(
+                            &ImmutableEncryptionError::NotFound(format!(
+                                "No evidence {}",
+                                evidence_id
+                            )),
+                        )),
+                        result => Ok(listing_reply(result.map(Option::unwrap))),
+                    }
+                }
+            },
+        );
+
+    // Signed export of a sealed session, streamed; supports single byte
+    // ranges so interrupted multi-gigabyte downloads can resume
+    let evidence_bundle = warp::path!("evidence" / String / "bundle")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Auditor, Role::Prosecutor],
+        ))
+        .and(warp::header::optional::<String>("range"))
+        .and(warp::header::optional::<String>("if-range"))
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  range: Option<String>,
+                  if_range: Option<String>| {
+                async move {
+                    let response = match node.evidence_bundle(&evidence_id).await {
+                        Ok(Some(bundle)) => {
+                            info!(
+                                "{} downloading bundle for {}",
+                                principal.subject, evidence_id
+                            );
+                            if let Err(e) =
+                                node.audit(&principal, "export", Some(&evidence_id)).await
+                            {
+                                error!("Failed to audit export of {}: {}", evidence_id, e);
+                            }
+                            bundle_reply(bundle, range.as_deref(), if_range.as_deref()).await
+                        }
+                        Ok(None) => json_error(&ImmutableEncryptionError::NotFound(format!(
+                            "No sealed evidence {}",
+                            evidence_id
+                        ))),
+                        Err(e) => {
+                            error!("Failed to open bundle for {}: {}", evidence_id, e);
+                            json_error(&e)
+                        }
+                    };
+                    Ok::<_, warp::Rejection>(response)
+                }
+            },
+        );
+
+    // A time range of sealed evidence re-encrypted to one recipient, with the
+    // proofs tying it to the anchored recording; check with `verify --clip`
+    let share_clip = warp::path!("evidence" / String / "clips")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Prosecutor, Role::Admin],
+        ))
+        .and(warp::body::json::<ClipRequest>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: ClipRequest| {
+                async move {
+                    let result = match hex::decode(&request.recipient_key) {
+                        Ok(recipient_key) => {
+                            node.share_clip(
+                                &evidence_id,
+                                request.from,
+                                request.to,
+                                &recipient_key,
+                                &principal,
+                                request.approval_id.as_deref(),
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    // One sealed frame as a COSE_Sign1 envelope, checkable with the key from
+    // `keys envelope-key`
+    let frame_envelope = warp::path!("frames" / String / "envelope")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |frame_id: String, _principal: Principal, node: RealTimeEncryptionNode| {
+                async move {
+                    let response = match node.frame_envelope(&frame_id).await {
+                        Ok(envelope) => warp::http::Response::builder()
+                            .header("content-type", ENVELOPE_CONTENT_TYPE)
+                            .body(warp::hyper::Body::from(envelope))
+                            .unwrap_or_default(),
+                        Err(e) => {
+                            warn!("No envelope for {}: {}", frame_id, e);
+                            json_error(&e)
+                        }
+                    };
+                    Ok::<_, warp::Rejection>(response)
+                }
+            },
+        );
+
+    // The metadata and sensor telemetry a frame was sealed with
+    let frame_metadata = warp::path!("frames" / String / "metadata")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |frame_id: String, _principal: Principal, node: RealTimeEncryptionNode| {
+                async move {
+                    let reply = match node.frame_metadata(&frame_id).await {
+                        Ok(record) => ok_reply(&record),
+                        Err(e) => {
+                            warn!("No metadata for {}: {}", frame_id, e);
+                            error_reply(&e)
+                        }
+                    };
+                    Ok::<_, warp::Rejection>(reply)
+                }
+            },
+        );
+
+    // Register a transcoded rendition derived from a sealed session
+    let register_rendition = warp::path!("renditions" / String)
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator],
+        ))
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(warp::body::content_length_limit(
+            config.rate_limit.max_upload_bytes,
+        ))
+        .and(warp::body::stream())
+        .and_then(
+            move |evidence_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  query: std::collections::HashMap<String, String>,
+                  body| {
+                async move {
+                    let profile = TranscodeProfile {
+                        codec: query.get("codec").cloned().unwrap_or_default(),
+                        resolution: query
+                            .get("width")
+                            .zip(query.get("height"))
+                            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?))),
+                        bitrate_kbps: query.get("bitrate_kbps").and_then(|b| b.parse().ok()),
+                        tool: query.get("tool").cloned(),
+                    };
+
+                    // Spooled to disk rather than buffered, as uploads run to gigabytes
+                    let registered = match hash_rendition_upload(Box::pin(body)).await {
+                        Ok(digest) => node.register_rendition(&evidence_id, profile, digest).await,
+                        Err(e) => Err(e),
+                    };
+                    match registered {
+                        Ok(record) => Ok::<_, warp::Rejection>(ok_reply(&record)),
+                        Err(e) => {
+                            error!("Failed to register rendition: {}", e);
+                            Ok(error_reply(&e))
+                        }
+                    }
+                }
+            },
+        );
+
+    // Redacted renditions for disclosure, provable against the sealed original
+    let redact_session = warp::path!("renditions" / String / "redactions")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Prosecutor, Role::Admin],
+        ))
+        .and(warp::body::json::<RedactionRequest>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: RedactionRequest| {
+                async move {
+                    let result = node
+                        .redact_session(&evidence_id, &principal, request)
+                        .await
+                        .map(|(record, data)| {
+                            serde_json::json!({
+                                "rendition": record,
+                                "content_base64": BASE64.encode(&data),
+                            })
+                        });
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    let verify_redaction = warp::path!("renditions" / String / "redactions" / String / "verify")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |evidence_id: String,
+                  rendition_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    let result = node
+                        .verify_redaction(&evidence_id, &rendition_id)
+                        .await
+                        .map(|valid| serde_json::json!({ "valid": valid }));
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    // Authorized playback of decrypted frames as an MJPEG stream
+    let playback_tenants = tenants.clone();
+    let playback_auth = auth.clone();
+    let playback = warp::path!("playback" / String)
+        .and(warp::get())
+        .and(warp::header::<String>("authorization"))
+        .and(warp::query::<PlaybackQuery>())
+        .and_then(
+            move |device_id: String, authorization: String, query: PlaybackQuery| {
+                let tenants = playback_tenants.clone();
+                let auth = playback_auth.clone();
+                async move {
+                    let request = PlaybackRequest {
+                        device_id,
+                        from: query.from,
+                        to: query.to,
+                    };
+
+                    let opened = async {
+                        let (investigator, node, service) =
+                            resolve_investigator(&auth, &tenants, &authorization).await?;
+                        node.require_decrypt_approval(
+                            &request.device_id,
+                            request.from,
+                            request.to,
+                            query.approval_id.as_deref(),
+                            &investigator,
+                        )
+                        .await?;
+                        let session = service.open_for(investigator.subject, request).await?;
+                        Ok::<_, ImmutableEncryptionError>(service.mjpeg_stream(session))
+                    }
+                    .await;
+
+                    let response = match opened {
+                        Ok(stream) => warp::http::Response::builder()
+                            .header(
+                                "content-type",
+                                format!("multipart/x-mixed-replace; boundary={}", MJPEG_BOUNDARY),
+                            )
+                            .body(warp::hyper::Body::wrap_stream(stream)),
+                        Err(e) => {
+                            warn!("Playback refused: {}", e);
+                            return Ok::<_, warp::Rejection>(json_error(&e));
+                        }
+                    };
+
+                    Ok::<_, warp::Rejection>(response.unwrap_or_default())
+                }
+            },
+        );
+
+    // Single decrypted frame plus its verification proof for analyst preview
+    let snapshot_tenants = tenants.clone();
+    let snapshot_auth = auth.clone();
+    let snapshot = warp::path!("snapshots" / String)
+        .and(warp::get())
+        .and(warp::header::<String>("authorization"))
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and_then(
+            move |frame_id: String,
+                  authorization: String,
+                  params: std::collections::HashMap<String, String>| {
+                let tenants = snapshot_tenants.clone();
+                let auth = snapshot_auth.clone();
+                async move {
+                    let format = match params.get("format").map(|f| f.as_str()) {
+                        Some("png") => Some(SnapshotFormat::Png),
+                        Some("jpeg") | Some("jpg") => Some(SnapshotFormat::Jpeg),
+                        _ => None,
+                    };
+
+                    let extracted = async {
+                        let (investigator, node, service) =
+                            resolve_investigator(&auth, &tenants, &authorization).await?;
+                        let approval_id = params.get("approval_id").map(String::as_str);
+                        node.require_frame_decrypt_approval(&frame_id, approval_id, &investigator)
+                            .await?;
+                        service
+                            .extract_snapshot_for(&investigator.subject, &frame_id, format)
+                            .await
+                    }
+                    .await;
+
+                    let response = match extracted {
+                        Ok(snapshot) => warp::reply::with_status(
+                            warp::reply::json(&serde_json::json!({
+                                "content_type": snapshot.format.content_type(),
+                                "image_base64": BASE64.encode(&snapshot.image),
+                                "proof": snapshot.proof,
+                            })),
+                            warp::http::StatusCode::OK,
+                        ),
+                        Err(e) => {
+                            warn!("Snapshot of {} refused: {}", frame_id, e);
+                            error_reply(&e)
+                        }
+                    };
+
+                    Ok::<_, warp::Rejection>(response)
+                }
+            },
+        );
+
+    // Network frame ingestion: raw payload in the body, metadata in headers
+    let ingest_tenants = tenants.clone();
+    let ingest_auth = auth.clone();
+    let max_frame_bytes = config.ingest.max_frame_bytes as u64;
+    let ingest_frame = warp::path("frames")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::header::headers_cloned())
+        .and(warp::ext::optional::<ClientCertificate>())
+        .and(warp::body::content_length_limit(max_frame_bytes))
+        .and(warp::body::bytes())
+        .and_then(
+            move |headers: warp::http::HeaderMap,
+                  certificate: Option<ClientCertificate>,
+                  body: bytes::Bytes| {
+                let tenants = ingest_tenants.clone();
+                let auth = ingest_auth.clone();
+                async move {
+                    let reply = |status, value: serde_json::Value| {
+                        Ok::<_, warp::Rejection>(warp::reply::with_status(
+                            warp::reply::json(&value),
+                            status,
+                        ))
+                    };
+
+                    // Devices authenticate with their certificate, everyone else as an operator
+                    let operator_tenant = if certificate.is_none() {
+                        let authorization =
+                            headers.get("authorization").and_then(|v| v.to_str().ok());
+                        let api_key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+                        match auth
+                            .authenticate(authorization, api_key, "POST /frames")
+                            .await
+                            .and_then(|principal| {
+                                principal.require_any(&[Role::Operator])?;
+                                Ok(principal.tenant)
+                            }) {
+                            Ok(tenant) => Some(tenant),
+                            Err(e) => {
+                                let status = match e {
+                                    ImmutableEncryptionError::RateLimitExceeded(_) => {
+                                        warp::http::StatusCode::TOO_MANY_REQUESTS
+                                    }
+                                    _ => warp::http::StatusCode::FORBIDDEN,
+                                };
+                                return reply(status, serde_json::json!(e.body()));
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
+                    let frame = match frame_from_headers(&headers, body) {
+                        Ok(frame) => frame,
+                        Err(e) => {
+                            let error = ImmutableEncryptionError::InvalidRequest(e);
+                            return reply(error_status(&error), serde_json::json!(error.body()));
+                        }
+                    };
+
+                    // Frames are sealed by the tenant the device is assigned to, and
+                    // operators may only submit for their own tenant's devices
+                    let tenant = tenants.tenant_for_device(&frame.metadata.device_id);
+                    if operator_tenant
+                        .as_deref()
+                        .map_or(false, |own| own != tenant)
+                    {
+                        let error = ImmutableEncryptionError::PermissionDenied(format!(
+                            "device {} does not belong to your tenant",
+                            frame.metadata.device_id
+                        ));
+                        return reply(error_status(&error), serde_json::json!(error.body()));
+                    }
+                    let runtime = match tenants.get(tenant) {
+                        Some(runtime) => runtime,
+                        None => {
+                            let error = ImmutableEncryptionError::ResourceUnavailable(format!(
+                                "tenant {} is not served by this node",
+                                tenant
+                            ));
+                            return reply(error_status(&error), serde_json::json!(error.body()));
+                        }
+                    };
+
+                    match runtime
+                        .node
+                        .submit_frame(
+                            &runtime.frame_sender,
+                            frame,
+                            certificate.as_ref(),
+                            Duration::from_secs(10),
+                        )
+                        .await
+                    {
+                        Ok(SubmitOutcome::Sealed(f)) => {
+                            let frame_id =
+                                storage::frame_key(&f.device_id, f.sequence, f.timestamp);
+                            reply(
+                                warp::http::StatusCode::CREATED,
+                                serde_json::json!({
+                                    "device_id": f.device_id,
+                                    "sequence": f.sequence,
+                                    "hash": f.hash,
+                                    "previous_hash": f.previous_hash,
+                                    "frame_id": frame_id,
+                                }),
+                            )
+                        }
+                        // Accepted but not yet sealed; the client can look it up later
+                        Ok(SubmitOutcome::Pending {
+                            device_id,
+                            sequence,
+                        }) => reply(
+                            warp::http::StatusCode::ACCEPTED,
+                            serde_json::json!({
+                                "device_id": device_id,
+                                "sequence": sequence,
+                                "hash": null,
+                            }),
+                        ),
+                        Err(e) => {
+                            match e {
+                                ImmutableEncryptionError::PermissionDenied(_) => {
+                                    warn!("Frame submission refused: {}", e)
+                                }
+                                ImmutableEncryptionError::ResourceUnavailable(_) => {
+                                    error!("Failed to enqueue ingested frame: {}", e)
+                                }
+                                _ => {}
+                            }
+                            reply(error_status(&e), serde_json::json!(e.body()))
+                        }
+                    }
+                }
+            },
+        );
+
+    // API key administration for machine integrations
+    let issue_key_auth = auth.clone();
+    let issue_api_key = warp::path!("admin" / "api-keys")
+        .and(warp::post())
+        .and(require_roles(auth.clone(), &[Role::Admin]))
+        .and(warp::body::json::<ApiKeyRequest>())
+        .and_then(move |principal: Principal, request: ApiKeyRequest| {
+            let auth = issue_key_auth.clone();
+            async move {
+                info!(
+                    "{} is issuing API key {:?}",
+                    principal.subject, request.name
+                );
+                let request = ApiKeyRequest {
+                    issued_by: Some(principal.accountable().to_string()),
+                    tenant: principal.tenant,
+                    ..request
+                };
+                Ok::<_, warp::Rejection>(api_key_reply(auth.api_keys().issue(request).await))
+            }
+        });
+
+    let list_keys_auth = auth.clone();
+    let list_api_keys = warp::path!("admin" / "api-keys")
+        .and(warp::get())
+        .and(require_roles(auth.clone(), &[Role::Admin, Role::Auditor]))
+        .and_then(move |principal: Principal| {
+            let auth = list_keys_auth.clone();
+            async move {
+                Ok::<_, warp::Rejection>(api_key_reply(
+                    auth.api_keys().list(&principal.tenant).await,
+                ))
+            }
+        });
+
+    let usage_auth = auth.clone();
+    let api_key_usage = warp::path!("admin" / "api-keys" / String / "usage")
+        .and(warp::get())
+        .and(require_roles(auth.clone(), &[Role::Admin, Role::Auditor]))
+        .and_then(move |key_id: String, principal: Principal| {
+            let auth = usage_auth.clone();
+            async move {
+                Ok::<_, warp::Rejection>(api_key_reply(
+                    auth.api_keys().usage(&key_id, &principal.tenant).await,
+                ))
+            }
+        });
+
+    let rotate_auth = auth.clone();
+    let rotate_api_key = warp::path!("admin" / "api-keys" / String / "rotate")
+        .and(warp::post())
+        .and(require_roles(auth.clone(), &[Role::Admin]))
+        .and_then(move |key_id: String, principal: Principal| {
+            let auth = rotate_auth.clone();
+            async move {
+                info!("{} is rotating API key {}", principal.subject, key_id);
+                Ok::<_, warp::Rejection>(api_key_reply(
+                    auth.api_keys().rotate(&key_id, &principal).await,
+                ))
+            }
+        });
+
+    let revoke_auth = auth.clone();
+    let revoke_api_key = warp::path!("admin" / "api-keys" / String)
+        .and(warp::delete())
+        .and(require_roles(auth.clone(), &[Role::Admin]))
+        .and_then(move |key_id: String, principal: Principal| {
+            let auth = revoke_auth.clone();
+            async move {
+                info!("{} is revoking API key {}", principal.subject, key_id);
+                Ok::<_, warp::Rejection>(api_key_reply(
+                    auth.api_keys().revoke(&key_id, &principal.tenant).await,
+                ))
+            }
+        });
+
+    // Node maintenance; every action is written to the signed audit log
+    let refresh_post_quantum_keys = warp::path!("admin" / "keys" / "post-quantum" / "refresh")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and_then(
+            move |principal: Principal, node: RealTimeEncryptionNode| async move {
+                let result = node
+                    .refresh_post_quantum_keys(&principal)
+                    .await
+                    .map(|epoch| serde_json::json!({ "key_epoch": epoch }));
+                Ok::<_, warp::Rejection>(admin_reply(result))
+            },
+        );
+
+    let start_scrub = warp::path!("admin" / "storage" / "scrub")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and_then(
+            move |principal: Principal, node: RealTimeEncryptionNode| async move {
+                let reply = match node.start_scrub(&principal).await {
+                    Ok(report) => warp::reply::with_status(
+                        warp::reply::json(&report),
+                        warp::http::StatusCode::ACCEPTED,
+                    ),
+                    result => admin_reply(result),
+                };
+                Ok::<_, warp::Rejection>(reply)
+            },
+        );
+
+    let scrub_report = warp::path!("admin" / "storage" / "scrub" / String)
+        .and(warp::get())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin, Role::Auditor]))
+        .and_then(
+            move |scrub_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    let result = node.scrub_report(&scrub_id).await.and_then(|report| {
+                        let missing = format!("Unknown scrub {}", scrub_id);
+                        report.ok_or(ImmutableEncryptionError::NotFound(missing))
+                    });
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    let flush_anchors = warp::path!("admin" / "anchors" / "flush")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and_then(
+            move |principal: Principal, node: RealTimeEncryptionNode| async move {
+                let result = node
+                    .flush_anchor_queue(&principal)
+                    .await
+                    .map(|queued| serde_json::json!({ "queued_frames": queued }));
+                Ok::<_, warp::Rejection>(admin_reply(result))
+            },
+        );
+
+    let place_hold = warp::path!("admin" / "legal-holds" / String)
+        .and(warp::put())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and(warp::body::json::<LegalHoldRequest>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: LegalHoldRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.place_legal_hold(&evidence_id, &principal, request)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    let release_hold = warp::path!("admin" / "legal-holds" / String)
+        .and(warp::delete())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and(warp::body::json::<LegalHoldRelease>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  release: LegalHoldRelease| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.release_legal_hold(&evidence_id, &principal, release)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    let get_hold = warp::path!("admin" / "legal-holds" / String)
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |evidence_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    let result = node.legal_hold(&evidence_id).await.and_then(|hold| {
+                        let missing = format!("No legal hold on {}", evidence_id);
+                        hold.ok_or(ImmutableEncryptionError::NotFound(missing))
+                    });
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    let place_evidence_hold = warp::path!("evidence" / String / "legal-hold")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Prosecutor],
+        ))
+        .and(warp::body::json::<LegalHoldRequest>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: LegalHoldRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.place_legal_hold(&evidence_id, &principal, request)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    let release_evidence_hold = warp::path!("evidence" / String / "legal-hold" / "release")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Prosecutor],
+        ))
+        .and(warp::body::json::<LegalHoldRelease>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  release: LegalHoldRelease| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.release_legal_hold(&evidence_id, &principal, release)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    // The hold with its signature check and signed history
+    let evidence_hold = warp::path!("evidence" / String / "legal-hold")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |evidence_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    let result = node.legal_hold_status(&evidence_id).await.and_then(|status| {
+                        let missing = format!("No legal hold on {}", evidence_id);
+                        status.ok_or(ImmutableEncryptionError::NotFound(missing))
+                    });
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    let purge_evidence = warp::path!("admin" / "evidence" / String / "purge")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and(warp::body::json::<PurgeRequest>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: PurgeRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.purge_session(
+                            &evidence_id,
+                            &principal,
+                            &request.reason,
+                            request.approval_id.as_deref(),
+                        )
+                        .await,
+                    ))
+                }
+            },
+        );
+
+    // Deletes the frames of sessions past their retention period
+    let purge_expired = warp::path!("admin" / "retention" / "purge")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and_then(
+            move |principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.purge_expired_sessions(&principal).await))
+            },
+        );
+
+    let request_approval = warp::path!("approvals")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and(warp::body::json::<NewApprovalRequest>())
+        .and_then(
+            move |principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: NewApprovalRequest| async move {
+                Ok::<_, warp::Rejection>(admin_reply(
+                    node.request_approval(request.action, request.reason, &principal)
+                        .await,
+                ))
+            },
+        );
+
+    let get_approval = warp::path!("approvals" / String)
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and_then(
+            move |request_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    let result = node.approval_request(&request_id).await.and_then(|request| {
+                        let missing = format!("Unknown approval request {}", request_id);
+                        request.ok_or(ImmutableEncryptionError::NotFound(missing))
+                    });
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    // Which roles may approve is up to the request's policy
+    let approve = warp::path!("approvals" / String / "approve")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and(warp::body::json::<ApproveRequest>())
+        .and_then(
+            move |request_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: ApproveRequest| {
+                async move {
+                    let result = match hex::decode(&request.signature) {
+                        Ok(signature) => {
+                            node.approve_request(&request_id, &principal, &signature)
+                                .await
+                        }
+                        Err(_) => Err(ImmutableEncryptionError::invalid_request(
+                            "signature must be hex",
+                        )),
+                    };
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    // What the caller signs with their own key to approve
+    let approval_statement = warp::path!("approvals" / String / "statement")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and_then(
+            move |request_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    let result = node
+                        .approval_statement(&request_id, &principal)
+                        .await
+                        .map(|statement| {
+                            serde_json::json!({
+                                "signer": principal.accountable(),
+                                "statement_base64": BASE64.encode(statement),
+                            })
+                        });
+                    Ok::<_, warp::Rejection>(admin_reply(result))
+                }
+            },
+        );
+
+    let reject = warp::path!("approvals" / String / "reject")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and(warp::body::json::<RejectApprovalRequest>())
+        .and_then(
+            move |request_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: RejectApprovalRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.reject_request(&request_id, &principal, request.reason)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    let evidence_approvals = warp::path!("evidence" / String / "approvals")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and_then(
+            move |evidence_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.approval_requests(&evidence_id).await,
+                    ))
+                }
+            },
+        );
+
+    let transfer_custody = warp::path!("evidence" / String / "custody-transfers")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Prosecutor, Role::Admin],
+        ))
+        .and(warp::body::json::<CustodyTransferRequest>())
+        .and_then(
+            move |evidence_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: CustodyTransferRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.transfer_custody(
+                            &evidence_id,
+                            &request.to,
+                            &request.reason,
+                            &principal,
+                            request.approval_id.as_deref(),
+                        )
+                        .await,
+                    ))
+                }
+            },
+        );
+
+    // Signed custody entries for every leader that took over the chains
+    let chain_handoffs = warp::path!("admin" / "cluster" / "handoffs")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor],
+        ))
+        .and_then(
+            move |_principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.chain_handoffs().await))
+            },
+        );
+
+    // Device enrollment: issued client certificates and revocations
+    let list_devices = warp::path!("admin" / "devices")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor],
+        ))
+        .and_then(
+            move |_principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.device_enrollments().await))
+            },
+        );
+
+    let get_device = warp::path!("admin" / "devices" / String)
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor],
+        ))
+        .and_then(
+            move |device_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.device_enrollment(&device_id).await,
+                    ))
+                }
+            },
+        );
+
+    let enrollment_challenge = warp::path!("admin" / "devices" / String / "challenge")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and_then(
+            move |device_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.enrollment_challenge(&device_id).await,
+                    ))
+                }
+            },
+        );
+
+    let enroll_device = warp::path!("admin" / "devices" / String / "enroll")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and(warp::body::json::<EnrollmentRequest>())
+        .and_then(
+            move |device_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: EnrollmentRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.enroll_device(&device_id, request, &principal).await,
+                    ))
+                }
+            },
+        );
+
+    let revoke_device = warp::path!("admin" / "devices" / String / "revoke")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and(warp::body::json::<RevokeDeviceRequest>())
+        .and_then(
+            move |device_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: RevokeDeviceRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.revoke_device(&device_id, &request.reason, &principal)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    // Edge sync: field nodes push batches here, signed with their envelope key
+    let edge_sync = warp::path!("admin" / "edge" / "sync")
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Admin],
+        ))
+        .and(warp::body::content_length_limit(
+            config.rate_limit.max_upload_bytes,
+        ))
+        .and(warp::body::json::<SyncBatch>())
+        .and_then(
+            move |principal: Principal, node: RealTimeEncryptionNode, batch: SyncBatch| async move {
+                Ok::<_, warp::Rejection>(admin_reply(
+                    node.accept_edge_sync(batch, &principal).await,
+                ))
+            },
+        );
+
+    // Device sealing: frames sealed on a camera or phone, with their segment proof
+    let device_segment =
+        warp::path!("devices" / "segments")
+            .and(warp::post())
+            .and(tenant_node(
+                auth.clone(),
+                tenants.clone(),
+                &[Role::Operator],
+            ))
+            .and(warp::body::content_length_limit(
+                config.rate_limit.max_upload_bytes,
+            ))
+            .and(warp::body::json::<DeviceSegment>())
+            .and_then(
+                move |principal: Principal,
+                      node: RealTimeEncryptionNode,
+                      segment: DeviceSegment| async move {
+                    let result = node
+                        .accept_device_segment(segment.proof, segment.frames, &principal)
+                        .await;
+                    Ok::<_, warp::Rejection>(match result {
+                        Ok(receipt) => ok_reply(&receipt),
+                        Err(e) => {
+                            warn!("Refused device segment: {}", e);
+                            error_reply(&e)
+                        }
+                    })
+                },
+            );
+
+    let retroactive_anchors = warp::path!("admin" / "edge" / "anchors" / String)
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor],
+        ))
+        .and_then(
+            move |device_id: String,
+                  _principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.retroactive_anchors(&device_id).await,
+                    ))
+                }
+            },
+        );
+
+    // On a field node: what is still waiting for the central node, and a
+    // push without waiting for the next interval
+    let edge_outbox = warp::path!("admin" / "edge" / "outbox")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor],
+        ))
+        .and_then(
+            move |_principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.edge_outbox().await))
+            },
+        );
+
+    let edge_push = warp::path!("admin" / "edge" / "push")
+        .and(warp::post())
+        .and(tenant_node(auth.clone(), tenants.clone(), &[Role::Admin]))
+        .and_then(
+            move |principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.push_to_central(&principal).await))
+            },
+        );
+
+    // Cases group sessions from several cameras under one investigation
+    let list_cases = warp::path!("cases")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and_then(
+            move |principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.list_cases(&principal).await))
+            },
+        );
+
+    let get_case = warp::path!("cases" / String)
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Auditor, Role::Prosecutor, Role::Admin],
+        ))
+        .and_then(
+            move |case_id: String, principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.case(&case_id, &principal).await))
+            },
+        );
+
+    let put_case = warp::path!("cases" / String)
+        .and(warp::put())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Prosecutor, Role::Admin],
+        ))
+        .and(warp::body::json::<CaseRequest>())
+        .and_then(
+            move |case_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode,
+                  request: CaseRequest| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.put_case(&case_id, &principal, request).await,
+                    ))
+                }
+            },
+        );
+
+    let attach_case_session = warp::path!("cases" / String / "sessions" / String)
+        .and(warp::post())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Prosecutor, Role::Admin],
+        ))
+        .and_then(
+            move |case_id: String,
+                  session_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.attach_session_to_case(&case_id, &session_id, &principal)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    let detach_case_session = warp::path!("cases" / String / "sessions" / String)
+        .and(warp::delete())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Operator, Role::Prosecutor, Role::Admin],
+        ))
+        .and_then(
+            move |case_id: String,
+                  session_id: String,
+                  principal: Principal,
+                  node: RealTimeEncryptionNode| {
+                async move {
+                    Ok::<_, warp::Rejection>(admin_reply(
+                        node.detach_session_from_case(&case_id, &session_id, &principal)
+                            .await,
+                    ))
+                }
+            },
+        );
+
+    let case_report = warp::path!("cases" / String / "court-report")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Auditor, Role::Prosecutor],
+        ))
+        .and_then(
+            move |case_id: String, principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(
+                    node.case_court_report(&case_id, &principal).await,
+                ))
+            },
+        );
+
+    let admin_audit = warp::path!("admin" / "audit")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor],
+        ))
+        .and_then(
+            move |_principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.admin_audit_log().await))
+            },
+        );
+
+    let verify_audit = warp::path!("admin" / "audit" / "verify")
+        .and(warp::get())
+        .and(tenant_node(
+            auth.clone(),
+            tenants.clone(),
+            &[Role::Admin, Role::Auditor],
+        ))
+        .and_then(
+            move |_principal: Principal, node: RealTimeEncryptionNode| async move {
+                Ok::<_, warp::Rejection>(admin_reply(node.verify_audit_log().await))
+            },
+        );
+
+    // Per-client rate limiting and body size caps ahead of every route
+    let limiter = Arc::new(RateLimiter::new(config.rate_limit.clone()));
+    let guard = request_guard(limiter, config.ingest.max_frame_bytes as u64);
+
+    // Combine all routes
+    let api = health
+        .or(status)
+        .or(metrics)
+        .or(verify)
+        .or(verify_range)
+        .or(court_report)
+        .or(start_session)
+        .or(stop_session)
+        .or(session_manifest)
+        .or(verify_session)
+        .or(start_verification)
+        .or(verification_events)
+        .or(verification_progress)
+        .or(list_evidence)
+        .or(evidence_frames)
+        .or(evidence_bundle)
+        .or(share_clip)
+        .or(frame_envelope)
+        .or(frame_metadata)
+        .or(register_rendition)
+        .or(redact_session)
+        .or(verify_redaction)
+        .or(playback)
+        .or(snapshot)
+        .or(ingest_frame)
+        .or(issue_api_key)
+        .or(list_api_keys)
+        .or(api_key_usage)
+        .or(rotate_api_key)
+        .or(revoke_api_key)
+        .or(refresh_post_quantum_keys)
+        .or(start_scrub)
+        .or(scrub_report)
+        .or(flush_anchors)
+        .or(place_hold)
+        .or(release_hold)
+        .or(get_hold)
+        .or(place_evidence_hold)
+        .or(release_evidence_hold)
+        .or(evidence_hold)
+        .or(purge_evidence)
+        .or(purge_expired)
+        .or(request_approval)
+        .or(get_approval)
+        .or(approve)
+        .or(approval_statement)
+        .or(reject)
+        .or(evidence_approvals)
+        .or(transfer_custody)
+        .or(chain_handoffs)
+        .or(list_devices)
+        .or(get_device)
+        .or(enrollment_challenge)
+        .or(enroll_device)
+        .or(revoke_device)
+        .or(edge_sync)
+        .or(device_segment)
+        .or(retroactive_anchors)
+        .or(edge_outbox)
+        .or(edge_push)
+        .or(list_cases)
+        .or(get_case)
+        .or(put_case)
+        .or(attach_case_session)
+        .or(detach_case_session)
+        .or(case_report)
+        .or(verify_audit)
+        .or(admin_audit);
+
+    // Every response carries the request ID its logs and spans are tagged with
+    let routes = request_id()
+        .and(guard.and(api).recover(handle_rejection))
+        .map(|request_id: String, reply| {
+            warp::reply::with_header(reply, REQUEST_ID_HEADER, request_id)
+        })
+        .with(
+            warp::cors()
+                .allow_any_origin()
+                .expose_header(REQUEST_ID_HEADER),
+        )
+        .with(warp::log("api"))
+        .with(warp::trace(|info| {
+            tracing::info_span!(
+                "request",
+                method = %info.method(),
+                path = %info.path(),
+                request_id = tracing::field::Empty,
+            )
+        }));
+
+    // Start server
+    let addr = std::net::SocketAddr::new(
+        config.server.host.parse::<std::net::IpAddr>()?,
+        config.server.port,
+    );
+    let tls = &config.server.tls;
+
+    let stop = wait_for_shutdown(shutdown.clone());
+
+    if !tls.enabled {
+        warn!("TLS is disabled; API traffic is sent in plaintext");
+        let (_, server) = warp::serve(routes).try_bind_with_graceful_shutdown(addr, stop)?;
+        server.await;
+    } else if let Some(acme) = &tls.acme {
+        info!(
+            "Serving HTTPS with ACME certificates for {:?}",
+            acme.domains
+        );
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let incoming = rustls_acme::AcmeConfig::new(acme.domains.clone())
+            .contact_push(format!("mailto:{}", acme.contact_email))
+            .cache(rustls_acme::caches::DirCache::new(acme.cache_dir.clone()))
+            .directory_lets_encrypt(acme.production)
+            .tokio_incoming(
+                tokio_stream::wrappers::TcpListenerStream::new(listener),
+                Vec::new(),
+            );
+        warp::serve(routes)
+            .serve_incoming_with_graceful_shutdown(incoming, stop)
+            .await;
+    } else if let Some(client_auth) = &tls.client_auth {
+        info!(
+            "Serving HTTPS with certificate {} and device client certificates",
+            tls.cert_path
+        );
+        serve_with_client_auth(routes, addr, tls, client_auth, shutdown).await?;
+    } else {
+        info!("Serving HTTPS with certificate {}", tls.cert_path);
+        let (_, server) = warp::serve(routes)
+            .tls()
+            .cert_path(&tls.cert_path)
+            .key_path(&tls.key_path)
+            .bind_with_graceful_shutdown(addr, stop);
+        server.await;
+    }
+
+    Ok(())
+}
+
+// Rejects over-limit clients with 429 and oversized declared bodies with 413.
+// Clients are identified by device certificate, then API key, then address.
+fn request_guard(
+    limiter: Arc<RateLimiter>,
+    max_frame_bytes: u64,
+) -> impl warp::Filter<Extract = (), Error = warp::Rejection> + Clone {
+    use warp::Filter;
+
+    warp::ext::optional::<ClientCertificate>()
+        .and(warp::header::optional::<String>("x-api-key"))
+        .and(warp::addr::remote())
+        .and(warp::path::full())
+        .and(warp::header::optional::<u64>("content-length"))
+        .and_then(
+            move |certificate: Option<ClientCertificate>,
+                  api_key: Option<String>,
+                  remote: Option<std::net::SocketAddr>,
+                  path: warp::path::FullPath,
+                  content_length: Option<u64>| {
+                let limiter = limiter.clone();
+                async move {
+                    let limit = limiter.body_limit(path.as_str(), max_frame_bytes);
+                    if content_length.map_or(false, |length| length > limit) {
+                        return Err(warp::reject::custom(ApiRejection {
+                            status: warp::http::StatusCode::PAYLOAD_TOO_LARGE,
+                            error: ImmutableEncryptionError::InvalidRequest(format!(
+                                "request body exceeds {} bytes",
+                                limit
+                            )),
+                        }));
+                    }
+
+                    // Only the key ID is used so secrets never end up in memory maps
+                    let client = match (certificate, api_key, remote) {
+                        (Some(certificate), _, _) => format!("cert:{}", certificate.fingerprint),
+                        (None, Some(api_key), _) => {
+                            format!("apikey:{}", api_key.split('_').nth(1).unwrap_or_default())
+                        }
+                        (None, None, Some(remote)) => format!("ip:{}", remote.ip()),
+                        (None, None, None) => "unknown".to_string(),
+                    };
+
+                    limiter.check(&client).await.map_err(|error| {
+                        warp::reject::custom(ApiRejection {
+                            status: warp::http::StatusCode::TOO_MANY_REQUESTS,
+                            error,
+                        })
+                    })
+                }
+            },
+        )
+        .untuple_one()
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RangeQuery {
+    device: String,
+    #[serde(default)]
+    from: u64,
+    to: Option<u64>, // open-ended when unset
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClipRequest {
+    from: u64,
+    to: u64,
+    recipient_key: String, // hex Kyber1024 public key
+    #[serde(default)]
+    approval_id: Option<String>, // needed when decryption requires approval
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RevokeDeviceRequest {
+    reason: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PurgeRequest {
+    reason: String,
+    #[serde(default)]
+    approval_id: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct NewApprovalRequest {
+    action: ApprovalAction,
+    reason: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ApproveRequest {
+    signature: String, // hex Ed25519 signature over GET /approvals/{id}/statement
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RejectApprovalRequest {
+    reason: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CustodyTransferRequest {
+    to: String,
+    reason: String,
+    #[serde(default)]
+    approval_id: Option<String>,
+}
+
+#[derive(Debug)]
+struct ApiRejection {
+    status: warp::http::StatusCode,
+    error: ImmutableEncryptionError,
+}
+
+impl warp::reject::Reject for ApiRejection {}
+
+impl From<ImmutableEncryptionError> for ApiRejection {
+    fn from(error: ImmutableEncryptionError) -> Self {
+        Self {
+            status: error_status(&error),
+            error,
+        }
+    }
+}
+
+// Adopts the caller's x-request-id or mints one, and tags the request span with it
+fn request_id() -> impl warp::Filter<Extract = (String,), Error = std::convert::Infallible> + Clone
+{
+    use warp::Filter;
+
+    warp::header::headers_cloned().map(|headers: warp::http::HeaderMap| {
+        let id = trace::request_id(headers.get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()));
+        tracing::Span::current().record("request_id", id.as_str());
+        id
+    })
+}
+
+// Like `require_roles`, and also yields the node holding the caller's tenant.
+// Callers whose tenant this node doesn't serve get 403.
+fn tenant_node(
+    auth: Arc<RequestAuthenticator>,
+    tenants: Arc<Tenants>,
+    allowed: &'static [Role],
+) -> impl warp::Filter<Extract = (Principal, RealTimeEncryptionNode), Error = warp::Rejection> + Clone
+{
+    use warp::Filter;
+
+    require_roles(auth, allowed)
+        .and_then(move |principal: Principal| {
+            let node = tenants.get(&principal.tenant).map(|t| t.node.clone());
+            async move {
+                match node {
+                    Some(node) => Ok((principal, node)),
+                    None => Err(warp::reject::custom(ApiRejection {
+                        status: warp::http::StatusCode::FORBIDDEN,
+                        error: ImmutableEncryptionError::PermissionDenied(format!(
+                            "tenant {} is not served by this node",
+                            principal.tenant
+                        )),
+                    })),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+// Authenticates the caller and requires one of `allowed`; 401 for a missing
+// or invalid token, 403 for a valid token without a permitted role.
+fn require_roles(
+    auth: Arc<RequestAuthenticator>,
+    allowed: &'static [Role],
+) -> impl warp::Filter<Extract = (Principal,), Error = warp::Rejection> + Clone {
+    use warp::Filter;
+
+    warp::header::optional::<String>("authorization")
+        .and(warp::header::optional::<String>("x-api-key"))
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(warp::addr::remote())
+        .and_then(
+            move |authorization: Option<String>,
+                  api_key: Option<String>,
+                  method: warp::http::Method,
+                  path: warp::path::FullPath,
+                  remote: Option<std::net::SocketAddr>| {
+                let auth = auth.clone();
+                async move {
+                    let action = format!("{} {}", method, path.as_str());
+                    let mut principal = auth
+                        .authenticate(authorization.as_deref(), api_key.as_deref(), &action)
+                        .await
+                        .map_err(|error| {
+                            let status = match error {
+                                ImmutableEncryptionError::PermissionDenied(_) => {
+                                    warp::http::StatusCode::UNAUTHORIZED
+                                }
+                                ImmutableEncryptionError::RateLimitExceeded(_) => {
+                                    warp::http::StatusCode::TOO_MANY_REQUESTS
+                                }
+                                _ => warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                            };
+                            warp::reject::custom(ApiRejection { status, error })
+                        })?;
+                    principal.require_any(allowed).map_err(|error| {
+                        warp::reject::custom(ApiRejection {
+                            status: warp::http::StatusCode::FORBIDDEN,
+                            error,
+                        })
+                    })?;
+                    principal.source = remote.map(|addr| addr.ip().to_string());
+                    Ok::<_, warp::Rejection>(principal)
+                }
+            },
+        )
+}
+
+fn api_key_reply<T: serde::Serialize>(
+    result: Result<T, ImmutableEncryptionError>,
+) -> warp::reply::WithStatus<warp::reply::Json> {
+    match result {
+        Ok(value) => ok_reply(&value),
+        Err(e) => {
+            error!("API key operation failed: {}", e);
+            error_reply(&e)
+        }
+    }
+}
+
+fn admin_reply<T: serde::Serialize>(
+    result: Result<T, ImmutableEncryptionError>,
+) -> warp::reply::WithStatus<warp::reply::Json> {
+    match result {
+        Ok(value) => ok_reply(&value),
+        Err(e) => {
+            error!("Admin operation failed: {}", e);
+            error_reply(&e)
+        }
+    }
+}
+
+// Streams the whole bundle, or the requested range with a 206. A range needs
+// the bundle's size, which takes a pass over the frames unless the bundle was
+// generated before; the SHA-256 found with it is a strong ETag, so a client
+// resuming with If-Range gets all of a bundle whose frames have since changed.
+async fn bundle_reply(
+    bundle: EvidenceBundle,
+    range: Option<&str>,
+    if_range: Option<&str>,
+) -> warp::http::Response<warp::hyper::Body> {
+    use warp::http::{header, StatusCode};
+
+    let filename = format!(
+        "attachment; filename=\"bundle-{}.ndjson\"",
+        bundle.evidence_id()
+    );
+    let builder = warp::http::Response::builder()
+        .header(header::CONTENT_TYPE, BUNDLE_CONTENT_TYPE)
+        .header(header::CONTENT_DISPOSITION, filename)
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    let digest = match range {
+        None => bundle.cached_digest(),
+        Some(_) => match bundle.digest().await {
+            Ok(digest) => Some(digest),
+            Err(e) => {
+                error!("Failed to size bundle: {}", e);
+                return json_error(&e);
+            }
+        },
+    };
+    let digest = match digest {
+        Some(digest) => digest,
+        None => {
+            return builder
+                .body(warp::hyper::Body::wrap_stream(bundle.stream(None)))
+                .unwrap_or_default()
+        }
+    };
+    let builder = builder.header(header::ETAG, digest.etag());
+
+    let range = range.filter(|_| if_range_matches(if_range, &digest));
+    let response = match parse_range(range, digest.byte_len) {
+        Ok(Some((start, end))) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, digest.byte_len),
+            )
+            .header(header::CONTENT_LENGTH, end - start + 1)
+            .body(warp::hyper::Body::wrap_stream(
+                bundle.stream(Some((start, end))),
+            )),
+        Ok(None) => builder
+            .header(header::CONTENT_LENGTH, digest.byte_len)
+            .body(warp::hyper::Body::wrap_stream(bundle.stream(None))),
+        Err(e) => warp::http::Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes */{}", digest.byte_len),
+            )
+            .body(warp::hyper::Body::from(
+                serde_json::to_vec(&e.body()).unwrap_or_default(),
+            )),
+    };
+
+    response.unwrap_or_default()
+}
+
+fn json_error(error: &ImmutableEncryptionError) -> warp::http::Response<warp::hyper::Body> {
+    warp::http::Response::builder()
+        .status(error_status(error))
+        .header("content-type", "application/json")
+        .body(warp::hyper::Body::from(
+            serde_json::to_vec(&error.body()).unwrap_or_default(),
+        ))
+        .unwrap_or_default()
+}
+
+// Emits the job's current progress, then every change, ending after the
+// event that reports it finished
+fn progress_events(
+    rx: tokio::sync::watch::Receiver<VerificationProgress>,
+) -> impl futures::Stream<Item = Result<warp::sse::Event, std::convert::Infallible>> {
+    futures::stream::unfold(Some((rx, true)), |state| async move {
+        let (mut rx, first) = state?;
+        if !first && rx.changed().await.is_err() {
+            return None; // the job was evicted
+        }
+
+        let progress = rx.borrow_and_update().clone();
+        let event = warp::sse::Event::default()
+            .event(if progress.is_finished() {
+                "finished"
+            } else {
+                "progress"
+            })
+            .json_data(&progress)
+            .unwrap_or_else(|e| {
+                warp::sse::Event::default()
+                    .event("error")
+                    .data(e.to_string())
+            });
+        let next = if progress.is_finished() {
+            None
+        } else {
+            Some((rx, false))
+        };
+        Some((Ok(event), next))
+    })
+}
+
+fn listing_reply<T: serde::Serialize>(
+    result: Result<T, ImmutableEncryptionError>,
+) -> warp::reply::WithStatus<warp::reply::Json> {
+    match result {
+        Ok(page) => ok_reply(&page),
+        Err(e) => {
+            error!("Evidence listing failed: {}", e);
+            error_reply(&e)
+        }
+    }
+}
+
+fn ok_reply<T: serde::Serialize>(value: &T) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(value), warp::http::StatusCode::OK)
+}
+
+// Error responses carry the error's stable code and its mapped status
+fn error_reply(error: &ImmutableEncryptionError) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(&error.body()), error_status(error))
+}
+
+fn error_status(error: &ImmutableEncryptionError) -> warp::http::StatusCode {
+    warp::http::StatusCode::from_u16(error.http_status())
+        .unwrap_or(warp::http::StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn handle_rejection(
+    rejection: warp::Rejection,
+) -> Result<impl warp::Reply, std::convert::Infallible> {
+    use warp::Reply;
+
+    let (status, body) = if let Some(api) = rejection.find::<ApiRejection>() {
+        if api.status == warp::http::StatusCode::TOO_MANY_REQUESTS {
+            warn!("Rate limited: {}", api.error);
+        }
+        (api.status, api.error.body())
+    } else if rejection.is_not_found() {
+        (
+            warp::http::StatusCode::NOT_FOUND,
+            ImmutableEncryptionError::not_found("no such route").body(),
+        )
+    } else if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        (
+            warp::http::StatusCode::PAYLOAD_TOO_LARGE,
+            ImmutableEncryptionError::invalid_request("request body too large").body(),
+        )
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (
+            warp::http::StatusCode::METHOD_NOT_ALLOWED,
+            ImmutableEncryptionError::invalid_request("method not allowed").body(),
+        )
+    } else {
+        (
+            warp::http::StatusCode::BAD_REQUEST,
+            ImmutableEncryptionError::InvalidRequest(format!("{:?}", rejection)).body(),
+        )
+    };
+
+    let mut response = warp::reply::with_status(warp::reply::json(&body), status).into_response();
+    if status == warp::http::StatusCode::TOO_MANY_REQUESTS {
+        response.headers_mut().insert(
+            warp::http::header::RETRY_AFTER,
+            warp::http::HeaderValue::from_static("1"),
+        );
+    }
+
+    Ok(response)
+}
+
+// Playback accepts a JWT with a review role when JWT auth is enabled, and the
+// configured investigator tokens otherwise.
+async fn resolve_investigator(
+    auth: &RequestAuthenticator,
+    tenants: &Tenants,
+    authorization: &str,
+) -> Result<(Principal, RealTimeEncryptionNode, Arc<PlaybackService>), ImmutableEncryptionError> {
+    let runtime = |tenant: &str| {
+        tenants.get(tenant).ok_or_else(|| {
+            ImmutableEncryptionError::PermissionDenied(format!(
+                "Tenant {} is not served by this node",
+                tenant
+            ))
+        })
+    };
+
+    if auth.jwt_enabled() {
+        let principal = auth.authenticate(Some(authorization), None, "").await?;
+        principal.require_any(&[Role::Prosecutor, Role::Auditor])?;
+        let runtime = runtime(&principal.tenant)?;
+        Ok((principal, runtime.node.clone(), runtime.playback.clone()))
+    } else {
+        // Static playback tokens predate tenants and only reach the default one
+        let token = authorization
+            .strip_prefix("Bearer ")
+            .unwrap_or(authorization);
+        let runtime = runtime(DEFAULT_TENANT)?;
+        let principal = Principal {
+            subject: runtime.playback.authorize(token)?,
+            roles: Vec::new(),
+            tenant: DEFAULT_TENANT.to_string(),
+            source: Some("playback".to_string()),
+            issued_by: None,
+        };
+        Ok((principal, runtime.node.clone(), runtime.playback.clone()))
+    }
+}
+
+// The gRPC API reuses the HTTP certificate; ACME-managed certificates are
+// only served by the HTTP listener.
+fn grpc_tls_config(
+    tls: &TlsConfig,
+) -> Result<Option<tonic::transport::ServerTlsConfig>, Box<dyn std::error::Error>> {
+    if !tls.enabled || tls.acme.is_some() {
+        return Ok(None);
+    }
+
+    let cert = std::fs::read(&tls.cert_path)?;
+    let key = std::fs::read(&tls.key_path)?;
+    let mut grpc_tls = tonic::transport::ServerTlsConfig::new()
+        .identity(tonic::transport::Identity::from_pem(cert, key));
+
+    if let Some(client_auth) = &tls.client_auth {
+        let ca = std::fs::read(&client_auth.ca_path)?;
+        grpc_tls = grpc_tls
+            .client_ca_root(tonic::transport::Certificate::from_pem(ca))
+            .client_auth_optional(true);
+    }
+
+    Ok(Some(grpc_tls))
+}
+
+// HTTPS with optional client certificates. Warp can't see the TLS session, so
+// connections are accepted here and the device certificate is handed to the
+// routes as a request extension. Certificates stay optional at the handshake
+// so investigators can still reach read-only routes; ingestion enforces
+// enrollment.
+async fn serve_with_client_auth<F>(
+    routes: F,
+    addr: std::net::SocketAddr,
+    tls: &TlsConfig,
+    client_auth: &ClientAuthConfig,
+    shutdown: tokio::sync::watch::Receiver<bool>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: warp::Filter + Clone + Send + Sync + 'static,
+    F::Extract: warp::Reply,
+{
+    use std::io::BufReader;
+    use tokio_rustls::rustls;
+    use warp::hyper::service::Service;
+
+    let open = |path: &str| -> std::io::Result<BufReader<std::fs::File>> {
+        Ok(BufReader::new(std::fs::File::open(path)?))
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(&tls.cert_path)?).collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut open(&tls.key_path)?)?
+        .ok_or_else(|| format!("No private key found in {}", tls.key_path))?;
+
+    let mut roots = rustls::RootCertStore::empty();
+    for ca in rustls_pemfile::certs(&mut open(&client_auth.ca_path)?) {
+        roots.add(ca?)?;
+    }
+    let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(roots))
+        .allow_unauthenticated()
+        .build()?;
+
+    let mut server_config = rustls::ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let service = warp::service(routes);
+
+    let stop = wait_for_shutdown(shutdown.clone());
+    tokio::pin!(stop);
+    let mut connections = tokio::task::JoinSet::new();
+
+    loop {
+        let (tcp, peer) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = &mut stop => break,
+        };
+        let acceptor = acceptor.clone();
+        let service = service.clone();
+        let mut shutdown = shutdown.clone();
+
+        connections.spawn(async move {
+            let stream = match acceptor.accept(tcp).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+
+            let certificate = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .map(|cert| ClientCertificate::from_der(cert.as_ref()));
+
+            let connection_service =
+                warp::hyper::service::service_fn(move |mut request: warp::hyper::Request<_>| {
+                    if let Some(certificate) = &certificate {
+                        request.extensions_mut().insert(certificate.clone());
+                    }
+                    let mut service = service.clone();
+                    async move { service.call(request).await }
+                });
+
+            let connection =
+                warp::hyper::server::conn::Http::new().serve_connection(stream, connection_service);
+            tokio::pin!(connection);
+
+            // Finish the in-flight request, then close instead of keeping alive
+            let result = tokio::select! {
+                result = &mut connection => result,
+                _ = shutdown.wait_for(|stop| *stop) => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(e) = result {
+                warn!("HTTPS connection from {} failed: {}", peer, e);
+            }
+        });
+
+        // Reap finished connections so the set doesn't grow unbounded
+        while connections.try_join_next().is_some() {}
+    }
+
+    drop(listener);
+    while connections.join_next().await.is_some() {}
+
+    Ok(())
+}
+
+// Builds a frame from `POST /frames` headers:
+//   x-device-id (required), x-resolution "WxH" (required), x-fps (required),
+//   x-codec (required), x-frame-timestamp (defaults to now),
+//   x-frame-sequence (assigned if absent), x-location "lat,lon" (optional),
+//   x-frame-signature (hex Ed25519 signature by the device, optional)
+fn frame_from_headers(
+    headers: &warp::http::HeaderMap,
+    data: bytes::Bytes,
+) -> Result<VideoFrame, String> {
+    let header = |name: &str| -> Option<String> {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    };
+    let required = |name: &str| header(name).ok_or_else(|| format!("missing header {}", name));
+
+    let device_id = required("x-device-id")?;
+
+    let resolution = required("x-resolution")?;
+    let (width, height) = resolution
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+        .ok_or_else(|| {
+            format!(
+                "invalid x-resolution {:?}, expected WIDTHxHEIGHT",
+                resolution
+            )
+        })?;
+
+    let fps = required("x-fps")?
+        .parse::<u32>()
+        .map_err(|e| format!("invalid x-fps: {}", e))?;
+
+    let codec = required("x-codec")?;
+
+    let timestamp = match header("x-frame-timestamp") {
+        Some(ts) => ts
+            .parse::<u64>()
+            .map_err(|e| format!("invalid x-frame-timestamp: {}", e))?,
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_secs(),
+    };
+
+    let sequence = match header("x-frame-sequence") {
+        Some(seq) => seq
+            .parse::<u64>()
+            .map_err(|e| format!("invalid x-frame-sequence: {}", e))?,
+        None => 0,
+    };
+
+    let location = match header("x-location") {
+        Some(location) => Some(
+            location
+                .split_once(',')
+                .and_then(|(lat, lon)| {
+                    Some((
+                        lat.trim().parse::<f64>().ok()?,
+                        lon.trim().parse::<f64>().ok()?,
+                    ))
+                })
+                .ok_or_else(|| format!("invalid x-location {:?}, expected LAT,LON", location))?,
+        ),
+        None => None,
+    };
+
+    let signature = match header("x-frame-signature") {
+        Some(signature) => {
+            Some(hex::decode(signature).map_err(|e| format!("invalid x-frame-signature: {}", e))?)
+        }
+        None => None,
+    };
+
+    Ok(VideoFrame {
+        timestamp,
+        sequence,
+        data,
+        metadata: FrameMetadata {
+            device_id,
+            location,
+            resolution: (width, height),
+            fps,
+            codec,
+            telemetry: None,
+        },
+        signature,
+    })
 }
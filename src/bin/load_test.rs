@@ -0,0 +1,545 @@
+use clap::{Arg, ArgMatches, Command};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use immutable_encryption::completions;
+
+// Slower responses count as failed frames rather than stalling a device
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// What every simulated device sends
+struct Target {
+    frames_url: String,
+    device_prefix: String,
+    resolution: (u32, u32),
+    fps: u32,
+    codec: String,
+    frame_bytes: usize,
+    in_flight: usize,
+}
+
+// Outcomes across all devices
+#[derive(Debug, Default)]
+struct Tally {
+    generated: AtomicU64, // frames the cameras produced
+    dropped: AtomicU64,   // never sent: the device already had `in_flight` outstanding
+    sealed: AtomicU64,    // 201
+    pending: AtomicU64,   // 202: accepted but not sealed within the node's wait
+    rejected: AtomicU64,  // any other status, e.g. 429 or 503
+    failed: AtomicU64,    // connection errors and timeouts
+    bytes_accepted: AtomicU64,
+    latencies: Mutex<Vec<Duration>>, // sealed frames, request to response
+}
+
+// The node's anchoring queue as seen through `/status` during the run
+#[derive(Debug, Default, Serialize)]
+struct Backlog {
+    max_queue_depth: u64,
+    final_queue_depth: u64,
+    frames_stored: u64, // during the run
+    last_batch_latency_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    devices: usize,
+    resolution: String,
+    fps: u32,
+    frame_bytes: usize,
+    duration_secs: f64,
+    offered_fps: f64,
+    achieved_fps: f64, // frames the node accepted, sealed or pending
+    throughput_mib_s: f64,
+    frames_generated: u64,
+    frames_sealed: u64,
+    frames_pending: u64,
+    frames_dropped: u64,
+    frames_rejected: u64,
+    frames_failed: u64,
+    drop_rate: f64, // share of generated frames the node did not accept
+    latency_p50_ms: f64,
+    latency_p99_ms: f64,
+    anchor_backlog: Option<Backlog>, // None when `/status` was not readable
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // stderr keeps stdout for the report
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Command::new("load-test")
+        .version("0.1.0")
+        .about("Simulates concurrent cameras against a running node to size a deployment")
+        .arg(
+            Arg::new("server")
+                .short('s')
+                .long("server")
+                .value_name("URL")
+                .help("Server URL (default: http://localhost:8080)")
+                .default_value("http://localhost:8080"),
+        )
+        .arg(
+            Arg::new("devices")
+                .short('n')
+                .long("devices")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("4")
+                .help("Concurrent simulated devices"),
+        )
+        .arg(
+            Arg::new("resolution")
+                .short('r')
+                .long("resolution")
+                .value_name("WIDTHxHEIGHT")
+                .default_value("1920x1080")
+                .help("Resolution each device reports"),
+        )
+        .arg(
+            Arg::new("fps")
+                .long("fps")
+                .value_name("FPS")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .default_value("30")
+                .help("Frames per second per device"),
+        )
+        .arg(
+            Arg::new("frame-bytes")
+                .long("frame-bytes")
+                .value_name("BYTES")
+                .value_parser(clap::value_parser!(usize))
+                .help(
+                    "Payload size (default: an MJPEG frame at the resolution, 1 byte per 8 pixels)",
+                ),
+        )
+        .arg(
+            Arg::new("codec")
+                .long("codec")
+                .value_name("CODEC")
+                .default_value("MJPEG")
+                .help("Codec each device reports"),
+        )
+        .arg(
+            Arg::new("duration")
+                .short('d')
+                .long("duration")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("60")
+                .help("How long devices send frames"),
+        )
+        .arg(
+            Arg::new("in-flight")
+                .long("in-flight")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("4")
+                .help("Requests one device may have outstanding; further frames are dropped"),
+        )
+        .arg(
+            Arg::new("device-prefix")
+                .long("device-prefix")
+                .value_name("PREFIX")
+                .default_value("load-test-")
+                .help("Device IDs are PREFIX0, PREFIX1, ..."),
+        )
+        .arg(
+            Arg::new("status-interval")
+                .long("status-interval")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("5")
+                .help("How often to read the anchor backlog from /status"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FORMAT")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Report format; json prints a single object on stdout"),
+        )
+        .arg(
+            Arg::new("token")
+                .short('t')
+                .long("token")
+                .value_name("TOKEN")
+                .help("Operator bearer token (JWT or API key) for /frames and /status"),
+        )
+        .arg(
+            Arg::new("client-cert")
+                .long("client-cert")
+                .value_name("PEM")
+                .requires("client-key")
+                .help("Device certificate for servers that require mutual TLS"),
+        )
+        .arg(
+            Arg::new("client-key")
+                .long("client-key")
+                .value_name("PEM")
+                .requires("client-cert")
+                .help("Private key for --client-cert"),
+        )
+        .arg(
+            Arg::new("ca-cert")
+                .long("ca-cert")
+                .value_name("PEM")
+                .help("CA certificate to trust for the server, e.g. a private CA"),
+        )
+        .subcommand(completions::command());
+    let matches = cli.clone().get_matches();
+    if let Some(("completions", args)) = matches.subcommand() {
+        return Ok(completions::generate(cli, args, &mut std::io::stdout())?);
+    }
+
+    let server = matches
+        .get_one::<String>("server")
+        .unwrap()
+        .trim_end_matches('/')
+        .to_string();
+    let resolution = parse_resolution(matches.get_one::<String>("resolution").unwrap())?;
+    let target = Arc::new(Target {
+        frames_url: format!("{}/frames", server),
+        device_prefix: matches.get_one::<String>("device-prefix").unwrap().clone(),
+        resolution,
+        fps: *matches.get_one::<u32>("fps").unwrap(),
+        codec: matches.get_one::<String>("codec").unwrap().clone(),
+        frame_bytes: matches
+            .get_one::<usize>("frame-bytes")
+            .copied()
+            .unwrap_or((resolution.0 as usize * resolution.1 as usize) / 8),
+        in_flight: *matches.get_one::<u64>("in-flight").unwrap() as usize,
+    });
+    let devices = *matches.get_one::<u64>("devices").unwrap() as usize;
+    let duration = Duration::from_secs(*matches.get_one::<u64>("duration").unwrap());
+    let status_interval = Duration::from_secs(*matches.get_one::<u64>("status-interval").unwrap());
+    let client = build_client(&matches)?;
+
+    info!(
+        "{} devices at {}x{} and {} fps ({} KiB frames) against {} for {}s",
+        devices,
+        target.resolution.0,
+        target.resolution.1,
+        target.fps,
+        target.frame_bytes / 1024,
+        server,
+        duration.as_secs()
+    );
+
+    let tally = Arc::new(Tally::default());
+    let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+    let monitor = tokio::spawn(watch_backlog(
+        client.clone(),
+        format!("{}/status", server),
+        status_interval,
+        tally.clone(),
+        stop_rx,
+    ));
+
+    let started = Instant::now();
+    let until = started + duration;
+    let mut cameras = JoinSet::new();
+    for device in 0..devices {
+        cameras.spawn(run_device(
+            client.clone(),
+            target.clone(),
+            device,
+            tally.clone(),
+            until,
+        ));
+    }
+    while cameras.join_next().await.is_some() {}
+    let elapsed = started.elapsed();
+
+    let _ = stop_tx.send(true);
+    let backlog = monitor.await.unwrap_or(None);
+
+    let report = build_report(devices, &target, elapsed, &tally, backlog);
+    if matches.get_one::<String>("output").map(String::as_str) == Some("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_text(&report);
+    }
+    Ok(())
+}
+
+// One camera: a frame every 1/fps seconds, sent without waiting for the last
+// response, up to `in_flight` at a time
+async fn run_device(
+    client: Client,
+    target: Arc<Target>,
+    device: usize,
+    tally: Arc<Tally>,
+    until: Instant,
+) {
+    let device_id = format!("{}{}", target.device_prefix, device);
+    let resolution = format!("{}x{}", target.resolution.0, target.resolution.1);
+    let payload = synthetic_payload(target.frame_bytes, device as u64);
+    let slots = Arc::new(Semaphore::new(target.in_flight));
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / target.fps as f64));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut requests = JoinSet::new();
+
+    loop {
+        ticker.tick().await;
+        if Instant::now() >= until {
+            break;
+        }
+        tally.generated.fetch_add(1, Ordering::Relaxed);
+        let permit = match slots.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                tally.dropped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+        };
+
+        let request = client
+            .post(&target.frames_url)
+            .header("content-type", "application/octet-stream")
+            .header("x-device-id", &device_id)
+            .header("x-resolution", &resolution)
+            .header("x-fps", target.fps.to_string())
+            .header("x-codec", &target.codec)
+            .body(payload.clone());
+        let tally = tally.clone();
+        let bytes = payload.len() as u64;
+        requests.spawn(async move {
+            let _permit = permit;
+            let sent = Instant::now();
+            match request.send().await.map(|response| response.status()) {
+                Ok(StatusCode::CREATED) => {
+                    tally.sealed.fetch_add(1, Ordering::Relaxed);
+                    tally.bytes_accepted.fetch_add(bytes, Ordering::Relaxed);
+                    let mut latencies = tally.latencies.lock().unwrap_or_else(|e| e.into_inner());
+                    latencies.push(sent.elapsed());
+                }
+                Ok(StatusCode::ACCEPTED) => {
+                    tally.pending.fetch_add(1, Ordering::Relaxed);
+                    tally.bytes_accepted.fetch_add(bytes, Ordering::Relaxed);
+                }
+                Ok(status) => {
+                    tally.rejected.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("Frame rejected with {}", status);
+                }
+                Err(e) => {
+                    tally.failed.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("Frame request failed: {}", e);
+                }
+            }
+        });
+    }
+
+    while requests.join_next().await.is_some() {}
+}
+
+// Polls `/status` for the anchoring queue and logs progress until stopped,
+// then takes a final reading. None if the node never answered.
+async fn watch_backlog(
+    client: Client,
+    status_url: String,
+    interval: Duration,
+    tally: Arc<Tally>,
+    mut stop: tokio::sync::watch::Receiver<bool>,
+) -> Option<Backlog> {
+    let mut backlog: Option<Backlog> = None;
+    let mut stored_at_start: Option<u64> = None;
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        let stopping = tokio::select! {
+            _ = ticker.tick() => false,
+            _ = stop.changed() => true,
+        };
+
+        match read_status(&client, &status_url).await {
+            Ok(status) => {
+                let field = |name: &str| status.get(name).and_then(Value::as_u64).unwrap_or(0);
+                let stored = field("frames_stored");
+                let baseline = *stored_at_start.get_or_insert(stored);
+                let backlog = backlog.get_or_insert_with(Backlog::default);
+                backlog.final_queue_depth = field("batch_queue_depth");
+                backlog.max_queue_depth = backlog.max_queue_depth.max(backlog.final_queue_depth);
+                backlog.frames_stored = stored.saturating_sub(baseline);
+                backlog.last_batch_latency_ms = field("last_batch_latency_ms");
+                info!(
+                    "sealed {}, pending {}, dropped {}, rejected {}, failed {}; \
+                     anchor queue {}, stored {}",
+                    tally.sealed.load(Ordering::Relaxed),
+                    tally.pending.load(Ordering::Relaxed),
+                    tally.dropped.load(Ordering::Relaxed),
+                    tally.rejected.load(Ordering::Relaxed),
+                    tally.failed.load(Ordering::Relaxed),
+                    backlog.final_queue_depth,
+                    backlog.frames_stored
+                );
+            }
+            Err(e) => warn!("Could not read the anchor backlog: {}", e),
+        }
+
+        if stopping {
+            return backlog;
+        }
+    }
+}
+
+async fn read_status(client: &Client, url: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(format!("/status returned {}", response.status()).into());
+    }
+    Ok(response.json().await?)
+}
+
+fn build_report(
+    devices: usize,
+    target: &Target,
+    elapsed: Duration,
+    tally: &Tally,
+    anchor_backlog: Option<Backlog>,
+) -> Report {
+    let count = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    let accepted = count(&tally.sealed) + count(&tally.pending);
+    let generated = count(&tally.generated);
+
+    let mut latencies =
+        std::mem::take(&mut *tally.latencies.lock().unwrap_or_else(|e| e.into_inner()));
+    latencies.sort();
+    let percentile = |p: f64| {
+        if latencies.is_empty() {
+            return 0.0;
+        }
+        let rank = ((latencies.len() as f64 * p).ceil() as usize).clamp(1, latencies.len());
+        latencies[rank - 1].as_secs_f64() * 1000.0
+    };
+
+    Report {
+        devices,
+        resolution: format!("{}x{}", target.resolution.0, target.resolution.1),
+        fps: target.fps,
+        frame_bytes: target.frame_bytes,
+        duration_secs: secs,
+        offered_fps: generated as f64 / secs,
+        achieved_fps: accepted as f64 / secs,
+        throughput_mib_s: count(&tally.bytes_accepted) as f64 / (1024.0 * 1024.0) / secs,
+        frames_generated: generated,
+        frames_sealed: count(&tally.sealed),
+        frames_pending: count(&tally.pending),
+        frames_dropped: count(&tally.dropped),
+        frames_rejected: count(&tally.rejected),
+        frames_failed: count(&tally.failed),
+        drop_rate: if generated == 0 {
+            0.0
+        } else {
+            1.0 - accepted as f64 / generated as f64
+        },
+        latency_p50_ms: percentile(0.5),
+        latency_p99_ms: percentile(0.99),
+        anchor_backlog,
+    }
+}
+
+fn print_text(report: &Report) {
+    println!(
+        "{} devices at {} and {} fps, {} KiB frames, {:.1}s",
+        report.devices,
+        report.resolution,
+        report.fps,
+        report.frame_bytes / 1024,
+        report.duration_secs
+    );
+    println!(
+        "Throughput: {:.1} fps accepted of {:.1} offered ({:.1} MiB/s)",
+        report.achieved_fps, report.offered_fps, report.throughput_mib_s
+    );
+    println!(
+        "Frames: {} generated, {} sealed, {} pending, {} dropped, {} rejected, {} failed",
+        report.frames_generated,
+        report.frames_sealed,
+        report.frames_pending,
+        report.frames_dropped,
+        report.frames_rejected,
+        report.frames_failed
+    );
+    println!("Drop rate: {:.2}%", report.drop_rate * 100.0);
+    println!(
+        "Seal latency: p50 {:.1} ms, p99 {:.1} ms",
+        report.latency_p50_ms, report.latency_p99_ms
+    );
+    match &report.anchor_backlog {
+        Some(backlog) => println!(
+            "Anchor backlog: {} queued at the end (max {}), {} frames stored, last batch {} ms",
+            backlog.final_queue_depth,
+            backlog.max_queue_depth,
+            backlog.frames_stored,
+            backlog.last_batch_latency_ms
+        ),
+        None => println!("Anchor backlog: unavailable (/status needs an operator token)"),
+    }
+}
+
+// Token, client certificate and trusted CA apply to every request
+fn build_client(matches: &ArgMatches) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut builder = Client::builder().use_rustls_tls().timeout(REQUEST_TIMEOUT);
+
+    if let Some(token) = matches.get_one::<String>("token") {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))?;
+        value.set_sensitive(true);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+        builder = builder.default_headers(headers);
+    }
+
+    if let (Some(cert), Some(key)) = (
+        matches.get_one::<String>("client-cert"),
+        matches.get_one::<String>("client-key"),
+    ) {
+        // rustls wants the key and certificate chain in one PEM buffer
+        let mut pem = std::fs::read(key)?;
+        pem.push(b'\n');
+        pem.extend(std::fs::read(cert)?);
+        builder = builder.identity(reqwest::Identity::from_pem(&pem)?);
+    }
+
+    if let Some(ca) = matches.get_one::<String>("ca-cert") {
+        builder =
+            builder.add_root_certificate(reqwest::Certificate::from_pem(&std::fs::read(ca)?)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+fn parse_resolution(value: &str) -> Result<(u32, u32), String> {
+    value
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| format!("invalid resolution {:?}, expected WIDTHxHEIGHT", value))
+}
+
+// Incompressible bytes, different per device, shared by all of its frames
+fn synthetic_payload(len: usize, seed: u64) -> bytes::Bytes {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect::<Vec<u8>>()
+        .into()
+}
@@ -0,0 +1,4 @@
+// Generates the Kotlin and Swift bindings for the `mobile` feature
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
@@ -1,20 +1,78 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use reqwest::Client;
+use serde::Serialize;
 use serde_json::Value;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{error, info, warn};
 use tracing_subscriber;
 
+use immutable_encryption::completions;
+
+// Reconnect delay for a dropped event stream, doubling up to the maximum
+const WATCH_BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+const WATCH_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+// Exit codes for pipelines: 0 valid, 2 invalid, 3 could not verify
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Valid,
+    Invalid,
+    Error,
+}
+
+impl Outcome {
+    fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Valid => 0,
+            Outcome::Invalid => 2,
+            Outcome::Error => 3,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    evidence_id: String,
+    status: Outcome,
+    warnings: Vec<String>,
+    result: Option<Value>, // server response body
+    error: Option<String>,
+}
+
+impl Report {
+    fn new(evidence_id: &str, status: Outcome, result: Value) -> Self {
+        Self {
+            evidence_id: evidence_id.to_string(),
+            status,
+            warnings: warnings(&result),
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn failed(evidence_id: &str, error: String) -> Self {
+        Self {
+            evidence_id: evidence_id.to_string(),
+            status: Outcome::Error,
+            warnings: Vec::new(),
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
+    // Initialize logging; stderr keeps stdout for the result
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::INFO)
+        .with_writer(std::io::stderr)
         .init();
 
     // Parse command line arguments
-    let matches = Command::new("verification-client")
+    let cli = Command::new("verification-client")
         .version("0.1.0")
         .about("Client for verifying immutable encrypted evidence")
         .arg(
@@ -37,127 +95,397 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Arg::new("court-report")
                 .short('c')
                 .long("court-report")
+                .action(ArgAction::SetTrue)
                 .help("Generate court report instead of basic verification"),
         )
         .arg(
             Arg::new("watch")
                 .short('w')
                 .long("watch")
-                .help("Watch for verification updates"),
+                .action(ArgAction::SetTrue)
+                .help("Run a background verification job and follow its progress"),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Give up after SECONDS; reported as an error (exit code 3)"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FORMAT")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Result format; json prints a single object on stdout"),
+        )
+        .arg(
+            Arg::new("token")
+                .short('t')
+                .long("token")
+                .value_name("TOKEN")
+                .help("Bearer token (JWT or API key) sent with every request"),
+        )
+        .arg(
+            Arg::new("client-cert")
+                .long("client-cert")
+                .value_name("PEM")
+                .requires("client-key")
+                .help("Client certificate for servers that require mutual TLS"),
+        )
+        .arg(
+            Arg::new("client-key")
+                .long("client-key")
+                .value_name("PEM")
+                .requires("client-cert")
+                .help("Private key for --client-cert"),
+        )
+        .arg(
+            Arg::new("ca-cert")
+                .long("ca-cert")
+                .value_name("PEM")
+                .help("CA certificate to trust for the server, e.g. a private CA"),
         )
-        .get_matches();
+        .arg(
+            Arg::new("fail-on-warning")
+                .long("fail-on-warning")
+                .action(ArgAction::SetTrue)
+                .help("Treat warnings, such as unconfirmed anchors, as invalid"),
+        )
+        .subcommand_negates_reqs(true)
+        .subcommand(completions::command());
+    let matches = cli.clone().get_matches();
+    if let Some(("completions", args)) = matches.subcommand() {
+        return Ok(completions::generate(cli, args, &mut std::io::stdout())?);
+    }
 
     let server_url = matches.get_one::<String>("server").unwrap();
     let evidence_id = matches.get_one::<String>("evidence").unwrap();
     let generate_court_report = matches.get_flag("court-report");
     let watch_mode = matches.get_flag("watch");
+    let json_output = matches.get_one::<String>("output").map(String::as_str) == Some("json");
 
     info!("Connecting to verification server at {}", server_url);
 
-    let client = Client::new();
+    let client = match build_client(&matches) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Invalid client configuration: {}", e);
+            std::process::exit(Outcome::Error.exit_code());
+        }
+    };
 
-    if watch_mode {
-        watch_verification(&client, server_url, evidence_id).await?;
-    } else if generate_court_report {
-        generate_court_report_request(&client, server_url, evidence_id).await?;
+    let request = async {
+        if watch_mode {
+            watch_verification(&client, server_url, evidence_id).await
+        } else if generate_court_report {
+            generate_court_report_request(&client, server_url, evidence_id).await
+        } else {
+            verify_evidence(&client, server_url, evidence_id).await
+        }
+    };
+    let report = match matches.get_one::<u64>("timeout") {
+        Some(&secs) => tokio::time::timeout(Duration::from_secs(secs), request)
+            .await
+            .unwrap_or_else(|_| Err(format!("Timed out after {}s", secs).into())),
+        None => request.await,
+    };
+    let mut report = report.unwrap_or_else(|e| Report::failed(evidence_id, e.to_string()));
+    if matches.get_flag("fail-on-warning")
+        && report.status == Outcome::Valid
+        && !report.warnings.is_empty()
+    {
+        report.status = Outcome::Invalid;
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
     } else {
-        verify_evidence(&client, server_url, evidence_id).await?;
+        print_text(&report)?;
+    }
+    std::process::exit(report.status.exit_code());
+}
+
+// Token, client certificate and trusted CA apply to every request
+fn build_client(matches: &clap::ArgMatches) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut builder = Client::builder().use_rustls_tls();
+
+    if let Some(token) = matches.get_one::<String>("token") {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))?;
+        value.set_sensitive(true);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+        builder = builder.default_headers(headers);
+    }
+
+    if let (Some(cert), Some(key)) = (
+        matches.get_one::<String>("client-cert"),
+        matches.get_one::<String>("client-key"),
+    ) {
+        // rustls wants the key and certificate chain in one PEM buffer
+        let mut pem = std::fs::read(key)?;
+        pem.push(b'\n');
+        pem.extend(std::fs::read(cert)?);
+        builder = builder.identity(reqwest::Identity::from_pem(&pem)?);
     }
 
+    if let Some(ca) = matches.get_one::<String>("ca-cert") {
+        builder =
+            builder.add_root_certificate(reqwest::Certificate::from_pem(&std::fs::read(ca)?)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+fn print_text(report: &Report) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(result) = &report.result {
+        println!("Verification Result:");
+        println!("{}", serde_json::to_string_pretty(result)?);
+    }
+    for warning in &report.warnings {
+        println!("Warning: {}", warning);
+    }
+    match (report.status, &report.error) {
+        (Outcome::Valid, _) => info!("✓ Evidence verification successful"),
+        (Outcome::Invalid, _) => warn!("✗ Evidence verification failed"),
+        (Outcome::Error, error) => {
+            error!("Verification could not be completed");
+            println!("Error: {}", error.as_deref().unwrap_or("unknown"));
+        }
+    }
     Ok(())
 }
 
+// Conditions that don't make evidence invalid but that strict pipelines may
+// refuse: nothing anchored yet, or anchors still awaiting confirmation
+fn warnings(result: &Value) -> Vec<String> {
+    let mut warnings = Vec::new();
+    // `/verify` results and finished verification jobs name the counts differently
+    let frames = result
+        .get("frame_count")
+        .or_else(|| result.get("frames_total"));
+    if frames.and_then(Value::as_u64) == Some(0) {
+        warnings.push("Evidence contains no frames".to_string());
+    }
+    if result.get("anchors_confirmed").and_then(Value::as_u64) == Some(0) {
+        warnings.push("Evidence has no confirmed blockchain anchors".to_string());
+    }
+    if let Some(confirmations) = result
+        .get("blockchain_confirmations")
+        .and_then(Value::as_object)
+    {
+        if confirmations.is_empty() {
+            warnings.push("Evidence has no blockchain anchors".to_string());
+        }
+        for (chain, count) in confirmations {
+            if count.as_u64() == Some(0) {
+                warnings.push(format!("Anchor on {} is unconfirmed", chain));
+            }
+        }
+    }
+    warnings
+}
+
+async fn error_report(
+    evidence_id: &str,
+    response: reqwest::Response,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    let status = response.status();
+    let body = response.text().await?;
+    Ok(Report::failed(
+        evidence_id,
+        format!("Server returned {}: {}", status, body),
+    ))
+}
+
 async fn verify_evidence(
     client: &Client,
     server_url: &str,
     evidence_id: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<Report, Box<dyn std::error::Error>> {
     info!("Verifying evidence: {}", evidence_id);
 
     let url = format!("{}/verify/{}", server_url, evidence_id);
 
     let response = client.get(&url).send().await?;
-
-    if response.status().is_success() {
-        let result: Value = response.json().await?;
-        println!("Verification Result:");
-        println!("{}", serde_json::to_string_pretty(&result)?);
-
-        if let Some(is_valid) = result.get("is_valid") {
-            if is_valid.as_bool().unwrap_or(false) {
-                info!("✓ Evidence verification successful");
-            } else {
-                warn!("✗ Evidence verification failed");
-            }
-        }
-    } else {
-        error!("Verification request failed: {}", response.status());
-        let error_text = response.text().await?;
-        println!("Error: {}", error_text);
+    if !response.status().is_success() {
+        return error_report(evidence_id, response).await;
     }
 
-    Ok(())
+    let result: Value = response.json().await?;
+    let status = match result.get("is_valid").and_then(Value::as_bool) {
+        Some(true) => Outcome::Valid,
+        Some(false) => Outcome::Invalid,
+        None => Outcome::Error,
+    };
+    let mut report = Report::new(evidence_id, status, result);
+    if status == Outcome::Error {
+        report.error = Some("Response has no is_valid field".to_string());
+    }
+    Ok(report)
 }
 
 async fn generate_court_report_request(
     client: &Client,
     server_url: &str,
     evidence_id: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<Report, Box<dyn std::error::Error>> {
     info!("Generating court report for evidence: {}", evidence_id);
 
     let url = format!("{}/court-report/{}", server_url, evidence_id);
 
     let response = client.get(&url).send().await?;
-
-    if response.status().is_success() {
-        let result: Value = response.json().await?;
-        println!("Court Report:");
-        println!("{}", serde_json::to_string_pretty(&result)?);
-        info!("✓ Court report generated successfully");
-    } else {
-        error!("Court report request failed: {}", response.status());
-        let error_text = response.text().await?;
-        println!("Error: {}", error_text);
+    if !response.status().is_success() {
+        return error_report(evidence_id, response).await;
     }
 
-    Ok(())
+    let result: Value = response.json().await?;
+    info!("✓ Court report generated successfully");
+    Ok(Report::new(evidence_id, Outcome::Valid, result))
 }
 
+// Starts a verification job and follows its server-sent events until the job
+// finishes. A dropped stream is reopened with backoff; on reconnect the server
+// replays the latest progress, so nothing is missed.
 async fn watch_verification(
     client: &Client,
     server_url: &str,
     evidence_id: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Watching verification status for evidence: {}", evidence_id);
+) -> Result<Report, Box<dyn std::error::Error>> {
+    info!("Starting verification job for evidence: {}", evidence_id);
+
+    let response = client
+        .post(format!("{}/verifications", server_url))
+        .json(&serde_json::json!({ "evidence_id": evidence_id }))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return error_report(evidence_id, response).await;
+    }
+    let job: Value = response.json().await?;
+    let job_id = job
+        .get("job_id")
+        .and_then(Value::as_str)
+        .ok_or("Response has no job_id")?;
+    let url = format!("{}/verifications/{}/events", server_url, job_id);
 
+    let mut backoff = WATCH_BACKOFF_INITIAL;
     loop {
-        let url = format!("{}/verify/{}", server_url, evidence_id);
-
-        match client.get(&url).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    let result: Value = response.json().await?;
-                    println!("Status Update:");
-                    println!("{}", serde_json::to_string_pretty(&result)?);
-
-                    if let Some(is_valid) = result.get("is_valid") {
-                        if is_valid.as_bool().unwrap_or(false) {
-                            info!("✓ Verification completed successfully");
-                            break;
-                        }
-                    }
-                } else {
-                    println!("Status: Verification in progress...");
-                }
-            }
+        if let Some(progress) = follow_events(client, &url, &mut backoff).await? {
+            return Ok(finished_report(evidence_id, progress));
+        }
+
+        warn!("Progress stream closed; reconnecting in {:?}", backoff);
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(WATCH_BACKOFF_MAX);
+    }
+}
+
+// Reads one connection's events. Ok(Some) carries the finished job, Ok(None)
+// means the connection dropped and is worth retrying.
+async fn follow_events(
+    client: &Client,
+    url: &str,
+    backoff: &mut Duration,
+) -> Result<Option<Value>, Box<dyn std::error::Error>> {
+    let request = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "text/event-stream");
+    let mut response = match request.send().await {
+        Ok(response) if response.status().is_server_error() => {
+            warn!("Server returned {}", response.status());
+            return Ok(None);
+        }
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Failed to open progress stream: {}", e);
+            return Ok(None);
+        }
+    };
+    if !response.status().is_success() {
+        let status = response.status();
+        return Err(format!("Server returned {}: {}", status, response.text().await?).into());
+    }
+
+    let mut events = EventParser::default();
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => return Ok(None),
             Err(e) => {
-                error!("Failed to check verification status: {}", e);
+                warn!("Progress stream failed: {}", e);
+                return Ok(None);
+            }
+        };
+
+        for (event, data) in events.push(&chunk) {
+            *backoff = WATCH_BACKOFF_INITIAL;
+            match event.as_str() {
+                "progress" => {
+                    let progress: Value = serde_json::from_str(&data)?;
+                    info!(
+                        "Checked {} of {} frames, {} anomalies",
+                        progress["frames_checked"],
+                        progress["frames_total"],
+                        progress["anomaly_count"]
+                    );
+                }
+                "finished" => return Ok(Some(serde_json::from_str(&data)?)),
+                "error" => return Err(format!("Server reported: {}", data).into()),
+                _ => {}
             }
         }
+    }
+}
 
-        sleep(Duration::from_secs(5)).await;
+fn finished_report(evidence_id: &str, progress: Value) -> Report {
+    let status = match progress.get("is_valid").and_then(Value::as_bool) {
+        Some(true) => Outcome::Valid,
+        Some(false) => Outcome::Invalid,
+        None => Outcome::Error,
+    };
+    let error = progress
+        .get("error")
+        .and_then(Value::as_str)
+        .unwrap_or("Verification job ended without a result")
+        .to_string();
+
+    let mut report = Report::new(evidence_id, status, progress);
+    if status == Outcome::Error {
+        report.error = Some(error);
     }
+    report
+}
 
-    Ok(())
+// Splits a text/event-stream body into (event, data) pairs. Chunks can end
+// mid-event, so incomplete input is held until its blank line arrives.
+#[derive(Default)]
+struct EventParser {
+    buffer: Vec<u8>,
+}
+
+impl EventParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<(String, String)> {
+        self.buffer.extend(chunk.iter().filter(|&&b| b != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let (mut event, mut data) = ("message".to_string(), Vec::new());
+            for line in String::from_utf8_lossy(&block).lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    event = value.trim_start().to_string();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+                }
+                // Anything else, such as `:` keep-alive comments, is ignored
+            }
+            if !data.is_empty() {
+                events.push((event, data.join("\n")));
+            }
+        }
+        events
+    }
 }
@@ -1,508 +0,0 @@
-// Native Cryptography Implementation
-// Replaces: ring, blake3, sha2, hmac, pqcrypto
-// Adds: AES-256, ChaCha20, post-quantum cryptography
-
-use crate::sha2 as Sha256;
-use crate::crypto;
-use crate::digest::Digest;
-use std::collections::HashMap;
-
-// === NATIVE HASH FUNCTIONS ===
-
-pub fn hash_sha256(data: &[u8]) -> [u8; 32] {
-    use sha2::Sha256;
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    hasher.finalize().into_bytes()
-}
-
-pub fn hash_blake3(data: &[u8]) -> [u8; 32] {
-    use blake3;
-    let mut hasher = Blake3::new();
-    hasher.update(data);
-    hasher.finalize().into_bytes()
-}
-
-// === NATIVE HMAC FUNCTIONS ===
-
-pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
-    use hmac::Hmac;
-    use sha2::Sha256;
-    let key = hmac::Key::new(key);
-    let mut hmac = Hmac::new(key, Sha256::new());
-    hmac.update(data);
-    hmac.finalize().into_bytes()
-}
-
-// === NATIVE AES IMPLEMENTATION ===
-
-use aes_gcm::aead::Aes256Gcm;
-use aes_gcm::aead::{AeadConfig, NewAead};
-use aead::generic_array::GenericArray;
-use aead::KeyInit;
-use rand::Rng;
-
-pub struct Aes256GcmCipher {
-    cipher: Aes256Gcm<aead::Aes256Gcm<aead::generic_array::GenericArray<aead::generic_array::OsRng>>,
-}
-
-impl AesGcmCipher {
-    pub fn new(key: &[u8], nonce: &[u8]) -> Self {
-        let key = aes_gcm::KeyInit::from_bytes(key);
-        
-        Self {
-            cipher: AeadConfig::builder()
-                .key(&key)
-                .nonce_length(12)
-                .build(),
-        }
-    }
-    
-    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        use rand::RngCore;
-        use rand::Rng;
-        
-        let mut rng = rand::rngs::OsRng;
-        let nonce: [u8; 12];
-        rng.fill_bytes(&mut nonce);
-        
-        let cipher = AeadGcm<aead::Aes256Gcm<aead::generic_array::OsRng>>::new(&self.cipher);
-        
-        let mut buffer = Vec::new();
-        buffer.extend_from_slice(plaintext);
-        
-        // Encrypt in place
-        let ciphertext = cipher.encrypt_in_place_detached(&nonce, &[], &buffer, &[])?;
-        
-        Ok(ciphertext)
-    }
-    
-    pub fn decrypt(&self, ciphertext: &[u8], nonce: &[u8], tag: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let cipher = AeadGcm<aead::Aes256Gcm<aead::generic_array::OsRng>>::new(&self.cipher);
-        
-        let mut plaintext = Vec::new();
-        plaintext.resize(ciphertext.len() - 16); // Tag + ciphertext
-        cipher.decrypt_in_place_detached(&tag, nonce, &mut plaintext)?;
-        
-        Ok(plaintext)
-    }
-}
-
-// === POST-QUANTUM IMPLEMENTATION ===
-
-use pqcrypto::traits::KeyExchange;
-use pqcrypto::kyber512;
-use pqcrypto::traits::{Ciphertext, KemPlaintext};
-use pqcrypto::traits::{KeyPairGenerator, Kyber512};
-
-pub struct Kyber512KeyPair {
-    public_key: kyber512::PublicKey,
-    private_key: kyber512::SecretKey,
-}
-
-pub struct Kyber512EncryptedData {
-    ciphertext: Vec<u8>,
-    shared_secret: Vec<u8>,
-}
-
-pub struct Kyber512Plaintext {
-    keypair: Kyber512KeyPair,
-}
-
-impl KeyPairGenerator for Kyber512KeyPair {
-    type Output = KeyPair<kyber512::PublicKey, kyber512::SecretKey>;
-    
-    fn generate_keypair(&self) -> Self::Output {
-        let mut rng = rand::thread_rng();
-        rng.generate_keypair()
-    }
-}
-
-impl Encrypt for Kyber512EncryptedData {
-    fn encrypt(&self, data: &KemPlaintext) -> Kyber512EncryptedData {
-        let mut rng = rand::thread_rng();
-        
-        let (public_key, shared_secret) = data.keypair.generate();
-        
-        let ciphertext = public_key.encrypt(&rng, data, &shared_secret)?;
-        
-        Kyber512EncryptedData {
-            ciphertext,
-            shared_secret,
-        }
-    }
-}
-
-impl Decrypt for Kyber512EncryptedData {
-    type Output = Result<Vec<u8>, Box<dyn std::error::Error>>;
-    
-    fn decrypt(&self, data: &Kyber512EncryptedData) -> Self::Output {
-        let (public_key, shared_secret) = data.keypair;
-        
-        if let Some(ciphertext) = data.ciphertext {
-            if let Some(shared_secret) = data.shared_secret {
-                let mut plaintext = Vec::new();
-                plaintext.resize(ciphertext.len() - 64); // Encapsulated + shared secret
-                
-                if public_key.decrypt(&shared_secret, &mut plaintext, &ciphertext)? {
-                    Ok(plaintext)
-                } else {
-                    Err("Decryption failed".to_string())
-                }
-            } else {
-                Err("No shared secret found".to_string())
-                }
-            } else {
-                Err("No ciphertext found".to_string())
-                }
-            }
-        } else {
-            Err("No encrypted data found".to_string())
-                }
-        }
-    }
-}
-
-// === MAIN SERVER ===
-
-pub struct EncryptionServer {
-    port: u16,
-    aes_cipher: Aes256GcmCipher,
-}
-
-impl EncryptionServer {
-    pub fn new(port: u16) -> Self {
-        Self {
-            port,
-            aes_cipher: AesGcmCipher::new(&[0u8; 32]),
-        }
-    }
-    
-    pub fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🔐 Starting Native Encryption Server");
-        println!("📦 Zero Dependencies: Complete Rust replacement");
-        println!("⚡ Performance: Optimized cryptographic operations");
-        println!("🛡️ Security: Native post-quantum cryptography ready");
-        
-        let listener = std::net::TcpListener::bind(("127.0.0.1", self.port))?;
-        
-        match listener {
-            Ok(listener) => {
-                println!("✅ Server listening on port {}", self.port);
-                
-                for stream in listener.incoming() {
-                    match stream {
-                        Ok(s) => {
-                            let addr = s.peer_addr().to_string();
-                            println!("Connection from {}", addr);
-                            
-                            let mut buffer = [0u8; 4096];
-                            
-                            loop {
-                                match s.read(&mut buffer) {
-                                    Ok(n) => {
-                                        let request = String::from_utf8(&buffer[..n]);
-                                        println!("Request: {}", request);
-                                        
-                                        match self.handle_request(&request) {
-                                            Ok(response) => {
-                                                s.write_all(response.as_bytes())?;
-                                                println!("✓ Response sent");
-                                            }
-                                            Err(e) => {
-                                                eprintln!("Error writing response: {}", e);
-                                            }
-                                        }
-                                    }
-                                    Err(_) => {
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                        
-                        Err(e) => {
-                            eprintln!("Connection error: {}", e);
-                            break;
-                        }
-                    }
-                }
-                
-                std::io::Result::Ok(())
-            }
-            Err(e) => {
-                eprintln!("Failed to bind: {}", e);
-                return Err(e);
-            }
-        }
-    }
-    
-    fn handle_request(&self, request: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let parts: Vec<&str> = request.split_whitespace().collect();
-        
-        match parts.get(0) {
-            "GET" => self.handle_get(&parts[1..]),
-            "POST" => self.handle_post(&parts[1..]),
-            _ => {
-                Err(format!("Unsupported method: {}", parts.get(0).unwrap_or(""))
-            }
-        }
-    }
-    
-    fn handle_get(&self, parts: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
-        match parts.get(0) {
-            "health" => Ok(json!({
-                "status": "healthy",
-                "dependencies": 0,
-                "algorithms": ["SHA-256", "BLAKE3", "HMAC-SHA256", "AES-256-GCM", "Kyber512"],
-                "post_quantum": true
-            })),
-            "encrypt" => {
-                if parts.len() < 3 {
-                    return Err("Usage: GET /encrypt <algorithm> <data>".to_string());
-                }
-                
-                let algorithm = parts.get(1).unwrap_or("");
-                let data = parts.get(2).unwrap_or("");
-                
-                match algorithm.as_str() {
-                    "aes-256-gcm" => {
-                        let key = self.generate_key();
-                        if let Ok(ciphertext) = self.aes_encrypt(&data, &key) {
-                            Ok(json!({
-                                "success": true,
-                                "algorithm": algorithm,
-                                "ciphertext": hex::encode(ciphertext),
-                                "implementation": "native"
-                            }))
-                        } else {
-                            Err("Encryption failed".to_string())
-                        }
-                    }
-                    "sha256" => {
-                        let hash = hash_sha256(data.as_bytes());
-                        Ok(json!({
-                            "success": true,
-                            "algorithm": algorithm,
-                            "hash": hex::encode(hash),
-                            "implementation": "native"
-                        })
-                    }
-                    "blake3" => {
-                        let hash = hash_blake3(data.as_bytes());
-                        Ok(json!({
-                            "success": true,
-                            "algorithm": algorithm,
-                            "hash": hex::encode(hash),
-                            "implementation": "native"
-                        }))
-                    }
-                    "hmac-sha256" => {
-                        let key = [0u8; 32]; // Default test key
-                        if let Ok(hmac) = hmac_sha256(&key, data) {
-                            Ok(json!({
-                                "success": true,
-                                "algorithm": algorithm,
-                                "hash": hex::encode(hmac),
-                                "implementation": "native"
-                            }))
-                        } else {
-                            Err("HMAC generation failed".to_string())
-                        }
-                    }
-                    "post-quantum" => {
-                        if parts.len() < 4 {
-                            return Err("Usage: GET /post-quantum <public_key>".to_string());
-                        }
-                        
-                        let public_key_hex = parts.get(2).unwrap_or("");
-                        if public_key_hex.is_empty() {
-                            return Err("Public key required for post-quantum".to_string());
-                        }
-                        
-                        let public_key = hex::decode(public_key_hex).map_err(|e| e.to_string())?;
-                        
-                        if let Ok(keypair) = generate_kyber512_keypair() {
-                            let plaintext = parts.get(3).unwrap_or("").as_bytes();
-                            
-                            if let Ok(ciphertext) = post_quantum_encrypt(&keypair, &plaintext) {
-                                Ok(json!({
-                                    "success": true,
-                                    "algorithm": "kyber512",
-                                    "ciphertext": hex::encode(ciphertext),
-                                    "public_key": hex::encode(keypair.public_key),
-                                    "implementation": "native-post-quantum"
-                                }))
-                            } else {
-                                Err("Encryption failed".to_string())
-                            }
-                        } else {
-                            Err("Key generation failed".to_string())
-                        }
-                    }
-                    _ => {
-                        return Err("Unsupported algorithm".to_string());
-                    }
-                }
-            },
-            _ => {
-                Err("Invalid request".to_string())
-            }
-        }
-    }
-    
-    fn handle_post(&self, parts: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
-        match parts.get(0) {
-            "encrypt" => {
-                if parts.len() < 4 {
-                    return Err("Usage: POST /encrypt <algorithm> <data>".to_string());
-                }
-                
-                let algorithm = parts.get(1).unwrap_or("");
-                let data = parts.get(2).unwrap_or("");
-                let key = parts.get(3).unwrap_or("");
-                
-                match algorithm.as_str() {
-                    "aes-256-gcm" => {
-                        let key = self.generate_key();
-                        if let Ok(ciphertext) = self.aes_encrypt(&data, &key) {
-                            Ok(json!({
-                                "success": true,
-                                "algorithm": algorithm,
-                                "ciphertext": hex::encode(ciphertext),
-                                "implementation": "native-aes-gcm"
-                            }))
-                        } else {
-                            Err("AES encryption failed".to_string())
-                        }
-                    }
-                    "post-quantum" => {
-                        if parts.len() < 4 {
-                            return Err("Usage: POST /post-quantum <public_key>".to_string());
-                        }
-                        
-                        let public_key_hex = parts.get(2).unwrap_or("");
-                        if public_key_hex.is_empty() {
-                            return Err("Public key required for post-quantum".to_string());
-                        }
-                        
-                        let public_key = hex::decode(public_key_hex).map_err(|e| e.to_string())?;
-                        
-                        let plaintext = parts.get(3).unwrap_or("").as_bytes();
-                        
-                        if let Ok(keypair) = generate_kyber512_keypair() {
-                            if let Ok(ciphertext) = post_quantum_encrypt(&keypair, &plaintext) {
-                                Ok(json!({
-                                    "success": true,
-                                    "algorithm": "kyber512",
-                                    "ciphertext": hex::encode(ciphertext),
-                                    "public_key": hex::encode(keypair.public_key),
-                                    "implementation": "native-post-quantum"
-                                }))
-                            } else {
-                                Err("Kyber512 encryption failed".to_string())
-                            }
-                        } else {
-                            Err("Key generation failed".to_string())
-                        }
-                    }
-                    _ => {
-                        return Err("Unsupported algorithm".to_string())
-                    }
-                }
-            },
-            _ => {
-                return Err("Invalid request".to_string())
-            }
-        }
-    }
-}
-
-// === NATIVE KEY GENERATION ===
-
-pub fn generate_key() -> [u8; 32] {
-    use rand::thread_rng;
-    
-    let mut rng = rand::thread_rng();
-    rng.fill_bytes(&mut [0u8; 32])
-}
-
-// === POST-QUANTUM KEY PAIR GENERATION ===
-
-fn generate_kyber512_keypair() -> Result<Kyber512KeyPair, kyber512::DecryptionError> {
-    let mut rng = rand::thread_rng();
-    let keypair = rng.generate_keypair();
-    Ok(keypair)
-}
-
-// === AES-256-GCM KEY ENCRYPTION ===
-
-pub fn aes_gcm_encrypt(key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let key = aes_gcm::KeyInit::from_bytes(key);
-    
-    let cipher = AeadConfig::builder()
-        .key(&key)
-        .nonce_length(12)
-        .build();
-    
-    let nonce = [0u8; 12];
-    
-    // Encrypt in place
-    let mut buffer = Vec::new();
-    buffer.extend_from_slice(plaintext);
-    
-    let ciphertext = cipher.encrypt_in_place_detached(&nonce, &[], &buffer, &[])?;
-    
-    Ok(ciphertext)
-}
-
-// === POST-QUANTUM ENCRYPTION ===
-
-fn post_quantum_encrypt(keypair: &Kyber512KeyPair, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    use pqcrypto::traits::Ciphertext;
-    use pqcrypto::traits::KemPlaintext;
-    
-    // Create Kyber512 plaintext and encapsulated data
-    let mut message = plaintext.to_vec();
-    
-    // Add metadata
-    let mut encapsulated_data = Vec::new();
-    let mut rng = rand::thread_rng();
-    rng.fill_bytes(&mut encapsulated_data);
-    
-    // Create post-quantum ciphertext
-    let ciphertext = keypair.public_key.encrypt(&rng, &message, &encapsulated_data)?;
-    
-    Ok(ciphertext)
-}
-
-// === POST-QUANTUM DECRYPTION ===
-
-fn post_quantum_decrypt(keypair: &KyberKeyPair, ciphertext: &Kyber512EncryptedData) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    // Extract shared secret and encapsulated data
-    let shared_secret = ciphertext.shared_secret.as_ref();
-    let encapsulated_data = ciphertext.encapsulated_data.as_ref();
-    
-    let mut plaintext = Vec::new();
-    plaintext.resize(ciphertext.len() - 64); // Ciphertext length - tag length
-    
-    if keypair.decrypt(&shared_secret, &mut plaintext, &ciphertext)? {
-        Ok(plaintext)
-    } else {
-        Err("Decryption failed".to_string())
-        }
-    }
-}
-
-// === HELPER FUNCTIONS ===
-
-fn generate_kyber512_keypair() -> Result<Kyber512KeyPair, kyber512::DecryptionError> {
-    pqcrypto::traits::KeyExchange::generate_keypair()
-}
-
-fn generate_aes_key() -> [u8; 32] {
-    use rand::thread_rng;
-    
-    let mut rng = rand::thread_rng();
-    rng.fill_bytes(&mut [0u8; 32])
-}
\ No newline at end of file
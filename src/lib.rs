@@ -1,91 +1,120 @@
+pub mod admin;
+pub mod aff4;
+pub mod alerts;
+pub mod anchor_history;
+pub mod api_keys;
+pub mod approval;
+pub mod audit;
+pub mod auth;
 pub mod blockchain;
+pub mod bundle;
+pub mod case;
+pub mod clip;
+pub mod cluster;
+pub mod completions;
 pub mod config;
+pub mod content_credentials;
+pub mod cose;
 pub mod crypto;
+pub mod device_auth;
+pub mod devices;
+pub mod edge;
+pub mod enrollment;
 pub mod error;
+pub mod evidence;
+pub mod export;
+pub mod ffi;
+pub mod geofence;
+#[cfg(feature = "video")]
+pub mod grpc;
+pub mod health;
+pub mod hsm;
+pub mod ingest;
+pub mod keystore;
+pub mod kms;
+pub mod metrics;
+pub mod migration;
+#[cfg(feature = "mobile")]
+pub mod mobile;
+pub mod mp4;
+pub mod notifications;
+pub mod playback;
+pub mod quantum;
+pub mod rate_limit;
+pub mod recovery;
+pub mod redaction;
+pub mod rendition;
+pub mod report;
+pub mod retry;
+pub mod secrets;
+pub mod sensors;
+pub mod session;
+pub mod stats;
 pub mod storage;
+pub mod tenant;
+pub mod timesync;
+pub mod tpm;
+pub mod trace;
+pub mod uring;
 pub mod verification;
+pub mod verify_jobs;
 #[cfg(feature = "video")]
 pub mod video;
+pub mod watermark;
+pub mod wire;
+
+#[cfg(feature = "mobile")]
+uniffi::setup_scaffolding!();
 
-use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
-#[derive(Debug, Clone)]
-pub struct VideoFrame {
-    pub timestamp: u64,
-    pub sequence: u64,
-    pub data: Vec<u8>,
-    pub metadata: FrameMetadata,
-}
-
-#[derive(Debug, Clone)]
-pub struct FrameMetadata {
-    pub device_id: String,
-    pub location: Option<(f64, f64)>,
-    pub resolution: (u32, u32),
-    pub fps: u32,
-    pub codec: String,
-}
-
-#[derive(Debug, Clone)]
-pub struct EncryptedFrame {
-    pub sequence: u64,
-    pub ciphertext: Vec<u8>,
-    pub hash: String,
-    pub previous_hash: String,
-    pub nonce: Vec<u8>,
-    pub timestamp: u64,
-    pub blockchain_anchors: Vec<BlockchainAnchor>,
-}
+use crate::error::Result;
 
-#[derive(Debug, Clone)]
-pub struct BlockchainAnchor {
-    pub chain: String,
-    pub transaction_hash: String,
-    pub block_number: u64,
-    pub timestamp: u64,
-    pub proof: String,
-}
+// Evidence types and the Merkle tree live in the dependency-light core crate
+// so verifiers can use them without the node
+pub use immutable_encryption_core::{
+    merkle, BlockchainAnchor, CustodyEntry, DeviceSignature, EncryptedFrame, FrameMetadata,
+    HardwareAttestation, LegalCompliance, Telemetry, VideoFrame,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationResult {
     pub is_valid: bool,
     pub frame_count: u64,
     pub blockchain_confirmations: HashMap<String, u64>,
     pub tamper_evidence: Option<String>,
     pub court_report: CourtReport,
+    // Implausible GPS metadata; flags for review that don't invalidate the chain
+    #[serde(default)]
+    pub location_findings: Vec<geofence::LocationFinding>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CourtReport {
     pub evidence_id: String,
     pub chain_of_custody: Vec<CustodyEntry>,
     pub cryptographic_proofs: Vec<String>,
     pub legal_compliance: LegalCompliance,
     pub generated_at: u64,
-}
-
-#[derive(Debug)]
-pub struct CustodyEntry {
-    pub timestamp: u64,
-    pub actor: String,
-    pub action: String,
-    pub signature: String,
-    pub blockchain_reference: String,
-}
-
-#[derive(Debug)]
-pub struct LegalCompliance {
-    pub standards_met: Vec<String>,
-    pub certifications: Vec<String>,
-    pub jurisdiction_compliance: Vec<String>,
+    #[serde(default)]
+    pub derived_renditions: Vec<rendition::RenditionRecord>,
+    // Clock checks made while a session recorded; absent when none were kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_sync: Option<timesync::SessionTimeSync>,
+    // Approval requests for sensitive actions on the evidence, with their
+    // signed approvals
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approvals: Vec<approval::ApprovalRequest>,
 }
 
 pub type FrameSender = mpsc::UnboundedSender<VideoFrame>;
 pub type FrameReceiver = mpsc::UnboundedReceiver<VideoFrame>;
-pub type EncryptedFrameSender = mpsc::UnboundedSender<EncryptedFrame>;
-pub type EncryptedFrameReceiver = mpsc::UnboundedReceiver<EncryptedFrame>;
+// Sealed frames are shared, not copied, between pipeline stages
+pub type EncryptedFrameSender = mpsc::UnboundedSender<Arc<EncryptedFrame>>;
+pub type EncryptedFrameReceiver = mpsc::UnboundedReceiver<Arc<EncryptedFrame>>;
 
 #[async_trait::async_trait]
 pub trait EncryptionEngine {
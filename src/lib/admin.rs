@@ -0,0 +1,378 @@
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::crypto::EncryptionEngine;
+use crate::error::Result;
+use crate::storage::{self, DistributedStorage};
+use crate::{wire, CustodyEntry, EncryptedFrame};
+
+const SCRUB_BATCH: usize = 512;
+
+// Evidence under a legal hold must be preserved until the hold is released.
+// Every change is signed with the node's envelope key and appended to the
+// `legal_hold:{evidence}` custody scope.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegalHold {
+    pub evidence_id: String,
+    pub case_id: Option<String>,
+    pub reason: String,
+    pub placed_by: String,
+    pub placed_at: u64,
+    pub released_by: Option<String>,
+    pub released_at: Option<u64>,
+    #[serde(default)]
+    pub release_reason: Option<String>,
+    #[serde(default)]
+    pub release_authorization: Option<String>, // order or approval lifting the hold
+    #[serde(default)]
+    pub signature: String, // hex Ed25519 over the fields above; empty on old holds
+}
+
+impl LegalHold {
+    pub fn is_active(&self) -> bool {
+        self.released_at.is_none()
+    }
+
+    pub fn sign(mut self, engine: &EncryptionEngine) -> Result<Self> {
+        self.signature = hex::encode(engine.sign_envelope(&self.signed_content()?)?);
+        Ok(self)
+    }
+
+    pub fn verify(&self, public_key: &[u8]) -> Result<bool> {
+        let signature = hex::decode(&self.signature)?;
+        Ok(UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&self.signed_content()?, &signature)
+            .is_ok())
+    }
+
+    pub fn custody_scope(evidence_id: &str) -> String {
+        format!("legal_hold:{}", evidence_id)
+    }
+
+    // The custody record of this version of the hold, carrying its signature
+    pub fn custody_entry(&self, timestamp: u64, engine: &EncryptionEngine) -> Result<CustodyEntry> {
+        let (actor, action) = match &self.released_by {
+            Some(released_by) => (
+                released_by.clone(),
+                format!(
+                    "legal_hold_released:{}:authorization={}:hold_signature={}",
+                    self.evidence_id,
+                    self.release_authorization.as_deref().unwrap_or("-"),
+                    self.signature
+                ),
+            ),
+            None => (
+                self.placed_by.clone(),
+                format!(
+                    "legal_hold_placed:{}:case={}:hold_signature={}",
+                    self.evidence_id,
+                    self.case_id.as_deref().unwrap_or("-"),
+                    self.signature
+                ),
+            ),
+        };
+        let signed = format!("{}|{}|{}", timestamp, actor, action);
+        Ok(CustodyEntry {
+            timestamp,
+            signature: hex::encode(engine.sign_envelope(signed.as_bytes())?),
+            actor,
+            action,
+            blockchain_reference: String::new(),
+        })
+    }
+
+    fn signed_content(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(
+            &self.evidence_id,
+            &self.case_id,
+            &self.reason,
+            &self.placed_by,
+            self.placed_at,
+            &self.released_by,
+            self.released_at,
+            &self.release_reason,
+            &self.release_authorization,
+        ))?)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LegalHoldRequest {
+    pub case_id: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LegalHoldRelease {
+    pub reason: String,
+    pub authorization: String, // e.g. the court order number
+}
+
+// A hold as stored, whether its signature still checks out, and every signed
+// change made to it
+#[derive(Debug, Clone, Serialize)]
+pub struct LegalHoldStatus {
+    pub hold: LegalHold,
+    pub signature_valid: bool,
+    pub history: Vec<CustodyEntry>,
+}
+
+// Frames deleted from a sealed session, persisted under
+// `retention_purge:{evidence}`; the manifest and custody trail stay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPurge {
+    pub evidence_id: String,
+    pub device_id: String,
+    pub frames_deleted: usize,
+    pub reason: String,
+    pub purged_by: String,
+    pub purged_at: u64,
+}
+
+impl RetentionPurge {
+    pub fn key(evidence_id: &str) -> String {
+        format!("retention_purge:{}", evidence_id)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionReport {
+    pub purged: Vec<RetentionPurge>,
+    pub held: Vec<String>, // expired, but under an active legal hold
+    pub awaiting_approval: Vec<String>, // expired; purge each with an approved request
+}
+
+pub fn legal_hold_key(evidence_id: &str) -> String {
+    format!("legal_hold:{}", evidence_id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrubState {
+    Running,
+    Completed,
+    Failed,
+}
+
+// Progress and findings of a storage scrub, persisted under `scrub:{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrubReport {
+    pub scrub_id: String,
+    pub state: ScrubState,
+    pub started_by: String,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub frames_scanned: u64,
+    pub corrupt_frames: Vec<String>, // records that no longer deserialize or match their key
+    pub dangling_index_entries: Vec<String>, // device index entries without a frame
+    pub unlinked_frames: Vec<String>, // previous_hash not found among stored frames
+    pub error: Option<String>,
+}
+
+impl ScrubReport {
+    pub fn new(scrub_id: String, started_by: &str) -> Result<Self> {
+        Ok(Self {
+            scrub_id,
+            state: ScrubState::Running,
+            started_by: started_by.to_string(),
+            started_at: now()?,
+            finished_at: None,
+            frames_scanned: 0,
+            corrupt_frames: Vec::new(),
+            dangling_index_entries: Vec::new(),
+            unlinked_frames: Vec::new(),
+            error: None,
+        })
+    }
+
+    pub fn key(scrub_id: &str) -> String {
+        format!("scrub:{}", scrub_id)
+    }
+}
+
+// Re-reads every stored frame and index entry, checking each record decodes,
+// matches its key, and links to a stored predecessor.
+pub async fn scrub_storage(storage: &DistributedStorage, report: &mut ScrubReport) -> Result<()> {
+    let mut frame_keys = HashSet::new();
+    let mut hashes = HashSet::new();
+    let mut links = Vec::new();
+
+    let mut start = "frame:".to_string();
+    loop {
+        let batch = storage.scan_page_raw("frame:", &start, SCRUB_BATCH).await?;
+        let last = match batch.last() {
+            Some((key, _)) => key.clone(),
+            None => break,
+        };
+
+        for (key, value) in batch {
+            report.frames_scanned += 1;
+            match wire::decode_frame(&value) {
+                Ok(frame) if key_matches(&key, &frame) => {
+                    hashes.insert(frame.hash.clone());
+                    links.push((key.clone(), frame.previous_hash));
+                }
+                _ => report.corrupt_frames.push(key.clone()),
+            }
+            frame_keys.insert(key);
+        }
+        start = format!("{}\0", last);
+    }
+
+    let genesis = "0".repeat(64);
+    report.unlinked_frames = links
+        .into_iter()
+        .filter(|(_, previous)| *previous != genesis && !hashes.contains(previous))
+        .map(|(key, _)| key)
+        .collect();
+
+    let mut start = "device:".to_string();
+    loop {
+        let batch = storage
+            .scan_page_raw("device:", &start, SCRUB_BATCH)
+            .await?;
+        let last = match batch.last() {
+            Some((key, _)) => key.clone(),
+            None => break,
+        };
+
+        for (key, value) in batch {
+            let target = String::from_utf8(value).unwrap_or_default();
+            if !frame_keys.contains(&target) {
+                report.dangling_index_entries.push(key);
+            }
+        }
+        start = format!("{}\0", last);
+    }
+
+    Ok(())
+}
+
+fn key_matches(key: &str, frame: &EncryptedFrame) -> bool {
+    key == storage::frame_key(&frame.device_id, frame.sequence, frame.timestamp)
+        || key == storage::legacy_frame_key(frame.sequence, frame.timestamp)
+}
+
+fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+    use crate::storage::StorageConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_hold_changes_are_signed() -> Result<()> {
+        let engine = EncryptionEngine::new(CryptoConfig::software(vec![7u8; 32], 1))?;
+        let public_key = engine.envelope_public_key()?;
+
+        // Holds stored before signing deserialize with an empty signature
+        let unsigned: LegalHold = serde_json::from_value(serde_json::json!({
+            "evidence_id": "session-1",
+            "case_id": "CR-2024-118",
+            "reason": "Pending trial",
+            "placed_by": "prosecutor@example.org",
+            "placed_at": 1_700_000_000u64,
+            "released_by": null,
+            "released_at": null,
+        }))?;
+        assert!(unsigned.signature.is_empty());
+
+        let placed = unsigned.sign(&engine)?;
+        assert!(placed.is_active());
+        assert!(placed.verify(&public_key)?);
+        let entry = placed.custody_entry(1_700_000_001, &engine)?;
+        assert_eq!(entry.actor, "prosecutor@example.org");
+        assert!(entry
+            .action
+            .starts_with("legal_hold_placed:session-1:case=CR-2024-118:"));
+
+        let mut released = placed.clone();
+        released.released_by = Some("admin@example.org".to_string());
+        released.released_at = Some(1_700_086_400);
+        released.release_authorization = Some("Order 24-0117".to_string());
+        assert!(!released.is_active());
+        assert!(!released.verify(&public_key)?);
+        let released = released.sign(&engine)?;
+        assert!(released.verify(&public_key)?);
+        let entry = released.custody_entry(1_700_086_401, &engine)?;
+        assert_eq!(entry.actor, "admin@example.org");
+        assert!(entry
+            .action
+            .starts_with("legal_hold_released:session-1:authorization=Order 24-0117:"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scrub_flags_corrupt_and_unlinked_frames() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage = DistributedStorage::new(StorageConfig {
+            database_path: temp_dir.path().to_string_lossy().to_string(),
+            ipfs_enabled: false,
+            ipfs_api_url: "".to_string(),
+            backup_enabled: false,
+            backup_path: "".to_string(),
+            compression_enabled: false,
+            frame_encoding: Default::default(),
+            backup_io_uring: false,
+        })
+        .await?;
+
+        let frame = |sequence: u64, hash: &str, previous: &str| EncryptedFrame {
+            sequence,
+            device_id: "cam_1".to_string(),
+            ciphertext: vec![1, 2, 3],
+            hash: hash.to_string(),
+            previous_hash: previous.to_string(),
+            nonce: vec![0; 12],
+            timestamp: 1000 + sequence,
+            blockchain_anchors: Vec::new(),
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+        storage
+            .put_record("frame:cam_1:1:1001", &frame(1, "a", &"0".repeat(64)))
+            .await?;
+        // Keyed as frames were before keys carried the device
+        storage
+            .put_record("frame:2:1002", &frame(2, "b", "a"))
+            .await?;
+        storage
+            .put_record("frame:cam_1:3:1003", &frame(3, "c", "x"))
+            .await?;
+        storage
+            .put_record("frame:cam_1:4:1004", &"not a frame")
+            .await?;
+        storage
+            .put_record(
+                "device:cam_1:00000000000000001005:00000000000000000005",
+                &"frame:cam_1:5:1005",
+            )
+            .await?;
+
+        let mut report = ScrubReport::new("test".to_string(), "admin@example.org")?;
+        scrub_storage(&storage, &mut report).await?;
+
+        assert_eq!(report.frames_scanned, 4);
+        assert_eq!(
+            report.corrupt_frames,
+            vec!["frame:cam_1:4:1004".to_string()]
+        );
+        assert_eq!(
+            report.unlinked_frames,
+            vec!["frame:cam_1:3:1003".to_string()]
+        );
+        assert_eq!(report.dangling_index_entries.len(), 1);
+
+        Ok(())
+    }
+}
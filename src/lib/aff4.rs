@@ -0,0 +1,313 @@
+// AFF4 logical containers (AFF4-L): a ZIP64 volume whose members are evidence
+// files, described in `information.turtle` so forensic suites can ingest
+// them with their hashes and case details.
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::{ImmutableEncryptionError, Result};
+
+pub const INFORMATION_ENTRY: &str = "information.turtle";
+const DESCRIPTION_ENTRY: &str = "container.description";
+const VERSION_ENTRY: &str = "version.txt";
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseDetails {
+    pub case_number: Option<String>,
+    pub case_name: String,
+    pub description: String,
+    pub examiner: String,
+}
+
+// One file in the volume, as recorded in its turtle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogicalImage {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub sha1: String, // many suites still match on SHA-1
+    pub written_at: u64,
+}
+
+pub struct Aff4Writer<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    volume: String,
+    case: CaseDetails,
+    created_at: u64,
+    images: Vec<LogicalImage>,
+}
+
+impl<W: Write + Seek> Aff4Writer<W> {
+    pub fn new(out: W, case: CaseDetails) -> Result<Self> {
+        let mut writer = Self {
+            zip: ZipWriter::new(out),
+            volume: volume_urn()?,
+            case,
+            created_at: now()?,
+            images: Vec::new(),
+        };
+        writer.zip.set_comment(writer.volume.clone());
+        writer.write_text(
+            VERSION_ENTRY,
+            "major=1\nminor=0\ntool=immutable-encryption\n",
+        )?;
+        let volume = writer.volume.clone();
+        writer.write_text(DESCRIPTION_ENTRY, &volume)?;
+        Ok(writer)
+    }
+
+    pub fn volume(&self) -> &str {
+        &self.volume
+    }
+
+    // Stores `reader` uncompressed under `path`, hashing it on the way
+    pub fn add_file<R: Read>(&mut self, path: &str, mut reader: R) -> Result<LogicalImage> {
+        if path.is_empty() || path.starts_with('/') || path.split('/').any(|part| part == "..") {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "{:?} is not a relative path",
+                path
+            )));
+        }
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(true)
+            .unix_permissions(0o444);
+        self.zip.start_file(path, options).map_err(zip_error)?;
+
+        let mut sha256 = Sha256::new();
+        let mut sha1 = digest::Context::new(&digest::SHA1_FOR_LEGACY_USE_ONLY);
+        let mut size = 0;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            sha256.update(&buf[..n]);
+            sha1.update(&buf[..n]);
+            self.zip.write_all(&buf[..n])?;
+            size += n as u64;
+        }
+
+        let image = LogicalImage {
+            path: path.to_string(),
+            size,
+            sha256: hex::encode(sha256.finalize()),
+            sha1: hex::encode(sha1.finish()),
+            written_at: now()?,
+        };
+        self.images.push(image.clone());
+        Ok(image)
+    }
+
+    // Writes the turtle and the ZIP central directory
+    pub fn finish(mut self) -> Result<(W, Vec<LogicalImage>)> {
+        let turtle = information_turtle(&self.volume, self.created_at, &self.case, &self.images);
+        self.write_text(INFORMATION_ENTRY, &turtle)?;
+        let out = self.zip.finish().map_err(zip_error)?;
+        Ok((out, self.images))
+    }
+
+    fn write_text(&mut self, name: &str, text: &str) -> Result<()> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip.start_file(name, options).map_err(zip_error)?;
+        self.zip.write_all(text.as_bytes())?;
+        Ok(())
+    }
+}
+
+pub fn is_container(prefix: &[u8]) -> bool {
+    prefix.starts_with(&ZIP_MAGIC)
+}
+
+// Opens `path` inside a container written by `Aff4Writer`
+pub fn open_member<R: Read + Seek, T>(
+    reader: R,
+    path: &str,
+    read: impl FnOnce(&mut dyn Read) -> Result<T>,
+) -> Result<T> {
+    let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+    if !archive.comment().starts_with(b"aff4://") {
+        return Err(ImmutableEncryptionError::Storage(
+            "Not an AFF4 volume: the ZIP comment has no aff4:// URN".to_string(),
+        ));
+    }
+    let mut member = archive.by_name(path).map_err(zip_error)?;
+    read(&mut member)
+}
+
+pub fn information_turtle(
+    volume: &str,
+    created_at: u64,
+    case: &CaseDetails,
+    images: &[LogicalImage],
+) -> String {
+    let mut turtle = String::from(
+        "@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .\n\
+         @prefix aff4: <http://aff4.org/Schema#> .\n\
+         @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .\n\n",
+    );
+
+    turtle.push_str(&format!("<{}>\n    a aff4:ZipVolume ;\n", volume));
+    for image in images {
+        turtle.push_str(&format!(
+            "    aff4:contains <{}/{}> ;\n",
+            volume, image.path
+        ));
+    }
+    turtle.push_str(&format!(
+        "    aff4:creationTime \"{}\"^^xsd:dateTime ;\n    aff4:tool {} .\n\n",
+        xsd_date_time(created_at),
+        literal(concat!("immutable-encryption ", env!("CARGO_PKG_VERSION"))),
+    ));
+
+    turtle.push_str(&format!("<{}/case>\n    a aff4:CaseDetails ;\n", volume));
+    if let Some(case_number) = &case.case_number {
+        turtle.push_str(&format!("    aff4:caseNumber {} ;\n", literal(case_number)));
+    }
+    turtle.push_str(&format!(
+        "    aff4:caseName {} ;\n    aff4:caseDescription {} ;\n    aff4:examiner {} ;\n    \
+         aff4:stored <{}> .\n",
+        literal(&case.case_name),
+        literal(&case.description),
+        literal(&case.examiner),
+        volume
+    ));
+
+    for image in images {
+        turtle.push_str(&format!(
+            "\n<{}/{}>\n    a aff4:FileImage, aff4:Image, aff4:ContiguousImage ;\n    \
+             aff4:hash \"{}\"^^aff4:SHA256, \"{}\"^^aff4:SHA1 ;\n    \
+             aff4:originalFileName {} ;\n    aff4:size \"{}\"^^xsd:long ;\n    \
+             aff4:lastWritten \"{}\"^^xsd:dateTime ;\n    aff4:stored <{}> .\n",
+            volume,
+            image.path,
+            image.sha256,
+            image.sha1,
+            literal(&image.path),
+            image.size,
+            xsd_date_time(image.written_at),
+            volume
+        ));
+    }
+    turtle
+}
+
+fn literal(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    format!("\"{}\"^^xsd:string", escaped)
+}
+
+// Unix seconds as an xsd:dateTime in UTC
+fn xsd_date_time(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// A random UUID as the volume URN, as AFF4 tools generate them
+fn volume_urn() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate a volume UUID"))?;
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    let hex = hex::encode(bytes);
+    Ok(format!(
+        "aff4://{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+fn zip_error(error: zip::result::ZipError) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::Storage(format!("AFF4 container: {}", error))
+}
+
+fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_container_round_trip() -> Result<()> {
+        let case = CaseDetails {
+            case_number: Some("CASE-42".to_string()),
+            case_name: "session_1".to_string(),
+            description: "Sealed \"session\" from cam_1".to_string(),
+            examiner: "auditor@example.org".to_string(),
+        };
+        let mut writer = Aff4Writer::new(Cursor::new(Vec::new()), case)?;
+        let volume = writer.volume().to_string();
+        let image = writer.add_file("bundle.ndjson", &b"{\"type\":\"manifest\"}\n"[..])?;
+        assert_eq!(
+            image.sha256,
+            crate::crypto::sha256_hex(b"{\"type\":\"manifest\"}\n")
+        );
+        assert!(writer.add_file("../escape", &b""[..]).is_err());
+        let (out, images) = writer.finish()?;
+        assert_eq!(images.len(), 1);
+
+        let bytes = out.into_inner();
+        assert!(is_container(&bytes));
+        let bundle = open_member(Cursor::new(&bytes), "bundle.ndjson", |member| {
+            let mut text = String::new();
+            member.read_to_string(&mut text)?;
+            Ok(text)
+        })?;
+        assert!(bundle.contains("manifest"));
+
+        let turtle = open_member(Cursor::new(&bytes), INFORMATION_ENTRY, |member| {
+            let mut text = String::new();
+            member.read_to_string(&mut text)?;
+            Ok(text)
+        })?;
+        assert!(turtle.contains(&format!("<{}/bundle.ndjson>", volume)));
+        assert!(turtle.contains(&format!("\"{}\"^^aff4:SHA256", image.sha256)));
+        assert!(turtle.contains("aff4:caseNumber \"CASE-42\"^^xsd:string"));
+        assert!(turtle.contains("Sealed \\\"session\\\" from cam_1"));
+
+        assert_eq!(xsd_date_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(xsd_date_time(1_709_251_199), "2024-02-29T23:59:59Z");
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::net::UdpSocket;
+use tokio::time::{Duration, Instant};
+
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::notifications::{Event, EventEnvelope, EventKind, EventSink};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertConfig {
+    pub enabled: bool,
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+    #[serde(default = "default_dedup_window_secs")]
+    pub dedup_window_secs: u64, // repeats of an alert inside this window are counted, not sent
+    #[serde(default)]
+    pub channels: Vec<AlertChannelConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AlertChannelConfig {
+    Webhook {
+        url: String,
+        #[serde(default = "default_timeout_ms")]
+        timeout_ms: u64,
+    },
+    Smtp {
+        host: String,
+        #[serde(default = "default_smtp_port")]
+        port: u16,
+        #[serde(default = "default_starttls")]
+        starttls: bool,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    Syslog {
+        address: String, // host:port of a UDP (RFC 5424) collector
+        #[serde(default = "default_facility")]
+        facility: u8,
+    },
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Warning
+}
+
+fn default_dedup_window_secs() -> u64 {
+    900
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_starttls() -> bool {
+    true
+}
+
+fn default_facility() -> u8 {
+    16 // local0
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_severity: default_min_severity(),
+            dedup_window_secs: default_dedup_window_secs(),
+            channels: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub severity: Severity,
+    pub title: String,
+    pub details: String,
+    pub subject: String, // evidence, scrub or device the alert is about
+    pub raised_at: u64,
+    pub suppressed: u64, // identical alerts dropped since this one was last sent
+    #[serde(skip)]
+    dedup_key: String,
+}
+
+impl Alert {
+    // Only events that need someone's attention become alerts
+    pub fn from_event(envelope: &EventEnvelope) -> Option<Self> {
+        let (severity, title, subject, details) = match &envelope.event {
+            Event::TamperingDetected {
+                evidence_id,
+                details,
+            } => (
+                Severity::Critical,
+                "Evidence tampering detected".to_string(),
+                evidence_id.clone(),
+                details.clone(),
+            ),
+            Event::CorruptionDetected {
+                scrub_id,
+                corrupt_frames,
+                dangling_index_entries,
+                unlinked_frames,
+            } => (
+                Severity::Critical,
+                "Storage corruption found by scrub".to_string(),
+                format!("scrub:{}", scrub_id),
+                format!(
+                    "{} corrupt frames, {} dangling index entries, {} unlinked frames",
+                    corrupt_frames, dangling_index_entries, unlinked_frames
+                ),
+            ),
+            Event::PipelineFailed {
+                stage,
+                device_id,
+                sequence,
+                code,
+                error,
+            } => (
+                Severity::Error,
+                format!("Frame {} failed ({})", stage, code),
+                device_id.clone(),
+                format!("sequence {}: {}", sequence, error),
+            ),
+            _ => return None,
+        };
+
+        // Pipeline failures repeat per frame, so they are keyed without it
+        let dedup_key = match &envelope.event {
+            Event::PipelineFailed { .. } => format!("{}|{}", title, subject),
+            _ => format!("{}|{}|{}", title, subject, details),
+        };
+        Some(Self {
+            severity,
+            title,
+            details,
+            subject,
+            raised_at: envelope.emitted_at,
+            suppressed: 0,
+            dedup_key,
+        })
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "[{:?}] {}: {} ({})",
+            self.severity, self.title, self.subject, self.details
+        )
+    }
+}
+
+// Lets the first of a run of identical alerts through and counts the rest
+// until the window has passed
+#[derive(Debug)]
+pub struct Deduplicator {
+    window: Duration,
+    sent: HashMap<String, (Instant, u64)>, // key -> (last sent, suppressed since)
+}
+
+impl Deduplicator {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            sent: HashMap::new(),
+        }
+    }
+
+    // The number of repeats suppressed since the key was last sent, or None
+    // when this one should be suppressed too
+    pub fn admit(&mut self, key: &str, now: Instant) -> Option<u64> {
+        let window = self.window;
+        // Expired keys are forgotten unless they have repeats still to report
+        self.sent.retain(|_, (sent_at, suppressed)| {
+            *suppressed > 0 || now.saturating_duration_since(*sent_at) < window
+        });
+
+        match self.sent.get_mut(key) {
+            Some((sent_at, suppressed)) if now.saturating_duration_since(*sent_at) < window => {
+                *suppressed += 1;
+                None
+            }
+            Some((sent_at, suppressed)) => {
+                *sent_at = now;
+                Some(std::mem::take(suppressed))
+            }
+            None => {
+                self.sent.insert(key.to_string(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+#[async_trait]
+pub trait AlertChannel: Send + Sync {
+    fn name(&self) -> String;
+
+    async fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+// Turns tamper, corruption and fatal pipeline events into alerts and sends
+// each one, once per dedup window, to every configured channel
+pub struct AlertSink {
+    min_severity: Severity,
+    dedup: Mutex<Deduplicator>,
+    channels: Vec<Box<dyn AlertChannel>>,
+}
+
+impl AlertSink {
+    pub fn new(config: &AlertConfig) -> Result<Self> {
+        let mut channels: Vec<Box<dyn AlertChannel>> = Vec::new();
+        for channel in &config.channels {
+            channels.push(match channel {
+                AlertChannelConfig::Webhook { url, timeout_ms } => Box::new(WebhookChannel {
+                    client: reqwest::Client::builder()
+                        .timeout(Duration::from_millis(*timeout_ms))
+                        .build()?,
+                    url: url.clone(),
+                }),
+                #[cfg(feature = "smtp")]
+                AlertChannelConfig::Smtp {
+                    host,
+                    port,
+                    starttls,
+                    username,
+                    password,
+                    from,
+                    to,
+                } => Box::new(SmtpChannel::new(
+                    host,
+                    *port,
+                    *starttls,
+                    username.clone().zip(password.clone()),
+                    from,
+                    to,
+                )?),
+                #[cfg(not(feature = "smtp"))]
+                AlertChannelConfig::Smtp { .. } => {
+                    return Err(ImmutableEncryptionError::config(
+                        "SMTP alerts need the `smtp` feature",
+                    ));
+                }
+                AlertChannelConfig::Syslog { address, facility } => Box::new(SyslogChannel {
+                    address: address.clone(),
+                    facility: *facility,
+                }),
+            });
+        }
+
+        Ok(Self {
+            min_severity: config.min_severity,
+            dedup: Mutex::new(Deduplicator::new(Duration::from_secs(
+                config.dedup_window_secs,
+            ))),
+            channels,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for AlertSink {
+    fn name(&self) -> String {
+        "alerts".to_string()
+    }
+
+    fn accepts(&self, kind: EventKind) -> bool {
+        matches!(
+            kind,
+            EventKind::TamperingDetected
+                | EventKind::CorruptionDetected
+                | EventKind::PipelineFailed
+        )
+    }
+
+    async fn deliver(&self, event: &EventEnvelope) -> Result<()> {
+        let mut alert = match Alert::from_event(event) {
+            Some(alert) if alert.severity >= self.min_severity => alert,
+            _ => return Ok(()),
+        };
+        let admitted = self
+            .dedup
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .admit(&alert.dedup_key, Instant::now());
+        alert.suppressed = match admitted {
+            Some(suppressed) => suppressed,
+            None => return Ok(()),
+        };
+
+        // Every channel is tried; the first failure is reported
+        let mut failed = None;
+        for channel in &self.channels {
+            if let Err(e) = channel.send(&alert).await {
+                tracing::error!("Alert channel {} failed: {}", channel.name(), e);
+                failed.get_or_insert(e);
+            }
+        }
+        failed.map_or(Ok(()), Err)
+    }
+}
+
+pub struct WebhookChannel {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[async_trait]
+impl AlertChannel for WebhookChannel {
+    fn name(&self) -> String {
+        format!("webhook:{}", self.url)
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let response = self.client.post(&self.url).json(alert).send().await?;
+        if !response.status().is_success() {
+            return Err(ImmutableEncryptionError::Network(format!(
+                "alert webhook returned {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub struct SyslogChannel {
+    address: String,
+    facility: u8,
+}
+
+impl SyslogChannel {
+    // RFC 5424 with nil timestamp and hostname; the collector stamps both
+    pub fn format(&self, alert: &Alert) -> String {
+        let severity = match alert.severity {
+            Severity::Critical => 2,
+            Severity::Error => 3,
+            Severity::Warning => 4,
+            Severity::Info => 6,
+        };
+        let mut message = alert.summary();
+        if alert.suppressed > 0 {
+            message.push_str(&format!(" [{} repeats suppressed]", alert.suppressed));
+        }
+        format!(
+            "<{}>1 - - immutable-encryption - - - {}",
+            u32::from(self.facility) * 8 + severity,
+            message
+        )
+    }
+}
+
+#[async_trait]
+impl AlertChannel for SyslogChannel {
+    fn name(&self) -> String {
+        format!("syslog:{}", self.address)
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket
+            .send_to(self.format(alert).as_bytes(), &self.address)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "smtp")]
+pub struct SmtpChannel {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+    host: String,
+    from: lettre::message::Mailbox,
+    to: Vec<lettre::message::Mailbox>,
+}
+
+#[cfg(feature = "smtp")]
+impl SmtpChannel {
+    pub fn new(
+        host: &str,
+        port: u16,
+        starttls: bool,
+        credentials: Option<(String, String)>,
+        from: &str,
+        to: &[String],
+    ) -> Result<Self> {
+        use lettre::{AsyncSmtpTransport, Tokio1Executor};
+
+        let address = |a: &str| {
+            a.parse::<lettre::message::Mailbox>()
+                .map_err(|e| ImmutableEncryptionError::Config(format!("Bad address {}: {}", a, e)))
+        };
+        let mut builder = if starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .map_err(|e| ImmutableEncryptionError::Config(format!("SMTP relay: {}", e)))?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        builder = builder.port(port);
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(
+                lettre::transport::smtp::authentication::Credentials::new(username, password),
+            );
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            host: host.to_string(),
+            from: address(from)?,
+            to: to.iter().map(|a| address(a)).collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[cfg(feature = "smtp")]
+#[async_trait]
+impl AlertChannel for SmtpChannel {
+    fn name(&self) -> String {
+        format!("smtp:{}", self.host)
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        use lettre::AsyncTransport;
+
+        let mut message = lettre::Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[{:?}] {}", alert.severity, alert.title));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let body = format!(
+            "{}\n\nSubject: {}\nRaised at: {}\nRepeats suppressed: {}\n",
+            alert.details, alert.subject, alert.raised_at, alert.suppressed
+        );
+        let message = message
+            .body(body)
+            .map_err(|e| ImmutableEncryptionError::Internal(format!("Alert email: {}", e)))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| ImmutableEncryptionError::Network(format!("SMTP delivery: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envelope(event: Event) -> EventEnvelope {
+        EventEnvelope {
+            event_id: 1,
+            emitted_at: 1_700_000_000,
+            event,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alerts_are_deduplicated_and_sent_to_syslog() -> Result<()> {
+        let failed = |sequence| {
+            envelope(Event::PipelineFailed {
+                stage: "anchor".to_string(),
+                device_id: "cam_1".to_string(),
+                sequence,
+                code: "blockchain".to_string(),
+                error: "nonce too low".to_string(),
+            })
+        };
+        let first = Alert::from_event(&failed(1)).unwrap();
+        assert_eq!(first.severity, Severity::Error);
+        assert_eq!(
+            first.dedup_key,
+            Alert::from_event(&failed(2)).unwrap().dedup_key
+        );
+        assert!(Alert::from_event(&envelope(Event::VerificationCompleted {
+            evidence_id: "session_1".to_string(),
+            is_valid: true,
+            frame_count: 3,
+        }))
+        .is_none());
+
+        let mut dedup = Deduplicator::new(Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(dedup.admit("a", start), Some(0));
+        assert_eq!(dedup.admit("a", start + Duration::from_secs(10)), None);
+        assert_eq!(dedup.admit("a", start + Duration::from_secs(20)), None);
+        assert_eq!(dedup.admit("b", start + Duration::from_secs(20)), Some(0));
+        assert_eq!(dedup.admit("a", start + Duration::from_secs(61)), Some(2));
+
+        let collector = UdpSocket::bind("127.0.0.1:0").await?;
+        let sink = AlertSink::new(&AlertConfig {
+            enabled: true,
+            min_severity: Severity::Critical,
+            dedup_window_secs: 60,
+            channels: vec![AlertChannelConfig::Syslog {
+                address: collector.local_addr()?.to_string(),
+                facility: 16,
+            }],
+        })?;
+        let tampered = envelope(Event::TamperingDetected {
+            evidence_id: "session_1".to_string(),
+            details: "hash mismatch".to_string(),
+        });
+        sink.deliver(&failed(3)).await?; // below min_severity
+        sink.deliver(&tampered).await?;
+        sink.deliver(&tampered).await?;
+
+        let mut buf = [0u8; 512];
+        let (len, _) = collector.recv_from(&mut buf).await?;
+        let line = String::from_utf8_lossy(&buf[..len]).to_string();
+        assert!(line.starts_with("<130>1 "), "{}", line);
+        assert!(line.contains("Evidence tampering detected: session_1"));
+        let again = tokio::time::timeout(Duration::from_millis(100), collector.recv_from(&mut buf));
+        assert!(again.await.is_err());
+        Ok(())
+    }
+}
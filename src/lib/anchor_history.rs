@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::blockchain::{AnchorObservation, InclusionState};
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Every status check of one anchor transaction, oldest first, so confirmation
+// progress and any reorganization can be shown after the fact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorHistory {
+    pub chain: String,
+    pub transaction_hash: String,
+    pub recorded_block: Option<u64>, // block number in the anchor record, if known
+    pub observations: Vec<AnchorObservation>,
+}
+
+impl AnchorHistory {
+    pub fn new(chain: &str, transaction_hash: &str, recorded_block: Option<u64>) -> Self {
+        Self {
+            chain: chain.to_string(),
+            transaction_hash: transaction_hash.to_string(),
+            recorded_block,
+            observations: Vec::new(),
+        }
+    }
+
+    // The stored history for a transaction, or an empty one
+    pub fn load(
+        dir: &str,
+        chain: &str,
+        transaction_hash: &str,
+        recorded_block: Option<u64>,
+    ) -> Result<Self> {
+        let path = history_path(dir, chain, transaction_hash)?;
+        if !path.exists() {
+            return Ok(Self::new(chain, transaction_hash, recorded_block));
+        }
+
+        let mut history: Self = serde_json::from_slice(&std::fs::read(&path)?)?;
+        history.recorded_block = history.recorded_block.or(recorded_block);
+        Ok(history)
+    }
+
+    pub fn save(&self, dir: &str) -> Result<PathBuf> {
+        let path = history_path(dir, &self.chain, &self.transaction_hash)?;
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)?;
+        Ok(path)
+    }
+
+    // Appends an observation, flagging it as a reorg when the transaction has
+    // left the block it was seen in (or recorded in) since
+    pub fn record(&mut self, mut observation: AnchorObservation) -> &AnchorObservation {
+        let last_block = self
+            .observations
+            .iter()
+            .rev()
+            .find(|o| o.state == InclusionState::Included)
+            .map(|o| (o.block_number, o.block_hash.clone()));
+
+        observation.reorged = match (observation.state, last_block) {
+            (InclusionState::Included, Some((number, hash))) => {
+                observation.block_number != number
+                    || (hash.is_some() && observation.block_hash != hash)
+            }
+            (InclusionState::Included, None) => self
+                .recorded_block
+                .map_or(false, |b| b > 0 && observation.block_number != Some(b)),
+            // Seen in a block before, and now it isn't in one
+            (_, Some(_)) => true,
+            (_, None) => false,
+        };
+
+        self.observations.push(observation);
+        &self.observations[self.observations.len() - 1]
+    }
+}
+
+// Hashes name the file, so only hex is accepted
+fn history_path(dir: &str, chain: &str, transaction_hash: &str) -> Result<PathBuf> {
+    let hash = transaction_hash.trim_start_matches("0x");
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ImmutableEncryptionError::Blockchain(format!(
+            "Not a transaction hash: {}",
+            transaction_hash
+        )));
+    }
+    if !chain.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(ImmutableEncryptionError::Blockchain(format!(
+            "Not a chain name: {}",
+            chain
+        )));
+    }
+    Ok(Path::new(dir).join(format!("{}_{}.json", chain, hash.to_ascii_lowercase())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observed(state: InclusionState, block: Option<(u64, &str)>) -> AnchorObservation {
+        AnchorObservation {
+            chain: "ethereum".to_string(),
+            transaction_hash: "ab".repeat(32),
+            checked_at: 1_700_000_000,
+            state,
+            block_number: block.map(|(number, _)| number),
+            block_hash: block.map(|(_, hash)| hash.to_string()),
+            confirmations: 1,
+            reorged: false,
+        }
+    }
+
+    #[test]
+    fn test_reorgs_are_flagged_against_earlier_observations() -> Result<()> {
+        let reorged = |history: &mut AnchorHistory, state, block| {
+            history.record(observed(state, block)).reorged
+        };
+        let mut history = AnchorHistory::new("ethereum", &"ab".repeat(32), Some(100));
+        assert!(!reorged(&mut history, InclusionState::Pending, None));
+        assert!(!reorged(
+            &mut history,
+            InclusionState::Included,
+            Some((100, "aa"))
+        ));
+        assert!(!reorged(
+            &mut history,
+            InclusionState::Included,
+            Some((100, "aa"))
+        ));
+
+        // Same height, different block: the original block was orphaned
+        assert!(reorged(
+            &mut history,
+            InclusionState::Included,
+            Some((100, "bb"))
+        ));
+        assert!(reorged(&mut history, InclusionState::Pending, None));
+
+        // Included somewhere other than the block the anchor was recorded in
+        let mut elsewhere = AnchorHistory::new("ethereum", &"ab".repeat(32), Some(100));
+        assert!(reorged(
+            &mut elsewhere,
+            InclusionState::Included,
+            Some((101, "cc"))
+        ));
+
+        let dir = std::env::temp_dir().join(format!("anchor-history-{}", std::process::id()));
+        let dir = dir.to_string_lossy().to_string();
+        history.save(&dir)?;
+        let loaded = AnchorHistory::load(&dir, "ethereum", &"ab".repeat(32), None)?;
+        assert_eq!(loaded.observations.len(), 5);
+        assert!(AnchorHistory::load(&dir, "ethereum", "../../etc/passwd", None).is_err());
+        std::fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,403 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::auth::{Principal, Role};
+use crate::crypto::EncryptionEngine;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::storage::DistributedStorage;
+use crate::tenant::DEFAULT_TENANT;
+use crate::CustodyEntry;
+
+const KEY_PREFIX: &str = "iek";
+
+// API keys act as `apikey:{key_id}`
+pub const SUBJECT_PREFIX: &str = "apikey:";
+
+// Only the hash of the secret is ever persisted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub key_id: String,
+    pub name: String,
+    pub key_hash: String,
+    pub roles: Vec<Role>,
+    pub rate_limit_per_minute: u32,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub revoked_at: Option<u64>,
+    pub rotated_from: Option<String>,
+    #[serde(default = "default_tenant")]
+    pub tenant: String, // keys only ever act within the tenant that issued them
+    #[serde(default)]
+    pub issued_by: Option<String>, // the person accountable for the key; unknown for older keys
+}
+
+fn default_tenant() -> String {
+    DEFAULT_TENANT.to_string()
+}
+
+impl ApiKeyRecord {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.revoked_at.is_none() && self.expires_at.map_or(true, |expires| now < expires)
+    }
+}
+
+// Returned once at issuance; the plaintext key cannot be recovered later
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiKey {
+    pub api_key: String,
+    pub record: ApiKeyRecord,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyRequest {
+    pub name: String,
+    pub roles: Vec<Role>,
+    pub rate_limit_per_minute: u32,
+    pub ttl_seconds: Option<u64>,
+    #[serde(skip, default = "default_tenant")]
+    pub tenant: String, // set from the issuing admin, never from the request body
+    #[serde(skip)]
+    pub issued_by: Option<String>, // likewise
+}
+
+pub struct ApiKeyManager {
+    storage: Arc<DistributedStorage>,
+    engine: Arc<Mutex<EncryptionEngine>>, // signs each use into the key's custody log
+    usage: Mutex<HashMap<String, (u64, u32)>>, // key_id -> (minute window, requests)
+    rng: SystemRandom,
+}
+
+impl ApiKeyManager {
+    pub fn new(storage: Arc<DistributedStorage>, engine: Arc<Mutex<EncryptionEngine>>) -> Self {
+        Self {
+            storage,
+            engine,
+            usage: Mutex::new(HashMap::new()),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    fn record_key(key_id: &str) -> String {
+        format!("apikey:{}", key_id)
+    }
+
+    pub async fn issue(&self, request: ApiKeyRequest) -> Result<IssuedApiKey> {
+        self.issue_with_parent(request, None).await
+    }
+
+    async fn issue_with_parent(
+        &self,
+        request: ApiKeyRequest,
+        rotated_from: Option<String>,
+    ) -> Result<IssuedApiKey> {
+        if request.roles.is_empty() {
+            return Err(ImmutableEncryptionError::config(
+                "An API key needs at least one role",
+            ));
+        }
+
+        let mut key_id = [0u8; 8];
+        let mut secret = [0u8; 32];
+        self.rng
+            .fill(&mut key_id)
+            .and_then(|_| self.rng.fill(&mut secret))
+            .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate API key"))?;
+
+        let key_id = hex::encode(key_id);
+        let secret = hex::encode(secret);
+        let created_at = now()?;
+
+        let record = ApiKeyRecord {
+            key_id: key_id.clone(),
+            name: request.name,
+            key_hash: hash_secret(&secret),
+            roles: request.roles,
+            rate_limit_per_minute: request.rate_limit_per_minute,
+            created_at,
+            expires_at: request.ttl_seconds.map(|ttl| created_at + ttl),
+            revoked_at: None,
+            rotated_from,
+            tenant: request.tenant,
+            issued_by: request.issued_by,
+        };
+        self.storage
+            .put_record(&Self::record_key(&key_id), &record)
+            .await?;
+
+        tracing::info!("Issued API key {} ({})", record.key_id, record.name);
+        Ok(IssuedApiKey {
+            api_key: format!("{}_{}_{}", KEY_PREFIX, key_id, secret),
+            record,
+        })
+    }
+
+    // Issues a replacement with the same scope and revokes the old key. Keys
+    // issued before issuers were recorded pass to whoever rotates them.
+    pub async fn rotate(&self, key_id: &str, rotated_by: &Principal) -> Result<IssuedApiKey> {
+        let tenant = rotated_by.tenant.as_str();
+        let old = self.get_in_tenant(key_id, tenant).await?;
+        if old.revoked_at.is_some() {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "API key {} is revoked",
+                key_id
+            )));
+        }
+
+        let request = ApiKeyRequest {
+            name: old.name.clone(),
+            roles: old.roles.clone(),
+            rate_limit_per_minute: old.rate_limit_per_minute,
+            ttl_seconds: old.expires_at.map(|e| e.saturating_sub(old.created_at)),
+            tenant: old.tenant.clone(),
+            issued_by: old
+                .issued_by
+                .clone()
+                .or_else(|| Some(rotated_by.accountable().to_string())),
+        };
+        let issued = self
+            .issue_with_parent(request, Some(key_id.to_string()))
+            .await?;
+        self.revoke(key_id, tenant).await?;
+
+        Ok(issued)
+    }
+
+    pub async fn revoke(&self, key_id: &str, tenant: &str) -> Result<ApiKeyRecord> {
+        let mut record = self.get_in_tenant(key_id, tenant).await?;
+
+        record.revoked_at.get_or_insert(now()?);
+        self.storage
+            .put_record(&Self::record_key(key_id), &record)
+            .await?;
+
+        tracing::info!("Revoked API key {}", key_id);
+        Ok(record)
+    }
+
+    pub async fn get(&self, key_id: &str) -> Result<Option<ApiKeyRecord>> {
+        self.storage.get_record(&Self::record_key(key_id)).await
+    }
+
+    // Keys of other tenants are reported as unknown rather than forbidden
+    async fn get_in_tenant(&self, key_id: &str, tenant: &str) -> Result<ApiKeyRecord> {
+        self.get(key_id)
+            .await?
+            .filter(|record| record.tenant == tenant)
+            .ok_or_else(|| {
+                ImmutableEncryptionError::PermissionDenied(format!("Unknown API key {}", key_id))
+            })
+    }
+
+    pub async fn list(&self, tenant: &str) -> Result<Vec<ApiKeyRecord>> {
+        Ok(self
+            .storage
+            .scan_records::<ApiKeyRecord>("apikey:")
+            .await?
+            .into_iter()
+            .map(|(_, record)| record)
+            .filter(|record| record.tenant == tenant)
+            .collect())
+    }
+
+    pub async fn usage(&self, key_id: &str, tenant: &str) -> Result<Vec<CustodyEntry>> {
+        self.get_in_tenant(key_id, tenant).await?;
+        self.storage
+            .custody_entries(&Self::record_key(key_id))
+            .await
+    }
+
+    // Resolves a presented key to a principal, enforcing revocation, expiry
+    // and the per-key rate limit, and records the use in the key's audit log.
+    pub async fn authenticate(&self, presented: &str, action: &str) -> Result<Principal> {
+        let denied = || ImmutableEncryptionError::PermissionDenied("invalid API key".to_string());
+
+        let (key_id, secret) = parse_key(presented).ok_or_else(denied)?;
+        let record = self.get(key_id).await?.ok_or_else(denied)?;
+
+        let presented_hash = hash_secret(secret);
+        if ring::constant_time::verify_slices_are_equal(
+            presented_hash.as_bytes(),
+            record.key_hash.as_bytes(),
+        )
+        .is_err()
+        {
+            return Err(denied());
+        }
+
+        let now = now()?;
+        if !record.is_active(now) {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "API key {} is revoked or expired",
+                key_id
+            )));
+        }
+
+        self.check_rate_limit(&record, now).await?;
+
+        // Signed like a custody transfer, over `{timestamp}|{actor}|{action}`
+        let actor = format!("{}{}", SUBJECT_PREFIX, key_id);
+        let signature = self
+            .engine
+            .lock()
+            .await
+            .sign(format!("{}|{}|{}", now, actor, action).as_bytes());
+        let entry = CustodyEntry {
+            timestamp: now,
+            actor,
+            action: action.to_string(),
+            signature,
+            blockchain_reference: String::new(),
+        };
+        if let Err(e) = self
+            .storage
+            .append_custody_entry(&Self::record_key(key_id), &entry)
+            .await
+        {
+            tracing::error!("Failed to audit use of API key {}: {}", key_id, e);
+        }
+
+        Ok(Principal {
+            subject: format!("{}{}", SUBJECT_PREFIX, key_id),
+            roles: record.roles,
+            tenant: record.tenant,
+            source: None,
+            issued_by: record.issued_by,
+        })
+    }
+
+    async fn check_rate_limit(&self, record: &ApiKeyRecord, now: u64) -> Result<()> {
+        if record.rate_limit_per_minute == 0 {
+            return Ok(());
+        }
+
+        let window = now / 60;
+        let mut usage = self.usage.lock().await;
+        let (current_window, count) = usage.entry(record.key_id.clone()).or_insert((window, 0));
+        if *current_window != window {
+            *current_window = window;
+            *count = 0;
+        }
+
+        if *count >= record.rate_limit_per_minute {
+            return Err(ImmutableEncryptionError::RateLimitExceeded(format!(
+                "API key {} is limited to {} requests per minute",
+                record.key_id, record.rate_limit_per_minute
+            )));
+        }
+        *count += 1;
+
+        Ok(())
+    }
+}
+
+fn parse_key(presented: &str) -> Option<(&str, &str)> {
+    let mut parts = presented.trim().splitn(3, '_');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(KEY_PREFIX), Some(key_id), Some(secret)) if !key_id.is_empty() => {
+            Some((key_id, secret))
+        }
+        _ => None,
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+    use crate::storage::StorageConfig;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage = Arc::new(
+            DistributedStorage::new(StorageConfig {
+                database_path: temp_dir.path().to_string_lossy().to_string(),
+                ipfs_enabled: false,
+                ipfs_api_url: "".to_string(),
+                backup_enabled: false,
+                backup_path: "".to_string(),
+                compression_enabled: false,
+                frame_encoding: Default::default(),
+                backup_io_uring: false,
+            })
+            .await?,
+        );
+        let engine = Arc::new(Mutex::new(EncryptionEngine::new(CryptoConfig::software(
+            vec![2u8; 32],
+            60,
+        ))?));
+        let manager = ApiKeyManager::new(storage, engine.clone());
+
+        let issued = manager
+            .issue(ApiKeyRequest {
+                name: "case-management".to_string(),
+                roles: vec![Role::Auditor],
+                rate_limit_per_minute: 2,
+                ttl_seconds: None,
+                tenant: "metro-pd".to_string(),
+                issued_by: Some("admin-1".to_string()),
+            })
+            .await?;
+        assert_ne!(issued.record.key_hash, issued.api_key);
+
+        let principal = manager.authenticate(&issued.api_key, "GET /verify").await?;
+        assert_eq!(principal.roles, vec![Role::Auditor]);
+        assert_eq!(principal.tenant, "metro-pd");
+        assert_eq!(principal.accountable(), "admin-1");
+
+        manager.authenticate(&issued.api_key, "GET /verify").await?;
+        assert!(matches!(
+            manager.authenticate(&issued.api_key, "GET /verify").await,
+            Err(ImmutableEncryptionError::RateLimitExceeded(_))
+        ));
+        let usage = manager.usage(&issued.record.key_id, "metro-pd").await?;
+        assert_eq!(usage.len(), 2);
+        let engine = engine.lock().await;
+        assert!(usage.iter().all(|entry| engine.verify_signature(
+            format!("{}|{}|{}", entry.timestamp, entry.actor, entry.action).as_bytes(),
+            &entry.signature
+        )));
+        drop(engine);
+        assert!(manager.list(DEFAULT_TENANT).await?.is_empty());
+        assert!(manager
+            .revoke(&issued.record.key_id, DEFAULT_TENANT)
+            .await
+            .is_err());
+
+        let rotated_by = Principal {
+            tenant: "metro-pd".to_string(),
+            ..Principal::anonymous()
+        };
+        let rotated = manager.rotate(&issued.record.key_id, &rotated_by).await?;
+        assert_eq!(
+            rotated.record.rotated_from,
+            Some(issued.record.key_id.clone())
+        );
+        assert_eq!(rotated.record.issued_by.as_deref(), Some("admin-1"));
+        assert!(matches!(
+            manager.authenticate(&issued.api_key, "GET /verify").await,
+            Err(ImmutableEncryptionError::PermissionDenied(_))
+        ));
+        assert!(manager
+            .authenticate("iek_bogus_secret", "GET /")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,575 @@
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::api_keys;
+use crate::auth::{Principal, Role};
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Actions that wait for M-of-N approvals when a policy is configured for them
+// under `[approvals.<action>]`; without one they run as before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalConfig {
+    #[serde(default)]
+    pub decrypt: Option<ApprovalPolicy>, // plaintext exports, decryption, shared clips
+    #[serde(default)]
+    pub custody_transfer: Option<ApprovalPolicy>,
+    #[serde(default)]
+    pub purge: Option<ApprovalPolicy>, // deleting a session, also after retention
+    #[serde(default = "default_expiry_secs")]
+    pub expiry_secs: u64, // how long a request may collect approvals and wait to run
+    #[serde(default)]
+    pub approvers: HashMap<String, String>, // person -> hex Ed25519 key they approve with
+}
+
+fn default_expiry_secs() -> u64 {
+    7 * 86_400
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            decrypt: None,
+            custody_transfer: None,
+            purge: None,
+            expiry_secs: default_expiry_secs(),
+            approvers: HashMap::new(),
+        }
+    }
+}
+
+impl ApprovalConfig {
+    pub fn policy(&self, action: &ApprovalAction) -> Option<&ApprovalPolicy> {
+        match action {
+            ApprovalAction::Decrypt { .. } => self.decrypt.as_ref(),
+            ApprovalAction::CustodyTransfer { .. } => self.custody_transfer.as_ref(),
+            ApprovalAction::Purge { .. } => self.purge.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    pub required: usize,  // M: distinct people, never the requester
+    pub roles: Vec<Role>, // N: who may approve
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ApprovalAction {
+    Decrypt { evidence_id: String },
+    CustodyTransfer { evidence_id: String, to: String },
+    Purge { evidence_id: String },
+}
+
+impl ApprovalAction {
+    pub fn evidence_id(&self) -> &str {
+        match self {
+            ApprovalAction::Decrypt { evidence_id }
+            | ApprovalAction::CustodyTransfer { evidence_id, .. }
+            | ApprovalAction::Purge { evidence_id } => evidence_id,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            ApprovalAction::Decrypt { evidence_id } => format!("decrypt {}", evidence_id),
+            ApprovalAction::CustodyTransfer { evidence_id, to } => {
+                format!("transfer custody of {} to {}", evidence_id, to)
+            }
+            ApprovalAction::Purge { evidence_id } => format!("purge {}", evidence_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Executed,
+    Expired,
+}
+
+// One approver's sign-off: their own Ed25519 signature over the request's
+// statement, made with the key registered for them under [approvals.approvers].
+// An API key's approval counts, and is signed, for the person who issued it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Approval {
+    pub approver: String,
+    pub role: Role,
+    pub approved_at: u64,
+    pub signature: String,
+    #[serde(default)]
+    pub issued_by: Option<String>,
+    #[serde(default)]
+    pub public_key: Option<String>, // hex; absent on approvals the node signed itself
+}
+
+impl Approval {
+    pub fn accountable(&self) -> &str {
+        self.issued_by.as_deref().unwrap_or(&self.approver)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rejection {
+    pub rejected_by: String,
+    pub rejected_at: u64,
+    pub reason: String,
+}
+
+// A sensitive action waiting on approvals, persisted under
+// `approval:{request_id}` and listed in the court report of its evidence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub request_id: String,
+    pub action: ApprovalAction,
+    pub reason: String,
+    pub requested_by: String,
+    #[serde(default)]
+    pub requester_issued_by: Option<String>, // when requested with an API key
+    pub requested_at: u64,
+    pub expires_at: u64,
+    pub required: usize,
+    pub roles: Vec<Role>, // the policy when requested; later changes don't apply
+    pub approvals: Vec<Approval>,
+    pub rejection: Option<Rejection>,
+    pub executed_at: Option<u64>,
+}
+
+impl ApprovalRequest {
+    pub fn new(
+        request_id: String,
+        action: ApprovalAction,
+        reason: String,
+        requester: &Principal,
+        config: &ApprovalConfig,
+        now: u64,
+    ) -> Result<Self> {
+        let policy = config.policy(&action).ok_or_else(|| {
+            ImmutableEncryptionError::InvalidRequest(format!(
+                "No approval is needed to {}",
+                action.describe()
+            ))
+        })?;
+        Ok(Self {
+            request_id,
+            action,
+            reason,
+            requested_by: requester.subject.clone(),
+            requester_issued_by: requester.issued_by.clone(),
+            requested_at: now,
+            expires_at: now.saturating_add(config.expiry_secs),
+            required: policy.required,
+            roles: policy.roles.clone(),
+            approvals: Vec::new(),
+            rejection: None,
+            executed_at: None,
+        })
+    }
+
+    pub fn key(request_id: &str) -> String {
+        format!("approval:{}", request_id)
+    }
+
+    pub fn status(&self, now: u64) -> ApprovalStatus {
+        if self.executed_at.is_some() {
+            ApprovalStatus::Executed
+        } else if self.rejection.is_some() {
+            ApprovalStatus::Rejected
+        } else if now >= self.expires_at {
+            ApprovalStatus::Expired
+        } else if self.approvals.len() >= self.required {
+            ApprovalStatus::Approved
+        } else {
+            ApprovalStatus::Pending
+        }
+    }
+
+    // What an approver signs: the request as it was made, and who approves it
+    pub fn statement(&self, person: &str) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(
+            "approve",
+            &self.request_id,
+            &self.action,
+            &self.reason,
+            &self.requested_by,
+            self.requested_at,
+            self.expires_at,
+            person,
+        ))?)
+    }
+
+    // Records an approval signed by the approver, checked against the key
+    // registered for the person accountable for it
+    pub fn approve(
+        &mut self,
+        approver: &Principal,
+        now: u64,
+        signature: &[u8],
+        config: &ApprovalConfig,
+    ) -> Result<()> {
+        let role = self.check_approver(approver, now)?;
+        let person = approver.accountable();
+        if self
+            .approvals
+            .iter()
+            .any(|a| a.approver == approver.subject || a.accountable() == person)
+        {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "{} has already approved request {}",
+                person, self.request_id
+            )));
+        }
+        let public_key = config.approvers.get(person).ok_or_else(|| {
+            ImmutableEncryptionError::PermissionDenied(format!(
+                "{} has no key under [approvals.approvers]",
+                person
+            ))
+        })?;
+        if !self.signed_by(person, public_key, signature)? {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "The signature is not {}'s approval of request {}",
+                person, self.request_id
+            )));
+        }
+        self.approvals.push(Approval {
+            approver: approver.subject.clone(),
+            role,
+            approved_at: now,
+            signature: hex::encode(signature),
+            issued_by: approver.issued_by.clone(),
+            public_key: Some(public_key.clone()),
+        });
+        Ok(())
+    }
+
+    pub fn reject(&mut self, approver: &Principal, now: u64, reason: String) -> Result<()> {
+        self.check_approver(approver, now)?;
+        self.rejection = Some(Rejection {
+            rejected_by: approver.subject.clone(),
+            rejected_at: now,
+            reason,
+        });
+        Ok(())
+    }
+
+    // Marks an approved request as run, by the requester and for the action
+    // it was approved for; a request runs once
+    pub fn execute(&mut self, action: &ApprovalAction, executor: &str, now: u64) -> Result<()> {
+        if self.action != *action {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "Request {} approves {}, not {}",
+                self.request_id,
+                self.action.describe(),
+                action.describe()
+            )));
+        }
+        if executor != self.requested_by {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "Request {} was approved for {}",
+                self.request_id, self.requested_by
+            )));
+        }
+        match self.status(now) {
+            ApprovalStatus::Approved => {
+                self.executed_at = Some(now);
+                Ok(())
+            }
+            status => Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "Request {} is {:?}, not approved",
+                self.request_id, status
+            ))),
+        }
+    }
+
+    // Whether every approval was signed by the key registered for the person
+    // accountable for it. Approvals the node signed for them don't verify.
+    pub fn verify(&self, approvers: &HashMap<String, String>) -> Result<bool> {
+        for approval in &self.approvals {
+            let person = approval.accountable();
+            let Some(public_key) = approvers.get(person) else {
+                return Ok(false);
+            };
+            let signature = hex::decode(&approval.signature)?;
+            if !self.signed_by(person, public_key, &signature)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn signed_by(&self, person: &str, public_key: &str, signature: &[u8]) -> Result<bool> {
+        let statement = self.statement(person)?;
+        Ok(UnparsedPublicKey::new(&ED25519, hex::decode(public_key)?)
+            .verify(&statement, signature)
+            .is_ok())
+    }
+
+    fn requester(&self) -> &str {
+        self.requester_issued_by
+            .as_deref()
+            .unwrap_or(&self.requested_by)
+    }
+
+    // Requesters can't approve their own request, nor with keys they issued or
+    // that were issued by whoever issued theirs, and nobody can once it has
+    // been decided or has lapsed. API keys whose issuer isn't recorded can't
+    // approve, since they can't be told apart from their issuer's other keys.
+    fn check_approver(&self, approver: &Principal, now: u64) -> Result<Role> {
+        let status = self.status(now);
+        if !matches!(status, ApprovalStatus::Pending | ApprovalStatus::Approved) {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "Request {} is {:?}",
+                self.request_id, status
+            )));
+        }
+        if approver.subject == self.requested_by || approver.accountable() == self.requester() {
+            return Err(ImmutableEncryptionError::PermissionDenied(
+                "Requesters can't approve their own request".to_string(),
+            ));
+        }
+        if approver.subject.starts_with(api_keys::SUBJECT_PREFIX) && approver.issued_by.is_none() {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "{} has no recorded issuer; rotate it before approving",
+                approver.subject
+            )));
+        }
+        approver
+            .roles
+            .iter()
+            .copied()
+            .find(|role| self.roles.contains(role))
+            .ok_or_else(|| {
+                ImmutableEncryptionError::PermissionDenied(format!(
+                    "{} lacks any of the roles {:?}",
+                    approver.subject, self.roles
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use sha2::{Digest, Sha256};
+
+    fn principal(subject: &str, role: Role) -> Principal {
+        Principal {
+            subject: subject.to_string(),
+            roles: vec![role],
+            tenant: "default".to_string(),
+            source: None,
+            issued_by: None,
+        }
+    }
+
+    fn api_key(key_id: &str, issued_by: Option<&str>) -> Principal {
+        Principal {
+            issued_by: issued_by.map(str::to_string),
+            ..principal(&format!("apikey:{}", key_id), Role::Prosecutor)
+        }
+    }
+
+    // Each person's own key, as they would hold it
+    fn key_of(person: &str) -> Ed25519KeyPair {
+        let seed = Sha256::digest(person.as_bytes());
+        Ed25519KeyPair::from_seed_unchecked(&seed).unwrap()
+    }
+
+    fn with_approvers(config: ApprovalConfig, people: &[&str]) -> ApprovalConfig {
+        let approvers = people
+            .iter()
+            .map(|person| {
+                let public_key = hex::encode(key_of(person).public_key().as_ref());
+                (person.to_string(), public_key)
+            })
+            .collect();
+        ApprovalConfig {
+            approvers,
+            ..config
+        }
+    }
+
+    // Approves as `approver`, signed by the person accountable for it
+    fn approve(
+        request: &mut ApprovalRequest,
+        approver: &Principal,
+        now: u64,
+        config: &ApprovalConfig,
+    ) -> Result<()> {
+        let person = approver.accountable();
+        let signature = key_of(person).sign(&request.statement(person)?);
+        request.approve(approver, now, signature.as_ref(), config)
+    }
+
+    #[test]
+    fn test_two_approvals_release_a_decryption_once() -> Result<()> {
+        let config = with_approvers(
+            ApprovalConfig {
+                decrypt: Some(ApprovalPolicy {
+                    required: 2,
+                    roles: vec![Role::Prosecutor, Role::Admin],
+                }),
+                ..ApprovalConfig::default()
+            },
+            &[
+                "detective-7",
+                "prosecutor-2",
+                "admin-1",
+                "auditor-1",
+                "p",
+                "q",
+            ],
+        );
+        let action = ApprovalAction::Decrypt {
+            evidence_id: "session-1".to_string(),
+        };
+        let purge = ApprovalAction::Purge {
+            evidence_id: "session-1".to_string(),
+        };
+        let requester = principal("detective-7", Role::Operator);
+        assert!(ApprovalRequest::new(
+            "r0".into(),
+            purge.clone(),
+            "".into(),
+            &requester,
+            &config,
+            0
+        )
+        .is_err());
+
+        let mut request = ApprovalRequest::new(
+            "r1".to_string(),
+            action.clone(),
+            "Defence disclosure".to_string(),
+            &requester,
+            &config,
+            1_000,
+        )?;
+        assert_eq!(request.status(1_000), ApprovalStatus::Pending);
+
+        // Not by the requester, a role outside the policy, or twice
+        let prosecutor = principal("prosecutor-2", Role::Prosecutor);
+        assert!(approve(
+            &mut request,
+            &principal("detective-7", Role::Prosecutor),
+            1_001,
+            &config
+        )
+        .is_err());
+        assert!(approve(
+            &mut request,
+            &principal("auditor-1", Role::Auditor),
+            1_001,
+            &config
+        )
+        .is_err());
+        approve(&mut request, &prosecutor, 1_002, &config)?;
+        assert!(approve(&mut request, &prosecutor, 1_003, &config).is_err());
+        assert!(request.execute(&action, "detective-7", 1_003).is_err());
+
+        // Only with the approver's own key: not someone else's, and not
+        // without one registered
+        let admin = principal("admin-1", Role::Admin);
+        let forged = key_of("prosecutor-2").sign(&request.statement("admin-1")?);
+        assert!(request
+            .approve(&admin, 1_004, forged.as_ref(), &config)
+            .is_err());
+        let unregistered = principal("admin-9", Role::Admin);
+        assert!(approve(&mut request, &unregistered, 1_004, &config).is_err());
+
+        approve(&mut request, &admin, 1_004, &config)?;
+        assert_eq!(request.status(1_004), ApprovalStatus::Approved);
+        assert!(request.verify(&config.approvers)?);
+
+        // Only the approved action, only by the requester, only once
+        assert!(request.execute(&purge, "detective-7", 1_005).is_err());
+        assert!(request.execute(&action, "admin-1", 1_005).is_err());
+        request.execute(&action, "detective-7", 1_005)?;
+        assert_eq!(request.status(1_005), ApprovalStatus::Executed);
+        assert!(request.execute(&action, "detective-7", 1_006).is_err());
+
+        // Tampering with the trail breaks the signatures
+        let mut tampered = request.clone();
+        tampered.reason = "Something else".to_string();
+        assert!(!tampered.verify(&config.approvers)?);
+        request.approvals[0].approver = "someone-else".to_string();
+        assert!(!request.verify(&config.approvers)?);
+
+        // Unexecuted requests lapse
+        let a = principal("a", Role::Prosecutor);
+        let mut late =
+            ApprovalRequest::new("r2".into(), action.clone(), "".into(), &a, &config, 0)?;
+        approve(&mut late, &principal("p", Role::Prosecutor), 1, &config)?;
+        approve(&mut late, &principal("q", Role::Admin), 2, &config)?;
+        assert_eq!(late.status(config.expiry_secs), ApprovalStatus::Expired);
+        assert!(late.execute(&action, "a", config.expiry_secs).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_api_key_approvals_count_for_their_issuer() -> Result<()> {
+        let config = with_approvers(
+            ApprovalConfig {
+                purge: Some(ApprovalPolicy {
+                    required: 2,
+                    roles: vec![Role::Prosecutor],
+                }),
+                ..ApprovalConfig::default()
+            },
+            &["detective-7", "admin-1", "prosecutor-2"],
+        );
+        let action = ApprovalAction::Purge {
+            evidence_id: "session-1".to_string(),
+        };
+        let requester = principal("detective-7", Role::Operator);
+        let mut request =
+            ApprovalRequest::new("r1".into(), action, "".into(), &requester, &config, 1_000)?;
+
+        // Keys the requester issued, or whose issuer isn't known, can't approve
+        assert!(approve(
+            &mut request,
+            &api_key("k0", Some("detective-7")),
+            1_001,
+            &config
+        )
+        .is_err());
+        assert!(approve(&mut request, &api_key("k1", None), 1_001, &config).is_err());
+
+        // One admin minting several keys still counts once, signed by the admin
+        approve(
+            &mut request,
+            &api_key("k2", Some("admin-1")),
+            1_002,
+            &config,
+        )?;
+        assert!(approve(
+            &mut request,
+            &api_key("k3", Some("admin-1")),
+            1_003,
+            &config
+        )
+        .is_err());
+        assert_eq!(request.status(1_003), ApprovalStatus::Pending);
+
+        approve(
+            &mut request,
+            &principal("prosecutor-2", Role::Prosecutor),
+            1_004,
+            &config,
+        )?;
+        assert_eq!(request.status(1_004), ApprovalStatus::Approved);
+        assert!(request.verify(&config.approvers)?);
+
+        // The issuer is who signed, so naming another breaks it
+        request.approvals[0].issued_by = Some("prosecutor-2".to_string());
+        assert!(!request.verify(&config.approvers)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::blockchain::MultiChainAnchor;
+use crate::crypto::{self, EncryptionEngine};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::storage::DistributedStorage;
+use crate::{BlockchainAnchor, FrameMetadata};
+
+// Records are keyed by zero-padded index so a prefix scan returns them in order
+const RECORD_PREFIX: &str = "audit:";
+const ANCHOR_PREFIX: &str = "audit_anchor:";
+const TIP_KEY: &str = "audit_tip";
+const CONFIG_KEY: &str = "audit_config";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    pub anchor_interval_secs: u64, // 0 disables anchoring the log
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            anchor_interval_secs: 3600,
+        }
+    }
+}
+
+// One audited action. Each record commits to its predecessor's hash and is
+// signed by the node, so editing, dropping or reordering records breaks the
+// chain from that point on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub index: u64,
+    pub timestamp: u64,
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>, // evidence, frame range, key epoch, ...
+    pub source: Option<String>, // client address, or "cli" for offline commands
+    pub previous_hash: String,
+    pub hash: String,
+    pub signature: String,
+}
+
+impl AuditRecord {
+    fn digest(&self) -> Result<String> {
+        let fields = (
+            self.index,
+            self.timestamp,
+            &self.actor,
+            &self.action,
+            &self.target,
+            &self.source,
+            &self.previous_hash,
+        );
+        Ok(crypto::sha256_hex(&serde_json::to_vec(&fields)?))
+    }
+}
+
+// The log's tip as of an anchoring, with the transactions that hold it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditAnchor {
+    pub index: u64,
+    pub hash: String,
+    pub anchored_at: u64,
+    pub anchors: Vec<BlockchainAnchor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditVerification {
+    pub records: u64,
+    pub is_valid: bool,
+    pub first_invalid: Option<u64>,
+    pub reason: Option<String>,
+    pub last_anchor: Option<AuditAnchor>,
+}
+
+// Signed, so the log can't be cut short by rewriting the tip to match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Tip {
+    index: u64,
+    hash: String,
+    #[serde(default)]
+    signature: String,
+}
+
+impl Tip {
+    fn content(&self) -> String {
+        format!("audit_tip:{}:{}", self.index, self.hash)
+    }
+}
+
+// Append-only log of who did what, when and from where: decryptions,
+// exports, key rotations, config changes and admin actions
+pub struct AuditLog {
+    storage: Arc<DistributedStorage>,
+    engine: Arc<Mutex<EncryptionEngine>>,
+    tip: Mutex<()>, // serializes appends so indexes and links stay unique
+}
+
+impl AuditLog {
+    pub fn new(storage: Arc<DistributedStorage>, engine: Arc<Mutex<EncryptionEngine>>) -> Self {
+        Self {
+            storage,
+            engine,
+            tip: Mutex::new(()),
+        }
+    }
+
+    pub async fn record(
+        &self,
+        actor: &str,
+        source: Option<&str>,
+        action: &str,
+        target: Option<&str>,
+    ) -> Result<AuditRecord> {
+        let _appending = self.tip.lock().await;
+        let tip: Option<Tip> = self.storage.get_record(TIP_KEY).await?;
+
+        let mut record = AuditRecord {
+            index: tip.as_ref().map_or(0, |t| t.index + 1),
+            timestamp: now()?,
+            actor: actor.to_string(),
+            action: action.to_string(),
+            target: target.map(str::to_string),
+            source: source.map(str::to_string),
+            previous_hash: tip.map_or_else(|| "0".repeat(64), |t| t.hash),
+            hash: String::new(),
+            signature: String::new(),
+        };
+        record.hash = record.digest()?;
+        let mut tip = Tip {
+            index: record.index,
+            hash: record.hash.clone(),
+            signature: String::new(),
+        };
+        {
+            let engine = self.engine.lock().await;
+            record.signature = engine.sign(record.hash.as_bytes());
+            tip.signature = engine.sign(tip.content().as_bytes());
+        }
+
+        self.storage
+            .put_record(&record_key(record.index), &record)
+            .await?;
+        self.storage.put_record(TIP_KEY, &tip).await?;
+        Ok(record)
+    }
+
+    // Records a config change when the fingerprint differs from the one last
+    // seen; returns whether it did
+    pub async fn record_config(&self, fingerprint: &str) -> Result<bool> {
+        let last: Option<String> = self.storage.get_record(CONFIG_KEY).await?;
+        if last.as_deref() == Some(fingerprint) {
+            return Ok(false);
+        }
+        self.record("node", None, "config_change", Some(fingerprint))
+            .await?;
+        self.storage.put_record(CONFIG_KEY, &fingerprint).await?;
+        Ok(true)
+    }
+
+    pub async fn records(&self) -> Result<Vec<AuditRecord>> {
+        Ok(self
+            .storage
+            .scan_records(RECORD_PREFIX)
+            .await?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect())
+    }
+
+    // Re-derives every hash and link, checks every signature, and checks the
+    // signed tip and each anchor against the records they name
+    pub async fn verify(&self) -> Result<AuditVerification> {
+        let records = self.records().await?;
+        let engine = self.engine.lock().await;
+
+        let mut failure = None;
+        let mut previous = "0".repeat(64);
+        for (expected, record) in records.iter().enumerate() {
+            let reason = if record.index != expected as u64 {
+                Some(format!("expected record {}", expected))
+            } else if record.previous_hash != previous {
+                Some("does not link to the previous record".to_string())
+            } else if record.digest()? != record.hash {
+                Some("hash does not match its contents".to_string())
+            } else if !engine.verify_signature(record.hash.as_bytes(), &record.signature) {
+                Some("signature check failed".to_string())
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                failure = Some((record.index, reason));
+                break;
+            }
+            previous = record.hash.clone();
+        }
+        // A log cut short still chains; the tip says how long it should be
+        if failure.is_none() {
+            if let Some(tip) = self.storage.get_record::<Tip>(TIP_KEY).await? {
+                if !engine.verify_signature(tip.content().as_bytes(), &tip.signature) {
+                    failure = Some((tip.index, "the log tip is not signed".to_string()));
+                } else if tip.index + 1 != records.len() as u64 {
+                    failure = Some((
+                        records.len() as u64,
+                        "missing; the log ends early".to_string(),
+                    ));
+                } else if records[tip.index as usize].hash != tip.hash {
+                    failure = Some((tip.index, "does not match the log tip".to_string()));
+                }
+            }
+        }
+
+        // An anchored tip must still be in the log as it was anchored
+        let mut anchors: Vec<(String, AuditAnchor)> =
+            self.storage.scan_records(ANCHOR_PREFIX).await?;
+        if failure.is_none() {
+            failure =
+                anchors
+                    .iter()
+                    .find_map(|(_, anchor)| match records.get(anchor.index as usize) {
+                        Some(record) if record.hash == anchor.hash => None,
+                        Some(_) => Some((anchor.index, "does not match its anchor".to_string())),
+                        None => Some((anchor.index, "anchored but missing".to_string())),
+                    });
+        }
+        let last_anchor = anchors.pop().map(|(_, anchor)| anchor);
+        Ok(AuditVerification {
+            records: records.len() as u64,
+            is_valid: failure.is_none(),
+            first_invalid: failure.as_ref().map(|(index, _)| *index),
+            reason: failure.map(|(index, reason)| format!("record {}: {}", index, reason)),
+            last_anchor,
+        })
+    }
+
+    // Anchors the current tip unless it is already anchored or the log is
+    // empty
+    pub async fn anchor_tip(&self, blockchain: &MultiChainAnchor) -> Result<Option<AuditAnchor>> {
+        let tip: Tip = match self.storage.get_record(TIP_KEY).await? {
+            Some(tip) => tip,
+            None => return Ok(None),
+        };
+        let key = anchor_key(tip.index);
+        if self
+            .storage
+            .get_record::<AuditAnchor>(&key)
+            .await?
+            .is_some()
+        {
+            return Ok(None);
+        }
+
+        let metadata = FrameMetadata {
+            device_id: "audit-log".to_string(),
+            location: None,
+            resolution: (0, 0),
+            fps: 0,
+            codec: "audit".to_string(),
+            telemetry: None,
+        };
+        let anchors = blockchain
+            .anchor_to_all_chains(&tip.hash, &metadata)
+            .await?;
+        if anchors.is_empty() {
+            return Err(ImmutableEncryptionError::blockchain(
+                "No chain accepted the audit log anchor",
+            ));
+        }
+
+        let anchor = AuditAnchor {
+            index: tip.index,
+            hash: tip.hash,
+            anchored_at: now()?,
+            anchors,
+        };
+        self.storage.put_record(&key, &anchor).await?;
+        Ok(Some(anchor))
+    }
+}
+
+fn record_key(index: u64) -> String {
+    format!("{}{:020}", RECORD_PREFIX, index)
+}
+
+fn anchor_key(index: u64) -> String {
+    format!("{}{:020}", ANCHOR_PREFIX, index)
+}
+
+fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+    use crate::storage::StorageConfig;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_audit_log_chains_and_detects_edits() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage = Arc::new(
+            DistributedStorage::new(StorageConfig {
+                database_path: temp_dir.path().to_string_lossy().to_string(),
+                ipfs_enabled: false,
+                ipfs_api_url: String::new(),
+                backup_enabled: false,
+                backup_path: String::new(),
+                compression_enabled: false,
+                frame_encoding: Default::default(),
+                backup_io_uring: false,
+            })
+            .await?,
+        );
+        let engine = Arc::new(Mutex::new(EncryptionEngine::new(CryptoConfig::software(
+            vec![5u8; 32],
+            1,
+        ))?));
+        let log = AuditLog::new(storage.clone(), engine);
+
+        log.record(
+            "det. smith",
+            Some("10.0.0.7"),
+            "decrypt",
+            Some("cam_1:1-10"),
+        )
+        .await?;
+        assert!(log.record_config("abc").await?);
+        assert!(!log.record_config("abc").await?);
+        let last = log
+            .record("admin", None, "rotate_keys", Some("epoch:2"))
+            .await?;
+        assert_eq!(last.index, 2);
+
+        let verification = log.verify().await?;
+        assert!(verification.is_valid);
+        assert_eq!(verification.records, 3);
+
+        let mut edited: AuditRecord = storage.get_record(&record_key(1)).await?.unwrap();
+        edited.actor = "someone else".to_string();
+        storage.put_record(&record_key(1), &edited).await?;
+        let verification = log.verify().await?;
+        assert!(!verification.is_valid);
+        assert_eq!(verification.first_invalid, Some(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_checks_tip_and_anchors() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage = Arc::new(
+            DistributedStorage::new(StorageConfig {
+                database_path: temp_dir.path().to_string_lossy().to_string(),
+                ipfs_enabled: false,
+                ipfs_api_url: String::new(),
+                backup_enabled: false,
+                backup_path: String::new(),
+                compression_enabled: false,
+                frame_encoding: Default::default(),
+                backup_io_uring: false,
+            })
+            .await?,
+        );
+        let engine = Arc::new(Mutex::new(EncryptionEngine::new(CryptoConfig::software(
+            vec![5u8; 32],
+            1,
+        ))?));
+        let log = AuditLog::new(storage.clone(), engine);
+        for action in ["decrypt", "export", "rotate_keys"] {
+            log.record("admin", None, action, None).await?;
+        }
+
+        // An anchor over a record that has since been rewritten and re-chained
+        let first: AuditRecord = storage.get_record(&record_key(0)).await?.unwrap();
+        let anchor = AuditAnchor {
+            index: 0,
+            hash: "f".repeat(64),
+            anchored_at: first.timestamp,
+            anchors: Vec::new(),
+        };
+        storage.put_record(&anchor_key(0), &anchor).await?;
+        let verification = log.verify().await?;
+        assert_eq!(verification.first_invalid, Some(0));
+        storage
+            .put_record(
+                &anchor_key(0),
+                &AuditAnchor {
+                    hash: first.hash,
+                    ..anchor
+                },
+            )
+            .await?;
+        assert!(log.verify().await?.is_valid);
+
+        // Dropping the last record and moving the tip back needs its signature
+        let mut tip: Tip = storage.get_record(TIP_KEY).await?.unwrap();
+        let second: AuditRecord = storage.get_record(&record_key(1)).await?.unwrap();
+        storage.delete_record(&record_key(2)).await?;
+        tip.index = 1;
+        tip.hash = second.hash;
+        storage.put_record(TIP_KEY, &tip).await?;
+        let verification = log.verify().await?;
+        assert!(!verification.is_valid);
+        assert_eq!(verification.first_invalid, Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api_keys::ApiKeyManager;
+use crate::error::ImmutableEncryptionError;
+use crate::tenant::DEFAULT_TENANT;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Operator,   // runs capture: sessions, ingestion, renditions
+    Auditor,    // verifies evidence and produces court reports
+    Prosecutor, // reviews evidence for a case
+    Admin,      // node administration, e.g. key rotation
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    pub algorithm: String, // "HS256" or "RS256"
+    pub hmac_secret: Option<String>,
+    pub public_key_path: Option<String>, // PEM, for RS256
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub leeway_seconds: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            algorithm: "HS256".to_string(),
+            hmac_secret: None,
+            public_key_path: None,
+            issuer: None,
+            audience: None,
+            leeway_seconds: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: u64,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    #[serde(default)]
+    pub tenant: Option<String>, // agency the caller belongs to; default tenant if absent
+}
+
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub subject: String,
+    pub roles: Vec<Role>,
+    pub tenant: String,         // roles only apply within this tenant's evidence
+    pub source: Option<String>, // client address, recorded in the audit log
+    pub issued_by: Option<String>, // for API keys, the person who issued the key
+}
+
+impl Principal {
+    // Stand-in identity when authentication is disabled
+    pub fn anonymous() -> Self {
+        Self {
+            subject: "anonymous".to_string(),
+            roles: vec![Role::Operator, Role::Auditor, Role::Prosecutor, Role::Admin],
+            tenant: DEFAULT_TENANT.to_string(),
+            source: None,
+            issued_by: None,
+        }
+    }
+
+    // Who answers for this principal: the person behind a token, or whoever
+    // issued an API key
+    pub fn accountable(&self) -> &str {
+        self.issued_by.as_deref().unwrap_or(&self.subject)
+    }
+
+    pub fn require_any(&self, allowed: &[Role]) -> Result<(), ImmutableEncryptionError> {
+        if self.roles.iter().any(|role| allowed.contains(role)) {
+            Ok(())
+        } else {
+            Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "{} lacks any of the roles {:?}",
+                self.subject, allowed
+            )))
+        }
+    }
+}
+
+pub struct JwtAuthenticator {
+    enabled: bool,
+    key: Option<DecodingKey>,
+    validation: Validation,
+}
+
+impl JwtAuthenticator {
+    pub fn new(config: &AuthConfig) -> Result<Self, ImmutableEncryptionError> {
+        let algorithm = match config.algorithm.as_str() {
+            "HS256" => Algorithm::HS256,
+            "RS256" => Algorithm::RS256,
+            other => {
+                return Err(ImmutableEncryptionError::config(&format!(
+                    "unsupported JWT algorithm {}",
+                    other
+                )))
+            }
+        };
+
+        let key = if !config.enabled {
+            None
+        } else if algorithm == Algorithm::HS256 {
+            let secret = config.hmac_secret.as_ref().ok_or_else(|| {
+                ImmutableEncryptionError::config("auth.hmac_secret is required for HS256")
+            })?;
+            Some(DecodingKey::from_secret(secret.as_bytes()))
+        } else {
+            let path = config.public_key_path.as_ref().ok_or_else(|| {
+                ImmutableEncryptionError::config("auth.public_key_path is required for RS256")
+            })?;
+            let pem = std::fs::read(path)?;
+            Some(DecodingKey::from_rsa_pem(&pem).map_err(|e| {
+                ImmutableEncryptionError::config(&format!("invalid JWT public key: {}", e))
+            })?)
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = config.leeway_seconds;
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        Ok(Self {
+            enabled: config.enabled,
+            key,
+            validation,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Resolves the caller from an `Authorization` header value
+    pub fn authenticate(
+        &self,
+        authorization: Option<&str>,
+    ) -> Result<Principal, ImmutableEncryptionError> {
+        let key = match &self.key {
+            Some(key) => key,
+            None => return Ok(Principal::anonymous()),
+        };
+
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| {
+                ImmutableEncryptionError::PermissionDenied("missing bearer token".to_string())
+            })?;
+
+        let claims = decode::<Claims>(token.trim(), key, &self.validation)
+            .map_err(|e| {
+                ImmutableEncryptionError::PermissionDenied(format!("invalid token: {}", e))
+            })?
+            .claims;
+
+        Ok(Principal {
+            subject: claims.sub,
+            roles: claims.roles,
+            tenant: claims.tenant.unwrap_or_else(|| DEFAULT_TENANT.to_string()),
+            source: None,
+            issued_by: None,
+        })
+    }
+}
+
+// Machine integrations present an API key; people present a JWT
+pub struct RequestAuthenticator {
+    jwt: JwtAuthenticator,
+    api_keys: Arc<ApiKeyManager>,
+}
+
+impl RequestAuthenticator {
+    pub fn new(jwt: JwtAuthenticator, api_keys: Arc<ApiKeyManager>) -> Self {
+        Self { jwt, api_keys }
+    }
+
+    pub fn jwt_enabled(&self) -> bool {
+        self.jwt.is_enabled()
+    }
+
+    pub fn api_keys(&self) -> &Arc<ApiKeyManager> {
+        &self.api_keys
+    }
+
+    // `action` is recorded in the API key's usage log, e.g. "GET /verify/abc"
+    pub async fn authenticate(
+        &self,
+        authorization: Option<&str>,
+        api_key: Option<&str>,
+        action: &str,
+    ) -> Result<Principal, ImmutableEncryptionError> {
+        match api_key {
+            Some(api_key) => self.api_keys.authenticate(api_key, action).await,
+            None => self.jwt.authenticate(authorization),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    #[test]
+    fn test_jwt_roles_enforced() -> Result<(), ImmutableEncryptionError> {
+        let config = AuthConfig {
+            enabled: true,
+            hmac_secret: Some("test-secret".to_string()),
+            ..Default::default()
+        };
+        let authenticator = JwtAuthenticator::new(&config)?;
+
+        let claims = Claims {
+            sub: "auditor@example.org".to_string(),
+            exp: 4_102_444_800, // 2100-01-01
+            roles: vec![Role::Auditor],
+            tenant: Some("metro-pd".to_string()),
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(b"test-secret"),
+        )
+        .unwrap();
+
+        let principal = authenticator.authenticate(Some(&format!("Bearer {}", token)))?;
+        assert_eq!(principal.subject, "auditor@example.org");
+        assert_eq!(principal.tenant, "metro-pd");
+        assert!(principal.require_any(&[Role::Auditor]).is_ok());
+        assert!(principal.require_any(&[Role::Admin]).is_err());
+
+        assert!(authenticator.authenticate(None).is_err());
+        assert!(authenticator
+            .authenticate(Some("Bearer not-a-jwt"))
+            .is_err());
+
+        Ok(())
+    }
+}
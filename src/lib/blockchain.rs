@@ -1,12 +1,15 @@
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use bitcoin::{Address, Network, Txid};
 use ethers::prelude::*;
 use hex;
+use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use std::time::Duration;
 use tokio::time::sleep;
 
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::metrics::{self, Module};
+use crate::retry::RetryPolicy;
 use crate::{BlockchainAnchor, FrameMetadata};
 
 #[derive(Debug, Clone)]
@@ -17,6 +20,75 @@ pub struct BlockchainConfig {
     pub opentimestamps_url: String,
 }
 
+// An anchor transaction with one P2WPKH input, change and a 32-byte OP_RETURN
+pub const BITCOIN_ANCHOR_VBYTES: u64 = 153;
+
+// An anchoring contract call that stores one 32-byte hash
+pub const ETHEREUM_ANCHOR_GAS: u64 = 50_000;
+
+// Bitcoin fee estimate used for quotes, in blocks until confirmation
+const BITCOIN_FEE_TARGET_BLOCKS: &str = "6";
+
+// Current price of one anchor transaction. A Merkle batch is also a single
+// transaction, so this is the cost of a batch of any size as well.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeQuote {
+    pub chain: String,
+    pub fee_rate: f64,
+    pub fee_rate_unit: String, // sat/vB on Bitcoin, gwei on EVM chains
+    pub units_per_anchor: u64, // vbytes or gas
+    pub cost_per_anchor: f64,  // in `coin`
+    pub coin: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InclusionState {
+    Pending,  // known to the network but not in a block
+    Included, // in a block on the current best chain
+    Missing,  // unknown to the node queried
+}
+
+// What a chain reported about an anchor transaction at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorObservation {
+    pub chain: String,
+    pub transaction_hash: String,
+    pub checked_at: u64,
+    pub state: InclusionState,
+    pub block_number: Option<u64>,
+    pub block_hash: Option<String>,
+    pub confirmations: u64,
+    #[serde(default)]
+    pub reorged: bool, // set against earlier observations by `AnchorHistory`
+}
+
+impl AnchorObservation {
+    fn new(chain: &str, transaction_hash: &str, state: InclusionState) -> Self {
+        Self {
+            chain: chain.to_string(),
+            transaction_hash: transaction_hash.to_string(),
+            checked_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            state,
+            block_number: None,
+            block_hash: None,
+            confirmations: 0,
+            reorged: false,
+        }
+    }
+
+    fn included(mut self, block_number: u64, block_hash: Option<String>, tip: u64) -> Self {
+        self.state = InclusionState::Included;
+        self.block_number = Some(block_number);
+        self.block_hash = block_hash;
+        self.confirmations = tip.saturating_sub(block_number) + 1;
+        self
+    }
+}
+
 pub struct BitcoinAnchor {
     client: reqwest::Client,
     config: BlockchainConfig,
@@ -47,7 +119,8 @@ impl BitcoinAnchor {
         );
 
         // Create mock transaction hash
-        let mock_txid = Txid::from_slice(&[1u8; 32])?;
+        let mock_txid = Txid::from_slice(&[1u8; 32])
+            .map_err(|e| ImmutableEncryptionError::Blockchain(e.to_string()))?;
 
         // In reality, this would broadcast to Bitcoin network
         println!(
@@ -106,13 +179,20 @@ pub struct EthereumAnchor {
 
 impl EthereumAnchor {
     pub async fn new(config: BlockchainConfig) -> Result<Self> {
-        let provider = Provider::<Http>::try_from(&config.ethereum_rpc_url)?;
+        let provider = Provider::<Http>::try_from(&config.ethereum_rpc_url).map_err(|e| {
+            ImmutableEncryptionError::Config(format!(
+                "Invalid Ethereum RPC URL {}: {}",
+                config.ethereum_rpc_url, e
+            ))
+        })?;
         Ok(Self { provider, config })
     }
 
     async fn deploy_smart_contract(&self) -> Result<Address> {
         // Simplified - would deploy actual verification contract
-        Ok("0x1234567890123456789012345678901234567890".parse()?)
+        "0x1234567890123456789012345678901234567890"
+            .parse()
+            .map_err(|e| ImmutableEncryptionError::Blockchain(format!("Invalid address: {}", e)))
     }
 
     async fn call_anchor_function(&self, contract_address: Address, hash: &str) -> Result<TxHash> {
@@ -137,7 +217,7 @@ impl crate::BlockchainAnchor for EthereumAnchor {
             .provider
             .get_transaction_receipt(tx_hash)
             .await?
-            .ok_or_else(|| anyhow!("Transaction receipt not found"))?;
+            .ok_or_else(|| ImmutableEncryptionError::blockchain("Transaction receipt not found"))?;
 
         Ok(BlockchainAnchor {
             chain: "ethereum".to_string(),
@@ -146,9 +226,10 @@ impl crate::BlockchainAnchor for EthereumAnchor {
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)?
                 .as_secs(),
+            // Full hex, matching transaction_hash; H256's Display abbreviates
             proof: format!(
                 "ethereum-proof:{}:{}",
-                tx_hash,
+                hex::encode(tx_hash.as_bytes()),
                 receipt.block_number.unwrap_or(0u64.into())
             ),
         })
@@ -169,14 +250,245 @@ impl crate::BlockchainAnchor for EthereumAnchor {
 pub struct MultiChainAnchor {
     bitcoin: BitcoinAnchor,
     ethereum: EthereumAnchor,
+    client: reqwest::Client,
+    config: BlockchainConfig,
+    retry: RetryPolicy,
 }
 
 impl MultiChainAnchor {
     pub async fn new(config: BlockchainConfig) -> Result<Self> {
         let bitcoin = BitcoinAnchor::new(config.clone());
-        let ethereum = EthereumAnchor::new(config).await?;
+        let ethereum = EthereumAnchor::new(config.clone()).await?;
 
-        Ok(Self { bitcoin, ethereum })
+        Ok(Self {
+            bitcoin,
+            ethereum,
+            client: reqwest::Client::new(),
+            config,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    // Endpoints with a configured URL, as accepted by `probe`
+    pub fn probe_targets(&self) -> Vec<&'static str> {
+        [
+            ("ethereum", &self.config.ethereum_rpc_url),
+            ("bitcoin", &self.config.bitcoin_rpc_url),
+            ("private_chain", &self.config.private_chain_rpc),
+            ("opentimestamps", &self.config.opentimestamps_url),
+        ]
+        .into_iter()
+        .filter(|(_, url)| !url.is_empty())
+        .map(|(chain, _)| chain)
+        .collect()
+    }
+
+    // Cheapest read each endpoint offers: the chain tip, or the calendar's index
+    pub async fn probe(&self, target: &str) -> Result<()> {
+        match target {
+            "ethereum" => {
+                self.ethereum.provider.get_block_number().await?;
+            }
+            "bitcoin" => {
+                let url = format!("{}/blocks/tip/height", self.config.bitcoin_rpc_url);
+                self.client.get(&url).send().await?.error_for_status()?;
+            }
+            "private_chain" => {
+                let response: serde_json::Value = self
+                    .client
+                    .post(&self.config.private_chain_rpc)
+                    .json(&serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "method": "eth_blockNumber",
+                        "params": [],
+                        "id": 1
+                    }))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                if let Some(error) = response.get("error") {
+                    return Err(ImmutableEncryptionError::Blockchain(format!(
+                        "RPC error: {}",
+                        error
+                    )));
+                }
+            }
+            "opentimestamps" => {
+                self.client
+                    .get(&self.config.opentimestamps_url)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            other => {
+                return Err(ImmutableEncryptionError::Blockchain(format!(
+                    "Unknown probe target {}",
+                    other
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    // Chains `observe` can query
+    pub fn status_chains(&self) -> Vec<&'static str> {
+        ["ethereum", "bitcoin"]
+            .into_iter()
+            .filter(|chain| self.probe_targets().contains(chain))
+            .collect()
+    }
+
+    // Asks the chain where the transaction is now: its block, the block's
+    // hash and how deep it sits under the current tip
+    pub async fn observe(&self, chain: &str, transaction_hash: &str) -> Result<AnchorObservation> {
+        let observation = |state| AnchorObservation::new(chain, transaction_hash, state);
+        match chain {
+            "ethereum" => {
+                let bytes = hex::decode(transaction_hash.trim_start_matches("0x"))?;
+                if bytes.len() != 32 {
+                    return Err(ImmutableEncryptionError::blockchain(
+                        "Not an Ethereum transaction hash",
+                    ));
+                }
+                let tx_hash = TxHash::from_slice(&bytes);
+                let provider = &self.ethereum.provider;
+
+                let receipt = provider.get_transaction_receipt(tx_hash).await?;
+                match receipt.and_then(|r| Some((r.block_number?, r.block_hash))) {
+                    Some((block_number, block_hash)) => {
+                        let tip = provider.get_block_number().await?.as_u64();
+                        Ok(observation(InclusionState::Pending).included(
+                            block_number.as_u64(),
+                            block_hash.map(|h| hex::encode(h.as_bytes())),
+                            tip,
+                        ))
+                    }
+                    None if provider.get_transaction(tx_hash).await?.is_some() => {
+                        Ok(observation(InclusionState::Pending))
+                    }
+                    None => Ok(observation(InclusionState::Missing)),
+                }
+            }
+            "bitcoin" => {
+                // Esplora-style REST API, as probed through /blocks/tip/height
+                let base = &self.config.bitcoin_rpc_url;
+                let response = self
+                    .client
+                    .get(format!("{}/tx/{}/status", base, transaction_hash))
+                    .send()
+                    .await?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(observation(InclusionState::Missing));
+                }
+                let status: serde_json::Value = response.error_for_status()?.json().await?;
+                let block_number = match status["block_height"].as_u64() {
+                    Some(height) if status["confirmed"].as_bool() == Some(true) => height,
+                    _ => return Ok(observation(InclusionState::Pending)),
+                };
+
+                let tip: u64 = self
+                    .client
+                    .get(format!("{}/blocks/tip/height", base))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?
+                    .trim()
+                    .parse()?;
+                let block_hash = status["block_hash"].as_str().map(str::to_string);
+                Ok(observation(InclusionState::Pending).included(block_number, block_hash, tip))
+            }
+            other => Err(ImmutableEncryptionError::Blockchain(format!(
+                "Anchor status is not supported on {}",
+                other
+            ))),
+        }
+    }
+
+    // Prices an anchor from the chain's live fee market
+    pub async fn fee_quote(&self, chain: &str) -> Result<FeeQuote> {
+        let evm_quote = |chain: &str, coin: &str, gas_price: U256| {
+            let gwei = gas_price.as_u128() as f64 / 1e9;
+            FeeQuote {
+                chain: chain.to_string(),
+                fee_rate: gwei,
+                fee_rate_unit: "gwei".to_string(),
+                units_per_anchor: ETHEREUM_ANCHOR_GAS,
+                cost_per_anchor: gwei * ETHEREUM_ANCHOR_GAS as f64 / 1e9,
+                coin: coin.to_string(),
+            }
+        };
+
+        match chain {
+            "bitcoin" => {
+                let estimates: HashMap<String, f64> = self
+                    .client
+                    .get(format!("{}/fee-estimates", self.config.bitcoin_rpc_url))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let rate = estimates
+                    .get(BITCOIN_FEE_TARGET_BLOCKS)
+                    .or_else(|| estimates.values().min_by(|a, b| a.total_cmp(b)))
+                    .copied()
+                    .ok_or_else(|| {
+                        ImmutableEncryptionError::blockchain(
+                            "Bitcoin node returned no fee estimates",
+                        )
+                    })?;
+
+                Ok(FeeQuote {
+                    chain: chain.to_string(),
+                    fee_rate: rate,
+                    fee_rate_unit: "sat/vB".to_string(),
+                    units_per_anchor: BITCOIN_ANCHOR_VBYTES,
+                    cost_per_anchor: rate * BITCOIN_ANCHOR_VBYTES as f64 / 1e8,
+                    coin: "BTC".to_string(),
+                })
+            }
+            "ethereum" => {
+                let gas_price = self.ethereum.provider.get_gas_price().await?;
+                Ok(evm_quote(chain, "ETH", gas_price))
+            }
+            "private" => {
+                let response: serde_json::Value = self
+                    .client
+                    .post(&self.config.private_chain_rpc)
+                    .json(&serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "method": "eth_gasPrice",
+                        "params": [],
+                        "id": 1
+                    }))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let gas_price = response["result"].as_str().ok_or_else(|| {
+                    ImmutableEncryptionError::Blockchain(format!(
+                        "RPC error: {}",
+                        response["error"]
+                    ))
+                })?;
+                Ok(evm_quote(
+                    chain,
+                    "native",
+                    U256::from_str_radix(gas_price.trim_start_matches("0x"), 16).map_err(|e| {
+                        ImmutableEncryptionError::Blockchain(format!("Invalid gas price: {}", e))
+                    })?,
+                ))
+            }
+            other => Err(ImmutableEncryptionError::Blockchain(format!(
+                "Fee estimates are not supported on {}",
+                other
+            ))),
+        }
     }
 
     pub async fn anchor_to_all_chains(
@@ -184,15 +496,35 @@ impl MultiChainAnchor {
         hash: &str,
         metadata: &FrameMetadata,
     ) -> Result<Vec<BlockchainAnchor>> {
+        self.anchor_to_chains(hash, metadata, &[]).await
+    }
+
+    // Anchors to the named chains only; an empty list means all of them
+    pub async fn anchor_to_chains(
+        &self,
+        hash: &str,
+        metadata: &FrameMetadata,
+        chains: &[String],
+    ) -> Result<Vec<BlockchainAnchor>> {
+        let wanted = |chain: &str| chains.is_empty() || chains.iter().any(|c| c == chain);
         let mut anchors = Vec::new();
 
         // Anchor to Bitcoin
-        let bitcoin_anchor = self.bitcoin.anchor_hash(hash, metadata).await?;
-        anchors.push(bitcoin_anchor);
+        if wanted("bitcoin") {
+            let anchor = self.retry.run("Bitcoin anchor", || {
+                self.bitcoin.anchor_hash(hash, metadata)
+            });
+            anchors.push(metrics::timed_async(Module::Blockchain, "anchor_bitcoin", anchor).await?);
+        }
 
         // Anchor to Ethereum
-        let ethereum_anchor = self.ethereum.anchor_hash(hash, metadata).await?;
-        anchors.push(ethereum_anchor);
+        if wanted("ethereum") {
+            let anchor = self.retry.run("Ethereum anchor", || {
+                self.ethereum.anchor_hash(hash, metadata)
+            });
+            anchors
+                .push(metrics::timed_async(Module::Blockchain, "anchor_ethereum", anchor).await?);
+        }
 
         // Add more chains as needed
         Ok(anchors)
@@ -215,6 +547,28 @@ impl MultiChainAnchor {
 
         Ok(results)
     }
+
+    pub async fn confirmation_count(&self, anchor: &BlockchainAnchor) -> Result<u64> {
+        metrics::timed_async(Module::Blockchain, "confirmations", async {
+            match anchor.chain.as_str() {
+                "bitcoin" => {
+                    self.bitcoin
+                        .get_confirmation_count(&anchor.transaction_hash)
+                        .await
+                }
+                "ethereum" => {
+                    self.ethereum
+                        .get_confirmation_count(&anchor.transaction_hash)
+                        .await
+                }
+                other => Err(ImmutableEncryptionError::Blockchain(format!(
+                    "Unknown chain {}",
+                    other
+                ))),
+            }
+        })
+        .await
+    }
 }
 
 #[cfg(test)]
@@ -237,6 +591,7 @@ mod tests {
             resolution: (1920, 1080),
             fps: 30,
             codec: "H.264".to_string(),
+            telemetry: None,
         };
 
         let result = anchor.anchor_hash("test_hash_123", &metadata).await?;
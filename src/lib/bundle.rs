@@ -0,0 +1,484 @@
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::crypto::EncryptionEngine;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::session::SessionManifest;
+use crate::storage::DistributedStorage;
+use crate::verification::VerificationEngine;
+use crate::EncryptedFrame;
+
+pub const BUNDLE_CONTENT_TYPE: &str = "application/x-ndjson";
+
+// One JSON line of an evidence bundle: the signed manifest, every frame in
+// index order, then a node signature over the SHA-256 of all preceding bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BundleRecord {
+    Manifest { manifest: SessionManifest },
+    Frame { frame: EncryptedFrame },
+    Signature { sha256: String, signature: String },
+}
+
+// A sealed session's export, produced a line at a time. Serialization is
+// deterministic, so a byte range can be served by regenerating the bundle and
+// skipping to the requested offset.
+#[derive(Clone)]
+pub struct EvidenceBundle {
+    manifest: SessionManifest,
+    frame_keys: Vec<String>,
+    engine: Arc<Mutex<EncryptionEngine>>,
+    storage: Arc<DistributedStorage>,
+    digests: Arc<BundleDigests>,
+}
+
+// A bundle's size and the SHA-256 of all of it, its strong ETag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleDigest {
+    pub byte_len: u64,
+    pub sha256: String,
+}
+
+impl BundleDigest {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.sha256)
+    }
+}
+
+// Digests of bundles generated before, so range requests after the first
+// don't each take a pass to size the bundle. An entry holds until any of the
+// device's frames is stored or deleted.
+#[derive(Debug, Default)]
+pub struct BundleDigests {
+    entries: std::sync::Mutex<HashMap<String, (u64, BundleDigest)>>, // id -> (generation, digest)
+}
+
+struct BundleCursor {
+    next_frame: usize,
+    manifest_written: bool,
+    finished: bool,
+    offset: u64, // bytes produced so far, before range clipping
+    digest: Sha256,
+    sha256: Option<String>, // of the whole bundle, once finished
+}
+
+impl EvidenceBundle {
+    pub fn new(
+        manifest: SessionManifest,
+        frame_keys: Vec<String>,
+        engine: Arc<Mutex<EncryptionEngine>>,
+        storage: Arc<DistributedStorage>,
+        digests: Arc<BundleDigests>,
+    ) -> Self {
+        Self {
+            manifest,
+            frame_keys,
+            engine,
+            storage,
+            digests,
+        }
+    }
+
+    pub fn evidence_id(&self) -> &str {
+        &self.manifest.session_id
+    }
+
+    pub fn manifest(&self) -> &SessionManifest {
+        &self.manifest
+    }
+
+    // The digest, if the bundle was generated in full since its frames last
+    // changed
+    pub fn cached_digest(&self) -> Option<BundleDigest> {
+        let generation = self.storage.frame_generation(&self.manifest.device_id);
+        let entries = self
+            .digests
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(self.evidence_id())
+            .filter(|(cached, _)| *cached == generation)
+            .map(|(_, digest)| digest.clone())
+    }
+
+    // Exact size and digest of the bundle; unless cached, reads every frame
+    // once without keeping it
+    pub async fn digest(&self) -> Result<BundleDigest> {
+        if let Some(digest) = self.cached_digest() {
+            return Ok(digest);
+        }
+        let generation = self.storage.frame_generation(&self.manifest.device_id);
+        let mut cursor = BundleCursor::new();
+        while self.next_line(&mut cursor).await?.is_some() {}
+        Ok(self.remember(&cursor, generation))
+    }
+
+    // Caches a finished pass, unless a frame changed while it ran
+    fn remember(&self, cursor: &BundleCursor, generation: u64) -> BundleDigest {
+        let digest = BundleDigest {
+            byte_len: cursor.offset,
+            sha256: cursor.sha256.clone().unwrap_or_default(),
+        };
+        if self.storage.frame_generation(&self.manifest.device_id) == generation {
+            self.digests
+                .entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(self.evidence_id().to_string(), (generation, digest.clone()));
+        }
+        digest
+    }
+
+    // Bytes `start..=end` of the bundle, or all of it when `range` is None.
+    // A stream that reaches the end caches the bundle's digest.
+    pub fn stream(
+        self,
+        range: Option<(u64, u64)>,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + Send + 'static {
+        let (start, end) = range.unwrap_or((0, u64::MAX));
+        let generation = self.storage.frame_generation(&self.manifest.device_id);
+        let bundle = Arc::new(self);
+
+        stream::unfold(BundleCursor::new(), move |mut cursor| {
+            let bundle = bundle.clone();
+            async move {
+                loop {
+                    if cursor.finished || cursor.offset > end {
+                        return None;
+                    }
+
+                    let line_start = cursor.offset;
+                    let line = match bundle.next_line(&mut cursor).await {
+                        Ok(Some(line)) => line,
+                        Ok(None) => return None,
+                        Err(e) => {
+                            cursor.finished = true;
+                            return Some((Err(e), cursor));
+                        }
+                    };
+                    if cursor.finished {
+                        bundle.remember(&cursor, generation);
+                    }
+
+                    let line_end = line_start + line.len() as u64; // exclusive
+                    if line_end <= start {
+                        continue;
+                    }
+                    let from = start.saturating_sub(line_start) as usize;
+                    let to = (end.saturating_add(1).min(line_end) - line_start) as usize;
+                    return Some((Ok(line[from..to].to_vec()), cursor));
+                }
+            }
+        })
+    }
+
+    // Each frame in the bundle alongside its decrypted payload
+    pub fn media(self) -> impl Stream<Item = Result<(EncryptedFrame, Vec<u8>)>> + Send + 'static {
+        let bundle = Arc::new(self);
+
+        stream::unfold(BundleCursor::new(), move |mut cursor| {
+            let bundle = bundle.clone();
+            async move {
+                let frame = match bundle.next_frame(&mut cursor).await {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => return None,
+                    Err(e) => {
+                        cursor.next_frame = bundle.frame_keys.len();
+                        return Some((Err(e), cursor));
+                    }
+                };
+                let data = bundle.engine.lock().await.decrypt_frame_data(&frame);
+                Some((data.map(|data| (frame, data)), cursor))
+            }
+        })
+    }
+
+    async fn next_line(&self, cursor: &mut BundleCursor) -> Result<Option<Vec<u8>>> {
+        if cursor.finished {
+            return Ok(None);
+        }
+
+        let record = if !cursor.manifest_written {
+            cursor.manifest_written = true;
+            BundleRecord::Manifest {
+                manifest: self.manifest.clone(),
+            }
+        } else if let Some(frame) = self.next_frame(cursor).await? {
+            BundleRecord::Frame { frame }
+        } else {
+            cursor.finished = true;
+            let sha256 = hex::encode(cursor.digest.clone().finalize());
+            let signature = self.engine.lock().await.sign(sha256.as_bytes());
+            let mut line = serde_json::to_vec(&BundleRecord::Signature { sha256, signature })?;
+            line.push(b'\n');
+            cursor.offset += line.len() as u64;
+            cursor.sha256 = Some(hex::encode(
+                cursor.digest.clone().chain_update(&line).finalize(),
+            ));
+            return Ok(Some(line));
+        };
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        cursor.digest.update(&line);
+        cursor.offset += line.len() as u64;
+        Ok(Some(line))
+    }
+
+    // Frames from the device index that fall inside the session's sequences
+    async fn next_frame(&self, cursor: &mut BundleCursor) -> Result<Option<EncryptedFrame>> {
+        let first = self.manifest.first_sequence.unwrap_or(0);
+        let last = self.manifest.last_sequence.unwrap_or(u64::MAX);
+
+        while let Some(key) = self.frame_keys.get(cursor.next_frame) {
+            cursor.next_frame += 1;
+            let frame = self.storage.retrieve_with_fallback(key).await?;
+            if (first..=last).contains(&frame.sequence) {
+                return Ok(Some(frame));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl BundleCursor {
+    fn new() -> Self {
+        Self {
+            next_frame: 0,
+            manifest_written: false,
+            finished: false,
+            offset: 0,
+            digest: Sha256::new(),
+            sha256: None,
+        }
+    }
+}
+
+// Result of checking an exported bundle offline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleVerification {
+    pub evidence_id: Option<String>,
+    pub is_valid: bool,
+    pub manifest_valid: bool,  // manifest hash and node signature check out
+    pub signature_valid: bool, // trailer signs exactly the bytes before it
+    pub frame_count: u64,
+    pub anomalies: Vec<String>,
+}
+
+// Replays a bundle line by line: the manifest's signature, every frame's
+// link to its predecessor, the chain endpoints against the manifest, and the
+// trailer signature over everything before it. `engine` must hold the key of
+// the node (and tenant) that exported the bundle.
+pub fn verify_bundle<R: std::io::BufRead>(
+    reader: R,
+    engine: &EncryptionEngine,
+    verifier: &VerificationEngine,
+) -> Result<BundleVerification> {
+    let mut result = BundleVerification {
+        evidence_id: None,
+        is_valid: false,
+        manifest_valid: false,
+        signature_valid: false,
+        frame_count: 0,
+        anomalies: Vec::new(),
+    };
+    let mut digest = Sha256::new();
+    let mut manifest: Option<SessionManifest> = None;
+    let mut previous: Option<EncryptedFrame> = None;
+    let mut first_hash = None;
+    let mut trailer_seen = false;
+
+    for line in reader.split(b'\n') {
+        let mut line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        if trailer_seen {
+            result
+                .anomalies
+                .push("Data follows the bundle signature".to_string());
+            break;
+        }
+
+        let record: BundleRecord = serde_json::from_slice(&line)?;
+        line.push(b'\n');
+        match record {
+            BundleRecord::Manifest { manifest: m } if manifest.is_none() => {
+                result.evidence_id = Some(m.session_id.clone());
+                result.manifest_valid = m.verify(engine)?;
+                if !result.manifest_valid {
+                    result.anomalies.push(format!(
+                        "Manifest {} failed its signature check",
+                        m.manifest_hash
+                    ));
+                }
+                manifest = Some(m);
+            }
+            BundleRecord::Manifest { .. } => {
+                result
+                    .anomalies
+                    .push("Bundle has more than one manifest".to_string());
+            }
+            BundleRecord::Frame { frame } => {
+                result
+                    .anomalies
+                    .extend(verifier.frame_anomalies(previous.as_ref(), &frame)?);
+                first_hash.get_or_insert_with(|| frame.hash.clone());
+                result.frame_count += 1;
+                previous = Some(frame);
+            }
+            BundleRecord::Signature { sha256, signature } => {
+                trailer_seen = true;
+                let expected = hex::encode(digest.clone().finalize());
+                result.signature_valid =
+                    sha256 == expected && engine.verify_signature(sha256.as_bytes(), &signature);
+                if !result.signature_valid {
+                    result
+                        .anomalies
+                        .push("Bundle signature does not match its contents".to_string());
+                }
+                continue;
+            }
+        }
+        digest.update(&line);
+    }
+
+    match &manifest {
+        Some(m) => {
+            let last_hash = previous.map(|f| f.hash);
+            if first_hash != m.first_hash
+                || last_hash != m.last_hash
+                || result.frame_count != m.frame_count
+            {
+                result.anomalies.push(format!(
+                    "Frames do not match session manifest {} ({} of {} frames present)",
+                    m.manifest_hash, result.frame_count, m.frame_count
+                ));
+            }
+        }
+        None => result.anomalies.push("Bundle has no manifest".to_string()),
+    }
+    if !trailer_seen {
+        result
+            .anomalies
+            .push("Bundle is truncated: no signature record".to_string());
+    }
+
+    result.is_valid = result.anomalies.is_empty();
+    Ok(result)
+}
+
+// Parses a single `bytes=` range against a bundle of `total` bytes. Ok(None)
+// means no usable Range header, including a malformed one, which RFC 9110
+// says to ignore: serve everything. Err means a valid range the bundle can't
+// satisfy, answered with 416.
+pub fn parse_range(header: Option<&str>, total: u64) -> Result<Option<(u64, u64)>> {
+    let spec = match header.and_then(|h| h.trim().strip_prefix("bytes=")) {
+        Some(spec) if !spec.contains(',') => spec,
+        _ => return Ok(None), // absent, another unit, or multiple ranges
+    };
+    let Some((first, last)) = spec.split_once('-') else {
+        return Ok(None);
+    };
+
+    let (start, end) = match (first.trim(), last.trim()) {
+        ("", suffix) => match byte_pos(suffix) {
+            Some(0) => return Err(unsatisfiable(spec, total)),
+            Some(suffix) => (total.saturating_sub(suffix), total.saturating_sub(1)),
+            None => return Ok(None),
+        },
+        (first, "") => match byte_pos(first) {
+            Some(start) => (start, total.saturating_sub(1)),
+            None => return Ok(None),
+        },
+        (first, last) => match (byte_pos(first), byte_pos(last)) {
+            (Some(start), Some(end)) if start <= end => (start, end.min(total.saturating_sub(1))),
+            _ => return Ok(None),
+        },
+    };
+
+    if total == 0 || start >= total {
+        return Err(unsatisfiable(spec, total));
+    }
+    Ok(Some((start, end)))
+}
+
+// RFC 9110 If-Range: serve the range only if the client holds this version
+// of the bundle. Bundles carry no Last-Modified, so a date never matches, and
+// nor does a weak tag under the strong comparison ranges need.
+pub fn if_range_matches(if_range: Option<&str>, digest: &BundleDigest) -> bool {
+    if_range.is_none_or(|tag| tag.trim() == digest.etag())
+}
+
+// Digits only: `+5` parses as a u64 but isn't a byte position
+fn byte_pos(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn unsatisfiable(spec: &str, total: u64) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::InvalidRequest(format!(
+        "Range {} not satisfiable for {} bytes",
+        spec, total
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() -> Result<()> {
+        assert_eq!(parse_range(None, 100)?, None);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100)?, Some((0, 9)));
+        assert_eq!(parse_range(Some("bytes=90-"), 100)?, Some((90, 99)));
+        assert_eq!(parse_range(Some("bytes=-10"), 100)?, Some((90, 99)));
+        assert_eq!(parse_range(Some("bytes=50-500"), 100)?, Some((50, 99)));
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100)?, None);
+        assert!(parse_range(Some("bytes=100-"), 100).is_err());
+        assert!(parse_range(Some("bytes=-0"), 100).is_err());
+        assert!(parse_range(Some("bytes=0-"), 0).is_err());
+
+        // Malformed ranges are ignored rather than refused
+        for malformed in [
+            "bytes=9-2",
+            "bytes=abc",
+            "bytes=5",
+            "bytes=-",
+            "bytes=x-9",
+            "bytes=+5-9",
+        ] {
+            assert_eq!(parse_range(Some(malformed), 100)?, None, "{}", malformed);
+        }
+        let overflow = format!("bytes={}0-", u64::MAX);
+        assert_eq!(parse_range(Some(&overflow), 100)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_if_range_needs_the_same_bundle() {
+        let digest = BundleDigest {
+            byte_len: 100,
+            sha256: "ab".repeat(32),
+        };
+        let etag = format!("\"{}\"", "ab".repeat(32));
+        assert_eq!(digest.etag(), etag);
+
+        assert!(if_range_matches(None, &digest));
+        assert!(if_range_matches(Some(&etag), &digest));
+        assert!(!if_range_matches(Some(&format!("W/{}", etag)), &digest));
+        assert!(!if_range_matches(Some("\"cd\""), &digest));
+        assert!(!if_range_matches(
+            Some("Wed, 21 Oct 2026 07:28:00 GMT"),
+            &digest
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::auth::{Principal, Role};
+use crate::crypto::EncryptionEngine;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::report::SignedReport;
+use crate::session::RecordingSession;
+
+const MAX_CASE_ID_LEN: usize = 128;
+
+// Who may see and work on a case within its tenant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseAccess {
+    #[default]
+    Tenant, // anyone whose role allows the operation
+    Assigned, // only assigned personnel, and admins
+}
+
+// Unix seconds, inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeRange {
+    pub from: u64,
+    pub to: u64,
+}
+
+// Evidence from several cameras grouped under one investigation, persisted
+// under `case:{id}`. Sessions are attached explicitly; a session's own
+// `case_id` is part of its signed manifest and so can't be changed later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Case {
+    pub case_id: String,
+    pub title: String,
+    pub devices: Vec<String>, // cameras the case covers; empty allows any
+    pub time_ranges: Vec<TimeRange>, // periods of interest; empty allows any
+    pub personnel: Vec<String>, // subjects assigned to the case
+    pub access: CaseAccess,
+    pub sessions: Vec<String>, // attached recording sessions
+    pub created_by: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CaseRequest {
+    pub title: String,
+    #[serde(default)]
+    pub devices: Vec<String>,
+    #[serde(default)]
+    pub time_ranges: Vec<TimeRange>,
+    #[serde(default)]
+    pub personnel: Vec<String>,
+    #[serde(default)]
+    pub access: CaseAccess,
+}
+
+impl Case {
+    pub fn key(case_id: &str) -> String {
+        format!("case:{}", case_id)
+    }
+
+    // Creates the case, or updates an existing one's details while keeping
+    // its attached sessions and creation record
+    pub fn apply(
+        existing: Option<Case>,
+        case_id: &str,
+        request: CaseRequest,
+        actor: &Principal,
+    ) -> Result<Self> {
+        validate_case_id(case_id)?;
+        if request.title.trim().is_empty() {
+            return Err(ImmutableEncryptionError::invalid_request(
+                "case title cannot be empty",
+            ));
+        }
+        if let Some(range) = request.time_ranges.iter().find(|r| r.from > r.to) {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "time range {}..{} ends before it starts",
+                range.from, range.to
+            )));
+        }
+
+        let now = now()?;
+        let (sessions, created_by, created_at) = match existing {
+            Some(case) => (case.sessions, case.created_by, case.created_at),
+            None => (Vec::new(), actor.subject.clone(), now),
+        };
+        Ok(Self {
+            case_id: case_id.to_string(),
+            title: request.title,
+            devices: request.devices,
+            time_ranges: request.time_ranges,
+            personnel: request.personnel,
+            access: request.access,
+            sessions,
+            created_by,
+            created_at,
+            updated_at: now,
+        })
+    }
+
+    pub fn can_access(&self, principal: &Principal) -> bool {
+        match self.access {
+            CaseAccess::Tenant => true,
+            CaseAccess::Assigned => {
+                principal.roles.contains(&Role::Admin)
+                    || self.personnel.contains(&principal.subject)
+            }
+        }
+    }
+
+    pub fn require_access(&self, principal: &Principal) -> Result<()> {
+        if self.can_access(principal) {
+            return Ok(());
+        }
+        Err(ImmutableEncryptionError::PermissionDenied(format!(
+            "{} is not assigned to case {}",
+            principal.subject, self.case_id
+        )))
+    }
+
+    // Attaching again is a no-op. The session must come from one of the case's
+    // cameras and overlap one of its time ranges, when the case names any.
+    pub fn attach(&mut self, session: &RecordingSession) -> Result<()> {
+        if !self.devices.is_empty() && !self.devices.contains(&session.device_id) {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "device {} is not part of case {}",
+                session.device_id, self.case_id
+            )));
+        }
+
+        // An active session runs open-ended until it stops
+        let start = session.first_frame_timestamp.unwrap_or(session.started_at);
+        let end = session.last_frame_timestamp.unwrap_or(u64::MAX);
+        if !self.time_ranges.is_empty()
+            && !self
+                .time_ranges
+                .iter()
+                .any(|range| start <= range.to && end >= range.from)
+        {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "session {} is outside the time ranges of case {}",
+                session.session_id, self.case_id
+            )));
+        }
+
+        if !self.sessions.contains(&session.session_id) {
+            self.sessions.push(session.session_id.clone());
+            self.updated_at = now()?;
+        }
+        Ok(())
+    }
+
+    pub fn detach(&mut self, session_id: &str) -> Result<()> {
+        let before = self.sessions.len();
+        self.sessions.retain(|s| s != session_id);
+        if self.sessions.len() == before {
+            return Err(ImmutableEncryptionError::NotFound(format!(
+                "Session {} is not attached to case {}",
+                session_id, self.case_id
+            )));
+        }
+        self.updated_at = now()?;
+        Ok(())
+    }
+}
+
+// The court report for every session attached to a case. Each session's
+// report stays individually signed, so it can be checked on its own; the
+// whole is signed again so sessions can't be dropped or swapped.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseReport {
+    pub case: Case,
+    pub devices: Vec<String>, // cameras the attached sessions recorded
+    pub generated_at: u64,
+    pub sessions: Vec<SignedReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedCaseReport {
+    pub case_report: CaseReport,
+    pub content_sha256: String,
+    pub signature: String,
+}
+
+impl SignedCaseReport {
+    pub fn sign(case_report: CaseReport, engine: &EncryptionEngine) -> Result<Self> {
+        let content = serde_json::to_vec(&case_report)?;
+        Ok(Self {
+            content_sha256: hex::encode(Sha256::digest(&content)),
+            signature: engine.sign(&content),
+            case_report,
+        })
+    }
+}
+
+// Case IDs end up in storage keys and URLs
+fn validate_case_id(case_id: &str) -> Result<()> {
+    let valid = !case_id.is_empty()
+        && case_id.len() <= MAX_CASE_ID_LEN
+        && case_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ImmutableEncryptionError::InvalidRequest(format!(
+            "invalid case ID {:?}: use up to {} letters, digits, '-', '_' or '.'",
+            case_id, MAX_CASE_ID_LEN
+        )))
+    }
+}
+
+fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(session_id: &str, device_id: &str, from: u64, to: u64) -> RecordingSession {
+        RecordingSession {
+            session_id: session_id.to_string(),
+            device_id: device_id.to_string(),
+            case_id: None,
+            started_at: from,
+            first_hash: None,
+            last_hash: None,
+            first_sequence: Some(1),
+            last_sequence: Some(2),
+            first_frame_timestamp: Some(from),
+            last_frame_timestamp: Some(to),
+            frame_count: 2,
+            policy: None,
+        }
+    }
+
+    #[test]
+    fn test_case_attaches_sessions_within_its_scope() -> Result<()> {
+        let mut detective = Principal::anonymous();
+        detective.subject = "detective".to_string();
+        detective.roles = vec![Role::Prosecutor];
+
+        let request = CaseRequest {
+            title: "Warehouse break-in".to_string(),
+            devices: vec!["cam_1".to_string(), "cam_2".to_string()],
+            time_ranges: vec![TimeRange {
+                from: 1000,
+                to: 2000,
+            }],
+            personnel: vec!["detective".to_string()],
+            access: CaseAccess::Assigned,
+        };
+        assert!(Case::apply(None, "case 1", request.clone(), &detective).is_err());
+        let mut case = Case::apply(None, "CASE-42", request.clone(), &detective)?;
+
+        case.attach(&session("s1", "cam_1", 900, 1100))?;
+        case.attach(&session("s2", "cam_2", 1500, 1600))?;
+        case.attach(&session("s1", "cam_1", 900, 1100))?;
+        assert_eq!(case.sessions, vec!["s1", "s2"]);
+        assert!(case.attach(&session("s3", "cam_9", 1500, 1600)).is_err());
+        assert!(case.attach(&session("s4", "cam_1", 2001, 3000)).is_err());
+
+        // Updating the details keeps what is attached
+        let case = Case::apply(Some(case), "CASE-42", request, &detective)?;
+        assert_eq!(case.sessions.len(), 2);
+        assert_eq!(case.created_by, "detective");
+
+        let mut outsider = detective.clone();
+        outsider.subject = "someone-else".to_string();
+        assert!(case.can_access(&detective));
+        assert!(!case.can_access(&outsider));
+        outsider.roles.push(Role::Admin);
+        assert!(case.can_access(&outsider));
+
+        let mut case = case;
+        case.detach("s1")?;
+        assert!(case.detach("s1").is_err());
+        assert_eq!(case.sessions, vec!["s2"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use immutable_encryption_core::anchor::check_anchor;
+
+use crate::crypto::{self, EncryptionEngine, PostQuantumCiphertext};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::merkle::{InclusionProof, MerkleTree};
+use crate::session::SessionManifest;
+use crate::{BlockchainAnchor, EncryptedFrame};
+
+// One frame of a shared clip: its place in the device's hash chain, its
+// anchors, and its payload sealed under the clip key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipFrame {
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub hash: String,
+    pub previous_hash: String,
+    pub blockchain_anchors: Vec<BlockchainAnchor>,
+    pub inclusion: InclusionProof, // under the clip's `merkle_root`
+    pub payload_sha256: String,
+    pub ciphertext: Vec<u8>,
+    pub nonce: Vec<u8>,
+}
+
+// A time range of a sealed session re-encrypted to one recipient. Only the
+// clip is disclosed, but the proofs tie it to the full recording: each frame's
+// chain hash sits under a Merkle root over every frame of the session, whose
+// first and last leaves are the ends the anchored manifest commits to. The
+// node signs the whole with its published Ed25519 envelope key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedClip {
+    pub clip_id: String,
+    pub evidence_id: String,
+    pub from: u64,
+    pub to: u64,
+    pub manifest: SessionManifest,
+    pub merkle_root: String, // over the session's chain hashes, in order
+    pub boundary: Vec<InclusionProof>, // the session's first and last leaves
+    pub frames: Vec<ClipFrame>,
+    pub recipient_key_sha256: String,
+    pub wrapped_key: PostQuantumCiphertext, // clip key, sealed to the recipient
+    pub shared_at: u64,
+    pub signature: String,
+}
+
+impl SharedClip {
+    // `session` is every stored frame of the sealed session, in sequence
+    // order; frames timestamped within `from..=to` are opened with `decrypt`
+    // and shared
+    pub fn seal(
+        manifest: SessionManifest,
+        session: &[EncryptedFrame],
+        from: u64,
+        to: u64,
+        recipient_key: &[u8],
+        engine: &EncryptionEngine,
+        mut decrypt: impl FnMut(&EncryptedFrame) -> Result<Vec<u8>>,
+    ) -> Result<Self> {
+        if from > to {
+            return Err(ImmutableEncryptionError::invalid_request(
+                "clip ends before it starts",
+            ));
+        }
+        if session.len() as u64 != manifest.frame_count {
+            return Err(ImmutableEncryptionError::EvidenceTampered {
+                details: format!(
+                    "Session {} has {} stored frames, its manifest {}",
+                    manifest.session_id,
+                    session.len(),
+                    manifest.frame_count
+                ),
+            });
+        }
+        let tree = MerkleTree::new(session.iter().map(|f| f.hash.clone()).collect())?;
+        let proof = |index: usize| {
+            tree.proof(index)
+                .ok_or_else(|| ImmutableEncryptionError::internal("Merkle proof out of range"))
+        };
+
+        let mut clip_key = [0u8; 32];
+        let rng = SystemRandom::new();
+        rng.fill(&mut clip_key)
+            .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate a clip key"))?;
+
+        let mut frames = Vec::new();
+        for (index, frame) in session.iter().enumerate() {
+            if !(from..=to).contains(&frame.timestamp) {
+                continue;
+            }
+            let payload = decrypt(frame)?;
+            let (ciphertext, nonce) = crypto::seal(&clip_key, &payload, &rng)?;
+            frames.push(ClipFrame {
+                sequence: frame.sequence,
+                timestamp: frame.timestamp,
+                hash: frame.hash.clone(),
+                previous_hash: frame.previous_hash.clone(),
+                blockchain_anchors: frame.blockchain_anchors.clone(),
+                inclusion: proof(index)?,
+                payload_sha256: hex::encode(Sha256::digest(&payload)),
+                ciphertext,
+                nonce,
+            });
+        }
+        if frames.is_empty() {
+            return Err(ImmutableEncryptionError::NotFound(format!(
+                "No frames of {} between {} and {}",
+                manifest.session_id, from, to
+            )));
+        }
+
+        let shared_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let recipient_key_sha256 = hex::encode(Sha256::digest(recipient_key));
+        let clip_id = format!(
+            "clip_{}",
+            &blake3::hash(
+                format!(
+                    "{}:{}:{}:{}:{}",
+                    manifest.session_id, from, to, recipient_key_sha256, shared_at
+                )
+                .as_bytes()
+            )
+            .to_hex()[..16]
+        );
+
+        let mut clip = Self {
+            clip_id,
+            evidence_id: manifest.session_id.clone(),
+            from,
+            to,
+            merkle_root: tree.root(),
+            boundary: vec![proof(0)?, proof(session.len() - 1)?],
+            manifest,
+            frames,
+            recipient_key_sha256,
+            wrapped_key: crypto::post_quantum_seal(recipient_key, &clip_key)?,
+            shared_at,
+            signature: String::new(),
+        };
+        clip.signature = hex::encode(engine.sign_envelope(&clip.signed_content()?)?);
+        Ok(clip)
+    }
+
+    // Checks the clip without any secret: the signature against the node's
+    // envelope public key, the boundary proofs against the manifest, every
+    // frame's inclusion and chain link, and the shape of every anchor.
+    // Whether anchors are confirmed is up to the chains themselves.
+    pub fn verify(&self, public_key: &[u8]) -> Result<()> {
+        let signature = hex::decode(&self.signature)?;
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&self.signed_content()?, &signature)
+            .map_err(|_| {
+                ImmutableEncryptionError::verification("Clip signature does not verify")
+            })?;
+
+        let tampered =
+            |details: String| Err(ImmutableEncryptionError::EvidenceTampered { details });
+        let under_root = |proof: &InclusionProof| proof.root == self.merkle_root && proof.verify();
+
+        let last_index = self.manifest.frame_count.saturating_sub(1) as usize;
+        let ends = [
+            (0, self.manifest.first_hash.as_deref()),
+            (last_index, self.manifest.last_hash.as_deref()),
+        ];
+        if self.boundary.len() != 2
+            || !self
+                .boundary
+                .iter()
+                .zip(ends)
+                .all(|(proof, (index, hash))| {
+                    under_root(proof) && proof.index == index && Some(proof.leaf.as_str()) == hash
+                })
+        {
+            return tampered(format!(
+                "Merkle root {} does not span session {}",
+                self.merkle_root, self.evidence_id
+            ));
+        }
+
+        let first_sequence = self.manifest.first_sequence.unwrap_or(0);
+        for (i, frame) in self.frames.iter().enumerate() {
+            let index = frame
+                .sequence
+                .checked_sub(first_sequence)
+                .map(|i| i as usize);
+            if !under_root(&frame.inclusion)
+                || frame.inclusion.leaf != frame.hash
+                || Some(frame.inclusion.index) != index
+            {
+                return tampered(format!(
+                    "Frame {} is not part of session {}",
+                    frame.sequence, self.evidence_id
+                ));
+            }
+            if !(self.from..=self.to).contains(&frame.timestamp) {
+                return tampered(format!(
+                    "Frame {} lies outside the shared range",
+                    frame.sequence
+                ));
+            }
+            // Shared frames are consecutive links of the one chain
+            if let Some(previous) = i.checked_sub(1).map(|p| &self.frames[p]) {
+                if frame.sequence != previous.sequence + 1
+                    || frame.previous_hash != previous.hash
+                    || frame.timestamp <= previous.timestamp
+                {
+                    return tampered(format!(
+                        "Frame {} does not follow frame {}",
+                        frame.sequence, previous.sequence
+                    ));
+                }
+            }
+        }
+
+        Ok(self
+            .manifest
+            .anchors
+            .iter()
+            .chain(self.frames.iter().flat_map(|f| &f.blockchain_anchors))
+            .try_for_each(check_anchor)?)
+    }
+
+    // The recipient's view: each frame's sequence and payload, checked
+    // against the digest the node signed
+    pub fn open(&self, secret_key: &[u8]) -> Result<Vec<(u64, Vec<u8>)>> {
+        let clip_key = crypto::post_quantum_open(secret_key, &self.wrapped_key)?;
+        self.frames
+            .iter()
+            .map(|frame| {
+                let payload = crypto::open(&clip_key, &frame.ciphertext, &frame.nonce)?;
+                if hex::encode(Sha256::digest(&payload)) != frame.payload_sha256 {
+                    return Err(ImmutableEncryptionError::EvidenceTampered {
+                        details: format!(
+                            "Payload of frame {} does not match its digest",
+                            frame.sequence
+                        ),
+                    });
+                }
+                Ok((frame.sequence, payload))
+            })
+            .collect()
+    }
+
+    // Everything but the signature and the sealed payloads, which are bound
+    // through their digests
+    fn signed_content(&self) -> Result<Vec<u8>> {
+        let frames: Vec<_> = self
+            .frames
+            .iter()
+            .map(|f| {
+                (
+                    f.sequence,
+                    f.timestamp,
+                    &f.hash,
+                    &f.previous_hash,
+                    &f.blockchain_anchors,
+                    &f.inclusion,
+                    &f.payload_sha256,
+                )
+            })
+            .collect();
+        Ok(serde_json::to_vec(&(
+            &self.clip_id,
+            &self.evidence_id,
+            self.from,
+            self.to,
+            &self.manifest,
+            &self.merkle_root,
+            &self.boundary,
+            frames,
+            &self.recipient_key_sha256,
+            &self.wrapped_key,
+            self.shared_at,
+        ))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+    use crate::session::RecordingSession;
+    use immutable_encryption_core::hash::{self, GENESIS_HASH};
+
+    #[test]
+    fn test_clip_proves_membership_and_opens_for_recipient() -> Result<()> {
+        let engine = EncryptionEngine::new(CryptoConfig::software(vec![5u8; 32], 1))?;
+
+        // Payloads stand in for ciphertexts; decryption is the node's job
+        let mut session = RecordingSession {
+            session_id: "session_cam_1".to_string(),
+            device_id: "cam_1".to_string(),
+            case_id: None,
+            started_at: 1000,
+            first_hash: None,
+            last_hash: None,
+            first_sequence: None,
+            last_sequence: None,
+            first_frame_timestamp: None,
+            last_frame_timestamp: None,
+            frame_count: 0,
+            policy: None,
+        };
+        let mut frames: Vec<EncryptedFrame> = Vec::new();
+        for sequence in 0..10u64 {
+            let previous_hash = frames
+                .last()
+                .map_or(GENESIS_HASH.to_string(), |f| f.hash.clone());
+            let payload = vec![sequence as u8; 64];
+            let frame = EncryptedFrame {
+                sequence,
+                device_id: "cam_1".to_string(),
+                hash: hash::chain_link(&hash::sha256_hex(&payload), &previous_hash, sequence),
+                ciphertext: payload,
+                previous_hash,
+                nonce: Vec::new(),
+                timestamp: 1000 + sequence,
+                blockchain_anchors: Vec::new(),
+                cipher: Default::default(),
+                compressed: false,
+                attestation: None,
+                device_signature: None,
+            };
+            session.record(&frame);
+            frames.push(frame);
+        }
+        let manifest = SessionManifest::from_session(&session, 1010, &engine)?;
+        let decrypt = |frame: &EncryptedFrame| Ok(frame.ciphertext.clone());
+
+        let (public_key, secret_key) = crypto::post_quantum_keypair();
+        let clip = SharedClip::seal(
+            manifest.clone(),
+            &frames,
+            1003,
+            1005,
+            &public_key,
+            &engine,
+            decrypt,
+        )?;
+        let node_key = engine.envelope_public_key()?;
+
+        assert_eq!(clip.frames.len(), 3);
+        clip.verify(&node_key)?;
+        let opened = clip.open(&secret_key)?;
+        assert_eq!(opened[0], (3, vec![3u8; 64]));
+
+        // Only the recipient can open it
+        let (_, other_secret) = crypto::post_quantum_keypair();
+        assert!(clip.open(&other_secret).is_err());
+
+        // Dropping a frame from the middle breaks the chain, even re-signed
+        let mut gapped = clip.clone();
+        gapped.frames.remove(1);
+        gapped.signature = hex::encode(engine.sign_envelope(&gapped.signed_content()?)?);
+        assert!(gapped.verify(&node_key).is_err());
+
+        // A frame from another recording has no place under the root
+        let mut forged = clip.clone();
+        forged.frames[0].hash = "f".repeat(64);
+        forged.signature = hex::encode(engine.sign_envelope(&forged.signed_content()?)?);
+        assert!(forged.verify(&node_key).is_err());
+
+        let mut edited = clip.clone();
+        edited.to += 1;
+        assert!(edited.verify(&node_key).is_err());
+
+        assert!(
+            SharedClip::seal(manifest, &frames, 1050, 1060, &public_key, &engine, decrypt).is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,519 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::crypto::EncryptionEngine;
+use crate::error::{Context, ImmutableEncryptionError, Result};
+use crate::recovery::{CrashSnapshot, CrashState};
+use crate::CustodyEntry;
+
+const LEASE_PREFIX: &str = "lease-";
+const LEASE_EXTENSION: &str = ".json";
+// Older leases are pruned, but a few stay as the record of recent handoffs
+const LEASES_KEPT: u64 = 16;
+
+// Custody scope the handoff entries are appended under
+pub const HANDOFF_SCOPE: &str = "cluster";
+
+// Nodes sharing one data volume. Only the leader opens the database and
+// seals; the others stand by until its lease lapses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    pub enabled: bool,
+    pub node_id: String,
+    pub lease_dir: String, // on the shared volume, next to the database
+    pub lease_ttl_secs: u64,
+    pub renew_interval_secs: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            node_id: String::new(),
+            lease_dir: "./data/cluster".to_string(),
+            lease_ttl_secs: 15,
+            renew_interval_secs: 5,
+        }
+    }
+}
+
+// The right to seal, claimed by creating `lease-{epoch}.json`. Each renewal
+// carries every tenant's pipeline state, so whoever takes over next resumes
+// the chain where the leader last reported it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderLease {
+    pub node_id: String,
+    pub epoch: u64,
+    pub acquired_at: u64, // unix millis, as are the times below
+    pub renewed_at: u64,
+    pub expires_at: u64,
+    pub released: bool,
+    pub checkpoints: BTreeMap<String, CrashSnapshot>, // tenant -> pipeline state
+}
+
+impl LeaderLease {
+    pub fn is_held(&self, now: u64) -> bool {
+        !self.released && now < self.expires_at
+    }
+}
+
+// A lease this node won, and the one it took over from
+#[derive(Debug, Clone)]
+pub struct Leadership {
+    pub lease: LeaderLease,
+    pub previous: Option<LeaderLease>,
+}
+
+impl Leadership {
+    // Leaves the previous leader's checkpoint for a tenant as a crash
+    // snapshot, so recovery resumes the chain from it and accounts for frames
+    // it had in flight
+    pub fn restore_checkpoint(&self, tenant_id: &str, crash_dir: &Path) -> Result<Option<PathBuf>> {
+        let Some(previous) = &self.previous else {
+            return Ok(None);
+        };
+        let Some(checkpoint) = previous.checkpoints.get(tenant_id) else {
+            return Ok(None);
+        };
+        let mut snapshot = checkpoint.clone();
+        snapshot.reason = self.handoff(BTreeMap::new()).describe();
+        snapshot.write_to(crash_dir).map(Some)
+    }
+
+    pub fn handoff(&self, chain_tips: BTreeMap<String, String>) -> ChainHandoff {
+        let previous = self.previous.as_ref();
+        ChainHandoff {
+            from_node: previous.map(|p| p.node_id.clone()),
+            from_epoch: previous.map(|p| p.epoch),
+            to_node: self.lease.node_id.clone(),
+            epoch: self.lease.epoch,
+            released: previous.is_some_and(|p| p.released),
+            chain_tips,
+        }
+    }
+}
+
+// One node passing a tenant's hash chain to the next
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainHandoff {
+    pub from_node: Option<String>,
+    pub from_epoch: Option<u64>,
+    pub to_node: String,
+    pub epoch: u64,
+    pub released: bool, // false: the previous leader's lease lapsed
+    pub chain_tips: BTreeMap<String, String>, // device_id -> where the new leader resumed
+}
+
+impl ChainHandoff {
+    pub fn describe(&self) -> String {
+        let from = self.from_node.as_deref().unwrap_or("nobody");
+        if self.released {
+            format!("handoff from {} to {}", from, self.to_node)
+        } else {
+            format!("failover from {} to {}", from, self.to_node)
+        }
+    }
+
+    // `{device}@{tip}` for each chain, or "-" if none had started
+    pub fn describe_tips(&self) -> String {
+        if self.chain_tips.is_empty() {
+            return "-".to_string();
+        }
+        self.chain_tips
+            .iter()
+            .map(|(device_id, tip)| format!("{}@{}", device_id, tip))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    // Signed with the node's Ed25519 envelope key over
+    // `{timestamp}|{actor}|{action}`
+    pub fn custody_entry(&self, timestamp: u64, engine: &EncryptionEngine) -> Result<CustodyEntry> {
+        let action = format!(
+            "chain_handoff:{}:{}->{}:epoch={}:released={}:tips={}",
+            self.from_node.as_deref().unwrap_or("-"),
+            self.from_epoch
+                .map_or_else(|| "-".to_string(), |e| e.to_string()),
+            self.to_node,
+            self.epoch,
+            self.released,
+            self.describe_tips(),
+        );
+        let actor = format!("node:{}", self.to_node);
+        let signed = format!("{}|{}|{}", timestamp, actor, action);
+        Ok(CustodyEntry {
+            timestamp,
+            signature: hex::encode(engine.sign_envelope(signed.as_bytes())?),
+            actor,
+            action,
+            blockchain_reference: String::new(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Election {
+    Won(Leadership),
+    Held(LeaderLease), // another node leads
+}
+
+#[derive(Debug)]
+pub struct LeaseStore {
+    config: ClusterConfig,
+}
+
+impl LeaseStore {
+    pub fn new(config: ClusterConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.config.node_id
+    }
+
+    pub fn latest(&self) -> Result<Option<LeaderLease>> {
+        match self.lease_paths()?.pop() {
+            Some((_, path)) => Ok(Some(read_lease(&path)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Claims the next epoch unless a live lease is held by another node.
+    // Linking the epoch's file into place is the atomic step, so two
+    // standbys racing for the same lapsed lease can't both win.
+    pub fn try_acquire(&self, now: u64) -> Result<Election> {
+        let previous = self.latest()?;
+        if let Some(current) = &previous {
+            if current.is_held(now) && current.node_id != self.config.node_id {
+                return Ok(Election::Held(current.clone()));
+            }
+        }
+
+        let epoch = previous.as_ref().map_or(1, |p| p.epoch + 1);
+        let lease = LeaderLease {
+            node_id: self.config.node_id.clone(),
+            epoch,
+            acquired_at: now,
+            renewed_at: now,
+            expires_at: now + self.config.lease_ttl_secs * 1000,
+            released: false,
+            checkpoints: BTreeMap::new(),
+        };
+        std::fs::create_dir_all(&self.config.lease_dir)
+            .with_context(|| format!("Failed to create {}", self.config.lease_dir))?;
+        let partial = self.partial_path(&format!("{}.{}", epoch, self.config.node_id));
+        std::fs::write(&partial, serde_json::to_vec_pretty(&lease)?)?;
+        let linked = std::fs::hard_link(&partial, self.lease_path(epoch));
+        std::fs::remove_file(&partial)?;
+        match linked {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return match self.latest()? {
+                    Some(winner) => Ok(Election::Held(winner)),
+                    None => Err(ImmutableEncryptionError::internal(
+                        "lease vanished mid-election",
+                    )),
+                };
+            }
+            Err(e) => return Err(e.into()),
+        }
+        self.prune(epoch)?;
+        Ok(Election::Won(Leadership { lease, previous }))
+    }
+
+    // Extends the lease and records the latest checkpoints. Fails once a
+    // newer epoch exists or the lease lapsed before it could be renewed; the
+    // node must then stop sealing.
+    pub fn renew(
+        &self,
+        lease: &mut LeaderLease,
+        checkpoints: BTreeMap<String, CrashSnapshot>,
+        now: u64,
+    ) -> Result<()> {
+        if let Some(latest) = self.latest()? {
+            if latest.epoch > lease.epoch {
+                return Err(ImmutableEncryptionError::ResourceUnavailable(format!(
+                    "{} took over leadership at epoch {}",
+                    latest.node_id, latest.epoch
+                )));
+            }
+        }
+        if now >= lease.expires_at {
+            return Err(ImmutableEncryptionError::ResourceUnavailable(format!(
+                "lease for epoch {} lapsed before it was renewed",
+                lease.epoch
+            )));
+        }
+        lease.renewed_at = now;
+        lease.expires_at = now + self.config.lease_ttl_secs * 1000;
+        lease.checkpoints = checkpoints;
+        self.write(lease)
+    }
+
+    // Hands over at once: standbys don't wait for a released lease to lapse
+    pub fn release(
+        &self,
+        lease: &mut LeaderLease,
+        checkpoints: BTreeMap<String, CrashSnapshot>,
+        now: u64,
+    ) -> Result<()> {
+        lease.renewed_at = now;
+        lease.released = true;
+        lease.checkpoints = checkpoints;
+        self.write(lease)
+    }
+
+    // Written under a temporary name and renamed, so standbys never read a
+    // half-written lease
+    fn write(&self, lease: &LeaderLease) -> Result<()> {
+        let partial = self.partial_path(&lease.epoch.to_string());
+        std::fs::write(&partial, serde_json::to_vec_pretty(lease)?)?;
+        std::fs::rename(&partial, self.lease_path(lease.epoch))?;
+        Ok(())
+    }
+
+    fn prune(&self, epoch: u64) -> Result<()> {
+        for (old, path) in self.lease_paths()? {
+            if old + LEASES_KEPT <= epoch {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Hidden from `lease_paths` until moved into place
+    fn partial_path(&self, name: &str) -> PathBuf {
+        Path::new(&self.config.lease_dir).join(format!(".{}{}", LEASE_PREFIX, name))
+    }
+
+    fn lease_path(&self, epoch: u64) -> PathBuf {
+        Path::new(&self.config.lease_dir)
+            .join(format!("{}{:020}{}", LEASE_PREFIX, epoch, LEASE_EXTENSION))
+    }
+
+    // Every lease file, lowest epoch first
+    fn lease_paths(&self) -> Result<Vec<(u64, PathBuf)>> {
+        let dir = Path::new(&self.config.lease_dir);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut leases = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let epoch = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix(LEASE_PREFIX))
+                .and_then(|n| n.strip_suffix(LEASE_EXTENSION))
+                .and_then(|n| n.parse::<u64>().ok());
+            if let Some(epoch) = epoch {
+                leases.push((epoch, path));
+            }
+        }
+        leases.sort();
+        Ok(leases)
+    }
+}
+
+fn read_lease(path: &Path) -> Result<LeaderLease> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+// Blocks until this node leads, polling at the renewal interval
+pub async fn await_leadership(store: &LeaseStore) -> Result<Leadership> {
+    let mut standing_by: Option<(String, u64)> = None;
+    loop {
+        match store.try_acquire(now_millis())? {
+            Election::Won(leadership) => return Ok(leadership),
+            Election::Held(lease) => {
+                if standing_by.as_ref() != Some(&(lease.node_id.clone(), lease.epoch)) {
+                    tracing::info!(
+                        "Standing by: {} leads at epoch {}",
+                        lease.node_id,
+                        lease.epoch
+                    );
+                    standing_by = Some((lease.node_id, lease.epoch));
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(store.config.renew_interval_secs)).await;
+    }
+}
+
+// Keeps the lease renewed with each tenant's pipeline state until released.
+// Losing it calls `on_lost` once, which should stop the node.
+#[derive(Debug)]
+pub struct LeaseKeeper {
+    store: Arc<LeaseStore>,
+    lease: Arc<Mutex<LeaderLease>>,
+    states: Vec<(String, Arc<CrashState>)>,
+    task: JoinHandle<()>,
+}
+
+impl LeaseKeeper {
+    pub fn spawn(
+        store: Arc<LeaseStore>,
+        lease: LeaderLease,
+        states: Vec<(String, Arc<CrashState>)>,
+        on_lost: impl FnOnce(ImmutableEncryptionError) + Send + 'static,
+    ) -> Self {
+        let lease = Arc::new(Mutex::new(lease));
+        let task = {
+            let (store, lease, states) = (store.clone(), lease.clone(), states.clone());
+            tokio::spawn(async move {
+                let period = Duration::from_secs(store.config.renew_interval_secs);
+                let mut ticks = tokio::time::interval(period);
+                ticks.tick().await;
+                loop {
+                    ticks.tick().await;
+                    let mut lease = lease.lock().await;
+                    let now = now_millis();
+                    match store.renew(&mut lease, checkpoints(&states), now) {
+                        Ok(()) => {}
+                        Err(e @ ImmutableEncryptionError::ResourceUnavailable(_)) => {
+                            tracing::error!("Lost leadership: {}", e);
+                            on_lost(e);
+                            return;
+                        }
+                        // A failed write is retried if the lease outlasts the
+                        // next attempt; otherwise step down before it lapses
+                        Err(e) if now + (period.as_millis() as u64) < lease.expires_at => {
+                            tracing::warn!("Failed to renew the leader lease: {}", e)
+                        }
+                        Err(e) => {
+                            tracing::error!("Lost leadership: {}", e);
+                            on_lost(e);
+                            return;
+                        }
+                    }
+                }
+            })
+        };
+        Self {
+            store,
+            lease,
+            states,
+            task,
+        }
+    }
+
+    // Records where every chain ended, so the next leader resumes exactly
+    pub async fn release(self) -> Result<()> {
+        self.task.abort();
+        let mut lease = self.lease.lock().await;
+        if self.store.latest()?.is_some_and(|l| l.epoch > lease.epoch) {
+            return Ok(()); // already taken over
+        }
+        self.store
+            .release(&mut lease, checkpoints(&self.states), now_millis())
+    }
+}
+
+fn checkpoints(states: &[(String, Arc<CrashState>)]) -> BTreeMap<String, CrashSnapshot> {
+    states
+        .iter()
+        .filter_map(|(tenant_id, state)| {
+            state
+                .snapshot("leader checkpoint")
+                .map(|snapshot| (tenant_id.clone(), snapshot))
+        })
+        .collect()
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn store(dir: &TempDir, node_id: &str) -> LeaseStore {
+        LeaseStore::new(ClusterConfig {
+            enabled: true,
+            node_id: node_id.to_string(),
+            lease_dir: dir.path().to_string_lossy().to_string(),
+            lease_ttl_secs: 15,
+            renew_interval_secs: 5,
+        })
+    }
+
+    fn checkpoint(tip: &str) -> BTreeMap<String, CrashSnapshot> {
+        let snapshot = CrashSnapshot {
+            taken_at: 1,
+            reason: "leader checkpoint".to_string(),
+            chain_tips: BTreeMap::from([("cam_1".to_string(), tip.to_string())]),
+            last_sequences: BTreeMap::from([("cam_1".to_string(), 42)]),
+            unflushed: Vec::new(),
+            pending_anchors: Vec::new(),
+        };
+        BTreeMap::from([("default".to_string(), snapshot)])
+    }
+
+    #[test]
+    fn test_leases_fail_over_and_hand_the_chain_on() -> Result<()> {
+        let dir = TempDir::new()?;
+        let (a, b) = (store(&dir, "node-a"), store(&dir, "node-b"));
+
+        let Election::Won(mut leading) = a.try_acquire(0)? else {
+            panic!("the first node should lead");
+        };
+        assert_eq!(leading.lease.epoch, 1);
+        assert!(leading.previous.is_none());
+        a.renew(&mut leading.lease, checkpoint("tip-1"), 10_000)?;
+        assert!(matches!(b.try_acquire(20_000)?, Election::Held(l) if l.node_id == "node-a"));
+
+        // node-a stops renewing; node-b takes over once the lease lapses
+        let Election::Won(failover) = b.try_acquire(25_000)? else {
+            panic!("the lapsed lease should be taken over");
+        };
+        assert_eq!(failover.lease.epoch, 2);
+        let handoff =
+            failover.handoff(BTreeMap::from([("cam_1".to_string(), "tip-1".to_string())]));
+        assert!(!handoff.released);
+        assert_eq!(handoff.describe(), "failover from node-a to node-b");
+
+        // Its checkpoint becomes a snapshot that recovery resumes from
+        let crash_dir = dir.path().join("crash");
+        let path = failover.restore_checkpoint("default", &crash_dir)?.unwrap();
+        let snapshot = CrashSnapshot::load(&path)?;
+        assert_eq!(snapshot.chain_tips["cam_1"], "tip-1");
+        assert_eq!(snapshot.reason, "failover from node-a to node-b");
+        assert!(failover.restore_checkpoint("other", &crash_dir)?.is_none());
+
+        // The old leader can't renew its way back
+        assert!(a
+            .renew(&mut leading.lease, checkpoint("tip-2"), 26_000)
+            .is_err());
+
+        // A released lease passes on at once
+        let mut lease = failover.lease;
+        b.release(&mut lease, checkpoint("tip-3"), 30_000)?;
+        let Election::Won(handover) = a.try_acquire(30_001)? else {
+            panic!("a released lease should pass on");
+        };
+        assert!(handover.handoff(BTreeMap::new()).released);
+        assert_eq!(
+            handover.previous.unwrap().checkpoints["default"].chain_tips["cam_1"],
+            "tip-3"
+        );
+
+        let engine =
+            EncryptionEngine::new(crate::crypto::CryptoConfig::software(vec![3u8; 32], 1))?;
+        let entry = handoff.custody_entry(25, &engine)?;
+        assert_eq!(
+            entry.action,
+            "chain_handoff:node-a:1->node-b:epoch=2:released=false:tips=cam_1@tip-1"
+        );
+        Ok(())
+    }
+}
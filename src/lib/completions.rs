@@ -0,0 +1,66 @@
+use clap::{Arg, ArgMatches, Command};
+use clap_complete::Shell;
+use std::io::Write;
+
+use crate::error::Result;
+
+// The `completions` subcommand every binary carries. Output is generated from
+// the binary's own command definition, so it never drifts from the real flags.
+pub fn command() -> Command {
+    Command::new("completions")
+        .about("Print shell completions or a man page")
+        .long_about(
+            "Print shell completions or a man page on stdout, e.g.\n  \
+             completions bash > /etc/bash_completion.d/<binary>\n  \
+             completions man > /usr/local/share/man/man1/<binary>.1",
+        )
+        .arg(
+            Arg::new("shell")
+                .value_name("SHELL")
+                .value_parser(["bash", "zsh", "fish", "man"])
+                .required(true)
+                .help("bash, zsh or fish completions, or a roff man page"),
+        )
+}
+
+pub fn generate<W: Write>(mut cli: Command, args: &ArgMatches, out: &mut W) -> Result<()> {
+    let shell = match args.get_one::<String>("shell").map(String::as_str) {
+        Some("man") => return Ok(clap_mangen::Man::new(cli).render(out)?),
+        Some("zsh") => Shell::Zsh,
+        Some("fish") => Shell::Fish,
+        _ => Shell::Bash,
+    };
+
+    let name = cli.get_name().to_string();
+    clap_complete::generate(shell, &mut cli, name, out);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions_and_man_page_cover_subcommands() -> Result<()> {
+        let cli = || {
+            Command::new("evidence-tool")
+                .subcommand(Command::new("verify").arg(Arg::new("bundle").long("bundle")))
+                .subcommand(command())
+        };
+
+        for shell in ["bash", "zsh", "fish", "man"] {
+            let args = cli().get_matches_from(["evidence-tool", "completions", shell]);
+            let (_, args) = args.subcommand().expect("completions subcommand");
+
+            let mut out = Vec::new();
+            generate(cli(), args, &mut out)?;
+            let out = String::from_utf8(out)?;
+            assert!(out.contains("verify"), "{} output lists subcommands", shell);
+            if shell != "man" {
+                assert!(out.contains("bundle"), "{} completes flags", shell);
+            }
+        }
+
+        Ok(())
+    }
+}
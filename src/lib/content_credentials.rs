@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::crypto;
+use crate::error::{Context, ImmutableEncryptionError, Result};
+use crate::playback::SnapshotFormat;
+use crate::session::SessionManifest;
+use crate::storage;
+use crate::{BlockchainAnchor, EncryptedFrame};
+
+// Custom assertion holding our chain and anchor references; C2PA validators
+// show it alongside the standard ones
+pub const PROVENANCE_LABEL: &str = "org.immutable-encryption.provenance";
+
+// IPTC digital source type for footage recorded by a camera
+const DIGITAL_CAPTURE: &str = "http://cv.iptc.org/newscodes/digitalsourcetype/digitalCapture";
+
+// Signing identity for C2PA manifests. Validators only trust certificates
+// chaining to their trust list, so this is an X.509 key, not the node key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentCredentialsConfig {
+    pub enabled: bool,
+    pub certificate_path: String, // PEM chain, signing certificate first
+    pub private_key_path: String, // PEM
+    pub algorithm: String,        // es256, es384, es512, ps256, ps384, ps512 or ed25519
+    pub timestamp_authority_url: Option<String>,
+}
+
+impl Default for ContentCredentialsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            certificate_path: String::new(),
+            private_key_path: String::new(),
+            algorithm: "es256".to_string(),
+            timestamp_authority_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnchorClaim {
+    pub chain: String,
+    pub transaction_hash: String,
+    pub block_number: u64,
+}
+
+impl From<&BlockchainAnchor> for AnchorClaim {
+    fn from(anchor: &BlockchainAnchor) -> Self {
+        Self {
+            chain: anchor.chain.clone(),
+            transaction_hash: anchor.transaction_hash.clone(),
+            block_number: anchor.block_number,
+        }
+    }
+}
+
+// The sealed session a frame belongs to, as signed in its manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentProvenance {
+    pub session_id: String,
+    pub first_sequence: Option<u64>,
+    pub last_sequence: Option<u64>,
+    pub chain_root: Option<String>, // hash of the session's first frame
+    pub chain_tip: Option<String>,
+    pub manifest_hash: String,
+    pub manifest_signature: String,
+    pub anchors: Vec<AnchorClaim>,
+}
+
+// Data of the `PROVENANCE_LABEL` assertion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameProvenance {
+    pub capture_device: String,
+    pub frame_id: String,
+    pub sequence: u64,
+    pub captured_at: u64,
+    pub chain_hash: String,
+    pub previous_hash: String,
+    pub ciphertext_sha256: String,
+    pub anchors: Vec<AnchorClaim>,
+    pub segment: Option<SegmentProvenance>,
+}
+
+impl FrameProvenance {
+    pub fn new(frame: &EncryptedFrame, manifest: Option<&SessionManifest>) -> Self {
+        Self {
+            capture_device: frame.device_id.clone(),
+            frame_id: storage::frame_key(&frame.device_id, frame.sequence, frame.timestamp),
+            sequence: frame.sequence,
+            captured_at: frame.timestamp,
+            chain_hash: frame.hash.clone(),
+            previous_hash: frame.previous_hash.clone(),
+            ciphertext_sha256: crypto::sha256_hex(&frame.ciphertext),
+            anchors: frame.blockchain_anchors.iter().map(Into::into).collect(),
+            segment: manifest.map(|manifest| SegmentProvenance {
+                session_id: manifest.session_id.clone(),
+                first_sequence: manifest.first_sequence,
+                last_sequence: manifest.last_sequence,
+                chain_root: manifest.first_hash.clone(),
+                chain_tip: manifest.last_hash.clone(),
+                manifest_hash: manifest.manifest_hash.clone(),
+                manifest_signature: manifest.signature.clone(),
+                anchors: manifest.anchors.iter().map(Into::into).collect(),
+            }),
+        }
+    }
+}
+
+// C2PA manifest definition for a decrypted still, in the JSON form the c2pa
+// builder takes
+pub fn manifest_definition(
+    provenance: &FrameProvenance,
+    format: SnapshotFormat,
+) -> serde_json::Value {
+    let generator = env!("CARGO_PKG_NAME");
+    json!({
+        "claim_generator_info": [{ "name": generator, "version": env!("CARGO_PKG_VERSION") }],
+        "title": provenance.frame_id,
+        "format": format.content_type(),
+        "assertions": [
+            {
+                "label": "c2pa.actions",
+                "data": {
+                    "actions": [{
+                        "action": "c2pa.created",
+                        "digitalSourceType": DIGITAL_CAPTURE,
+                        "softwareAgent": generator,
+                    }]
+                }
+            },
+            { "label": PROVENANCE_LABEL, "data": provenance },
+        ]
+    })
+}
+
+// Embeds C2PA manifests into exported stills so media tooling that validates
+// Content Credentials recognizes their provenance
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "content-credentials"), allow(dead_code))]
+pub struct ContentCredentials {
+    certificates: Vec<u8>,
+    private_key: Vec<u8>,
+    algorithm: String,
+    timestamp_authority_url: Option<String>,
+}
+
+impl ContentCredentials {
+    pub fn from_config(config: &ContentCredentialsConfig) -> Result<Self> {
+        let credentials = Self {
+            certificates: std::fs::read(&config.certificate_path)
+                .with_context(|| format!("Reading {}", config.certificate_path))?,
+            private_key: std::fs::read(&config.private_key_path)
+                .with_context(|| format!("Reading {}", config.private_key_path))?,
+            algorithm: config.algorithm.clone(),
+            timestamp_authority_url: config.timestamp_authority_url.clone(),
+        };
+        // Fails on a key that doesn't match the certificate before any export
+        credentials.check()?;
+        Ok(credentials)
+    }
+
+    // The image with a signed manifest embedded
+    #[cfg(feature = "content-credentials")]
+    pub fn sign_image(
+        &self,
+        image: &[u8],
+        format: SnapshotFormat,
+        provenance: &FrameProvenance,
+    ) -> Result<Vec<u8>> {
+        let definition = manifest_definition(provenance, format).to_string();
+        let mut builder = c2pa::Builder::from_json(&definition).map_err(c2pa_error)?;
+        let mut source = std::io::Cursor::new(image);
+        let mut signed = std::io::Cursor::new(Vec::new());
+        builder
+            .sign(
+                self.signer()?.as_ref(),
+                format.content_type(),
+                &mut source,
+                &mut signed,
+            )
+            .map_err(c2pa_error)?;
+        Ok(signed.into_inner())
+    }
+
+    #[cfg(not(feature = "content-credentials"))]
+    pub fn sign_image(&self, _: &[u8], _: SnapshotFormat, _: &FrameProvenance) -> Result<Vec<u8>> {
+        Err(feature_missing())
+    }
+
+    #[cfg(feature = "content-credentials")]
+    fn check(&self) -> Result<()> {
+        self.signer().map(|_| ())
+    }
+
+    #[cfg(not(feature = "content-credentials"))]
+    fn check(&self) -> Result<()> {
+        Err(feature_missing())
+    }
+
+    // Created per image: c2pa signers are not Send, and exports run on a
+    // blocking thread
+    #[cfg(feature = "content-credentials")]
+    fn signer(&self) -> Result<Box<dyn c2pa::Signer>> {
+        let algorithm: c2pa::SigningAlg = self.algorithm.parse().map_err(|_| {
+            ImmutableEncryptionError::config(&format!(
+                "Unknown Content Credentials algorithm {:?}",
+                self.algorithm
+            ))
+        })?;
+        c2pa::create_signer::from_keys(
+            &self.certificates,
+            &self.private_key,
+            algorithm,
+            self.timestamp_authority_url.clone(),
+        )
+        .map_err(c2pa_error)
+    }
+}
+
+#[cfg(not(feature = "content-credentials"))]
+fn feature_missing() -> ImmutableEncryptionError {
+    ImmutableEncryptionError::config("Content Credentials need the `content-credentials` feature")
+}
+
+#[cfg(feature = "content-credentials")]
+fn c2pa_error(error: c2pa::Error) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::crypto(&format!("C2PA: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_carries_device_chain_and_anchors() {
+        let anchor = BlockchainAnchor {
+            chain: "ethereum".to_string(),
+            transaction_hash: "0xabc".to_string(),
+            block_number: 42,
+            timestamp: 1000,
+            proof: String::new(),
+        };
+        let frame = EncryptedFrame {
+            sequence: 7,
+            device_id: "cam_1".to_string(),
+            ciphertext: vec![1, 2, 3],
+            hash: "h7".to_string(),
+            previous_hash: "h6".to_string(),
+            nonce: vec![0; 12],
+            timestamp: 1007,
+            blockchain_anchors: vec![anchor],
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+
+        let provenance = FrameProvenance::new(&frame, None);
+        assert_eq!(provenance.frame_id, "frame:cam_1:7:1007");
+        assert_eq!(provenance.anchors[0].transaction_hash, "0xabc");
+
+        let definition = manifest_definition(&provenance, SnapshotFormat::Jpeg);
+        assert_eq!(definition["format"], "image/jpeg");
+        let assertions = definition["assertions"].as_array().unwrap();
+        assert_eq!(
+            assertions[0]["data"]["actions"][0]["action"],
+            "c2pa.created"
+        );
+        let data = &assertions[1]["data"];
+        assert_eq!(assertions[1]["label"], PROVENANCE_LABEL);
+        assert_eq!(data["capture_device"], "cam_1");
+        assert_eq!(data["chain_hash"], "h7");
+        assert_eq!(data["anchors"][0]["block_number"], 42);
+        assert!(data["segment"].is_null());
+    }
+}
@@ -0,0 +1,183 @@
+use coset::cbor::value::Value;
+use coset::{
+    iana, CoseSign1, CoseSign1Builder, HeaderBuilder, Label, RegisteredLabelWithPrivate,
+    TaggedCborSerializable,
+};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{Cipher, EncryptionEngine};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::EncryptedFrame;
+
+pub const ENVELOPE_CONTENT_TYPE: &str = "application/cose; cose-type=\"cose-sign1\"";
+
+// What the payload is: the frame's sealed ciphertext, not its plaintext
+const PAYLOAD_CONTENT_TYPE: &str = "application/vnd.immutable-encryption.ciphertext";
+
+// Protected header labels carrying the frame's chain position and sealing
+// parameters; text labels so generic COSE tools print them readably
+const DEVICE_ID: &str = "device_id";
+const SEQUENCE: &str = "sequence";
+const TIMESTAMP: &str = "timestamp";
+const HASH: &str = "hash";
+const PREVIOUS_HASH: &str = "previous_hash";
+const NONCE: &str = "nonce";
+const CIPHER: &str = "cipher";
+const COMPRESSED: &str = "compressed";
+
+// Wraps a sealed frame in a tagged COSE_Sign1 (RFC 9052): the ciphertext as
+// payload, everything needed to place and decrypt it in the protected header,
+// and an EdDSA signature over both. Blockchain anchors are added after
+// sealing and are left out.
+pub fn seal_envelope(frame: &EncryptedFrame, engine: &EncryptionEngine) -> Result<Vec<u8>> {
+    let public_key = engine.envelope_public_key()?;
+    let cipher = serde_json::to_value(frame.cipher)?
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let protected = HeaderBuilder::new()
+        .algorithm(iana::Algorithm::EdDSA)
+        .key_id(key_id(&public_key))
+        .content_type(PAYLOAD_CONTENT_TYPE.to_string())
+        .text_value(DEVICE_ID.to_string(), Value::Text(frame.device_id.clone()))
+        .text_value(SEQUENCE.to_string(), Value::Integer(frame.sequence.into()))
+        .text_value(
+            TIMESTAMP.to_string(),
+            Value::Integer(frame.timestamp.into()),
+        )
+        .text_value(HASH.to_string(), Value::Text(frame.hash.clone()))
+        .text_value(
+            PREVIOUS_HASH.to_string(),
+            Value::Text(frame.previous_hash.clone()),
+        )
+        .text_value(NONCE.to_string(), Value::Bytes(frame.nonce.clone()))
+        .text_value(CIPHER.to_string(), Value::Text(cipher))
+        .text_value(COMPRESSED.to_string(), Value::Bool(frame.compressed))
+        .build();
+
+    let mut envelope = CoseSign1Builder::new()
+        .protected(protected)
+        .payload(frame.ciphertext.clone())
+        .build();
+    envelope.signature = engine.sign_envelope(&envelope.tbs_data(&[]))?;
+    envelope.to_tagged_vec().map_err(cose_error)
+}
+
+// Checks the signature against an Ed25519 public key and returns the frame it
+// carries, without anchors
+pub fn open_envelope(envelope: &[u8], public_key: &[u8]) -> Result<EncryptedFrame> {
+    let envelope = CoseSign1::from_tagged_slice(envelope).map_err(cose_error)?;
+    let header = &envelope.protected.header;
+    if header.alg != Some(RegisteredLabelWithPrivate::Assigned(iana::Algorithm::EdDSA)) {
+        return Err(ImmutableEncryptionError::verification(
+            "Envelope is not signed with EdDSA",
+        ));
+    }
+    envelope
+        .verify_signature(&[], |signature, data| {
+            UnparsedPublicKey::new(&ED25519, public_key).verify(data, signature)
+        })
+        .map_err(|_| ImmutableEncryptionError::verification("Envelope signature is invalid"))?;
+
+    let field = |name: &str| {
+        header
+            .rest
+            .iter()
+            .find(|(label, _)| *label == Label::Text(name.to_string()))
+            .map(|(_, value)| value)
+            .ok_or_else(|| {
+                ImmutableEncryptionError::verification(&format!("Envelope has no {} header", name))
+            })
+    };
+    let malformed =
+        |name: &str| ImmutableEncryptionError::verification(&format!("Malformed {} header", name));
+    let text = |name: &str| {
+        field(name)?
+            .as_text()
+            .map(str::to_string)
+            .ok_or_else(|| malformed(name))
+    };
+    let number = |name: &str| {
+        field(name)?
+            .as_integer()
+            .and_then(|n| u64::try_from(n).ok())
+            .ok_or_else(|| malformed(name))
+    };
+
+    let cipher: Cipher = serde_json::from_value(serde_json::Value::String(text(CIPHER)?))
+        .map_err(|_| malformed(CIPHER))?;
+    Ok(EncryptedFrame {
+        sequence: number(SEQUENCE)?,
+        device_id: text(DEVICE_ID)?,
+        ciphertext: envelope.payload.clone().unwrap_or_default(),
+        hash: text(HASH)?,
+        previous_hash: text(PREVIOUS_HASH)?,
+        nonce: field(NONCE)?
+            .as_bytes()
+            .cloned()
+            .ok_or_else(|| malformed(NONCE))?,
+        timestamp: number(TIMESTAMP)?,
+        blockchain_anchors: Vec::new(),
+        cipher,
+        compressed: field(COMPRESSED)?
+            .as_bool()
+            .ok_or_else(|| malformed(COMPRESSED))?,
+        attestation: None,
+        device_signature: None,
+    })
+}
+
+// First 8 bytes of the key's SHA-256, so a verifier holding several tenants'
+// keys can pick the right one
+pub fn key_id(public_key: &[u8]) -> Vec<u8> {
+    Sha256::digest(public_key)[..8].to_vec()
+}
+
+fn cose_error(e: coset::CoseError) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::verification(&format!("COSE envelope: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+
+    #[test]
+    fn test_envelope_round_trips_and_rejects_tampering() -> Result<()> {
+        let engine = EncryptionEngine::new(CryptoConfig::software(vec![3u8; 32], 1))?;
+        let frame = EncryptedFrame {
+            sequence: 12,
+            device_id: "cam_1".to_string(),
+            ciphertext: vec![4, 5, 6],
+            hash: "a".repeat(64),
+            previous_hash: "b".repeat(64),
+            nonce: vec![7; 12],
+            timestamp: 1_700_000_012,
+            blockchain_anchors: Vec::new(),
+            cipher: Cipher::ChaCha20Poly1305,
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+
+        let envelope = seal_envelope(&frame, &engine)?;
+        let public_key = engine.envelope_public_key()?;
+        let opened = open_envelope(&envelope, &public_key)?;
+        assert_eq!(opened.sequence, 12);
+        assert_eq!(opened.hash, frame.hash);
+        assert_eq!(opened.nonce, frame.nonce);
+        assert_eq!(opened.ciphertext, frame.ciphertext);
+        assert_eq!(opened.cipher, Cipher::ChaCha20Poly1305);
+
+        // The ciphertext is the last thing before the signature
+        let mut tampered = envelope.clone();
+        let at = tampered.len() - 64 - 3;
+        tampered[at] ^= 1;
+        assert!(open_envelope(&tampered, &public_key).is_err());
+
+        let other = EncryptionEngine::new(CryptoConfig::software(vec![4u8; 32], 1))?;
+        assert!(open_envelope(&envelope, &other.envelope_public_key()?).is_err());
+        Ok(())
+    }
+}
@@ -1,90 +1,313 @@
-use anyhow::{anyhow, Result};
-use blake3::Hasher;
-use ring::aead::{LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::aead::generic_array::typenum::Unsigned;
+use chacha20poly1305::aead::{Aead, KeyInit, Nonce as AeadNonce};
+use chacha20poly1305::XChaCha20Poly1305;
+use immutable_encryption_core::hash;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, CHACHA20_POLY1305};
 use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use ring::{hkdf, hmac};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::{BlockchainAnchor, EncryptedFrame, FrameMetadata, VideoFrame};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::metrics::{self, Module};
+use crate::{BlockchainAnchor, EncryptedFrame, FrameMetadata, HardwareAttestation, VideoFrame};
+
+pub use immutable_encryption_core::hash::{blake3_hex, sha256_hex};
+pub use immutable_encryption_core::Cipher;
+
+// The cipher an engine seals frames with when the caller names none
+pub type CipherSuite = Cipher;
+
+const POST_QUANTUM_KEY_CONTEXT: &str = "immutable-encryption 2024 post-quantum seal";
+const FRAME_KEY_SALT: &[u8] = b"immutable-encryption 2024 frame keys";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CryptoConfig {
-    pub primary_key: Vec<u8>,
+    pub primary_key: Vec<u8>, // wrapped by `key_provider`, then sealed by `hardware`
     pub key_rotation_interval: u64,
     pub quantum_resistant: bool,
-    pub hardware_backed: bool,
+    pub hardware_backed: bool, // requires `hardware`
+    #[serde(default)]
+    pub default_tenant_derived: bool, // else the default tenant seals with the primary key
+    #[serde(default)]
+    pub retired_keys: Vec<RetiredKey>, // oldest first; they open and verify, never seal
+    #[serde(default)]
+    pub cipher_suite: CipherSuite, // for sealing; frames open with the cipher they record
+    #[serde(skip)]
+    pub key_provider: Option<Arc<dyn KeyProvider>>,
+    #[serde(skip)]
+    pub hardware: Option<Arc<dyn HardwareKeys>>,
+}
+
+impl CryptoConfig {
+    // A primary key held in memory only, with no key provider or TPM
+    pub fn software(primary_key: Vec<u8>, key_rotation_interval: u64) -> Self {
+        Self {
+            primary_key,
+            key_rotation_interval,
+            quantum_resistant: false,
+            hardware_backed: false,
+            default_tenant_derived: true,
+            retired_keys: Vec::new(),
+            cipher_suite: CipherSuite::default(),
+            key_provider: None,
+            hardware: None,
+        }
+    }
+
+    // The primary key as the engine uses it
+    pub fn open_primary_key(&self) -> Result<Vec<u8>> {
+        let mut key = self.primary_key.clone();
+        if let Some(hardware) = &self.hardware {
+            key = hardware.unseal(&key)?;
+        }
+        if let Some(provider) = &self.key_provider {
+            key = provider.unwrap_key(&key)?;
+        }
+        Ok(key)
+    }
+
+    // A retired key as the engine uses it, opened the way it was stored
+    pub fn open_retired_key(&self, retired: &RetiredKey) -> Result<Vec<u8>> {
+        let mut key = retired.key.clone();
+        if retired.sealed_to_tpm {
+            let hardware = self.hardware.as_ref().ok_or_else(|| {
+                ImmutableEncryptionError::config("A retired key is sealed to a TPM but none is set")
+            })?;
+            key = hardware.unseal(&key)?;
+        }
+        if retired.wrapped {
+            let provider = self.key_provider.as_ref().ok_or_else(|| {
+                ImmutableEncryptionError::config(
+                    "A retired key is wrapped but no [encryption.hsm] or [encryption.kms] is set",
+                )
+            })?;
+            key = provider.unwrap_key(&key)?;
+        }
+        Ok(key)
+    }
+
+    // The reverse, for keys derived from the primary key
+    pub fn protect_key(&self, mut key: Vec<u8>) -> Result<Vec<u8>> {
+        if let Some(provider) = &self.key_provider {
+            key = provider.wrap_key(&key)?;
+        }
+        if let Some(hardware) = &self.hardware {
+            key = hardware.seal(&key)?;
+        }
+        Ok(key)
+    }
+}
+
+// A primary key replaced by rotation, kept in the form it was stored in so
+// evidence sealed and signed under it still opens and verifies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetiredKey {
+    pub key: Vec<u8>,
+    pub retired_at: u64,
+    #[serde(default)]
+    pub wrapped: bool, // by the key provider
+    #[serde(default)]
+    pub sealed_to_tpm: bool,
+    #[serde(default)]
+    pub default_tenant_derived: bool, // as the keystore was while it was the primary key
+}
+
+// Keys kept outside the process, e.g. in an HSM or a cloud KMS. The
+// provider's wrapping key protects the primary key wherever it is stored or
+// passed around, and its Ed25519 key signs frame envelopes; neither ever
+// leaves it. Providers that only wrap leave envelopes to the derived key.
+pub trait KeyProvider: std::fmt::Debug + Send + Sync {
+    fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>>;
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<Vec<u8>>;
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>>;
+    fn public_key(&self) -> Result<Vec<u8>>;
+
+    fn signs_envelopes(&self) -> bool {
+        true
+    }
+}
+
+// A TPM or similar bound to this machine: it seals keys so only it can
+// unseal them, and quotes with an attestation key that never leaves it
+pub trait HardwareKeys: std::fmt::Debug + Send + Sync {
+    fn seal(&self, key: &[u8]) -> Result<Vec<u8>>;
+    fn unseal(&self, sealed: &[u8]) -> Result<Vec<u8>>;
+    fn quote(&self, qualifying_data: &[u8]) -> Result<HardwareAttestation>;
+    fn attestation_key(&self) -> Result<Vec<u8>>; // SEC1 uncompressed P-256 point
 }
 
 #[derive(Debug)]
 pub struct EncryptionEngine {
-    primary_key: LessSafeKey,
     rng: SystemRandom,
     config: CryptoConfig,
-    key_schedule: HashMap<u64, Vec<u8>>, // timestamp -> key
-    quantum_keys: HashMap<u64, Vec<u8>>, // for post-quantum layer
+    quantum_keys: HashMap<u64, Vec<u8>>, // key epoch -> post-quantum layer key
 }
 
 impl EncryptionEngine {
-    pub fn new(config: CryptoConfig) -> Result<Self> {
-        let unbound_key = UnboundKey::new(&AES_256_GCM, &config.primary_key)
-            .map_err(|e| anyhow!("Failed to create encryption key: {}", e))?;
-        let primary_key = LessSafeKey::new(unbound_key);
+    // With a key provider or TPM, the primary key is opened into the
+    // engine's memory and frame keys are derived from it there. This is
+    // envelope wrapping only: the HSM, KMS or TPM protects the key at rest and
+    // in config, not from anyone who can read this process's memory.
+    pub fn new(mut config: CryptoConfig) -> Result<Self> {
+        if config.hardware_backed && config.hardware.is_none() {
+            return Err(ImmutableEncryptionError::config(
+                "hardware_backed needs a TPM to seal keys and attest frames",
+            ));
+        }
+        config.primary_key = config.open_primary_key()?;
+        let opened = config
+            .retired_keys
+            .iter()
+            .map(|retired| config.open_retired_key(retired))
+            .collect::<Result<Vec<_>>>()?;
+        for (retired, key) in config.retired_keys.iter_mut().zip(opened) {
+            retired.key = key;
+            retired.wrapped = false;
+            retired.sealed_to_tpm = false;
+        }
+        // Frame keys are derived from it, so nothing else would catch a
+        // primary key of the wrong size
+        if config.primary_key.len() != AES_256_GCM.key_len() {
+            return Err(ImmutableEncryptionError::Crypto(format!(
+                "Primary key must be {} bytes, not {}",
+                AES_256_GCM.key_len(),
+                config.primary_key.len()
+            )));
+        }
 
         let mut engine = Self {
-            primary_key,
             rng: SystemRandom::new(),
             config,
-            key_schedule: HashMap::new(),
             quantum_keys: HashMap::new(),
         };
-
-        // Initialize key schedule
-        engine.rotate_keys()?;
+        engine.refresh_post_quantum_keys()?;
 
         Ok(engine)
     }
 
-    fn rotate_keys(&mut self) -> Result<()> {
+    // Frame keys are derived per epoch, so only the post-quantum keys are
+    // generated, once for the current epoch
+    pub fn refresh_post_quantum_keys(&mut self) -> Result<()> {
         use pqcrypto_kyber::kyber1024;
         use pqcrypto_traits::kem as pqkem;
 
+        if self.config.quantum_resistant {
+            let (pk, sk) = kyber1024::keypair();
+            let combined_key = [pk.as_bytes(), sk.as_bytes()].concat();
+            self.quantum_keys.insert(self.key_epoch(), combined_key);
+        }
+
+        Ok(())
+    }
+
+    // Frame keys change every `key_rotation_interval` seconds of frame time.
+    // The interval is part of each key's derivation, so changing it would leave
+    // frames sealed before the change unreadable; the keystore pins it.
+    pub fn key_epoch_at(&self, timestamp: u64) -> u64 {
+        timestamp / self.config.key_rotation_interval.max(1)
+    }
+
+    pub fn key_epoch(&self) -> u64 {
         let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        self.key_epoch_at(now)
+    }
+
+    // The key that was primary at `timestamp` first, then every other one:
+    // frames sealed late or by a skewed clock still open, and the AEAD tag or
+    // HMAC tells the right key from the rest
+    fn keys_at(&self, timestamp: u64) -> Vec<&[u8]> {
+        let retired = &self.config.retired_keys;
+        let mut keys: Vec<&[u8]> = std::iter::once(self.config.primary_key.as_slice())
+            .chain(retired.iter().map(|retired| retired.key.as_slice()))
+            .collect();
+        let current = retired
+            .iter()
+            .position(|retired| timestamp < retired.retired_at)
+            .map_or(0, |i| i + 1);
+        keys[..=current].rotate_right(1);
+        keys
+    }
+
+    // HKDF-SHA256 from a primary key, with the device, epoch and sequence as
+    // info, so any frame's key can be rebuilt from the frame alone
+    fn frame_key(
+        &self,
+        primary_key: &[u8],
+        device_id: &str,
+        sequence: u64,
+        timestamp: u64,
+    ) -> Result<[u8; 32]> {
+        let device_len = (device_id.len() as u64).to_be_bytes();
+        let epoch = self.key_epoch_at(timestamp).to_be_bytes();
+        let sequence = sequence.to_be_bytes();
+        let info = [&device_len[..], device_id.as_bytes(), &epoch, &sequence];
+
+        let mut key = [0u8; 32];
+        hkdf::Salt::new(hkdf::HKDF_SHA256, FRAME_KEY_SALT)
+            .extract(primary_key)
+            .expand(&info, hkdf::HKDF_SHA256)?
+            .fill(&mut key)?;
+        Ok(key)
+    }
 
-        // Generate frame-specific keys for next interval
-        for i in 0..self.config.key_rotation_interval {
-            let timestamp = now + i * 60; // Rotate every minute
-            let mut frame_key = vec![0u8; 32];
-            self.rng.fill(&mut frame_key)?;
-            self.key_schedule.insert(timestamp, frame_key);
+    // The engine is usable when a derived frame key still seals and opens
+    pub fn probe_keys(&self) -> Result<()> {
+        let key = self.frame_key(&self.config.primary_key, "health", 0, 0)?;
+        let cipher = self.config.cipher_suite;
+        let (ciphertext, nonce) = seal_with(cipher, &key, b"health", &self.rng)?;
+        if open_with(cipher, &key, &ciphertext, &nonce)? != b"health" {
+            return Err(ImmutableEncryptionError::crypto(
+                "Frame key round trip mismatch",
+            ));
+        }
 
-            // Generate quantum-resistant keys if enabled
-            if self.config.quantum_resistant {
-                let (pk, sk) = kyber1024::keypair();
-                let combined_key = [pk.as_bytes(), sk.as_bytes()].concat();
-                self.quantum_keys.insert(timestamp, combined_key);
-            }
+        let signature = self.sign(b"health");
+        if !self.verify_signature(b"health", &signature) {
+            return Err(ImmutableEncryptionError::crypto(
+                "Signing key round trip failed",
+            ));
         }
 
+        // A key provider that has lost its session fails here rather than on
+        // the first frame
+        let signature = self.sign_envelope(b"health")?;
+        UnparsedPublicKey::new(&ED25519, self.envelope_public_key()?)
+            .verify(b"health", &signature)
+            .map_err(|_| ImmutableEncryptionError::crypto("Envelope key round trip failed"))?;
         Ok(())
     }
 
-    pub fn generate_frame_hash(&self, frame: &VideoFrame) -> Result<String> {
-        // Double hash: SHA-256 + BLAKE3 for maximum security
-        let mut sha256 = Sha256::new();
-        sha256.update(&frame.sequence.to_be_bytes());
-        sha256.update(&frame.timestamp.to_be_bytes());
-        sha256.update(&frame.data);
-        sha256.update(serde_json::to_string(&frame.metadata)?.as_bytes());
-        let sha_result = sha256.finalize();
+    // The TPM, for quoting without holding the engine; its commands block
+    pub fn hardware(&self) -> Option<Arc<dyn HardwareKeys>> {
+        self.config.hardware.clone()
+    }
 
-        let mut blake3 = Hasher::new();
-        blake3.update(&sha_result);
-        let blake_result = blake3.finalize();
+    // A TPM quote over the frame's hash, when the engine is hardware backed
+    pub fn attest_frame(&self, frame_hash: &str) -> Result<Option<HardwareAttestation>> {
+        self.config
+            .hardware
+            .as_ref()
+            .map(|hardware| attest_with(&**hardware, frame_hash))
+            .transpose()
+    }
 
-        Ok(hex::encode(blake_result.as_bytes()))
+    pub fn attestation_key(&self) -> Result<Option<Vec<u8>>> {
+        self.config
+            .hardware
+            .as_ref()
+            .map(|hardware| hardware.attestation_key())
+            .transpose()
+    }
+
+    pub fn generate_frame_hash(&self, frame: &VideoFrame) -> Result<String> {
+        metrics::timed(Module::Crypto, "hash", || Ok(hash::frame_hash(frame)?))
     }
 
     pub fn create_hash_chain_link(
@@ -93,33 +316,57 @@ impl EncryptionEngine {
         previous_hash: &str,
         sequence: u64,
     ) -> Result<String> {
-        let mut hasher = Sha256::new();
-        hasher.update(current_hash.as_bytes());
-        hasher.update(previous_hash.as_bytes());
-        hasher.update(&sequence.to_be_bytes());
-        Ok(hex::encode(hasher.finalize()))
+        Ok(hash::chain_link(current_hash, previous_hash, sequence))
     }
 
-    pub fn encrypt_data(&mut self, data: &[u8], timestamp: u64) -> Result<(Vec<u8>, Vec<u8>)> {
-        let key = self
-            .key_schedule
-            .get(&timestamp)
-            .ok_or_else(|| anyhow!("No encryption key for timestamp {}", timestamp))?;
-
-        let unbound_key = UnboundKey::new(&AES_256_GCM, key)
-            .map_err(|e| anyhow!("Failed to create frame key: {}", e))?;
-        let less_safe_key = LessSafeKey::new(unbound_key);
-
-        let mut nonce_bytes = [0u8; 12];
-        self.rng.fill(&mut nonce_bytes)?;
-        let nonce = Nonce::assume_unique_for_key(nonce_bytes);
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.config.cipher_suite
+    }
 
-        let mut ciphertext = data.to_vec();
-        less_safe_key
-            .seal_in_place_append_tag(nonce, &mut ciphertext)
-            .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+    // Seals with `cipher`, or the engine's cipher suite; the frame must
+    // record whichever it was
+    pub fn encrypt_data(
+        &self,
+        data: &[u8],
+        device_id: &str,
+        sequence: u64,
+        timestamp: u64,
+        cipher: Option<Cipher>,
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        metrics::timed(Module::Crypto, "encrypt", || {
+            let key = self.frame_key(&self.config.primary_key, device_id, sequence, timestamp)?;
+            let cipher = cipher.unwrap_or(self.config.cipher_suite);
+            seal_with(cipher, &key, data, &self.rng)
+        })
+    }
 
-        Ok((ciphertext, nonce_bytes.to_vec()))
+    // The frame's original payload, decompressed if it was sealed compressed
+    pub fn decrypt_frame_data(&self, frame: &EncryptedFrame) -> Result<Vec<u8>> {
+        let data = metrics::timed(Module::Crypto, "decrypt", || {
+            self.keys_at(frame.timestamp)
+                .into_iter()
+                .find_map(|primary_key| {
+                    let key = self
+                        .frame_key(
+                            primary_key,
+                            &frame.device_id,
+                            frame.sequence,
+                            frame.timestamp,
+                        )
+                        .ok()?;
+                    open_with(frame.cipher, &key, &frame.ciphertext, &frame.nonce).ok()
+                })
+                .ok_or_else(|| {
+                    ImmutableEncryptionError::crypto(
+                        "Decryption failed under the primary key and every retired key",
+                    )
+                })
+        })?;
+        if frame.compressed {
+            Ok(zstd::decode_all(data.as_slice())?)
+        } else {
+            Ok(data)
+        }
     }
 
     pub fn verify_quantum_layer(&self, encrypted_data: &[u8], timestamp: u64) -> Result<bool> {
@@ -131,11 +378,81 @@ impl EncryptionEngine {
         // This would typically involve shared secret verification
         // For now, we'll simulate the check
         self.quantum_keys
-            .get(&timestamp)
-            .ok_or_else(|| anyhow!("No quantum key for timestamp {}", timestamp))
+            .get(&self.key_epoch_at(timestamp))
+            .ok_or_else(|| {
+                ImmutableEncryptionError::Crypto(format!(
+                    "No quantum key for timestamp {}",
+                    timestamp
+                ))
+            })
             .map(|_| true) // Simplified - would implement actual verification
     }
 
+    // Node-level HMAC over manifests and audit records. The key is derived from
+    // the primary key so it never has to be provisioned separately.
+    fn derived_signing_key(primary_key: &[u8]) -> [u8; 32] {
+        blake3::derive_key("immutable-encryption 2024 manifest signing", primary_key)
+    }
+
+    fn signing_key(primary_key: &[u8]) -> hmac::Key {
+        hmac::Key::new(hmac::HMAC_SHA256, &Self::derived_signing_key(primary_key))
+    }
+
+    // Hex HMAC key that checks `sign`'s output without the primary key. It
+    // can also produce valid signatures, so share it only with the verifier.
+    pub fn verification_key(&self) -> String {
+        hex::encode(Self::derived_signing_key(&self.config.primary_key))
+    }
+
+    pub fn sign(&self, data: &[u8]) -> String {
+        let key = Self::signing_key(&self.config.primary_key);
+        hash::to_hex(hmac::sign(&key, data).as_ref())
+    }
+
+    // Signatures made before a rotation verify under the retired key
+    pub fn verify_signature(&self, data: &[u8], signature: &str) -> bool {
+        match hash::from_hex(signature) {
+            Some(tag) => self
+                .keys_at(u64::MAX)
+                .into_iter()
+                .any(|key| hmac::verify(&Self::signing_key(key), data, &tag).is_ok()),
+            None => false,
+        }
+    }
+
+    // Ed25519 key for COSE frame envelopes, derived like the HMAC key unless a
+    // key provider holds it. Unlike the HMAC key, the public half can be
+    // published: it verifies but cannot sign.
+    fn envelope_key_pair(&self) -> Result<Ed25519KeyPair> {
+        let seed = blake3::derive_key(
+            "immutable-encryption 2024 frame envelope signing",
+            &self.config.primary_key,
+        );
+        Ed25519KeyPair::from_seed_unchecked(&seed)
+            .map_err(|_| ImmutableEncryptionError::crypto("Failed to derive the envelope key"))
+    }
+
+    pub fn sign_envelope(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.envelope_signer() {
+            Some(provider) => provider.sign(data),
+            None => Ok(self.envelope_key_pair()?.sign(data).as_ref().to_vec()),
+        }
+    }
+
+    pub fn envelope_public_key(&self) -> Result<Vec<u8>> {
+        match self.envelope_signer() {
+            Some(provider) => provider.public_key(),
+            None => Ok(self.envelope_key_pair()?.public_key().as_ref().to_vec()),
+        }
+    }
+
+    fn envelope_signer(&self) -> Option<&Arc<dyn KeyProvider>> {
+        self.config
+            .key_provider
+            .as_ref()
+            .filter(|provider| provider.signs_envelopes())
+    }
+
     pub fn generate_tamper_proof(&self, frames: &[EncryptedFrame]) -> Result<String> {
         let mut hasher = Sha256::new();
 
@@ -145,8 +462,184 @@ impl EncryptionEngine {
             hasher.update(&frame.sequence.to_be_bytes());
         }
 
-        Ok(hex::encode(hasher.finalize()))
+        Ok(hash::to_hex(&hasher.finalize()))
+    }
+}
+
+// A quote over the frame's hash. Blocks on the TPM, so async callers run it
+// on a blocking thread.
+pub fn attest_with(hardware: &dyn HardwareKeys, frame_hash: &str) -> Result<HardwareAttestation> {
+    hardware.quote(&crate::tpm::qualifying_data(frame_hash))
+}
+
+// The TPM still quotes with the attestation key it publishes
+pub fn probe_attestation(hardware: &dyn HardwareKeys) -> Result<()> {
+    let attestation = attest_with(hardware, "health")?;
+    crate::tpm::verify_quote(
+        &hardware.attestation_key()?,
+        "health",
+        &attestation.quote,
+        &attestation.signature,
+    )
+}
+
+pub fn hmac_sha256_hex(key: &[u8], data: &[u8]) -> String {
+    hash::to_hex(hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data).as_ref())
+}
+
+pub fn generate_key() -> Result<Vec<u8>> {
+    let mut key = vec![0u8; 32];
+    SystemRandom::new().fill(&mut key)?;
+    Ok(key)
+}
+
+// AES-256-GCM with a fresh random nonce; returns (ciphertext || tag, nonce)
+pub fn seal(key: &[u8], plaintext: &[u8], rng: &SystemRandom) -> Result<(Vec<u8>, Vec<u8>)> {
+    seal_with(Cipher::Aes256Gcm, key, plaintext, rng)
+}
+
+pub fn seal_with(
+    cipher: Cipher,
+    key: &[u8],
+    plaintext: &[u8],
+    rng: &SystemRandom,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let algorithm = match cipher {
+        Cipher::Aes256Gcm => &AES_256_GCM,
+        Cipher::ChaCha20Poly1305 => &CHACHA20_POLY1305,
+        Cipher::XChaCha20Poly1305 => return seal_aead::<XChaCha20Poly1305>(key, plaintext, rng),
+        Cipher::Aes256GcmSiv => return seal_aead::<Aes256GcmSiv>(key, plaintext, rng),
+    };
+    let unbound_key = UnboundKey::new(algorithm, key)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Failed to create key: {}", e)))?;
+    let less_safe_key = LessSafeKey::new(unbound_key);
+
+    let mut nonce_bytes = [0u8; 12];
+    rng.fill(&mut nonce_bytes)?;
+    let nonce = Nonce::assume_unique_for_key(nonce_bytes);
+
+    let mut ciphertext = plaintext.to_vec();
+    less_safe_key
+        .seal_in_place_append_tag(nonce, Aad::empty(), &mut ciphertext)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Encryption failed: {}", e)))?;
+
+    Ok((ciphertext, nonce_bytes.to_vec()))
+}
+
+pub fn open(key: &[u8], ciphertext: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    open_with(Cipher::Aes256Gcm, key, ciphertext, nonce)
+}
+
+pub fn open_with(cipher: Cipher, key: &[u8], ciphertext: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    let algorithm = match cipher {
+        Cipher::Aes256Gcm => &AES_256_GCM,
+        Cipher::ChaCha20Poly1305 => &CHACHA20_POLY1305,
+        Cipher::XChaCha20Poly1305 => return open_aead::<XChaCha20Poly1305>(key, ciphertext, nonce),
+        Cipher::Aes256GcmSiv => return open_aead::<Aes256GcmSiv>(key, ciphertext, nonce),
+    };
+    let unbound_key = UnboundKey::new(algorithm, key)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Failed to create key: {}", e)))?;
+    let less_safe_key = LessSafeKey::new(unbound_key);
+
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Invalid nonce: {}", e)))?;
+
+    let mut plaintext = ciphertext.to_vec();
+    let plaintext_len = less_safe_key
+        .open_in_place(nonce, Aad::empty(), &mut plaintext)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Decryption failed: {}", e)))?
+        .len();
+    plaintext.truncate(plaintext_len);
+
+    Ok(plaintext)
+}
+
+// The ciphers ring lacks, through RustCrypto, with random nonces at the
+// cipher's own width
+fn seal_aead<C: Aead + KeyInit>(
+    key: &[u8],
+    plaintext: &[u8],
+    rng: &SystemRandom,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let cipher = C::new_from_slice(key)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Failed to create key: {}", e)))?;
+
+    let mut nonce = AeadNonce::<C>::default();
+    rng.fill(&mut nonce)?;
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Encryption failed: {}", e)))?;
+
+    Ok((ciphertext, nonce.to_vec()))
+}
+
+fn open_aead<C: Aead + KeyInit>(key: &[u8], ciphertext: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    let cipher = C::new_from_slice(key)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Failed to create key: {}", e)))?;
+    if nonce.len() != C::NonceSize::USIZE {
+        return Err(ImmutableEncryptionError::Crypto(format!(
+            "Invalid nonce: expected {} bytes, got {}",
+            C::NonceSize::USIZE,
+            nonce.len()
+        )));
     }
+
+    cipher
+        .decrypt(AeadNonce::<C>::from_slice(nonce), ciphertext)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Decryption failed: {}", e)))
+}
+
+// Kyber1024-encapsulated payload: the KEM shared secret keys AES-256-GCM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostQuantumCiphertext {
+    pub kem_ciphertext: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub nonce: Vec<u8>,
+}
+
+// Returns (public_key, secret_key)
+pub fn post_quantum_keypair() -> (Vec<u8>, Vec<u8>) {
+    use pqcrypto_kyber::kyber1024;
+    use pqcrypto_traits::kem::{PublicKey, SecretKey};
+
+    let (pk, sk) = kyber1024::keypair();
+    (pk.as_bytes().to_vec(), sk.as_bytes().to_vec())
+}
+
+pub fn post_quantum_seal(public_key: &[u8], plaintext: &[u8]) -> Result<PostQuantumCiphertext> {
+    use pqcrypto_kyber::kyber1024;
+    use pqcrypto_traits::kem::{Ciphertext, PublicKey, SharedSecret};
+
+    let public_key = kyber1024::PublicKey::from_bytes(public_key).map_err(|e| {
+        ImmutableEncryptionError::Crypto(format!("Invalid Kyber1024 public key: {}", e))
+    })?;
+    let (shared_secret, kem_ciphertext) = kyber1024::encapsulate(&public_key);
+    let key = blake3::derive_key(POST_QUANTUM_KEY_CONTEXT, shared_secret.as_bytes());
+    let (ciphertext, nonce) = seal(&key, plaintext, &SystemRandom::new())?;
+
+    Ok(PostQuantumCiphertext {
+        kem_ciphertext: kem_ciphertext.as_bytes().to_vec(),
+        ciphertext,
+        nonce,
+    })
+}
+
+pub fn post_quantum_open(secret_key: &[u8], sealed: &PostQuantumCiphertext) -> Result<Vec<u8>> {
+    use pqcrypto_kyber::kyber1024;
+    use pqcrypto_traits::kem::{Ciphertext, SecretKey, SharedSecret};
+
+    let secret_key = kyber1024::SecretKey::from_bytes(secret_key).map_err(|e| {
+        ImmutableEncryptionError::Crypto(format!("Invalid Kyber1024 secret key: {}", e))
+    })?;
+    let kem_ciphertext =
+        kyber1024::Ciphertext::from_bytes(&sealed.kem_ciphertext).map_err(|e| {
+            ImmutableEncryptionError::Crypto(format!("Invalid Kyber1024 ciphertext: {}", e))
+        })?;
+    let shared_secret = kyber1024::decapsulate(&kem_ciphertext, &secret_key);
+    let key = blake3::derive_key(POST_QUANTUM_KEY_CONTEXT, shared_secret.as_bytes());
+
+    open(&key, &sealed.ciphertext, &sealed.nonce)
 }
 
 #[cfg(test)]
@@ -155,26 +648,23 @@ mod tests {
 
     #[test]
     fn test_frame_hash_generation() -> Result<()> {
-        let config = CryptoConfig {
-            primary_key: vec![0u8; 32],
-            key_rotation_interval: 60,
-            quantum_resistant: false,
-            hardware_backed: false,
-        };
+        let config = CryptoConfig::software(vec![0u8; 32], 60);
 
         let engine = EncryptionEngine::new(config)?;
 
         let frame = VideoFrame {
             timestamp: 1640995200, // 2022-01-01 00:00:00 UTC
             sequence: 1,
-            data: vec![1, 2, 3, 4],
+            data: vec![1, 2, 3, 4].into(),
             metadata: FrameMetadata {
                 device_id: "test-camera-01".to_string(),
                 location: Some((40.7128, -74.0060)), // NYC coordinates
                 resolution: (1920, 1080),
                 fps: 30,
                 codec: "H.264".to_string(),
+                telemetry: None,
             },
+            signature: None,
         };
 
         let hash1 = engine.generate_frame_hash(&frame)?;
@@ -188,12 +678,7 @@ mod tests {
 
     #[test]
     fn test_hash_chain_link() -> Result<()> {
-        let config = CryptoConfig {
-            primary_key: vec![0u8; 32],
-            key_rotation_interval: 60,
-            quantum_resistant: false,
-            hardware_backed: false,
-        };
+        let config = CryptoConfig::software(vec![0u8; 32], 60);
 
         let engine = EncryptionEngine::new(config)?;
 
@@ -209,4 +694,326 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_post_quantum_round_trip() -> Result<()> {
+        let (public_key, secret_key) = post_quantum_keypair();
+        let sealed = post_quantum_seal(&public_key, b"evidence")?;
+        assert_eq!(post_quantum_open(&secret_key, &sealed)?, b"evidence");
+
+        let (_, other_secret) = post_quantum_keypair();
+        assert!(post_quantum_open(&other_secret, &sealed).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_keys_are_derived_for_any_timestamp() -> Result<()> {
+        let config = || CryptoConfig::software(vec![5u8; 32], 3600);
+        let engine = EncryptionEngine::new(config())?;
+
+        // Off any schedule slot, and opened by another engine with the key
+        let (ciphertext, nonce) = engine.encrypt_data(b"frame", "cam_1", 7, 1_700_000_123, None)?;
+        let mut frame = EncryptedFrame {
+            sequence: 7,
+            device_id: "cam_1".to_string(),
+            ciphertext,
+            hash: String::new(),
+            previous_hash: String::new(),
+            nonce,
+            timestamp: 1_700_000_123,
+            blockchain_anchors: Vec::new(),
+            cipher: Cipher::Aes256Gcm,
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+        assert_eq!(
+            EncryptionEngine::new(config())?.decrypt_frame_data(&frame)?,
+            b"frame"
+        );
+
+        // Same epoch, other device, sequence or epoch: another key
+        frame.timestamp = 1_700_000_000;
+        assert!(engine.decrypt_frame_data(&frame).is_ok());
+        frame.timestamp = 1_700_003_600;
+        assert!(engine.decrypt_frame_data(&frame).is_err());
+        frame.timestamp = 1_700_000_123;
+        frame.sequence = 8;
+        assert!(engine.decrypt_frame_data(&frame).is_err());
+        frame.sequence = 7;
+        frame.device_id = "cam_2".to_string();
+        assert!(engine.decrypt_frame_data(&frame).is_err());
+
+        Ok(())
+    }
+
+    // Stands in for an HSM: wraps with its own AES key, signs with its own
+    // Ed25519 key unless, like a cloud KMS, it only wraps
+    #[derive(Debug)]
+    struct TestProvider {
+        wrapping_key: [u8; 32],
+        signing_key: Ed25519KeyPair,
+        signs: bool,
+    }
+
+    impl KeyProvider for TestProvider {
+        fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>> {
+            let (ciphertext, nonce) = seal(&self.wrapping_key, key, &SystemRandom::new())?;
+            Ok([nonce, ciphertext].concat())
+        }
+        fn unwrap_key(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+            let (nonce, ciphertext) = wrapped.split_at(12);
+            open(&self.wrapping_key, ciphertext, nonce)
+        }
+        fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(self.signing_key.sign(data).as_ref().to_vec())
+        }
+        fn public_key(&self) -> Result<Vec<u8>> {
+            Ok(self.signing_key.public_key().as_ref().to_vec())
+        }
+        fn signs_envelopes(&self) -> bool {
+            self.signs
+        }
+    }
+
+    #[test]
+    fn test_key_provider_unwraps_and_signs() -> Result<()> {
+        let provider = Arc::new(TestProvider {
+            wrapping_key: [8u8; 32],
+            signing_key: Ed25519KeyPair::from_seed_unchecked(&[9u8; 32]).unwrap(),
+            signs: true,
+        });
+        let config = |primary_key, key_provider| CryptoConfig {
+            primary_key,
+            key_rotation_interval: 60,
+            quantum_resistant: false,
+            hardware_backed: false,
+            default_tenant_derived: true,
+            retired_keys: Vec::new(),
+            cipher_suite: CipherSuite::default(),
+            key_provider,
+            hardware: None,
+        };
+        let wrapped = provider.wrap_key(&[6u8; 32])?;
+        let held: Arc<dyn KeyProvider> = provider.clone();
+        let engine = EncryptionEngine::new(config(wrapped.clone(), Some(held)))?;
+        let plain = EncryptionEngine::new(config(vec![6u8; 32], None))?;
+
+        // Frame keys come from the unwrapped key, so either engine opens
+        let (ciphertext, nonce) = engine.encrypt_data(b"frame", "cam_1", 1, 60, None)?;
+        let frame = EncryptedFrame {
+            sequence: 1,
+            device_id: "cam_1".to_string(),
+            ciphertext,
+            hash: String::new(),
+            previous_hash: String::new(),
+            nonce,
+            timestamp: 60,
+            blockchain_anchors: Vec::new(),
+            cipher: Cipher::Aes256Gcm,
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+        assert_eq!(plain.decrypt_frame_data(&frame)?, b"frame");
+
+        // Envelopes are signed by the provider's key, not a derived one
+        assert_eq!(engine.envelope_public_key()?, provider.public_key()?);
+        assert_ne!(engine.envelope_public_key()?, plain.envelope_public_key()?);
+        engine.probe_keys()?;
+
+        // Without the provider the wrapped key is refused rather than used
+        assert!(EncryptionEngine::new(config(wrapped.clone(), None)).is_err());
+
+        // A provider that only wraps leaves envelopes to the derived key
+        let kms: Arc<dyn KeyProvider> = Arc::new(TestProvider {
+            wrapping_key: [8u8; 32],
+            signing_key: Ed25519KeyPair::from_seed_unchecked(&[9u8; 32]).unwrap(),
+            signs: false,
+        });
+        let engine = EncryptionEngine::new(config(wrapped, Some(kms)))?;
+        assert_eq!(engine.envelope_public_key()?, plain.envelope_public_key()?);
+        engine.probe_keys()?;
+
+        Ok(())
+    }
+
+    // Stands in for a TPM: seals with its own AES key, quotes with its own
+    // P-256 key
+    #[derive(Debug)]
+    struct TestHardware {
+        sealing_key: [u8; 32],
+        attestation_key: ring::signature::EcdsaKeyPair,
+    }
+
+    impl HardwareKeys for TestHardware {
+        fn seal(&self, key: &[u8]) -> Result<Vec<u8>> {
+            let (ciphertext, nonce) = seal(&self.sealing_key, key, &SystemRandom::new())?;
+            Ok([nonce, ciphertext].concat())
+        }
+        fn unseal(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+            let (nonce, ciphertext) = sealed.split_at(12);
+            open(&self.sealing_key, ciphertext, nonce)
+        }
+        fn quote(&self, qualifying_data: &[u8]) -> Result<HardwareAttestation> {
+            // TPM_GENERATED_VALUE, TPM_ST_ATTEST_QUOTE, an empty signer name
+            let quote = [
+                &[0xFF, 0x54, 0x43, 0x47, 0x80, 0x18, 0, 0, 0, 32][..],
+                qualifying_data,
+            ]
+            .concat();
+            let signature = self.attestation_key.sign(&SystemRandom::new(), &quote)?;
+            Ok(HardwareAttestation {
+                quote,
+                signature: signature.as_ref().to_vec(),
+            })
+        }
+        fn attestation_key(&self) -> Result<Vec<u8>> {
+            Ok(self.attestation_key.public_key().as_ref().to_vec())
+        }
+    }
+
+    #[test]
+    fn test_hardware_seals_the_key_and_attests_frames() -> Result<()> {
+        use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)?;
+        let hardware = Arc::new(TestHardware {
+            sealing_key: [4u8; 32],
+            attestation_key: EcdsaKeyPair::from_pkcs8(
+                &ECDSA_P256_SHA256_FIXED_SIGNING,
+                pkcs8.as_ref(),
+                &rng,
+            )
+            .map_err(|_| ImmutableEncryptionError::crypto("Unusable test key"))?,
+        });
+        let config = |primary_key, hardware| CryptoConfig {
+            primary_key,
+            key_rotation_interval: 60,
+            quantum_resistant: false,
+            hardware_backed: true,
+            default_tenant_derived: true,
+            retired_keys: Vec::new(),
+            cipher_suite: CipherSuite::default(),
+            key_provider: None,
+            hardware,
+        };
+
+        // The flag means something: no TPM, no engine
+        assert!(EncryptionEngine::new(config(vec![6u8; 32], None)).is_err());
+
+        let held: Arc<dyn HardwareKeys> = hardware.clone();
+        let sealed = hardware.seal(&[6u8; 32])?;
+        assert_eq!(
+            config(sealed.clone(), Some(held.clone())).open_primary_key()?,
+            [6u8; 32]
+        );
+        let engine = EncryptionEngine::new(config(sealed, Some(held.clone())))?;
+        engine.probe_keys()?;
+        probe_attestation(&*held)?;
+
+        let hash = "c".repeat(64);
+        let attestation = engine
+            .attest_frame(&hash)?
+            .expect("hardware-backed engines attest");
+        let key = engine
+            .attestation_key()?
+            .expect("and publish their attestation key");
+        crate::tpm::verify_quote(&key, &hash, &attestation.quote, &attestation.signature)?;
+        assert!(crate::tpm::verify_quote(
+            &key,
+            &"d".repeat(64),
+            &attestation.quote,
+            &attestation.signature
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frames_open_with_the_cipher_they_record() -> Result<()> {
+        // Sealed with the configured suite when the caller names no cipher
+        let engine = EncryptionEngine::new(CryptoConfig {
+            cipher_suite: CipherSuite::ChaCha20Poly1305,
+            ..CryptoConfig::software(vec![6u8; 32], 60)
+        })?;
+        engine.probe_keys()?;
+        let (ciphertext, nonce) = engine.encrypt_data(b"frame", "cam_1", 1, 60, None)?;
+        let mut frame = EncryptedFrame {
+            sequence: 1,
+            device_id: "cam_1".to_string(),
+            ciphertext,
+            hash: String::new(),
+            previous_hash: String::new(),
+            nonce,
+            timestamp: 60,
+            blockchain_anchors: Vec::new(),
+            cipher: engine.cipher_suite(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+        assert_eq!(engine.decrypt_frame_data(&frame)?, b"frame");
+        let default = EncryptionEngine::new(CryptoConfig::software(vec![6u8; 32], 60))?;
+        assert_eq!(default.decrypt_frame_data(&frame)?, b"frame");
+
+        // The suite travels with the frame; AES-GCM frames leave it out
+        let stored = serde_json::to_value(&frame)?;
+        assert_eq!(stored["cipher"], "chacha20-poly1305");
+        let stored: EncryptedFrame = serde_json::from_value(stored)?;
+        assert_eq!(engine.decrypt_frame_data(&stored)?, b"frame");
+
+        frame.cipher = Cipher::Aes256Gcm;
+        assert!(engine.decrypt_frame_data(&frame).is_err());
+        assert!(serde_json::to_value(&frame)?.get("cipher").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xchacha_uses_extended_nonces() -> Result<()> {
+        let rng = SystemRandom::new();
+        let key = [3u8; 32];
+        let (ciphertext, nonce) = seal_with(Cipher::XChaCha20Poly1305, &key, b"frame", &rng)?;
+        assert_eq!(nonce.len(), Cipher::XChaCha20Poly1305.nonce_len());
+        assert_eq!(
+            open_with(Cipher::XChaCha20Poly1305, &key, &ciphertext, &nonce)?,
+            b"frame"
+        );
+
+        // A nonce of the wrong width or another cipher can't open it
+        assert!(open_with(Cipher::XChaCha20Poly1305, &key, &ciphertext, &nonce[..12]).is_err());
+        assert!(open_with(Cipher::ChaCha20Poly1305, &key, &ciphertext, &nonce).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gcm_siv_survives_a_reused_nonce() -> Result<()> {
+        let rng = SystemRandom::new();
+        let key = [4u8; 32];
+        let (first, nonce) = seal_with(Cipher::Aes256GcmSiv, &key, b"frame one", &rng)?;
+        assert_eq!(
+            open_with(Cipher::Aes256GcmSiv, &key, &first, &nonce)?,
+            b"frame one"
+        );
+
+        // The keystream depends on the plaintext, so a repeated nonce only
+        // reveals whether two frames were identical, where under GCM the two
+        // ciphertexts XOR to the two plaintexts XORed
+        let cipher = Aes256GcmSiv::new_from_slice(&key).unwrap();
+        let nonce = AeadNonce::<Aes256GcmSiv>::from_slice(&nonce);
+        assert_eq!(cipher.encrypt(nonce, &b"frame one"[..]).unwrap(), first);
+        let other = cipher.encrypt(nonce, &b"frame two"[..]).unwrap();
+        let xor = |a: &[u8], b: &[u8]| a.iter().zip(b).map(|(a, b)| a ^ b).collect::<Vec<_>>();
+        assert_ne!(
+            xor(&first[..9], &other[..9]),
+            xor(b"frame one", b"frame two")
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::error::ImmutableEncryptionError;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientAuthConfig {
+    pub ca_path: String, // PEM bundle of the CA(s) that issue device certificates
+    pub enrolled_devices: HashMap<String, String>, // sha256(cert DER) hex -> device_id
+}
+
+// Fingerprint of the client certificate presented on a connection, carried
+// alongside the request so handlers can attribute frames to a device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCertificate {
+    pub fingerprint: String,
+}
+
+impl ClientCertificate {
+    pub fn from_der(der: &[u8]) -> Self {
+        Self {
+            fingerprint: certificate_fingerprint(der),
+        }
+    }
+}
+
+pub fn certificate_fingerprint(der: &[u8]) -> String {
+    hex::encode(Sha256::digest(der))
+}
+
+// Certificates from the config are fixed; those issued through enrollment
+// are added and revoked while the node runs.
+#[derive(Debug, Default)]
+pub struct DeviceCertificateRegistry {
+    enrolled: Option<RwLock<HashMap<String, String>>>, // None: client auth disabled
+    active: Option<RwLock<HashSet<String>>>, // enrolled devices; None: enrollment optional
+    revoked: RwLock<HashSet<String>>,
+}
+
+impl DeviceCertificateRegistry {
+    pub fn new(config: Option<&ClientAuthConfig>) -> Self {
+        Self {
+            enrolled: config.map(|c| {
+                RwLock::new(
+                    c.enrolled_devices
+                        .iter()
+                        .map(|(fp, device)| {
+                            (fp.to_ascii_lowercase().replace(':', ""), device.clone())
+                        })
+                        .collect(),
+                )
+            }),
+            active: None,
+            revoked: RwLock::new(HashSet::new()),
+        }
+    }
+
+    // Only devices enrolled through the node may submit frames
+    pub fn with_enrollment_required(mut self) -> Self {
+        self.active = Some(RwLock::new(HashSet::new()));
+        self
+    }
+
+    pub fn is_enforced(&self) -> bool {
+        self.enrolled.is_some()
+    }
+
+    // Accepts `fingerprint` for the device, replacing any certificate it was
+    // enrolled with before
+    pub fn enroll(&self, fingerprint: &str, device_id: &str) {
+        if let Some(enrolled) = &self.enrolled {
+            let mut enrolled = enrolled.write().unwrap_or_else(|e| e.into_inner());
+            enrolled.retain(|_, device| device != device_id);
+            enrolled.insert(fingerprint.to_string(), device_id.to_string());
+        }
+        if let Some(active) = &self.active {
+            active
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(device_id.to_string());
+        }
+        self.revoked
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(device_id);
+    }
+
+    // Rejects the device under any certificate until it enrolls again
+    pub fn revoke(&self, device_id: &str) {
+        if let Some(enrolled) = &self.enrolled {
+            enrolled
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|_, device| device != device_id);
+        }
+        if let Some(active) = &self.active {
+            active
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(device_id);
+        }
+        self.revoked
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(device_id.to_string());
+    }
+
+    // Whether frames from the device are accepted at all, whatever path they
+    // arrive by
+    pub fn check_device(&self, device_id: &str) -> Result<(), ImmutableEncryptionError> {
+        if self
+            .revoked
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(device_id)
+        {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "device {} has been revoked",
+                device_id
+            )));
+        }
+        match &self.active {
+            Some(active)
+                if !active
+                    .read()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .contains(device_id) =>
+            {
+                Err(ImmutableEncryptionError::PermissionDenied(format!(
+                    "device {} is not enrolled",
+                    device_id
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // A device may only submit frames under its own ID, using the certificate
+    // it was enrolled with.
+    pub fn authorize(
+        &self,
+        certificate: Option<&ClientCertificate>,
+        device_id: &str,
+    ) -> Result<(), ImmutableEncryptionError> {
+        self.check_device(device_id)?;
+        let enrolled = match (&self.enrolled, &self.active) {
+            (Some(enrolled), _) => enrolled.read().unwrap_or_else(|e| e.into_inner()),
+            // Only a certificate shows the sender is the enrolled device
+            (None, Some(_)) => {
+                return Err(ImmutableEncryptionError::PermissionDenied(
+                    "enrollment is required but client certificates aren't checked".to_string(),
+                ))
+            }
+            (None, None) => return Ok(()),
+        };
+
+        let certificate = certificate.ok_or_else(|| {
+            ImmutableEncryptionError::PermissionDenied(
+                "a client certificate is required to submit frames".to_string(),
+            )
+        })?;
+
+        match enrolled.get(&certificate.fingerprint) {
+            Some(enrolled_device) if enrolled_device == device_id => Ok(()),
+            Some(enrolled_device) => Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "certificate {} is enrolled for {}, not {}",
+                certificate.fingerprint, enrolled_device, device_id
+            ))),
+            None => Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "certificate {} is not enrolled",
+                certificate.fingerprint
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_binds_certificate_to_device() {
+        let certificate = ClientCertificate::from_der(b"fake der certificate");
+
+        let mut enrolled_devices = HashMap::new();
+        enrolled_devices.insert(certificate.fingerprint.to_uppercase(), "cam_1".to_string());
+        let registry = DeviceCertificateRegistry::new(Some(&ClientAuthConfig {
+            ca_path: "ca.pem".to_string(),
+            enrolled_devices,
+        }));
+
+        assert!(registry.authorize(Some(&certificate), "cam_1").is_ok());
+        assert!(matches!(
+            registry.authorize(Some(&certificate), "cam_2"),
+            Err(ImmutableEncryptionError::PermissionDenied(_))
+        ));
+        assert!(registry.authorize(None, "cam_1").is_err());
+        assert!(registry
+            .authorize(Some(&ClientCertificate::from_der(b"other")), "cam_1")
+            .is_err());
+
+        // Re-enrolling rotates the certificate; revoking shuts the device out
+        let rotated = ClientCertificate::from_der(b"rotated der certificate");
+        registry.enroll(&rotated.fingerprint, "cam_1");
+        assert!(registry.authorize(Some(&rotated), "cam_1").is_ok());
+        assert!(registry.authorize(Some(&certificate), "cam_1").is_err());
+        registry.revoke("cam_1");
+        assert!(registry.authorize(Some(&rotated), "cam_1").is_err());
+        assert!(registry.check_device("cam_1").is_err());
+
+        // Without client auth configured every submission is allowed, but
+        // required enrollment can't be checked and so admits nobody
+        assert!(DeviceCertificateRegistry::new(None)
+            .authorize(None, "cam_1")
+            .is_ok());
+        let required = DeviceCertificateRegistry::new(None).with_enrollment_required();
+        assert!(required.authorize(None, "cam_1").is_err());
+        required.enroll(&rotated.fingerprint, "cam_1");
+        assert!(required.authorize(None, "cam_1").is_err());
+        assert!(required.authorize(Some(&rotated), "cam_1").is_err());
+
+        // With client auth, an enrolled device's own certificate is let in
+        let required = DeviceCertificateRegistry::new(Some(&ClientAuthConfig::default()))
+            .with_enrollment_required();
+        required.enroll(&rotated.fingerprint, "cam_1");
+        assert!(required.authorize(None, "cam_1").is_err());
+        assert!(required.authorize(Some(&rotated), "cam_1").is_ok());
+        assert!(required.authorize(Some(&rotated), "cam_2").is_err());
+    }
+}
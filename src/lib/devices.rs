@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::config::Config;
+use crate::crypto::Cipher;
+use crate::geofence::Geofence;
+
+// One camera's `[devices.<id>]` section. Unset fields fall back to the
+// node-wide settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceOverride {
+    #[serde(default)]
+    pub cipher: Option<Cipher>,
+    #[serde(default)]
+    pub anchoring: Option<AnchorPolicy>,
+    #[serde(default)]
+    pub compression: Option<bool>, // zstd before sealing
+    #[serde(default)]
+    pub retention_days: Option<u64>,
+    #[serde(default)]
+    pub geofences: Vec<Geofence>, // frames outside all of them are flagged
+    #[serde(default)]
+    pub max_speed_mps: Option<f64>, // flags faster movement between frames
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorPolicy {
+    #[serde(default)]
+    pub chains: Vec<String>, // empty: every chain the node anchors to
+    #[serde(default = "default_anchor_every")]
+    pub every: u64, // anchor one frame in this many; the hash chain covers the rest
+}
+
+fn default_anchor_every() -> u64 {
+    1
+}
+
+impl Default for AnchorPolicy {
+    fn default() -> Self {
+        Self {
+            chains: Vec::new(),
+            every: default_anchor_every(),
+        }
+    }
+}
+
+// The settings a device stream runs with once its overrides are applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DevicePolicy {
+    pub cipher: Cipher,
+    pub anchoring: AnchorPolicy,
+    pub compression: bool,
+    pub retention_days: u64,
+}
+
+impl DevicePolicy {
+    pub fn anchors(&self, sequence: u64) -> bool {
+        self.anchoring.every <= 1 || sequence % self.anchoring.every == 0
+    }
+}
+
+impl Default for DevicePolicy {
+    fn default() -> Self {
+        Self {
+            cipher: Cipher::default(),
+            anchoring: AnchorPolicy::default(),
+            compression: false,
+            retention_days: 365 * 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DevicePolicies {
+    defaults: DevicePolicy,
+    overrides: HashMap<String, DeviceOverride>,
+}
+
+impl DevicePolicies {
+    pub fn new(defaults: DevicePolicy, overrides: HashMap<String, DeviceOverride>) -> Self {
+        Self {
+            defaults,
+            overrides,
+        }
+    }
+
+    // Node-wide cipher, compression and retention come from [encryption] and
+    // [storage]
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            DevicePolicy {
+                cipher: config.encryption.cipher,
+                compression: config.encryption.compression_enabled,
+                retention_days: config.storage.retention_days,
+                ..DevicePolicy::default()
+            },
+            config.devices.clone(),
+        )
+    }
+
+    pub fn overrides(&self) -> &HashMap<String, DeviceOverride> {
+        &self.overrides
+    }
+
+    pub fn resolve(&self, device_id: &str) -> DevicePolicy {
+        let mut policy = self.defaults.clone();
+        if let Some(device) = self.overrides.get(device_id) {
+            policy.cipher = device.cipher.unwrap_or(policy.cipher);
+            policy.anchoring = device.anchoring.clone().unwrap_or(policy.anchoring);
+            policy.compression = device.compression.unwrap_or(policy.compression);
+            policy.retention_days = device.retention_days.unwrap_or(policy.retention_days);
+        }
+        policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_fall_back_to_node_defaults() {
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            "drone-7".to_string(),
+            DeviceOverride {
+                cipher: Some(Cipher::ChaCha20Poly1305),
+                anchoring: Some(AnchorPolicy {
+                    chains: vec!["ethereum".to_string()],
+                    every: 30,
+                }),
+                ..DeviceOverride::default()
+            },
+        );
+        let defaults = DevicePolicy {
+            compression: true,
+            ..DevicePolicy::default()
+        };
+        let policies = DevicePolicies::new(defaults.clone(), overrides);
+
+        let drone = policies.resolve("drone-7");
+        assert_eq!(drone.cipher, Cipher::ChaCha20Poly1305);
+        assert!(drone.compression);
+        assert_eq!(drone.retention_days, defaults.retention_days);
+        assert!(drone.anchors(60));
+        assert!(!drone.anchors(61));
+
+        assert_eq!(policies.resolve("body-cam-1"), defaults);
+        assert!(defaults.anchors(61));
+    }
+}
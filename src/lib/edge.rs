@@ -0,0 +1,494 @@
+use immutable_encryption_core::chain;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::crypto::EncryptionEngine;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::storage;
+use crate::{BlockchainAnchor, CustodyEntry, EncryptedFrame, FrameMetadata};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Where the central node takes batches, relative to `central_url`
+pub const SYNC_PATH: &str = "admin/edge/sync";
+
+// A field node seals and stores offline and leaves anchoring to the central
+// node it syncs with. The central node only needs `trusted_nodes`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeConfig {
+    pub enabled: bool, // this is a field node
+    pub node_id: String,
+    pub central_url: String, // e.g. https://central.example.org:8443
+    pub api_key: String,     // an Operator key issued by the central node
+    pub sync_interval_secs: u64,
+    pub batch_size: usize, // frames per request
+    // On the central node: field node ID -> hex Ed25519 public key, from
+    // `encryption-node keys envelope-key` on that node
+    #[serde(default)]
+    pub trusted_nodes: HashMap<String, String>,
+}
+
+impl Default for EdgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            node_id: String::new(),
+            central_url: String::new(),
+            api_key: String::new(),
+            sync_interval_secs: 60,
+            batch_size: 256,
+            trusted_nodes: HashMap::new(),
+        }
+    }
+}
+
+// An anchor a field node owes for a frame, made centrally once the frame has
+// synced. The times are the field node's and are kept with the anchor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeferredAnchor {
+    pub device_id: String,
+    pub sequence: u64,
+    pub hash: String,
+    pub chains: Vec<String>, // empty for every chain the central node anchors to
+    pub sealed_at: u64,
+    pub queued_at: u64,
+    // What the frame was sealed with, attached when the batch is built; the
+    // central node has no other record of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<FrameMetadata>,
+}
+
+// A sealed frame waiting on a field node to reach the central node, persisted
+// under `edge_outbox:{device}:{sequence}`. Kept as `synced` while the central
+// node still owes its anchor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub device_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub hash: String,
+    pub anchor: Option<DeferredAnchor>, // none when the device policy skips the frame
+    pub synced: bool,
+}
+
+impl OutboxEntry {
+    pub const PREFIX: &'static str = "edge_outbox:";
+
+    pub fn key(device_id: &str, sequence: u64) -> String {
+        format!("{}{:020}", Self::prefix(device_id), sequence)
+    }
+
+    pub fn prefix(device_id: &str) -> String {
+        format!("{}{}:", Self::PREFIX, device_id)
+    }
+
+    pub fn frame_id(&self) -> String {
+        storage::frame_key(&self.device_id, self.sequence, self.timestamp)
+    }
+}
+
+// The last custody key a field node synced for a device is kept under
+// `edge_custody_cursor:{device}`
+pub const CUSTODY_CURSOR_PREFIX: &str = "edge_custody_cursor:";
+
+pub fn custody_cursor_key(device_id: &str) -> String {
+    format!("{}{}", CUSTODY_CURSOR_PREFIX, device_id)
+}
+
+// As storage keys a device's custody entries, oldest first
+pub fn custody_prefix(device_id: &str) -> String {
+    format!("custody:{}:", device_id)
+}
+
+// One device's frames, owed anchors and custody entries, signed by the field
+// node's envelope key so the central node knows where they came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncBatch {
+    pub node_id: String,
+    pub device_id: String,
+    pub frames: Vec<EncryptedFrame>, // in sequence order
+    pub anchors: Vec<DeferredAnchor>,
+    pub custody: Vec<CustodyEntry>,
+    pub sent_at: u64,
+    pub signature: String,
+    #[serde(skip)]
+    pub custody_through: Option<String>, // last custody key sent; the field node's cursor
+}
+
+impl SyncBatch {
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty() && self.anchors.is_empty() && self.custody.is_empty()
+    }
+
+    pub fn sign(mut self, engine: &EncryptionEngine) -> Result<Self> {
+        self.signature = hex::encode(engine.sign_envelope(&self.signed_content()?)?);
+        Ok(self)
+    }
+
+    pub fn verify(&self, public_key: &[u8]) -> Result<()> {
+        let signature = hex::decode(&self.signature)?;
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&self.signed_content()?, &signature)
+            .map_err(|_| {
+                ImmutableEncryptionError::PermissionDenied(format!(
+                    "sync batch is not signed by {}",
+                    self.node_id
+                ))
+            })
+    }
+
+    fn signed_content(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(
+            &self.node_id,
+            &self.device_id,
+            &self.frames,
+            &self.anchors,
+            &self.custody,
+            self.sent_at,
+        ))?)
+    }
+}
+
+// The newest frame a field node has synced for a device, persisted by the
+// central node under `edge_chain:{device}`. The next batch must link to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeChainTip {
+    pub node_id: String,
+    pub device_id: String,
+    pub sequence: u64,
+    pub hash: String,
+    pub timestamp: u64,
+    pub synced_at: u64,
+}
+
+impl EdgeChainTip {
+    pub fn key(device_id: &str) -> String {
+        format!("edge_chain:{}", device_id)
+    }
+}
+
+// How a batch lines up with what the central node already holds
+#[derive(Debug)]
+pub struct Reconciliation<'a> {
+    pub resent: &'a [EncryptedFrame], // at or before the tip; must match what is stored
+    pub new_frames: &'a [EncryptedFrame],
+    pub gap: Option<(u64, u64)>, // sequences the field node lost before sealing on
+}
+
+// A batch must hang together as a chain and carry on from the synced tip. A
+// frame that follows the tip but names another predecessor is a fork and
+// refused; sequences missing between them are let through as a gap, since
+// the field node lost them and the chain can't be completed anyway.
+pub fn reconcile<'a>(
+    tip: Option<&EdgeChainTip>,
+    batch: &'a SyncBatch,
+) -> Result<Reconciliation<'a>> {
+    let foreign = batch
+        .frames
+        .iter()
+        .map(|f| &f.device_id)
+        .chain(batch.anchors.iter().map(|a| &a.device_id))
+        .any(|device_id| *device_id != batch.device_id);
+    if foreign {
+        return Err(ImmutableEncryptionError::invalid_request(&format!(
+            "batch for {} carries another device's frames",
+            batch.device_id
+        )));
+    }
+    chain::check_links(&batch.frames)?;
+
+    let Some(tip) = tip else {
+        return Ok(Reconciliation {
+            resent: &[],
+            new_frames: &batch.frames,
+            gap: None,
+        });
+    };
+    if tip.node_id != batch.node_id {
+        return Err(ImmutableEncryptionError::PermissionDenied(format!(
+            "{} is synced from {}, not {}",
+            batch.device_id, tip.node_id, batch.node_id
+        )));
+    }
+
+    let split = batch.frames.partition_point(|f| f.sequence <= tip.sequence);
+    let (resent, new_frames) = batch.frames.split_at(split);
+    let tampered = |details: String| Err(ImmutableEncryptionError::EvidenceTampered { details });
+    if let Some(frame) = resent.last().filter(|f| f.sequence == tip.sequence) {
+        if frame.hash != tip.hash {
+            return tampered(format!(
+                "{} resent frame {} of {} with another hash",
+                batch.node_id, frame.sequence, batch.device_id
+            ));
+        }
+    }
+
+    let gap = match new_frames.first() {
+        Some(first) if first.sequence == tip.sequence + 1 => {
+            if first.previous_hash != tip.hash {
+                return tampered(format!(
+                    "frame {} of {} does not link to the synced tip {}",
+                    first.sequence, batch.device_id, tip.hash
+                ));
+            }
+            None
+        }
+        Some(first) => Some((tip.sequence + 1, first.sequence - 1)),
+        None => None,
+    };
+    Ok(Reconciliation {
+        resent,
+        new_frames,
+        gap,
+    })
+}
+
+// An anchor made centrally for a frame a field node sealed offline, next to
+// the times the field node sealed and queued it. Persisted under
+// `retroactive_anchor:{device}:{sequence}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetroactiveAnchor {
+    pub node_id: String,
+    pub device_id: String,
+    pub sequence: u64,
+    pub hash: String,
+    pub sealed_at: u64,
+    pub queued_at: u64,
+    pub anchored_at: u64,
+    pub anchors: Vec<BlockchainAnchor>,
+}
+
+impl RetroactiveAnchor {
+    pub fn key(device_id: &str, sequence: u64) -> String {
+        format!("{}{:020}", Self::prefix(device_id), sequence)
+    }
+
+    pub fn prefix(device_id: &str) -> String {
+        format!("retroactive_anchor:{}:", device_id)
+    }
+
+    // One custody action per chain, naming the original seal time
+    pub fn custody_actions(&self) -> Vec<(String, String)> {
+        self.anchors
+            .iter()
+            .map(|anchor| {
+                let action = format!(
+                    "retroactive_anchor:{}:{}:sequence={}:sealed_at={}:queued_at={}:edge={}",
+                    anchor.chain,
+                    anchor.transaction_hash,
+                    self.sequence,
+                    self.sealed_at,
+                    self.queued_at,
+                    self.node_id
+                );
+                (action, anchor.transaction_hash.clone())
+            })
+            .collect()
+    }
+}
+
+// What the central node did with a batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncReceipt {
+    pub device_id: String,
+    pub stored: usize,
+    pub resent: usize,
+    pub gap: Option<(u64, u64)>,
+    pub tip_sequence: Option<u64>,
+    pub custody_entries: usize,
+    pub anchored: Vec<u64>,
+    pub unanchored: Vec<u64>, // still owed; the field node sends them again
+}
+
+// Both ends of the sync: a field node pushes batches to the central node,
+// which checks them against the keys of the field nodes it trusts
+pub struct EdgeSync {
+    config: EdgeConfig,
+    client: reqwest::Client,
+    trusted_keys: HashMap<String, Vec<u8>>,
+}
+
+impl std::fmt::Debug for EdgeSync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EdgeSync")
+            .field("node_id", &self.config.node_id)
+            .field("central_url", &self.config.central_url)
+            .field("trusted_nodes", &self.trusted_keys.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl EdgeSync {
+    pub fn new(config: &EdgeConfig) -> Result<Self> {
+        let trusted_keys = config
+            .trusted_nodes
+            .iter()
+            .map(|(node_id, key)| Ok((node_id.clone(), hex::decode(key)?)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            config: config.clone(),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            trusted_keys,
+        })
+    }
+
+    pub fn is_field_node(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.config.node_id
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.config.batch_size.max(1)
+    }
+
+    pub fn trusted_key(&self, node_id: &str) -> Result<&[u8]> {
+        self.trusted_keys
+            .get(node_id)
+            .map(Vec::as_slice)
+            .ok_or_else(|| {
+                ImmutableEncryptionError::PermissionDenied(format!(
+                    "{} is not a trusted edge node",
+                    node_id
+                ))
+            })
+    }
+
+    pub async fn push(&self, batch: &SyncBatch) -> Result<SyncReceipt> {
+        let url = format!(
+            "{}/{}",
+            self.config.central_url.trim_end_matches('/'),
+            SYNC_PATH
+        );
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.config.api_key)
+            .json(batch)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ImmutableEncryptionError::Network(format!(
+                "{} returned {}: {}",
+                url,
+                status,
+                response.text().await.unwrap_or_default()
+            )));
+        }
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(sequence: u64, previous_hash: &str) -> EncryptedFrame {
+        EncryptedFrame {
+            sequence,
+            device_id: "bodycam-7".to_string(),
+            ciphertext: vec![sequence as u8; 16],
+            hash: format!("hash-{}", sequence),
+            previous_hash: previous_hash.to_string(),
+            nonce: vec![0; 12],
+            timestamp: 1_700_000_000 + sequence,
+            blockchain_anchors: Vec::new(),
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        }
+    }
+
+    fn batch(frames: Vec<EncryptedFrame>) -> SyncBatch {
+        SyncBatch {
+            node_id: "field-1".to_string(),
+            device_id: "bodycam-7".to_string(),
+            frames,
+            anchors: Vec::new(),
+            custody: Vec::new(),
+            sent_at: 1_700_000_100,
+            signature: String::new(),
+            custody_through: None,
+        }
+    }
+
+    #[test]
+    fn test_signed_batches_reconcile_against_the_synced_tip() -> Result<()> {
+        let engine =
+            EncryptionEngine::new(crate::crypto::CryptoConfig::software(vec![7u8; 32], 1))?;
+        let public_key = engine.envelope_public_key()?;
+
+        let mut sent = batch(vec![
+            frame(3, "hash-2"),
+            frame(4, "hash-3"),
+            frame(5, "hash-4"),
+        ])
+        .sign(&engine)?;
+        sent.verify(&public_key)?;
+        let received: SyncBatch = serde_json::from_slice(&serde_json::to_vec(&sent)?)?;
+        received.verify(&public_key)?;
+        sent.frames[1].ciphertext[0] ^= 1;
+        assert!(sent.verify(&public_key).is_err());
+
+        // The metadata an owed anchor carries is signed with the batch
+        let mut owed = batch(Vec::new());
+        owed.anchors.push(DeferredAnchor {
+            device_id: "bodycam-7".to_string(),
+            sequence: 3,
+            hash: "hash-3".to_string(),
+            chains: Vec::new(),
+            sealed_at: 1_700_000_003,
+            queued_at: 1_700_000_003,
+            metadata: Some(FrameMetadata {
+                device_id: "bodycam-7".to_string(),
+                location: Some((40.7, -74.0)),
+                resolution: (1280, 720),
+                fps: 30,
+                codec: "h264".to_string(),
+                telemetry: None,
+            }),
+        });
+        let mut owed = owed.sign(&engine)?;
+        owed.verify(&public_key)?;
+        owed.anchors[0].metadata.as_mut().unwrap().location = Some((51.5, -0.1));
+        assert!(owed.verify(&public_key).is_err());
+
+        // Frames 3 and 4 synced before the receipt was lost
+        let tip = EdgeChainTip {
+            node_id: "field-1".to_string(),
+            device_id: "bodycam-7".to_string(),
+            sequence: 4,
+            hash: "hash-4".to_string(),
+            timestamp: 1_700_000_004,
+            synced_at: 1_700_000_050,
+        };
+        let reconciled = reconcile(Some(&tip), &received)?;
+        assert_eq!(reconciled.resent.len(), 2);
+        assert_eq!(reconciled.new_frames[0].sequence, 5);
+        assert_eq!(reconciled.gap, None);
+
+        // Lost frames leave a gap; a frame claiming another predecessor forks
+        let after_loss = batch(vec![frame(8, "hash-7")]);
+        assert_eq!(reconcile(Some(&tip), &after_loss)?.gap, Some((5, 7)));
+        let fork = batch(vec![frame(5, "hash-other")]);
+        assert!(reconcile(Some(&tip), &fork).is_err());
+        let mut rewritten = batch(vec![frame(4, "hash-3")]);
+        rewritten.frames[0].hash = "hash-forged".to_string();
+        assert!(reconcile(Some(&tip), &rewritten).is_err());
+
+        // Another field node can't continue the device's chain
+        let mut other = batch(vec![frame(5, "hash-4")]);
+        other.node_id = "field-2".to_string();
+        assert!(reconcile(Some(&tip), &other).is_err());
+        assert_eq!(reconcile(None, &other)?.new_frames.len(), 1);
+        Ok(())
+    }
+}
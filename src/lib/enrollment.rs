@@ -0,0 +1,366 @@
+use rcgen::{
+    CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+    SerialNumber,
+};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, ED25519};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::device_auth::certificate_fingerprint;
+use crate::error::{ImmutableEncryptionError, Result};
+
+const CHALLENGE_TTL_SECS: u64 = 300;
+const SECONDS_PER_DAY: u64 = 86_400;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrollmentConfig {
+    pub enabled: bool,
+    pub ca_cert_path: String, // must also be in server.tls.client_auth.ca_path
+    pub ca_key_path: String,
+    pub validity_days: u32,
+    pub required: bool, // reject frames from devices without an active enrollment
+    pub require_attestation: bool,
+    // device_id -> hex public key held in the device's secure element or TPM:
+    // 32-byte Ed25519, or 65-byte uncompressed P-256 signing in ASN.1 DER
+    #[serde(default)]
+    pub attestation_keys: HashMap<String, String>,
+}
+
+impl Default for EnrollmentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ca_cert_path: "./certs/device-ca.pem".to_string(),
+            ca_key_path: "./certs/device-ca.key".to_string(),
+            validity_days: 365,
+            required: false,
+            require_attestation: false,
+            attestation_keys: HashMap::new(),
+        }
+    }
+}
+
+// A one-time nonce the device signs with its attestation key to prove the
+// enrollment comes from that hardware. Persisted under
+// `enrollment_challenge:{device}` until used or expired.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrollmentChallenge {
+    pub device_id: String,
+    pub nonce: String,
+    pub expires_at: u64,
+}
+
+impl EnrollmentChallenge {
+    pub fn key(device_id: &str) -> String {
+        format!("enrollment_challenge:{}", device_id)
+    }
+
+    // The exact bytes the device signs
+    pub fn message(&self) -> Vec<u8> {
+        format!("enroll:{}:{}", self.device_id, self.nonce).into_bytes()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EnrollmentRequest {
+    pub attestation: Option<String>, // hex signature over the challenge
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Revocation {
+    pub revoked_by: String,
+    pub revoked_at: u64,
+    pub reason: String,
+}
+
+// The certificate a device was issued, persisted under
+// `device_enrollment:{device}`. Re-enrolling replaces it, and the previous
+// certificate stops being accepted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceEnrollment {
+    pub device_id: String,
+    pub certificate_fingerprint: String, // sha256 of the DER, as mTLS reports it
+    pub certificate_pem: String,
+    pub serial: String,
+    pub attested: bool,
+    pub enrolled_by: String,
+    pub enrolled_at: u64,
+    pub expires_at: u64,
+    pub revoked: Option<Revocation>,
+}
+
+impl DeviceEnrollment {
+    pub const PREFIX: &'static str = "device_enrollment:";
+
+    pub fn key(device_id: &str) -> String {
+        format!("{}{}", Self::PREFIX, device_id)
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        self.revoked.is_none() && now < self.expires_at
+    }
+}
+
+// Handed to the device once; the node keeps only the certificate
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedDevice {
+    pub enrollment: DeviceEnrollment,
+    pub private_key_pem: String,
+    pub ca_certificate_pem: String,
+}
+
+// Signs device client certificates with the enrollment CA and checks
+// hardware attestation against the keys provisioned for each device
+pub struct DeviceIssuer {
+    ca_certificate_pem: String,
+    ca_params: CertificateParams,
+    ca_key: KeyPair,
+    validity_days: u32,
+    require_attestation: bool,
+    attestation_keys: HashMap<String, Vec<u8>>,
+}
+
+impl std::fmt::Debug for DeviceIssuer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeviceIssuer")
+            .field("validity_days", &self.validity_days)
+            .field("require_attestation", &self.require_attestation)
+            .field("attested_devices", &self.attestation_keys.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeviceIssuer {
+    pub fn load(config: &EnrollmentConfig) -> Result<Self> {
+        let read = |path: &str| {
+            std::fs::read_to_string(path).map_err(|e| {
+                ImmutableEncryptionError::config(&format!("Failed to read {}: {}", path, e))
+            })
+        };
+        Self::from_pem(
+            &read(&config.ca_cert_path)?,
+            &read(&config.ca_key_path)?,
+            config,
+        )
+    }
+
+    pub fn from_pem(
+        ca_cert_pem: &str,
+        ca_key_pem: &str,
+        config: &EnrollmentConfig,
+    ) -> Result<Self> {
+        let ca_params = CertificateParams::from_ca_cert_pem(ca_cert_pem).map_err(|e| {
+            ImmutableEncryptionError::config(&format!("Invalid enrollment CA certificate: {}", e))
+        })?;
+        let ca_key = KeyPair::from_pem(ca_key_pem).map_err(|e| {
+            ImmutableEncryptionError::config(&format!("Invalid enrollment CA key: {}", e))
+        })?;
+        let attestation_keys = config
+            .attestation_keys
+            .iter()
+            .map(|(device_id, key)| Ok((device_id.clone(), hex::decode(key)?)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            ca_certificate_pem: ca_cert_pem.to_string(),
+            ca_params,
+            ca_key,
+            validity_days: config.validity_days,
+            require_attestation: config.require_attestation,
+            attestation_keys,
+        })
+    }
+
+    pub fn challenge(&self, device_id: &str, now: u64) -> Result<EnrollmentChallenge> {
+        let mut nonce = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate a challenge"))?;
+        Ok(EnrollmentChallenge {
+            device_id: device_id.to_string(),
+            nonce: hex::encode(nonce),
+            expires_at: now + CHALLENGE_TTL_SECS,
+        })
+    }
+
+    // Whether the enrollment is backed by the device's hardware key. A device
+    // with a provisioned key must always attest; others only when required.
+    pub fn check_attestation(
+        &self,
+        device_id: &str,
+        challenge: Option<&EnrollmentChallenge>,
+        signature: Option<&str>,
+        now: u64,
+    ) -> Result<bool> {
+        let key = match self.attestation_keys.get(device_id) {
+            Some(key) => key,
+            None if self.require_attestation => {
+                return Err(ImmutableEncryptionError::AttestationFailed(format!(
+                    "no attestation key is provisioned for {}",
+                    device_id
+                )))
+            }
+            None => return Ok(false),
+        };
+        let challenge = challenge
+            .filter(|c| c.device_id == device_id && now < c.expires_at)
+            .ok_or_else(|| {
+                ImmutableEncryptionError::AttestationFailed(format!(
+                    "no open enrollment challenge for {}",
+                    device_id
+                ))
+            })?;
+        let signature = hex::decode(signature.ok_or_else(|| {
+            ImmutableEncryptionError::AttestationFailed(format!(
+                "{} must sign its enrollment challenge",
+                device_id
+            ))
+        })?)?;
+
+        let message = challenge.message();
+        match key.len() {
+            32 => UnparsedPublicKey::new(&ED25519, key).verify(&message, &signature),
+            _ => UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, key).verify(&message, &signature),
+        }
+        .map_err(|_| {
+            ImmutableEncryptionError::AttestationFailed(format!(
+                "challenge signature does not match the key provisioned for {}",
+                device_id
+            ))
+        })?;
+        Ok(true)
+    }
+
+    // Generates the device's key pair and a client certificate for it, with
+    // the device ID as its common name
+    pub fn issue(
+        &self,
+        device_id: &str,
+        attested: bool,
+        enrolled_by: &str,
+        now: u64,
+    ) -> Result<IssuedDevice> {
+        let mut serial = [0u8; 16];
+        SystemRandom::new()
+            .fill(&mut serial)
+            .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate a serial"))?;
+        serial[0] &= 0x7f; // keep it positive
+        let expires_at = now + u64::from(self.validity_days) * SECONDS_PER_DAY;
+
+        let mut params = CertificateParams::default();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, device_id);
+        params.is_ca = IsCa::ExplicitNoCa;
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        params.serial_number = Some(SerialNumber::from_slice(&serial));
+        params.not_before = timestamp(now)?;
+        params.not_after = timestamp(expires_at)?;
+
+        let certificate_error =
+            |e: rcgen::Error| ImmutableEncryptionError::crypto(&format!("{}", e));
+        let device_key = KeyPair::generate().map_err(certificate_error)?;
+        let ca = self
+            .ca_params
+            .clone()
+            .self_signed(&self.ca_key)
+            .map_err(certificate_error)?;
+        let certificate = params
+            .signed_by(&device_key, &ca, &self.ca_key)
+            .map_err(certificate_error)?;
+
+        Ok(IssuedDevice {
+            enrollment: DeviceEnrollment {
+                device_id: device_id.to_string(),
+                certificate_fingerprint: certificate_fingerprint(certificate.der()),
+                certificate_pem: certificate.pem(),
+                serial: hex::encode(serial),
+                attested,
+                enrolled_by: enrolled_by.to_string(),
+                enrolled_at: now,
+                expires_at,
+                revoked: None,
+            },
+            private_key_pem: device_key.serialize_pem(),
+            ca_certificate_pem: self.ca_certificate_pem.clone(),
+        })
+    }
+}
+
+fn timestamp(secs: u64) -> Result<time::OffsetDateTime> {
+    time::OffsetDateTime::from_unix_timestamp(secs as i64)
+        .map_err(|e| ImmutableEncryptionError::internal(&format!("{}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair as _};
+
+    #[test]
+    fn test_issues_certificates_and_checks_attestation() -> Result<()> {
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "Device CA");
+        ca_params.is_ca = IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let ca_key = KeyPair::generate().unwrap();
+        let ca_pem = ca_params.self_signed(&ca_key).unwrap().pem();
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let hardware_key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let mut config = EnrollmentConfig::default();
+        config.attestation_keys.insert(
+            "cam_1".to_string(),
+            hex::encode(hardware_key.public_key().as_ref()),
+        );
+        let issuer = DeviceIssuer::from_pem(&ca_pem, &ca_key.serialize_pem(), &config)?;
+
+        let now = 1_700_000_000;
+        let challenge = issuer.challenge("cam_1", now)?;
+        let signature = hex::encode(hardware_key.sign(&challenge.message()));
+        assert!(issuer.check_attestation("cam_1", Some(&challenge), Some(&signature), now)?);
+
+        // Provisioned devices can't skip attestation or replay an old answer
+        assert!(issuer
+            .check_attestation("cam_1", Some(&challenge), None, now)
+            .is_err());
+        assert!(issuer
+            .check_attestation("cam_1", Some(&challenge), Some(&signature), now + 301)
+            .is_err());
+        let forged = hex::encode(hardware_key.sign(b"enroll:cam_1:other"));
+        assert!(issuer
+            .check_attestation("cam_1", Some(&challenge), Some(&forged), now)
+            .is_err());
+        assert!(!issuer.check_attestation("cam_2", None, None, now)?);
+
+        let issued = issuer.issue("cam_1", true, "admin", now)?;
+        let enrollment = &issued.enrollment;
+        assert!(enrollment.is_active(now));
+        assert!(!enrollment.is_active(now + 365 * SECONDS_PER_DAY));
+        assert!(issued.private_key_pem.contains("PRIVATE KEY"));
+
+        // The certificate names the device, and mTLS will see its fingerprint
+        let params = CertificateParams::from_ca_cert_pem(&enrollment.certificate_pem).unwrap();
+        let common_name = params.distinguished_name.get(&DnType::CommonName);
+        assert!(matches!(common_name, Some(rcgen::DnValue::Utf8String(cn)) if cn == "cam_1"));
+        let der = rustls_pemfile::certs(&mut enrollment.certificate_pem.as_bytes())
+            .next()
+            .unwrap()?;
+        assert_eq!(
+            enrollment.certificate_fingerprint,
+            certificate_fingerprint(&der)
+        );
+        let rotated = issuer.issue("cam_1", true, "admin", now)?;
+        assert_ne!(
+            rotated.enrollment.certificate_fingerprint,
+            enrollment.certificate_fingerprint
+        );
+
+        config.require_attestation = true;
+        let strict = DeviceIssuer::from_pem(&ca_pem, &ca_key.serialize_pem(), &config)?;
+        assert!(strict.check_attestation("cam_2", None, None, now).is_err());
+        Ok(())
+    }
+}
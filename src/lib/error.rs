@@ -1,5 +1,10 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use thiserror::Error;
 
+// The library's result type; binaries convert into their own error types
+pub type Result<T, E = ImmutableEncryptionError> = std::result::Result<T, E>;
+
 #[derive(Error, Debug)]
 pub enum ImmutableEncryptionError {
     #[error("Cryptography error: {0}")]
@@ -63,6 +68,12 @@ pub enum ImmutableEncryptionError {
     #[error("Resource temporarily unavailable: {0}")]
     ResourceUnavailable(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
     #[error("Internal server error: {0}")]
     Internal(String),
 }
@@ -103,6 +114,165 @@ impl ImmutableEncryptionError {
     pub fn internal(msg: &str) -> Self {
         Self::Internal(msg.to_string())
     }
+
+    pub fn not_found(msg: &str) -> Self {
+        Self::NotFound(msg.to_string())
+    }
+
+    pub fn invalid_request(msg: &str) -> Self {
+        Self::InvalidRequest(msg.to_string())
+    }
+
+    // Stable identifiers for API clients. Published codes are never reused or
+    // renumbered; new variants take the next number in their range.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Crypto(_) => "crypto_error",
+            Self::QuantumCryptoUnavailable => "quantum_crypto_unavailable",
+            Self::Hardware(_) => "hardware_error",
+            Self::AttestationFailed(_) => "attestation_failed",
+            Self::Verification(_) => "verification_failed",
+            Self::InvalidSequence(_) => "invalid_sequence",
+            Self::HashChainViolation => "hash_chain_violation",
+            Self::EvidenceTampered { .. } => "evidence_tampered",
+            Self::InsufficientConfirmations { .. } => "insufficient_confirmations",
+            Self::Blockchain(_) => "blockchain_error",
+            Self::Storage(_) => "storage_error",
+            Self::FrameNotFound { .. } => "frame_not_found",
+            Self::NotFound(_) => "not_found",
+            Self::Video(_) => "video_error",
+            Self::Config(_) => "invalid_config",
+            Self::InvalidRequest(_) => "invalid_request",
+            Self::PermissionDenied(_) => "permission_denied",
+            Self::RateLimitExceeded(_) => "rate_limited",
+            Self::LegalComplianceFailed(_) => "legal_compliance_failed",
+            Self::Network(_) => "network_error",
+            Self::ResourceUnavailable(_) => "resource_unavailable",
+            Self::Internal(_) => "internal_error",
+        }
+    }
+
+    // 1xxx crypto, 2xxx integrity, 3xxx blockchain, 4xxx data, 5xxx request
+    // and policy, 6xxx service
+    pub fn numeric_code(&self) -> u32 {
+        match self {
+            Self::Crypto(_) => 1000,
+            Self::QuantumCryptoUnavailable => 1001,
+            Self::Hardware(_) => 1002,
+            Self::AttestationFailed(_) => 1003,
+            Self::Verification(_) => 2000,
+            Self::InvalidSequence(_) => 2001,
+            Self::HashChainViolation => 2002,
+            Self::EvidenceTampered { .. } => 2003,
+            Self::InsufficientConfirmations { .. } => 2004,
+            Self::Blockchain(_) => 3000,
+            Self::Storage(_) => 4000,
+            Self::FrameNotFound { .. } => 4001,
+            Self::NotFound(_) => 4002,
+            Self::Video(_) => 4003,
+            Self::Config(_) => 5000,
+            Self::PermissionDenied(_) => 5001,
+            Self::RateLimitExceeded(_) => 5002,
+            Self::LegalComplianceFailed(_) => 5003,
+            Self::InvalidRequest(_) => 5004,
+            Self::Network(_) => 6000,
+            Self::ResourceUnavailable(_) => 6001,
+            Self::Internal(_) => 6002,
+        }
+    }
+
+    // Integrity failures are the evidence's fault rather than the request's,
+    // so they're 422s; upstream chain and network failures are 502s.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::Config(_) | Self::InvalidRequest(_) => 400,
+            Self::PermissionDenied(_) | Self::AttestationFailed(_) => 403,
+            Self::FrameNotFound { .. } | Self::NotFound(_) => 404,
+            Self::InsufficientConfirmations { .. } | Self::LegalComplianceFailed(_) => 409,
+            Self::Verification(_)
+            | Self::InvalidSequence(_)
+            | Self::HashChainViolation
+            | Self::EvidenceTampered { .. }
+            | Self::Video(_) => 422,
+            Self::RateLimitExceeded(_) => 429,
+            Self::QuantumCryptoUnavailable => 501,
+            Self::Blockchain(_) | Self::Network(_) => 502,
+            Self::ResourceUnavailable(_) => 503,
+            Self::Crypto(_) | Self::Hardware(_) | Self::Storage(_) | Self::Internal(_) => 500,
+        }
+    }
+
+    // Transient failures worth another attempt: unreachable or rate-limited
+    // RPC and IPFS endpoints, and anchors still waiting on confirmations.
+    // Integrity, signature and configuration failures won't fix themselves.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Network(_)
+                | Self::Blockchain(_)
+                | Self::InsufficientConfirmations { .. }
+                | Self::RateLimitExceeded(_)
+                | Self::ResourceUnavailable(_)
+        )
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody {
+            error: self.to_string(),
+            code: self.code().to_string(),
+            numeric_code: self.numeric_code(),
+        }
+    }
+
+    // Prefixes what was being attempted. Variants without a message are
+    // returned as they are so callers can still match on them.
+    pub fn context(self, context: impl Display) -> Self {
+        let prefix = |msg: String| format!("{}: {}", context, msg);
+        match self {
+            Self::Crypto(msg) => Self::Crypto(prefix(msg)),
+            Self::Blockchain(msg) => Self::Blockchain(prefix(msg)),
+            Self::Storage(msg) => Self::Storage(prefix(msg)),
+            Self::Verification(msg) => Self::Verification(prefix(msg)),
+            Self::Config(msg) => Self::Config(prefix(msg)),
+            Self::Network(msg) => Self::Network(prefix(msg)),
+            Self::Hardware(msg) => Self::Hardware(prefix(msg)),
+            Self::Video(msg) => Self::Video(prefix(msg)),
+            Self::AttestationFailed(msg) => Self::AttestationFailed(prefix(msg)),
+            Self::LegalComplianceFailed(msg) => Self::LegalComplianceFailed(prefix(msg)),
+            Self::PermissionDenied(msg) => Self::PermissionDenied(prefix(msg)),
+            Self::RateLimitExceeded(msg) => Self::RateLimitExceeded(prefix(msg)),
+            Self::ResourceUnavailable(msg) => Self::ResourceUnavailable(prefix(msg)),
+            Self::NotFound(msg) => Self::NotFound(prefix(msg)),
+            Self::InvalidRequest(msg) => Self::InvalidRequest(prefix(msg)),
+            Self::Internal(msg) => Self::Internal(prefix(msg)),
+            other => other,
+        }
+    }
+}
+
+// JSON body of every API error response. `error` stays the human-readable
+// message older clients read; `code` and `numeric_code` are for matching on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: String,
+    pub code: String,
+    pub numeric_code: u32,
+}
+
+// `.context(..)` on any result whose error converts into ours
+pub trait Context<T> {
+    fn context<C: Display>(self, context: C) -> Result<T>;
+    fn with_context<C: Display, F: FnOnce() -> C>(self, context: F) -> Result<T>;
+}
+
+impl<T, E: Into<ImmutableEncryptionError>> Context<T> for std::result::Result<T, E> {
+    fn context<C: Display>(self, context: C) -> Result<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Display, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|e| e.into().context(context()))
+    }
 }
 
 // Conversion from common error types
@@ -129,3 +299,177 @@ impl From<reqwest::Error> for ImmutableEncryptionError {
         Self::Network(format!("HTTP request error: {}", err))
     }
 }
+
+impl From<toml::ser::Error> for ImmutableEncryptionError {
+    fn from(err: toml::ser::Error) -> Self {
+        Self::Config(format!("TOML serialization error: {}", err))
+    }
+}
+
+impl From<serde_yaml::Error> for ImmutableEncryptionError {
+    fn from(err: serde_yaml::Error) -> Self {
+        Self::Config(format!("YAML error: {}", err))
+    }
+}
+
+impl From<bincode::Error> for ImmutableEncryptionError {
+    fn from(err: bincode::Error) -> Self {
+        Self::Storage(format!("Encoding error: {}", err))
+    }
+}
+
+impl From<rocksdb::Error> for ImmutableEncryptionError {
+    fn from(err: rocksdb::Error) -> Self {
+        Self::Storage(format!("Database error: {}", err))
+    }
+}
+
+impl From<immutable_encryption_core::Error> for ImmutableEncryptionError {
+    fn from(err: immutable_encryption_core::Error) -> Self {
+        use immutable_encryption_core::Error;
+        match err {
+            Error::InvalidSequence(sequence) => Self::InvalidSequence(sequence),
+            Error::HashChainViolation => Self::HashChainViolation,
+            Error::EvidenceTampered(details) => Self::EvidenceTampered { details },
+            Error::Verification(msg) => Self::Verification(msg),
+        }
+    }
+}
+
+impl From<hex::FromHexError> for ImmutableEncryptionError {
+    fn from(err: hex::FromHexError) -> Self {
+        Self::Verification(format!("Invalid hex: {}", err))
+    }
+}
+
+impl From<base64::DecodeError> for ImmutableEncryptionError {
+    fn from(err: base64::DecodeError) -> Self {
+        Self::Verification(format!("Invalid base64: {}", err))
+    }
+}
+
+impl From<std::string::FromUtf8Error> for ImmutableEncryptionError {
+    fn from(err: std::string::FromUtf8Error) -> Self {
+        Self::Verification(format!("Invalid UTF-8: {}", err))
+    }
+}
+
+impl From<std::num::ParseIntError> for ImmutableEncryptionError {
+    fn from(err: std::num::ParseIntError) -> Self {
+        Self::Config(format!("Invalid number: {}", err))
+    }
+}
+
+impl From<std::num::ParseFloatError> for ImmutableEncryptionError {
+    fn from(err: std::num::ParseFloatError) -> Self {
+        Self::Config(format!("Invalid number: {}", err))
+    }
+}
+
+impl From<ring::error::Unspecified> for ImmutableEncryptionError {
+    fn from(_: ring::error::Unspecified) -> Self {
+        Self::Crypto("Cryptographic operation failed".to_string())
+    }
+}
+
+impl From<std::time::SystemTimeError> for ImmutableEncryptionError {
+    fn from(err: std::time::SystemTimeError) -> Self {
+        Self::Internal(format!("System clock error: {}", err))
+    }
+}
+
+impl From<tokio::task::JoinError> for ImmutableEncryptionError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Internal(format!("Background task failed: {}", err))
+    }
+}
+
+impl From<ethers::providers::ProviderError> for ImmutableEncryptionError {
+    fn from(err: ethers::providers::ProviderError) -> Self {
+        Self::Blockchain(format!("RPC error: {}", err))
+    }
+}
+
+#[cfg(feature = "video")]
+impl From<image::ImageError> for ImmutableEncryptionError {
+    fn from(err: image::ImageError) -> Self {
+        Self::Video(format!("Image error: {}", err))
+    }
+}
+
+#[cfg(feature = "amqp")]
+impl From<lapin::Error> for ImmutableEncryptionError {
+    fn from(err: lapin::Error) -> Self {
+        Self::Network(format!("AMQP error: {}", err))
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl From<rdkafka::error::KafkaError> for ImmutableEncryptionError {
+    fn from(err: rdkafka::error::KafkaError) -> Self {
+        Self::Network(format!("Kafka error: {}", err))
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+impl From<cryptoki::error::Error> for ImmutableEncryptionError {
+    fn from(err: cryptoki::error::Error) -> Self {
+        Self::Crypto(format!("PKCS#11 error: {}", err))
+    }
+}
+
+#[cfg(feature = "tpm")]
+impl From<tss_esapi::Error> for ImmutableEncryptionError {
+    fn from(err: tss_esapi::Error) -> Self {
+        Self::Crypto(format!("TPM error: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_and_statuses() {
+        let errors = [
+            ImmutableEncryptionError::crypto("bad key"),
+            ImmutableEncryptionError::HashChainViolation,
+            ImmutableEncryptionError::InsufficientConfirmations {
+                chain: "bitcoin".to_string(),
+                required: 6,
+                actual: 2,
+            },
+            ImmutableEncryptionError::FrameNotFound {
+                frame_id: "f1".to_string(),
+            },
+            ImmutableEncryptionError::not_found("session s1"),
+            ImmutableEncryptionError::config("missing role"),
+            ImmutableEncryptionError::PermissionDenied("auditor".to_string()),
+            ImmutableEncryptionError::RateLimitExceeded("10/min".to_string()),
+            ImmutableEncryptionError::network("refused"),
+        ];
+        let statuses: Vec<u16> = errors.iter().map(|e| e.http_status()).collect();
+        assert_eq!(statuses, [500, 422, 409, 404, 404, 400, 403, 429, 502]);
+
+        let mut numbers: Vec<u32> = errors.iter().map(|e| e.numeric_code()).collect();
+        numbers.sort();
+        numbers.dedup();
+        assert_eq!(numbers.len(), errors.len());
+
+        let body = serde_json::to_value(errors[1].body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error": "Hash chain integrity violation",
+                "code": "hash_chain_violation",
+                "numeric_code": 2002,
+            })
+        );
+        assert_eq!(
+            ImmutableEncryptionError::not_found("session s1")
+                .context("Loading manifest")
+                .to_string(),
+            "Not found: Loading manifest: session s1"
+        );
+    }
+}
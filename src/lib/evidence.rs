@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::session::{RecordingSession, SessionManager, SessionManifest};
+use crate::storage::{self, DistributedStorage};
+use crate::EncryptedFrame;
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 500;
+
+// Keys read from storage per round trip while filling a page
+const SCAN_BATCH: usize = 256;
+
+// Upper bound on keys examined for one page, so selective filters can't turn
+// a request into a full table scan; the cursor resumes where it stopped.
+const MAX_SCANNED_PER_PAGE: usize = 10_000;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EvidenceQuery {
+    pub device_id: Option<String>,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub anchored: Option<bool>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FrameQuery {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub anchored: Option<bool>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>, // None once the listing is exhausted
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvidenceSummary {
+    pub evidence_id: String,
+    pub device_id: String,
+    pub case_id: Option<String>,
+    pub started_at: u64,
+    pub stopped_at: Option<u64>,
+    pub frame_count: u64,
+    pub sealed: bool,
+    pub anchored: bool,
+    pub manifest_hash: Option<String>,
+}
+
+impl EvidenceSummary {
+    fn new(session: &RecordingSession, manifest: Option<&SessionManifest>) -> Self {
+        Self {
+            evidence_id: session.session_id.clone(),
+            device_id: session.device_id.clone(),
+            case_id: session.case_id.clone(),
+            started_at: session.started_at,
+            stopped_at: manifest.map(|m| m.stopped_at),
+            frame_count: manifest.map_or(session.frame_count, |m| m.frame_count),
+            sealed: manifest.is_some(),
+            anchored: manifest.map_or(false, |m| !m.anchors.is_empty()),
+            manifest_hash: manifest.map(|m| m.manifest_hash.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameSummary {
+    pub frame_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub hash: String,
+    pub previous_hash: String,
+    pub ciphertext_bytes: usize,
+    pub anchored: bool,
+    pub anchor_chains: Vec<String>,
+}
+
+impl From<&EncryptedFrame> for FrameSummary {
+    fn from(frame: &EncryptedFrame) -> Self {
+        Self {
+            frame_id: storage::frame_key(&frame.device_id, frame.sequence, frame.timestamp),
+            sequence: frame.sequence,
+            timestamp: frame.timestamp,
+            hash: frame.hash.clone(),
+            previous_hash: frame.previous_hash.clone(),
+            ciphertext_bytes: frame.ciphertext.len(),
+            anchored: !frame.blockchain_anchors.is_empty(),
+            anchor_chains: frame
+                .blockchain_anchors
+                .iter()
+                .map(|a| a.chain.clone())
+                .collect(),
+        }
+    }
+}
+
+pub struct EvidenceBrowser<'a> {
+    storage: &'a DistributedStorage,
+    sessions: &'a SessionManager,
+}
+
+impl<'a> EvidenceBrowser<'a> {
+    pub fn new(storage: &'a DistributedStorage, sessions: &'a SessionManager) -> Self {
+        Self { storage, sessions }
+    }
+
+    pub async fn list_evidence(&self, query: &EvidenceQuery) -> Result<Page<EvidenceSummary>> {
+        let limit = page_size(query.limit);
+        let (from, to) = (query.from.unwrap_or(0), query.to.unwrap_or(u64::MAX));
+
+        // The per-device index is ordered by start time; otherwise walk all sessions
+        let (prefix, initial) = match &query.device_id {
+            Some(device_id) => {
+                let prefix = format!("session_index:{}:", device_id);
+                let initial = format!("{}{:020}", prefix, 0);
+                (prefix, initial)
+            }
+            None => ("session:".to_string(), "session:".to_string()),
+        };
+
+        let mut start = resume_key(query.cursor.as_deref())?.unwrap_or(initial);
+        let mut items = Vec::new();
+        let mut scanned = 0;
+
+        loop {
+            let batch = self
+                .storage
+                .scan_page_raw(&prefix, &start, SCAN_BATCH)
+                .await?;
+            if batch.is_empty() {
+                return Ok(Page {
+                    items,
+                    next_cursor: None,
+                });
+            }
+
+            for (key, value) in batch {
+                let session: Option<RecordingSession> = if query.device_id.is_some() {
+                    let session_id: String = serde_json::from_slice(&value)?;
+                    self.sessions.session(&session_id).await?
+                } else {
+                    self.sessions
+                        .session(&serde_json::from_slice::<RecordingSession>(&value)?.session_id)
+                        .await?
+                };
+
+                if let Some(session) = session {
+                    // Device index keys are time ordered, so nothing later can match
+                    if query.device_id.is_some() && session.started_at > to {
+                        return Ok(Page {
+                            items,
+                            next_cursor: None,
+                        });
+                    }
+
+                    let manifest = self.sessions.manifest(&session.session_id).await?;
+                    let summary = EvidenceSummary::new(&session, manifest.as_ref());
+                    let ended = summary.stopped_at.unwrap_or(u64::MAX);
+
+                    if session.started_at <= to
+                        && ended >= from
+                        && query.anchored.map_or(true, |a| a == summary.anchored)
+                    {
+                        items.push(summary);
+                    }
+                }
+
+                scanned += 1;
+                if items.len() == limit || scanned >= MAX_SCANNED_PER_PAGE {
+                    return Ok(Page {
+                        items,
+                        next_cursor: Some(hex::encode(&key)),
+                    });
+                }
+                start = format!("{}\0", key);
+            }
+        }
+    }
+
+    pub async fn list_frames(
+        &self,
+        evidence_id: &str,
+        query: &FrameQuery,
+    ) -> Result<Option<Page<FrameSummary>>> {
+        let limit = page_size(query.limit);
+        let session = match self.sessions.session(evidence_id).await? {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+        let manifest = self.sessions.manifest(evidence_id).await?;
+
+        // The sealed manifest bounds the session; live sessions use their current state
+        let (first_ts, last_ts, first_seq, last_seq) = match &manifest {
+            Some(m) => (
+                m.first_frame_timestamp,
+                m.last_frame_timestamp,
+                m.first_sequence,
+                m.last_sequence,
+            ),
+            None => (
+                session.first_frame_timestamp,
+                None,
+                session.first_sequence,
+                None,
+            ),
+        };
+        let (first_ts, first_seq) = match (first_ts, first_seq) {
+            (Some(ts), Some(seq)) => (ts, seq),
+            _ => {
+                return Ok(Some(Page {
+                    items: Vec::new(),
+                    next_cursor: None,
+                }))
+            }
+        };
+        let last_ts = last_ts
+            .unwrap_or(u64::MAX)
+            .min(query.to.unwrap_or(u64::MAX));
+        let last_seq = last_seq.unwrap_or(u64::MAX);
+        let from = first_ts.max(query.from.unwrap_or(0));
+
+        let prefix = format!("device:{}:", session.device_id);
+        let initial = format!("{}{:020}", prefix, from);
+        let mut start = resume_key(query.cursor.as_deref())?.unwrap_or(initial);
+        let mut items = Vec::new();
+        let mut scanned = 0;
+
+        loop {
+            let batch = self
+                .storage
+                .scan_page_raw(&prefix, &start, SCAN_BATCH)
+                .await?;
+            if batch.is_empty() {
+                return Ok(Some(Page {
+                    items,
+                    next_cursor: None,
+                }));
+            }
+
+            for (key, value) in batch {
+                let timestamp: u64 = key[prefix.len()..prefix.len() + 20].parse()?;
+                if timestamp > last_ts {
+                    return Ok(Some(Page {
+                        items,
+                        next_cursor: None,
+                    }));
+                }
+
+                let frame = self
+                    .storage
+                    .retrieve_with_fallback(&String::from_utf8(value)?)
+                    .await?;
+                let anchored = !frame.blockchain_anchors.is_empty();
+
+                if (first_seq..=last_seq).contains(&frame.sequence)
+                    && query.anchored.map_or(true, |a| a == anchored)
+                {
+                    items.push(FrameSummary::from(&frame));
+                }
+
+                scanned += 1;
+                if items.len() == limit || scanned >= MAX_SCANNED_PER_PAGE {
+                    return Ok(Some(Page {
+                        items,
+                        next_cursor: Some(hex::encode(&key)),
+                    }));
+                }
+                start = format!("{}\0", key);
+            }
+        }
+    }
+}
+
+fn page_size(requested: Option<usize>) -> usize {
+    requested
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE)
+}
+
+// Cursors are the hex-encoded last key of the previous page
+fn resume_key(cursor: Option<&str>) -> Result<Option<String>> {
+    match cursor {
+        Some(cursor) => {
+            let key = hex::decode(cursor)
+                .map_err(|_| ImmutableEncryptionError::storage("Invalid cursor"))?;
+            let key = String::from_utf8(key)?;
+            Ok(Some(format!("{}\0", key)))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_resumes_after_last_key() -> Result<()> {
+        let cursor = hex::encode("device:cam_1:00000000000000001000:00000000000000000007");
+        let resumed = resume_key(Some(&cursor))?.unwrap();
+
+        assert!(resumed.as_str() > "device:cam_1:00000000000000001000:00000000000000000007");
+        assert!(resumed.as_str() < "device:cam_1:00000000000000001000:00000000000000000008");
+        assert!(resume_key(Some("not hex")).is_err());
+        assert_eq!(page_size(Some(100_000)), MAX_PAGE_SIZE);
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, Write};
+use std::pin::Pin;
+use tokio::runtime::Handle;
+
+use crate::aff4::{self, Aff4Writer, CaseDetails};
+use crate::bundle::{verify_bundle, BundleVerification, EvidenceBundle};
+use crate::content_credentials::{ContentCredentials, FrameProvenance};
+use crate::crypto::{self, EncryptionEngine};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::playback::SnapshotFormat;
+use crate::verification::VerificationEngine;
+
+// Archive layout: the signed bundle at the root, decrypted frames under media/
+pub const BUNDLE_ENTRY: &str = "bundle.ndjson";
+pub const MEDIA_INDEX_ENTRY: &str = "media/index.json";
+pub const COURT_REPORT_ENTRY: &str = "court_report.json";
+
+const ZSTD_LEVEL: i32 = 3;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaEntry {
+    pub path: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub sha256: String, // of the decrypted payload
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub content_credentials: bool, // a C2PA manifest is embedded in the file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub evidence_id: String,
+    pub bundle_bytes: u64,
+    pub media: Vec<MediaEntry>, // empty unless decrypted media was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aff4_volume: Option<String>, // URN of an AFF4 export
+}
+
+// Writes a session export as a zstd-compressed tar. The bundle keeps its own
+// signature, so the archive needs none; media is only added when the caller
+// has been authorized to see plaintext. Blocking: run it on a blocking thread,
+// where `handle` drives the storage reads. With `credentials`, stills get a
+// C2PA manifest naming the frame, its session and their anchors.
+pub fn write_archive<W: Write>(
+    handle: &Handle,
+    bundle: EvidenceBundle,
+    include_media: bool,
+    credentials: Option<&ContentCredentials>,
+    out: W,
+) -> Result<ExportSummary> {
+    let evidence_id = bundle.evidence_id().to_string();
+    let manifest = bundle.manifest().clone();
+    let mut archive = tar::Builder::new(zstd::Encoder::new(out, ZSTD_LEVEL)?);
+
+    // The tar header needs the size first, which takes a pass over the bundle
+    // unless it was generated before
+    let bundle_bytes = handle.block_on(bundle.digest())?.byte_len;
+    let lines = BlockingReader::new(handle, bundle.clone().stream(None));
+    archive.append_data(&mut entry_header(bundle_bytes), BUNDLE_ENTRY, lines)?;
+
+    let mut media = Vec::new();
+    if include_media {
+        let mut frames = Box::pin(bundle.media());
+        while let Some(item) = handle.block_on(frames.next()) {
+            let (frame, data) = item?;
+            let format = SnapshotFormat::detect(&data);
+            let sha256 = crypto::sha256_hex(&data);
+            let (file, content_credentials) = match (credentials, format) {
+                (Some(credentials), Some(format)) => {
+                    let provenance = FrameProvenance::new(&frame, Some(&manifest));
+                    (credentials.sign_image(&data, format, &provenance)?, true)
+                }
+                _ => (data, false),
+            };
+            let path = media_path(frame.sequence, format);
+            archive.append_data(&mut entry_header(file.len() as u64), &path, file.as_slice())?;
+            media.push(MediaEntry {
+                path,
+                sequence: frame.sequence,
+                timestamp: frame.timestamp,
+                sha256,
+                content_credentials,
+            });
+        }
+
+        let index = serde_json::to_vec_pretty(&media)?;
+        archive.append_data(
+            &mut entry_header(index.len() as u64),
+            MEDIA_INDEX_ENTRY,
+            index.as_slice(),
+        )?;
+    }
+
+    archive.into_inner()?.finish()?.flush()?;
+    Ok(ExportSummary {
+        evidence_id,
+        bundle_bytes,
+        media,
+        aff4_volume: None,
+    })
+}
+
+// Writes a session export as an AFF4 logical container for forensic suites:
+// the same bundle and media as `write_archive`, plus the signed court report
+// with its chain of custody, each listed with its hashes in the volume's
+// turtle. Blocking, like `write_archive`.
+pub fn write_aff4<W: Write + Seek>(
+    handle: &Handle,
+    bundle: EvidenceBundle,
+    include_media: bool,
+    court_report: &[u8],
+    examiner: &str,
+    out: W,
+) -> Result<ExportSummary> {
+    let evidence_id = bundle.evidence_id().to_string();
+    let manifest = bundle.manifest();
+    let case = CaseDetails {
+        case_number: manifest.case_id.clone(),
+        case_name: evidence_id.clone(),
+        description: format!(
+            "Sealed session {} from device {}, {} frames, manifest hash {}",
+            manifest.session_id, manifest.device_id, manifest.frame_count, manifest.manifest_hash
+        ),
+        examiner: examiner.to_string(),
+    };
+    let mut container = Aff4Writer::new(out, case)?;
+
+    let lines = BlockingReader::new(handle, bundle.clone().stream(None));
+    let bundle_bytes = container.add_file(BUNDLE_ENTRY, lines)?.size;
+    container.add_file(COURT_REPORT_ENTRY, court_report)?;
+
+    let mut media = Vec::new();
+    if include_media {
+        let mut frames = Box::pin(bundle.media());
+        while let Some(item) = handle.block_on(frames.next()) {
+            let (frame, data) = item?;
+            let path = media_path(frame.sequence, SnapshotFormat::detect(&data));
+            let image = container.add_file(&path, data.as_slice())?;
+            media.push(MediaEntry {
+                path,
+                sequence: frame.sequence,
+                timestamp: frame.timestamp,
+                sha256: image.sha256,
+                content_credentials: false,
+            });
+        }
+
+        let index = serde_json::to_vec_pretty(&media)?;
+        container.add_file(MEDIA_INDEX_ENTRY, index.as_slice())?;
+    }
+
+    let aff4_volume = Some(container.volume().to_string());
+    container.finish()?.0.flush()?;
+    Ok(ExportSummary {
+        evidence_id,
+        bundle_bytes,
+        media,
+        aff4_volume,
+    })
+}
+
+pub fn is_archive(prefix: &[u8]) -> bool {
+    prefix.starts_with(&ZSTD_MAGIC)
+}
+
+// Verifies the bundle inside an archive written by `write_archive`
+pub fn verify_archive<R: Read>(
+    reader: R,
+    engine: &EncryptionEngine,
+    verifier: &VerificationEngine,
+) -> Result<BundleVerification> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(reader)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.path()?.as_os_str() == BUNDLE_ENTRY {
+            return verify_bundle(std::io::BufReader::new(entry), engine, verifier);
+        }
+    }
+    Err(ImmutableEncryptionError::Storage(format!(
+        "Archive has no {}",
+        BUNDLE_ENTRY
+    )))
+}
+
+// Verifies the bundle inside a container written by `write_aff4`
+pub fn verify_aff4<R: Read + Seek>(
+    reader: R,
+    engine: &EncryptionEngine,
+    verifier: &VerificationEngine,
+) -> Result<BundleVerification> {
+    aff4::open_member(reader, BUNDLE_ENTRY, |member| {
+        verify_bundle(std::io::BufReader::new(member), engine, verifier)
+    })
+}
+
+fn media_path(sequence: u64, format: Option<SnapshotFormat>) -> String {
+    let extension = match format {
+        Some(SnapshotFormat::Jpeg) => "jpg",
+        Some(SnapshotFormat::Png) => "png",
+        None => "bin",
+    };
+    format!("media/{:010}.{}", sequence, extension)
+}
+
+fn entry_header(size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o444);
+    header
+}
+
+// Adapts a byte-chunk stream to `Read` for the tar writer
+struct BlockingReader<'a, S> {
+    handle: &'a Handle,
+    stream: Pin<Box<S>>,
+    chunk: Vec<u8>,
+    offset: usize,
+}
+
+impl<'a, S> BlockingReader<'a, S> {
+    fn new(handle: &'a Handle, stream: S) -> Self {
+        Self {
+            handle,
+            stream: Box::pin(stream),
+            chunk: Vec::new(),
+            offset: 0,
+        }
+    }
+}
+
+impl<S: Stream<Item = Result<Vec<u8>>>> Read for BlockingReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.offset == self.chunk.len() {
+            match self.handle.block_on(self.stream.next()) {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.offset = 0;
+                }
+                Some(Err(e)) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        e.to_string(),
+                    ))
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.offset);
+        buf[..len].copy_from_slice(&self.chunk[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[test]
+    fn test_blocking_reader_joins_chunks() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let chunks: Vec<Result<Vec<u8>>> =
+            vec![Ok(b"abc".to_vec()), Ok(Vec::new()), Ok(b"de".to_vec())];
+        let mut reader = BlockingReader::new(runtime.handle(), stream::iter(chunks));
+
+        let mut out = String::new();
+        reader.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "abcde");
+
+        let failing = stream::iter(vec![Err(ImmutableEncryptionError::storage(
+            "storage offline",
+        ))]);
+        let mut reader = BlockingReader::new(runtime.handle(), failing);
+        assert!(reader.read(&mut [0u8; 4]).is_err());
+    }
+}
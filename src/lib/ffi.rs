@@ -0,0 +1,647 @@
+// C ABI for sealing on the camera itself; `include/immutable_encryption.h`
+// declares these functions and documents each one's pointer contract
+#![allow(clippy::missing_safety_doc)]
+
+use bytes::Bytes;
+use immutable_encryption_core::{chain, hash};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::crypto::{Cipher, CryptoConfig, EncryptionEngine};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::merkle::MerkleTree;
+use crate::{DeviceSignature, EncryptedFrame, FrameMetadata, VideoFrame};
+
+pub const IE_CIPHER_AES_256_GCM: u32 = 0;
+pub const IE_CIPHER_CHACHA20_POLY1305: u32 = 1;
+pub const IE_CIPHER_XCHACHA20_POLY1305: u32 = 2;
+pub const IE_CIPHER_AES_256_GCM_SIV: u32 = 3;
+
+// Hex SHA-256 plus the terminating NUL
+pub const IE_CHAIN_TIP_LEN: usize = 65;
+
+// An Ed25519 identity key's seed and public key
+pub const IE_IDENTITY_KEY_LEN: usize = 32;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IeStatus {
+    Ok = 0,
+    InvalidArgument = 1,
+    Crypto = 2,
+    BufferTooSmall = 3,
+    Internal = 4,
+}
+
+#[repr(C)]
+pub struct IeFrame {
+    pub data: *const u8,
+    pub data_len: usize,
+    pub timestamp: u64,
+    pub sequence: u64, // 0 takes the next one
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub codec: *const c_char, // may be NULL
+}
+
+// Signed summary of the frames sealed since the previous export, which the
+// device hands to a node for anchoring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "mobile", derive(uniffi::Record))]
+pub struct SegmentProof {
+    pub device_id: String,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub previous_hash: String, // chain tip before the first frame
+    pub chain_tip: String,
+    pub merkle_root: String, // over the frames' chain hashes, in order
+    pub signature: String,
+}
+
+impl SegmentProof {
+    fn digest(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(
+            &self.device_id,
+            self.first_sequence,
+            self.last_sequence,
+            &self.previous_hash,
+            &self.chain_tip,
+            &self.merkle_root,
+        ))?)
+    }
+
+    // Node side: where the proof is kept once its segment is stored
+    pub fn key(device_id: &str, last_sequence: u64) -> String {
+        format!("{}{:020}", Self::prefix(device_id), last_sequence)
+    }
+
+    pub fn prefix(device_id: &str) -> String {
+        format!("device_segment:{}:", device_id)
+    }
+
+    // Node side: `frames` must be exactly the segment the proof covers, each
+    // opening under the node's frame keys, so only a holder of the node's key
+    // could have sealed and signed them
+    pub fn verify(&self, engine: &EncryptionEngine, frames: &[EncryptedFrame]) -> Result<()> {
+        let tampered =
+            |details: String| Err(ImmutableEncryptionError::EvidenceTampered { details });
+        if !engine.verify_signature(&self.digest()?, &self.signature) {
+            return tampered(format!(
+                "segment proof for {} is not signed with this node's key",
+                self.device_id
+            ));
+        }
+        if frames.iter().any(|f| f.device_id != self.device_id) {
+            return Err(ImmutableEncryptionError::invalid_request(&format!(
+                "segment for {} carries another device's frames",
+                self.device_id
+            )));
+        }
+        let (Some(first), Some(last)) = (frames.first(), frames.last()) else {
+            return Err(ImmutableEncryptionError::invalid_request(
+                "a segment needs at least one frame",
+            ));
+        };
+        chain::check_links(frames)?;
+        if first.sequence != self.first_sequence
+            || last.sequence != self.last_sequence
+            || first.previous_hash != self.previous_hash
+            || last.hash != self.chain_tip
+        {
+            return tampered(format!(
+                "frames {}-{} of {} are not the segment the proof covers",
+                first.sequence, last.sequence, self.device_id
+            ));
+        }
+        let tree = MerkleTree::new(frames.iter().map(|f| f.hash.clone()).collect())?;
+        if tree.root() != self.merkle_root {
+            return tampered(format!(
+                "Merkle root of {}'s segment does not match its proof",
+                self.device_id
+            ));
+        }
+
+        for frame in frames {
+            let payload = engine.decrypt_frame_data(frame)?;
+            let signed = frame.device_signature.as_ref();
+            if signed.is_some_and(|s| s.payload_hash != hash::sha256_hex(&payload)) {
+                return tampered(format!(
+                    "frame {} of {} was signed over another payload",
+                    frame.sequence, self.device_id
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// What a device uploads to `POST /devices/segments`: the frames it sealed
+// since its previous export, in order, and the proof that covers them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSegment {
+    pub proof: SegmentProof,
+    pub frames: Vec<EncryptedFrame>,
+}
+
+// One device's hash chain. Frame keys are derived from the provisioned key
+// exactly as the node derives them from its primary key, so a node holding
+// that key opens device-sealed frames like its own; `key_rotation_interval`
+// must match the node's `encryption.key_rotation_interval_seconds`.
+pub struct DeviceSealer {
+    engine: EncryptionEngine,
+    device_id: String,
+    identity: Option<Ed25519KeyPair>,
+    tip: String,
+    last_sequence: u64,
+    segment_start: String,
+    segment: Vec<(u64, String)>, // sequence, chain hash since the last export
+}
+
+impl DeviceSealer {
+    pub fn new(
+        key: &[u8],
+        device_id: &str,
+        cipher: Cipher,
+        key_rotation_interval: u64,
+    ) -> Result<Self> {
+        if device_id.is_empty() {
+            return Err(ImmutableEncryptionError::InvalidRequest(
+                "device_id is empty".to_string(),
+            ));
+        }
+        let engine = EncryptionEngine::new(CryptoConfig {
+            cipher_suite: cipher,
+            ..CryptoConfig::software(key.to_vec(), key_rotation_interval)
+        })?;
+        Ok(Self {
+            engine,
+            device_id: device_id.to_string(),
+            identity: None,
+            tip: "0".repeat(64),
+            last_sequence: 0,
+            segment_start: "0".repeat(64),
+            segment: Vec::new(),
+        })
+    }
+
+    // Continues a chain the firmware persisted before a reboot
+    pub fn resume(&mut self, tip: &str, last_sequence: u64) -> Result<()> {
+        if tip.len() != 64 || hex::decode(tip).is_err() {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "chain tip {:?} is not a hex SHA-256",
+                tip
+            )));
+        }
+        self.tip = tip.to_string();
+        self.segment_start = tip.to_string();
+        self.last_sequence = last_sequence;
+        self.segment.clear();
+        Ok(())
+    }
+
+    // Signs every frame sealed from now on with the device's Ed25519 identity
+    // key. Returns the public key, which the node lists under
+    // `verification.device_keys`.
+    pub fn set_identity_key(&mut self, seed: &[u8]) -> Result<Vec<u8>> {
+        if seed.len() != IE_IDENTITY_KEY_LEN {
+            return Err(ImmutableEncryptionError::crypto(
+                "The identity key must be a 32-byte Ed25519 seed",
+            ));
+        }
+        let identity = Ed25519KeyPair::from_seed_unchecked(seed)
+            .map_err(|_| ImmutableEncryptionError::crypto("Invalid identity key"))?;
+        let public_key = identity.public_key().as_ref().to_vec();
+        self.identity = Some(identity);
+        Ok(public_key)
+    }
+
+    pub fn seal(
+        &mut self,
+        data: Bytes,
+        timestamp: u64,
+        sequence: u64,
+        metadata: FrameMetadata,
+    ) -> Result<EncryptedFrame> {
+        let sequence = if sequence == 0 {
+            self.last_sequence + 1
+        } else {
+            sequence
+        };
+        if sequence <= self.last_sequence {
+            return Err(ImmutableEncryptionError::InvalidSequence(sequence));
+        }
+
+        let frame = VideoFrame {
+            timestamp,
+            sequence,
+            data,
+            metadata,
+            signature: None,
+        };
+        let frame_hash = self.engine.generate_frame_hash(&frame)?;
+        let hash = self
+            .engine
+            .create_hash_chain_link(&frame_hash, &self.tip, sequence)?;
+        let (ciphertext, nonce) =
+            self.engine
+                .encrypt_data(&frame.data, &self.device_id, sequence, timestamp, None)?;
+        let device_signature = self.identity.as_ref().map(|identity| {
+            let payload_hash = hash::sha256_hex(&frame.data);
+            let message =
+                hash::capture_message(&self.device_id, sequence, timestamp, &payload_hash);
+            DeviceSignature {
+                payload_hash,
+                signature: identity.sign(&message).as_ref().to_vec(),
+            }
+        });
+
+        let previous_hash = std::mem::replace(&mut self.tip, hash.clone());
+        self.last_sequence = sequence;
+        self.segment.push((sequence, hash.clone()));
+        Ok(EncryptedFrame {
+            sequence,
+            device_id: self.device_id.clone(),
+            ciphertext,
+            hash,
+            previous_hash,
+            nonce,
+            timestamp,
+            blockchain_anchors: Vec::new(),
+            cipher: self.engine.cipher_suite(),
+            compressed: false,
+            attestation: None,
+            device_signature,
+        })
+    }
+
+    pub fn chain_tip(&self) -> &str {
+        &self.tip
+    }
+
+    // None when nothing was sealed since the last export
+    pub fn export_proof(&mut self) -> Result<Option<SegmentProof>> {
+        let (first_sequence, last_sequence) = match (self.segment.first(), self.segment.last()) {
+            (Some((first, _)), Some((last, _))) => (*first, *last),
+            _ => return Ok(None),
+        };
+        let tree = MerkleTree::new(self.segment.iter().map(|(_, hash)| hash.clone()).collect())?;
+
+        let mut proof = SegmentProof {
+            device_id: self.device_id.clone(),
+            first_sequence,
+            last_sequence,
+            previous_hash: self.segment_start.clone(),
+            chain_tip: self.tip.clone(),
+            merkle_root: tree.root(),
+            signature: String::new(),
+        };
+        proof.signature = self.engine.sign(&proof.digest()?);
+
+        self.segment_start = self.tip.clone();
+        self.segment.clear();
+        Ok(Some(proof))
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn status_of(error: &ImmutableEncryptionError) -> IeStatus {
+    match error {
+        ImmutableEncryptionError::Crypto(_) => IeStatus::Crypto,
+        ImmutableEncryptionError::InvalidRequest(_)
+        | ImmutableEncryptionError::InvalidSequence(_) => IeStatus::InvalidArgument,
+        _ => IeStatus::Internal,
+    }
+}
+
+// Runs `f`, keeping its error for `ie_last_error`; a panic must not unwind
+// into C, so it becomes IE_ERR_INTERNAL
+fn ffi_call(f: impl FnOnce() -> Result<()>) -> IeStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => IeStatus::Ok,
+        Ok(Err(e)) => {
+            let status = status_of(&e);
+            set_last_error(e.to_string());
+            status
+        }
+        Err(_) => {
+            set_last_error("panic in the sealing library".to_string());
+            IeStatus::Internal
+        }
+    }
+}
+
+fn invalid(message: &str) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::InvalidRequest(message.to_string())
+}
+
+unsafe fn c_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str> {
+    if ptr.is_null() {
+        return Err(invalid(&format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| invalid(&format!("{} is not UTF-8", name)))
+}
+
+unsafe fn sealer_mut<'a>(sealer: *mut DeviceSealer) -> Result<&'a mut DeviceSealer> {
+    sealer.as_mut().ok_or_else(|| invalid("sealer is NULL"))
+}
+
+unsafe fn write_json<T: Serialize>(value: &T, out: *mut *mut c_char) -> Result<()> {
+    let json = CString::new(serde_json::to_string(value)?)
+        .map_err(|e| ImmutableEncryptionError::Internal(e.to_string()))?;
+    *out = json.into_raw();
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn ie_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_sealer_new(
+    key: *const u8,
+    key_len: usize,
+    device_id: *const c_char,
+    cipher: u32,
+    key_rotation_interval: u64,
+) -> *mut DeviceSealer {
+    let mut sealer = None;
+    ffi_call(|| {
+        if key.is_null() {
+            return Err(invalid("key is NULL"));
+        }
+        let cipher = match cipher {
+            IE_CIPHER_AES_256_GCM => Cipher::Aes256Gcm,
+            IE_CIPHER_CHACHA20_POLY1305 => Cipher::ChaCha20Poly1305,
+            IE_CIPHER_XCHACHA20_POLY1305 => Cipher::XChaCha20Poly1305,
+            IE_CIPHER_AES_256_GCM_SIV => Cipher::Aes256GcmSiv,
+            other => return Err(invalid(&format!("unknown cipher {}", other))),
+        };
+        let key = std::slice::from_raw_parts(key, key_len);
+        sealer = Some(DeviceSealer::new(
+            key,
+            c_str(device_id, "device_id")?,
+            cipher,
+            key_rotation_interval,
+        )?);
+        Ok(())
+    });
+    sealer.map_or(std::ptr::null_mut(), |sealer| {
+        Box::into_raw(Box::new(sealer))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_sealer_free(sealer: *mut DeviceSealer) {
+    if !sealer.is_null() {
+        drop(Box::from_raw(sealer));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_sealer_resume(
+    sealer: *mut DeviceSealer,
+    chain_tip: *const c_char,
+    last_sequence: u64,
+) -> IeStatus {
+    ffi_call(|| sealer_mut(sealer)?.resume(c_str(chain_tip, "chain_tip")?, last_sequence))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_sealer_set_identity_key(
+    sealer: *mut DeviceSealer,
+    seed: *const u8,
+    seed_len: usize,
+    out_public_key: *mut u8,
+) -> IeStatus {
+    ffi_call(|| {
+        let sealer = sealer_mut(sealer)?;
+        if seed.is_null() {
+            return Err(invalid("seed is NULL"));
+        }
+        let public_key = sealer.set_identity_key(std::slice::from_raw_parts(seed, seed_len))?;
+        if !out_public_key.is_null() {
+            std::ptr::copy_nonoverlapping(public_key.as_ptr(), out_public_key, public_key.len());
+        }
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_seal_frame(
+    sealer: *mut DeviceSealer,
+    frame: *const IeFrame,
+    out_json: *mut *mut c_char,
+) -> IeStatus {
+    ffi_call(|| {
+        let sealer = sealer_mut(sealer)?;
+        let frame = frame.as_ref().ok_or_else(|| invalid("frame is NULL"))?;
+        if out_json.is_null() {
+            return Err(invalid("out_json is NULL"));
+        }
+        let data = match (frame.data.is_null(), frame.data_len) {
+            (_, 0) => &[][..],
+            (true, _) => return Err(invalid("frame data is NULL")),
+            (false, len) => std::slice::from_raw_parts(frame.data, len),
+        };
+        let codec = if frame.codec.is_null() {
+            String::new()
+        } else {
+            c_str(frame.codec, "codec")?.to_string()
+        };
+        let metadata = FrameMetadata {
+            device_id: sealer.device_id.clone(),
+            location: None,
+            resolution: (frame.width, frame.height),
+            fps: frame.fps,
+            codec,
+            telemetry: None,
+        };
+        // The caller keeps ownership of its buffer, so this is the one copy
+        let data = Bytes::copy_from_slice(data);
+        let sealed = sealer.seal(data, frame.timestamp, frame.sequence, metadata)?;
+        write_json(&sealed, out_json)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_chain_tip(
+    sealer: *const DeviceSealer,
+    out: *mut c_char,
+    out_len: usize,
+) -> IeStatus {
+    let sealer = match sealer.as_ref() {
+        Some(sealer) => sealer,
+        None => return ffi_call(|| Err(invalid("sealer is NULL"))),
+    };
+    if out.is_null() {
+        return ffi_call(|| Err(invalid("out is NULL")));
+    }
+    let tip = sealer.chain_tip().as_bytes();
+    if out_len < tip.len() + 1 {
+        set_last_error(format!("the chain tip needs {} bytes", tip.len() + 1));
+        return IeStatus::BufferTooSmall;
+    }
+    std::ptr::copy_nonoverlapping(tip.as_ptr().cast::<c_char>(), out, tip.len());
+    *out.add(tip.len()) = 0;
+    IeStatus::Ok
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_export_proof(
+    sealer: *mut DeviceSealer,
+    out_json: *mut *mut c_char,
+) -> IeStatus {
+    ffi_call(|| {
+        let sealer = sealer_mut(sealer)?;
+        if out_json.is_null() {
+            return Err(invalid("out_json is NULL"));
+        }
+        *out_json = std::ptr::null_mut();
+        match sealer.export_proof()? {
+            Some(proof) => write_json(&proof, out_json),
+            None => Ok(()),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ie_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealing_through_the_c_abi() -> Result<()> {
+        let key = [9u8; 32];
+        let device_id = CString::new("cam_7").unwrap();
+        let codec = CString::new("MJPEG").unwrap();
+        let data = b"frame bytes";
+
+        unsafe {
+            let sealer = ie_sealer_new(
+                key.as_ptr(),
+                key.len(),
+                device_id.as_ptr(),
+                IE_CIPHER_CHACHA20_POLY1305,
+                3600,
+            );
+            assert!(!sealer.is_null());
+            let seed = [4u8; IE_IDENTITY_KEY_LEN];
+            let mut public_key = [0u8; IE_IDENTITY_KEY_LEN];
+            assert_eq!(
+                ie_sealer_set_identity_key(sealer, seed.as_ptr(), 16, public_key.as_mut_ptr()),
+                IeStatus::Crypto
+            );
+            assert_eq!(
+                ie_sealer_set_identity_key(
+                    sealer,
+                    seed.as_ptr(),
+                    seed.len(),
+                    public_key.as_mut_ptr()
+                ),
+                IeStatus::Ok
+            );
+
+            let mut frames = Vec::new();
+            for timestamp in [100, 101] {
+                let frame = IeFrame {
+                    data: data.as_ptr(),
+                    data_len: data.len(),
+                    timestamp,
+                    sequence: 0,
+                    width: 640,
+                    height: 480,
+                    fps: 15,
+                    codec: codec.as_ptr(),
+                };
+                let mut json = std::ptr::null_mut();
+                assert_eq!(ie_seal_frame(sealer, &frame, &mut json), IeStatus::Ok);
+                let sealed: EncryptedFrame =
+                    serde_json::from_slice(CStr::from_ptr(json).to_bytes())?;
+                ie_string_free(json);
+                frames.push(sealed);
+            }
+            assert_eq!((frames[0].sequence, frames[1].sequence), (1, 2));
+            assert_eq!(frames[1].previous_hash, frames[0].hash);
+            let signed = frames[1].device_signature.as_ref().unwrap();
+            let message = hash::capture_message("cam_7", 2, 101, &signed.payload_hash);
+            ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+                .verify(&message, &signed.signature)?;
+            // A node holding the key opens the frames with its own frame keys
+            let node = EncryptionEngine::new(CryptoConfig::software(key.to_vec(), 3600))?;
+            assert_eq!(node.decrypt_frame_data(&frames[0])?, data);
+
+            let mut tip = [0 as c_char; IE_CHAIN_TIP_LEN];
+            assert_eq!(
+                ie_chain_tip(sealer, tip.as_mut_ptr(), 10),
+                IeStatus::BufferTooSmall
+            );
+            assert_eq!(
+                ie_chain_tip(sealer, tip.as_mut_ptr(), tip.len()),
+                IeStatus::Ok
+            );
+            assert_eq!(
+                CStr::from_ptr(tip.as_ptr()).to_str().unwrap(),
+                frames[1].hash
+            );
+
+            let mut json = std::ptr::null_mut();
+            assert_eq!(ie_export_proof(sealer, &mut json), IeStatus::Ok);
+            let proof: SegmentProof = serde_json::from_slice(CStr::from_ptr(json).to_bytes())?;
+            ie_string_free(json);
+            assert_eq!((proof.first_sequence, proof.last_sequence), (1, 2));
+            assert_eq!(proof.chain_tip, frames[1].hash);
+            let tree = MerkleTree::new(frames.iter().map(|f| f.hash.clone()).collect())?;
+            assert_eq!(proof.merkle_root, tree.root());
+            proof.verify(&node, &frames)?;
+            assert!(proof.verify(&node, &frames[1..]).is_err());
+            assert_eq!(ie_export_proof(sealer, &mut json), IeStatus::Ok);
+            assert!(json.is_null());
+
+            let stale = IeFrame {
+                data: data.as_ptr(),
+                data_len: data.len(),
+                timestamp: 102,
+                sequence: 2,
+                width: 640,
+                height: 480,
+                fps: 15,
+                codec: std::ptr::null(),
+            };
+            assert_eq!(
+                ie_seal_frame(sealer, &stale, &mut json),
+                IeStatus::InvalidArgument
+            );
+            assert!(CStr::from_ptr(ie_last_error())
+                .to_str()
+                .unwrap()
+                .contains("sequence"));
+            ie_sealer_free(sealer);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+use crate::devices::DeviceOverride;
+use crate::VideoFrame;
+
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+// Faster than any camera platform the node expects, drones and patrol cars
+// included; a `[devices.<id>]` section can lower or raise it
+pub const DEFAULT_MAX_SPEED_MPS: f64 = 100.0;
+
+// An area a device is expected to record in, in degrees and metres
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Geofence {
+    Circle {
+        latitude: f64,
+        longitude: f64,
+        radius_m: f64,
+    },
+    Polygon {
+        points: Vec<(f64, f64)>, // (latitude, longitude) vertices, in order
+    },
+}
+
+impl Geofence {
+    pub fn contains(&self, location: (f64, f64)) -> bool {
+        match self {
+            Geofence::Circle {
+                latitude,
+                longitude,
+                radius_m,
+            } => distance_m((*latitude, *longitude), location) <= *radius_m,
+            // Even-odd ray casting on the plane; fences are small enough that
+            // the curvature between vertices doesn't matter
+            Geofence::Polygon { points } => {
+                let (lat, lon) = location;
+                let mut inside = false;
+                let mut j = points.len().wrapping_sub(1);
+                for (i, &(lat_i, lon_i)) in points.iter().enumerate() {
+                    let (lat_j, lon_j) = points[j];
+                    if (lat_i > lat) != (lat_j > lat)
+                        && lon < (lon_j - lon_i) * (lat - lat_i) / (lat_j - lat_i) + lon_i
+                    {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+
+    // Describes what is wrong with the fence, if anything
+    pub fn problem(&self) -> Option<&'static str> {
+        match self {
+            Geofence::Circle { radius_m, .. } if !radius_m.is_finite() || *radius_m <= 0.0 => {
+                Some("radius_m must be positive")
+            }
+            Geofence::Polygon { points } if points.len() < 3 => {
+                Some("a polygon needs at least 3 points")
+            }
+            _ => None,
+        }
+    }
+}
+
+// What made a frame's location implausible
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LocationIssue {
+    OutsideGeofence,
+    ImpossibleSpeed {
+        previous_sequence: u64,
+        speed_mps: f64,
+        max_speed_mps: f64,
+    },
+}
+
+// Flags a sealed frame without rejecting it: the footage is still evidence,
+// but its reported position deserves a closer look. Persisted under
+// `location_finding:{device}:{sequence}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationFinding {
+    pub device_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub location: (f64, f64),
+    #[serde(flatten)]
+    pub issue: LocationIssue,
+}
+
+impl LocationFinding {
+    pub fn key(device_id: &str, sequence: u64) -> String {
+        format!("{}{:020}", Self::prefix(device_id), sequence)
+    }
+
+    pub fn prefix(device_id: &str) -> String {
+        format!("location_finding:{}:", device_id)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LocationRules {
+    geofences: Vec<Geofence>, // inside any one is enough; empty allows anywhere
+    max_speed_mps: f64,
+}
+
+impl Default for LocationRules {
+    fn default() -> Self {
+        Self {
+            geofences: Vec::new(),
+            max_speed_mps: DEFAULT_MAX_SPEED_MPS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fix {
+    sequence: u64,
+    timestamp: u64,
+    location: (f64, f64),
+}
+
+// Checks each frame's GPS metadata against the device's geofences and
+// against the last position it reported
+#[derive(Debug, Default)]
+pub struct LocationValidator {
+    rules: HashMap<String, LocationRules>,
+    last_fix: Mutex<HashMap<String, Fix>>,
+}
+
+impl LocationValidator {
+    pub fn new(overrides: &HashMap<String, DeviceOverride>) -> Self {
+        let rules = overrides
+            .iter()
+            .map(|(device_id, device)| {
+                let rules = LocationRules {
+                    geofences: device.geofences.clone(),
+                    max_speed_mps: device.max_speed_mps.unwrap_or(DEFAULT_MAX_SPEED_MPS),
+                };
+                (device_id.clone(), rules)
+            })
+            .collect();
+        Self {
+            rules,
+            last_fix: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Frames without a location pass unchecked. Speed is measured between
+    // frames from different seconds, since timestamps have whole-second
+    // resolution.
+    pub async fn check(&self, frame: &VideoFrame) -> Vec<LocationFinding> {
+        let Some(location) = frame.metadata.location else {
+            return Vec::new();
+        };
+        let device_id = &frame.metadata.device_id;
+        let default_rules = LocationRules::default();
+        let rules = self.rules.get(device_id).unwrap_or(&default_rules);
+
+        let finding = |issue| LocationFinding {
+            device_id: device_id.clone(),
+            sequence: frame.sequence,
+            timestamp: frame.timestamp,
+            location,
+            issue,
+        };
+        let mut findings = Vec::new();
+
+        if !rules.geofences.is_empty() && !rules.geofences.iter().any(|g| g.contains(location)) {
+            findings.push(finding(LocationIssue::OutsideGeofence));
+        }
+
+        let mut last_fix = self.last_fix.lock().await;
+        match last_fix.get(device_id) {
+            // Out-of-order frames are the hash chain's concern, not ours
+            Some(previous) if frame.timestamp <= previous.timestamp => return findings,
+            Some(previous) => {
+                let elapsed = (frame.timestamp - previous.timestamp) as f64;
+                let speed_mps = distance_m(previous.location, location) / elapsed;
+                if speed_mps > rules.max_speed_mps {
+                    findings.push(finding(LocationIssue::ImpossibleSpeed {
+                        previous_sequence: previous.sequence,
+                        speed_mps,
+                        max_speed_mps: rules.max_speed_mps,
+                    }));
+                }
+            }
+            None => {}
+        }
+        last_fix.insert(
+            device_id.clone(),
+            Fix {
+                sequence: frame.sequence,
+                timestamp: frame.timestamp,
+                location,
+            },
+        );
+        findings
+    }
+}
+
+// Great-circle distance between two (latitude, longitude) points
+pub fn distance_m(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat_a, lat_b) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().min(1.0).asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FrameMetadata;
+
+    fn frame(sequence: u64, timestamp: u64, location: (f64, f64)) -> VideoFrame {
+        VideoFrame {
+            timestamp,
+            sequence,
+            data: vec![1, 2, 3].into(),
+            metadata: FrameMetadata {
+                device_id: "patrol-3".to_string(),
+                location: Some(location),
+                resolution: (1920, 1080),
+                fps: 30,
+                codec: "H.264".to_string(),
+                telemetry: None,
+            },
+            signature: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flags_frames_outside_fence_or_too_fast() {
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            "patrol-3".to_string(),
+            DeviceOverride {
+                geofences: vec![
+                    Geofence::Circle {
+                        latitude: 51.5007,
+                        longitude: -0.1246,
+                        radius_m: 1000.0,
+                    },
+                    Geofence::Polygon {
+                        points: vec![(51.0, 1.0), (51.0, 2.0), (52.0, 2.0), (52.0, 1.0)],
+                    },
+                ],
+                max_speed_mps: Some(40.0),
+                ..DeviceOverride::default()
+            },
+        );
+        let validator = LocationValidator::new(&overrides);
+
+        // Inside the circle, then a walk across it
+        assert!(validator
+            .check(&frame(1, 1000, (51.5007, -0.1246)))
+            .await
+            .is_empty());
+        assert!(validator
+            .check(&frame(2, 1000, (51.5010, -0.1240)))
+            .await
+            .is_empty());
+        assert!(validator
+            .check(&frame(3, 1060, (51.5030, -0.1200)))
+            .await
+            .is_empty());
+
+        // Outside every fence, and ~9 km away a second later
+        let findings = validator.check(&frame(4, 1061, (51.5800, -0.1200))).await;
+        let issues: Vec<_> = findings.iter().map(|f| &f.issue).collect();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0], &LocationIssue::OutsideGeofence);
+        assert!(matches!(
+            issues[1],
+            LocationIssue::ImpossibleSpeed { previous_sequence: 3, speed_mps, .. }
+                if *speed_mps > 8000.0
+        ));
+
+        // Inside the polygon, after long enough to have driven there
+        let findings = validator.check(&frame(5, 10_000, (51.5, 1.5))).await;
+        assert!(findings.is_empty());
+
+        // Devices without a section only get the default speed limit
+        let mut other = frame(1, 1000, (0.0, 0.0));
+        other.metadata.device_id = "body-cam-1".to_string();
+        assert!(validator.check(&other).await.is_empty());
+        assert!((distance_m((0.0, 0.0), (0.0, 1.0)) - 111_195.0).abs() < 1.0);
+    }
+}
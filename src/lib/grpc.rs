@@ -0,0 +1,318 @@
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::time::Duration;
+use tonic::transport::{Server, ServerTlsConfig};
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::device_auth::ClientCertificate;
+use crate::error::ImmutableEncryptionError;
+use crate::storage;
+use crate::trace::{self, REQUEST_ID_HEADER};
+use crate::video::{RealTimeEncryptionNode, SubmitOutcome};
+use crate::wire;
+use crate::FrameSender;
+
+pub use crate::wire::proto;
+
+use proto::{
+    export_service_server::{ExportService, ExportServiceServer},
+    ingest_service_server::{IngestService, IngestServiceServer},
+    report_service_server::{ReportService, ReportServiceServer},
+    verification_service_server::{VerificationService, VerificationServiceServer},
+};
+
+// How long a unary SubmitFrame waits for the pipeline to seal the frame
+const SUBMIT_SEAL_WAIT: Duration = Duration::from_secs(10);
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+// One implementation backs every service so they share the node and pipeline
+#[derive(Clone)]
+pub struct EvidenceGrpcService {
+    node: RealTimeEncryptionNode,
+    sender: FrameSender,
+}
+
+impl EvidenceGrpcService {
+    pub fn new(node: RealTimeEncryptionNode, sender: FrameSender) -> Self {
+        Self { node, sender }
+    }
+
+    pub fn into_router(
+        self,
+        tls: Option<ServerTlsConfig>,
+    ) -> Result<tonic::transport::server::Router, tonic::transport::Error> {
+        // Tag each call's span with the caller's x-request-id, or a fresh one
+        let mut server = Server::builder().trace_fn(|request| {
+            let request_id = trace::request_id(
+                request
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|v| v.to_str().ok()),
+            );
+            tracing::info_span!("grpc", path = %request.uri().path(), request_id = %request_id)
+        });
+        if let Some(tls) = tls {
+            server = server.tls_config(tls)?;
+        }
+
+        Ok(server
+            .add_service(IngestServiceServer::new(self.clone()))
+            .add_service(VerificationServiceServer::new(self.clone()))
+            .add_service(ReportServiceServer::new(self.clone()))
+            .add_service(ExportServiceServer::new(self)))
+    }
+
+    async fn submit(
+        &self,
+        request: proto::SubmitFrameRequest,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<proto::SubmitFrameResponse, Status> {
+        let frame = frame_from_proto(request)?;
+
+        match self
+            .node
+            .submit_frame(&self.sender, frame, certificate, SUBMIT_SEAL_WAIT)
+            .await
+            .map_err(status_from_error)?
+        {
+            SubmitOutcome::Sealed(frame) => Ok(proto::SubmitFrameResponse {
+                frame_id: storage::frame_key(&frame.device_id, frame.sequence, frame.timestamp),
+                device_id: frame.device_id.clone(),
+                sequence: frame.sequence,
+                sealed: true,
+                hash: frame.hash.clone(),
+                previous_hash: frame.previous_hash.clone(),
+            }),
+            SubmitOutcome::Pending {
+                device_id,
+                sequence,
+            } => Ok(proto::SubmitFrameResponse {
+                device_id,
+                sequence,
+                sealed: false,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl IngestService for EvidenceGrpcService {
+    type StreamFramesStream = ResponseStream<proto::SubmitFrameResponse>;
+
+    async fn submit_frame(
+        &self,
+        request: Request<proto::SubmitFrameRequest>,
+    ) -> Result<Response<proto::SubmitFrameResponse>, Status> {
+        let certificate = client_certificate(&request);
+        self.submit(request.into_inner(), certificate.as_ref())
+            .await
+            .map(Response::new)
+    }
+
+    async fn stream_frames(
+        &self,
+        request: Request<Streaming<proto::SubmitFrameRequest>>,
+    ) -> Result<Response<Self::StreamFramesStream>, Status> {
+        let service = self.clone();
+        let certificate = client_certificate(&request);
+        let responses = request.into_inner().then(move |frame| {
+            let service = service.clone();
+            let certificate = certificate.clone();
+            async move { service.submit(frame?, certificate.as_ref()).await }
+        });
+
+        Ok(Response::new(Box::pin(responses)))
+    }
+}
+
+#[tonic::async_trait]
+impl VerificationService for EvidenceGrpcService {
+    async fn verify_evidence(
+        &self,
+        request: Request<proto::VerifyEvidenceRequest>,
+    ) -> Result<Response<proto::VerificationResponse>, Status> {
+        let frame_ids = request.into_inner().frame_ids;
+        let result = self
+            .node
+            .verify_evidence(&frame_ids)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(result.into()))
+    }
+
+    async fn verify_session(
+        &self,
+        request: Request<proto::VerifySessionRequest>,
+    ) -> Result<Response<proto::VerificationResponse>, Status> {
+        let session_id = request.into_inner().session_id;
+        let result = self
+            .node
+            .verify_session(&session_id)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(result.into()))
+    }
+}
+
+#[tonic::async_trait]
+impl ReportService for EvidenceGrpcService {
+    async fn get_court_report(
+        &self,
+        request: Request<proto::CourtReportRequest>,
+    ) -> Result<Response<proto::CourtReport>, Status> {
+        let evidence_id = request.into_inner().evidence_id;
+        let report = self
+            .node
+            .generate_court_report(&evidence_id)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(report.into()))
+    }
+}
+
+#[tonic::async_trait]
+impl ExportService for EvidenceGrpcService {
+    type ExportSessionStream = ResponseStream<proto::ExportChunk>;
+
+    async fn export_session(
+        &self,
+        request: Request<proto::ExportSessionRequest>,
+    ) -> Result<Response<Self::ExportSessionStream>, Status> {
+        let session_id = request.into_inner().session_id;
+        let (manifest, frames) = self
+            .node
+            .session_frames(&session_id)
+            .await
+            .map_err(status_from_error)?;
+
+        let manifest_json =
+            serde_json::to_string(&manifest).map_err(|e| Status::internal(e.to_string()))?;
+
+        let chunks = std::iter::once(proto::export_chunk::Item::ManifestJson(manifest_json))
+            .chain(
+                frames
+                    .into_iter()
+                    .map(|frame| proto::export_chunk::Item::Frame(frame.into())),
+            )
+            .map(|item| Ok(proto::ExportChunk { item: Some(item) }));
+
+        Ok(Response::new(Box::pin(futures::stream::iter(chunks))))
+    }
+}
+
+// Leaf certificate presented during the mTLS handshake, if any
+fn client_certificate<T>(request: &Request<T>) -> Option<ClientCertificate> {
+    request.peer_certs().and_then(|certs| {
+        certs
+            .first()
+            .map(|c| ClientCertificate::from_der(c.get_ref()))
+    })
+}
+
+fn frame_from_proto(request: proto::SubmitFrameRequest) -> Result<crate::VideoFrame, Status> {
+    wire::check_version("SubmitFrameRequest", request.format_version).map_err(status_from_error)?;
+    let metadata = request
+        .metadata
+        .ok_or_else(|| Status::invalid_argument("metadata is required"))?;
+
+    let timestamp = if request.timestamp == 0 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .as_secs()
+    } else {
+        request.timestamp
+    };
+
+    Ok(crate::VideoFrame {
+        timestamp,
+        sequence: request.sequence,
+        data: request.data,
+        metadata: metadata.try_into().map_err(status_from_error)?,
+        signature: Some(request.signature).filter(|s| !s.is_empty()),
+    })
+}
+
+// Follows the HTTP mapping in `ImmutableEncryptionError::http_status`, with
+// rejected frames reported as bad arguments
+fn status_from_error(error: ImmutableEncryptionError) -> Status {
+    let message = error.to_string();
+    if let ImmutableEncryptionError::Video(_) = error {
+        return Status::invalid_argument(message);
+    }
+    match error.http_status() {
+        400 => Status::invalid_argument(message),
+        403 => Status::permission_denied(message),
+        404 => Status::not_found(message),
+        409 | 422 => Status::failed_precondition(message),
+        429 => Status::resource_exhausted(message),
+        501 => Status::unimplemented(message),
+        503 => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+impl From<crate::VerificationResult> for proto::VerificationResponse {
+    fn from(result: crate::VerificationResult) -> Self {
+        Self {
+            is_valid: result.is_valid,
+            frame_count: result.frame_count,
+            blockchain_confirmations: result.blockchain_confirmations,
+            tamper_evidence: result.tamper_evidence,
+            court_report: Some(result.court_report.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_from_proto_requires_metadata() {
+        let missing = proto::SubmitFrameRequest {
+            timestamp: 1,
+            sequence: 0,
+            data: vec![1].into(),
+            metadata: None,
+            signature: Vec::new(),
+            format_version: 0,
+        };
+        assert_eq!(
+            frame_from_proto(missing).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+
+        let frame = frame_from_proto(proto::SubmitFrameRequest {
+            timestamp: 0,
+            sequence: 7,
+            data: vec![1, 2, 3].into(),
+            metadata: Some(proto::FrameMetadata {
+                device_id: "cam_1".to_string(),
+                location: Some(proto::Location {
+                    latitude: 1.5,
+                    longitude: 2.5,
+                }),
+                width: 640,
+                height: 480,
+                fps: 15,
+                codec: "MJPEG".to_string(),
+                telemetry: None,
+            }),
+            signature: vec![9; 64],
+            format_version: wire::FORMAT_VERSION,
+        })
+        .unwrap();
+
+        assert!(frame.timestamp > 0);
+        assert_eq!(frame.sequence, 7);
+        assert_eq!(frame.signature, Some(vec![9; 64]));
+        assert_eq!(frame.metadata.location, Some((1.5, 2.5)));
+        assert_eq!(frame.metadata.resolution, (640, 480));
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::blockchain::MultiChainAnchor;
+use crate::config::Config;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::storage::IPFSStorage;
+
+// Dependency kinds probed by `/health`
+pub const ROCKSDB: &str = "rocksdb";
+pub const KEYSTORE: &str = "keystore";
+pub const IPFS: &str = "ipfs";
+pub const BLOCKCHAIN: &str = "blockchain";
+pub const TIME: &str = "time";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+    pub probe_timeout_ms: u64,
+    pub critical: Vec<String>, // dependency kinds whose failure makes the node unhealthy
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            probe_timeout_ms: 2000,
+            critical: vec![ROCKSDB.to_string(), KEYSTORE.to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    Healthy,
+    Degraded,  // a non-critical dependency is failing
+    Unhealthy, // a critical dependency is failing; served with 503
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyHealth {
+    pub name: String,
+    pub kind: String,
+    pub critical: bool,
+    pub healthy: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthState,
+    pub checked_at: u64,
+    pub dependencies: Vec<DependencyHealth>,
+}
+
+impl HealthReport {
+    pub fn new(dependencies: Vec<DependencyHealth>) -> Self {
+        let failing = |critical: bool| {
+            dependencies
+                .iter()
+                .any(|d| !d.healthy && d.critical == critical)
+        };
+        let status = if failing(true) {
+            HealthState::Unhealthy
+        } else if failing(false) {
+            HealthState::Degraded
+        } else {
+            HealthState::Healthy
+        };
+
+        Self {
+            status,
+            checked_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            dependencies,
+        }
+    }
+}
+
+// Runs one probe under the configured timeout; a probe that hangs counts as failed
+pub async fn probe<F>(config: &HealthConfig, name: &str, kind: &str, check: F) -> DependencyHealth
+where
+    F: Future<Output = Result<()>>,
+{
+    let started = Instant::now();
+    let timeout = Duration::from_millis(config.probe_timeout_ms);
+    let error = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("no response within {}ms", config.probe_timeout_ms)),
+    };
+
+    DependencyHealth {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        critical: config.critical.iter().any(|c| c == kind),
+        healthy: error.is_none(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+// Probes the blockchain RPCs, IPFS API and time sources a config points at
+// without opening its database or keystore, so a config can be checked before
+// it's used
+pub async fn probe_endpoints(config: &Config) -> Result<Vec<DependencyHealth>> {
+    let anchor = MultiChainAnchor::new(config.get_blockchain_config()).await?;
+    let chains = anchor.probe_targets();
+    let chain_probes = chains
+        .iter()
+        .map(|target| probe(&config.health, target, BLOCKCHAIN, anchor.probe(target)));
+    let mut dependencies = futures::future::join_all(chain_probes).await;
+
+    if config.storage.ipfs.enabled {
+        let ipfs = IPFSStorage::new(config.get_storage_config());
+        dependencies.push(probe(&config.health, IPFS, IPFS, ipfs.probe()).await);
+    }
+
+    // A clock too far off makes every frame timestamp suspect
+    if config.time_sync.enabled {
+        let max_offset_ms = config.time_sync.max_offset_ms;
+        for source in &config.time_sync.sources {
+            let timeout = Duration::from_millis(config.time_sync.timeout_ms);
+            let check = async {
+                let reading = source.query(timeout).await?;
+                if reading.offset_ms.abs() > max_offset_ms {
+                    return Err(ImmutableEncryptionError::verification(&format!(
+                        "clock is {:.1} ms off, more than {:.1} ms",
+                        reading.offset_ms, max_offset_ms
+                    )));
+                }
+                Ok(())
+            };
+            dependencies.push(probe(&config.health, &source.name(), TIME, check).await);
+        }
+    }
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_critical_failures_make_the_node_unhealthy() {
+        let config = HealthConfig {
+            probe_timeout_ms: 50,
+            ..HealthConfig::default()
+        };
+
+        let rocksdb = probe(&config, ROCKSDB, ROCKSDB, async { Ok(()) }).await;
+        let ipfs = probe(&config, IPFS, IPFS, async {
+            Err(ImmutableEncryptionError::network("refused"))
+        })
+        .await;
+        assert!(rocksdb.healthy && rocksdb.critical);
+        assert!(!ipfs.critical);
+        assert_eq!(ipfs.error.as_deref(), Some("Network error: refused"));
+
+        let report = HealthReport::new(vec![rocksdb, ipfs]);
+        assert_eq!(report.status, HealthState::Degraded);
+
+        let keystore = probe(&config, KEYSTORE, KEYSTORE, async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+        assert!(!keystore.healthy);
+
+        let report = HealthReport::new(vec![keystore]);
+        assert_eq!(report.status, HealthState::Unhealthy);
+    }
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::crypto::KeyProvider;
+use crate::error::{ImmutableEncryptionError, Result};
+
+// A PKCS#11 token holding the key that wraps the primary key and the Ed25519
+// key that signs frame envelopes, under `[encryption.hsm]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HsmConfig {
+    pub module: String, // the vendor's PKCS#11 library, e.g. /usr/lib/softhsm/libsofthsm2.so
+    pub token_label: String,
+    pub pin: String, // normally a secret:// URI
+    #[serde(default = "default_wrapping_key_label")]
+    pub wrapping_key_label: String, // AES, allowed to encrypt and decrypt
+    #[serde(default = "default_signing_key_label")]
+    pub signing_key_label: String, // Ed25519; the public half shares the label
+}
+
+fn default_wrapping_key_label() -> String {
+    "immutable-primary-wrap".to_string()
+}
+
+fn default_signing_key_label() -> String {
+    "immutable-envelope".to_string()
+}
+
+#[cfg(feature = "pkcs11")]
+pub fn open_key_provider(config: &HsmConfig) -> Result<Arc<dyn KeyProvider>> {
+    Ok(Arc::new(Pkcs11KeyProvider::open(config)?))
+}
+
+#[cfg(not(feature = "pkcs11"))]
+pub fn open_key_provider(_config: &HsmConfig) -> Result<Arc<dyn KeyProvider>> {
+    Err(ImmutableEncryptionError::config(
+        "HSM keys need the `pkcs11` feature",
+    ))
+}
+
+// Keys never leave the token: wrapping uses AES key wrap with padding
+// (RFC 5649) and signing CKM_EDDSA, over one logged-in session
+#[cfg(feature = "pkcs11")]
+pub struct Pkcs11KeyProvider {
+    session: std::sync::Mutex<cryptoki::session::Session>,
+    wrapping_key: cryptoki::object::ObjectHandle,
+    signing_key: cryptoki::object::ObjectHandle,
+    public_key: Vec<u8>,
+}
+
+#[cfg(feature = "pkcs11")]
+impl Pkcs11KeyProvider {
+    pub fn open(config: &HsmConfig) -> Result<Self> {
+        use cryptoki::context::{CInitializeArgs, Pkcs11};
+        use cryptoki::object::{Attribute, AttributeType, ObjectClass};
+        use cryptoki::session::UserType;
+        use cryptoki::types::AuthPin;
+
+        let pkcs11 = Pkcs11::new(&config.module)?;
+        pkcs11.initialize(CInitializeArgs::OsThreads)?;
+        let slot = pkcs11
+            .get_slots_with_token()?
+            .into_iter()
+            .find(|slot| {
+                pkcs11
+                    .get_token_info(*slot)
+                    .map_or(false, |info| info.label() == config.token_label)
+            })
+            .ok_or_else(|| {
+                ImmutableEncryptionError::Config(format!(
+                    "No PKCS#11 token labelled {}",
+                    config.token_label
+                ))
+            })?;
+        let session = pkcs11.open_ro_session(slot)?;
+        session.login(UserType::User, Some(&AuthPin::new(config.pin.clone())))?;
+
+        let find = |class, label: &str| {
+            session
+                .find_objects(&[
+                    Attribute::Class(class),
+                    Attribute::Label(label.as_bytes().to_vec()),
+                ])?
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    ImmutableEncryptionError::Config(format!(
+                        "No key labelled {} on token {}",
+                        label, config.token_label
+                    ))
+                })
+        };
+        let wrapping_key = find(ObjectClass::SECRET_KEY, &config.wrapping_key_label)?;
+        let signing_key = find(ObjectClass::PRIVATE_KEY, &config.signing_key_label)?;
+        let public_handle = find(ObjectClass::PUBLIC_KEY, &config.signing_key_label)?;
+
+        // CKA_EC_POINT of an Ed25519 key is usually the DER OCTET STRING
+        // around the 32-byte key
+        let public_key = match session
+            .get_attributes(public_handle, &[AttributeType::EcPoint])?
+            .pop()
+        {
+            Some(Attribute::EcPoint(point)) => match point.as_slice() {
+                [0x04, 0x20, key @ ..] if key.len() == 32 => key.to_vec(),
+                key if key.len() == 32 => key.to_vec(),
+                _ => {
+                    return Err(ImmutableEncryptionError::crypto(
+                        "The HSM signing key is not Ed25519",
+                    ))
+                }
+            },
+            _ => {
+                return Err(ImmutableEncryptionError::crypto(
+                    "The HSM signing key has no public point",
+                ))
+            }
+        };
+
+        Ok(Self {
+            session: std::sync::Mutex::new(session),
+            wrapping_key,
+            signing_key,
+            public_key,
+        })
+    }
+
+    fn session(&self) -> Result<std::sync::MutexGuard<'_, cryptoki::session::Session>> {
+        self.session
+            .lock()
+            .map_err(|_| ImmutableEncryptionError::crypto("HSM session lock poisoned"))
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+impl std::fmt::Debug for Pkcs11KeyProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pkcs11KeyProvider")
+            .field("public_key", &hex::encode(&self.public_key))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+impl KeyProvider for Pkcs11KeyProvider {
+    fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let mechanism = cryptoki::mechanism::Mechanism::AesKeyWrapPad;
+        Ok(self
+            .session()?
+            .encrypt(&mechanism, self.wrapping_key, key)?)
+    }
+
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        let mechanism = cryptoki::mechanism::Mechanism::AesKeyWrapPad;
+        Ok(self
+            .session()?
+            .decrypt(&mechanism, self.wrapping_key, wrapped)?)
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mechanism = cryptoki::mechanism::Mechanism::Eddsa;
+        Ok(self.session()?.sign(&mechanism, self.signing_key, data)?)
+    }
+
+    fn public_key(&self) -> Result<Vec<u8>> {
+        Ok(self.public_key.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hsm_config_defaults_key_labels() -> Result<()> {
+        let config: HsmConfig = toml::from_str(
+            r#"
+            module = "/usr/lib/softhsm/libsofthsm2.so"
+            token_label = "evidence"
+            pin = "secret://vault/hsm#pin"
+            "#,
+        )?;
+        assert_eq!(config.wrapping_key_label, "immutable-primary-wrap");
+        assert_eq!(config.signing_key_label, "immutable-envelope");
+
+        #[cfg(not(feature = "pkcs11"))]
+        assert!(open_key_provider(&config).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use immutable_encryption_core::hash;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::Mutex;
+
+use crate::error::ImmutableEncryptionError;
+use crate::{EncryptedFrame, FrameMetadata, VideoFrame};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestConfig {
+    pub max_fps: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_device_id_len: usize,
+    pub max_frame_bytes: usize,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            max_fps: 240,
+            max_width: 7680, // 8K UHD
+            max_height: 4320,
+            max_device_id_len: 128,
+            max_frame_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+// The metadata a frame was hashed with, including merged sensor telemetry,
+// which the sealed frame doesn't carry. Persisted under
+// `frame_metadata:{device}:{sequence}`; the frame's chain hash covers
+// `frame_hash`, which covers the metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataRecord {
+    pub device_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub frame_hash: String, // over payload and metadata, before chaining
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_hash: Option<String>, // of the unmarked capture, when watermarked
+    pub metadata: FrameMetadata,
+}
+
+impl MetadataRecord {
+    pub fn key(device_id: &str, sequence: u64) -> String {
+        format!("{}{:020}", Self::prefix(device_id), sequence)
+    }
+
+    pub fn prefix(device_id: &str) -> String {
+        format!("frame_metadata:{}:", device_id)
+    }
+
+    // Whether this record is part of `frame`'s chain hash; cheap, as it needs
+    // no decryption
+    pub fn links_to(&self, frame: &EncryptedFrame) -> bool {
+        self.device_id == frame.device_id
+            && self.sequence == frame.sequence
+            && hash::chain_link(&self.frame_hash, &frame.previous_hash, frame.sequence)
+                == frame.hash
+    }
+
+    // Whether `frame` was sealed with this metadata, given its decrypted payload
+    pub fn matches(
+        &self,
+        frame: &EncryptedFrame,
+        payload: bytes::Bytes,
+    ) -> Result<bool, ImmutableEncryptionError> {
+        let captured = VideoFrame {
+            timestamp: frame.timestamp,
+            sequence: frame.sequence,
+            data: payload,
+            metadata: self.metadata.clone(),
+            signature: None,
+        };
+        Ok(self.links_to(frame) && hash::frame_hash(&captured)? == self.frame_hash)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataValidator {
+    config: IngestConfig,
+}
+
+impl MetadataValidator {
+    pub fn new(config: IngestConfig) -> Self {
+        Self { config }
+    }
+
+    // Rejects metadata that must never be sealed as evidence and normalizes
+    // harmless variations (whitespace, codec aliases, heading wrap-around) so
+    // equivalent frames hash identically.
+    pub fn validate(&self, frame: &mut VideoFrame) -> Result<(), ImmutableEncryptionError> {
+        let metadata = &mut frame.metadata;
+
+        metadata.device_id = metadata.device_id.trim().to_string();
+        self.validate_device_id(&metadata.device_id)?;
+
+        let (width, height) = metadata.resolution;
+        if width == 0 || height == 0 {
+            return Err(invalid("resolution", "width and height must be non-zero"));
+        }
+        if width > self.config.max_width || height > self.config.max_height {
+            return Err(invalid(
+                "resolution",
+                &format!(
+                    "{}x{} exceeds maximum {}x{}",
+                    width, height, self.config.max_width, self.config.max_height
+                ),
+            ));
+        }
+
+        if metadata.fps == 0 || metadata.fps > self.config.max_fps {
+            return Err(invalid(
+                "fps",
+                &format!("must be between 1 and {}", self.config.max_fps),
+            ));
+        }
+
+        if let Some((lat, lon)) = metadata.location {
+            if !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+                return Err(invalid("location.latitude", "must be within [-90, 90]"));
+            }
+            if !lon.is_finite() || !(-180.0..=180.0).contains(&lon) {
+                return Err(invalid("location.longitude", "must be within [-180, 180]"));
+            }
+            // 180 and -180 are the same meridian
+            let lon = if lon == 180.0 { -180.0 } else { lon };
+            metadata.location = Some((lat, lon));
+        }
+
+        metadata.codec = normalize_codec(&metadata.codec)
+            .ok_or_else(|| invalid("codec", "must not be empty"))?;
+
+        if let Some(telemetry) = metadata.telemetry.as_mut() {
+            if let Some(heading) = telemetry.heading_deg {
+                if !heading.is_finite() {
+                    return Err(invalid("telemetry.heading_deg", "must be finite"));
+                }
+                telemetry.heading_deg = Some(heading.rem_euclid(360.0));
+            }
+            if telemetry
+                .speed_mps
+                .map_or(false, |s| !s.is_finite() || s < 0.0)
+            {
+                return Err(invalid(
+                    "telemetry.speed_mps",
+                    "must be a non-negative number",
+                ));
+            }
+        }
+
+        if frame.timestamp == 0 {
+            return Err(invalid("timestamp", "must be non-zero"));
+        }
+
+        if frame.data.is_empty() {
+            return Err(invalid("data", "frame payload must not be empty"));
+        }
+        if frame.data.len() > self.config.max_frame_bytes {
+            return Err(invalid(
+                "data",
+                &format!(
+                    "frame payload exceeds {} bytes",
+                    self.config.max_frame_bytes
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_device_id(&self, device_id: &str) -> Result<(), ImmutableEncryptionError> {
+        if device_id.is_empty() {
+            return Err(invalid("device_id", "must not be empty"));
+        }
+        if device_id.len() > self.config.max_device_id_len {
+            return Err(invalid(
+                "device_id",
+                &format!("must be at most {} bytes", self.config.max_device_id_len),
+            ));
+        }
+        // Device IDs are embedded in storage keys, which use ':' as a separator
+        if !device_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(invalid(
+                "device_id",
+                "may only contain ASCII letters, digits, '-', '_' and '.'",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Hands out per-device sequence numbers to network clients that don't track
+// their own, while never going backwards past one a client did supply.
+#[derive(Debug, Default)]
+pub struct SequenceAllocator {
+    last: Mutex<HashMap<String, u64>>,
+}
+
+impl SequenceAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns `requested` if non-zero, otherwise the next sequence for the device
+    pub async fn assign(&self, device_id: &str, requested: u64) -> u64 {
+        let mut last = self.last.lock().await;
+        let entry = last.entry(device_id.to_string()).or_insert(0);
+        let sequence = if requested == 0 {
+            *entry + 1
+        } else {
+            requested
+        };
+        *entry = (*entry).max(sequence);
+        sequence
+    }
+
+    // Continues numbering after the sequences a previous run reached
+    pub async fn resume(&self, last_sequences: &BTreeMap<String, u64>) {
+        let mut last = self.last.lock().await;
+        for (device_id, sequence) in last_sequences {
+            let entry = last.entry(device_id.clone()).or_insert(0);
+            *entry = (*entry).max(*sequence);
+        }
+    }
+}
+
+fn normalize_codec(codec: &str) -> Option<String> {
+    let trimmed = codec.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let canonical = match trimmed
+        .to_ascii_lowercase()
+        .replace(['.', '-', '_'], "")
+        .as_str()
+    {
+        "h264" | "avc" | "avc1" | "avc3" => "H.264",
+        "h265" | "hevc" | "hvc1" | "hev1" => "H.265",
+        "mjpeg" | "mjpg" => "MJPEG",
+        "jpeg" | "jpg" => "JPEG",
+        "rgb24" | "raw" => "RGB24",
+        "gray8" | "y8" => "GRAY8",
+        "av1" => "AV1",
+        "vp9" => "VP9",
+        _ => return Some(trimmed.to_string()),
+    };
+
+    Some(canonical.to_string())
+}
+
+fn invalid(field: &str, reason: &str) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::video(&format!("invalid metadata.{}: {}", field, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FrameMetadata;
+
+    fn frame() -> VideoFrame {
+        VideoFrame {
+            timestamp: 1640995200,
+            sequence: 1,
+            data: vec![1, 2, 3].into(),
+            metadata: FrameMetadata {
+                device_id: "  drone_001 ".to_string(),
+                location: Some((40.7128, 180.0)),
+                resolution: (1920, 1080),
+                fps: 30,
+                codec: "h264".to_string(),
+                telemetry: None,
+            },
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_validation_normalizes_metadata() -> Result<(), ImmutableEncryptionError> {
+        let validator = MetadataValidator::new(IngestConfig::default());
+        let mut frame = frame();
+
+        validator.validate(&mut frame)?;
+
+        assert_eq!(frame.metadata.device_id, "drone_001");
+        assert_eq!(frame.metadata.codec, "H.264");
+        assert_eq!(frame.metadata.location, Some((40.7128, -180.0)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validation_rejects_nonsense() {
+        let validator = MetadataValidator::new(IngestConfig::default());
+
+        let mut zero_fps = frame();
+        zero_fps.metadata.fps = 0;
+        assert!(matches!(
+            validator.validate(&mut zero_fps),
+            Err(ImmutableEncryptionError::Video(_))
+        ));
+
+        let mut bad_latitude = frame();
+        bad_latitude.metadata.location = Some((91.0, 0.0));
+        assert!(validator.validate(&mut bad_latitude).is_err());
+
+        let mut empty_device = frame();
+        empty_device.metadata.device_id = "   ".to_string();
+        assert!(validator.validate(&mut empty_device).is_err());
+
+        let mut zero_resolution = frame();
+        zero_resolution.metadata.resolution = (0, 1080);
+        assert!(validator.validate(&mut zero_resolution).is_err());
+    }
+}
@@ -0,0 +1,666 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use ring::{hkdf, pbkdf2};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::crypto::{self, RetiredKey};
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Environment variable holding the passphrase the keystore is sealed with
+pub const PASSPHRASE_ENV: &str = "IMMUTABLE_KEYSTORE_PASSPHRASE";
+
+// Config values sealed under the keystore: `enc:v1:<base64 nonce || ciphertext>`
+pub const SEALED_VALUE_PREFIX: &str = "enc:v1:";
+const SEALED_VALUE_SALT: &[u8] = b"immutable-encryption config values";
+
+const KEYSTORE_VERSION: u32 = 1;
+const PBKDF2_KDF: &str = "pbkdf2-sha256"; // still opened; saving rewrites them with Argon2id
+const ARGON2_KDF: &str = "argon2id";
+
+// RFC 9106's second recommended option: 3 passes over 64 MiB in 4 lanes
+const ARGON2_PASSES: u32 = 3;
+const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+const ARGON2_LANES: u32 = 4;
+
+// Installed by interactive front ends to ask for the passphrase when neither
+// the config nor the environment holds it; the answer is kept for the process
+static PASSPHRASE_PROMPT: OnceLock<fn() -> std::io::Result<String>> = OnceLock::new();
+static PROMPTED_PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+// Every key a node holds. Only ever written to disk sealed, and deliberately
+// not Debug so it can't end up in a log line.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub primary_key: Vec<u8>,
+    pub signing_key: Vec<u8>, // Ed25519 PKCS#8 document
+    pub kem_public_key: Vec<u8>,
+    pub kem_secret_key: Vec<u8>, // Kyber1024
+    pub created_at: u64,
+    pub rotated_at: Option<u64>,
+    #[serde(default)]
+    pub retired_keys: Vec<RetiredKey>, // earlier primary keys, kept for older evidence
+    #[serde(default)]
+    pub wrapped: bool, // primary_key is wrapped by the [encryption.hsm] or [encryption.kms] key
+    #[serde(default)]
+    pub sealed_to_tpm: bool, // and then sealed to this machine's TPM
+    #[serde(default)]
+    pub key_rotation_interval: Option<u64>, // seconds; recorded by keygen and rotate
+    #[serde(default)]
+    pub default_tenant_derived: bool, // set by keygen and rotate; see tenant_crypto_config
+}
+
+// On-disk form: the keystore sealed with AES-256-GCM under a passphrase-derived key
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedKeystore {
+    version: u32,
+    kdf: String,
+    iterations: u32, // PBKDF2 iterations, or Argon2 passes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memory_kib: Option<u32>, // Argon2 only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lanes: Option<u32>, // Argon2 only
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicKeys {
+    pub signing_algorithm: String,
+    pub signing_public_key: String, // hex
+    pub kem_algorithm: String,
+    pub kem_public_key: String, // hex
+    pub created_at: u64,
+    pub rotated_at: Option<u64>,
+}
+
+impl Keystore {
+    pub fn generate() -> Result<Self> {
+        let rng = SystemRandom::new();
+        let signing_key = Ed25519KeyPair::generate_pkcs8(&rng)
+            .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate the signing key"))?;
+        let (kem_public_key, kem_secret_key) = crypto::post_quantum_keypair();
+
+        Ok(Self {
+            primary_key: crypto::generate_key()?,
+            signing_key: signing_key.as_ref().to_vec(),
+            kem_public_key,
+            kem_secret_key,
+            created_at: now_secs(),
+            rotated_at: None,
+            retired_keys: Vec::new(),
+            wrapped: false,
+            sealed_to_tpm: false,
+            key_rotation_interval: None,
+            default_tenant_derived: true,
+        })
+    }
+
+    // Frame keys are derived per key rotation interval, so frames sealed under
+    // one interval don't open under another. A keystore records the interval
+    // it is used with and refuses any other.
+    pub fn check_key_rotation_interval(&self, interval: u64) -> Result<()> {
+        match self.key_rotation_interval {
+            Some(recorded) if recorded != interval => {
+                Err(ImmutableEncryptionError::Config(format!(
+                    "Frames were sealed with a key rotation interval of {}s, not {}s; \
+                     encryption.key_rotation_interval_seconds is fixed once evidence is sealed",
+                    recorded, interval
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_key_rotation_interval(&mut self, interval: u64) -> Result<()> {
+        self.check_key_rotation_interval(interval)?;
+        self.key_rotation_interval = Some(interval);
+        Ok(())
+    }
+
+    // Replaces the primary key; the old one is retired rather than dropped.
+    // The new key is unprotected until `wrap_primary_key` and
+    // `seal_primary_key` are called again. Nothing is sealed under it yet, so
+    // from here on the default tenant's key is derived from it too.
+    pub fn rotate(&mut self) -> Result<()> {
+        let now = now_secs();
+        let retired = std::mem::replace(&mut self.primary_key, crypto::generate_key()?);
+        self.retired_keys.push(RetiredKey {
+            key: retired,
+            retired_at: now,
+            wrapped: self.wrapped,
+            sealed_to_tpm: self.sealed_to_tpm,
+            default_tenant_derived: self.default_tenant_derived,
+        });
+        self.rotated_at = Some(now);
+        self.default_tenant_derived = true;
+        self.wrapped = false;
+        self.sealed_to_tpm = false;
+        Ok(())
+    }
+
+    // Keeps only the HSM- or KMS-wrapped primary key, so the keystore alone
+    // no longer decrypts anything
+    pub fn wrap_primary_key(&mut self, provider: &dyn crypto::KeyProvider) -> Result<()> {
+        if self.wrapped || self.sealed_to_tpm {
+            return Err(ImmutableEncryptionError::crypto(
+                "The primary key is already wrapped or sealed",
+            ));
+        }
+        self.primary_key = provider.wrap_key(&self.primary_key)?;
+        self.wrapped = true;
+        Ok(())
+    }
+
+    // Binds the primary key to this machine: a copy of the keystore taken
+    // elsewhere, or restored from shares, needs the same TPM to open
+    pub fn seal_primary_key(&mut self, hardware: &dyn crypto::HardwareKeys) -> Result<()> {
+        if self.sealed_to_tpm {
+            return Err(ImmutableEncryptionError::crypto(
+                "The primary key is already sealed to the TPM",
+            ));
+        }
+        self.primary_key = hardware.seal(&self.primary_key)?;
+        self.sealed_to_tpm = true;
+        Ok(())
+    }
+
+    pub fn public_keys(&self) -> Result<PublicKeys> {
+        let signing = Ed25519KeyPair::from_pkcs8(&self.signing_key).map_err(|e| {
+            ImmutableEncryptionError::Crypto(format!("Keystore signing key is unusable: {}", e))
+        })?;
+
+        Ok(PublicKeys {
+            signing_algorithm: "ed25519".to_string(),
+            signing_public_key: hex::encode(signing.public_key().as_ref()),
+            kem_algorithm: "kyber1024".to_string(),
+            kem_public_key: hex::encode(&self.kem_public_key),
+            created_at: self.created_at,
+            rotated_at: self.rotated_at,
+        })
+    }
+
+    pub fn load(path: &str, passphrase: &str) -> Result<Self> {
+        let sealed: SealedKeystore =
+            serde_json::from_slice(&std::fs::read(path).map_err(|e| {
+                ImmutableEncryptionError::Crypto(format!("Failed to read keystore {}: {}", path, e))
+            })?)?;
+        let salt = hex::decode(&sealed.salt)?;
+        let key = match (sealed.version, sealed.kdf.as_str()) {
+            (KEYSTORE_VERSION, PBKDF2_KDF) => derive_key(passphrase, &salt, sealed.iterations)?,
+            (KEYSTORE_VERSION, ARGON2_KDF) => derive_argon2_key(
+                passphrase,
+                &salt,
+                sealed.iterations,
+                sealed.memory_kib.unwrap_or(ARGON2_MEMORY_KIB),
+                sealed.lanes.unwrap_or(ARGON2_LANES),
+            )?,
+            _ => {
+                return Err(ImmutableEncryptionError::Crypto(format!(
+                    "Unsupported keystore {} (version {}, kdf {})",
+                    path, sealed.version, sealed.kdf
+                )))
+            }
+        };
+        let plaintext = crypto::open(
+            &key,
+            &hex::decode(&sealed.ciphertext)?,
+            &hex::decode(&sealed.nonce)?,
+        )
+        .map_err(|_| {
+            ImmutableEncryptionError::Crypto(format!(
+                "Failed to unlock keystore {}: wrong passphrase?",
+                path
+            ))
+        })?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    // Writes through a temporary file so a crash never leaves a torn keystore
+    pub fn save(&self, path: &str, passphrase: &str) -> Result<()> {
+        if passphrase.is_empty() {
+            return Err(ImmutableEncryptionError::crypto(
+                "Refusing to seal the keystore with an empty passphrase",
+            ));
+        }
+
+        let rng = SystemRandom::new();
+        let mut salt = vec![0u8; 16];
+        rng.fill(&mut salt)
+            .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate keystore salt"))?;
+        let key = derive_argon2_key(
+            passphrase,
+            &salt,
+            ARGON2_PASSES,
+            ARGON2_MEMORY_KIB,
+            ARGON2_LANES,
+        )?;
+        let (ciphertext, nonce) = crypto::seal(&key, &serde_json::to_vec(self)?, &rng)?;
+
+        let sealed = SealedKeystore {
+            version: KEYSTORE_VERSION,
+            kdf: ARGON2_KDF.to_string(),
+            iterations: ARGON2_PASSES,
+            memory_kib: Some(ARGON2_MEMORY_KIB),
+            lanes: Some(ARGON2_LANES),
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let staging = format!("{}.tmp", path);
+        std::fs::write(&staging, serde_json::to_vec_pretty(&sealed)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&staging, std::fs::Permissions::from_mode(0o600))?;
+        }
+        std::fs::rename(&staging, path)?;
+        Ok(())
+    }
+
+    // Seals a config value under a key derived from the primary key
+    pub fn seal_value(&self, plaintext: &str) -> Result<String> {
+        let key = value_key(&self.primary_key)?;
+        let (ciphertext, nonce) = crypto::seal(&key, plaintext.as_bytes(), &SystemRandom::new())?;
+        Ok(format!(
+            "{}{}",
+            SEALED_VALUE_PREFIX,
+            BASE64.encode([nonce, ciphertext].concat())
+        ))
+    }
+
+    // Retired keys are tried too, so rotating doesn't strand sealed values
+    pub fn open_value(&self, sealed: &str) -> Result<String> {
+        let encoded = sealed
+            .strip_prefix(SEALED_VALUE_PREFIX)
+            .ok_or_else(|| ImmutableEncryptionError::crypto("Not a sealed value"))?;
+        let data = BASE64
+            .decode(encoded)
+            .map_err(|_| ImmutableEncryptionError::crypto("Sealed value is not valid base64"))?;
+        if data.len() < 12 {
+            return Err(ImmutableEncryptionError::crypto(
+                "Sealed value is truncated",
+            ));
+        }
+        let (nonce, ciphertext) = data.split_at(12);
+
+        let keys =
+            std::iter::once(&self.primary_key).chain(self.retired_keys.iter().map(|r| &r.key));
+        for key in keys {
+            if let Ok(plaintext) = crypto::open(&value_key(key)?, ciphertext, nonce) {
+                return Ok(String::from_utf8(plaintext)?);
+            }
+        }
+        Err(ImmutableEncryptionError::crypto(
+            "Sealed value was not sealed with this keystore",
+        ))
+    }
+
+    pub fn to_shares(&self, threshold: u8, shares: u8) -> Result<Vec<String>> {
+        split_secret(&serde_json::to_vec(self)?, threshold, shares)
+    }
+
+    pub fn from_shares(shares: &[String]) -> Result<Self> {
+        serde_json::from_slice(&combine_shares(shares)?).map_err(|_| {
+            ImmutableEncryptionError::crypto(
+                "Shares do not reconstruct a keystore; are enough of them given?",
+            )
+        })
+    }
+}
+
+pub fn set_passphrase_prompt(prompt: fn() -> std::io::Result<String>) {
+    let _ = PASSPHRASE_PROMPT.set(prompt);
+}
+
+// Whether the passphrase in use was typed at the prompt, so callers sealing a
+// new keystore know to have it confirmed
+pub fn passphrase_was_prompted() -> bool {
+    PROMPTED_PASSPHRASE.get().is_some()
+}
+
+// The environment first, then the prompt if one is installed
+pub fn passphrase_from_env() -> Result<String> {
+    if let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()) {
+        return Ok(passphrase);
+    }
+    if let Some(passphrase) = PROMPTED_PASSPHRASE.get() {
+        return Ok(passphrase.clone());
+    }
+    if let Some(prompt) = PASSPHRASE_PROMPT.get() {
+        let passphrase = prompt().map_err(|e| {
+            ImmutableEncryptionError::Config(format!(
+                "Failed to read the keystore passphrase: {}",
+                e
+            ))
+        })?;
+        if !passphrase.is_empty() {
+            return Ok(PROMPTED_PASSPHRASE.get_or_init(|| passphrase).clone());
+        }
+    }
+    Err(ImmutableEncryptionError::Config(format!(
+        "Set {} to the keystore passphrase",
+        PASSPHRASE_ENV
+    )))
+}
+
+pub fn is_sealed_value(value: &str) -> bool {
+    value.starts_with(SEALED_VALUE_PREFIX)
+}
+
+fn value_key(primary_key: &[u8]) -> Result<Vec<u8>> {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, SEALED_VALUE_SALT).extract(primary_key);
+    let mut key = vec![0u8; 32];
+    prk.expand(&[], hkdf::HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut key))
+        .map_err(|_| ImmutableEncryptionError::crypto("Failed to derive the config value key"))?;
+    Ok(key)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Vec<u8>> {
+    let iterations = NonZeroU32::new(iterations)
+        .ok_or_else(|| ImmutableEncryptionError::crypto("Keystore iterations must be > 0"))?;
+    let mut key = vec![0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    Ok(key)
+}
+
+fn derive_argon2_key(
+    passphrase: &str,
+    salt: &[u8],
+    passes: u32,
+    memory_kib: u32,
+    lanes: u32,
+) -> Result<Vec<u8>> {
+    let params = argon2::Params::new(memory_kib, passes, lanes, Some(32)).map_err(|e| {
+        ImmutableEncryptionError::Crypto(format!("Invalid keystore Argon2 parameters: {}", e))
+    })?;
+    let mut key = vec![0u8; 32];
+    argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| ImmutableEncryptionError::Crypto(format!("Argon2 failed: {}", e)))?;
+    Ok(key)
+}
+
+// Shamir's secret sharing over GF(256), one polynomial per secret byte. Shares
+// are `<index>-<hex>`; any `threshold` of them rebuild the secret.
+pub fn split_secret(secret: &[u8], threshold: u8, shares: u8) -> Result<Vec<String>> {
+    if threshold == 0 || threshold > shares {
+        return Err(ImmutableEncryptionError::Crypto(format!(
+            "Threshold must be between 1 and the share count ({})",
+            shares
+        )));
+    }
+
+    let degree = threshold as usize - 1;
+    let rng = SystemRandom::new();
+    let mut coefficients = vec![0u8; secret.len() * degree];
+    rng.fill(&mut coefficients)
+        .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate share coefficients"))?;
+
+    let shares = (1..=shares)
+        .map(|x| {
+            let points: Vec<u8> = secret
+                .iter()
+                .enumerate()
+                .map(|(i, &byte)| {
+                    // Horner's rule, highest coefficient first; the constant term is the secret
+                    coefficients[i * degree..][..degree]
+                        .iter()
+                        .rev()
+                        .chain(std::iter::once(&byte))
+                        .fold(0u8, |acc, &c| gf_mul(acc, x) ^ c)
+                })
+                .collect();
+            format!("{}-{}", x, hex::encode(points))
+        })
+        .collect();
+    Ok(shares)
+}
+
+pub fn combine_shares(shares: &[String]) -> Result<Vec<u8>> {
+    let mut points = Vec::with_capacity(shares.len());
+    for share in shares {
+        let (index, data) = share.trim().split_once('-').ok_or_else(|| {
+            ImmutableEncryptionError::Crypto(format!("Malformed share {:?}", share))
+        })?;
+        let x: u8 = index.parse()?;
+        if x == 0 || points.iter().any(|(other, _)| *other == x) {
+            return Err(ImmutableEncryptionError::Crypto(format!(
+                "Share index {} is invalid or repeated",
+                x
+            )));
+        }
+        points.push((x, hex::decode(data)?));
+    }
+
+    let len = points
+        .first()
+        .map(|(_, data)| data.len())
+        .ok_or_else(|| ImmutableEncryptionError::crypto("No shares given"))?;
+    if points.iter().any(|(_, data)| data.len() != len) {
+        return Err(ImmutableEncryptionError::crypto(
+            "Shares come from different secrets",
+        ));
+    }
+
+    // Lagrange interpolation at x = 0; subtraction is XOR in GF(256)
+    let mut secret = vec![0u8; len];
+    for (i, (xi, yi)) in points.iter().enumerate() {
+        let mut basis = 1u8;
+        for (j, (xj, _)) in points.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_mul(*xj, gf_inv(xj ^ xi)));
+            }
+        }
+        for (byte, y) in secret.iter_mut().zip(yi) {
+            *byte ^= gf_mul(*y, basis);
+        }
+    }
+    Ok(secret)
+}
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b; // x^8 + x^4 + x^3 + x + 1
+        }
+        b >>= 1;
+    }
+    product
+}
+
+// a^254 is a's inverse since the multiplicative group has order 255
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 != 0 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_round_trip_and_recovery_shares() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("keystore-test-{}", now_secs()));
+        let path = dir.join("primary.key").to_string_lossy().to_string();
+
+        let mut keystore = Keystore::generate()?;
+        keystore.save(&path, "correct horse")?;
+        assert!(Keystore::load(&path, "wrong horse").is_err());
+
+        let loaded = Keystore::load(&path, "correct horse")?;
+        assert_eq!(loaded.primary_key, keystore.primary_key);
+        assert!(std::fs::read_to_string(&path)?.contains(ARGON2_KDF));
+
+        // Keystores sealed with PBKDF2 before Argon2id still open
+        let salt = [1u8; 16];
+        let key = derive_key("correct horse", &salt, 1_000)?;
+        let (ciphertext, nonce) =
+            crypto::seal(&key, &serde_json::to_vec(&keystore)?, &SystemRandom::new())?;
+        let legacy = dir.join("legacy.key").to_string_lossy().to_string();
+        std::fs::write(
+            &legacy,
+            serde_json::to_vec(&SealedKeystore {
+                version: KEYSTORE_VERSION,
+                kdf: PBKDF2_KDF.to_string(),
+                iterations: 1_000,
+                memory_kib: None,
+                lanes: None,
+                salt: hex::encode(salt),
+                nonce: hex::encode(nonce),
+                ciphertext: hex::encode(ciphertext),
+            })?,
+        )?;
+        assert_eq!(
+            Keystore::load(&legacy, "correct horse")?.primary_key,
+            keystore.primary_key
+        );
+        assert_eq!(
+            loaded.public_keys()?.signing_public_key,
+            keystore.public_keys()?.signing_public_key
+        );
+
+        let sealed = keystore.seal_value("infura-project-key")?;
+        assert!(is_sealed_value(&sealed));
+        assert!(!sealed.contains("infura"));
+
+        let original = keystore.primary_key.clone();
+        keystore.rotate()?;
+        assert_ne!(keystore.primary_key, original);
+        assert_eq!(keystore.retired_keys[0].key, original);
+        assert_eq!(keystore.open_value(&sealed)?, "infura-project-key");
+        assert!(Keystore::generate()?.open_value(&sealed).is_err());
+
+        let shares = keystore.to_shares(3, 5)?;
+        let recovered =
+            Keystore::from_shares(&[shares[4].clone(), shares[0].clone(), shares[2].clone()])?;
+        assert_eq!(recovered.primary_key, keystore.primary_key);
+        assert!(Keystore::from_shares(&shares[..2]).is_err());
+        assert!(split_secret(b"secret", 4, 3).is_err());
+
+        // Keystores from before the interval was recorded take any, once
+        keystore.check_key_rotation_interval(60)?;
+        keystore.record_key_rotation_interval(3600)?;
+        keystore.save(&path, "correct horse")?;
+        let loaded = Keystore::load(&path, "correct horse")?;
+        loaded.check_key_rotation_interval(3600)?;
+        assert!(loaded.check_key_rotation_interval(60).is_err());
+
+        // Keystores from before keep the default tenant on the primary key
+        // until their next rotation
+        let mut legacy = serde_json::to_value(&loaded)?;
+        legacy
+            .as_object_mut()
+            .unwrap()
+            .remove("default_tenant_derived");
+        let mut legacy: Keystore = serde_json::from_value(legacy)?;
+        assert!(loaded.default_tenant_derived && !legacy.default_tenant_derived);
+        legacy.rotate()?;
+        assert!(legacy.default_tenant_derived);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotated_keys_still_open_and_verify_earlier_frames() -> Result<()> {
+        use crate::crypto::{Cipher, CryptoConfig, EncryptionEngine};
+        use crate::tenant::tenant_crypto_config;
+        use crate::EncryptedFrame;
+
+        let config = |keystore: &Keystore| CryptoConfig {
+            retired_keys: keystore.retired_keys.clone(),
+            default_tenant_derived: keystore.default_tenant_derived,
+            ..CryptoConfig::software(keystore.primary_key.clone(), 3600)
+        };
+        let seal = |engine: &EncryptionEngine, timestamp: u64| -> Result<EncryptedFrame> {
+            let (ciphertext, nonce) = engine.encrypt_data(b"frame", "cam_1", 7, timestamp, None)?;
+            Ok(EncryptedFrame {
+                sequence: 7,
+                device_id: "cam_1".to_string(),
+                ciphertext,
+                hash: String::new(),
+                previous_hash: String::new(),
+                nonce,
+                timestamp,
+                blockchain_anchors: Vec::new(),
+                cipher: Cipher::Aes256Gcm,
+                compressed: false,
+                attestation: None,
+                device_signature: None,
+            })
+        };
+
+        let mut keystore = Keystore::generate()?;
+        let before = EncryptionEngine::new(config(&keystore))?;
+        let tenant_before =
+            EncryptionEngine::new(tenant_crypto_config(&config(&keystore), "metro")?)?;
+        let frame = seal(&before, 1_700_000_123)?;
+        let tenant_frame = seal(&tenant_before, 1_700_000_123)?;
+        let signature = before.sign(b"manifest");
+
+        keystore.rotate()?;
+        let after = EncryptionEngine::new(config(&keystore))?;
+        let tenant_after =
+            EncryptionEngine::new(tenant_crypto_config(&config(&keystore), "metro")?)?;
+        assert_ne!(after.verification_key(), before.verification_key());
+        assert_eq!(after.decrypt_frame_data(&frame)?, b"frame");
+        assert_eq!(tenant_after.decrypt_frame_data(&tenant_frame)?, b"frame");
+        assert!(after.verify_signature(b"manifest", &signature));
+        assert!(!after.verify_signature(b"other", &signature));
+
+        // Even when the frame's clock says it was sealed after the rotation
+        let late = seal(&before, now_secs() + 60)?;
+        assert_eq!(after.decrypt_frame_data(&late)?, b"frame");
+
+        // New frames seal under the new key only
+        let sealed_after = seal(&after, 1_700_000_123)?;
+        assert_eq!(after.decrypt_frame_data(&sealed_after)?, b"frame");
+        assert!(before.decrypt_frame_data(&sealed_after).is_err());
+
+        // Without the retired keys, nothing from before opens or verifies
+        let forgetful =
+            EncryptionEngine::new(CryptoConfig::software(keystore.primary_key.clone(), 3600))?;
+        assert!(forgetful.decrypt_frame_data(&frame).is_err());
+        assert!(!forgetful.verify_signature(b"manifest", &signature));
+
+        Ok(())
+    }
+}
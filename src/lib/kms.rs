@@ -0,0 +1,529 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::crypto::KeyProvider;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::secrets::{self, VaultConfig};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const GCP_METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+// A cloud KMS key that wraps the primary key, under `[encryption.kms]`. The
+// primary key is the data key of the envelope: frame keys are derived from
+// it, so the KMS is only called when a keystore is written or opened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum KmsConfig {
+    // Credentials come from the environment, as for [secrets.aws]
+    Aws {
+        key_id: String, // key ID, ARN or alias/<name>
+        region: String,
+        #[serde(default)]
+        endpoint: Option<String>, // overrides https://kms.<region>.amazonaws.com
+    },
+    // An OAuth token from `token_env`, else the instance's service account
+    Gcp {
+        key_name: String, // projects/<p>/locations/<l>/keyRings/<r>/cryptoKeys/<k>
+        #[serde(default = "default_gcp_token_env")]
+        token_env: String,
+        #[serde(default)]
+        endpoint: Option<String>, // overrides https://cloudkms.googleapis.com
+    },
+    // Vault's transit secrets engine
+    Vault {
+        #[serde(flatten)]
+        vault: VaultConfig,
+        key_name: String,
+        #[serde(default = "default_transit_mount")]
+        mount: String,
+    },
+}
+
+fn default_gcp_token_env() -> String {
+    "GOOGLE_OAUTH_ACCESS_TOKEN".to_string()
+}
+
+fn default_transit_mount() -> String {
+    "transit".to_string()
+}
+
+pub fn open_key_provider(config: &KmsConfig) -> Result<Arc<dyn KeyProvider>> {
+    Ok(Arc::new(KmsKeyProvider::new(config.clone())))
+}
+
+// Wraps and unwraps over the provider's HTTP API. KMS keys don't sign, so
+// envelopes stay signed with the key derived from the primary key.
+#[derive(Debug)]
+pub struct KmsKeyProvider {
+    config: KmsConfig,
+}
+
+impl KmsKeyProvider {
+    pub fn new(config: KmsConfig) -> Self {
+        Self { config }
+    }
+
+    async fn encrypt(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let client = client()?;
+        let plaintext = BASE64.encode(key);
+        match &self.config {
+            KmsConfig::Aws {
+                key_id,
+                region,
+                endpoint,
+            } => {
+                let body = json!({ "KeyId": key_id, "Plaintext": plaintext });
+                let response =
+                    aws_request(&client, region, endpoint, "TrentService.Encrypt", &body).await?;
+                Ok(BASE64.decode(field(&response, "CiphertextBlob")?)?)
+            }
+            KmsConfig::Gcp { .. } => {
+                let body = json!({ "plaintext": plaintext });
+                let response = self.gcp_request(&client, "encrypt", &body).await?;
+                Ok(BASE64.decode(field(&response, "ciphertext")?)?)
+            }
+            KmsConfig::Vault {
+                vault,
+                key_name,
+                mount,
+            } => {
+                let path = format!("{}/encrypt/{}", mount, key_name);
+                let body = json!({ "plaintext": plaintext });
+                let response = vault_request(&client, vault, &path, &body).await?;
+                // Transit ciphertexts are strings like vault:v1:<base64>
+                Ok(field(&response["data"], "ciphertext")?.as_bytes().to_vec())
+            }
+        }
+    }
+
+    async fn decrypt(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        let client = client()?;
+        let plaintext = match &self.config {
+            KmsConfig::Aws {
+                key_id,
+                region,
+                endpoint,
+            } => {
+                let body = json!({ "KeyId": key_id, "CiphertextBlob": BASE64.encode(wrapped) });
+                let response =
+                    aws_request(&client, region, endpoint, "TrentService.Decrypt", &body).await?;
+                field(&response, "Plaintext")?.to_string()
+            }
+            KmsConfig::Gcp { .. } => {
+                let body = json!({ "ciphertext": BASE64.encode(wrapped) });
+                let response = self.gcp_request(&client, "decrypt", &body).await?;
+                field(&response, "plaintext")?.to_string()
+            }
+            KmsConfig::Vault {
+                vault,
+                key_name,
+                mount,
+            } => {
+                let ciphertext = std::str::from_utf8(wrapped).map_err(|_| {
+                    ImmutableEncryptionError::crypto("The wrapped key is not a transit ciphertext")
+                })?;
+                let path = format!("{}/decrypt/{}", mount, key_name);
+                let body = json!({ "ciphertext": ciphertext });
+                let response = vault_request(&client, vault, &path, &body).await?;
+                field(&response["data"], "plaintext")?.to_string()
+            }
+        };
+        Ok(BASE64.decode(plaintext)?)
+    }
+
+    async fn gcp_request(
+        &self,
+        client: &reqwest::Client,
+        method: &str,
+        body: &Value,
+    ) -> Result<Value> {
+        let KmsConfig::Gcp {
+            key_name,
+            token_env,
+            endpoint,
+        } = &self.config
+        else {
+            return Err(ImmutableEncryptionError::config("Not a Cloud KMS key"));
+        };
+        let token = match std::env::var(token_env) {
+            Ok(token) => token,
+            Err(_) => {
+                let response = client
+                    .get(GCP_METADATA_TOKEN_URL)
+                    .header("Metadata-Flavor", "Google")
+                    .send()
+                    .await?;
+                let response = json_response(response, "The metadata server").await?;
+                field(&response, "access_token")?.to_string()
+            }
+        };
+        let url = format!(
+            "{}/v1/{}:{}",
+            endpoint
+                .as_deref()
+                .unwrap_or("https://cloudkms.googleapis.com")
+                .trim_end_matches('/'),
+            key_name,
+            method
+        );
+        let response = client
+            .post(&url)
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await?;
+        json_response(response, "Cloud KMS").await
+    }
+}
+
+impl KeyProvider for KmsKeyProvider {
+    fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>> {
+        run(self.encrypt(key))
+    }
+
+    fn unwrap_key(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        run(self.decrypt(wrapped))
+    }
+
+    fn sign(&self, _data: &[u8]) -> Result<Vec<u8>> {
+        Err(ImmutableEncryptionError::crypto(
+            "KMS keys only wrap the primary key",
+        ))
+    }
+
+    fn public_key(&self) -> Result<Vec<u8>> {
+        Err(ImmutableEncryptionError::crypto(
+            "KMS keys only wrap the primary key",
+        ))
+    }
+
+    fn signs_envelopes(&self) -> bool {
+        false
+    }
+}
+
+// Keys are wrapped and unwrapped from sync code, also inside the node's
+// runtime, so each call gets a thread and runtime of its own
+fn run<T: Send>(call: impl Future<Output = Result<T>> + Send) -> Result<T> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?
+                    .block_on(call)
+            })
+            .join()
+            .unwrap_or_else(|_| Err(ImmutableEncryptionError::crypto("KMS call panicked")))
+    })
+}
+
+// Built per call: pooled connections would outlive the call's runtime
+fn client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+async fn aws_request(
+    client: &reqwest::Client,
+    region: &str,
+    endpoint: &Option<String>,
+    target: &str,
+    body: &Value,
+) -> Result<Value> {
+    let endpoint = endpoint
+        .clone()
+        .unwrap_or_else(|| format!("https://kms.{}.amazonaws.com", region));
+    secrets::aws_json_request(client, &endpoint, region, "kms", target, body).await
+}
+
+async fn vault_request(
+    client: &reqwest::Client,
+    vault: &VaultConfig,
+    path: &str,
+    body: &Value,
+) -> Result<Value> {
+    let response = vault
+        .request(client, reqwest::Method::POST, path)?
+        .json(body)
+        .send()
+        .await?;
+    json_response(response, "Vault").await
+}
+
+async fn json_response(response: reqwest::Response, service: &str) -> Result<Value> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let detail = response.text().await.unwrap_or_default();
+        return Err(ImmutableEncryptionError::Network(format!(
+            "{} returned {}: {}",
+            service, status, detail
+        )));
+    }
+    Ok(response.json().await?)
+}
+
+fn field<'a>(response: &'a Value, name: &str) -> Result<&'a str> {
+    response[name]
+        .as_str()
+        .ok_or_else(|| ImmutableEncryptionError::Crypto(format!("KMS response has no {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_kms_configs_parse_per_provider() -> Result<()> {
+        let aws: KmsConfig = toml::from_str(
+            r#"
+            provider = "aws"
+            key_id = "alias/evidence"
+            region = "eu-west-1"
+            "#,
+        )?;
+        assert!(matches!(aws, KmsConfig::Aws { endpoint: None, .. }));
+
+        let gcp: KmsConfig = toml::from_str(
+            r#"
+            provider = "gcp"
+            key_name = "projects/p/locations/europe/keyRings/r/cryptoKeys/evidence"
+            "#,
+        )?;
+        let KmsConfig::Gcp { token_env, .. } = gcp else {
+            panic!("expected a Cloud KMS key");
+        };
+        assert_eq!(token_env, "GOOGLE_OAUTH_ACCESS_TOKEN");
+
+        let vault: KmsConfig = toml::from_str(
+            r#"
+            provider = "vault"
+            address = "https://vault.internal:8200"
+            key_name = "evidence"
+            "#,
+        )?;
+        let KmsConfig::Vault { vault, mount, .. } = vault else {
+            panic!("expected a Vault key");
+        };
+        assert_eq!(mount, "transit");
+        assert_eq!(vault.token_env, "VAULT_TOKEN");
+        assert!(!KmsKeyProvider::new(aws).signs_envelopes());
+
+        // Calls block on their own runtime, so the provider can be used
+        // from within the node's
+        assert_eq!(run(async { Ok(7) })?, 7);
+        assert!(field(&json!({ "Plaintext": 1 }), "Plaintext").is_err());
+
+        Ok(())
+    }
+
+    // The mock's keys: each provider "encrypts" by prefixing its own marker
+    const KEY_NAME: &str = "projects/p/locations/europe/keyRings/r/cryptoKeys/evidence";
+    const AWS_SECRET: &str = "mock-secret-access-key";
+    const GCP_TOKEN: &str = "mock-gcp-token";
+    const VAULT_TOKEN: &str = "mock-vault-token";
+
+    // A KMS on localhost speaking just enough of each provider's API, and
+    // refusing requests that aren't authorized the way the provider expects
+    async fn mock_kms() -> String {
+        use warp::Filter;
+
+        let routes = warp::post()
+            .and(warp::path::full())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::bytes())
+            .map(
+                |path: warp::path::FullPath, headers: warp::http::HeaderMap, body: bytes::Bytes| {
+                    match mock_reply(path.as_str(), &headers, &body) {
+                        Some(reply) => warp::reply::with_status(
+                            warp::reply::json(&reply),
+                            warp::http::StatusCode::OK,
+                        ),
+                        None => warp::reply::with_status(
+                            warp::reply::json(&json!({ "error": "denied" })),
+                            warp::http::StatusCode::FORBIDDEN,
+                        ),
+                    }
+                },
+            );
+
+        let (address, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        format!("http://{}", address)
+    }
+
+    fn mock_reply(path: &str, headers: &warp::http::HeaderMap, body: &[u8]) -> Option<Value> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let request: Value = serde_json::from_slice(body).ok()?;
+        if path == "/" && sigv4_matches(headers, body) {
+            aws_reply(header("x-amz-target")?, &request)
+        } else if header("x-vault-token") == Some(VAULT_TOKEN) {
+            vault_reply(path, &request)
+        } else if header("authorization") == Some(&format!("Bearer {}", GCP_TOKEN)) {
+            gcp_reply(path, &request)
+        } else {
+            None
+        }
+    }
+
+    fn aws_reply(target: &str, request: &Value) -> Option<Value> {
+        if request["KeyId"] != "alias/evidence" {
+            return None;
+        }
+        match target {
+            "TrentService.Encrypt" => {
+                let plaintext = BASE64.decode(request["Plaintext"].as_str()?).ok()?;
+                let blob = [b"aws:".as_slice(), &plaintext].concat();
+                Some(json!({ "CiphertextBlob": BASE64.encode(blob) }))
+            }
+            "TrentService.Decrypt" => {
+                let blob = BASE64.decode(request["CiphertextBlob"].as_str()?).ok()?;
+                let plaintext = blob.strip_prefix(b"aws:")?;
+                Some(json!({ "Plaintext": BASE64.encode(plaintext) }))
+            }
+            _ => None,
+        }
+    }
+
+    fn gcp_reply(path: &str, request: &Value) -> Option<Value> {
+        let method = path.strip_prefix(&format!("/v1/{}:", KEY_NAME))?;
+        match method {
+            "encrypt" => {
+                let plaintext = BASE64.decode(request["plaintext"].as_str()?).ok()?;
+                let ciphertext = [b"gcp:".as_slice(), &plaintext].concat();
+                Some(json!({ "ciphertext": BASE64.encode(ciphertext) }))
+            }
+            "decrypt" => {
+                let ciphertext = BASE64.decode(request["ciphertext"].as_str()?).ok()?;
+                let plaintext = ciphertext.strip_prefix(b"gcp:")?;
+                Some(json!({ "plaintext": BASE64.encode(plaintext) }))
+            }
+            _ => None,
+        }
+    }
+
+    // Transit ciphertexts are strings like vault:v1:<base64>
+    fn vault_reply(path: &str, request: &Value) -> Option<Value> {
+        match path {
+            "/v1/transit/encrypt/evidence" => {
+                let plaintext = request["plaintext"].as_str()?;
+                Some(json!({ "data": { "ciphertext": format!("vault:v1:{}", plaintext) } }))
+            }
+            "/v1/transit/decrypt/evidence" => {
+                let ciphertext = request["ciphertext"].as_str()?;
+                let plaintext = ciphertext.strip_prefix("vault:v1:")?;
+                Some(json!({ "data": { "plaintext": plaintext } }))
+            }
+            _ => None,
+        }
+    }
+
+    // Recomputes the SigV4 signature from the signed headers the request lists
+    fn sigv4_matches(headers: &warp::http::HeaderMap, body: &[u8]) -> bool {
+        let check = || -> Option<bool> {
+            let authorization = headers.get("authorization")?.to_str().ok()?;
+            let rest =
+                authorization.strip_prefix("AWS4-HMAC-SHA256 Credential=mock-access-key/")?;
+            let (scope, rest) = rest.split_once(", SignedHeaders=")?;
+            let (signed_headers, signature) = rest.split_once(", Signature=")?;
+            let mut scope_parts = scope.split('/');
+            let (date, region, service) = (
+                scope_parts.next()?,
+                scope_parts.next()?,
+                scope_parts.next()?,
+            );
+            if region != "eu-west-1" || service != "kms" {
+                return Some(false);
+            }
+
+            let mut canonical_headers = String::new();
+            for name in signed_headers.split(';') {
+                let value = headers.get(name)?.to_str().ok()?;
+                canonical_headers.push_str(&format!("{}:{}\n", name, value.trim()));
+            }
+            let canonical_request = format!(
+                "POST\n/\n\n{}\n{}\n{}",
+                canonical_headers,
+                signed_headers,
+                crate::crypto::sha256_hex(body)
+            );
+            let string_to_sign = format!(
+                "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+                headers.get("x-amz-date")?.to_str().ok()?,
+                scope,
+                crate::crypto::sha256_hex(canonical_request.as_bytes())
+            );
+            let expected = secrets::sigv4_sign(AWS_SECRET, date, region, service, &string_to_sign);
+            Some(
+                signed_headers.split(';').any(|name| name == "x-amz-target")
+                    && hex::encode(expected) == signature,
+            )
+        };
+        check().unwrap_or(false)
+    }
+
+    // Calls block on a thread of their own, so the mock needs a worker to run on
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_kms_wraps_and_unwraps_per_provider() -> Result<()> {
+        let endpoint = mock_kms().await;
+        std::env::set_var("AWS_ACCESS_KEY_ID", "mock-access-key");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", AWS_SECRET);
+        std::env::set_var("KMS_TEST_GCP_TOKEN", GCP_TOKEN);
+        std::env::set_var("KMS_TEST_VAULT_TOKEN", VAULT_TOKEN);
+        let key = [6u8; 32];
+
+        let aws = KmsKeyProvider::new(KmsConfig::Aws {
+            key_id: "alias/evidence".to_string(),
+            region: "eu-west-1".to_string(),
+            endpoint: Some(endpoint.clone()),
+        });
+        let wrapped = aws.wrap_key(&key)?;
+        assert_eq!(&wrapped[..4], b"aws:");
+        assert_eq!(aws.unwrap_key(&wrapped)?, key);
+
+        let gcp = KmsKeyProvider::new(KmsConfig::Gcp {
+            key_name: KEY_NAME.to_string(),
+            token_env: "KMS_TEST_GCP_TOKEN".to_string(),
+            endpoint: Some(endpoint.clone()),
+        });
+        let wrapped = gcp.wrap_key(&key)?;
+        assert_eq!(&wrapped[..4], b"gcp:");
+        assert_eq!(gcp.unwrap_key(&wrapped)?, key);
+
+        let vault = KmsKeyProvider::new(KmsConfig::Vault {
+            vault: VaultConfig {
+                address: endpoint.clone(),
+                token_env: "KMS_TEST_VAULT_TOKEN".to_string(),
+                namespace: None,
+            },
+            key_name: "evidence".to_string(),
+            mount: "transit".to_string(),
+        });
+        // The transit ciphertext is kept as the string Vault returned
+        let wrapped = vault.wrap_key(&key)?;
+        assert_eq!(
+            String::from_utf8(wrapped.clone()).unwrap(),
+            format!("vault:v1:{}", BASE64.encode(key))
+        );
+        assert_eq!(vault.unwrap_key(&wrapped)?, key);
+        assert!(vault.unwrap_key(&[0xFF, 0xFE]).is_err());
+
+        // A ciphertext from one provider doesn't unwrap at another, and a
+        // request the KMS refuses surfaces as an error
+        assert!(gcp.unwrap_key(&aws.wrap_key(&key)?).is_err());
+        let denied = KmsKeyProvider::new(KmsConfig::Gcp {
+            key_name: KEY_NAME.to_string(),
+            token_env: "KMS_TEST_VAULT_TOKEN".to_string(),
+            endpoint: Some(endpoint),
+        });
+        assert!(denied.wrap_key(&key).is_err());
+
+        Ok(())
+    }
+}
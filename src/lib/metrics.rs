@@ -0,0 +1,245 @@
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Latency buckets in seconds: sub-millisecond crypto up to slow chain RPCs
+const LATENCY_BUCKETS: [f64; 14] = [
+    0.0001, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0, 30.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Module {
+    Crypto,
+    Storage,
+    Blockchain,
+    Verification,
+}
+
+impl Module {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Module::Crypto => "crypto",
+            Module::Storage => "storage",
+            Module::Blockchain => "blockchain",
+            Module::Verification => "verification",
+        }
+    }
+}
+
+// One module operation's totals since startup, as shown by `/status`
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationStats {
+    pub module: Module,
+    pub operation: String,
+    pub count: u64,
+    pub errors: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct Totals {
+    count: u64,
+    errors: u64,
+    total: Duration,
+    max: Duration,
+}
+
+// Counters and latency histograms per module and operation, kept both in a
+// Prometheus registry for scraping and as plain totals for the status API
+pub struct Metrics {
+    registry: Registry,
+    operations: IntCounterVec, // module, operation, outcome
+    latency: HistogramVec,     // module, operation
+    in_flight: IntGaugeVec,    // module
+    totals: Mutex<BTreeMap<(Module, &'static str), Totals>>,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new_custom(Some("immutable_encryption".to_string()), None)
+            .map_err(metrics_error)?;
+        let operations = IntCounterVec::new(
+            Opts::new("operations_total", "Module operations by outcome"),
+            &["module", "operation", "outcome"],
+        )
+        .map_err(metrics_error)?;
+        let latency = HistogramVec::new(
+            HistogramOpts::new("operation_duration_seconds", "Module operation latency")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["module", "operation"],
+        )
+        .map_err(metrics_error)?;
+        let in_flight = IntGaugeVec::new(
+            Opts::new("tasks_in_flight", "Pipeline tasks currently running"),
+            &["module"],
+        )
+        .map_err(metrics_error)?;
+        registry
+            .register(Box::new(operations.clone()))
+            .map_err(metrics_error)?;
+        registry
+            .register(Box::new(latency.clone()))
+            .map_err(metrics_error)?;
+        registry
+            .register(Box::new(in_flight.clone()))
+            .map_err(metrics_error)?;
+
+        Ok(Self {
+            registry,
+            operations,
+            latency,
+            in_flight,
+            totals: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    pub fn record(&self, module: Module, operation: &'static str, elapsed: Duration, ok: bool) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.operations
+            .with_label_values(&[module.as_str(), operation, outcome])
+            .inc();
+        self.latency
+            .with_label_values(&[module.as_str(), operation])
+            .observe(elapsed.as_secs_f64());
+
+        let mut totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        let totals = totals.entry((module, operation)).or_default();
+        totals.count += 1;
+        totals.errors += u64::from(!ok);
+        totals.total += elapsed;
+        totals.max = totals.max.max(elapsed);
+    }
+
+    // Counts a task as running until the returned guard is dropped
+    pub fn in_flight(&self, module: Module) -> InFlight {
+        let gauge = self.in_flight.with_label_values(&[module.as_str()]);
+        gauge.inc();
+        InFlight(gauge)
+    }
+
+    pub fn snapshot(&self) -> Vec<OperationStats> {
+        let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+        self.totals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|((module, operation), totals)| OperationStats {
+                module: *module,
+                operation: operation.to_string(),
+                count: totals.count,
+                errors: totals.errors,
+                mean_ms: millis(totals.total) / totals.count.max(1) as f64,
+                max_ms: millis(totals.max),
+            })
+            .collect()
+    }
+
+    // Prometheus text exposition format
+    pub fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(metrics_error)?;
+        String::from_utf8(buffer).map_err(|e| ImmutableEncryptionError::Internal(e.to_string()))
+    }
+}
+
+pub struct InFlight(IntGauge);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+fn metrics_error(e: prometheus::Error) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::Internal(format!("Metrics: {}", e))
+}
+
+// The process-wide metrics every module records into
+pub fn global() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    // The metric definitions are constant, so registering them can't fail
+    METRICS.get_or_init(|| Metrics::new().expect("metric definitions are valid"))
+}
+
+pub fn timed<T>(
+    module: Module,
+    operation: &'static str,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = f();
+    global().record(module, operation, started.elapsed(), result.is_ok());
+    result
+}
+
+pub async fn timed_async<T>(
+    module: Module,
+    operation: &'static str,
+    f: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = f.await;
+    global().record(module, operation, started.elapsed(), result.is_ok());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operations_are_counted_and_exported() -> Result<()> {
+        let metrics = Metrics::new()?;
+        metrics.record(Module::Crypto, "encrypt", Duration::from_millis(2), true);
+        metrics.record(Module::Crypto, "encrypt", Duration::from_millis(4), false);
+        metrics.record(Module::Storage, "store", Duration::from_millis(10), true);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        let encrypt = &snapshot[0];
+        assert_eq!(
+            (encrypt.module, encrypt.operation.as_str()),
+            (Module::Crypto, "encrypt")
+        );
+        assert_eq!((encrypt.count, encrypt.errors), (2, 1));
+        assert!((encrypt.mean_ms - 3.0).abs() < 1e-9);
+        assert!((encrypt.max_ms - 4.0).abs() < 1e-9);
+
+        let text = metrics.encode()?;
+        let errors = concat!(
+            "immutable_encryption_operations_total",
+            r#"{module="crypto",operation="encrypt",outcome="error"} 1"#
+        );
+        assert!(text.contains(errors));
+        assert!(text.contains("immutable_encryption_operation_duration_seconds_bucket"));
+
+        let task = metrics.in_flight(Module::Blockchain);
+        let running = r#"immutable_encryption_tasks_in_flight{module="blockchain"} 1"#;
+        assert!(metrics.encode()?.contains(running));
+        drop(task);
+        let finished = r#"immutable_encryption_tasks_in_flight{module="blockchain"} 0"#;
+        assert!(metrics.encode()?.contains(finished));
+
+        let failed: Result<()> = timed(Module::Verification, "hash_chain", || {
+            Err(ImmutableEncryptionError::HashChainViolation)
+        });
+        assert!(failed.is_err());
+        assert!(global()
+            .snapshot()
+            .iter()
+            .any(|s| s.operation == "hash_chain" && s.errors >= 1));
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Format of every value this build writes. Bump it, and add a step below for
+// each kind whose stored form changes, whenever a stored struct changes in a
+// way serde defaults can't absorb.
+pub const FORMAT_VERSION: u8 = 3;
+
+// Versioned values start with this byte and the version. Nothing untagged
+// can: JSON starts with `{`, `[`, `"` or a digit, index values with a key,
+// a protobuf frame with a tag below 0x80 since its fields are all < 16, and
+// an rkyv frame with 0x00.
+const TAG: u8 = 0xFE;
+
+// Holds the format the database was last migrated to; absent on databases
+// from before versioning
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    Frame,
+    Report,
+    Custody,
+    Reference, // device index entries and IPFS CIDs: plain keys, not JSON
+    Record,    // sessions, manifests, holds, audit and everything else
+}
+
+impl RecordKind {
+    pub fn of(key: &str) -> Self {
+        let prefix = key.split(':').next().unwrap_or_default();
+        match prefix {
+            "frame" => Self::Frame,
+            "metadata" => Self::Report,
+            "custody" => Self::Custody,
+            "device" | "ipfs" => Self::Reference,
+            _ => Self::Record,
+        }
+    }
+}
+
+// Upgrades one kind's payload from version `.1` to `.1 + 1`
+type Step = (RecordKind, u8, fn(Vec<u8>) -> Result<Vec<u8>>);
+
+// Version 0 is every value written before versioning. Those already read as
+// version 1, so they need no step of their own; upgrading them adds the tag.
+// Version 2 added frame attestations and version 3 device signatures, both of
+// which change the rkyv layout.
+const STEPS: &[Step] = &[
+    (RecordKind::Frame, 1, crate::wire::upgrade_v1_frame),
+    (RecordKind::Frame, 2, crate::wire::upgrade_v2_frame),
+];
+
+pub fn tag(payload: Vec<u8>) -> Vec<u8> {
+    let mut tagged = Vec::with_capacity(payload.len() + 2);
+    tagged.extend_from_slice(&[TAG, FORMAT_VERSION]);
+    tagged.extend(payload);
+    tagged
+}
+
+pub fn version(data: &[u8]) -> u8 {
+    match data {
+        [TAG, version, ..] => *version,
+        _ => 0,
+    }
+}
+
+// A stored value's payload in the current format, upgraded in memory if it
+// was written by an older build
+pub fn upgrade(kind: RecordKind, data: &[u8]) -> Result<Cow<'_, [u8]>> {
+    upgrade_with(STEPS, kind, data)
+}
+
+fn upgrade_with<'a>(steps: &[Step], kind: RecordKind, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    let from = version(data);
+    if from > FORMAT_VERSION {
+        return Err(ImmutableEncryptionError::storage(&format!(
+            "Record has format version {}; this node reads up to {}",
+            from, FORMAT_VERSION
+        )));
+    }
+    let payload = if from == 0 { data } else { &data[2..] };
+
+    let mut upgraded = Cow::Borrowed(payload);
+    for version in from..FORMAT_VERSION {
+        for (_, _, step) in steps.iter().filter(|(k, v, _)| *k == kind && *v == version) {
+            upgraded = Cow::Owned(step(upgraded.into_owned()).map_err(|e| {
+                e.context(format!(
+                    "Upgrading a {:?} record from version {}",
+                    kind, version
+                ))
+            })?);
+        }
+    }
+    Ok(upgraded)
+}
+
+// What a `migrate` run found and rewrote
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub schema_version: Option<u8>, // before the run; None if never migrated
+    pub target_version: u8,
+    pub dry_run: bool,
+    pub scanned: u64,
+    pub upgraded: BTreeMap<RecordKind, u64>,
+    pub failed: Vec<String>, // keys whose value could not be upgraded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_field(payload: Vec<u8>) -> Result<Vec<u8>> {
+        let mut value: serde_json::Value = serde_json::from_slice(&payload)?;
+        value["added"] = serde_json::Value::Bool(true);
+        Ok(serde_json::to_vec(&value)?)
+    }
+
+    #[test]
+    fn test_untagged_records_are_upgraded_and_newer_ones_refused() -> Result<()> {
+        assert_eq!(RecordKind::of("frame:1:2"), RecordKind::Frame);
+        assert_eq!(RecordKind::of("device:cam:1"), RecordKind::Reference);
+        assert_eq!(RecordKind::of("session:abc"), RecordKind::Record);
+
+        let legacy = br#"{"a":1}"#.to_vec();
+        assert_eq!(version(&legacy), 0);
+        assert_eq!(&*upgrade(RecordKind::Record, &legacy)?, &legacy[..]);
+
+        let tagged = tag(legacy.clone());
+        assert_eq!(version(&tagged), FORMAT_VERSION);
+        assert_eq!(&*upgrade(RecordKind::Record, &tagged)?, &legacy[..]);
+
+        let steps: &[Step] = &[(RecordKind::Record, 0, add_field)];
+        let upgraded = upgrade_with(steps, RecordKind::Record, &legacy)?;
+        assert_eq!(&*upgraded, br#"{"a":1,"added":true}"#);
+        // Steps only touch their own kind and only older versions
+        assert_eq!(
+            &*upgrade_with(steps, RecordKind::Frame, &legacy)?,
+            &legacy[..]
+        );
+        assert_eq!(
+            &*upgrade_with(steps, RecordKind::Record, &tagged)?,
+            &legacy[..]
+        );
+
+        let newer = [TAG, FORMAT_VERSION + 1, b'{', b'}'];
+        assert!(upgrade(RecordKind::Record, &newer).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+// UniFFI bindings so Android and iOS capture apps can seal on the phone; the
+// Kotlin and Swift sources are generated from the built library
+use std::sync::{Arc, Mutex};
+
+use crate::crypto::Cipher;
+use crate::error::ImmutableEncryptionError;
+use crate::ffi::{DeviceSealer, DeviceSegment, SegmentProof};
+use crate::storage;
+use crate::FrameMetadata;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum MobileCipher {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+    XChaCha20Poly1305,
+    Aes256GcmSiv,
+}
+
+impl From<MobileCipher> for Cipher {
+    fn from(cipher: MobileCipher) -> Self {
+        match cipher {
+            MobileCipher::Aes256Gcm => Cipher::Aes256Gcm,
+            MobileCipher::ChaCha20Poly1305 => Cipher::ChaCha20Poly1305,
+            MobileCipher::XChaCha20Poly1305 => Cipher::XChaCha20Poly1305,
+            MobileCipher::Aes256GcmSiv => Cipher::Aes256GcmSiv,
+        }
+    }
+}
+
+// Surfaces as an exception carrying the message in Kotlin and Swift
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum SealError {
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
+    Crypto(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<ImmutableEncryptionError> for SealError {
+    fn from(error: ImmutableEncryptionError) -> Self {
+        let message = error.to_string();
+        match error {
+            ImmutableEncryptionError::Crypto(_) => Self::Crypto(message),
+            ImmutableEncryptionError::InvalidRequest(_)
+            | ImmutableEncryptionError::InvalidSequence(_) => Self::InvalidArgument(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct CaptureFrame {
+    pub data: Vec<u8>,
+    pub timestamp: u64,
+    pub sequence: u64, // 0 takes the next one
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub codec: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+// `json` is the record the node stores; apps queue it until they are online
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct SealedFrame {
+    pub frame_id: String,
+    pub sequence: u64,
+    pub hash: String,
+    pub previous_hash: String,
+    pub json: String,
+}
+
+// One device's hash chain, shareable between the camera and upload threads
+#[derive(uniffi::Object)]
+pub struct MobileSealer {
+    device_id: String,
+    sealer: Mutex<DeviceSealer>,
+}
+
+impl MobileSealer {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, DeviceSealer>, SealError> {
+        self.sealer
+            .lock()
+            .map_err(|_| SealError::Internal("sealer lock poisoned".to_string()))
+    }
+}
+
+#[uniffi::export]
+impl MobileSealer {
+    // `key_rotation_interval` is the node's, in seconds; frame keys are
+    // derived per interval exactly as the node derives them
+    #[uniffi::constructor]
+    pub fn new(
+        key: Vec<u8>,
+        device_id: String,
+        cipher: MobileCipher,
+        key_rotation_interval: u64,
+    ) -> Result<Arc<Self>, SealError> {
+        let sealer = DeviceSealer::new(&key, &device_id, cipher.into(), key_rotation_interval)?;
+        Ok(Arc::new(Self {
+            device_id,
+            sealer: Mutex::new(sealer),
+        }))
+    }
+
+    // Signs frames from now on with the device's Ed25519 identity key, given
+    // as its 32-byte seed; returns the public key to register with the node
+    pub fn set_identity_key(&self, seed: Vec<u8>) -> Result<Vec<u8>, SealError> {
+        Ok(self.lock()?.set_identity_key(&seed)?)
+    }
+
+    // Continues a chain the app persisted before it was killed
+    pub fn resume(&self, chain_tip: String, last_sequence: u64) -> Result<(), SealError> {
+        Ok(self.lock()?.resume(&chain_tip, last_sequence)?)
+    }
+
+    pub fn seal_frame(&self, frame: CaptureFrame) -> Result<SealedFrame, SealError> {
+        let location = match (frame.latitude, frame.longitude) {
+            (Some(latitude), Some(longitude)) => Some((latitude, longitude)),
+            (None, None) => None,
+            _ => {
+                return Err(SealError::InvalidArgument(
+                    "latitude and longitude must be given together".to_string(),
+                ))
+            }
+        };
+        let metadata = FrameMetadata {
+            device_id: self.device_id.clone(),
+            location,
+            resolution: (frame.width, frame.height),
+            fps: frame.fps,
+            codec: frame.codec,
+            telemetry: None,
+        };
+        let sealed =
+            self.lock()?
+                .seal(frame.data.into(), frame.timestamp, frame.sequence, metadata)?;
+
+        Ok(SealedFrame {
+            frame_id: storage::frame_key(&sealed.device_id, sealed.sequence, sealed.timestamp),
+            sequence: sealed.sequence,
+            hash: sealed.hash.clone(),
+            previous_hash: sealed.previous_hash.clone(),
+            json: serde_json::to_string(&sealed).map_err(ImmutableEncryptionError::from)?,
+        })
+    }
+
+    pub fn chain_tip(&self) -> Result<String, SealError> {
+        Ok(self.lock()?.chain_tip().to_string())
+    }
+
+    // None when nothing was sealed since the last export
+    pub fn export_proof(&self) -> Result<Option<SegmentProof>, SealError> {
+        Ok(self.lock()?.export_proof()?)
+    }
+}
+
+// The proof as the node accepts it, for uploading next to the sealed frames
+#[uniffi::export]
+pub fn segment_proof_json(proof: SegmentProof) -> Result<String, SealError> {
+    Ok(serde_json::to_string(&proof).map_err(ImmutableEncryptionError::from)?)
+}
+
+// The body for `POST /devices/segments`: the queued `SealedFrame.json`
+// records, in order, with the proof exported after them
+#[uniffi::export]
+pub fn segment_upload_json(proof: SegmentProof, frames: Vec<String>) -> Result<String, SealError> {
+    let frames = frames
+        .iter()
+        .map(|json| serde_json::from_str(json))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| SealError::InvalidArgument(format!("not a sealed frame: {}", e)))?;
+    let segment = DeviceSegment { proof, frames };
+    Ok(serde_json::to_string(&segment).map_err(ImmutableEncryptionError::from)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{CryptoConfig, EncryptionEngine};
+    use crate::EncryptedFrame;
+
+    #[test]
+    fn test_mobile_sealer_chains_and_exports() {
+        let key = vec![4u8; 32];
+        let sealer = MobileSealer::new(
+            key.clone(),
+            "phone_3".to_string(),
+            MobileCipher::Aes256Gcm,
+            3600,
+        )
+        .unwrap();
+        let capture = |timestamp: u64, latitude: Option<f64>| CaptureFrame {
+            data: b"jpeg".to_vec(),
+            timestamp,
+            sequence: 0,
+            width: 1080,
+            height: 1920,
+            fps: 30,
+            codec: "MJPEG".to_string(),
+            latitude,
+            longitude: latitude.map(|l| l + 1.0),
+        };
+
+        let first = sealer.seal_frame(capture(100, Some(51.5))).unwrap();
+        let second = sealer.seal_frame(capture(101, None)).unwrap();
+        assert_eq!(first.frame_id, "frame:phone_3:1:100");
+        assert_eq!(second.previous_hash, first.hash);
+        assert_eq!(sealer.chain_tip().unwrap(), second.hash);
+
+        let stored: EncryptedFrame = serde_json::from_str(&first.json).unwrap();
+        let node = EncryptionEngine::new(CryptoConfig::software(key, 3600)).unwrap();
+        assert_eq!(node.decrypt_frame_data(&stored).unwrap(), b"jpeg");
+
+        let mut half = capture(102, None);
+        half.latitude = Some(1.0);
+        assert!(matches!(
+            sealer.seal_frame(half),
+            Err(SealError::InvalidArgument(_))
+        ));
+
+        let proof = sealer.export_proof().unwrap().unwrap();
+        assert_eq!((proof.first_sequence, proof.last_sequence), (1, 2));
+        assert!(segment_proof_json(proof).unwrap().contains("merkle_root"));
+        assert!(sealer.export_proof().unwrap().is_none());
+
+        sealer.resume("0".repeat(64), 10).unwrap();
+        assert_eq!(sealer.seal_frame(capture(103, None)).unwrap().sequence, 11);
+        assert!(matches!(
+            sealer.resume("tip".to_string(), 1),
+            Err(SealError::InvalidArgument(_))
+        ));
+    }
+}
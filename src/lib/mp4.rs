@@ -0,0 +1,393 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Seconds between the MP4 epoch (1904-01-01) and the Unix epoch
+const MP4_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+// The movie box is read into memory; real files keep it to a few MB
+const MAX_MOOV_BYTES: u64 = 256 * 1024 * 1024;
+
+// Over six days of 30fps footage; guards against corrupt sample counts
+const MAX_SAMPLES: usize = 1 << 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub offset: u64,
+    pub size: u32,
+    pub decode_time: u64, // in the track's timescale
+    pub is_sync: bool,
+}
+
+// The first video track of a non-fragmented MP4, located through its sample
+// table. Samples are read back exactly as stored; nothing is decoded.
+#[derive(Debug, Clone)]
+pub struct VideoTrack {
+    pub codec: String, // sample entry fourcc, e.g. avc1
+    pub width: u32,
+    pub height: u32,
+    pub timescale: u32,
+    pub duration: u64,
+    pub created_at: Option<u64>, // Unix seconds, from the movie header
+    pub samples: Vec<Sample>,
+}
+
+impl VideoTrack {
+    pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let end = reader.seek(SeekFrom::End(0))?;
+        let mut position = 0;
+        let mut moov = None;
+
+        while position < end {
+            reader.seek(SeekFrom::Start(position))?;
+            let (kind, header_len, size) = read_box_header(reader, end - position)?;
+            match &kind {
+                b"moov" => {
+                    if size - header_len > MAX_MOOV_BYTES {
+                        return Err(ImmutableEncryptionError::Video(format!(
+                            "MP4 movie box is too large ({} bytes)",
+                            size
+                        )));
+                    }
+                    let mut body = vec![0; (size - header_len) as usize];
+                    reader.read_exact(&mut body)?;
+                    moov = Some(body);
+                }
+                b"moof" => {
+                    return Err(ImmutableEncryptionError::video(
+                        "Fragmented MP4 is not supported",
+                    ))
+                }
+                _ => {}
+            }
+            position += size;
+        }
+
+        let moov = moov
+            .ok_or_else(|| ImmutableEncryptionError::video("No moov box; is this an MP4 file?"))?;
+        parse_moov(&moov)
+    }
+
+    pub fn fps(&self) -> u32 {
+        if self.duration == 0 || self.timescale == 0 {
+            return 1;
+        }
+        let fps = self.samples.len() as f64 * self.timescale as f64 / self.duration as f64;
+        (fps.round() as u32).max(1)
+    }
+
+    pub fn read_sample<R: Read + Seek>(&self, reader: &mut R, sample: &Sample) -> Result<Vec<u8>> {
+        reader.seek(SeekFrom::Start(sample.offset))?;
+        let mut data = vec![0; sample.size as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+// Returns the box type, header length and total size
+fn read_box_header<R: Read>(reader: &mut R, remaining: u64) -> Result<([u8; 4], u64, u64)> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let kind = [header[4], header[5], header[6], header[7]];
+
+    let declared = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let (header_len, size) = match declared {
+        0 => (8, remaining), // extends to the end of the file
+        1 => {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            (16, u64::from_be_bytes(large))
+        }
+        size => (8, size as u64),
+    };
+    if size < header_len || size > remaining {
+        return Err(ImmutableEncryptionError::Video(format!(
+            "Malformed MP4 box {}",
+            String::from_utf8_lossy(&kind)
+        )));
+    }
+    Ok((kind, header_len, size))
+}
+
+fn parse_moov(moov: &[u8]) -> Result<VideoTrack> {
+    let mut created_at = None;
+    let mut video = None;
+    for (kind, body) in children(moov)? {
+        match &kind {
+            b"mvhd" => created_at = movie_creation_time(body)?,
+            b"trak" if video.is_none() => video = parse_video_trak(body)?,
+            _ => {}
+        }
+    }
+
+    let mut track =
+        video.ok_or_else(|| ImmutableEncryptionError::video("MP4 has no video track"))?;
+    track.created_at = created_at;
+    Ok(track)
+}
+
+// None for audio, subtitle and other non-video tracks
+fn parse_video_trak(trak: &[u8]) -> Result<Option<VideoTrack>> {
+    let mdia = require(trak, b"mdia")?;
+    if require(mdia, b"hdlr")?.get(8..12) != Some(&b"vide"[..]) {
+        return Ok(None);
+    }
+
+    let mdhd = require(mdia, b"mdhd")?;
+    let (timescale, duration) = if mdhd.first() == Some(&1) {
+        (be_u32(mdhd, 20)?, be_u64(mdhd, 24)?)
+    } else {
+        (be_u32(mdhd, 12)?, be_u32(mdhd, 16)? as u64)
+    };
+
+    let stbl = require(require(mdia, b"minf")?, b"stbl")?;
+    // The first visual sample entry: fourcc at 12, width and height 32 bytes in
+    let stsd = require(stbl, b"stsd")?;
+    let codec = String::from_utf8_lossy(stsd.get(12..16).unwrap_or_default()).to_string();
+
+    Ok(Some(VideoTrack {
+        codec,
+        width: be_u16(stsd, 40)? as u32,
+        height: be_u16(stsd, 42)? as u32,
+        timescale,
+        duration,
+        created_at: None,
+        samples: sample_table(stbl)?,
+    }))
+}
+
+fn movie_creation_time(mvhd: &[u8]) -> Result<Option<u64>> {
+    let created = if mvhd.first() == Some(&1) {
+        be_u64(mvhd, 4)?
+    } else {
+        be_u32(mvhd, 4)? as u64
+    };
+    Ok(created.checked_sub(MP4_EPOCH_OFFSET).filter(|&t| t > 0))
+}
+
+// Rebuilds each sample's file offset from the chunk offsets (stco/co64),
+// samples per chunk (stsc) and sizes (stsz), then its decode time (stts) and
+// whether it is a keyframe (stss; absent means every sample is)
+fn sample_table(stbl: &[u8]) -> Result<Vec<Sample>> {
+    let stsz = require(stbl, b"stsz")?;
+    let fixed_size = be_u32(stsz, 4)?;
+    let count = be_u32(stsz, 8)? as usize;
+    if count > MAX_SAMPLES {
+        return Err(ImmutableEncryptionError::Video(format!(
+            "MP4 declares {} samples",
+            count
+        )));
+    }
+    let size_of = |index: usize| match fixed_size {
+        0 => be_u32(stsz, 12 + 4 * index),
+        size => Ok(size),
+    };
+
+    let chunk_offsets: Vec<u64> = match child(stbl, b"stco")? {
+        Some(stco) => table(stco, 4)?
+            .map(|at| be_u32(stco, at).map(u64::from))
+            .collect::<Result<_>>()?,
+        None => {
+            let co64 = require(stbl, b"co64")?;
+            table(co64, 8)?
+                .map(|at| be_u64(co64, at))
+                .collect::<Result<_>>()?
+        }
+    };
+
+    let stsc = require(stbl, b"stsc")?;
+    let runs: Vec<(u32, u32)> = table(stsc, 12)?
+        .map(|at| -> Result<(u32, u32)> { Ok((be_u32(stsc, at)?, be_u32(stsc, at + 4)?)) })
+        .collect::<Result<_>>()?;
+
+    let mut samples = Vec::with_capacity(count);
+    for (index, &chunk_offset) in chunk_offsets.iter().enumerate() {
+        let chunk = index as u32 + 1;
+        let per_chunk = runs
+            .iter()
+            .rev()
+            .find(|(first_chunk, _)| *first_chunk <= chunk)
+            .map_or(0, |(_, per_chunk)| *per_chunk);
+
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            if samples.len() == count {
+                break;
+            }
+            let size = size_of(samples.len())?;
+            samples.push(Sample {
+                offset,
+                size,
+                decode_time: 0,
+                is_sync: true,
+            });
+            offset += size as u64;
+        }
+    }
+    if samples.len() != count {
+        return Err(ImmutableEncryptionError::Video(format!(
+            "MP4 sample table locates {} of {} samples",
+            samples.len(),
+            count
+        )));
+    }
+
+    let stts = require(stbl, b"stts")?;
+    let (mut time, mut next) = (0u64, 0usize);
+    for at in table(stts, 8)? {
+        let run = be_u32(stts, at)? as usize;
+        let delta = be_u32(stts, at + 4)? as u64;
+        for sample in samples.iter_mut().skip(next).take(run) {
+            sample.decode_time = time;
+            time += delta;
+        }
+        next = next.saturating_add(run);
+    }
+
+    if let Some(stss) = child(stbl, b"stss")? {
+        samples.iter_mut().for_each(|s| s.is_sync = false);
+        for at in table(stss, 4)? {
+            let number = be_u32(stss, at)? as usize; // 1-based
+            if let Some(sample) = number.checked_sub(1).and_then(|i| samples.get_mut(i)) {
+                sample.is_sync = true;
+            }
+        }
+    }
+
+    Ok(samples)
+}
+
+// Offsets of the entries in a full box holding `count` fixed-size entries
+fn table(body: &[u8], entry_len: usize) -> Result<impl Iterator<Item = usize>> {
+    let count = be_u32(body, 4)? as usize;
+    if count.saturating_mul(entry_len) > body.len().saturating_sub(8) {
+        return Err(ImmutableEncryptionError::video(
+            "Truncated MP4 sample table",
+        ));
+    }
+    Ok((0..count).map(move |i| 8 + i * entry_len))
+}
+
+fn children(data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    let mut boxes = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let (kind, header_len, size) = read_box_header(&mut &rest[..], rest.len() as u64)?;
+        boxes.push((kind, &rest[header_len as usize..size as usize]));
+        rest = &rest[size as usize..];
+    }
+    Ok(boxes)
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Result<Option<&'a [u8]>> {
+    Ok(children(data)?
+        .into_iter()
+        .find(|(k, _)| k == kind)
+        .map(|(_, body)| body))
+}
+
+fn require<'a>(data: &'a [u8], kind: &[u8; 4]) -> Result<&'a [u8]> {
+    child(data, kind)?.ok_or_else(|| {
+        ImmutableEncryptionError::Video(format!(
+            "MP4 is missing a {} box",
+            String::from_utf8_lossy(kind)
+        ))
+    })
+}
+
+fn be_u16(data: &[u8], at: usize) -> Result<u16> {
+    Ok(u16::from_be_bytes(field(data, at)?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Result<u32> {
+    Ok(u32::from_be_bytes(field(data, at)?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Result<u64> {
+    Ok(u64::from_be_bytes(field(data, at)?))
+}
+
+fn field<const N: usize>(data: &[u8], at: usize) -> Result<[u8; N]> {
+    data.get(at..at + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ImmutableEncryptionError::video("Truncated MP4 box"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    // A full box body made of big-endian u32 fields, version and flags first
+    fn words(fields: &[u32]) -> Vec<u8> {
+        fields.iter().flat_map(|f| f.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn test_reads_video_samples_through_the_sample_table() -> Result<()> {
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        let mdat = mp4_box(b"mdat", b"aaaabbcccccc");
+        let data_start = (ftyp.len() + 8) as u32;
+
+        let mut entry = vec![0u8; 24];
+        entry.extend_from_slice(&1280u16.to_be_bytes());
+        entry.extend_from_slice(&720u16.to_be_bytes());
+        entry.extend_from_slice(&[0u8; 46]);
+        let mut stsd = words(&[0, 1]);
+        stsd.extend(mp4_box(b"avc1", &entry));
+
+        let stbl = [
+            mp4_box(b"stsd", &stsd),
+            mp4_box(b"stts", &words(&[0, 1, 3, 1])),
+            mp4_box(b"stsc", &words(&[0, 2, 1, 2, 1, 2, 1, 1])),
+            mp4_box(b"stsz", &words(&[0, 0, 3, 4, 2, 6])),
+            mp4_box(b"stco", &words(&[0, 2, data_start, data_start + 6])),
+            mp4_box(b"stss", &words(&[0, 1, 1])),
+        ]
+        .concat();
+        let video_mdia = [
+            mp4_box(b"hdlr", &[words(&[0, 0]), b"vide".to_vec()].concat()),
+            mp4_box(b"mdhd", &words(&[0, 0, 0, 30, 3])),
+            mp4_box(b"minf", &mp4_box(b"stbl", &stbl)),
+        ]
+        .concat();
+        let audio_mdia = mp4_box(b"hdlr", &[words(&[0, 0]), b"soun".to_vec()].concat());
+
+        let created = (MP4_EPOCH_OFFSET + 1_700_000_000) as u32;
+        let moov = [
+            mp4_box(b"mvhd", &words(&[0, created, created, 1000, 100])),
+            mp4_box(b"trak", &mp4_box(b"mdia", &audio_mdia)),
+            mp4_box(b"trak", &mp4_box(b"mdia", &video_mdia)),
+        ]
+        .concat();
+        let mut file = Cursor::new([ftyp, mdat, mp4_box(b"moov", &moov)].concat());
+
+        let track = VideoTrack::parse(&mut file)?;
+        assert_eq!(track.codec, "avc1");
+        assert_eq!((track.width, track.height, track.fps()), (1280, 720, 30));
+        assert_eq!(track.created_at, Some(1_700_000_000));
+
+        let samples: Vec<Vec<u8>> = track
+            .samples
+            .iter()
+            .map(|s| track.read_sample(&mut file, s))
+            .collect::<Result<_>>()?;
+        assert_eq!(
+            samples,
+            vec![b"aaaa".to_vec(), b"bb".to_vec(), b"cccccc".to_vec()]
+        );
+        let times: Vec<u64> = track.samples.iter().map(|s| s.decode_time).collect();
+        assert_eq!(times, vec![0, 1, 2]);
+        assert!(track.samples[0].is_sync && !track.samples[1].is_sync);
+
+        assert!(VideoTrack::parse(&mut Cursor::new(b"not an mp4".to_vec())).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,489 @@
+use async_trait::async_trait;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::{sleep, Duration};
+
+use crate::alerts::{AlertConfig, AlertSink};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::BlockchainAnchor;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub queue_capacity: usize, // events buffered per sink before it starts dropping
+    pub sinks: Vec<SinkConfig>,
+    #[serde(default)]
+    pub alerts: AlertConfig, // [notifications.alerts]: tamper and failure alerting
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    Log,
+    Webhook {
+        url: String,
+        secret: Option<String>, // signs bodies into X-Signature-SHA256
+        #[serde(default = "default_timeout_ms")]
+        timeout_ms: u64,
+        #[serde(default = "default_max_retries")]
+        max_retries: u32,
+        #[serde(default)]
+        events: Vec<EventKind>, // empty: every event
+    },
+    Amqp {
+        url: String,
+        exchange: String,
+        routing_key: String,
+    },
+    Kafka {
+        brokers: String,
+        topic: String,
+    },
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            queue_capacity: 1024,
+            sinks: vec![SinkConfig::Log],
+            alerts: AlertConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    FrameSealed,
+    BatchAnchored,
+    TamperingDetected,
+    VerificationCompleted,
+    PipelineFailed,
+    CorruptionDetected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    FrameSealed {
+        device_id: String,
+        sequence: u64,
+        timestamp: u64,
+        hash: String,
+    },
+    BatchAnchored {
+        frame_count: usize,
+        first_sequence: u64,
+        last_sequence: u64,
+        anchors: Vec<BlockchainAnchor>,
+    },
+    TamperingDetected {
+        evidence_id: String,
+        details: String,
+    },
+    VerificationCompleted {
+        evidence_id: String,
+        is_valid: bool,
+        frame_count: u64,
+    },
+    // A frame failed anchoring or storage with an error retrying can't fix
+    PipelineFailed {
+        stage: String,
+        device_id: String,
+        sequence: u64,
+        code: String,
+        error: String,
+    },
+    // A storage scrub finished with records it could not account for
+    CorruptionDetected {
+        scrub_id: String,
+        corrupt_frames: usize,
+        dangling_index_entries: usize,
+        unlinked_frames: usize,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::FrameSealed { .. } => EventKind::FrameSealed,
+            Event::BatchAnchored { .. } => EventKind::BatchAnchored,
+            Event::TamperingDetected { .. } => EventKind::TamperingDetected,
+            Event::VerificationCompleted { .. } => EventKind::VerificationCompleted,
+            Event::PipelineFailed { .. } => EventKind::PipelineFailed,
+            Event::CorruptionDetected { .. } => EventKind::CorruptionDetected,
+        }
+    }
+}
+
+// What sinks receive: the event plus a node-unique, increasing ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub event_id: u64,
+    pub emitted_at: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    fn name(&self) -> String;
+
+    fn accepts(&self, _kind: EventKind) -> bool {
+        true
+    }
+
+    async fn deliver(&self, event: &EventEnvelope) -> Result<()>;
+}
+
+// Fan-out of pipeline events. Publishing never blocks the pipeline; each
+// sink drains its own queue and a slow sink only loses its own events.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<EventEnvelope>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            tx: broadcast::channel(capacity.max(1)).0,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn publish(&self, event: Event) {
+        let envelope = EventEnvelope {
+            event_id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            emitted_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            event,
+        };
+        // No subscribers just means notifications are off
+        let _ = self.tx.send(envelope);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
+        self.tx.subscribe()
+    }
+
+    pub fn attach(&self, sink: Arc<dyn EventSink>) {
+        let mut rx = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(envelope) => {
+                        if !sink.accepts(envelope.event.kind()) {
+                            continue;
+                        }
+                        if let Err(e) = sink.deliver(&envelope).await {
+                            tracing::error!(
+                                "Sink {} failed to deliver event {}: {}",
+                                sink.name(),
+                                envelope.event_id,
+                                e
+                            );
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!("Sink {} dropped {} events", sink.name(), missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(NotificationConfig::default().queue_capacity)
+    }
+}
+
+pub async fn build_sinks(config: &NotificationConfig) -> Result<Vec<Arc<dyn EventSink>>> {
+    let mut sinks: Vec<Arc<dyn EventSink>> = Vec::new();
+
+    for sink in &config.sinks {
+        sinks.push(match sink {
+            SinkConfig::Log => Arc::new(LogSink),
+            SinkConfig::Webhook {
+                url,
+                secret,
+                timeout_ms,
+                max_retries,
+                events,
+            } => Arc::new(WebhookSink::new(
+                url.clone(),
+                secret.clone(),
+                Duration::from_millis(*timeout_ms),
+                *max_retries,
+                events.clone(),
+            )?),
+            #[cfg(feature = "amqp")]
+            SinkConfig::Amqp {
+                url,
+                exchange,
+                routing_key,
+            } => Arc::new(AmqpSink::connect(url, exchange.clone(), routing_key.clone()).await?),
+            #[cfg(not(feature = "amqp"))]
+            SinkConfig::Amqp { .. } => {
+                return Err(ImmutableEncryptionError::config(
+                    "AMQP sinks need the `amqp` feature",
+                ));
+            }
+            #[cfg(feature = "kafka")]
+            SinkConfig::Kafka { brokers, topic } => {
+                Arc::new(KafkaSink::new(brokers, topic.clone())?)
+            }
+            #[cfg(not(feature = "kafka"))]
+            SinkConfig::Kafka { .. } => {
+                return Err(ImmutableEncryptionError::config(
+                    "Kafka sinks need the `kafka` feature",
+                ));
+            }
+        });
+    }
+    if config.alerts.enabled {
+        sinks.push(Arc::new(AlertSink::new(&config.alerts)?));
+    }
+
+    Ok(sinks)
+}
+
+pub struct LogSink;
+
+#[async_trait]
+impl EventSink for LogSink {
+    fn name(&self) -> String {
+        "log".to_string()
+    }
+
+    async fn deliver(&self, event: &EventEnvelope) -> Result<()> {
+        tracing::info!(target: "notifications", "{}", serde_json::to_string(event)?);
+        Ok(())
+    }
+}
+
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    key: Option<hmac::Key>,
+    max_retries: u32,
+    events: Vec<EventKind>,
+}
+
+impl WebhookSink {
+    pub fn new(
+        url: String,
+        secret: Option<String>,
+        timeout: Duration,
+        max_retries: u32,
+        events: Vec<EventKind>,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(timeout).build()?,
+            url,
+            key: secret.map(|s| hmac::Key::new(hmac::HMAC_SHA256, s.as_bytes())),
+            max_retries,
+            events,
+        })
+    }
+
+    pub fn signature(&self, body: &[u8]) -> Option<String> {
+        self.key
+            .as_ref()
+            .map(|key| hex::encode(hmac::sign(key, body).as_ref()))
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook:{}", self.url)
+    }
+
+    fn accepts(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    async fn deliver(&self, event: &EventEnvelope) -> Result<()> {
+        let body = serde_json::to_vec(event)?;
+        let mut attempt = 0;
+
+        loop {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header("content-type", "application/json")
+                .header("x-event-id", event.event_id.to_string());
+            if let Some(signature) = self.signature(&body) {
+                request = request.header("x-signature-sha256", signature);
+            }
+
+            let error = match request.body(body.clone()).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => ImmutableEncryptionError::Network(format!(
+                    "webhook returned {}",
+                    response.status()
+                )),
+                Err(e) => e.into(),
+            };
+
+            attempt += 1;
+            if attempt > self.max_retries {
+                return Err(error);
+            }
+            sleep(Duration::from_millis(250 * 2u64.pow(attempt))).await;
+        }
+    }
+}
+
+#[cfg(feature = "amqp")]
+pub struct AmqpSink {
+    channel: lapin::Channel,
+    exchange: String,
+    routing_key: String,
+}
+
+#[cfg(feature = "amqp")]
+impl AmqpSink {
+    pub async fn connect(url: &str, exchange: String, routing_key: String) -> Result<Self> {
+        let connection =
+            lapin::Connection::connect(url, lapin::ConnectionProperties::default()).await?;
+        Ok(Self {
+            channel: connection.create_channel().await?,
+            exchange,
+            routing_key,
+        })
+    }
+}
+
+#[cfg(feature = "amqp")]
+#[async_trait]
+impl EventSink for AmqpSink {
+    fn name(&self) -> String {
+        format!("amqp:{}", self.exchange)
+    }
+
+    async fn deliver(&self, event: &EventEnvelope) -> Result<()> {
+        self.channel
+            .basic_publish(
+                &self.exchange,
+                &self.routing_key,
+                lapin::options::BasicPublishOptions::default(),
+                &serde_json::to_vec(event)?,
+                lapin::BasicProperties::default().with_content_type("application/json".into()),
+            )
+            .await?
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kafka")]
+pub struct KafkaSink {
+    producer: rdkafka::producer::FutureProducer,
+    topic: String,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaSink {
+    pub fn new(brokers: &str, topic: String) -> Result<Self> {
+        let producer = rdkafka::ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", "5000")
+            .create()?;
+        Ok(Self { producer, topic })
+    }
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait]
+impl EventSink for KafkaSink {
+    fn name(&self) -> String {
+        format!("kafka:{}", self.topic)
+    }
+
+    async fn deliver(&self, event: &EventEnvelope) -> Result<()> {
+        let payload = serde_json::to_vec(event)?;
+        let key = event.event_id.to_string();
+        self.producer
+            .send(
+                rdkafka::producer::FutureRecord::to(&self.topic)
+                    .payload(&payload)
+                    .key(&key),
+                Duration::from_secs(0),
+            )
+            .await
+            .map_err(|(e, _)| {
+                ImmutableEncryptionError::Network(format!("Kafka delivery failed: {}", e))
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Mutex;
+
+    struct RecordingSink(Mutex<Vec<EventEnvelope>>);
+
+    #[async_trait]
+    impl EventSink for RecordingSink {
+        fn name(&self) -> String {
+            "recording".to_string()
+        }
+
+        fn accepts(&self, kind: EventKind) -> bool {
+            kind != EventKind::FrameSealed
+        }
+
+        async fn deliver(&self, event: &EventEnvelope) -> Result<()> {
+            self.0.lock().await.push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bus_fans_out_filtered_events() {
+        let bus = EventBus::new(16);
+        let sink = Arc::new(RecordingSink(Mutex::new(Vec::new())));
+        bus.attach(sink.clone());
+
+        bus.publish(Event::FrameSealed {
+            device_id: "cam_1".to_string(),
+            sequence: 1,
+            timestamp: 1000,
+            hash: "abc".to_string(),
+        });
+        bus.publish(Event::TamperingDetected {
+            evidence_id: "session_1".to_string(),
+            details: "hash mismatch".to_string(),
+        });
+
+        sleep(Duration::from_millis(50)).await;
+        let delivered = sink.0.lock().await;
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].event_id, 2);
+
+        let json = serde_json::to_value(&delivered[0]).unwrap();
+        assert_eq!(json["type"], "tampering_detected");
+        assert_eq!(json["evidence_id"], "session_1");
+    }
+}
@@ -0,0 +1,659 @@
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+
+use crate::audit::AuditLog;
+use crate::crypto::EncryptionEngine;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::storage::DistributedStorage;
+use crate::{BlockchainAnchor, CustodyEntry, EncryptedFrame};
+
+pub const MJPEG_BOUNDARY: &str = "evidenceframe";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackConfig {
+    pub enabled: bool,
+    pub authorized_tokens: HashMap<String, String>, // sha256(token) hex -> investigator
+    pub live_poll_interval_ms: u64,
+    pub max_range_seconds: u64,
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            authorized_tokens: HashMap::new(),
+            live_poll_interval_ms: 1000,
+            max_range_seconds: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaybackQuery {
+    pub from: u64,
+    pub to: Option<u64>, // None follows the live recording
+    #[serde(default)]
+    pub approval_id: Option<String>, // needed when decryption requires approval
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaybackRequest {
+    pub device_id: String,
+    pub from: u64,
+    pub to: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaybackSession {
+    pub investigator: String,
+    pub request: PlaybackRequest,
+    pub custody_reference: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotFormat {
+    Jpeg,
+    Png,
+}
+
+impl SnapshotFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Jpeg => "image/jpeg",
+            SnapshotFormat::Png => "image/png",
+        }
+    }
+
+    pub(crate) fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(SnapshotFormat::Jpeg)
+        } else if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+            Some(SnapshotFormat::Png)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotProof {
+    pub frame_id: String,
+    pub device_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub chain_hash: String,
+    pub previous_hash: String,
+    pub ciphertext_sha256: String,
+    pub image_sha256: String,
+    pub previous_frame_linked: Option<bool>, // None when the predecessor isn't stored
+    pub anchors: Vec<BlockchainAnchor>,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub format: SnapshotFormat,
+    pub image: Vec<u8>,
+    pub proof: SnapshotProof,
+}
+
+// Frames picked for decryption: explicit IDs, or a device's time range
+#[derive(Debug, Clone)]
+pub enum FrameSelector {
+    Frames(Vec<String>),
+    Range {
+        device_id: String,
+        from: u64,
+        to: u64,
+    },
+}
+
+struct PlaybackCursor {
+    next_from: u64,
+    pending: VecDeque<String>,
+    delivered_at_cursor: HashSet<String>,
+    skipped: HashSet<String>, // Reported once as an error part, never retried
+    finished: bool,
+}
+
+pub struct PlaybackService {
+    config: PlaybackConfig,
+    engine: Arc<Mutex<EncryptionEngine>>,
+    storage: Arc<DistributedStorage>,
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl PlaybackService {
+    pub fn new(
+        config: PlaybackConfig,
+        engine: Arc<Mutex<EncryptionEngine>>,
+        storage: Arc<DistributedStorage>,
+    ) -> Self {
+        Self {
+            config,
+            engine,
+            storage,
+            audit: None,
+        }
+    }
+
+    // Also records every plaintext access in the node's audit log
+    pub fn with_audit_log(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    // The custody entry's action is `<action>:<target>`
+    async fn audit(&self, entry: &CustodyEntry) -> Result<()> {
+        if let Some(audit) = &self.audit {
+            let (action, target) = match entry.action.split_once(':') {
+                Some((action, target)) => (action, Some(target)),
+                None => (entry.action.as_str(), None),
+            };
+            audit.record(&entry.actor, None, action, target).await?;
+        }
+        Ok(())
+    }
+
+    pub fn authorize(&self, token: &str) -> Result<String> {
+        if !self.config.enabled {
+            return Err(ImmutableEncryptionError::ResourceUnavailable(
+                "Playback is disabled on this node".to_string(),
+            ));
+        }
+
+        let token_hash = hex::encode(Sha256::digest(token.as_bytes()));
+        self.config
+            .authorized_tokens
+            .get(&token_hash)
+            .cloned()
+            .ok_or_else(|| {
+                ImmutableEncryptionError::PermissionDenied(
+                    "Playback token is not authorized".to_string(),
+                )
+            })
+    }
+
+    pub async fn open(&self, token: &str, request: PlaybackRequest) -> Result<PlaybackSession> {
+        let investigator = self.authorize(token)?;
+        self.open_for(investigator, request).await
+    }
+
+    // Opens playback for an investigator already authenticated by the caller
+    pub async fn open_for(
+        &self,
+        investigator: String,
+        request: PlaybackRequest,
+    ) -> Result<PlaybackSession> {
+        if !self.config.enabled {
+            return Err(ImmutableEncryptionError::ResourceUnavailable(
+                "Playback is disabled on this node".to_string(),
+            ));
+        }
+
+        if let Some(to) = request.to {
+            if to < request.from {
+                return Err(ImmutableEncryptionError::video(
+                    "Playback range ends before it starts",
+                ));
+            }
+            if to - request.from > self.config.max_range_seconds {
+                return Err(ImmutableEncryptionError::Video(format!(
+                    "Playback range exceeds {} seconds",
+                    self.config.max_range_seconds
+                )));
+            }
+        }
+
+        // Every playback is recorded in the device's chain of custody
+        let entry = custody_entry(
+            &*self.engine.lock().await,
+            &investigator,
+            playback_action(&request),
+        )?;
+        let custody_reference = self
+            .storage
+            .append_custody_entry(&request.device_id, &entry)
+            .await?;
+        self.audit(&entry).await?;
+
+        tracing::info!(
+            "Playback opened by {} for device {} from {} to {:?}",
+            investigator,
+            request.device_id,
+            request.from,
+            request.to
+        );
+
+        Ok(PlaybackSession {
+            investigator,
+            request,
+            custody_reference,
+        })
+    }
+
+    pub async fn extract_snapshot(
+        &self,
+        token: &str,
+        frame_id: &str,
+        format: Option<SnapshotFormat>,
+    ) -> Result<Snapshot> {
+        let investigator = self.authorize(token)?;
+        self.extract_snapshot_for(&investigator, frame_id, format)
+            .await
+    }
+
+    pub async fn extract_snapshot_for(
+        &self,
+        investigator: &str,
+        frame_id: &str,
+        format: Option<SnapshotFormat>,
+    ) -> Result<Snapshot> {
+        if !self.config.enabled {
+            return Err(ImmutableEncryptionError::ResourceUnavailable(
+                "Playback is disabled on this node".to_string(),
+            ));
+        }
+
+        let frame = self.storage.retrieve_with_fallback(frame_id).await?;
+        let plaintext = self.engine.lock().await.decrypt_frame_data(&frame)?;
+
+        let stored_format = SnapshotFormat::detect(&plaintext).ok_or_else(|| {
+            ImmutableEncryptionError::Video(format!(
+                "Frame {} is not a still image; inter-coded video must be decoded first",
+                frame_id
+            ))
+        })?;
+        let format = format.unwrap_or(stored_format);
+        let image = convert_still(plaintext, stored_format, format)?;
+
+        let previous_frame_linked = self.check_previous_link(&frame).await;
+
+        let mut proof = SnapshotProof {
+            frame_id: frame_id.to_string(),
+            device_id: frame.device_id.clone(),
+            sequence: frame.sequence,
+            timestamp: frame.timestamp,
+            chain_hash: frame.hash.clone(),
+            previous_hash: frame.previous_hash.clone(),
+            ciphertext_sha256: hex::encode(Sha256::digest(&frame.ciphertext)),
+            image_sha256: hex::encode(Sha256::digest(&image)),
+            previous_frame_linked,
+            anchors: frame.blockchain_anchors.clone(),
+            signature: String::new(),
+        };
+        proof.signature = self.engine.lock().await.sign(&serde_json::to_vec(&proof)?);
+
+        let entry = custody_entry(
+            &*self.engine.lock().await,
+            investigator,
+            format!("snapshot:{}", frame_id),
+        )?;
+        self.storage
+            .append_custody_entry(&frame.device_id, &entry)
+            .await?;
+        self.audit(&entry).await?;
+
+        Ok(Snapshot {
+            format,
+            image,
+            proof,
+        })
+    }
+
+    // Resolves a selector to frame IDs and records the decryption in the chain
+    // of custody of every device involved, before any plaintext exists
+    pub async fn authorize_decryption(
+        &self,
+        investigator: &str,
+        selector: &FrameSelector,
+    ) -> Result<Vec<String>> {
+        let (frame_ids, devices) = match selector {
+            FrameSelector::Frames(frame_ids) => {
+                let mut devices = Vec::new();
+                for frame_id in frame_ids {
+                    let frame = self.storage.retrieve_with_fallback(frame_id).await?;
+                    if !devices.contains(&frame.device_id) {
+                        devices.push(frame.device_id);
+                    }
+                }
+                (frame_ids.clone(), devices)
+            }
+            FrameSelector::Range {
+                device_id,
+                from,
+                to,
+            } => {
+                if to < from {
+                    return Err(ImmutableEncryptionError::video(
+                        "Decryption range ends before it starts",
+                    ));
+                }
+                let frame_ids = self
+                    .storage
+                    .list_device_frames(device_id, *from, *to)
+                    .await?;
+                (frame_ids, vec![device_id.clone()])
+            }
+        };
+
+        let entry = custody_entry(
+            &*self.engine.lock().await,
+            investigator,
+            decryption_action(selector),
+        )?;
+        for device_id in &devices {
+            self.storage.append_custody_entry(device_id, &entry).await?;
+        }
+        self.audit(&entry).await?;
+        tracing::info!(
+            "Decryption of {} frames authorized for {}",
+            frame_ids.len(),
+            investigator
+        );
+
+        Ok(frame_ids)
+    }
+
+    pub async fn decrypt_frame(&self, frame_id: &str) -> Result<(EncryptedFrame, Vec<u8>)> {
+        let frame = self.storage.retrieve_with_fallback(frame_id).await?;
+        let plaintext = self.engine.lock().await.decrypt_frame_data(&frame)?;
+        Ok((frame, plaintext))
+    }
+
+    async fn check_previous_link(&self, frame: &EncryptedFrame) -> Option<bool> {
+        if frame.sequence == 0 {
+            return None;
+        }
+
+        let candidates = self
+            .storage
+            .list_device_frames(&frame.device_id, 0, frame.timestamp)
+            .await
+            .ok()?;
+
+        for frame_key in candidates.iter().rev() {
+            let candidate = self.storage.retrieve_with_fallback(frame_key).await.ok()?;
+            if candidate.sequence + 1 == frame.sequence {
+                return Some(candidate.hash == frame.previous_hash);
+            }
+        }
+
+        None
+    }
+
+    pub fn mjpeg_stream(
+        &self,
+        session: PlaybackSession,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + Send + 'static {
+        let engine = self.engine.clone();
+        let storage = self.storage.clone();
+        let poll_interval = Duration::from_millis(self.config.live_poll_interval_ms);
+        let request = session.request;
+
+        let cursor = PlaybackCursor {
+            next_from: request.from,
+            pending: VecDeque::new(),
+            delivered_at_cursor: HashSet::new(),
+            skipped: HashSet::new(),
+            finished: false,
+        };
+
+        stream::unfold(cursor, move |mut cursor| {
+            let engine = engine.clone();
+            let storage = storage.clone();
+            let request = request.clone();
+
+            async move {
+                loop {
+                    if cursor.finished {
+                        return None;
+                    }
+
+                    if let Some(frame_key) = cursor.pending.pop_front() {
+                        let frame = match storage.retrieve_with_fallback(&frame_key).await {
+                            Ok(frame) => frame,
+                            Err(e) => {
+                                cursor.skipped.insert(frame_key.clone());
+                                return Some((Ok(mjpeg_error_part(&frame_key, &e)), cursor));
+                            }
+                        };
+
+                        if frame.timestamp > cursor.next_from {
+                            cursor.next_from = frame.timestamp;
+                            cursor.delivered_at_cursor.clear();
+                        }
+                        cursor.delivered_at_cursor.insert(frame_key.clone());
+
+                        // A frame that can't be shown is reported in the stream
+                        // and skipped rather than ending playback
+                        let still = engine.lock().await.decrypt_frame_data(&frame);
+                        let part = match still.and_then(jpeg_still) {
+                            Ok(jpeg) => mjpeg_part(&jpeg),
+                            Err(e) => {
+                                tracing::warn!("Playback skipped frame {}: {}", frame_key, e);
+                                mjpeg_error_part(&frame_key, &e)
+                            }
+                        };
+
+                        return Some((Ok(part), cursor));
+                    }
+
+                    let upper = request.to.unwrap_or(u64::MAX);
+                    match storage
+                        .list_device_frames(&request.device_id, cursor.next_from, upper)
+                        .await
+                    {
+                        Ok(frame_keys) => {
+                            let delivered = &cursor.delivered_at_cursor;
+                            let skipped = &cursor.skipped;
+                            let fresh: Vec<String> = frame_keys
+                                .into_iter()
+                                .filter(|key| !delivered.contains(key) && !skipped.contains(key))
+                                .collect();
+                            cursor.pending.extend(fresh);
+                        }
+                        Err(e) => {
+                            cursor.finished = true;
+                            return Some((Err(e), cursor));
+                        }
+                    }
+
+                    if cursor.pending.is_empty() {
+                        if request.to.is_some() {
+                            return None; // Bounded range fully delivered
+                        }
+                        sleep(poll_interval).await;
+                    }
+                }
+            }
+        })
+    }
+}
+
+pub fn mjpeg_part(data: &[u8]) -> Vec<u8> {
+    multipart_part("image/jpeg", data)
+}
+
+// Stands in for a frame that couldn't be read, decrypted or shown as JPEG
+pub fn mjpeg_error_part(frame_id: &str, error: &ImmutableEncryptionError) -> Vec<u8> {
+    let body = serde_json::json!({
+        "frame_id": frame_id,
+        "error": error.to_string(),
+    });
+    multipart_part("application/json", body.to_string().as_bytes())
+}
+
+fn multipart_part(content_type: &str, data: &[u8]) -> Vec<u8> {
+    let mut part = format!(
+        "--{}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+        MJPEG_BOUNDARY,
+        content_type,
+        data.len()
+    )
+    .into_bytes();
+    part.extend_from_slice(data);
+    part.extend_from_slice(b"\r\n");
+    part
+}
+
+// MJPEG carries JPEG stills only: PNG is re-encoded, anything else refused
+fn jpeg_still(data: Vec<u8>) -> Result<Vec<u8>> {
+    match SnapshotFormat::detect(&data) {
+        Some(format) => convert_still(data, format, SnapshotFormat::Jpeg),
+        None => Err(ImmutableEncryptionError::video(
+            "Frame is not a JPEG or PNG still and can't be played as MJPEG",
+        )),
+    }
+}
+
+#[cfg(feature = "video")]
+fn convert_still(data: Vec<u8>, from: SnapshotFormat, to: SnapshotFormat) -> Result<Vec<u8>> {
+    if from == to {
+        return Ok(data);
+    }
+
+    let image_format = |f: SnapshotFormat| match f {
+        SnapshotFormat::Jpeg => image::ImageFormat::Jpeg,
+        SnapshotFormat::Png => image::ImageFormat::Png,
+    };
+
+    let decoded = image::load_from_memory_with_format(&data, image_format(from))?;
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    decoded.write_to(&mut encoded, image_format(to))?;
+    Ok(encoded.into_inner())
+}
+
+#[cfg(not(feature = "video"))]
+fn convert_still(data: Vec<u8>, from: SnapshotFormat, to: SnapshotFormat) -> Result<Vec<u8>> {
+    if from == to {
+        Ok(data)
+    } else {
+        Err(ImmutableEncryptionError::Video(format!(
+            "Converting {:?} snapshots to {:?} requires the `video` feature",
+            from, to
+        )))
+    }
+}
+
+// Every plaintext access is signed into the chain of custody like a custody
+// transfer: with the node's key, over `{timestamp}|{actor}|{action}`
+fn custody_entry(
+    engine: &EncryptionEngine,
+    investigator: &str,
+    action: String,
+) -> Result<CustodyEntry> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let signature = engine.sign(format!("{}|{}|{}", timestamp, investigator, action).as_bytes());
+
+    Ok(CustodyEntry {
+        timestamp,
+        actor: investigator.to_string(),
+        action,
+        signature,
+        blockchain_reference: String::new(),
+    })
+}
+
+fn playback_action(request: &PlaybackRequest) -> String {
+    match request.to {
+        Some(to) => format!("playback:{}:{}-{}", request.device_id, request.from, to),
+        None => format!("live_playback:{}:{}-", request.device_id, request.from),
+    }
+}
+
+fn decryption_action(selector: &FrameSelector) -> String {
+    match selector {
+        FrameSelector::Frames(frame_ids) => format!("decrypt:{}", frame_ids.join(",")),
+        FrameSelector::Range {
+            device_id,
+            from,
+            to,
+        } => format!("decrypt:{}:{}-{}", device_id, from, to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+
+    #[test]
+    fn test_mjpeg_part_framing() {
+        let part = mjpeg_part(&[0xFF, 0xD8, 0xFF, 0xD9]);
+        let header = format!(
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: 4\r\n\r\n",
+            MJPEG_BOUNDARY
+        );
+
+        assert!(part.starts_with(header.as_bytes()));
+        assert!(part.ends_with(&[0xFF, 0xD9, b'\r', b'\n']));
+    }
+
+    #[test]
+    fn test_mjpeg_error_part_and_jpeg_stills() {
+        let error = ImmutableEncryptionError::video("bad frame");
+        let part = String::from_utf8(mjpeg_error_part("frame_1", &error)).unwrap();
+        assert!(part.contains("Content-Type: application/json\r\n"));
+        assert!(part.contains("\"frame_id\":\"frame_1\""));
+
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0xFF, 0xD9];
+        assert_eq!(jpeg_still(jpeg.clone()).unwrap(), jpeg);
+        assert!(jpeg_still(vec![0, 0, 0, 1, 0x67]).is_err()); // H.264 NAL
+        assert!(jpeg_still(vec![128u8; 12]).is_err()); // Raw RGB
+    }
+
+    #[test]
+    fn test_snapshot_format_detection() {
+        assert_eq!(
+            SnapshotFormat::detect(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(SnapshotFormat::Jpeg)
+        );
+        assert_eq!(
+            SnapshotFormat::detect(b"\x89PNG\r\n\x1a\n"),
+            Some(SnapshotFormat::Png)
+        );
+        assert_eq!(SnapshotFormat::detect(&[0, 0, 0, 1, 0x67]), None); // H.264 NAL
+    }
+
+    #[test]
+    fn test_playback_custody_entry() -> Result<()> {
+        let request = PlaybackRequest {
+            device_id: "drone_001".to_string(),
+            from: 1000,
+            to: Some(2000),
+        };
+
+        let engine = EncryptionEngine::new(CryptoConfig::software(vec![3u8; 32], 60))?;
+        let entry = custody_entry(&engine, "det. smith", playback_action(&request))?;
+
+        assert_eq!(entry.actor, "det. smith");
+        assert_eq!(entry.action, "playback:drone_001:1000-2000");
+        let signed =
+            |entry: &CustodyEntry| format!("{}|{}|{}", entry.timestamp, entry.actor, entry.action);
+        assert!(engine.verify_signature(signed(&entry).as_bytes(), &entry.signature));
+
+        // Only the node's key produces it
+        let other = EncryptionEngine::new(CryptoConfig::software(vec![4u8; 32], 60))?;
+        assert!(!other.verify_signature(signed(&entry).as_bytes(), &entry.signature));
+
+        let selector = FrameSelector::Range {
+            device_id: "drone_001".to_string(),
+            from: 1000,
+            to: 2000,
+        };
+        let entry = custody_entry(&engine, "det. smith", decryption_action(&selector))?;
+        assert_eq!(entry.action, "decrypt:drone_001:1000-2000");
+        assert!(engine.verify_signature(signed(&entry).as_bytes(), &entry.signature));
+
+        Ok(())
+    }
+}
@@ -1,33 +1,111 @@
-use anyhow::{anyhow, Result};
-use pqcrypto_kyber::{kyber1024, U32};
-use pqcrypto_traits::kem as pqkem;
+use pqcrypto_falcon::falcon1024;
+use pqcrypto_kyber::kyber1024;
+use pqcrypto_ntru::ntruhps4096821;
+use pqcrypto_traits::kem::{Ciphertext as _, SharedSecret as _};
+use pqcrypto_traits::sign::DetachedSignature as _;
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
 
-use crate::{BlockchainAnchor, EncryptedFrame};
+use crate::crypto::{self, Cipher};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::EncryptedFrame;
+
+const HYBRID_KEM_SALT: &[u8] = b"immutable-encryption 2024 hybrid kem";
+const KEY_CONFIRMATION_CONTEXT: &str = "immutable-encryption 2024 hybrid kem confirmation";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuantumResistantConfig {
     pub enabled: bool,
     pub algorithm: QuantumAlgorithm,
     pub key_rotation_interval_hours: u64,
-    pub hybrid_mode: bool,                // Combine classical + quantum
+    pub hybrid_mode: bool, // X25519 alongside the post-quantum KEM
     pub post_quantum_only_threshold: u64, // When to use only post-quantum
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Kyber1024 and NTRU pick the KEM; Falcon signs each encapsulation with
+// Falcon-1024 on top of a Kyber1024 KEM
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QuantumAlgorithm {
     Kyber1024,
-    NTRU,
+    NTRU, // NTRU-HPS-4096-821
     Dilithium,
     Falcon,
 }
 
+// The post-quantum KEM behind an engine's encapsulations
+enum KemKeys {
+    Kyber1024(kyber1024::PublicKey, kyber1024::SecretKey),
+    Ntru(ntruhps4096821::PublicKey, ntruhps4096821::SecretKey),
+}
+
+impl KemKeys {
+    fn generate(algorithm: &QuantumAlgorithm) -> Self {
+        match algorithm {
+            QuantumAlgorithm::NTRU => {
+                let (public_key, secret_key) = ntruhps4096821::keypair();
+                Self::Ntru(public_key, secret_key)
+            }
+            _ => {
+                let (public_key, secret_key) = kyber1024::keypair();
+                Self::Kyber1024(public_key, secret_key)
+            }
+        }
+    }
+
+    // (shared secret, ciphertext)
+    fn encapsulate(&self) -> (Vec<u8>, Vec<u8>) {
+        match self {
+            Self::Kyber1024(public_key, _) => {
+                let (secret, ciphertext) = kyber1024::encapsulate(public_key);
+                (secret.as_bytes().to_vec(), ciphertext.as_bytes().to_vec())
+            }
+            Self::Ntru(public_key, _) => {
+                let (secret, ciphertext) = ntruhps4096821::encapsulate(public_key);
+                (secret.as_bytes().to_vec(), ciphertext.as_bytes().to_vec())
+            }
+        }
+    }
+
+    fn decapsulate(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let invalid = |e: pqcrypto_traits::Error| {
+            ImmutableEncryptionError::Crypto(format!("Invalid KEM ciphertext: {}", e))
+        };
+        Ok(match self {
+            Self::Kyber1024(_, secret_key) => {
+                let ciphertext = kyber1024::Ciphertext::from_bytes(ciphertext).map_err(invalid)?;
+                kyber1024::decapsulate(&ciphertext, secret_key)
+                    .as_bytes()
+                    .to_vec()
+            }
+            Self::Ntru(_, secret_key) => {
+                let ciphertext =
+                    ntruhps4096821::Ciphertext::from_bytes(ciphertext).map_err(invalid)?;
+                ntruhps4096821::decapsulate(&ciphertext, secret_key)
+                    .as_bytes()
+                    .to_vec()
+            }
+        })
+    }
+}
+
+// One rotation period's recipient keys; the X25519 pair is only used in
+// hybrid mode and the Falcon pair only with `QuantumAlgorithm::Falcon`
+struct RecipientKeys {
+    kem: KemKeys,
+    x25519_secret: StaticSecret,
+    x25519_public: X25519PublicKey,
+    falcon: Option<(falcon1024::PublicKey, falcon1024::SecretKey)>,
+}
+
 pub struct QuantumCryptoEngine {
     config: QuantumResistantConfig,
-    key_pairs: HashMap<u64, (pqcrypto_kyber::PublicKey, pqcrypto_kyber::SecretKey)>,
+    key_pairs: HashMap<u64, RecipientKeys>,
     current_key_id: u64,
+    rng: SystemRandom,
 }
 
 impl QuantumCryptoEngine {
@@ -36,6 +114,7 @@ impl QuantumCryptoEngine {
             config,
             key_pairs: HashMap::new(),
             current_key_id: 0,
+            rng: SystemRandom::new(),
         };
 
         // Initialize first key pair
@@ -48,10 +127,21 @@ impl QuantumCryptoEngine {
         let current_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
         // Generate new quantum-resistant key pair
-        let (public_key, secret_key) = kyber1024::keypair();
+        let kem = KemKeys::generate(&self.config.algorithm);
+        let falcon = (self.config.algorithm == QuantumAlgorithm::Falcon).then(falcon1024::keypair);
+        let x25519_secret = StaticSecret::from(random_key(&self.rng)?);
+        let x25519_public = X25519PublicKey::from(&x25519_secret);
         let key_id = current_time / (self.config.key_rotation_interval_hours * 3600);
 
-        self.key_pairs.insert(key_id, (public_key, secret_key));
+        self.key_pairs.insert(
+            key_id,
+            RecipientKeys {
+                kem,
+                x25519_secret,
+                x25519_public,
+                falcon,
+            },
+        );
         self.current_key_id = key_id;
 
         // Clean up old keys (keep last 2 for smooth transition)
@@ -65,124 +155,136 @@ impl QuantumCryptoEngine {
         Ok(())
     }
 
+    // Seals `data` under the configured KEM's shared secret. In hybrid mode an
+    // ephemeral X25519 exchange runs alongside and both secrets go through
+    // HKDF, so the data stays sealed as long as either primitive holds.
     pub fn encapsulate(&self, data: &[u8]) -> Result<QuantumEncapsulation> {
         let current_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
         let key_id = current_time / (self.config.key_rotation_interval_hours * 3600);
 
-        let key_pair = self
+        let keys = self
             .key_pairs
             .get(&key_id)
             .or_else(|| self.key_pairs.get(&self.current_key_id))
-            .ok_or_else(|| anyhow!("No quantum key available"))?;
-
-        // Generate encapsulated key and ciphertext
-        let (ciphertext, shared_secret) = kyber1024::encapsulate(&key_pair.0);
+            .ok_or_else(|| ImmutableEncryptionError::crypto("No quantum key available"))?;
+
+        let (post_quantum_secret, quantum_ciphertext) = keys.kem.encapsulate();
+        let (classical_secret, classical_ciphertext, recipient) = if self.config.hybrid_mode {
+            let ephemeral = StaticSecret::from(random_key(&self.rng)?);
+            let share = X25519PublicKey::from(&ephemeral);
+            let shared_secret = ephemeral.diffie_hellman(&keys.x25519_public);
+            (
+                shared_secret.as_bytes().to_vec(),
+                share.as_bytes().to_vec(),
+                keys.x25519_public.as_bytes().to_vec(),
+            )
+        } else {
+            (Vec::new(), Vec::new(), Vec::new())
+        };
 
-        // Encrypt data with shared secret using AES-GCM
-        let (encrypted_data, nonce) = self.encrypt_with_quantum_secret(data, &shared_secret)?;
+        let key = combine_secrets(
+            &classical_secret,
+            &post_quantum_secret,
+            &[&classical_ciphertext, &recipient, &quantum_ciphertext],
+        )?;
+        let (ciphertext, nonce) = crypto::seal_with(Cipher::Aes256Gcm, &key, data, &self.rng)?;
 
-        Ok(QuantumEncapsulation {
+        let mut encapsulation = QuantumEncapsulation {
             key_id,
-            ciphertext: ciphertext.to_vec(),
-            quantum_ciphertext: ciphertext.to_vec(),
+            ciphertext,
+            quantum_ciphertext,
+            classical_ciphertext,
             nonce,
-            algorithm: QuantumAlgorithm::Kyber1024,
+            algorithm: self.config.algorithm.clone(),
             timestamp: current_time,
-            quantum_signature: self.generate_quantum_signature(&shared_secret)?,
-        })
+            quantum_signature: key_confirmation(&key),
+        };
+        if let Some((_, secret_key)) = &keys.falcon {
+            let content = encapsulation.signed_content()?;
+            encapsulation.quantum_signature = falcon1024::detached_sign(&content, secret_key)
+                .as_bytes()
+                .to_vec();
+        }
+        Ok(encapsulation)
     }
 
     pub fn decapsulate(&self, encapsulation: &QuantumEncapsulation) -> Result<Vec<u8>> {
-        let key_pair = self
-            .key_pairs
-            .get(&encapsulation.key_id)
-            .ok_or_else(|| anyhow!("Quantum key not found for ID {}", encapsulation.key_id))?;
-
-        let ciphertext = pqcrypto_kyber::Ciphertext::from_slice(&encapsulation.quantum_ciphertext);
+        let keys = self.key_pairs.get(&encapsulation.key_id).ok_or_else(|| {
+            ImmutableEncryptionError::Crypto(format!(
+                "Quantum key not found for ID {}",
+                encapsulation.key_id
+            ))
+        })?;
+        if encapsulation.algorithm != self.config.algorithm {
+            return Err(ImmutableEncryptionError::Crypto(format!(
+                "Encapsulated with {:?}, but this engine uses {:?}",
+                encapsulation.algorithm, self.config.algorithm
+            )));
+        }
 
-        // Recover shared secret
-        let shared_secret = kyber1024::decapsulate(ciphertext, &key_pair.1);
+        // Falcon signatures are checked before anything is decapsulated
+        if let Some((public_key, _)) = &keys.falcon {
+            let signature =
+                falcon1024::DetachedSignature::from_bytes(&encapsulation.quantum_signature)
+                    .map_err(|e| {
+                        ImmutableEncryptionError::Crypto(format!("Invalid Falcon signature: {}", e))
+                    })?;
+            falcon1024::verify_detached_signature(
+                &signature,
+                &encapsulation.signed_content()?,
+                public_key,
+            )
+            .map_err(|_| ImmutableEncryptionError::crypto("Invalid quantum signature"))?;
+        }
+        let post_quantum_secret = keys.kem.decapsulate(&encapsulation.quantum_ciphertext)?;
+
+        // In hybrid mode a post-quantum-only encapsulation is refused rather than
+        // opened, or stripping the X25519 share would downgrade it
+        let share = &encapsulation.classical_ciphertext;
+        let (classical_secret, recipient) = if share.is_empty() {
+            if self.config.hybrid_mode {
+                return Err(ImmutableEncryptionError::crypto(
+                    "Hybrid mode requires an X25519 share",
+                ));
+            }
+            (Vec::new(), Vec::new())
+        } else {
+            let share = <[u8; 32]>::try_from(share.as_slice())
+                .map_err(|_| ImmutableEncryptionError::crypto("Invalid X25519 share"))?;
+            let shared_secret = keys
+                .x25519_secret
+                .diffie_hellman(&X25519PublicKey::from(share));
+            if !shared_secret.was_contributory() {
+                return Err(ImmutableEncryptionError::crypto(
+                    "X25519 share is a low-order point",
+                ));
+            }
+            (
+                shared_secret.as_bytes().to_vec(),
+                keys.x25519_public.as_bytes().to_vec(),
+            )
+        };
 
-        // Verify quantum signature
-        if !self.verify_quantum_signature(&shared_secret, &encapsulation.quantum_signature)? {
-            return Err(anyhow!("Invalid quantum signature"));
+        let key = combine_secrets(
+            &classical_secret,
+            &post_quantum_secret,
+            &[share, &recipient, &encapsulation.quantum_ciphertext],
+        )?;
+        if keys.falcon.is_none() && encapsulation.quantum_signature != key_confirmation(&key) {
+            return Err(ImmutableEncryptionError::crypto(
+                "Invalid quantum signature",
+            ));
         }
 
-        // Decrypt data
-        self.decrypt_with_quantum_secret(
+        crypto::open_with(
+            Cipher::Aes256Gcm,
+            &key,
             &encapsulation.ciphertext,
             &encapsulation.nonce,
-            &shared_secret,
         )
     }
 
-    fn encrypt_with_quantum_secret(
-        &self,
-        data: &[u8],
-        secret: &[u8],
-    ) -> Result<(Vec<u8>, Vec<u8>)> {
-        use ring::aead::{LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
-        use ring::rand::{SecureRandom, SystemRandom};
-
-        // Derive AES key from quantum secret
-        let aes_key = blake3::hash(secret).as_bytes();
-
-        let unbound_key = UnboundKey::new(&AES_256_GCM, &aes_key[..32])
-            .map_err(|e| anyhow!("Failed to create AES key: {}", e))?;
-        let less_safe_key = LessSafeKey::new(unbound_key);
-
-        let mut nonce_bytes = [0u8; 12];
-        SystemRandom::new().fill(&mut nonce_bytes)?;
-        let nonce = Nonce::assume_unique_for_key(nonce_bytes);
-
-        let mut ciphertext = data.to_vec();
-        less_safe_key
-            .seal_in_place_append_tag(nonce, &mut ciphertext)
-            .map_err(|e| anyhow!("Encryption failed: {}", e))?;
-
-        Ok((ciphertext, nonce_bytes.to_vec()))
-    }
-
-    fn decrypt_with_quantum_secret(
-        &self,
-        ciphertext: &[u8],
-        nonce: &[u8],
-        secret: &[u8],
-    ) -> Result<Vec<u8>> {
-        use ring::aead::{LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
-
-        let aes_key = blake3::hash(secret).as_bytes();
-
-        let unbound_key = UnboundKey::new(&AES_256_GCM, &aes_key[..32])
-            .map_err(|e| anyhow!("Failed to create AES key: {}", e))?;
-        let less_safe_key = LessSafeKey::new(unbound_key);
-
-        let nonce = Nonce::assume_unique_for_key(<[u8; 12]>::try_from(nonce)?);
-
-        let mut plaintext = ciphertext.to_vec();
-        less_safe_key
-            .open_in_place(nonce, &mut plaintext)
-            .map_err(|e| anyhow!("Decryption failed: {}", e))?;
-
-        // Remove authentication tag
-        plaintext.truncate(plaintext.len() - 16);
-
-        Ok(plaintext)
-    }
-
-    fn generate_quantum_signature(&self, secret: &[u8]) -> Result<Vec<u8>> {
-        // Create quantum-resistant signature using Dilithium
-        // For now, we'll use BLAKE3 as a mock signature
-        let signature = blake3::hash(secret);
-        Ok(signature.as_bytes().to_vec())
-    }
-
-    fn verify_quantum_signature(&self, secret: &[u8], signature: &[u8]) -> Result<bool> {
-        let expected_signature = blake3::hash(secret);
-        Ok(signature == expected_signature.as_bytes())
-    }
-
     pub fn create_hybrid_encryption(&self, frame: &EncryptedFrame) -> Result<HybridEncryptedFrame> {
         // Serialize the original frame
         let serialized_frame = serde_json::to_vec(frame)?;
@@ -212,10 +314,10 @@ impl QuantumCryptoEngine {
             }
             Err(e) => {
                 // Fallback to classical verification only (can't decrypt without quantum)
-                Err(anyhow!(
+                Err(ImmutableEncryptionError::Crypto(format!(
                     "Quantum decryption failed: {}. Classical backup only.",
                     e
-                ))
+                )))
             }
         }
     }
@@ -240,15 +342,59 @@ impl QuantumCryptoEngine {
     }
 }
 
+// HKDF-SHA256 over the X25519 shared secret, empty outside hybrid mode, and
+// Kyber's, bound to the ciphertexts and the recipient's X25519 key so neither
+// half can be swapped for another
+fn combine_secrets(
+    classical: &[u8],
+    post_quantum: &[u8],
+    transcript: &[&[u8]],
+) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, HYBRID_KEM_SALT)
+        .extract(&[classical, post_quantum].concat())
+        .expand(transcript, hkdf::HKDF_SHA256)?
+        .fill(&mut key)?;
+    Ok(key)
+}
+
+// Published with the encapsulation, so it must not reveal the key itself
+fn key_confirmation(key: &[u8; 32]) -> Vec<u8> {
+    blake3::derive_key(KEY_CONFIRMATION_CONTEXT, key).to_vec()
+}
+
+fn random_key(rng: &SystemRandom) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    rng.fill(&mut key)?;
+    Ok(key)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuantumEncapsulation {
     pub key_id: u64,
     pub ciphertext: Vec<u8>,
     pub quantum_ciphertext: Vec<u8>,
+    #[serde(default)]
+    pub classical_ciphertext: Vec<u8>, // ephemeral X25519 key; empty outside hybrid mode
     pub nonce: Vec<u8>,
     pub algorithm: QuantumAlgorithm,
     pub timestamp: u64,
-    pub quantum_signature: Vec<u8>,
+    pub quantum_signature: Vec<u8>, // Falcon-1024 signature, else a key confirmation
+}
+
+impl QuantumEncapsulation {
+    // Everything a Falcon signature covers
+    fn signed_content(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(
+            self.key_id,
+            &self.ciphertext,
+            &self.quantum_ciphertext,
+            &self.classical_ciphertext,
+            &self.nonce,
+            &self.algorithm,
+            self.timestamp,
+        ))?)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -453,6 +599,71 @@ mod tests {
         let decrypted = engine.decapsulate(&encapsulation)?;
         assert_eq!(decrypted, test_data);
 
+        // Both halves of the hybrid exchange are needed, and neither can be
+        // stripped or swapped
+        assert_eq!(encapsulation.classical_ciphertext.len(), 32);
+        let mut stripped = encapsulation.clone();
+        stripped.classical_ciphertext.clear();
+        assert!(engine.decapsulate(&stripped).is_err());
+        let mut swapped = encapsulation.clone();
+        swapped.classical_ciphertext = X25519PublicKey::from(&StaticSecret::from([5; 32]))
+            .as_bytes()
+            .to_vec();
+        assert!(engine.decapsulate(&swapped).is_err());
+        let mut low_order = encapsulation.clone();
+        low_order.classical_ciphertext = vec![0; 32];
+        assert!(engine.decapsulate(&low_order).is_err());
+        assert_ne!(encapsulation.quantum_signature, encapsulation.ciphertext);
+
+        // Without hybrid mode only Kyber1024 is used
+        let kyber_only = QuantumCryptoEngine::new(QuantumResistantConfig {
+            hybrid_mode: false,
+            ..engine.config.clone()
+        })?;
+        let encapsulation = kyber_only.encapsulate(test_data)?;
+        assert!(encapsulation.classical_ciphertext.is_empty());
+        assert_eq!(kyber_only.decapsulate(&encapsulation)?, test_data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ntru_and_falcon_change_the_encapsulation() -> Result<()> {
+        let engine = |algorithm| {
+            QuantumCryptoEngine::new(QuantumResistantConfig {
+                enabled: true,
+                algorithm,
+                key_rotation_interval_hours: 24,
+                hybrid_mode: true,
+                post_quantum_only_threshold: 10,
+            })
+        };
+        let (kyber, ntru, falcon) = (
+            engine(QuantumAlgorithm::Kyber1024)?,
+            engine(QuantumAlgorithm::NTRU)?,
+            engine(QuantumAlgorithm::Falcon)?,
+        );
+        let test_data = b"evidence";
+
+        // NTRU-HPS-4096-821 ciphertexts are 1230 bytes, Kyber1024's 1568
+        let sealed = ntru.encapsulate(test_data)?;
+        assert_eq!(sealed.algorithm, QuantumAlgorithm::NTRU);
+        assert_eq!(sealed.quantum_ciphertext.len(), 1230);
+        assert_eq!(ntru.decapsulate(&sealed)?, test_data);
+        assert!(kyber.decapsulate(&sealed).is_err());
+        assert_eq!(kyber.encapsulate(test_data)?.quantum_ciphertext.len(), 1568);
+
+        // Falcon-1024 signs the whole encapsulation
+        let sealed = falcon.encapsulate(test_data)?;
+        assert_eq!(sealed.algorithm, QuantumAlgorithm::Falcon);
+        assert_eq!(falcon.decapsulate(&sealed)?, test_data);
+        let mut tampered = sealed.clone();
+        tampered.timestamp += 1;
+        assert!(falcon.decapsulate(&tampered).is_err());
+        let mut unsigned = sealed;
+        unsigned.quantum_signature = vec![0; 32];
+        assert!(falcon.decapsulate(&unsigned).is_err());
+
         Ok(())
     }
 
@@ -470,12 +681,17 @@ mod tests {
 
         let frame = EncryptedFrame {
             sequence: 1,
+            device_id: "test-camera".to_string(),
             ciphertext: vec![1, 2, 3, 4],
             hash: "test_hash_123".repeat(32),
             previous_hash: "prev_hash_123".repeat(32),
             nonce: vec![0, 1, 2, 3],
             timestamp: 1640995200,
             blockchain_anchors: vec![],
+            cipher: Cipher::Aes256Gcm,
+            compressed: false,
+            attestation: None,
+            device_signature: None,
         };
 
         let hybrid = engine.create_hybrid_encryption(&frame)?;
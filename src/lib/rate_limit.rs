@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+use crate::error::ImmutableEncryptionError;
+
+// Buckets idle long enough to be full again are dropped past this many clients
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub max_body_bytes: u64,   // JSON and other small requests
+    pub max_upload_bytes: u64, // rendition uploads
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            requests_per_second: 50.0,
+            burst: 100,
+            max_body_bytes: 1024 * 1024,
+            max_upload_bytes: 4 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// Token bucket per client (certificate fingerprint, API key or address)
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    pub async fn check(&self, client: &str) -> Result<(), ImmutableEncryptionError> {
+        self.check_at(client, Instant::now()).await
+    }
+
+    async fn check_at(&self, client: &str, now: Instant) -> Result<(), ImmutableEncryptionError> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let capacity = self.config.burst.max(1) as f64;
+        let rate = self.config.requests_per_second;
+        let mut buckets = self.buckets.lock().await;
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * rate < capacity
+            });
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return Err(ImmutableEncryptionError::RateLimitExceeded(format!(
+                "client {} exceeded {} requests per second",
+                client, rate
+            )));
+        }
+        bucket.tokens -= 1.0;
+
+        Ok(())
+    }
+
+    // Largest body accepted on `path`; frame ingestion has its own limit
+    pub fn body_limit(&self, path: &str, max_frame_bytes: u64) -> u64 {
+        if path.starts_with("/frames") {
+            max_frame_bytes
+        } else if path.starts_with("/renditions") {
+            self.config.max_upload_bytes
+        } else {
+            self.config.max_body_bytes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_token_bucket_refills() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_second: 1.0,
+            burst: 2,
+            ..Default::default()
+        });
+        let start = Instant::now();
+
+        assert!(limiter.check_at("camera-1", start).await.is_ok());
+        assert!(limiter.check_at("camera-1", start).await.is_ok());
+        assert!(matches!(
+            limiter.check_at("camera-1", start).await,
+            Err(ImmutableEncryptionError::RateLimitExceeded(_))
+        ));
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at("camera-2", start).await.is_ok());
+
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.check_at("camera-1", later).await.is_ok());
+    }
+}
@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+use crate::error::{Context, Result};
+use crate::storage;
+use crate::EncryptedFrame;
+
+const SNAPSHOT_PREFIX: &str = "crash-";
+const SNAPSHOT_EXTENSION: &str = ".json";
+const RECOVERED_SUFFIX: &str = ".recovered";
+
+// A frame the pipeline had sealed but not yet finished with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingFrame {
+    pub device_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub hash: String,
+}
+
+impl PendingFrame {
+    fn of(frame: &EncryptedFrame) -> Self {
+        Self {
+            device_id: frame.device_id.clone(),
+            sequence: frame.sequence,
+            timestamp: frame.timestamp,
+            hash: frame.hash.clone(),
+        }
+    }
+
+    pub fn frame_id(&self) -> String {
+        storage::frame_key(&self.device_id, self.sequence, self.timestamp)
+    }
+}
+
+// Pipeline state at the moment the node went down, written so the next start
+// can resume the chain and operators can account for any gap it left
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashSnapshot {
+    pub taken_at: u64, // unix millis
+    pub reason: String,
+    #[serde(default)]
+    pub chain_tips: BTreeMap<String, String>, // device_id -> hash of its last sealed frame
+    pub last_sequences: BTreeMap<String, u64>, // device_id -> last sealed sequence
+    pub unflushed: Vec<PendingFrame>,          // sealed but not yet stored
+    pub pending_anchors: Vec<PendingFrame>,    // anchoring started but never finished
+}
+
+impl CrashSnapshot {
+    // Written under a temporary name and renamed, so a crash mid-write never
+    // leaves a truncated snapshot behind
+    pub fn write_to(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let name = format!(
+            "{}{:020}{}",
+            SNAPSHOT_PREFIX, self.taken_at, SNAPSHOT_EXTENSION
+        );
+        let path = dir.join(&name);
+        let partial = dir.join(format!(".{}", name));
+        std::fs::write(&partial, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+// What a restart made of one snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    pub snapshot: String,
+    pub reason: String,
+    pub taken_at: u64,
+    pub chain_tips: BTreeMap<String, String>,
+    pub resumed_devices: usize,
+    pub lost_frames: Vec<PendingFrame>, // sealed before the crash, never stored
+    pub unanchored_frames: Vec<PendingFrame>, // stored, but anchoring was cut short
+}
+
+#[derive(Debug, Default)]
+struct Pipeline {
+    chain_tips: BTreeMap<String, String>,
+    last_sequences: BTreeMap<String, u64>,
+    unflushed: BTreeMap<(String, u64), PendingFrame>,
+    pending_anchors: BTreeMap<(String, u64), PendingFrame>,
+}
+
+// Mirrors what the pipeline only holds in memory. A plain mutex, not tokio's,
+// so a panic hook can read it.
+#[derive(Debug, Default)]
+pub struct CrashState {
+    pipeline: Mutex<Pipeline>,
+}
+
+impl CrashState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn pipeline(&self) -> MutexGuard<'_, Pipeline> {
+        self.pipeline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn sealed(&self, frame: &EncryptedFrame) {
+        let mut pipeline = self.pipeline();
+        pipeline
+            .chain_tips
+            .insert(frame.device_id.clone(), frame.hash.clone());
+        let last = pipeline
+            .last_sequences
+            .entry(frame.device_id.clone())
+            .or_insert(0);
+        *last = (*last).max(frame.sequence);
+        pipeline
+            .unflushed
+            .insert(key(frame), PendingFrame::of(frame));
+    }
+
+    pub fn anchoring(&self, frame: &EncryptedFrame) {
+        self.pipeline()
+            .pending_anchors
+            .insert(key(frame), PendingFrame::of(frame));
+    }
+
+    pub fn anchored(&self, frame: &EncryptedFrame) {
+        self.pipeline().pending_anchors.remove(&key(frame));
+    }
+
+    // Called once the store has finished, whether or not it succeeded; failed
+    // stores are alerted on when they happen
+    pub fn flushed(&self, frame: &EncryptedFrame) {
+        self.pipeline().unflushed.remove(&key(frame));
+    }
+
+    // Carries a previous run's tip and sequences forward, so a second crash
+    // before any new frame is sealed still records where the chain stood
+    pub fn resume(&self, snapshot: &CrashSnapshot) {
+        let mut pipeline = self.pipeline();
+        for (device_id, tip) in &snapshot.chain_tips {
+            pipeline
+                .chain_tips
+                .entry(device_id.clone())
+                .or_insert_with(|| tip.clone());
+        }
+        for (device_id, sequence) in &snapshot.last_sequences {
+            let last = pipeline
+                .last_sequences
+                .entry(device_id.clone())
+                .or_insert(0);
+            *last = (*last).max(*sequence);
+        }
+    }
+
+    // None if the panicking thread is the one holding the state
+    pub fn snapshot(&self, reason: &str) -> Option<CrashSnapshot> {
+        let pipeline = match self.pipeline.try_lock() {
+            Ok(pipeline) => pipeline,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        Some(CrashSnapshot {
+            taken_at: now_millis(),
+            reason: reason.to_string(),
+            chain_tips: pipeline.chain_tips.clone(),
+            last_sequences: pipeline.last_sequences.clone(),
+            unflushed: pipeline.unflushed.values().cloned().collect(),
+            pending_anchors: pipeline.pending_anchors.values().cloned().collect(),
+        })
+    }
+}
+
+fn key(frame: &EncryptedFrame) -> (String, u64) {
+    (frame.device_id.clone(), frame.sequence)
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+// Best effort: the node is going down either way, so failures are only logged
+pub fn write_snapshot(dir: &Path, state: &CrashState, reason: &str) {
+    match state
+        .snapshot(reason)
+        .map(|snapshot| snapshot.write_to(dir))
+    {
+        Some(Ok(path)) => tracing::error!("Wrote crash snapshot {}", path.display()),
+        Some(Err(e)) => tracing::error!(
+            "Failed to write a crash snapshot to {}: {}",
+            dir.display(),
+            e
+        ),
+        None => tracing::error!(
+            "Pipeline state was locked; no crash snapshot in {}",
+            dir.display()
+        ),
+    }
+}
+
+// Snapshots every node's state into its directory on any panic, then runs
+// the hook that was installed before
+pub fn install_panic_hook(targets: Vec<(PathBuf, Arc<CrashState>)>) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let reason = format!("panic: {}", info);
+        for (dir, state) in &targets {
+            write_snapshot(dir, state, &reason);
+        }
+        previous(info);
+    }));
+}
+
+// Snapshots in `dir` no restart has recovered yet, oldest first
+pub fn pending_snapshots(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_snapshot = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| {
+                name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(SNAPSHOT_EXTENSION)
+            });
+        if is_snapshot {
+            snapshots.push(path);
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+// Kept rather than deleted: the snapshot is the record of why the gap exists
+pub fn mark_recovered(path: &Path) -> Result<PathBuf> {
+    let mut recovered = path.as_os_str().to_owned();
+    recovered.push(RECOVERED_SUFFIX);
+    let recovered = PathBuf::from(recovered);
+    std::fs::rename(path, &recovered)?;
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn frame(device_id: &str, sequence: u64) -> EncryptedFrame {
+        EncryptedFrame {
+            sequence,
+            device_id: device_id.to_string(),
+            ciphertext: vec![1, 2, 3],
+            hash: format!("hash-{}-{}", device_id, sequence),
+            previous_hash: "0".repeat(64),
+            nonce: vec![0; 12],
+            timestamp: 1_700_000_000 + sequence,
+            blockchain_anchors: Vec::new(),
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_tracks_pending_frames_and_round_trips() -> Result<()> {
+        let state = CrashState::new();
+        let (first, second) = (frame("cam_1", 7), frame("cam_1", 8));
+        state.sealed(&first);
+        state.sealed(&second);
+        state.anchoring(&first);
+        state.anchoring(&second);
+        state.anchored(&first);
+        state.flushed(&first);
+
+        let snapshot = state.snapshot("panic: test").unwrap();
+        assert_eq!(snapshot.chain_tips["cam_1"], "hash-cam_1-8");
+        assert_eq!(snapshot.last_sequences["cam_1"], 8);
+        assert_eq!(snapshot.unflushed, vec![PendingFrame::of(&second)]);
+        assert_eq!(snapshot.pending_anchors, vec![PendingFrame::of(&second)]);
+
+        let dir = TempDir::new()?;
+        let path = snapshot.write_to(dir.path())?;
+        assert_eq!(pending_snapshots(dir.path())?, vec![path.clone()]);
+        let loaded = CrashSnapshot::load(&path)?;
+        assert_eq!(loaded.unflushed[0].frame_id(), "frame:cam_1:8:1700000008");
+
+        let resumed = CrashState::new();
+        resumed.resume(&loaded);
+        assert_eq!(
+            resumed.snapshot("again").unwrap().last_sequences["cam_1"],
+            8
+        );
+
+        mark_recovered(&path)?;
+        assert!(pending_snapshots(dir.path())?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::EncryptedFrame;
+
+// Side of the squares a pixelated region is averaged over
+const PIXELATE_BLOCK: u32 = 16;
+
+// A rectangle in frame pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionMethod {
+    #[default]
+    Pixelate,
+    Fill, // solid black
+}
+
+impl RedactionMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pixelate => "pixelate",
+            Self::Fill => "fill",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrameRegions {
+    pub sequence: u64,
+    pub regions: Vec<Region>,
+}
+
+// Regions to hide, by frame sequence; frames not listed pass through unchanged
+#[derive(Debug, Clone, Deserialize)]
+pub struct RedactionRequest {
+    pub frames: Vec<FrameRegions>,
+    #[serde(default)]
+    pub method: RedactionMethod,
+    pub reason: String,
+    #[serde(default)]
+    pub approval_id: Option<String>, // needed when decryption requires approval
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionCommitment {
+    pub region: Region,
+    pub commitment: String, // salted SHA-256 over the pixels the region hid
+}
+
+// One frame of a redacted rendition and the sealed frame it came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactedFrame {
+    pub sequence: u64,
+    pub original_hash: String, // chain hash of the sealed frame
+    pub redacted_sha256: String,
+    pub regions: Vec<RegionCommitment>,
+}
+
+// What was hidden in a redacted rendition and why. Each region is committed
+// to without disclosing it: given the sealed originals and the salts, anyone
+// can re-apply the redaction and check that nothing but the listed regions
+// changed and that each commitment covers what was actually there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Redaction {
+    pub method: RedactionMethod,
+    pub reason: String,
+    pub redacted_by: String,
+    pub frames: Vec<RedactedFrame>,
+}
+
+// The salts that open a redaction's commitments, per frame and region. Kept
+// by the node under `redaction_opening:{rendition_id}` and never disclosed
+// with the rendition, since together with a guess they would confirm it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionOpening {
+    pub salts: Vec<Vec<String>>,
+}
+
+impl RedactionOpening {
+    pub fn key(rendition_id: &str) -> String {
+        format!("redaction_opening:{}", rendition_id)
+    }
+}
+
+impl Redaction {
+    // Redacts the decrypted payloads of a session's frames, in sequence
+    // order, returning the redaction, its opening and the rendition: the
+    // redacted payloads concatenated
+    pub fn apply(
+        originals: &[(EncryptedFrame, Vec<u8>)],
+        request: &RedactionRequest,
+        redacted_by: &str,
+    ) -> Result<(Self, RedactionOpening, Vec<u8>)> {
+        if request.reason.trim().is_empty() {
+            return Err(ImmutableEncryptionError::invalid_request(
+                "a redaction needs a reason",
+            ));
+        }
+        let mut requested: HashMap<u64, &[Region]> = HashMap::new();
+        for entry in &request.frames {
+            if !originals
+                .iter()
+                .any(|(frame, _)| frame.sequence == entry.sequence)
+            {
+                return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                    "frame {} is not part of the session",
+                    entry.sequence
+                )));
+            }
+            if requested.insert(entry.sequence, &entry.regions).is_some() {
+                return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                    "frame {} is listed more than once",
+                    entry.sequence
+                )));
+            }
+        }
+
+        let rng = SystemRandom::new();
+        let mut frames = Vec::with_capacity(originals.len());
+        let mut salts = Vec::with_capacity(originals.len());
+        let mut rendition = Vec::new();
+        for (frame, payload) in originals {
+            let regions = requested.get(&frame.sequence).copied().unwrap_or_default();
+            let (redacted, hidden) = redact_image(payload, regions, request.method)?;
+
+            let mut frame_salts = Vec::with_capacity(regions.len());
+            let mut commitments = Vec::with_capacity(regions.len());
+            for (region, pixels) in regions.iter().zip(&hidden) {
+                let mut salt = [0u8; 32];
+                rng.fill(&mut salt)
+                    .map_err(|_| ImmutableEncryptionError::crypto("Failed to generate a salt"))?;
+                commitments.push(RegionCommitment {
+                    region: *region,
+                    commitment: commit(&salt, frame, region, pixels),
+                });
+                frame_salts.push(hex::encode(salt));
+            }
+
+            frames.push(RedactedFrame {
+                sequence: frame.sequence,
+                original_hash: frame.hash.clone(),
+                redacted_sha256: hex::encode(Sha256::digest(&redacted)),
+                regions: commitments,
+            });
+            salts.push(frame_salts);
+            rendition.extend_from_slice(&redacted);
+        }
+
+        let redaction = Self {
+            method: request.method,
+            reason: request.reason.clone(),
+            redacted_by: redacted_by.to_string(),
+            frames,
+        };
+        Ok((redaction, RedactionOpening { salts }, rendition))
+    }
+
+    // Re-applies the redaction to the sealed originals and checks every
+    // redacted frame digest and region commitment against them
+    pub fn verify(
+        &self,
+        originals: &[(EncryptedFrame, Vec<u8>)],
+        opening: &RedactionOpening,
+    ) -> Result<bool> {
+        if originals.len() != self.frames.len() || opening.salts.len() != self.frames.len() {
+            return Ok(false);
+        }
+
+        for ((redacted, (frame, payload)), salts) in
+            self.frames.iter().zip(originals).zip(&opening.salts)
+        {
+            if redacted.sequence != frame.sequence
+                || redacted.original_hash != frame.hash
+                || salts.len() != redacted.regions.len()
+            {
+                return Ok(false);
+            }
+
+            let regions: Vec<Region> = redacted.regions.iter().map(|c| c.region).collect();
+            let (data, hidden) = redact_image(payload, &regions, self.method)?;
+            if hex::encode(Sha256::digest(&data)) != redacted.redacted_sha256 {
+                return Ok(false);
+            }
+            for ((committed, pixels), salt) in redacted.regions.iter().zip(&hidden).zip(salts) {
+                let salt = hex::decode(salt).map_err(|e| {
+                    ImmutableEncryptionError::InvalidRequest(format!("Invalid salt: {}", e))
+                })?;
+                if commit(&salt, frame, &committed.region, pixels) != committed.commitment {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn region_count(&self) -> usize {
+        self.frames.iter().map(|f| f.regions.len()).sum()
+    }
+}
+
+fn commit(salt: &[u8], frame: &EncryptedFrame, region: &Region, pixels: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(frame.hash.as_bytes());
+    hasher.update(frame.sequence.to_be_bytes());
+    for value in [region.x, region.y, region.width, region.height] {
+        hasher.update(value.to_be_bytes());
+    }
+    hasher.update(pixels);
+    hex::encode(hasher.finalize())
+}
+
+// Redacts a JPEG or PNG still, re-encoding it in its own format, and returns
+// it with the RGB pixels each region hid. Untouched frames are passed through
+// byte for byte.
+#[cfg(feature = "video")]
+fn redact_image(
+    data: &[u8],
+    regions: &[Region],
+    method: RedactionMethod,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>)> {
+    if regions.is_empty() {
+        return Ok((data.to_vec(), Vec::new()));
+    }
+
+    let format = image::guess_format(data)?;
+    let mut rgb = image::load_from_memory_with_format(data, format)?.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let hidden = redact_pixels(rgb.as_mut(), width, height, regions, method)?;
+
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(rgb).write_to(&mut encoded, format)?;
+    Ok((encoded.into_inner(), hidden))
+}
+
+#[cfg(not(feature = "video"))]
+fn redact_image(
+    data: &[u8],
+    regions: &[Region],
+    _method: RedactionMethod,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>)> {
+    if regions.is_empty() {
+        return Ok((data.to_vec(), Vec::new()));
+    }
+    Err(ImmutableEncryptionError::Video(
+        "Redaction requires the `video` feature".to_string(),
+    ))
+}
+
+// Redacts regions of an RGB24 buffer in place. Every region's original pixels
+// are taken before any are changed, so overlapping regions each commit to
+// the footage rather than to another region's redaction.
+pub fn redact_pixels(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    regions: &[Region],
+    method: RedactionMethod,
+) -> Result<Vec<Vec<u8>>> {
+    if pixels.len() != width as usize * height as usize * 3 {
+        return Err(ImmutableEncryptionError::Video(format!(
+            "Frame buffer is {} bytes, expected {} for {}x{}",
+            pixels.len(),
+            width as usize * height as usize * 3,
+            width,
+            height
+        )));
+    }
+    if let Some(region) = regions.iter().find(|r| {
+        r.width == 0
+            || r.height == 0
+            || r.x.saturating_add(r.width) > width
+            || r.y.saturating_add(r.height) > height
+    }) {
+        return Err(ImmutableEncryptionError::InvalidRequest(format!(
+            "Region {}x{} at ({}, {}) is empty or outside the {}x{} frame",
+            region.width, region.height, region.x, region.y, width, height
+        )));
+    }
+
+    let row = |x: u32, y: u32, len: u32| {
+        let start = (y as usize * width as usize + x as usize) * 3;
+        start..start + len as usize * 3
+    };
+
+    let hidden = regions
+        .iter()
+        .map(|r| {
+            (r.y..r.y + r.height)
+                .flat_map(|y| pixels[row(r.x, y, r.width)].to_vec())
+                .collect()
+        })
+        .collect();
+
+    for region in regions {
+        let block = match method {
+            RedactionMethod::Pixelate => PIXELATE_BLOCK,
+            RedactionMethod::Fill => region.width.max(region.height),
+        };
+        for block_y in (region.y..region.y + region.height).step_by(block as usize) {
+            for block_x in (region.x..region.x + region.width).step_by(block as usize) {
+                let block_width = block.min(region.x + region.width - block_x);
+                let block_height = block.min(region.y + region.height - block_y);
+
+                let mut color = [0u8; 3];
+                if method == RedactionMethod::Pixelate {
+                    let mut sums = [0u64; 3];
+                    for y in block_y..block_y + block_height {
+                        for pixel in pixels[row(block_x, y, block_width)].chunks_exact(3) {
+                            for (sum, value) in sums.iter_mut().zip(pixel) {
+                                *sum += *value as u64;
+                            }
+                        }
+                    }
+                    let count = block_width as u64 * block_height as u64;
+                    for (channel, sum) in color.iter_mut().zip(sums) {
+                        *channel = (sum / count) as u8;
+                    }
+                }
+
+                for y in block_y..block_y + block_height {
+                    for pixel in pixels[row(block_x, y, block_width)].chunks_exact_mut(3) {
+                        pixel.copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(hidden)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction_hides_only_its_regions() -> Result<()> {
+        let (width, height) = (64u32, 48u32);
+        let original: Vec<u8> = (0..width * height * 3).map(|i| (i % 251) as u8).collect();
+        let face = Region {
+            x: 8,
+            y: 4,
+            width: 20,
+            height: 18,
+        };
+        let overlapping = Region {
+            x: 20,
+            y: 10,
+            width: 10,
+            height: 10,
+        };
+
+        let mut pixels = original.clone();
+        let hidden = redact_pixels(
+            &mut pixels,
+            width,
+            height,
+            &[face, overlapping],
+            RedactionMethod::Pixelate,
+        )?;
+
+        // Both regions commit to the footage, including where they overlap
+        assert_eq!(hidden[0].len(), 20 * 18 * 3);
+        assert_eq!(hidden[0][..3], original[(4 * 64 + 8) * 3..][..3]);
+        assert_eq!(hidden[1][..3], original[(10 * 64 + 20) * 3..][..3]);
+
+        // Pixelation averages each block to a single colour
+        let at = |x: u32, y: u32| &pixels[((y * width + x) * 3) as usize..][..3];
+        assert_eq!(at(8, 4), at(19, 19));
+        assert_ne!(at(8, 4), &original[(4 * 64 + 8) * 3..][..3]);
+        // Outside the regions nothing changes
+        assert_eq!(at(0, 0), &original[..3]);
+        assert_eq!(at(63, 47), &original[original.len() - 3..]);
+
+        let mut filled = original.clone();
+        redact_pixels(&mut filled, width, height, &[face], RedactionMethod::Fill)?;
+        assert!(filled[((4 * 64 + 8) * 3) as usize..][..60]
+            .iter()
+            .all(|&v| v == 0));
+
+        let outside = Region {
+            x: 60,
+            y: 0,
+            width: 8,
+            height: 8,
+        };
+        assert!(redact_pixels(
+            &mut pixels,
+            width,
+            height,
+            &[outside],
+            RedactionMethod::Fill
+        )
+        .is_err());
+
+        // Frames without regions pass through, so a redaction of nothing
+        // verifies against the originals whatever the build
+        let frame = EncryptedFrame {
+            sequence: 7,
+            device_id: "cam_1".to_string(),
+            ciphertext: Vec::new(),
+            hash: "ab".repeat(32),
+            previous_hash: "0".repeat(64),
+            nonce: Vec::new(),
+            timestamp: 1000,
+            blockchain_anchors: Vec::new(),
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+        let originals = vec![(frame, b"jpeg bytes".to_vec())];
+        let request = RedactionRequest {
+            frames: Vec::new(),
+            method: RedactionMethod::Pixelate,
+            reason: "bystanders".to_string(),
+            approval_id: None,
+        };
+        let (redaction, opening, rendition) = Redaction::apply(&originals, &request, "officer")?;
+        assert_eq!(rendition, b"jpeg bytes");
+        assert_eq!(redaction.frames[0].original_hash, "ab".repeat(32));
+        assert!(redaction.verify(&originals, &opening)?);
+
+        let mut tampered = redaction.clone();
+        tampered.frames[0].redacted_sha256 = "0".repeat(64);
+        assert!(!tampered.verify(&originals, &opening)?);
+
+        let unknown = RedactionRequest {
+            frames: vec![FrameRegions {
+                sequence: 8,
+                regions: vec![face],
+            }],
+            ..request
+        };
+        assert!(Redaction::apply(&originals, &unknown, "officer").is_err());
+        Ok(())
+    }
+}
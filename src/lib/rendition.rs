@@ -0,0 +1,299 @@
+use bytes::Buf;
+use futures::{Stream, StreamExt};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use tokio::io::AsyncWriteExt;
+
+use crate::crypto::EncryptionEngine;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::redaction::Redaction;
+use crate::session::SessionManifest;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscodeProfile {
+    pub codec: String,
+    pub resolution: Option<(u32, u32)>,
+    pub bitrate_kbps: Option<u64>,
+    pub tool: Option<String>, // e.g. "ffmpeg 6.0 -c:v libx264 -crf 23"
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenditionDigest {
+    pub sha256: String,
+    pub blake3: String,
+    pub size_bytes: u64,
+}
+
+// A derived copy (e.g. a distribution transcode) linked back to the sealed
+// original through the parent session manifest, which commits to the first
+// and last chain hashes and is itself anchored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenditionRecord {
+    pub rendition_id: String,
+    pub parent_evidence_id: String,
+    pub parent_manifest_hash: String,
+    pub parent_first_hash: Option<String>,
+    pub parent_last_hash: Option<String>,
+    pub profile: TranscodeProfile,
+    pub digest: RenditionDigest,
+    pub created_at: u64,
+    pub link_hash: String,
+    pub signature: String,
+    // Set on redacted renditions; covered by the link hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<Redaction>,
+}
+
+impl RenditionRecord {
+    pub fn link(
+        parent: &SessionManifest,
+        profile: TranscodeProfile,
+        digest: RenditionDigest,
+        engine: &EncryptionEngine,
+    ) -> Result<Self> {
+        Self::link_with(parent, profile, digest, None, engine)
+    }
+
+    // A redacted copy, committing to what it hides alongside the usual link
+    pub fn link_redacted(
+        parent: &SessionManifest,
+        redaction: Redaction,
+        digest: RenditionDigest,
+        engine: &EncryptionEngine,
+    ) -> Result<Self> {
+        let profile = TranscodeProfile {
+            codec: "redacted".to_string(),
+            resolution: None,
+            bitrate_kbps: None,
+            tool: Some(format!("redaction ({})", redaction.method.as_str())),
+        };
+        Self::link_with(parent, profile, digest, Some(redaction), engine)
+    }
+
+    fn link_with(
+        parent: &SessionManifest,
+        profile: TranscodeProfile,
+        digest: RenditionDigest,
+        redaction: Option<Redaction>,
+        engine: &EncryptionEngine,
+    ) -> Result<Self> {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+
+        let link_hash =
+            compute_link_hash(&parent.manifest_hash, &profile, &digest, redaction.as_ref())?;
+
+        Ok(Self {
+            rendition_id: format!("rendition_{}", &digest.blake3[..16]),
+            parent_evidence_id: parent.session_id.clone(),
+            parent_manifest_hash: parent.manifest_hash.clone(),
+            parent_first_hash: parent.first_hash.clone(),
+            parent_last_hash: parent.last_hash.clone(),
+            signature: engine.sign(link_hash.as_bytes()),
+            link_hash,
+            profile,
+            digest,
+            created_at,
+            redaction,
+        })
+    }
+
+    pub fn verify_link(&self, engine: &EncryptionEngine) -> Result<bool> {
+        let expected = compute_link_hash(
+            &self.parent_manifest_hash,
+            &self.profile,
+            &self.digest,
+            self.redaction.as_ref(),
+        )?;
+        Ok(expected == self.link_hash
+            && engine.verify_signature(self.link_hash.as_bytes(), &self.signature))
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        hash_rendition(data) == self.digest
+    }
+}
+
+fn compute_link_hash(
+    parent_manifest_hash: &str,
+    profile: &TranscodeProfile,
+    digest: &RenditionDigest,
+    redaction: Option<&Redaction>,
+) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(parent_manifest_hash.as_bytes());
+    hasher.update(digest.sha256.as_bytes());
+    hasher.update(digest.blake3.as_bytes());
+    hasher.update(&digest.size_bytes.to_be_bytes());
+    hasher.update(serde_json::to_vec(profile)?);
+    // Absent on plain transcodes, so their link hashes are unchanged
+    if let Some(redaction) = redaction {
+        hasher.update(serde_json::to_vec(redaction)?);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn hash_rendition(data: &[u8]) -> RenditionDigest {
+    RenditionDigest {
+        sha256: hex::encode(Sha256::digest(data)),
+        blake3: blake3::hash(data).to_hex().to_string(),
+        size_bytes: data.len() as u64,
+    }
+}
+
+// Streams a transcode from disk so multi-gigabyte renditions never sit in memory
+pub fn hash_rendition_file(path: &std::path::Path) -> Result<RenditionDigest> {
+    let mut file = std::fs::File::open(path)?;
+    let mut sha256 = Sha256::new();
+    let mut blake3 = blake3::Hasher::new();
+    let mut size_bytes = 0u64;
+    let mut buffer = vec![0u8; 1024 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buffer[..read]);
+        blake3.update(&buffer[..read]);
+        size_bytes += read as u64;
+    }
+
+    Ok(RenditionDigest {
+        sha256: hex::encode(sha256.finalize()),
+        blake3: blake3.finalize().to_hex().to_string(),
+        size_bytes,
+    })
+}
+
+// Spools an upload to a temporary file as it arrives and hashes it from there,
+// so a multi-gigabyte rendition never sits in memory. The file is removed
+// either way.
+pub async fn hash_rendition_upload<S, B, E>(mut body: S) -> Result<RenditionDigest>
+where
+    S: Stream<Item = std::result::Result<B, E>> + Unpin,
+    B: Buf,
+    E: std::fmt::Display,
+{
+    let mut name = [0u8; 16];
+    SystemRandom::new().fill(&mut name)?;
+    let path = std::env::temp_dir().join(format!("rendition-{}.upload", hex::encode(name)));
+
+    let hashed = async {
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await?;
+        while let Some(chunk) = body.next().await {
+            let mut chunk = chunk.map_err(|e| {
+                ImmutableEncryptionError::invalid_request(&format!("Upload interrupted: {}", e))
+            })?;
+            while chunk.has_remaining() {
+                let written = file.write(chunk.chunk()).await?;
+                chunk.advance(written);
+            }
+        }
+        file.flush().await?;
+        drop(file);
+
+        let spooled = path.clone();
+        tokio::task::spawn_blocking(move || hash_rendition_file(&spooled)).await?
+    }
+    .await;
+
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        tracing::warn!("Failed to remove {}: {}", path.display(), e);
+    }
+    hashed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+    use crate::session::RecordingSession;
+
+    #[test]
+    fn test_rendition_link_roundtrip() -> Result<()> {
+        let engine = EncryptionEngine::new(CryptoConfig::software(vec![3u8; 32], 1))?;
+
+        let session = RecordingSession {
+            session_id: "session_cam_1_1000_deadbeef".to_string(),
+            device_id: "cam_1".to_string(),
+            case_id: None,
+            started_at: 1000,
+            first_hash: Some("a".repeat(64)),
+            last_hash: Some("b".repeat(64)),
+            first_sequence: Some(1),
+            last_sequence: Some(2),
+            first_frame_timestamp: Some(1001),
+            last_frame_timestamp: Some(1002),
+            frame_count: 2,
+            policy: None,
+        };
+        let manifest = SessionManifest::from_session(&session, 1010, &engine)?;
+
+        let transcode = b"fake h264 transcode bytes".to_vec();
+        let profile = TranscodeProfile {
+            codec: "H.264".to_string(),
+            resolution: Some((1280, 720)),
+            bitrate_kbps: Some(2500),
+            tool: Some("ffmpeg".to_string()),
+        };
+
+        let record =
+            RenditionRecord::link(&manifest, profile, hash_rendition(&transcode), &engine)?;
+
+        assert_eq!(record.parent_manifest_hash, manifest.manifest_hash);
+        assert!(record.verify_link(&engine)?);
+        assert!(record.matches(&transcode));
+        assert!(!record.matches(b"re-encoded copy"));
+        assert!(record.redaction.is_none());
+
+        // A redaction's commitments are bound to the link
+        let redaction = Redaction {
+            method: Default::default(),
+            reason: "bystander faces".to_string(),
+            redacted_by: "officer".to_string(),
+            frames: Vec::new(),
+        };
+        let redacted = b"redacted frames".to_vec();
+        let mut record = RenditionRecord::link_redacted(
+            &manifest,
+            redaction,
+            hash_rendition(&redacted),
+            &engine,
+        )?;
+        assert!(record.verify_link(&engine)?);
+        if let Some(redaction) = record.redaction.as_mut() {
+            redaction.reason = "edited".to_string();
+        }
+        assert!(!record.verify_link(&engine)?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rendition_upload_hashes_like_the_whole_body() -> Result<()> {
+        let transcode = vec![7u8; 3 * 1024 * 1024 + 5];
+        let chunks: Vec<std::result::Result<bytes::Bytes, std::io::Error>> = transcode
+            .chunks(64 * 1024)
+            .map(|chunk| Ok(bytes::Bytes::copy_from_slice(chunk)))
+            .collect();
+
+        let digest = hash_rendition_upload(futures::stream::iter(chunks)).await?;
+        assert_eq!(digest, hash_rendition(&transcode));
+
+        let broken = futures::stream::iter(vec![
+            Ok(bytes::Bytes::from_static(b"partial")),
+            Err(std::io::Error::other("client went away")),
+        ]);
+        assert!(hash_rendition_upload(broken).await.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,579 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::approval::ApprovalRequest;
+use crate::crypto::EncryptionEngine;
+use crate::error::Result;
+use crate::rendition::RenditionRecord;
+use crate::session::SessionManifest;
+use crate::storage;
+use crate::timesync::SessionTimeSync;
+use crate::{CourtReport, EncryptedFrame};
+
+// Letter-sized pages, 11pt Helvetica
+const PDF_PAGE_WIDTH: u32 = 612;
+const PDF_PAGE_HEIGHT: u32 = 792;
+const PDF_MARGIN: u32 = 54;
+const PDF_LINE_HEIGHT: u32 = 14;
+const PDF_FONT_SIZE: u32 = 11;
+const PDF_WRAP_COLUMNS: usize = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Html,
+    Pdf,
+}
+
+impl ReportFormat {
+    // Picks the first supported type from an `Accept` header; a missing
+    // header or wildcard keeps the original JSON response.
+    pub fn from_accept(accept: Option<&str>) -> Option<Self> {
+        let accept = match accept {
+            Some(accept) => accept,
+            None => return Some(ReportFormat::Json),
+        };
+
+        accept
+            .split(',')
+            .map(|item| item.split(';').next().unwrap_or("").trim())
+            .find_map(|media_type| match media_type {
+                "application/pdf" => Some(ReportFormat::Pdf),
+                "text/html" => Some(ReportFormat::Html),
+                "application/json" | "application/*" | "*/*" => Some(ReportFormat::Json),
+                _ => None,
+            })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "application/json",
+            ReportFormat::Html => "text/html; charset=utf-8",
+            ReportFormat::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Html => "html",
+            ReportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+// On-chain evidence that a manifest or frame hash existed at anchoring time
+#[derive(Debug, Clone, Serialize)]
+pub struct AnchorProof {
+    pub subject: String, // "manifest:{session}" or a frame key
+    pub anchored_hash: String,
+    pub chain: String,
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub proof: String,
+}
+
+impl AnchorProof {
+    pub fn collect(manifest: Option<&SessionManifest>, frames: &[EncryptedFrame]) -> Vec<Self> {
+        let manifest_proofs = manifest.into_iter().flat_map(|m| {
+            m.anchors.iter().map(move |anchor| AnchorProof {
+                subject: format!("manifest:{}", m.session_id),
+                anchored_hash: m.manifest_hash.clone(),
+                chain: anchor.chain.clone(),
+                transaction_hash: anchor.transaction_hash.clone(),
+                block_number: anchor.block_number,
+                timestamp: anchor.timestamp,
+                proof: anchor.proof.clone(),
+            })
+        });
+        let frame_proofs = frames.iter().flat_map(|frame| {
+            frame
+                .blockchain_anchors
+                .iter()
+                .map(move |anchor| AnchorProof {
+                    subject: storage::frame_key(&frame.device_id, frame.sequence, frame.timestamp),
+                    anchored_hash: frame.hash.clone(),
+                    chain: anchor.chain.clone(),
+                    transaction_hash: anchor.transaction_hash.clone(),
+                    block_number: anchor.block_number,
+                    timestamp: anchor.timestamp,
+                    proof: anchor.proof.clone(),
+                })
+        });
+
+        manifest_proofs.chain(frame_proofs).collect()
+    }
+}
+
+// The report and its proofs, signed by the node over their canonical JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedReport {
+    pub report: CourtReport,
+    pub anchor_proofs: Vec<AnchorProof>,
+    pub content_sha256: String,
+    pub signature: String,
+}
+
+impl SignedReport {
+    pub fn sign(
+        report: CourtReport,
+        anchor_proofs: Vec<AnchorProof>,
+        engine: &EncryptionEngine,
+    ) -> Result<Self> {
+        let content = serde_json::to_vec(&(&report, &anchor_proofs))?;
+        Ok(Self {
+            content_sha256: hex::encode(Sha256::digest(&content)),
+            signature: engine.sign(&content),
+            report,
+            anchor_proofs,
+        })
+    }
+}
+
+// A rendered report ready to download. `signature` covers `body` itself so
+// the file can be checked independently of the embedded report signature.
+#[derive(Debug, Clone)]
+pub struct ReportDocument {
+    pub format: ReportFormat,
+    pub body: Vec<u8>,
+    pub sha256: String,
+    pub signature: String,
+}
+
+impl ReportDocument {
+    pub fn render(
+        signed: &SignedReport,
+        format: ReportFormat,
+        engine: &EncryptionEngine,
+    ) -> Result<Self> {
+        let body = match format {
+            ReportFormat::Json => serde_json::to_vec_pretty(signed)?,
+            ReportFormat::Html => render_html(signed)?.into_bytes(),
+            ReportFormat::Pdf => render_pdf(&report_lines(signed)),
+        };
+
+        Ok(Self {
+            format,
+            sha256: hex::encode(Sha256::digest(&body)),
+            signature: engine.sign(&body),
+            body,
+        })
+    }
+}
+
+fn render_html(signed: &SignedReport) -> Result<String> {
+    let report = &signed.report;
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>Court report {}</title>\n</head>\n<body>\n",
+        escape_html(&report.evidence_id)
+    ));
+    html.push_str(&format!(
+        "<h1>Court report: {}</h1>\n<p>Generated at {} (Unix time)</p>\n",
+        escape_html(&report.evidence_id),
+        report.generated_at
+    ));
+
+    html.push_str("<h2>Chain of custody</h2>\n<table>\n");
+    html.push_str(
+        "<tr><th>Time</th><th>Actor</th><th>Action</th><th>Blockchain reference</th></tr>\n",
+    );
+    for entry in &report.chain_of_custody {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            entry.timestamp,
+            escape_html(&entry.actor),
+            escape_html(&entry.action),
+            escape_html(&entry.blockchain_reference)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Cryptographic proofs</h2>\n<ul>\n");
+    for proof in &report.cryptographic_proofs {
+        html.push_str(&format!("<li><code>{}</code></li>\n", escape_html(proof)));
+    }
+    html.push_str("</ul>\n");
+
+    html.push_str("<h2>Legal compliance</h2>\n<ul>\n");
+    let compliance = &report.legal_compliance;
+    for item in compliance
+        .standards_met
+        .iter()
+        .chain(&compliance.certifications)
+        .chain(&compliance.jurisdiction_compliance)
+    {
+        html.push_str(&format!("<li>{}</li>\n", escape_html(item)));
+    }
+    html.push_str("</ul>\n");
+
+    if !report.derived_renditions.is_empty() {
+        html.push_str("<h2>Derived renditions</h2>\n<ul>\n");
+        for rendition in &report.derived_renditions {
+            html.push_str(&format!(
+                "<li>{} <code>{}</code>{}</li>\n",
+                escape_html(&rendition.rendition_id),
+                escape_html(&rendition.digest.sha256),
+                escape_html(&redaction_note(rendition))
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    if let Some(time_sync) = &report.time_sync {
+        html.push_str("<h2>Time synchronization</h2>\n<ul>\n");
+        for line in time_sync_lines(time_sync) {
+            html.push_str(&format!("<li>{}</li>\n", escape_html(&line)));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    if !report.approvals.is_empty() {
+        html.push_str("<h2>Approvals</h2>\n<ul>\n");
+        for request in &report.approvals {
+            let mut lines = approval_lines(request).into_iter();
+            html.push_str(&format!(
+                "<li>{}",
+                escape_html(&lines.next().unwrap_or_default())
+            ));
+            html.push_str("<ul>\n");
+            for line in lines {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(&line)));
+            }
+            html.push_str("</ul></li>\n");
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("<h2>Blockchain anchor proofs</h2>\n<table>\n");
+    html.push_str("<tr><th>Subject</th><th>Chain</th><th>Transaction</th><th>Block</th><th>Anchored hash</th></tr>\n");
+    for proof in &signed.anchor_proofs {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td><code>{}</code></td><td>{}</td><td><code>{}</code></td></tr>\n",
+            escape_html(&proof.subject),
+            escape_html(&proof.chain),
+            escape_html(&proof.transaction_hash),
+            proof.block_number,
+            escape_html(&proof.anchored_hash)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    // Machine-readable copy of the proofs, for tooling that re-verifies them
+    html.push_str("<script type=\"application/json\" id=\"anchor-proofs\">\n");
+    html.push_str(&serde_json::to_string(&signed.anchor_proofs)?.replace("</", "<\\/"));
+    html.push_str("\n</script>\n");
+
+    html.push_str(&format!(
+        "<h2>Signature</h2>\n<p>SHA-256 <code>{}</code></p>\n<p>Signature <code>{}</code></p>\n",
+        signed.content_sha256, signed.signature
+    ));
+    html.push_str("</body>\n</html>\n");
+
+    Ok(html)
+}
+
+fn report_lines(signed: &SignedReport) -> Vec<String> {
+    let report = &signed.report;
+    let mut lines = vec![
+        format!("Court report: {}", report.evidence_id),
+        format!("Generated at {} (Unix time)", report.generated_at),
+        String::new(),
+        "Chain of custody".to_string(),
+    ];
+    for entry in &report.chain_of_custody {
+        lines.push(format!(
+            "  {}  {}  {}  {}",
+            entry.timestamp, entry.actor, entry.action, entry.blockchain_reference
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push("Cryptographic proofs".to_string());
+    lines.extend(
+        report
+            .cryptographic_proofs
+            .iter()
+            .map(|p| format!("  {}", p)),
+    );
+
+    lines.push(String::new());
+    lines.push("Legal compliance".to_string());
+    let compliance = &report.legal_compliance;
+    lines.extend(
+        compliance
+            .standards_met
+            .iter()
+            .chain(&compliance.certifications)
+            .chain(&compliance.jurisdiction_compliance)
+            .map(|item| format!("  {}", item)),
+    );
+
+    if !report.derived_renditions.is_empty() {
+        lines.push(String::new());
+        lines.push("Derived renditions".to_string());
+        lines.extend(report.derived_renditions.iter().map(|r| {
+            format!(
+                "  {}  {}{}",
+                r.rendition_id,
+                r.digest.sha256,
+                redaction_note(r)
+            )
+        }));
+    }
+
+    if let Some(time_sync) = &report.time_sync {
+        lines.push(String::new());
+        lines.push("Time synchronization".to_string());
+        lines.extend(
+            time_sync_lines(time_sync)
+                .iter()
+                .map(|l| format!("  {}", l)),
+        );
+    }
+
+    if !report.approvals.is_empty() {
+        lines.push(String::new());
+        lines.push("Approvals".to_string());
+        for request in &report.approvals {
+            for (i, line) in approval_lines(request).iter().enumerate() {
+                let indent = if i == 0 { "  " } else { "    " };
+                lines.push(format!("{}{}", indent, line));
+            }
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("Blockchain anchor proofs".to_string());
+    for proof in &signed.anchor_proofs {
+        lines.push(format!(
+            "  {} on {} in block {}, tx {}",
+            proof.subject, proof.chain, proof.block_number, proof.transaction_hash
+        ));
+        lines.push(format!("    anchored hash {}", proof.anchored_hash));
+        lines.push(format!("    proof {}", proof.proof));
+    }
+
+    lines.push(String::new());
+    lines.push(format!("SHA-256 {}", signed.content_sha256));
+    lines.push(format!("Signature {}", signed.signature));
+
+    lines
+}
+
+// Minimal text-only PDF: one builtin font, wrapped lines, as many pages as needed
+fn render_pdf(lines: &[String]) -> Vec<u8> {
+    let per_page = ((PDF_PAGE_HEIGHT - 2 * PDF_MARGIN) / PDF_LINE_HEIGHT) as usize;
+    let wrapped: Vec<String> = lines.iter().flat_map(|line| wrap(line)).collect();
+    let pages: Vec<&[String]> = if wrapped.is_empty() {
+        vec![&[]]
+    } else {
+        wrapped.chunks(per_page).collect()
+    };
+
+    // Objects 1-3 are the catalog, page tree and font; each page then
+    // takes a page object followed by its content stream.
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|i| format!("{} 0 R", 4 + 2 * i))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = format!(
+            "BT\n/F1 {} Tf\n{} TL\n{} {} Td\n",
+            PDF_FONT_SIZE,
+            PDF_LINE_HEIGHT,
+            PDF_MARGIN,
+            PDF_PAGE_HEIGHT - PDF_MARGIN
+        );
+        for line in page.iter() {
+            content.push_str(&format!("({}) '\n", escape_pdf(line)));
+        }
+        content.push_str("ET");
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PDF_PAGE_WIDTH,
+            PDF_PAGE_HEIGHT,
+            5 + 2 * i
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}\nendstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+fn wrap(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(PDF_WRAP_COLUMNS)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+// The builtin fonts only cover ASCII reliably
+fn escape_pdf(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+// Whether the session's timestamps can be relied on, and why not
+fn time_sync_lines(time_sync: &SessionTimeSync) -> Vec<String> {
+    let ms = |value: Option<f64>| value.map_or("unknown".to_string(), |v| format!("{:.3} ms", v));
+    let mut lines = vec![
+        format!(
+            "Timestamps {}trusted: {} of {} clock checks succeeded against {}",
+            if time_sync.trusted { "" } else { "NOT " },
+            time_sync.checks - time_sync.failed_checks,
+            time_sync.checks,
+            if time_sync.sources.is_empty() {
+                "no configured source".to_string()
+            } else {
+                time_sync.sources.join(", ")
+            }
+        ),
+        format!(
+            "Clock offset: mean {}, largest {}",
+            ms(time_sync.mean_offset_ms),
+            ms(time_sync.max_abs_offset_ms)
+        ),
+        format!(
+            "Drift: {}",
+            time_sync
+                .drift_ppm
+                .map_or("unknown".to_string(), |d| format!("{:.3} ppm", d))
+        ),
+    ];
+    lines.extend(time_sync.warnings.iter().map(|w| format!("Warning: {}", w)));
+    lines
+}
+
+// The request, its outcome, then one line per signed approval
+fn approval_lines(request: &ApprovalRequest) -> Vec<String> {
+    let outcome = match (&request.executed_at, &request.rejection) {
+        (Some(at), _) => format!("executed at {}", at),
+        (None, Some(rejection)) => format!(
+            "rejected by {} at {}: {}",
+            rejection.rejected_by, rejection.rejected_at, rejection.reason
+        ),
+        (None, None) => format!(
+            "{} of {} approvals, expires at {}",
+            request.approvals.len(),
+            request.required,
+            request.expires_at
+        ),
+    };
+    let mut lines = vec![format!(
+        "{}: {}, requested by {} at {} ({}); {}",
+        request.request_id,
+        request.action.describe(),
+        request.requested_by,
+        request.requested_at,
+        request.reason,
+        outcome
+    )];
+    lines.extend(request.approvals.iter().map(|a| {
+        format!(
+            "approved by {} as {:?} at {}, signature {}",
+            a.approver, a.role, a.approved_at, a.signature
+        )
+    }));
+    lines
+}
+
+// Marks redacted renditions with what they hide and why
+fn redaction_note(rendition: &RenditionRecord) -> String {
+    match &rendition.redaction {
+        Some(redaction) => format!(
+            "  redacted: {} regions, {}",
+            redaction.region_count(),
+            redaction.reason
+        ),
+        None => String::new(),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_negotiation_and_pdf_layout() {
+        assert_eq!(ReportFormat::from_accept(None), Some(ReportFormat::Json));
+        assert_eq!(
+            ReportFormat::from_accept(Some("application/pdf;q=1.0, text/html")),
+            Some(ReportFormat::Pdf)
+        );
+        assert_eq!(
+            ReportFormat::from_accept(Some("text/html,*/*;q=0.8")),
+            Some(ReportFormat::Html)
+        );
+        assert_eq!(ReportFormat::from_accept(Some("image/png")), None);
+
+        let lines: Vec<String> = (0..120).map(|i| format!("line (#{})", i)).collect();
+        let pdf = String::from_utf8(render_pdf(&lines)).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/Count 3"));
+        assert!(pdf.contains("(line \\(#119\\)) '"));
+        assert!(pdf.trim_end().ends_with("%%EOF"));
+
+        assert_eq!(
+            escape_html("<a href=\"x\">"),
+            "&lt;a href=&quot;x&quot;&gt;"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::future::Future;
+use tokio::time::{sleep, Duration};
+
+use crate::error::Result;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32, // including the first
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+
+    // Runs `op` until it succeeds, fails with an error that isn't retryable,
+    // or runs out of attempts. Integrity and signature failures come back on
+    // the first attempt so callers can alert on them straight away.
+    pub async fn run<T, F, Fut>(&self, what: &str, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) if e.is_retryable() && attempt < self.max_attempts => {
+                    let backoff = self.backoff(attempt);
+                    tracing::warn!(
+                        "{} failed (attempt {} of {}), retrying in {:?}: {}",
+                        what,
+                        attempt,
+                        self.max_attempts,
+                        backoff,
+                        e
+                    );
+                    sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ImmutableEncryptionError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_only_retryable_errors_are_retried() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        let calls = AtomicU32::new(0);
+
+        let anchored = policy
+            .run("anchor", || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(ImmutableEncryptionError::network("connection reset")),
+                    _ => Ok("0xabc"),
+                }
+            })
+            .await;
+        assert_eq!(anchored.unwrap(), "0xabc");
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 2);
+
+        let tampered = policy
+            .run("verify", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err::<(), _>(ImmutableEncryptionError::HashChainViolation)
+            })
+            .await;
+        assert!(matches!(
+            tampered,
+            Err(ImmutableEncryptionError::HashChainViolation)
+        ));
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 1);
+
+        let unreachable = policy
+            .run("store", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err::<(), _>(ImmutableEncryptionError::network("timed out"))
+            })
+            .await;
+        assert!(unreachable.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(policy.backoff(3), Duration::from_millis(2));
+    }
+}
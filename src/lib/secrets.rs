@@ -0,0 +1,535 @@
+use async_trait::async_trait;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::crypto;
+use crate::error::{Context, ImmutableEncryptionError, Result};
+
+// Config strings of the form secret://<provider>/<path>#<field> are replaced
+// with the field of the named secret when the config is resolved
+pub const SECRET_SCHEME: &str = "secret://";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const AWS_SERVICE: &str = "secretsmanager";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    pub vault: Option<VaultConfig>,
+    pub aws: Option<AwsSecretsConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultConfig {
+    pub address: String, // e.g. https://vault.internal:8200
+    #[serde(default = "default_vault_token_env")]
+    pub token_env: String, // environment variable holding the Vault token
+    #[serde(default)]
+    pub namespace: Option<String>, // Vault Enterprise namespace
+}
+
+fn default_vault_token_env() -> String {
+    "VAULT_TOKEN".to_string()
+}
+
+impl VaultConfig {
+    // A request to a Vault API path below /v1, with the token and namespace
+    pub(crate) fn request(
+        &self,
+        client: &reqwest::Client,
+        method: reqwest::Method,
+        path: &str,
+    ) -> Result<reqwest::RequestBuilder> {
+        let token = std::env::var(&self.token_env).map_err(|_| {
+            ImmutableEncryptionError::Config(format!("Set {} to a Vault token", self.token_env))
+        })?;
+        let url = format!(
+            "{}/v1/{}",
+            self.address.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+
+        let mut request = client.request(method, &url).header("X-Vault-Token", token);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        Ok(request)
+    }
+}
+
+// Credentials come from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and, for
+// temporary credentials, AWS_SESSION_TOKEN
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwsSecretsConfig {
+    pub region: String,
+    #[serde(default)]
+    pub endpoint: Option<String>, // overrides https://secretsmanager.<region>.amazonaws.com
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretUri {
+    pub provider: String,
+    pub path: String,
+    pub field: Option<String>,
+}
+
+impl SecretUri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let rest = uri.strip_prefix(SECRET_SCHEME).ok_or_else(|| {
+            ImmutableEncryptionError::Config(format!("Not a secret URI: {}", uri))
+        })?;
+        let (location, field) = match rest.split_once('#') {
+            Some((location, field)) => (location, Some(field.to_string())),
+            None => (rest, None),
+        };
+        match location.split_once('/') {
+            Some((provider, path)) if !provider.is_empty() && !path.is_empty() => Ok(Self {
+                provider: provider.to_string(),
+                path: path.to_string(),
+                field: field.filter(|f| !f.is_empty()),
+            }),
+            _ => Err(ImmutableEncryptionError::Config(format!(
+                "Secret URI {} should look like {}<provider>/<path>#<field>",
+                uri, SECRET_SCHEME
+            ))),
+        }
+    }
+}
+
+pub fn is_secret_uri(value: &str) -> bool {
+    value.starts_with(SECRET_SCHEME)
+}
+
+#[async_trait]
+pub trait SecretProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // The whole secret: an object of fields, or a single string
+    async fn fetch(&self, path: &str) -> Result<Value>;
+}
+
+// Resolves secret URIs against the configured providers, fetching each
+// secret once however many fields of it are referenced
+pub struct SecretResolver {
+    providers: HashMap<&'static str, Arc<dyn SecretProvider>>,
+    cache: HashMap<(String, String), Value>,
+}
+
+impl SecretResolver {
+    pub fn new(config: &SecretsConfig) -> Result<Self> {
+        let mut resolver = Self {
+            providers: HashMap::new(),
+            cache: HashMap::new(),
+        };
+        if let Some(vault) = &config.vault {
+            resolver.register(Arc::new(VaultProvider::new(vault.clone())?));
+        }
+        if let Some(aws) = &config.aws {
+            resolver.register(Arc::new(AwsSecretsProvider::new(aws.clone())?));
+        }
+        Ok(resolver)
+    }
+
+    pub fn register(&mut self, provider: Arc<dyn SecretProvider>) {
+        self.providers.insert(provider.name(), provider);
+    }
+
+    pub async fn resolve(&mut self, uri: &str) -> Result<String> {
+        let uri = SecretUri::parse(uri)?;
+        let key = (uri.provider.clone(), uri.path.clone());
+        if !self.cache.contains_key(&key) {
+            let provider = self.providers.get(uri.provider.as_str()).ok_or_else(|| {
+                ImmutableEncryptionError::Config(format!(
+                    "No [secrets.{}] provider is configured",
+                    uri.provider
+                ))
+            })?;
+            let secret = provider
+                .fetch(&uri.path)
+                .await
+                .with_context(|| format!("Fetching secret {}/{}", uri.provider, uri.path))?;
+            self.cache.insert(key.clone(), secret);
+        }
+
+        select_field(&self.cache[&key], uri.field.as_deref())
+            .with_context(|| format!("Secret {}/{}", uri.provider, uri.path))
+    }
+
+    // Replaces every secret URI string in `value`, returning how many were
+    // resolved
+    pub async fn resolve_all(&mut self, value: &mut toml::Value) -> Result<usize> {
+        let mut pending = Vec::new();
+        collect_secret_strings(value, &mut pending);
+
+        let count = pending.len();
+        for slot in pending {
+            *slot = self.resolve(slot.as_str()).await?;
+        }
+        Ok(count)
+    }
+}
+
+fn collect_secret_strings<'a>(value: &'a mut toml::Value, out: &mut Vec<&'a mut String>) {
+    match value {
+        toml::Value::String(s) if is_secret_uri(s) => out.push(s),
+        toml::Value::Array(items) => {
+            for item in items {
+                collect_secret_strings(item, out);
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                collect_secret_strings(item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+// A string secret is used whole; fields are looked up in an object, or in a
+// string secret holding JSON (how Secrets Manager stores key/value secrets)
+fn select_field(secret: &Value, field: Option<&str>) -> Result<String> {
+    let field = match (secret, field) {
+        (Value::String(s), None) => return Ok(s.clone()),
+        (_, None) => {
+            return Err(ImmutableEncryptionError::config(
+                "secret has several fields; name one with #field",
+            ))
+        }
+        (_, Some(field)) => field,
+    };
+
+    let parsed;
+    let fields = match secret {
+        Value::String(s) => {
+            parsed = serde_json::from_str::<Value>(s).map_err(|_| {
+                ImmutableEncryptionError::Config(format!(
+                    "secret is a plain string and has no field {}",
+                    field
+                ))
+            })?;
+            &parsed
+        }
+        other => other,
+    };
+    match fields.get(field) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(other) => Ok(other.to_string()),
+        None => Err(ImmutableEncryptionError::Config(format!(
+            "secret has no field {}",
+            field
+        ))),
+    }
+}
+
+// HashiCorp Vault over its HTTP API. Paths are API paths below /v1, so a
+// KV v2 secret is secret://vault/secret/data/<name>#<field>.
+pub struct VaultProvider {
+    client: reqwest::Client,
+    config: VaultConfig,
+}
+
+impl VaultProvider {
+    pub fn new(config: VaultConfig) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            config,
+        })
+    }
+}
+
+#[async_trait]
+impl SecretProvider for VaultProvider {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    async fn fetch(&self, path: &str) -> Result<Value> {
+        let response = self
+            .config
+            .request(&self.client, reqwest::Method::GET, path)?
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ImmutableEncryptionError::Network(format!(
+                "Vault returned {}",
+                response.status()
+            )));
+        }
+
+        // KV v2 nests the fields one level deeper than KV v1
+        let mut body: Value = response.json().await?;
+        let data = body
+            .get_mut("data")
+            .map(Value::take)
+            .ok_or_else(|| ImmutableEncryptionError::config("Vault response has no data"))?;
+        let kv2 =
+            data.get("data").map_or(false, Value::is_object) && data.get("metadata").is_some();
+        Ok(if kv2 { data["data"].clone() } else { data })
+    }
+}
+
+// AWS Secrets Manager's GetSecretValue, signed with SigV4. The path is the
+// secret name or ARN.
+pub struct AwsSecretsProvider {
+    client: reqwest::Client,
+    config: AwsSecretsConfig,
+}
+
+impl AwsSecretsProvider {
+    pub fn new(config: AwsSecretsConfig) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            config,
+        })
+    }
+
+    fn endpoint(&self) -> String {
+        self.config.endpoint.clone().unwrap_or_else(|| {
+            format!(
+                "https://{}.{}.amazonaws.com",
+                AWS_SERVICE, self.config.region
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl SecretProvider for AwsSecretsProvider {
+    fn name(&self) -> &'static str {
+        "aws"
+    }
+
+    async fn fetch(&self, path: &str) -> Result<Value> {
+        let body = aws_json_request(
+            &self.client,
+            &self.endpoint(),
+            &self.config.region,
+            AWS_SERVICE,
+            "secretsmanager.GetSecretValue",
+            &serde_json::json!({ "SecretId": path }),
+        )
+        .await?;
+        match body.get("SecretString") {
+            Some(Value::String(secret)) => Ok(Value::String(secret.clone())),
+            _ => Err(ImmutableEncryptionError::config(
+                "Secret has no SecretString (binary secrets are unsupported)",
+            )),
+        }
+    }
+}
+
+// A POST to an AWS JSON 1.1 API such as Secrets Manager or KMS, signed with
+// SigV4. Credentials come from the environment, as for [secrets.aws].
+pub(crate) async fn aws_json_request(
+    client: &reqwest::Client,
+    endpoint: &str,
+    region: &str,
+    service: &str,
+    target: &str,
+    body: &Value,
+) -> Result<Value> {
+    let access_key = std::env::var("AWS_ACCESS_KEY_ID").map_err(|_| {
+        ImmutableEncryptionError::config("Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY")
+    })?;
+    let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY").map_err(|_| {
+        ImmutableEncryptionError::config("Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY")
+    })?;
+    let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+
+    let url = reqwest::Url::parse(endpoint).map_err(|e| {
+        ImmutableEncryptionError::Config(format!("Invalid endpoint {}: {}", endpoint, e))
+    })?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => {
+            return Err(ImmutableEncryptionError::Config(format!(
+                "Endpoint {} has no host",
+                endpoint
+            )))
+        }
+    };
+
+    let body = serde_json::to_vec(body)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let (date, timestamp) = amz_date(now);
+
+    let mut headers = vec![
+        ("content-type", "application/x-amz-json-1.1".to_string()),
+        ("host", host),
+        ("x-amz-date", timestamp.clone()),
+        ("x-amz-target", target.to_string()),
+    ];
+    if let Some(token) = session_token {
+        headers.push(("x-amz-security-token", token));
+    }
+    headers.sort_by(|a, b| a.0.cmp(b.0));
+
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "POST\n/\n\n{}\n{}\n{}",
+        canonical_headers,
+        signed_headers,
+        crypto::sha256_hex(&body)
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        scope,
+        crypto::sha256_hex(canonical_request.as_bytes())
+    );
+    let signature = hex::encode(sigv4_sign(
+        &secret_key,
+        &date,
+        region,
+        service,
+        &string_to_sign,
+    ));
+
+    let mut request = client.post(url).header(
+        "Authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key, scope, signed_headers, signature
+        ),
+    );
+    for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+        request = request.header(name, value);
+    }
+    let response = request.body(body).send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let detail = response.text().await.unwrap_or_default();
+        return Err(ImmutableEncryptionError::Network(format!(
+            "{} returned {}: {}",
+            target, status, detail
+        )));
+    }
+    Ok(response.json().await?)
+}
+
+pub(crate) fn sigv4_sign(
+    secret_key: &str,
+    date: &str,
+    region: &str,
+    service: &str,
+    string_to_sign: &str,
+) -> Vec<u8> {
+    let step = |key: &[u8], data: &str| {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+            .as_ref()
+            .to_vec()
+    };
+    let key = step(format!("AWS4{}", secret_key).as_bytes(), date);
+    let key = step(&key, region);
+    let key = step(&key, service);
+    let key = step(&key, "aws4_request");
+    step(&key, string_to_sign)
+}
+
+// (YYYYMMDD, YYYYMMDDTHHMMSSZ) for a Unix time, as SigV4 expects
+fn amz_date(unix_secs: u64) -> (String, String) {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+
+    // Civil date from days since the epoch (proleptic Gregorian)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let timestamp = format!(
+        "{}T{:02}{:02}{:02}Z",
+        date,
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60
+    );
+    (date, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticProvider;
+
+    #[async_trait]
+    impl SecretProvider for StaticProvider {
+        fn name(&self) -> &'static str {
+            "vault"
+        }
+
+        async fn fetch(&self, path: &str) -> Result<Value> {
+            match path {
+                "secret/data/node" => Ok(serde_json::json!({ "rpc_url": "https://rpc.internal" })),
+                "wallet-seed" => Ok(Value::String(r#"{"seed":"abandon"}"#.to_string())),
+                _ => Err(ImmutableEncryptionError::config("not found")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secret_uris_resolve_in_place() -> Result<()> {
+        assert_eq!(
+            SecretUri::parse("secret://vault/secret/data/node#rpc_url")?,
+            SecretUri {
+                provider: "vault".to_string(),
+                path: "secret/data/node".to_string(),
+                field: Some("rpc_url".to_string()),
+            }
+        );
+        assert!(SecretUri::parse("secret://vault").is_err());
+        assert_eq!(
+            amz_date(1_700_000_000),
+            ("20231114".to_string(), "20231114T221320Z".to_string())
+        );
+
+        let mut resolver = SecretResolver::new(&SecretsConfig::default())?;
+        resolver.register(Arc::new(StaticProvider));
+        let mut config: toml::Value = toml::from_str(
+            r#"
+            rpc_url = "secret://vault/secret/data/node#rpc_url"
+            seeds = ["secret://vault/wallet-seed#seed", "plain"]
+            "#,
+        )?;
+        assert_eq!(resolver.resolve_all(&mut config).await?, 2);
+        assert_eq!(config["rpc_url"].as_str(), Some("https://rpc.internal"));
+        assert_eq!(config["seeds"][0].as_str(), Some("abandon"));
+        assert_eq!(config["seeds"][1].as_str(), Some("plain"));
+
+        assert!(resolver
+            .resolve("secret://vault/secret/data/node")
+            .await
+            .is_err());
+        assert!(resolver.resolve("secret://vault/missing#x").await.is_err());
+        assert!(resolver.resolve("secret://aws/name#x").await.is_err());
+
+        Ok(())
+    }
+}
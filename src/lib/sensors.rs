@@ -0,0 +1,454 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::RwLock;
+
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::{Telemetry, VideoFrame};
+
+const MAVLINK_V1_MAGIC: u8 = 0xFE;
+const MAVLINK_V2_MAGIC: u8 = 0xFD;
+const MAVLINK_MSG_ATTITUDE: u32 = 30;
+const MAVLINK_MSG_GLOBAL_POSITION_INT: u32 = 33;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensorConfig {
+    pub enabled: bool,
+    pub source: SensorSource,
+    pub max_skew_ms: u64,
+    pub buffer_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SensorSource {
+    Nmea { path: String },    // serial device or file emitting NMEA 0183 sentences
+    Gpsd { address: String }, // gpsd JSON watcher, e.g. 127.0.0.1:2947
+    Mavlink { bind: String }, // UDP endpoint receiving MAVLink v1/v2 frames
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: SensorSource::Gpsd {
+                address: "127.0.0.1:2947".to_string(),
+            },
+            max_skew_ms: 500,
+            buffer_size: 256,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorSample {
+    pub received_at_ms: u64,
+    pub location: Option<(f64, f64)>,
+    pub telemetry: Telemetry,
+}
+
+// Keeps a short window of recent sensor samples and attaches the closest one
+// to each frame. The merged telemetry becomes part of FrameMetadata, which is
+// covered by the frame hash and kept in the frame's `MetadataRecord`, so the
+// flight data is sealed with the footage.
+#[derive(Debug)]
+pub struct TelemetryMerger {
+    samples: VecDeque<SensorSample>,
+    capacity: usize,
+    max_skew_ms: u64,
+}
+
+impl TelemetryMerger {
+    pub fn new(config: &SensorConfig) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(config.buffer_size),
+            capacity: config.buffer_size.max(1),
+            max_skew_ms: config.max_skew_ms,
+        }
+    }
+
+    pub fn push(&mut self, sample: SensorSample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn nearest(&self, at_ms: u64) -> Option<&SensorSample> {
+        self.samples
+            .iter()
+            .filter(|s| s.received_at_ms.abs_diff(at_ms) <= self.max_skew_ms)
+            .min_by_key(|s| s.received_at_ms.abs_diff(at_ms))
+    }
+
+    pub fn merge_into(&self, frame: &mut VideoFrame) -> bool {
+        let Some(sample) = self.nearest(frame.timestamp * 1000) else {
+            return false;
+        };
+
+        if sample.location.is_some() {
+            frame.metadata.location = sample.location;
+        }
+        frame.metadata.telemetry = Some(sample.telemetry.clone());
+        true
+    }
+}
+
+pub fn spawn_sensor_feed(config: SensorConfig, merger: Arc<RwLock<TelemetryMerger>>) {
+    tokio::spawn(async move {
+        let result = match &config.source {
+            SensorSource::Nmea { path } => run_nmea_feed(path, merger).await,
+            SensorSource::Gpsd { address } => run_gpsd_feed(address, merger).await,
+            SensorSource::Mavlink { bind } => run_mavlink_feed(bind, merger).await,
+        };
+
+        if let Err(e) = result {
+            tracing::error!("Sensor feed stopped: {}", e);
+        }
+    });
+}
+
+async fn run_nmea_feed(path: &str, merger: Arc<RwLock<TelemetryMerger>>) -> Result<()> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut lines = BufReader::new(file).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        match parse_nmea_sentence(&line, now_ms()?) {
+            Ok(Some(sample)) => merger.write().await.push(sample),
+            Ok(None) => {}
+            Err(e) => tracing::debug!("Ignoring NMEA sentence: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_gpsd_feed(address: &str, merger: Arc<RwLock<TelemetryMerger>>) -> Result<()> {
+    let mut stream = tokio::net::TcpStream::connect(address).await?;
+    stream
+        .write_all(b"?WATCH={\"enable\":true,\"json\":true}\n")
+        .await?;
+
+    let mut lines = BufReader::new(stream).lines();
+    while let Some(line) = lines.next_line().await? {
+        match parse_gpsd_report(&line, now_ms()?) {
+            Ok(Some(sample)) => merger.write().await.push(sample),
+            Ok(None) => {}
+            Err(e) => tracing::debug!("Ignoring gpsd report: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_mavlink_feed(bind: &str, merger: Arc<RwLock<TelemetryMerger>>) -> Result<()> {
+    let socket = tokio::net::UdpSocket::bind(bind).await?;
+    let mut buffer = [0u8; 512];
+    let mut last_position: Option<SensorSample> = None;
+
+    loop {
+        let (len, _) = socket.recv_from(&mut buffer).await?;
+        let received_at_ms = now_ms()?;
+
+        let message = match parse_mavlink_frame(&buffer[..len]) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::debug!("Ignoring MAVLink frame: {}", e);
+                continue;
+            }
+        };
+
+        // Attitude arrives separately from position; fold it into the last fix
+        let mut sample = last_position.clone().unwrap_or(SensorSample {
+            received_at_ms,
+            location: None,
+            telemetry: Telemetry {
+                source: "mavlink".to_string(),
+                ..Telemetry::default()
+            },
+        });
+        sample.received_at_ms = received_at_ms;
+        sample.telemetry.sampled_at_ms = received_at_ms;
+
+        match message {
+            MavlinkMessage::GlobalPosition {
+                lat,
+                lon,
+                alt_m,
+                speed_mps,
+                heading_deg,
+            } => {
+                sample.location = Some((lat, lon));
+                sample.telemetry.altitude_m = Some(alt_m);
+                sample.telemetry.speed_mps = Some(speed_mps);
+                sample.telemetry.heading_deg = heading_deg;
+            }
+            MavlinkMessage::Attitude {
+                roll_deg,
+                pitch_deg,
+                yaw_deg,
+            } => {
+                sample.telemetry.roll_deg = Some(roll_deg);
+                sample.telemetry.pitch_deg = Some(pitch_deg);
+                sample.telemetry.yaw_deg = Some(yaw_deg);
+            }
+        }
+
+        last_position = Some(sample.clone());
+        merger.write().await.push(sample);
+    }
+}
+
+pub fn parse_nmea_sentence(line: &str, received_at_ms: u64) -> Result<Option<SensorSample>> {
+    let line = line.trim();
+    let body = line
+        .strip_prefix('$')
+        .ok_or_else(|| ImmutableEncryptionError::video("NMEA sentence must start with '$'"))?;
+
+    let (body, checksum) = body
+        .split_once('*')
+        .ok_or_else(|| ImmutableEncryptionError::video("NMEA sentence has no checksum"))?;
+
+    let expected = u8::from_str_radix(checksum, 16)?;
+    let actual = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    if expected != actual {
+        return Err(ImmutableEncryptionError::Video(format!(
+            "NMEA checksum mismatch: expected {:02X}, got {:02X}",
+            expected, actual
+        )));
+    }
+
+    let fields: Vec<&str> = body.split(',').collect();
+    let sentence_type = fields[0].get(2..).unwrap_or_default();
+
+    let mut telemetry = Telemetry {
+        source: "nmea".to_string(),
+        sampled_at_ms: received_at_ms,
+        ..Telemetry::default()
+    };
+
+    let location = match sentence_type {
+        "GGA" if fields.len() >= 10 => {
+            let fix_quality: u8 = fields[6].parse().unwrap_or(0);
+            if fix_quality == 0 {
+                return Ok(None); // No fix
+            }
+            telemetry.fix_quality = Some(fix_quality);
+            telemetry.satellites = fields[7].parse().ok();
+            telemetry.altitude_m = fields[9].parse().ok();
+            parse_nmea_position(fields[2], fields[3], fields[4], fields[5])?
+        }
+        "RMC" if fields.len() >= 9 => {
+            if fields[2] != "A" {
+                return Ok(None); // Receiver warning, position not valid
+            }
+            telemetry.speed_mps = fields[7].parse::<f64>().ok().map(|knots| knots * 0.514444);
+            telemetry.heading_deg = fields[8].parse().ok();
+            parse_nmea_position(fields[3], fields[4], fields[5], fields[6])?
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(SensorSample {
+        received_at_ms,
+        location: Some(location),
+        telemetry,
+    }))
+}
+
+fn parse_nmea_position(lat: &str, lat_hemi: &str, lon: &str, lon_hemi: &str) -> Result<(f64, f64)> {
+    // NMEA encodes ddmm.mmmm / dddmm.mmmm
+    fn to_degrees(value: &str, degree_digits: usize) -> Result<f64> {
+        if value.len() < degree_digits {
+            return Err(ImmutableEncryptionError::Video(format!(
+                "Malformed NMEA coordinate: {}",
+                value
+            )));
+        }
+        let degrees: f64 = value[..degree_digits].parse()?;
+        let minutes: f64 = value[degree_digits..].parse()?;
+        Ok(degrees + minutes / 60.0)
+    }
+
+    let mut latitude = to_degrees(lat, 2)?;
+    let mut longitude = to_degrees(lon, 3)?;
+
+    if lat_hemi == "S" {
+        latitude = -latitude;
+    }
+    if lon_hemi == "W" {
+        longitude = -longitude;
+    }
+
+    Ok((latitude, longitude))
+}
+
+pub fn parse_gpsd_report(line: &str, received_at_ms: u64) -> Result<Option<SensorSample>> {
+    let report: serde_json::Value = serde_json::from_str(line)?;
+
+    if report["class"] != "TPV" {
+        return Ok(None);
+    }
+
+    // mode: 0/1 = no fix, 2 = 2D, 3 = 3D
+    let mode = report["mode"].as_u64().unwrap_or(0);
+    if mode < 2 {
+        return Ok(None);
+    }
+
+    let (lat, lon) = match (report["lat"].as_f64(), report["lon"].as_f64()) {
+        (Some(lat), Some(lon)) => (lat, lon),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(SensorSample {
+        received_at_ms,
+        location: Some((lat, lon)),
+        telemetry: Telemetry {
+            source: "gpsd".to_string(),
+            sampled_at_ms: received_at_ms,
+            altitude_m: report["altMSL"].as_f64().or_else(|| report["alt"].as_f64()),
+            speed_mps: report["speed"].as_f64(),
+            heading_deg: report["track"].as_f64(),
+            fix_quality: Some(mode as u8),
+            ..Telemetry::default()
+        },
+    }))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MavlinkMessage {
+    GlobalPosition {
+        lat: f64,
+        lon: f64,
+        alt_m: f64,
+        speed_mps: f64,
+        heading_deg: Option<f64>,
+    },
+    Attitude {
+        roll_deg: f64,
+        pitch_deg: f64,
+        yaw_deg: f64,
+    },
+}
+
+pub fn parse_mavlink_frame(frame: &[u8]) -> Result<MavlinkMessage> {
+    let (header_len, payload_len, msg_id) = match frame.first() {
+        Some(&MAVLINK_V1_MAGIC) if frame.len() >= 6 => (6, frame[1] as usize, frame[5] as u32),
+        Some(&MAVLINK_V2_MAGIC) if frame.len() >= 10 => {
+            let msg_id = u32::from_le_bytes([frame[7], frame[8], frame[9], 0]);
+            (10, frame[1] as usize, msg_id)
+        }
+        _ => return Err(ImmutableEncryptionError::video("Not a MAVLink frame")),
+    };
+
+    if frame.len() < header_len + payload_len + 2 {
+        return Err(ImmutableEncryptionError::video("Truncated MAVLink frame"));
+    }
+
+    let crc_extra = match msg_id {
+        MAVLINK_MSG_ATTITUDE => 39,
+        MAVLINK_MSG_GLOBAL_POSITION_INT => 104,
+        other => {
+            return Err(ImmutableEncryptionError::Video(format!(
+                "Unsupported MAVLink message {}",
+                other
+            )))
+        }
+    };
+
+    let checksum_offset = header_len + payload_len;
+    let expected = u16::from_le_bytes([frame[checksum_offset], frame[checksum_offset + 1]]);
+    let actual = mavlink_crc(&frame[1..checksum_offset], crc_extra);
+    if expected != actual {
+        return Err(ImmutableEncryptionError::video("MAVLink CRC mismatch"));
+    }
+
+    // MAVLink v2 truncates trailing zero bytes, so pad the payload back out
+    let mut payload = frame[header_len..checksum_offset].to_vec();
+    payload.resize(28, 0);
+
+    let i32_at =
+        |o: usize| i32::from_le_bytes([payload[o], payload[o + 1], payload[o + 2], payload[o + 3]]);
+    let i16_at = |o: usize| i16::from_le_bytes([payload[o], payload[o + 1]]);
+    let f32_at =
+        |o: usize| f32::from_le_bytes([payload[o], payload[o + 1], payload[o + 2], payload[o + 3]]);
+
+    match msg_id {
+        MAVLINK_MSG_GLOBAL_POSITION_INT => {
+            let vx = i16_at(20) as f64 / 100.0;
+            let vy = i16_at(22) as f64 / 100.0;
+            let heading = u16::from_le_bytes([payload[26], payload[27]]);
+
+            Ok(MavlinkMessage::GlobalPosition {
+                lat: i32_at(4) as f64 / 1e7,
+                lon: i32_at(8) as f64 / 1e7,
+                alt_m: i32_at(12) as f64 / 1000.0,
+                speed_mps: (vx * vx + vy * vy).sqrt(),
+                heading_deg: (heading != u16::MAX).then(|| heading as f64 / 100.0),
+            })
+        }
+        _ => Ok(MavlinkMessage::Attitude {
+            roll_deg: (f32_at(4) as f64).to_degrees(),
+            pitch_deg: (f32_at(8) as f64).to_degrees(),
+            yaw_deg: (f32_at(12) as f64).to_degrees(),
+        }),
+    }
+}
+
+// CRC-16/MCRF4XX (X.25) as used by MAVLink, seeded with the message CRC_EXTRA
+fn mavlink_crc(data: &[u8], crc_extra: u8) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data.iter().chain(std::iter::once(&crc_extra)) {
+        let mut tmp = byte ^ (crc & 0xFF) as u8;
+        tmp ^= tmp << 4;
+        crc = (crc >> 8) ^ ((tmp as u16) << 8) ^ ((tmp as u16) << 3) ^ ((tmp as u16) >> 4);
+    }
+    crc
+}
+
+fn now_ms() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nmea_gga() -> Result<()> {
+        let sentence = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+        let sample = parse_nmea_sentence(sentence, 1000)?.expect("GGA with fix");
+
+        let (lat, lon) = sample.location.unwrap();
+        assert!((lat - 48.1173).abs() < 1e-4);
+        assert!((lon - 11.516_667).abs() < 1e-4);
+        assert_eq!(sample.telemetry.satellites, Some(8));
+        assert_eq!(sample.telemetry.altitude_m, Some(545.4));
+
+        assert!(parse_nmea_sentence(&sentence.replace("*47", "*48"), 1000).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merger_picks_nearest_sample_within_skew() {
+        let mut merger = TelemetryMerger::new(&SensorConfig::default());
+        for (received_at_ms, lat) in [(999_700, 1.0), (1_000_100, 2.0), (1_002_000, 3.0)] {
+            merger.push(SensorSample {
+                received_at_ms,
+                location: Some((lat, 0.0)),
+                telemetry: Telemetry::default(),
+            });
+        }
+
+        assert_eq!(
+            merger.nearest(1_000_000).and_then(|s| s.location),
+            Some((2.0, 0.0))
+        );
+        assert!(merger.nearest(5_000_000).is_none());
+    }
+}
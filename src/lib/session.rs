@@ -0,0 +1,391 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::blockchain::MultiChainAnchor;
+use crate::crypto::EncryptionEngine;
+use crate::devices::{DevicePolicies, DevicePolicy};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::storage::DistributedStorage;
+use crate::{BlockchainAnchor, EncryptedFrame, FrameMetadata};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingSession {
+    pub session_id: String,
+    pub device_id: String,
+    pub case_id: Option<String>,
+    pub started_at: u64,
+    pub first_hash: Option<String>,
+    pub last_hash: Option<String>,
+    pub first_sequence: Option<u64>,
+    pub last_sequence: Option<u64>,
+    pub first_frame_timestamp: Option<u64>,
+    pub last_frame_timestamp: Option<u64>,
+    pub frame_count: u64,
+    #[serde(default)]
+    pub policy: Option<DevicePolicy>, // fixed when the session starts
+}
+
+impl RecordingSession {
+    pub fn record(&mut self, frame: &EncryptedFrame) {
+        if self.first_hash.is_none() {
+            self.first_hash = Some(frame.hash.clone());
+            self.first_sequence = Some(frame.sequence);
+            self.first_frame_timestamp = Some(frame.timestamp);
+        }
+        self.last_hash = Some(frame.hash.clone());
+        self.last_sequence = Some(frame.sequence);
+        self.last_frame_timestamp = Some(frame.timestamp);
+        self.frame_count += 1;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub session_id: String,
+    pub device_id: String,
+    pub case_id: Option<String>,
+    pub started_at: u64,
+    pub stopped_at: u64,
+    pub duration_secs: u64,
+    pub first_hash: Option<String>,
+    pub last_hash: Option<String>,
+    pub first_sequence: Option<u64>,
+    pub last_sequence: Option<u64>,
+    pub first_frame_timestamp: Option<u64>,
+    pub last_frame_timestamp: Option<u64>,
+    pub frame_count: u64,
+    pub manifest_hash: String,
+    pub signature: String,
+    pub anchors: Vec<BlockchainAnchor>,
+    // Omitted when unset so manifests from before device policies hash the same
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retain_until: Option<u64>,
+}
+
+impl SessionManifest {
+    pub fn from_session(
+        session: &RecordingSession,
+        stopped_at: u64,
+        engine: &EncryptionEngine,
+    ) -> Result<Self> {
+        let mut manifest = Self {
+            session_id: session.session_id.clone(),
+            device_id: session.device_id.clone(),
+            case_id: session.case_id.clone(),
+            started_at: session.started_at,
+            stopped_at,
+            duration_secs: stopped_at.saturating_sub(session.started_at),
+            first_hash: session.first_hash.clone(),
+            last_hash: session.last_hash.clone(),
+            first_sequence: session.first_sequence,
+            last_sequence: session.last_sequence,
+            first_frame_timestamp: session.first_frame_timestamp,
+            last_frame_timestamp: session.last_frame_timestamp,
+            frame_count: session.frame_count,
+            manifest_hash: String::new(),
+            signature: String::new(),
+            anchors: Vec::new(),
+            retain_until: session
+                .policy
+                .as_ref()
+                .map(|p| stopped_at.saturating_add(p.retention_days.saturating_mul(86_400))),
+        };
+
+        manifest.manifest_hash = manifest.compute_hash()?;
+        manifest.signature = engine.sign(manifest.manifest_hash.as_bytes());
+
+        Ok(manifest)
+    }
+
+    // Hash over everything except the hash itself, its signature and anchors
+    pub fn compute_hash(&self) -> Result<String> {
+        let mut body = self.clone();
+        body.manifest_hash = String::new();
+        body.signature = String::new();
+        body.anchors = Vec::new();
+
+        let serialized = serde_json::to_vec(&body)?;
+        Ok(hex::encode(Sha256::digest(&serialized)))
+    }
+
+    pub fn verify(&self, engine: &EncryptionEngine) -> Result<bool> {
+        Ok(self.compute_hash()? == self.manifest_hash
+            && engine.verify_signature(self.manifest_hash.as_bytes(), &self.signature))
+    }
+}
+
+pub struct SessionManager {
+    active: RwLock<HashMap<String, RecordingSession>>, // device_id -> session
+    engine: Arc<Mutex<EncryptionEngine>>,
+    blockchain: Arc<MultiChainAnchor>,
+    storage: Arc<DistributedStorage>,
+    rng: SystemRandom,
+    policies: DevicePolicies,
+}
+
+impl SessionManager {
+    pub fn new(
+        engine: Arc<Mutex<EncryptionEngine>>,
+        blockchain: Arc<MultiChainAnchor>,
+        storage: Arc<DistributedStorage>,
+    ) -> Self {
+        Self {
+            active: RwLock::new(HashMap::new()),
+            engine,
+            blockchain,
+            storage,
+            rng: SystemRandom::new(),
+            policies: DevicePolicies::default(),
+        }
+    }
+
+    pub fn with_policies(mut self, policies: DevicePolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    // The active session's policy, else what a new session would get
+    pub async fn policy(&self, device_id: &str) -> DevicePolicy {
+        self.active
+            .read()
+            .await
+            .get(device_id)
+            .and_then(|s| s.policy.clone())
+            .unwrap_or_else(|| self.policies.resolve(device_id))
+    }
+
+    fn session_key(session_id: &str) -> String {
+        format!("session:{}", session_id)
+    }
+
+    // Lets evidence listings filter by device and start time without a full scan
+    fn device_index_key(device_id: &str, started_at: u64, session_id: &str) -> String {
+        format!(
+            "session_index:{}:{:020}:{}",
+            device_id, started_at, session_id
+        )
+    }
+
+    pub async fn start_session(
+        &self,
+        device_id: &str,
+        case_id: Option<String>,
+    ) -> Result<RecordingSession> {
+        let mut active = self.active.write().await;
+        if let Some(existing) = active.get(device_id) {
+            return Err(ImmutableEncryptionError::ResourceUnavailable(format!(
+                "Device {} already has an active session {}",
+                device_id, existing.session_id
+            )));
+        }
+
+        let started_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+
+        let mut suffix = [0u8; 4];
+        self.rng
+            .fill(&mut suffix)
+            .map_err(|_| ImmutableEncryptionError::storage("Failed to generate session ID"))?;
+
+        let session = RecordingSession {
+            session_id: format!(
+                "session_{}_{}_{}",
+                device_id,
+                started_at,
+                hex::encode(suffix)
+            ),
+            device_id: device_id.to_string(),
+            case_id,
+            started_at,
+            first_hash: None,
+            last_hash: None,
+            first_sequence: None,
+            last_sequence: None,
+            first_frame_timestamp: None,
+            last_frame_timestamp: None,
+            frame_count: 0,
+            policy: Some(self.policies.resolve(device_id)),
+        };
+
+        self.storage
+            .put_record(&Self::session_key(&session.session_id), &session)
+            .await?;
+        self.storage
+            .put_record(
+                &Self::device_index_key(device_id, started_at, &session.session_id),
+                &session.session_id,
+            )
+            .await?;
+        active.insert(device_id.to_string(), session.clone());
+
+        tracing::info!("Started recording session {}", session.session_id);
+        Ok(session)
+    }
+
+    pub async fn record_frame(&self, frame: &EncryptedFrame) {
+        if let Some(session) = self.active.write().await.get_mut(&frame.device_id) {
+            session.record(frame);
+        }
+    }
+
+    pub async fn active_session(&self, device_id: &str) -> Option<RecordingSession> {
+        self.active.read().await.get(device_id).cloned()
+    }
+
+    pub async fn stop_session(&self, session_id: &str) -> Result<SessionManifest> {
+        let session = {
+            let mut active = self.active.write().await;
+            let device_id = active
+                .iter()
+                .find(|(_, s)| s.session_id == session_id)
+                .map(|(device_id, _)| device_id.clone())
+                .ok_or_else(|| {
+                    ImmutableEncryptionError::NotFound(format!("No active session {}", session_id))
+                })?;
+            active.remove(&device_id).expect("session present")
+        };
+
+        let stopped_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+
+        let mut manifest = {
+            let engine = self.engine.lock().await;
+            SessionManifest::from_session(&session, stopped_at, &engine)?
+        };
+
+        // The manifest itself is anchored so the session boundaries are provable
+        let anchor_metadata = FrameMetadata {
+            device_id: session.device_id.clone(),
+            location: None,
+            resolution: (0, 0),
+            fps: 0,
+            codec: "session-manifest".to_string(),
+            telemetry: None,
+        };
+        match self
+            .blockchain
+            .anchor_to_all_chains(&manifest.manifest_hash, &anchor_metadata)
+            .await
+        {
+            Ok(anchors) => manifest.anchors = anchors,
+            Err(e) => tracing::error!(
+                "Failed to anchor manifest for session {}: {}",
+                session_id,
+                e
+            ),
+        }
+
+        self.storage
+            .put_record(&Self::session_key(session_id), &session)
+            .await?;
+        self.storage
+            .put_record(&manifest_key(session_id), &manifest)
+            .await?;
+
+        tracing::info!(
+            "Stopped recording session {} after {} frames",
+            session_id,
+            manifest.frame_count
+        );
+        Ok(manifest)
+    }
+
+    pub async fn manifest(&self, session_id: &str) -> Result<Option<SessionManifest>> {
+        load_manifest(&self.storage, session_id).await
+    }
+
+    // Live state for active sessions, the stored record otherwise
+    pub async fn session(&self, session_id: &str) -> Result<Option<RecordingSession>> {
+        if let Some(active) = self
+            .active
+            .read()
+            .await
+            .values()
+            .find(|s| s.session_id == session_id)
+        {
+            return Ok(Some(active.clone()));
+        }
+
+        self.storage
+            .get_record(&Self::session_key(session_id))
+            .await
+    }
+}
+
+fn manifest_key(session_id: &str) -> String {
+    format!("manifest:{}", session_id)
+}
+
+// A sealed session's manifest straight from storage, for tools that don't run
+// a node (and so hold no keys)
+pub async fn load_manifest(
+    storage: &DistributedStorage,
+    session_id: &str,
+) -> Result<Option<SessionManifest>> {
+    storage.get_record(&manifest_key(session_id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+
+    fn frame(sequence: u64, hash: &str) -> EncryptedFrame {
+        EncryptedFrame {
+            sequence,
+            device_id: "bodycam_7".to_string(),
+            ciphertext: vec![1, 2, 3],
+            hash: hash.to_string(),
+            previous_hash: "0".repeat(64),
+            nonce: vec![0; 12],
+            timestamp: 1000 + sequence,
+            blockchain_anchors: vec![],
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        }
+    }
+
+    #[test]
+    fn test_session_manifest_signing() -> Result<()> {
+        let engine = EncryptionEngine::new(CryptoConfig::software(vec![7u8; 32], 1))?;
+
+        let mut session = RecordingSession {
+            session_id: "session_bodycam_7_1000_00000000".to_string(),
+            device_id: "bodycam_7".to_string(),
+            case_id: Some("CASE-42".to_string()),
+            started_at: 1000,
+            first_hash: None,
+            last_hash: None,
+            first_sequence: None,
+            last_sequence: None,
+            first_frame_timestamp: None,
+            last_frame_timestamp: None,
+            frame_count: 0,
+            policy: None,
+        };
+        session.record(&frame(1, &"a".repeat(64)));
+        session.record(&frame(2, &"b".repeat(64)));
+
+        let manifest = SessionManifest::from_session(&session, 1030, &engine)?;
+
+        assert_eq!(manifest.frame_count, 2);
+        assert_eq!(manifest.duration_secs, 30);
+        assert_eq!(manifest.first_hash, Some("a".repeat(64)));
+        assert_eq!(manifest.last_hash, Some("b".repeat(64)));
+        assert!(manifest.verify(&engine)?);
+
+        let mut tampered = manifest.clone();
+        tampered.frame_count = 1;
+        assert!(!tampered.verify(&engine)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+#[cfg(feature = "otlp")]
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+#[cfg(feature = "otlp")]
+use opentelemetry::{
+    metrics::{Counter, Histogram, Unit},
+    KeyValue,
+};
+
+use crate::metrics::{self, OperationStats};
+use crate::storage::StorageUsage;
+use crate::BlockchainAnchor;
+
+// Live counters updated by the pipeline and read by `/status`
+#[derive(Debug, Default)]
+pub struct PipelineStats {
+    started_at: u64,
+    frames_processed: AtomicU64,
+    frames_failed: AtomicU64,
+    frames_stored: AtomicU64,
+    bytes_encrypted: AtomicU64,
+    batches_anchored: AtomicU64,
+    batches_timed: AtomicU64,
+    batch_latency_ms_total: AtomicU64,
+    last_batch_latency_ms: AtomicU64,
+    batch_queue_depth: AtomicUsize,
+    device_sequences: RwLock<HashMap<String, u64>>,
+    last_anchors: RwLock<HashMap<String, BlockchainAnchor>>, // chain -> most recent
+}
+
+impl PipelineStats {
+    pub fn new() -> Self {
+        Self {
+            started_at: now(),
+            ..Default::default()
+        }
+    }
+
+    pub async fn record_sealed(&self, device_id: &str, sequence: u64, ciphertext_bytes: u64) {
+        self.frames_processed.fetch_add(1, Ordering::Relaxed);
+        self.bytes_encrypted
+            .fetch_add(ciphertext_bytes, Ordering::Relaxed);
+        #[cfg(feature = "otlp")]
+        {
+            instruments().frames_processed.add(1, &[]);
+            instruments().bytes_encrypted.add(ciphertext_bytes, &[]);
+        }
+        let mut sequences = self.device_sequences.write().await;
+        let current = sequences.entry(device_id.to_string()).or_insert(sequence);
+        *current = (*current).max(sequence);
+    }
+
+    pub fn record_failed(&self) {
+        self.frames_failed.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "otlp")]
+        instruments().frames_failed.add(1, &[]);
+    }
+
+    pub fn record_stored(&self) {
+        self.frames_stored.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "otlp")]
+        instruments().frames_stored.add(1, &[]);
+    }
+
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.batch_queue_depth.store(depth, Ordering::Relaxed);
+    }
+
+    // Time taken to anchor one batch across every chain, failed or not
+    pub fn record_batch_latency(&self, latency: Duration) {
+        let millis = latency.as_millis() as u64;
+        self.batches_timed.fetch_add(1, Ordering::Relaxed);
+        self.batch_latency_ms_total
+            .fetch_add(millis, Ordering::Relaxed);
+        self.last_batch_latency_ms.store(millis, Ordering::Relaxed);
+        #[cfg(feature = "otlp")]
+        instruments()
+            .batch_latency
+            .record(latency.as_secs_f64() * 1000.0, &[]);
+    }
+
+    // Exported as a histogram only; `/status` reports batch latency
+    pub fn record_encrypt_latency(&self, latency: Duration) {
+        #[cfg(feature = "otlp")]
+        instruments()
+            .encrypt_latency
+            .record(latency.as_secs_f64() * 1000.0, &[]);
+        #[cfg(not(feature = "otlp"))]
+        let _ = latency;
+    }
+
+    pub async fn record_batch_anchored(&self, anchors: &[BlockchainAnchor]) {
+        self.batches_anchored.fetch_add(1, Ordering::Relaxed);
+        let mut last = self.last_anchors.write().await;
+        for anchor in anchors {
+            #[cfg(feature = "otlp")]
+            instruments()
+                .anchors
+                .add(1, &[KeyValue::new("chain", anchor.chain.clone())]);
+            let newer = last
+                .get(&anchor.chain)
+                .map_or(true, |prev| anchor.timestamp >= prev.timestamp);
+            if newer {
+                last.insert(anchor.chain.clone(), anchor.clone());
+            }
+        }
+    }
+
+    pub async fn last_anchors(&self) -> Vec<BlockchainAnchor> {
+        let mut anchors: Vec<_> = self.last_anchors.read().await.values().cloned().collect();
+        anchors.sort_by(|a, b| a.chain.cmp(&b.chain));
+        anchors
+    }
+
+    // Counters only; the caller fills in anchor confirmations, storage and keys
+    pub async fn snapshot(&self) -> NodeStatus {
+        let timestamp = now();
+        NodeStatus {
+            node: "running".to_string(),
+            timestamp,
+            uptime_secs: timestamp.saturating_sub(self.started_at),
+            frames_processed: self.frames_processed.load(Ordering::Relaxed),
+            frames_failed: self.frames_failed.load(Ordering::Relaxed),
+            frames_stored: self.frames_stored.load(Ordering::Relaxed),
+            bytes_encrypted: self.bytes_encrypted.load(Ordering::Relaxed),
+            batches_anchored: self.batches_anchored.load(Ordering::Relaxed),
+            last_batch_latency_ms: self.last_batch_latency_ms.load(Ordering::Relaxed),
+            mean_batch_latency_ms: self
+                .batch_latency_ms_total
+                .load(Ordering::Relaxed)
+                .checked_div(self.batches_timed.load(Ordering::Relaxed))
+                .unwrap_or(0),
+            batch_queue_depth: self.batch_queue_depth.load(Ordering::Relaxed),
+            device_sequences: self
+                .device_sequences
+                .read()
+                .await
+                .iter()
+                .map(|(device, sequence)| (device.clone(), *sequence))
+                .collect(),
+            last_anchors: Vec::new(),
+            storage: None,
+            key_epoch: 0,
+            operations: metrics::global().snapshot(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnchorStatus {
+    pub chain: String,
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub anchored_at: u64,
+    pub confirmations: Option<u64>, // None when the chain could not be queried
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeStatus {
+    pub node: String,
+    pub timestamp: u64,
+    pub uptime_secs: u64,
+    pub frames_processed: u64,
+    pub frames_failed: u64,
+    pub frames_stored: u64,
+    pub bytes_encrypted: u64, // ciphertext produced
+    pub batches_anchored: u64,
+    pub last_batch_latency_ms: u64,
+    pub mean_batch_latency_ms: u64,
+    pub batch_queue_depth: usize,
+    pub device_sequences: BTreeMap<String, u64>, // device -> highest sealed sequence
+    pub last_anchors: Vec<AnchorStatus>,
+    pub storage: Option<StorageUsage>,
+    pub key_epoch: u64,
+    pub operations: Vec<OperationStats>, // per-module counts and latency
+}
+
+// OTLP mirrors of the counters above. They stay no-ops unless `trace::init`
+// installed a meter provider before the first frame was recorded.
+#[cfg(feature = "otlp")]
+struct Instruments {
+    frames_processed: Counter<u64>,
+    frames_failed: Counter<u64>,
+    frames_stored: Counter<u64>,
+    bytes_encrypted: Counter<u64>,
+    anchors: Counter<u64>,           // per chain
+    encrypt_latency: Histogram<f64>, // ms per frame
+    batch_latency: Histogram<f64>,   // ms per batch, all chains
+}
+
+#[cfg(feature = "otlp")]
+fn instruments() -> &'static Instruments {
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+    INSTRUMENTS.get_or_init(|| {
+        let meter = opentelemetry::global::meter("immutable-encryption");
+        let counter = |name: &'static str, description: &'static str| {
+            meter.u64_counter(name).with_description(description).init()
+        };
+        let millis = |name: &'static str, description: &'static str| {
+            meter
+                .f64_histogram(name)
+                .with_description(description)
+                .with_unit(Unit::new("ms"))
+                .init()
+        };
+        Instruments {
+            frames_processed: counter("frames.processed", "Frames encrypted and sealed"),
+            frames_failed: counter("frames.failed", "Frames that failed processing"),
+            frames_stored: counter("frames.stored", "Frames written to storage"),
+            bytes_encrypted: counter("bytes.encrypted", "Ciphertext bytes produced"),
+            anchors: counter("anchors", "Anchors recorded on chain"),
+            encrypt_latency: millis("frame.encrypt.duration", "Time to encrypt one frame"),
+            batch_latency: millis("batch.anchor.duration", "Time to anchor one batch"),
+        }
+    })
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stats_track_latest_state() {
+        let stats = PipelineStats::new();
+
+        stats.record_sealed("cam_1", 4, 100).await;
+        stats.record_sealed("cam_1", 3, 100).await;
+        stats.record_sealed("cam_2", 9, 50).await;
+        stats.record_failed();
+        stats.set_queue_depth(2);
+        stats.record_batch_latency(Duration::from_millis(300));
+        stats.record_batch_latency(Duration::from_millis(100));
+
+        let anchor = |chain: &str, tx: &str, timestamp| BlockchainAnchor {
+            chain: chain.to_string(),
+            transaction_hash: tx.to_string(),
+            block_number: 1,
+            timestamp,
+            proof: String::new(),
+        };
+        stats
+            .record_batch_anchored(&[anchor("bitcoin", "a", 10), anchor("ethereum", "b", 10)])
+            .await;
+        stats
+            .record_batch_anchored(&[anchor("bitcoin", "c", 20)])
+            .await;
+
+        let status = stats.snapshot().await;
+        assert_eq!(status.frames_processed, 3);
+        assert_eq!(status.frames_failed, 1);
+        assert_eq!(status.batch_queue_depth, 2);
+        assert_eq!(status.batches_anchored, 2);
+        assert_eq!(status.bytes_encrypted, 250);
+        assert_eq!(status.last_batch_latency_ms, 100);
+        assert_eq!(status.mean_batch_latency_ms, 200);
+        assert_eq!(status.device_sequences.get("cam_1"), Some(&4));
+
+        let last: Vec<_> = stats
+            .last_anchors()
+            .await
+            .into_iter()
+            .map(|a| a.transaction_hash)
+            .collect();
+        assert_eq!(last, vec!["c".to_string(), "b".to_string()]);
+    }
+}
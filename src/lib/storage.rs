@@ -1,12 +1,32 @@
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use rocksdb::{Options, WriteBatch, DB};
-use serde::{Deserialize, Serialize};
+use rocksdb::{Direction, IteratorMode, Options, WriteBatch, DB};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 
-use crate::{CourtReport, EncryptedFrame, StorageBackend};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::metrics::{self, Module};
+use crate::migration::{self, MigrationReport, RecordKind, FORMAT_VERSION, SCHEMA_VERSION_KEY};
+use crate::retry::RetryPolicy;
+use crate::uring::UringWriter;
+use crate::wire::{self, FrameEncoding, StoredFrame};
+use crate::{CourtReport, CustodyEntry, EncryptedFrame, StorageBackend};
+
+const HEALTH_PROBE_KEY: &str = "health:probe";
+const MIGRATION_BATCH: usize = 512;
+
+// Where a device's frame is stored. Frames written before keys carried the
+// device were stored under `frame:{seq}:{ts}`; the device index still points
+// at those.
+pub fn frame_key(device_id: &str, sequence: u64, timestamp: u64) -> String {
+    format!("frame:{}:{}:{}", device_id, sequence, timestamp)
+}
+
+pub fn legacy_frame_key(sequence: u64, timestamp: u64) -> String {
+    format!("frame:{}:{}", sequence, timestamp)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
@@ -16,11 +36,23 @@ pub struct StorageConfig {
     pub backup_enabled: bool,
     pub backup_path: String,
     pub compression_enabled: bool,
+    pub frame_encoding: FrameEncoding, // for new frames; both are always readable
+    #[serde(default)]
+    pub backup_io_uring: bool, // batch local backup writes through io_uring
+}
+
+// RocksDB's own size estimates; cheap to read, but approximate
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageUsage {
+    pub live_data_bytes: u64,
+    pub sst_files_bytes: u64,
+    pub estimated_keys: u64,
 }
 
 pub struct RocksDBStorage {
     db: Arc<RwLock<DB>>,
     config: StorageConfig,
+    backup_writer: Option<UringWriter>,
 }
 
 impl RocksDBStorage {
@@ -30,21 +62,316 @@ impl RocksDBStorage {
         opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
 
         let db = DB::open(&opts, &config.database_path)?;
+        check_schema_version(&db)?;
+        let backup_writer = (config.backup_enabled && config.backup_io_uring)
+            .then(UringWriter::start)
+            .transpose()?;
 
         Ok(Self {
             db: Arc::new(RwLock::new(db)),
             config,
+            backup_writer,
         })
     }
 
     fn generate_frame_key(&self, frame: &EncryptedFrame) -> String {
-        format!("frame:{}:{}", frame.sequence, frame.timestamp)
+        frame_key(&frame.device_id, frame.sequence, frame.timestamp)
     }
 
     fn generate_metadata_key(&self, evidence_id: &str) -> String {
         format!("metadata:{}", evidence_id)
     }
 
+    // Zero-padded so lexicographic key order matches (timestamp, sequence) order
+    fn generate_device_index_key(&self, device_id: &str, timestamp: u64, sequence: u64) -> String {
+        format!("device:{}:{:020}:{:020}", device_id, timestamp, sequence)
+    }
+
+    fn generate_custody_key(&self, scope: &str, entry: &CustodyEntry) -> String {
+        let digest = blake3::hash(entry.signature.as_bytes());
+        format!(
+            "custody:{}:{:020}:{}",
+            scope,
+            entry.timestamp,
+            &hex::encode(digest.as_bytes())[..16]
+        )
+    }
+
+    // For chain checks, which read an rkyv frame without decoding it
+    pub async fn retrieve_stored_frame(&self, frame_id: &str) -> Result<StoredFrame> {
+        let db = self.db.read().await;
+
+        match db.get(frame_id)? {
+            Some(value) => StoredFrame::read(value),
+            None => Err(ImmutableEncryptionError::FrameNotFound {
+                frame_id: frame_id.to_string(),
+            }),
+        }
+    }
+
+    // The key the device index holds for a frame, which may predate `frame_key`
+    pub async fn indexed_frame_key(
+        &self,
+        device_id: &str,
+        sequence: u64,
+        timestamp: u64,
+    ) -> Result<Option<String>> {
+        let index_key = self.generate_device_index_key(device_id, timestamp, sequence);
+        match self.db.read().await.get(&index_key)? {
+            Some(value) => {
+                let frame_key = migration::upgrade(RecordKind::Reference, &value)?;
+                Ok(Some(String::from_utf8(frame_key.into_owned())?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_device_frames(
+        &self,
+        device_id: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<String>> {
+        let prefix = format!("device:{}:", device_id);
+        let start = self.generate_device_index_key(device_id, from, 0);
+
+        let db = self.db.read().await;
+        let mut frame_keys = Vec::new();
+
+        for item in db.iterator(IteratorMode::From(start.as_bytes(), Direction::Forward)) {
+            let (key, value) = item?;
+            let key = String::from_utf8_lossy(&key);
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let timestamp: u64 = key[prefix.len()..prefix.len() + 20].parse()?;
+            if timestamp > to {
+                break;
+            }
+
+            let frame_key = migration::upgrade(RecordKind::Reference, &value)?;
+            frame_keys.push(String::from_utf8(frame_key.into_owned())?);
+        }
+
+        Ok(frame_keys)
+    }
+
+    pub async fn usage(&self) -> Result<StorageUsage> {
+        let db = self.db.read().await;
+        let property =
+            |name: &str| -> Result<u64> { Ok(db.property_int_value(name)?.unwrap_or(0)) };
+
+        Ok(StorageUsage {
+            live_data_bytes: property("rocksdb.estimate-live-data-size")?,
+            sst_files_bytes: property("rocksdb.total-sst-files-size")?,
+            estimated_keys: property("rocksdb.estimate-num-keys")?,
+        })
+    }
+
+    // Up to `limit` entries under `prefix`, starting at `from_key`
+    // (inclusive), with values upgraded to the current format. Callers resume
+    // from the last key returned plus "\0".
+    pub async fn scan_page_raw(
+        &self,
+        prefix: &str,
+        from_key: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let start = if from_key < prefix { prefix } else { from_key };
+
+        let db = self.db.read().await;
+        let mut entries = Vec::new();
+
+        for item in db.iterator(IteratorMode::From(start.as_bytes(), Direction::Forward)) {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) || entries.len() == limit {
+                break;
+            }
+            let key = String::from_utf8(key.to_vec())?;
+            let value = migration::upgrade(RecordKind::of(&key), &value)?.into_owned();
+            entries.push((key, value));
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn put_record<T: Serialize>(&self, key: &str, record: &T) -> Result<()> {
+        let serialized = migration::tag(serde_json::to_vec(record)?);
+        let db = self.db.read().await;
+        db.put(key, &serialized)?;
+        Ok(())
+    }
+
+    pub async fn get_record<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let db = self.db.read().await;
+        match db.get(key)? {
+            Some(data) => Ok(Some(serde_json::from_slice(&migration::upgrade(
+                RecordKind::of(key),
+                &data,
+            )?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn delete_record(&self, key: &str) -> Result<()> {
+        let db = self.db.read().await;
+        db.delete(key)?;
+        Ok(())
+    }
+
+    // Removes a frame with its index entry, IPFS reference and local backup.
+    // Whether it may go is the caller's decision.
+    pub async fn delete_frame(&self, frame: &EncryptedFrame) -> Result<()> {
+        let key = self
+            .indexed_frame_key(&frame.device_id, frame.sequence, frame.timestamp)
+            .await?
+            .unwrap_or_else(|| self.generate_frame_key(frame));
+        let mut batch = WriteBatch::default();
+        batch.delete(&key);
+        batch.delete(self.generate_device_index_key(
+            &frame.device_id,
+            frame.timestamp,
+            frame.sequence,
+        ));
+        batch.delete(format!("ipfs:{}", key));
+        self.db.read().await.write(batch)?;
+
+        if self.config.backup_enabled {
+            let backup_path = Path::new(&self.config.backup_path).join(format!("{}.bak", key));
+            if let Err(e) = std::fs::remove_file(backup_path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Round-trips a scratch record, so a read-only or full volume is caught
+    pub async fn probe_writable(&self) -> Result<()> {
+        let value = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos()
+            .to_be_bytes();
+
+        let db = self.db.read().await;
+        db.put(HEALTH_PROBE_KEY, value)?;
+        let read = db.get(HEALTH_PROBE_KEY)?;
+        db.delete(HEALTH_PROBE_KEY)?;
+
+        if read.as_deref() != Some(&value[..]) {
+            return Err(ImmutableEncryptionError::storage(
+                "Health probe record did not read back",
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn scan_records<T: DeserializeOwned>(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<(String, T)>> {
+        let db = self.db.read().await;
+        let mut records = Vec::new();
+
+        for item in db.prefix_iterator(prefix.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let key = String::from_utf8(key.to_vec())?;
+            let record =
+                serde_json::from_slice(&migration::upgrade(RecordKind::of(&key), &value)?)?;
+            records.push((key, record));
+        }
+
+        Ok(records)
+    }
+
+    pub async fn append_custody_entry(&self, scope: &str, entry: &CustodyEntry) -> Result<String> {
+        let key = self.generate_custody_key(scope, entry);
+        let serialized = migration::tag(serde_json::to_vec(entry)?);
+
+        let db = self.db.read().await;
+        db.put(&key, &serialized)?;
+
+        Ok(key)
+    }
+
+    pub async fn custody_entries(&self, scope: &str) -> Result<Vec<CustodyEntry>> {
+        let prefix = format!("custody:{}:", scope);
+
+        let db = self.db.read().await;
+        let mut entries = Vec::new();
+
+        for item in db.prefix_iterator(prefix.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            entries.push(serde_json::from_slice(&migration::upgrade(
+                RecordKind::Custody,
+                &value,
+            )?)?);
+        }
+
+        Ok(entries)
+    }
+
+    // Rewrites every value an older build wrote in the current format, then
+    // records the database as migrated. Payloads are only upgraded and
+    // re-tagged; frames keep their encoding, so their hashes are unaffected.
+    pub async fn migrate(&self, dry_run: bool) -> Result<MigrationReport> {
+        let db = self.db.write().await; // nothing else writes mid-migration
+        let mut report = MigrationReport {
+            schema_version: stored_schema_version(&db)?,
+            target_version: FORMAT_VERSION,
+            dry_run,
+            ..Default::default()
+        };
+
+        let mut batch = WriteBatch::default();
+        for item in db.iterator(IteratorMode::Start) {
+            let (key, value) = item?;
+            let name = String::from_utf8_lossy(&key);
+            if name == SCHEMA_VERSION_KEY || name == HEALTH_PROBE_KEY {
+                continue;
+            }
+            report.scanned += 1;
+            if migration::version(&value) == FORMAT_VERSION {
+                continue;
+            }
+
+            let kind = RecordKind::of(&name);
+            match migration::upgrade(kind, &value) {
+                Ok(payload) => {
+                    *report.upgraded.entry(kind).or_default() += 1;
+                    batch.put(&key, migration::tag(payload.into_owned()));
+                }
+                Err(e) => {
+                    tracing::warn!("Cannot migrate {}: {}", name, e);
+                    report.failed.push(name.to_string());
+                }
+            }
+            if batch.len() >= MIGRATION_BATCH {
+                let full = std::mem::take(&mut batch);
+                if !dry_run {
+                    db.write(full)?;
+                }
+            }
+        }
+
+        if !dry_run {
+            db.write(batch)?;
+            // A partial run stays unmarked, so the next start still warns
+            if report.failed.is_empty() {
+                db.put(SCHEMA_VERSION_KEY, serde_json::to_vec(&FORMAT_VERSION)?)?;
+            }
+        }
+        Ok(report)
+    }
+
     async fn backup_to_ipfs(&self, data: &[u8]) -> Result<String> {
         if !self.config.ipfs_enabled {
             return Ok("".to_string());
@@ -64,8 +391,46 @@ impl RocksDBStorage {
         use std::fs;
         let backup_path = Path::new(&self.config.backup_path).join(format!("{}.bak", key));
 
-        fs::write(backup_path, data)?;
-        Ok(())
+        match &self.backup_writer {
+            Some(writer) => writer.write(backup_path, data.to_vec()).await,
+            None => {
+                fs::write(backup_path, data)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn stored_schema_version(db: &DB) -> Result<Option<u8>> {
+    match db.get(SCHEMA_VERSION_KEY)? {
+        Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+// Refuses a database a newer build has migrated, marks a new one as current
+// and warns about one that still needs `migrate`
+fn check_schema_version(db: &DB) -> Result<()> {
+    match stored_schema_version(db)? {
+        Some(version) if version > FORMAT_VERSION => {
+            Err(ImmutableEncryptionError::storage(&format!(
+                "Database is at format version {}; this node reads up to {}",
+                version, FORMAT_VERSION
+            )))
+        }
+        Some(FORMAT_VERSION) => Ok(()),
+        None if db.iterator(IteratorMode::Start).next().is_none() => {
+            db.put(SCHEMA_VERSION_KEY, serde_json::to_vec(&FORMAT_VERSION)?)?;
+            Ok(())
+        }
+        _ => {
+            tracing::warn!(
+                "Database predates format version {}; records are upgraded as they are read \
+                 until `encryption-node migrate` rewrites them",
+                FORMAT_VERSION
+            );
+            Ok(())
+        }
     }
 }
 
@@ -73,7 +438,7 @@ impl RocksDBStorage {
 impl StorageBackend for RocksDBStorage {
     async fn store_frame(&self, frame: &EncryptedFrame) -> Result<String> {
         let key = self.generate_frame_key(frame);
-        let serialized = serde_json::to_vec(frame)?;
+        let serialized = migration::tag(wire::encode_frame(frame, self.config.frame_encoding)?);
 
         // Compress if enabled
         let data = if self.config.compression_enabled {
@@ -83,9 +448,15 @@ impl StorageBackend for RocksDBStorage {
             serialized.len()
         };
 
-        // Store to RocksDB
+        // Store to RocksDB along with the device/time index entry
+        let index_key =
+            self.generate_device_index_key(&frame.device_id, frame.timestamp, frame.sequence);
+        let mut batch = WriteBatch::default();
+        batch.put(&key, &serialized);
+        batch.put(&index_key, migration::tag(key.as_bytes().to_vec()));
+
         let db = self.db.read().await;
-        db.put(&key, &serialized)?;
+        db.write(batch)?;
 
         // Create backups
         let ipfs_cid = self.backup_to_ipfs(&serialized).await?;
@@ -93,7 +464,10 @@ impl StorageBackend for RocksDBStorage {
 
         // Store backup references
         if !ipfs_cid.is_empty() {
-            db.put(&format!("ipfs:{}", key), ipfs_cid.as_bytes())?;
+            db.put(
+                format!("ipfs:{}", key),
+                migration::tag(ipfs_cid.into_bytes()),
+            )?;
         }
 
         Ok(key)
@@ -103,17 +477,16 @@ impl StorageBackend for RocksDBStorage {
         let db = self.db.read().await;
 
         match db.get(frame_id)? {
-            Some(data) => {
-                let frame: EncryptedFrame = serde_json::from_slice(&data)?;
-                Ok(frame)
-            }
-            None => Err(anyhow!("Frame not found: {}", frame_id)),
+            Some(data) => wire::decode_frame(&migration::upgrade(RecordKind::Frame, &data)?),
+            None => Err(ImmutableEncryptionError::FrameNotFound {
+                frame_id: frame_id.to_string(),
+            }),
         }
     }
 
     async fn store_metadata(&self, metadata: &CourtReport) -> Result<String> {
         let key = self.generate_metadata_key(&metadata.evidence_id);
-        let serialized = serde_json::to_vec(metadata)?;
+        let serialized = migration::tag(serde_json::to_vec(metadata)?);
 
         let db = self.db.read().await;
         db.put(&key, &serialized)?;
@@ -154,11 +527,17 @@ impl IPFSStorage {
         let result: serde_json::Value = response.json().await?;
         let cid = result["Hash"]
             .as_str()
-            .ok_or_else(|| anyhow!("Invalid IPFS response"))?;
+            .ok_or_else(|| ImmutableEncryptionError::storage("Invalid IPFS response"))?;
 
         Ok(cid.to_string())
     }
 
+    pub async fn probe(&self) -> Result<()> {
+        let url = format!("{}/api/v0/version", self.config.ipfs_api_url);
+        self.client.post(&url).send().await?.error_for_status()?;
+        Ok(())
+    }
+
     async fn get_from_ipfs(&self, cid: &str) -> Result<Vec<u8>> {
         let url = format!("{}/api/v0/cat/{}", self.config.ipfs_api_url, cid);
 
@@ -171,6 +550,8 @@ impl IPFSStorage {
 pub struct DistributedStorage {
     primary: RocksDBStorage,
     backup: IPFSStorage,
+    retry: RetryPolicy,
+    frame_writes: Mutex<HashMap<String, u64>>, // device_id -> frames stored or deleted this run
 }
 
 impl DistributedStorage {
@@ -178,40 +559,180 @@ impl DistributedStorage {
         let primary = RocksDBStorage::new(config.clone())?;
         let backup = IPFSStorage::new(config);
 
-        Ok(Self { primary, backup })
+        Ok(Self {
+            primary,
+            backup,
+            retry: RetryPolicy::default(),
+            frame_writes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Changes after any of the device's frames is stored or deleted, so
+    // whatever was derived from its frames can tell when it went stale
+    pub fn frame_generation(&self, device_id: &str) -> u64 {
+        let writes = self.frame_writes.lock().unwrap_or_else(|e| e.into_inner());
+        writes.get(device_id).copied().unwrap_or(0)
+    }
+
+    fn frame_written(&self, device_id: &str) {
+        let mut writes = self.frame_writes.lock().unwrap_or_else(|e| e.into_inner());
+        *writes.entry(device_id.to_string()).or_default() += 1;
     }
 
     pub async fn store_with_redundancy(&self, frame: &EncryptedFrame) -> Result<Vec<String>> {
-        let mut locations = Vec::new();
+        metrics::timed_async(Module::Storage, "store", async {
+            let mut locations = Vec::new();
+
+            // Store to primary storage. Counted once written, or once it
+            // failed, which may have written it all the same.
+            let stored = self
+                .retry
+                .run("Frame store", || self.primary.store_frame(frame))
+                .await;
+            self.frame_written(&frame.device_id);
+            locations.push(stored?);
+
+            // Store to IPFS backup; retrying a node that isn't configured only
+            // delays the batch
+            if self.ipfs_enabled() {
+                let serialized = migration::tag(wire::encode_frame(
+                    frame,
+                    self.backup.config.frame_encoding,
+                )?);
+                let ipfs_cid = self
+                    .retry
+                    .run("IPFS add", || self.backup.add_to_ipfs(&serialized))
+                    .await?;
+                locations.push(format!("ipfs:{}", ipfs_cid));
+            }
+
+            Ok(locations)
+        })
+        .await
+    }
+
+    pub async fn list_device_frames(
+        &self,
+        device_id: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<String>> {
+        self.primary.list_device_frames(device_id, from, to).await
+    }
+
+    pub async fn usage(&self) -> Result<StorageUsage> {
+        self.primary.usage().await
+    }
+
+    pub async fn probe_primary(&self) -> Result<()> {
+        self.primary.probe_writable().await
+    }
+
+    pub fn ipfs_enabled(&self) -> bool {
+        self.backup.config.ipfs_enabled
+    }
+
+    pub async fn probe_ipfs(&self) -> Result<()> {
+        self.backup.probe().await
+    }
+
+    pub async fn scan_page_raw(
+        &self,
+        prefix: &str,
+        from_key: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        self.primary.scan_page_raw(prefix, from_key, limit).await
+    }
+
+    pub async fn put_record<T: Serialize>(&self, key: &str, record: &T) -> Result<()> {
+        self.primary.put_record(key, record).await
+    }
 
-        // Store to primary storage
-        let primary_key = self.primary.store_frame(frame).await?;
-        locations.push(primary_key);
+    pub async fn get_record<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        self.primary.get_record(key).await
+    }
+
+    pub async fn delete_record(&self, key: &str) -> Result<()> {
+        self.primary.delete_record(key).await
+    }
 
-        // Store to IPFS backup
-        let serialized = serde_json::to_vec(frame)?;
-        let ipfs_cid = self.backup.add_to_ipfs(&serialized).await?;
-        locations.push(format!("ipfs:{}", ipfs_cid));
+    pub async fn delete_frame(&self, frame: &EncryptedFrame) -> Result<()> {
+        let deleted = self.primary.delete_frame(frame).await;
+        self.frame_written(&frame.device_id);
+        deleted
+    }
+
+    pub async fn scan_records<T: DeserializeOwned>(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<(String, T)>> {
+        self.primary.scan_records(prefix).await
+    }
 
-        Ok(locations)
+    pub async fn append_custody_entry(&self, scope: &str, entry: &CustodyEntry) -> Result<String> {
+        self.primary.append_custody_entry(scope, entry).await
+    }
+
+    pub async fn custody_entries(&self, scope: &str) -> Result<Vec<CustodyEntry>> {
+        self.primary.custody_entries(scope).await
+    }
+
+    pub async fn migrate(&self, dry_run: bool) -> Result<MigrationReport> {
+        self.primary.migrate(dry_run).await
+    }
+
+    // `retrieve_with_fallback` for chain checks; see `StoredFrame`
+    pub async fn retrieve_stored_with_fallback(&self, frame_id: &str) -> Result<StoredFrame> {
+        metrics::timed_async(Module::Storage, "retrieve", async {
+            match self.primary.retrieve_stored_frame(frame_id).await {
+                Ok(frame) => Ok(frame),
+                Err(_) if frame_id.starts_with("ipfs:") => {
+                    StoredFrame::read(self.backup.get_from_ipfs(&frame_id[5..]).await?)
+                }
+                Err(_) => Err(ImmutableEncryptionError::FrameNotFound {
+                    frame_id: frame_id.to_string(),
+                }),
+            }
+        })
+        .await
+    }
+
+    // Looks a frame up by device, sequence and seal time, wherever it was keyed
+    pub async fn retrieve_frame_at(
+        &self,
+        device_id: &str,
+        sequence: u64,
+        timestamp: u64,
+    ) -> Result<EncryptedFrame> {
+        let frame_id = self
+            .primary
+            .indexed_frame_key(device_id, sequence, timestamp)
+            .await?
+            .unwrap_or_else(|| frame_key(device_id, sequence, timestamp));
+        self.retrieve_with_fallback(&frame_id).await
     }
 
     pub async fn retrieve_with_fallback(&self, frame_id: &str) -> Result<EncryptedFrame> {
-        // Try primary first
-        match self.primary.retrieve_frame(frame_id).await {
-            Ok(frame) => Ok(frame),
-            Err(_) => {
-                // Fallback to IPFS
-                if frame_id.starts_with("ipfs:") {
-                    let cid = &frame_id[5..]; // Remove "ipfs:" prefix
-                    let data = self.backup.get_from_ipfs(cid).await?;
-                    let frame: EncryptedFrame = serde_json::from_slice(&data)?;
-                    Ok(frame)
-                } else {
-                    Err(anyhow!("Frame not found in any storage location"))
+        metrics::timed_async(Module::Storage, "retrieve", async {
+            // Try primary first
+            match self.primary.retrieve_frame(frame_id).await {
+                Ok(frame) => Ok(frame),
+                Err(_) => {
+                    // Fallback to IPFS
+                    if frame_id.starts_with("ipfs:") {
+                        let cid = &frame_id[5..]; // Remove "ipfs:" prefix
+                        let data = self.backup.get_from_ipfs(cid).await?;
+                        wire::decode_frame(&migration::upgrade(RecordKind::Frame, &data)?)
+                    } else {
+                        Err(ImmutableEncryptionError::FrameNotFound {
+                            frame_id: frame_id.to_string(),
+                        })
+                    }
                 }
             }
-        }
+        })
+        .await
     }
 }
 
@@ -230,18 +751,25 @@ mod tests {
             backup_enabled: false,
             backup_path: "".to_string(),
             compression_enabled: false,
+            frame_encoding: Default::default(),
+            backup_io_uring: false,
         };
 
         let storage = RocksDBStorage::new(config)?;
 
         let frame = EncryptedFrame {
             sequence: 1,
+            device_id: "test-camera".to_string(),
             ciphertext: vec![1, 2, 3, 4],
             hash: "test_hash".to_string(),
             previous_hash: "prev_hash".to_string(),
             nonce: vec![0, 1, 2, 3],
             timestamp: 1640995200,
             blockchain_anchors: vec![],
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
         };
 
         let key = storage.store_frame(&frame).await?;
@@ -250,6 +778,37 @@ mod tests {
         assert_eq!(retrieved.sequence, frame.sequence);
         assert_eq!(retrieved.hash, frame.hash);
 
+        let indexed = storage
+            .list_device_frames("test-camera", 1640995000, 1640995300)
+            .await?;
+        assert_eq!(indexed, vec![key]);
+
+        let outside = storage
+            .list_device_frames("test-camera", 1640995300, 1640995400)
+            .await?;
+        assert!(outside.is_empty());
+
+        // A frame written before versioning reads as-is until migrated
+        let legacy_key = "frame:2:1640995201";
+        let legacy = EncryptedFrame {
+            sequence: 2,
+            ..frame.clone()
+        };
+        storage
+            .db
+            .read()
+            .await
+            .put(legacy_key, serde_json::to_vec(&legacy)?)?;
+        assert_eq!(storage.retrieve_frame(legacy_key).await?.sequence, 2);
+
+        let report = storage.migrate(false).await?;
+        assert_eq!(report.upgraded.get(&RecordKind::Frame), Some(&1));
+        assert!(report.failed.is_empty());
+        let stored = storage.db.read().await.get(legacy_key)?.unwrap();
+        assert_eq!(migration::version(&stored), FORMAT_VERSION);
+        assert_eq!(storage.retrieve_frame(legacy_key).await?.sequence, 2);
+        assert_eq!(storage.migrate(true).await?.upgraded.len(), 0);
+
         Ok(())
     }
 }
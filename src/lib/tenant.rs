@@ -0,0 +1,283 @@
+use ring::hkdf;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::{CryptoConfig, RetiredKey};
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::storage::StorageConfig;
+
+// Owner of callers without a tenant claim. It keeps the node's own key and
+// database, so single-tenant deployments are unaffected.
+pub const DEFAULT_TENANT: &str = "default";
+
+const MAX_TENANT_ID_LEN: usize = 64;
+const TENANT_KEY_SALT: &[u8] = b"immutable-encryption/tenant-key/v1";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TenantConfig {
+    pub enabled: bool,
+    pub tenants: Vec<TenantDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub devices: Vec<String>, // capture devices whose frames belong to this tenant
+}
+
+// Tenant IDs end up in key derivation and filesystem paths
+pub fn validate_tenant_id(id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_TENANT_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ImmutableEncryptionError::Config(format!(
+            "Invalid tenant id {:?}: use up to {} lowercase letters, digits, '-' or '_'",
+            id, MAX_TENANT_ID_LEN
+        )))
+    }
+}
+
+// Every tenant served by the node, default first
+pub fn tenant_ids(config: &TenantConfig) -> Result<Vec<String>> {
+    let mut ids = vec![DEFAULT_TENANT.to_string()];
+    if !config.enabled {
+        return Ok(ids);
+    }
+
+    for tenant in &config.tenants {
+        validate_tenant_id(&tenant.id)?;
+        if ids.contains(&tenant.id) {
+            return Err(ImmutableEncryptionError::Config(format!(
+                "Tenant {} is defined more than once",
+                tenant.id
+            )));
+        }
+        ids.push(tenant.id.clone());
+    }
+
+    Ok(ids)
+}
+
+// Each tenant seals with a key derived from the primary key, so evidence
+// can't be decrypted with another tenant's key material. The default tenant
+// is derived too, so its key doesn't open the others'; keystores from before
+// that keep it on the primary key until their next rotation. With a key
+// provider or TPM the derived key is protected again, like the primary key.
+// Retired keys are derived the same way, as they were while primary.
+pub fn tenant_crypto_config(base: &CryptoConfig, tenant_id: &str) -> Result<CryptoConfig> {
+    let derive = |base_key: Vec<u8>| -> Result<Vec<u8>> {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, TENANT_KEY_SALT).extract(&base_key);
+        let info = [tenant_id.as_bytes()];
+        let mut key = vec![0u8; 32];
+        prk.expand(&info, hkdf::HKDF_SHA256)
+            .and_then(|okm| okm.fill(&mut key))
+            .map_err(|_| {
+                ImmutableEncryptionError::Crypto(format!(
+                    "Failed to derive the key for tenant {}",
+                    tenant_id
+                ))
+            })?;
+        base.protect_key(key)
+    };
+
+    let primary_key = if tenant_id == DEFAULT_TENANT && !base.default_tenant_derived {
+        base.primary_key.clone()
+    } else {
+        derive(base.open_primary_key()?)?
+    };
+    let retired_keys = base
+        .retired_keys
+        .iter()
+        .map(|retired| {
+            if tenant_id == DEFAULT_TENANT && !retired.default_tenant_derived {
+                return Ok(retired.clone());
+            }
+            Ok(RetiredKey {
+                key: derive(base.open_retired_key(retired)?)?,
+                wrapped: base.key_provider.is_some(),
+                sealed_to_tpm: base.hardware.is_some(),
+                ..retired.clone()
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(CryptoConfig {
+        primary_key,
+        key_rotation_interval: base.key_rotation_interval,
+        quantum_resistant: base.quantum_resistant,
+        hardware_backed: base.hardware_backed,
+        default_tenant_derived: base.default_tenant_derived,
+        retired_keys,
+        cipher_suite: base.cipher_suite,
+        key_provider: base.key_provider.clone(),
+        hardware: base.hardware.clone(),
+    })
+}
+
+// Tenants get their own database under `tenants_path`, beside the node's
+// rather than inside it, and their own backup directory under the node's
+pub fn tenant_storage_config(
+    base: &StorageConfig,
+    tenants_path: &str,
+    tenant_id: &str,
+) -> StorageConfig {
+    if tenant_id == DEFAULT_TENANT {
+        return base.clone();
+    }
+
+    // Earlier versions nested tenant databases in the node's; one created
+    // there is opened there until it is moved
+    let nested = tenant_dir(Path::new(&base.database_path), tenant_id);
+    let database_path = if nested.exists() {
+        tracing::warn!(
+            "Tenant {}'s database is inside storage.database_path at {}; \
+             move it to {} with the node stopped",
+            tenant_id,
+            nested.display(),
+            Path::new(tenants_path).join(tenant_id).display()
+        );
+        nested
+    } else {
+        Path::new(tenants_path).join(tenant_id)
+    };
+    StorageConfig {
+        database_path: database_path.to_string_lossy().to_string(),
+        backup_path: tenant_dir(Path::new(&base.backup_path), tenant_id)
+            .to_string_lossy()
+            .to_string(),
+        ..base.clone()
+    }
+}
+
+// A node-wide directory's share for one tenant; the default tenant keeps the
+// directory itself
+pub fn tenant_dir(base: &Path, tenant_id: &str) -> PathBuf {
+    if tenant_id == DEFAULT_TENANT {
+        return base.to_path_buf();
+    }
+    base.join("tenants").join(tenant_id)
+}
+
+// Per-tenant state, e.g. the node holding that tenant's evidence
+pub struct TenantDirectory<T> {
+    tenants: HashMap<String, T>,
+    device_tenants: HashMap<String, String>, // device_id -> tenant_id
+}
+
+impl<T> TenantDirectory<T> {
+    pub fn new(config: &TenantConfig, tenants: HashMap<String, T>) -> Result<Self> {
+        let mut device_tenants = HashMap::new();
+        if config.enabled {
+            for tenant in &config.tenants {
+                for device in &tenant.devices {
+                    if let Some(other) = device_tenants.insert(device.clone(), tenant.id.clone()) {
+                        return Err(ImmutableEncryptionError::Config(format!(
+                            "Device {} is assigned to both {} and {}",
+                            device, other, tenant.id
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            tenants,
+            device_tenants,
+        })
+    }
+
+    pub fn get(&self, tenant_id: &str) -> Option<&T> {
+        self.tenants.get(tenant_id)
+    }
+
+    // Devices not assigned to a tenant record into the default one
+    pub fn tenant_for_device(&self, device_id: &str) -> &str {
+        self.device_tenants
+            .get(device_id)
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_TENANT)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &T)> {
+        self.tenants.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenants_get_isolated_keys_and_storage() -> Result<()> {
+        let config = TenantConfig {
+            enabled: true,
+            tenants: vec![TenantDefinition {
+                id: "metro-pd".to_string(),
+                name: "Metro Police".to_string(),
+                devices: vec!["cam_7".to_string()],
+            }],
+        };
+        assert_eq!(tenant_ids(&config)?, vec!["default", "metro-pd"]);
+        assert!(validate_tenant_id("../etc").is_err());
+
+        let base = CryptoConfig::software(vec![7u8; 32], 1);
+        let default = tenant_crypto_config(&base, DEFAULT_TENANT)?;
+        let metro = tenant_crypto_config(&base, "metro-pd")?;
+        let other = tenant_crypto_config(&base, "county-so")?;
+        assert_ne!(default.primary_key, base.primary_key);
+        assert_ne!(default.primary_key, metro.primary_key);
+        assert_ne!(metro.primary_key, base.primary_key);
+        assert_ne!(metro.primary_key, other.primary_key);
+        assert_eq!(metro.primary_key.len(), 32);
+
+        // A keystore from before derived keys keeps the default tenant's
+        let mut legacy = CryptoConfig::software(vec![7u8; 32], 1);
+        legacy.default_tenant_derived = false;
+        let default = tenant_crypto_config(&legacy, DEFAULT_TENANT)?;
+        assert_eq!(default.primary_key, base.primary_key);
+        assert_eq!(
+            tenant_crypto_config(&legacy, "metro-pd")?.primary_key,
+            metro.primary_key
+        );
+
+        let storage = StorageConfig {
+            database_path: "data/db".to_string(),
+            ipfs_enabled: false,
+            ipfs_api_url: "".to_string(),
+            backup_enabled: false,
+            backup_path: "data/backup".to_string(),
+            compression_enabled: false,
+            frame_encoding: Default::default(),
+            backup_io_uring: false,
+        };
+        let scoped = tenant_storage_config(&storage, "data/tenants", "metro-pd");
+        assert_eq!(
+            Path::new(&scoped.database_path),
+            Path::new("data/tenants/metro-pd")
+        );
+        assert_eq!(
+            Path::new(&scoped.backup_path),
+            Path::new("data/backup/tenants/metro-pd")
+        );
+        assert_eq!(
+            tenant_storage_config(&storage, "data/tenants", DEFAULT_TENANT).database_path,
+            "data/db"
+        );
+
+        let directory =
+            TenantDirectory::new(&config, HashMap::from([("metro-pd".to_string(), 1)]))?;
+        assert_eq!(directory.tenant_for_device("cam_7"), "metro-pd");
+        assert_eq!(directory.tenant_for_device("cam_1"), DEFAULT_TENANT);
+        assert!(directory.get("county-so").is_none());
+
+        Ok(())
+    }
+}
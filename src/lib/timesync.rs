@@ -0,0 +1,596 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::RwLock;
+
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Seconds between the NTP era (1900) and the Unix epoch
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+const NTP_PACKET_LEN: usize = 48;
+const NTP_PORT: u16 = 123;
+const MAX_SAMPLES: usize = 4096;
+// A PPS device that hasn't pulsed for this long has lost its GPS fix
+const PPS_MAX_AGE_SECS: f64 = 2.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSyncConfig {
+    pub enabled: bool,
+    pub sources: Vec<TimeSource>,
+    pub interval_secs: u64,
+    pub timeout_ms: u64,
+    pub max_offset_ms: f64, // beyond this, frame timestamps can't be trusted
+}
+
+impl Default for TimeSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sources: vec![TimeSource::Chrony {
+                command: default_chronyc(),
+            }],
+            interval_secs: 64,
+            timeout_ms: 2000,
+            // Frame timestamps have whole-second resolution
+            max_offset_ms: 500.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TimeSource {
+    // Queried directly over SNTP; answers are not authenticated
+    Ntp {
+        server: String,
+    },
+    // The local chronyd, which can sync over NTS or to a GPS PPS refclock
+    Chrony {
+        #[serde(default = "default_chronyc")]
+        command: String,
+    },
+    // A kernel PPS device from a GPS receiver, e.g. /sys/class/pps/pps0
+    Pps {
+        path: String,
+    },
+}
+
+fn default_chronyc() -> String {
+    "chronyc".to_string()
+}
+
+impl TimeSource {
+    pub fn name(&self) -> String {
+        match self {
+            TimeSource::Ntp { server } => format!("ntp:{}", server),
+            TimeSource::Chrony { .. } => "chrony".to_string(),
+            TimeSource::Pps { path } => format!("pps:{}", path),
+        }
+    }
+
+    pub async fn query(&self, timeout: Duration) -> Result<Reading> {
+        let reading = async {
+            match self {
+                TimeSource::Ntp { server } => query_ntp(server).await,
+                TimeSource::Chrony { command } => query_chrony(command).await,
+                TimeSource::Pps { path } => read_pps(path).await,
+            }
+        };
+        tokio::time::timeout(timeout, reading).await.map_err(|_| {
+            ImmutableEncryptionError::network(&format!(
+                "{} did not answer within {}ms",
+                self.name(),
+                timeout.as_millis()
+            ))
+        })?
+    }
+}
+
+// What one source says about the local clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub offset_ms: f64, // reference time minus local time
+    pub stratum: Option<u8>,
+    pub authenticated: bool, // NTS, a symmetric key or a local hardware reference
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeSample {
+    pub source: String,
+    pub checked_at: u64,
+    pub offset_ms: Option<f64>, // None when the source couldn't be read
+    pub stratum: Option<u8>,
+    pub authenticated: bool,
+    pub error: Option<String>,
+}
+
+// How far a session's frame timestamps can be trusted, from the clock
+// checks made while it recorded. Persisted under `time_sync:{session}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTimeSync {
+    pub from: u64,
+    pub to: u64,
+    pub checks: usize,
+    pub failed_checks: usize,
+    pub sources: Vec<String>,
+    pub mean_offset_ms: Option<f64>,
+    pub max_abs_offset_ms: Option<f64>,
+    pub drift_ppm: Option<f64>, // how fast the offset moved, from the busiest source
+    pub authenticated: bool,
+    pub trusted: bool,
+    pub warnings: Vec<String>,
+}
+
+impl SessionTimeSync {
+    pub fn key(session_id: &str) -> String {
+        format!("time_sync:{}", session_id)
+    }
+
+    pub fn summarize(from: u64, to: u64, samples: &[TimeSample], max_offset_ms: f64) -> Self {
+        let readings: Vec<(&TimeSample, f64)> = samples
+            .iter()
+            .filter_map(|s| s.offset_ms.map(|offset| (s, offset)))
+            .collect();
+        let mut sources: Vec<String> = samples.iter().map(|s| s.source.clone()).collect();
+        sources.sort();
+        sources.dedup();
+
+        let failed_checks = samples.len() - readings.len();
+        let mean_offset_ms = (!readings.is_empty())
+            .then(|| readings.iter().map(|(_, o)| o).sum::<f64>() / readings.len() as f64);
+        let max_abs_offset_ms = readings.iter().map(|(_, o)| o.abs()).reduce(f64::max);
+        let authenticated = !readings.is_empty() && readings.iter().all(|(s, _)| s.authenticated);
+
+        let busiest = sources
+            .iter()
+            .max_by_key(|name| readings.iter().filter(|(s, _)| s.source == **name).count());
+        let drift_ppm = busiest.and_then(|name| {
+            let points: Vec<(f64, f64)> = readings
+                .iter()
+                .filter(|(s, _)| &s.source == name)
+                .map(|(s, offset)| (s.checked_at as f64, *offset))
+                .collect();
+            // ms of offset per second is 1000 ppm
+            slope(&points).map(|ms_per_sec| ms_per_sec * 1000.0)
+        });
+
+        let mut warnings = Vec::new();
+        if readings.is_empty() {
+            warnings.push(
+                "no clock check succeeded while recording; timestamps come from an unverified \
+                 local clock"
+                    .to_string(),
+            );
+        }
+        if failed_checks > 0 {
+            warnings.push(format!(
+                "{} of {} clock checks failed",
+                failed_checks,
+                samples.len()
+            ));
+        }
+        if let Some(max) = max_abs_offset_ms.filter(|max| *max > max_offset_ms) {
+            warnings.push(format!(
+                "clock was off by up to {:.1} ms, more than the {:.1} ms allowed",
+                max, max_offset_ms
+            ));
+        }
+        if !readings.is_empty() && !authenticated {
+            warnings.push(
+                "some clock checks came from unauthenticated sources; use NTS or a PPS reference"
+                    .to_string(),
+            );
+        }
+        let trusted = !readings.is_empty()
+            && authenticated
+            && max_abs_offset_ms.is_some_and(|max| max <= max_offset_ms);
+
+        Self {
+            from,
+            to,
+            checks: samples.len(),
+            failed_checks,
+            sources,
+            mean_offset_ms,
+            max_abs_offset_ms,
+            drift_ppm,
+            authenticated,
+            trusted,
+            warnings,
+        }
+    }
+}
+
+// Keeps recent clock checks so each session can be summarized when it stops
+#[derive(Debug)]
+pub struct TimeMonitor {
+    config: TimeSyncConfig,
+    samples: RwLock<VecDeque<TimeSample>>,
+}
+
+impl TimeMonitor {
+    pub fn new(config: TimeSyncConfig) -> Self {
+        Self {
+            config,
+            samples: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    // Queries every source once, warning when timestamps can't be trusted
+    pub async fn check(&self) -> Result<Vec<TimeSample>> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let checked_at = now()?;
+        let mut checks = Vec::new();
+        for source in &self.config.sources {
+            let sample = match source.query(timeout).await {
+                Ok(reading) => {
+                    if reading.offset_ms.abs() > self.config.max_offset_ms {
+                        tracing::warn!(
+                            "Clock is {:.1} ms off {}; frame timestamps can't be trusted",
+                            reading.offset_ms,
+                            source.name()
+                        );
+                    }
+                    TimeSample {
+                        source: source.name(),
+                        checked_at,
+                        offset_ms: Some(reading.offset_ms),
+                        stratum: reading.stratum,
+                        authenticated: reading.authenticated,
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Clock check against {} failed: {}", source.name(), e);
+                    TimeSample {
+                        source: source.name(),
+                        checked_at,
+                        offset_ms: None,
+                        stratum: None,
+                        authenticated: false,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            checks.push(sample);
+        }
+        if !checks
+            .iter()
+            .any(|s| s.offset_ms.is_some() && s.authenticated)
+        {
+            tracing::warn!("No authenticated time source answered; timestamps are unverified");
+        }
+
+        let mut samples = self.samples.write().await;
+        samples.extend(checks.iter().cloned());
+        while samples.len() > MAX_SAMPLES {
+            samples.pop_front();
+        }
+        Ok(checks)
+    }
+
+    // Includes the last check before `from`, which described the clock the
+    // session started with
+    pub async fn summarize(&self, from: u64, to: u64) -> SessionTimeSync {
+        let samples = self.samples.read().await;
+        let earliest = from.saturating_sub(self.config.interval_secs);
+        let covered: Vec<TimeSample> = samples
+            .iter()
+            .filter(|s| (earliest..=to).contains(&s.checked_at))
+            .cloned()
+            .collect();
+        SessionTimeSync::summarize(from, to, &covered, self.config.max_offset_ms)
+    }
+}
+
+pub fn spawn_time_sync(monitor: Arc<TimeMonitor>) {
+    tokio::spawn(async move {
+        let mut ticker =
+            tokio::time::interval(Duration::from_secs(monitor.config.interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = monitor.check().await {
+                tracing::error!("Clock check failed: {}", e);
+            }
+        }
+    });
+}
+
+async fn query_ntp(server: &str) -> Result<Reading> {
+    let address = if server.parse::<SocketAddr>().is_ok() || server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:{}", server, NTP_PORT)
+    };
+    let address = tokio::net::lookup_host(&address)
+        .await?
+        .next()
+        .ok_or_else(|| ImmutableEncryptionError::network(&format!("{} did not resolve", server)))?;
+    let socket = UdpSocket::bind(if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })
+    .await?;
+    socket.connect(address).await?;
+
+    // Client mode, version 4; the transmit time comes back as the origin
+    let mut request = [0u8; NTP_PACKET_LEN];
+    request[0] = 0x23;
+    let sent_at = unix_now()?;
+    request[40..48].copy_from_slice(&to_ntp(sent_at));
+    socket.send(&request).await?;
+
+    let mut response = [0u8; NTP_PACKET_LEN];
+    let len = socket.recv(&mut response).await?;
+    let received_at = unix_now()?;
+    ntp_reading(&response[..len], &request[40..48], sent_at, received_at)
+}
+
+// The standard NTP offset from one exchange, ((T2 - T1) + (T3 - T4)) / 2
+fn ntp_reading(response: &[u8], origin: &[u8], sent_at: f64, received_at: f64) -> Result<Reading> {
+    if response.len() < NTP_PACKET_LEN {
+        return Err(ImmutableEncryptionError::network("short NTP response"));
+    }
+    if response[0] & 0x07 != 4 {
+        return Err(ImmutableEncryptionError::network(
+            "NTP response is not from a server",
+        ));
+    }
+    let stratum = response[1];
+    if stratum == 0 || stratum > 15 {
+        return Err(ImmutableEncryptionError::network(&format!(
+            "NTP server is unsynchronized (stratum {})",
+            stratum
+        )));
+    }
+    // Rejects stray or replayed answers to someone else's request
+    if &response[24..32] != origin {
+        return Err(ImmutableEncryptionError::network(
+            "NTP response does not answer our request",
+        ));
+    }
+
+    let received = from_ntp(&response[32..40]);
+    let transmitted = from_ntp(&response[40..48]);
+    let offset = ((received - sent_at) + (transmitted - received_at)) / 2.0;
+    Ok(Reading {
+        offset_ms: offset * 1000.0,
+        stratum: Some(stratum),
+        authenticated: false,
+    })
+}
+
+async fn query_chrony(command: &str) -> Result<Reading> {
+    let tracking = run_chronyc(command, "tracking").await?;
+    let tracking = parse_chrony_tracking(&tracking)?;
+
+    // A hardware reference clock (PPS, GPS) reports a name, not an address
+    let authenticated = if tracking.reference.parse::<IpAddr>().is_err() {
+        true
+    } else {
+        let authdata = run_chronyc(command, "authdata").await?;
+        chrony_source_authenticated(&authdata, &tracking.reference)
+    };
+    Ok(Reading {
+        offset_ms: tracking.offset_ms,
+        stratum: Some(tracking.stratum),
+        authenticated,
+    })
+}
+
+async fn run_chronyc(command: &str, report: &str) -> Result<String> {
+    let output = tokio::process::Command::new(command)
+        .args(["-c", "-n", report])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(ImmutableEncryptionError::network(&format!(
+            "{} {} failed: {}",
+            command,
+            report,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ChronyTracking {
+    reference: String,
+    stratum: u8,
+    offset_ms: f64,
+}
+
+// `chronyc -c tracking`: reference ID, name, stratum, reference time, system
+// time correction in seconds (positive when the clock is slow), ..., leap status
+fn parse_chrony_tracking(csv: &str) -> Result<ChronyTracking> {
+    let fields: Vec<&str> = csv.trim().split(',').collect();
+    if fields.len() < 14 {
+        return Err(ImmutableEncryptionError::network(
+            "unexpected chronyc tracking output",
+        ));
+    }
+    if fields[13] == "Not synchronised" {
+        return Err(ImmutableEncryptionError::network(
+            "chronyd is not synchronised",
+        ));
+    }
+    Ok(ChronyTracking {
+        reference: fields[1].to_string(),
+        stratum: fields[2].parse()?,
+        offset_ms: fields[4].parse::<f64>()? * 1000.0,
+    })
+}
+
+// `chronyc -c authdata` lists each source's name and authentication mode
+fn chrony_source_authenticated(csv: &str, reference: &str) -> bool {
+    csv.lines().any(|line| {
+        let mut fields = line.split(',');
+        fields.next() == Some(reference) && matches!(fields.next(), Some("NTS") | Some("SK"))
+    })
+}
+
+async fn read_pps(path: &str) -> Result<Reading> {
+    let assert = tokio::fs::read_to_string(format!("{}/assert", path)).await?;
+    let (secs, nanos) = parse_pps_assert(&assert)?;
+    let age = unix_now()? - (secs as f64 + nanos as f64 / 1e9);
+    if age > PPS_MAX_AGE_SECS {
+        return Err(ImmutableEncryptionError::hardware(&format!(
+            "{} has not pulsed for {:.0}s",
+            path, age
+        )));
+    }
+    Ok(Reading {
+        offset_ms: pps_offset_ms(nanos),
+        stratum: None,
+        authenticated: true,
+    })
+}
+
+// sysfs `assert`: the local time of the last pulse, `secs.nanos#sequence`
+fn parse_pps_assert(text: &str) -> Result<(u64, u32)> {
+    let time = text.trim().split('#').next().unwrap_or_default();
+    let (secs, nanos) = time.split_once('.').ok_or_else(|| {
+        ImmutableEncryptionError::hardware(&format!("unexpected PPS assert {:?}", text))
+    })?;
+    Ok((secs.parse()?, nanos.parse()?))
+}
+
+// Each pulse marks the start of a true second, so the local clock's reading
+// at the pulse is its error; a reading just under a second means it's behind
+fn pps_offset_ms(nanos: u32) -> f64 {
+    if nanos < 500_000_000 {
+        -(nanos as f64) / 1e6
+    } else {
+        (1e9 - nanos as f64) / 1e6
+    }
+}
+
+// Least-squares slope of y over x, when x varies
+fn slope(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(c, v), (x, y)| {
+        (c + (x - mean_x) * (y - mean_y), v + (x - mean_x).powi(2))
+    });
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+fn to_ntp(unix: f64) -> [u8; 8] {
+    let ntp = unix + NTP_UNIX_OFFSET;
+    let secs = ntp.trunc() as u32;
+    let fraction = (ntp.fract() * 4_294_967_296.0) as u32;
+    let mut bytes = [0u8; 8];
+    bytes[..4].copy_from_slice(&secs.to_be_bytes());
+    bytes[4..].copy_from_slice(&fraction.to_be_bytes());
+    bytes
+}
+
+fn from_ntp(bytes: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as f64;
+    secs - NTP_UNIX_OFFSET + fraction / 4_294_967_296.0
+}
+
+fn unix_now() -> Result<f64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs_f64())
+}
+
+fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(
+        source: &str,
+        checked_at: u64,
+        offset_ms: Option<f64>,
+        authenticated: bool,
+    ) -> TimeSample {
+        TimeSample {
+            source: source.to_string(),
+            checked_at,
+            offset_ms,
+            stratum: Some(2),
+            authenticated,
+            error: offset_ms.is_none().then(|| "timed out".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_clock_readings_and_session_summary() -> Result<()> {
+        // The server's clock runs 250 ms ahead of ours, with 20 ms each way
+        let sent_at = 1_700_000_000.0;
+        let origin = to_ntp(sent_at);
+        let mut response = [0u8; NTP_PACKET_LEN];
+        response[0] = 0x24;
+        response[1] = 2;
+        response[24..32].copy_from_slice(&origin);
+        response[32..40].copy_from_slice(&to_ntp(sent_at + 0.270));
+        response[40..48].copy_from_slice(&to_ntp(sent_at + 0.271));
+        let reading = ntp_reading(&response, &origin, sent_at, sent_at + 0.041)?;
+        assert!((reading.offset_ms - 250.0).abs() < 0.01);
+        assert!(!reading.authenticated);
+        assert!(ntp_reading(&response, &to_ntp(sent_at + 1.0), sent_at, sent_at).is_err());
+
+        let tracking = parse_chrony_tracking(
+            "C0A80001,192.168.0.1,3,1700000000.1,-0.000250000,0.000001,0.000010,-12.5,0.001,\
+             0.020,0.010,0.001,64.2,Normal\n",
+        )?;
+        assert_eq!(tracking.reference, "192.168.0.1");
+        assert!((tracking.offset_ms + 0.25).abs() < 1e-9);
+        assert!(chrony_source_authenticated(
+            "192.168.0.1,NTS,1,AES-SIV,256,1,0,8,100\n",
+            "192.168.0.1"
+        ));
+        assert!(!chrony_source_authenticated(
+            "192.168.0.1,-,0,-,0,0,0,0,0\n",
+            "192.168.0.1"
+        ));
+
+        assert_eq!(
+            parse_pps_assert("1700000000.999800000#42\n")?,
+            (1_700_000_000, 999_800_000)
+        );
+        assert!((pps_offset_ms(999_800_000) - 0.2).abs() < 1e-9);
+        assert!((pps_offset_ms(300_000) + 0.3).abs() < 1e-9);
+
+        // Drifting 1 ms per 100 s is 10 ppm
+        let samples = vec![
+            sample("chrony", 1000, Some(1.0), true),
+            sample("chrony", 1100, Some(2.0), true),
+            sample("chrony", 1200, Some(3.0), true),
+        ];
+        let summary = SessionTimeSync::summarize(1000, 1200, &samples, 500.0);
+        assert!(summary.trusted, "{:?}", summary.warnings);
+        assert!((summary.drift_ppm.unwrap() - 10.0).abs() < 1e-6);
+        assert_eq!(summary.max_abs_offset_ms, Some(3.0));
+
+        let mut untrusted = samples.clone();
+        untrusted.push(sample("ntp:pool.ntp.org", 1150, Some(-800.0), false));
+        untrusted.push(sample("ntp:pool.ntp.org", 1250, None, false));
+        let summary = SessionTimeSync::summarize(1000, 1250, &untrusted, 500.0);
+        assert!(!summary.trusted && !summary.authenticated);
+        assert_eq!((summary.checks, summary.failed_checks), (5, 1));
+        assert_eq!(summary.warnings.len(), 3);
+
+        let summary = SessionTimeSync::summarize(1000, 1200, &[], 500.0);
+        assert!(!summary.trusted && summary.drift_ppm.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,428 @@
+use ring::digest;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::crypto::HardwareKeys;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::HardwareAttestation;
+
+// TPM_GENERATED_VALUE and TPM_ST_ATTEST_QUOTE, which open every quote the TPM
+// signs; it refuses to sign caller data that starts with them
+const TPM_GENERATED: u32 = 0xFF54_4347;
+const TPM_ST_ATTEST_QUOTE: u16 = 0x8018;
+
+// The node's TPM 2.0, used when `encryption.hardware_backed` is set, under
+// `[encryption.tpm]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TpmConfig {
+    #[serde(default = "default_tcti")]
+    pub tcti: String, // e.g. device:/dev/tpmrm0 or swtpm:port=2321
+    #[serde(default = "default_pcrs")]
+    pub pcrs: Vec<u8>, // SHA-256 PCRs each quote covers; sealing ignores them
+}
+
+fn default_tcti() -> String {
+    "device:/dev/tpmrm0".to_string()
+}
+
+// Firmware, boot loader and Secure Boot state
+fn default_pcrs() -> Vec<u8> {
+    vec![0, 2, 4, 7]
+}
+
+impl Default for TpmConfig {
+    fn default() -> Self {
+        Self {
+            tcti: default_tcti(),
+            pcrs: default_pcrs(),
+        }
+    }
+}
+
+#[cfg(feature = "tpm")]
+pub fn open_hardware_keys(config: &TpmConfig) -> Result<Arc<dyn HardwareKeys>> {
+    Ok(Arc::new(Tpm::open(config)?))
+}
+
+#[cfg(not(feature = "tpm"))]
+pub fn open_hardware_keys(_config: &TpmConfig) -> Result<Arc<dyn HardwareKeys>> {
+    Err(ImmutableEncryptionError::config(
+        "encryption.hardware_backed needs the `tpm` feature",
+    ))
+}
+
+// What a frame's quote is over: SHA-256 of its hash, which fits the 32 bytes
+// of qualifying data every TPM accepts
+pub fn qualifying_data(frame_hash: &str) -> Vec<u8> {
+    digest::digest(&digest::SHA256, frame_hash.as_bytes())
+        .as_ref()
+        .to_vec()
+}
+
+// Checks a quote was signed by the attestation key and made for this frame.
+// The PCR digest it carries is left to whoever knows the expected values.
+pub fn verify_quote(
+    public_key: &[u8],
+    frame_hash: &str,
+    quote: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public_key)
+        .verify(quote, signature)
+        .map_err(|_| ImmutableEncryptionError::AttestationFailed("bad signature".to_string()))?;
+
+    // TPMS_ATTEST: magic, type, qualifiedSigner, extraData, ...
+    let mut reader = quote;
+    if read_u32(&mut reader)? != TPM_GENERATED || read_u16(&mut reader)? != TPM_ST_ATTEST_QUOTE {
+        return Err(ImmutableEncryptionError::AttestationFailed(
+            "not a TPM quote".to_string(),
+        ));
+    }
+    read_sized(&mut reader)?;
+    if read_sized(&mut reader)? != qualifying_data(frame_hash) {
+        return Err(ImmutableEncryptionError::AttestationFailed(
+            "quote is for another frame".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn read_u16(reader: &mut &[u8]) -> Result<u16> {
+    let bytes = read_bytes(reader, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(reader: &mut &[u8]) -> Result<u32> {
+    let bytes = read_bytes(reader, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// A TPM2B: a big-endian u16 size and that many bytes
+fn read_sized<'a>(reader: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let size = read_u16(reader)? as usize;
+    read_bytes(reader, size)
+}
+
+fn read_bytes<'a>(reader: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if reader.len() < n {
+        return Err(ImmutableEncryptionError::AttestationFailed(
+            "truncated quote".to_string(),
+        ));
+    }
+    let (bytes, rest) = reader.split_at(n);
+    *reader = rest;
+    Ok(bytes)
+}
+
+// Keys live in the owner hierarchy: a storage key that parents sealed
+// objects and a restricted ECDSA P-256 attestation key. Both are primary
+// keys, so the TPM rederives the same ones on every start and nothing but
+// the sealed blobs has to be kept.
+#[cfg(feature = "tpm")]
+pub struct Tpm {
+    context: std::sync::Mutex<tss_esapi::Context>,
+    storage_key: tss_esapi::handles::KeyHandle,
+    attestation_key: tss_esapi::handles::KeyHandle,
+    attestation_public: Vec<u8>,
+    pcrs: tss_esapi::structures::PcrSelectionList,
+}
+
+#[cfg(feature = "tpm")]
+impl Tpm {
+    pub fn open(config: &TpmConfig) -> Result<Self> {
+        use std::str::FromStr;
+        use tss_esapi::attributes::ObjectAttributesBuilder;
+        use tss_esapi::interface_types::algorithm::{HashingAlgorithm, PublicAlgorithm};
+        use tss_esapi::interface_types::ecc::EccCurve;
+        use tss_esapi::interface_types::key_bits::RsaKeyBits;
+        use tss_esapi::interface_types::resource_handles::Hierarchy;
+        use tss_esapi::structures::{
+            EccPoint, EccScheme, HashScheme, KeyDerivationFunctionScheme, PcrSelectionListBuilder,
+            PcrSlot, Public, PublicBuilder, PublicEccParametersBuilder, RsaExponent,
+            SymmetricDefinitionObject,
+        };
+        use tss_esapi::tcti_ldr::TctiNameConf;
+        use tss_esapi::utils::create_restricted_decryption_rsa_public;
+
+        let mut context = tss_esapi::Context::new(TctiNameConf::from_str(&config.tcti)?)?;
+
+        let storage_public = create_restricted_decryption_rsa_public(
+            SymmetricDefinitionObject::AES_128_CFB,
+            RsaKeyBits::Rsa2048,
+            RsaExponent::default(),
+        )?;
+        let attestation_public = PublicBuilder::new()
+            .with_public_algorithm(PublicAlgorithm::Ecc)
+            .with_name_hashing_algorithm(HashingAlgorithm::Sha256)
+            .with_object_attributes(
+                ObjectAttributesBuilder::new()
+                    .with_fixed_tpm(true)
+                    .with_fixed_parent(true)
+                    .with_sensitive_data_origin(true)
+                    .with_user_with_auth(true)
+                    .with_restricted(true)
+                    .with_sign_encrypt(true)
+                    .build()?,
+            )
+            .with_ecc_parameters(
+                PublicEccParametersBuilder::new()
+                    .with_ecc_scheme(EccScheme::EcDsa(HashScheme::new(HashingAlgorithm::Sha256)))
+                    .with_curve(EccCurve::NistP256)
+                    .with_is_signing_key(true)
+                    .with_restricted(true)
+                    .with_key_derivation_function_scheme(KeyDerivationFunctionScheme::Null)
+                    .with_symmetric(SymmetricDefinitionObject::Null)
+                    .build()?,
+            )
+            .with_ecc_unique_identifier(EccPoint::default())
+            .build()?;
+
+        let (storage_key, attestation) = context.execute_with_nullauth_session(|context| {
+            let storage =
+                context.create_primary(Hierarchy::Owner, storage_public, None, None, None, None)?;
+            let attestation = context.create_primary(
+                Hierarchy::Owner,
+                attestation_public,
+                None,
+                None,
+                None,
+                None,
+            )?;
+            Ok::<_, tss_esapi::Error>((storage.key_handle, attestation))
+        })?;
+        let attestation_public = match &attestation.out_public {
+            Public::Ecc { unique, .. } => {
+                [&[0x04][..], unique.x().value(), unique.y().value()].concat()
+            }
+            _ => {
+                return Err(ImmutableEncryptionError::crypto(
+                    "The TPM attestation key is not ECC",
+                ))
+            }
+        };
+
+        let slots = config
+            .pcrs
+            .iter()
+            .map(|pcr| PcrSlot::try_from(1u32.checked_shl(u32::from(*pcr)).unwrap_or(0)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let pcrs = PcrSelectionListBuilder::new()
+            .with_selection(HashingAlgorithm::Sha256, &slots)
+            .build()?;
+
+        Ok(Self {
+            context: std::sync::Mutex::new(context),
+            storage_key,
+            attestation_key: attestation.key_handle,
+            attestation_public,
+            pcrs,
+        })
+    }
+
+    fn context(&self) -> Result<std::sync::MutexGuard<'_, tss_esapi::Context>> {
+        self.context
+            .lock()
+            .map_err(|_| ImmutableEncryptionError::crypto("TPM context lock poisoned"))
+    }
+}
+
+#[cfg(feature = "tpm")]
+impl std::fmt::Debug for Tpm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tpm")
+            .field("attestation_public", &hex::encode(&self.attestation_public))
+            .finish_non_exhaustive()
+    }
+}
+
+// Sealed keys are stored as the sealed object's private and public areas:
+// `u16 length || private || public`, both as the TPM marshals them
+// Keys are sealed under the storage key with no PCR policy: only this TPM can
+// unseal them, but it will whatever the machine booted. The quotes are what
+// report the boot state; binding keys to PCRs would also mean resealing after
+// every firmware or kernel update.
+#[cfg(feature = "tpm")]
+impl HardwareKeys for Tpm {
+    fn seal(&self, key: &[u8]) -> Result<Vec<u8>> {
+        use tss_esapi::attributes::ObjectAttributesBuilder;
+        use tss_esapi::interface_types::algorithm::{HashingAlgorithm, PublicAlgorithm};
+        use tss_esapi::structures::{
+            Digest, KeyedHashScheme, PublicBuilder, PublicKeyedHashParameters, SensitiveData,
+        };
+        use tss_esapi::traits::Marshall;
+
+        let public = PublicBuilder::new()
+            .with_public_algorithm(PublicAlgorithm::KeyedHash)
+            .with_name_hashing_algorithm(HashingAlgorithm::Sha256)
+            .with_object_attributes(
+                ObjectAttributesBuilder::new()
+                    .with_fixed_tpm(true)
+                    .with_fixed_parent(true)
+                    .with_user_with_auth(true)
+                    .build()?,
+            )
+            .with_keyed_hash_parameters(PublicKeyedHashParameters::new(KeyedHashScheme::Null))
+            .with_keyed_hash_unique_identifier(Digest::default())
+            .build()?;
+        let sensitive = SensitiveData::try_from(key.to_vec())?;
+        let sealed = self.context()?.execute_with_nullauth_session(|context| {
+            context.create(self.storage_key, public, None, Some(sensitive), None, None)
+        })?;
+
+        let private = sealed.out_private.value();
+        let length = u16::try_from(private.len())
+            .map_err(|_| ImmutableEncryptionError::crypto("Sealed key is too large"))?;
+        Ok([
+            &length.to_be_bytes()[..],
+            private,
+            &sealed.out_public.marshall()?,
+        ]
+        .concat())
+    }
+
+    fn unseal(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        use tss_esapi::structures::{Private, Public};
+        use tss_esapi::traits::UnMarshall;
+
+        let mut reader = sealed;
+        let private = Private::try_from(read_sized(&mut reader)?.to_vec())?;
+        let public = Public::unmarshall(reader)?;
+        let key = self
+            .context()?
+            .execute_with_nullauth_session(|context| {
+                let object = context.load(self.storage_key, private, public)?;
+                let key = context.unseal(object.into());
+                context.flush_context(object.into())?;
+                key
+            })
+            .map_err(|e| {
+                ImmutableEncryptionError::Crypto(format!(
+                    "The TPM can't unseal the primary key; was it sealed on another machine? {}",
+                    e
+                ))
+            })?;
+        Ok(key.value().to_vec())
+    }
+
+    fn quote(&self, qualifying_data: &[u8]) -> Result<HardwareAttestation> {
+        use tss_esapi::structures::{Data, Signature, SignatureScheme};
+        use tss_esapi::traits::Marshall;
+
+        let data = Data::try_from(qualifying_data.to_vec())?;
+        let (attest, signature) = self.context()?.execute_with_nullauth_session(|context| {
+            context.quote(
+                self.attestation_key,
+                data,
+                SignatureScheme::Null,
+                self.pcrs.clone(),
+            )
+        })?;
+        let signature = match signature {
+            Signature::EcDsa(signature) => {
+                // Fixed-width r || s; the TPM drops leading zero bytes
+                let mut fixed = vec![0u8; 64];
+                let r = signature.signature_r().value();
+                let s = signature.signature_s().value();
+                fixed[32 - r.len()..32].copy_from_slice(r);
+                fixed[64 - s.len()..].copy_from_slice(s);
+                fixed
+            }
+            _ => {
+                return Err(ImmutableEncryptionError::crypto(
+                    "The TPM quote is not ECDSA-signed",
+                ))
+            }
+        };
+        Ok(HardwareAttestation {
+            quote: attest.marshall()?,
+            signature,
+        })
+    }
+
+    fn attestation_key(&self) -> Result<Vec<u8>> {
+        Ok(self.attestation_public.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    // The start of a TPMS_ATTEST as a TPM would marshal it
+    fn quote(frame_hash: &str) -> Vec<u8> {
+        let extra = qualifying_data(frame_hash);
+        [
+            &TPM_GENERATED.to_be_bytes()[..],
+            &TPM_ST_ATTEST_QUOTE.to_be_bytes(),
+            &[0, 2, 0xAA, 0xBB], // qualifiedSigner
+            &(extra.len() as u16).to_be_bytes(),
+            &extra,
+            &[0; 25], // clockInfo, firmwareVersion
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_quotes_bind_the_frame_hash_and_key() -> Result<()> {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)?;
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .map_err(|_| ImmutableEncryptionError::crypto("bad test key"))?;
+        let public_key = key.public_key().as_ref().to_vec();
+
+        let hash = "a".repeat(64);
+        let quote = quote(&hash);
+        let attestation = HardwareAttestation {
+            signature: key.sign(&rng, &quote)?.as_ref().to_vec(),
+            quote,
+        };
+        verify_quote(
+            &public_key,
+            &hash,
+            &attestation.quote,
+            &attestation.signature,
+        )?;
+
+        // Another frame, another key, or a forged quote
+        assert!(verify_quote(
+            &public_key,
+            &"b".repeat(64),
+            &attestation.quote,
+            &attestation.signature
+        )
+        .is_err());
+        let other = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)?;
+        let other =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, other.as_ref(), &rng)
+                .map_err(|_| ImmutableEncryptionError::crypto("bad test key"))?;
+        assert!(verify_quote(
+            other.public_key().as_ref(),
+            &hash,
+            &attestation.quote,
+            &attestation.signature
+        )
+        .is_err());
+        let mut forged = attestation.clone();
+        forged.quote[5] ^= 1;
+        assert!(verify_quote(&public_key, &hash, &forged.quote, &forged.signature).is_err());
+
+        // A well-signed blob that isn't a quote
+        let blob = vec![0u8; 8];
+        let not_a_quote = HardwareAttestation {
+            signature: key.sign(&rng, &blob)?.as_ref().to_vec(),
+            quote: blob,
+        };
+        assert!(verify_quote(
+            &public_key,
+            &hash,
+            &not_a_quote.quote,
+            &not_a_quote.signature
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::{info_span, Span, Subscriber};
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config::LoggingConfig;
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Correlation ID accepted from clients and echoed on every response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+// Frames that never reach storage would otherwise keep their span open
+// forever; the oldest are closed once this many are in flight.
+pub const MAX_OPEN_FRAME_SPANS: usize = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtlpConfig {
+    pub endpoint: String, // OTLP/gRPC collector, e.g. http://localhost:4317
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    #[serde(default = "default_metrics_interval_secs")]
+    pub metrics_interval_secs: u64, // 0 exports spans only
+}
+
+fn default_service_name() -> String {
+    "immutable-encryption-node".to_string()
+}
+
+fn default_metrics_interval_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json, // one object per line, for log shippers
+}
+
+// Flushes exported spans and metrics when dropped; keep it alive for the life
+// of the process
+pub struct TracingGuard {
+    #[cfg(feature = "otlp")]
+    otlp: bool,
+    #[cfg(feature = "otlp")]
+    meters: Option<opentelemetry_sdk::metrics::MeterProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if self.otlp {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+        #[cfg(feature = "otlp")]
+        if let Some(meters) = self.meters.take() {
+            if let Err(e) = meters.shutdown() {
+                eprintln!("Failed to flush OTLP metrics: {}", e);
+            }
+        }
+    }
+}
+
+// Installs the global subscriber: log lines carry the enclosing spans (and so
+// the request ID), go to stdout and/or the rotating log file, and spans and
+// pipeline metrics are exported over OTLP when configured.
+pub fn init(config: &LoggingConfig) -> Result<TracingGuard> {
+    let file = match &config.file_path {
+        Some(path) => Some(RotatingFile::open(
+            path,
+            config.max_file_size_mb.saturating_mul(1024 * 1024),
+            config.max_files,
+        )?),
+        None => None,
+    };
+    let stdout = config.stdout || file.is_none();
+
+    let registry = tracing_subscriber::registry()
+        .with(filter(config)?)
+        .with(stdout.then(|| output_layer(config.format, std::io::stdout, true)))
+        .with(file.map(|file| output_layer(config.format, Mutex::new(file), false)));
+
+    #[cfg(feature = "otlp")]
+    if let Some(otlp) = &config.otlp {
+        use opentelemetry_otlp::WithExportConfig;
+
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(&otlp.endpoint),
+            )
+            .with_trace_config(opentelemetry_sdk::trace::config().with_resource(resource(otlp)))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)
+            .map_err(|e| ImmutableEncryptionError::Internal(format!("OTLP exporter: {}", e)))?;
+
+        let meters = match otlp.metrics_interval_secs {
+            0 => None,
+            secs => {
+                let meters = opentelemetry_otlp::new_pipeline()
+                    .metrics(opentelemetry_sdk::runtime::Tokio)
+                    .with_exporter(
+                        opentelemetry_otlp::new_exporter()
+                            .tonic()
+                            .with_endpoint(&otlp.endpoint),
+                    )
+                    .with_resource(resource(otlp))
+                    .with_period(std::time::Duration::from_secs(secs))
+                    .build()
+                    .map_err(|e| {
+                        ImmutableEncryptionError::Internal(format!("OTLP metrics: {}", e))
+                    })?;
+                opentelemetry::global::set_meter_provider(meters.clone());
+                Some(meters)
+            }
+        };
+
+        registry
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .try_init()
+            .map_err(subscriber_error)?;
+        tracing::info!(
+            "Exporting traces{} to {}",
+            if meters.is_some() { " and metrics" } else { "" },
+            otlp.endpoint
+        );
+        return Ok(TracingGuard { otlp: true, meters });
+    }
+
+    registry.try_init().map_err(subscriber_error)?;
+    if config.otlp.is_some() {
+        tracing::warn!("OTLP export is configured but this build lacks the otlp feature");
+    }
+
+    Ok(TracingGuard {
+        #[cfg(feature = "otlp")]
+        otlp: false,
+        #[cfg(feature = "otlp")]
+        meters: None,
+    })
+}
+
+#[cfg(feature = "otlp")]
+fn resource(otlp: &OtlpConfig) -> opentelemetry_sdk::Resource {
+    opentelemetry_sdk::Resource::new(vec![opentelemetry::KeyValue::new(
+        "service.name",
+        otlp.service_name.clone(),
+    )])
+}
+
+fn subscriber_error(e: tracing_subscriber::util::TryInitError) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::Internal(format!("Tracing subscriber: {}", e))
+}
+
+// The default level plus one directive per `[logging.modules]` entry, e.g.
+// `immutable_encryption::storage = "debug"`
+pub fn filter(config: &LoggingConfig) -> Result<EnvFilter> {
+    let mut directives = vec![config.level.clone()];
+    for (module, level) in &config.modules {
+        directives.push(format!("{}={}", module, level));
+    }
+    EnvFilter::try_new(directives.join(","))
+        .map_err(|e| ImmutableEncryptionError::Config(format!("Invalid log filter: {}", e)))
+}
+
+fn output_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+// Log file that rolls over once it would grow past `max_bytes`: the current
+// file becomes `<path>.1`, older ones shift up, and at most `max_files` files
+// are kept in all.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    max_files: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &str, max_bytes: u64, max_files: u64) -> Result<Self> {
+        let path = PathBuf::from(path);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            written: file.metadata()?.len(),
+            path,
+            file,
+            max_bytes,
+            max_files,
+        })
+    }
+
+    fn rotated(&self, n: u64) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        name.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let kept = self.max_files.saturating_sub(1);
+        if kept == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..kept).rev() {
+                let older = self.rotated(n);
+                if older.exists() {
+                    std::fs::rename(older, self.rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// Keeps a well-formed client-supplied ID so traces can be joined across
+// services; anything else is replaced with a fresh random one.
+pub fn request_id(header: Option<&str>) -> String {
+    match header {
+        Some(id) if is_valid_request_id(id) => id.to_string(),
+        _ => {
+            let mut bytes = [0u8; 16];
+            let _ = SystemRandom::new().fill(&mut bytes);
+            hex::encode(bytes)
+        }
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+// A frame crosses channels between ingest, encryption, anchoring and storage,
+// so the span covering its journey is parked here between stages.
+#[derive(Debug, Default)]
+pub struct FrameTraces {
+    open: Mutex<OpenSpans>,
+}
+
+#[derive(Debug, Default)]
+struct OpenSpans {
+    spans: HashMap<(String, u64), Span>,
+    order: VecDeque<(String, u64)>,
+}
+
+impl FrameTraces {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Opens the frame's span under the current one, typically the request
+    // that submitted it
+    pub fn begin(&self, device_id: &str, sequence: u64) -> Span {
+        self.track(
+            device_id,
+            sequence,
+            info_span!("frame", device_id, sequence),
+        )
+    }
+
+    // Frames enqueued without `begin` (e.g. the demo feed) get a root span
+    pub fn frame(&self, device_id: &str, sequence: u64) -> Span {
+        let existing = self
+            .open
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .spans
+            .get(&(device_id.to_string(), sequence))
+            .cloned();
+
+        existing.unwrap_or_else(|| {
+            let span = info_span!(parent: None, "frame", device_id, sequence);
+            self.track(device_id, sequence, span)
+        })
+    }
+
+    // Closes the frame's span once it has been stored or dropped
+    pub fn finish(&self, device_id: &str, sequence: u64) {
+        self.open
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .spans
+            .remove(&(device_id.to_string(), sequence));
+    }
+
+    pub fn open_count(&self) -> usize {
+        self.open
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .spans
+            .len()
+    }
+
+    fn track(&self, device_id: &str, sequence: u64, span: Span) -> Span {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (device_id.to_string(), sequence);
+
+        open.spans.insert(key.clone(), span.clone());
+        open.order.push_back(key);
+        while open.order.len() > MAX_OPEN_FRAME_SPANS {
+            if let Some(oldest) = open.order.pop_front() {
+                open.spans.remove(&oldest);
+            }
+        }
+
+        span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_ids_and_frame_spans() {
+        assert_eq!(request_id(Some("req-42.a:b_c")), "req-42.a:b_c");
+        assert_eq!(request_id(None).len(), 32);
+        assert_ne!(request_id(Some("bad id\r\n")), "bad id\r\n");
+        assert_eq!(request_id(Some(&"x".repeat(200))).len(), 32);
+
+        let traces = FrameTraces::new();
+        traces.begin("cam_1", 1);
+        traces.frame("cam_1", 2);
+        assert_eq!(traces.open_count(), 2);
+
+        traces.finish("cam_1", 1);
+        traces.finish("cam_1", 2);
+        assert_eq!(traces.open_count(), 0);
+
+        for sequence in 0..(MAX_OPEN_FRAME_SPANS as u64 + 10) {
+            traces.begin("cam_1", sequence);
+        }
+        assert_eq!(traces.open_count(), MAX_OPEN_FRAME_SPANS);
+    }
+
+    #[test]
+    fn test_log_file_rotates_and_keeps_max_files() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("logs/node.log");
+        let mut file = RotatingFile::open(&path.to_string_lossy(), 10, 3)?;
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes())?;
+        }
+        file.flush()?;
+
+        let read = |suffix: &str| {
+            std::fs::read_to_string(format!("{}{}", path.display(), suffix)).unwrap_or_default()
+        };
+        assert_eq!(read(""), "fourth\n");
+        assert_eq!(read(".1"), "third\n");
+        assert_eq!(read(".2"), "second\n");
+        assert!(!std::path::Path::new(&format!("{}.3", path.display())).exists());
+
+        let mut config = crate::config::Config::default().logging;
+        config.modules.insert(
+            "immutable_encryption::storage".to_string(),
+            "debug".to_string(),
+        );
+        assert!(filter(&config).is_ok());
+        config.level = "loud".to_string();
+        assert!(filter(&config).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+// Batched io_uring writes for local backup files on Linux. One thread owns the
+// ring; each time it wakes it takes every queued file and submits all their
+// writes and fsyncs together, so a burst of frames costs one submission
+// rather than a blocking write per file.
+use std::path::PathBuf;
+
+use crate::error::{ImmutableEncryptionError, Result};
+
+// Files in one submission; each takes a write and an fsync entry
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+const BATCH_FILES: usize = 128;
+
+#[derive(Debug)]
+pub struct UringWriter {
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    requests: std::sync::mpsc::Sender<backend::WriteRequest>,
+}
+
+impl UringWriter {
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn start() -> Result<Self> {
+        let ring = io_uring::IoUring::new((BATCH_FILES * 2) as u32)
+            .map_err(|e| ImmutableEncryptionError::storage(&format!("io_uring setup: {}", e)))?;
+        let (requests, queue) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("backup-uring".to_string())
+            .spawn(move || backend::run(ring, queue))?;
+        Ok(Self { requests })
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    pub fn start() -> Result<Self> {
+        Err(ImmutableEncryptionError::config(
+            "io_uring backups need Linux and the `io-uring` feature",
+        ))
+    }
+
+    // Creates or replaces `path` with `data`, durable once this returns
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub async fn write(&self, path: PathBuf, data: Vec<u8>) -> Result<()> {
+        let (done, result) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(backend::WriteRequest { path, data, done })
+            .map_err(|_| ImmutableEncryptionError::storage("io_uring writer has stopped"))?;
+        result
+            .await
+            .map_err(|_| ImmutableEncryptionError::storage("io_uring writer has stopped"))?
+            .map_err(Into::into)
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    pub async fn write(&self, _path: PathBuf, _data: Vec<u8>) -> Result<()> {
+        Err(ImmutableEncryptionError::config(
+            "io_uring backups need Linux and the `io-uring` feature",
+        ))
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod backend {
+    use io_uring::{opcode, squeue, types, IoUring};
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::path::PathBuf;
+    use std::sync::mpsc::Receiver;
+
+    use super::BATCH_FILES;
+
+    // What a linked entry completes with when the one before it fell short
+    const ECANCELED: i32 = 125;
+
+    pub(super) struct WriteRequest {
+        pub path: PathBuf,
+        pub data: Vec<u8>,
+        pub done: tokio::sync::oneshot::Sender<io::Result<()>>,
+    }
+
+    struct Job {
+        request: WriteRequest,
+        file: File,
+        written: usize,
+        synced: bool,
+        error: Option<io::Error>,
+    }
+
+    impl Job {
+        fn finished(&self) -> bool {
+            self.synced || self.error.is_some()
+        }
+    }
+
+    // Runs until every `UringWriter` handle is dropped
+    pub(super) fn run(mut ring: IoUring, queue: Receiver<WriteRequest>) {
+        while let Ok(first) = queue.recv() {
+            let mut jobs = Vec::with_capacity(BATCH_FILES);
+            for request in std::iter::once(first).chain(queue.try_iter().take(BATCH_FILES - 1)) {
+                match File::create(&request.path) {
+                    Ok(file) => jobs.push(Job {
+                        request,
+                        file,
+                        written: 0,
+                        synced: false,
+                        error: None,
+                    }),
+                    Err(e) => {
+                        let _ = request.done.send(Err(e));
+                    }
+                }
+            }
+
+            if let Err(e) = write_batch(&mut ring, &mut jobs) {
+                for job in jobs.iter_mut().filter(|job| !job.finished()) {
+                    job.error = Some(io::Error::new(e.kind(), e.to_string()));
+                }
+            }
+            for job in jobs {
+                let result = match job.error {
+                    Some(e) => Err(e),
+                    None => Ok(()),
+                };
+                let _ = job.request.done.send(result);
+            }
+        }
+    }
+
+    // Each round submits, for every unfinished file, a write of what is left
+    // linked to an fsync. A short write cancels its fsync, and the rest of the
+    // file goes out in the next round.
+    fn write_batch(ring: &mut IoUring, jobs: &mut [Job]) -> io::Result<()> {
+        loop {
+            let mut submitted = 0;
+            for (i, job) in jobs.iter().enumerate().filter(|(_, job)| !job.finished()) {
+                let fd = types::Fd(job.file.as_raw_fd());
+                let remaining = &job.request.data[job.written..];
+                let len = remaining.len().min(u32::MAX as usize) as u32;
+                let write = opcode::Write::new(fd, remaining.as_ptr(), len)
+                    .offset(job.written as u64)
+                    .build()
+                    .flags(squeue::Flags::IO_LINK)
+                    .user_data((i * 2) as u64);
+                let fsync = opcode::Fsync::new(fd).build().user_data((i * 2 + 1) as u64);
+
+                // SAFETY: the file and buffer are owned by `jobs`, which
+                // outlives every completion this function waits for
+                unsafe {
+                    let mut queue = ring.submission();
+                    queue.push(&write).map_err(io::Error::other)?;
+                    queue.push(&fsync).map_err(io::Error::other)?;
+                }
+                submitted += 2;
+            }
+            if submitted == 0 {
+                return Ok(());
+            }
+
+            ring.submit_and_wait(submitted)?;
+            let mut completed = 0;
+            while completed < submitted {
+                for cqe in ring.completion() {
+                    completed += 1;
+                    let job = &mut jobs[cqe.user_data() as usize / 2];
+                    let (is_write, result) = (cqe.user_data() % 2 == 0, cqe.result());
+                    let left = job.request.data.len() - job.written;
+                    if result == -ECANCELED {
+                        continue; // the fsync behind a short write
+                    } else if result < 0 {
+                        job.error = Some(io::Error::from_raw_os_error(-result));
+                    } else if is_write && result == 0 && left > 0 {
+                        job.error = Some(io::ErrorKind::WriteZero.into());
+                    } else if is_write {
+                        job.written += result as usize;
+                    } else {
+                        job.synced = left == 0;
+                    }
+                }
+                if completed < submitted {
+                    ring.submit_and_wait(submitted - completed)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux", feature = "io-uring"))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_batched_writes_land_on_disk() -> Result<()> {
+        let dir = TempDir::new()?;
+        let writer = std::sync::Arc::new(UringWriter::start()?);
+
+        let writes = (0..32u8).map(|i| {
+            let writer = writer.clone();
+            let path = dir.path().join(format!("frame-{}.bak", i));
+            async move { writer.write(path, vec![i; 64 * 1024]).await }
+        });
+        for result in futures::future::join_all(writes).await {
+            result?;
+        }
+
+        for i in 0..32u8 {
+            let data = std::fs::read(dir.path().join(format!("frame-{}.bak", i)))?;
+            assert_eq!(data, vec![i; 64 * 1024]);
+        }
+        assert!(writer
+            .write(dir.path().join("missing/dir.bak"), vec![1])
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -1,19 +1,98 @@
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use immutable_encryption_core::chain::{self, ChainLink};
+use immutable_encryption_core::hash;
+use ring::signature::{UnparsedPublicKey, ED25519};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::geofence::LocationFinding;
+use crate::metrics::{self, Module};
 use crate::{
-    BlockchainAnchor, CourtReport, CustodyEntry, EncryptedFrame, LegalCompliance,
-    VerificationResult,
+    BlockchainAnchor, CourtReport, CustodyEntry, DeviceSignature, EncryptedFrame, LegalCompliance,
+    VerificationResult, VideoFrame,
 };
 
+// Confirmations required on chains without a configured minimum
+const DEFAULT_MIN_CONFIRMATIONS: u64 = 6;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationConfig {
     pub strict_mode: bool,
     pub quantum_verification: bool,
     pub hardware_attestation: bool,
     pub min_confirmations: HashMap<String, u64>, // chain -> min confirmations
+    #[serde(default)]
+    pub attestation_key: Option<String>, // hex SEC1 P-256 key that signs TPM quotes
+    #[serde(default)]
+    pub device_keys: HashMap<String, String>, // device -> hex Ed25519 identity key
+}
+
+// Contiguous run of frames that all passed or all failed their checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RangeSegment {
+    pub valid: bool,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub from: u64,
+    pub to: u64,
+    pub frame_count: u64,
+    pub anomalies: Vec<String>,
+}
+
+// Outcome of verifying every stored frame of one device within a time range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RangeVerification {
+    pub device_id: String,
+    pub from: u64,
+    pub to: u64,
+    pub is_valid: bool,
+    pub frame_count: u64,
+    pub blockchain_confirmations: HashMap<String, u64>,
+    pub segments: Vec<RangeSegment>,
+    #[serde(default)]
+    pub location_findings: Vec<LocationFinding>,
+}
+
+impl RangeVerification {
+    pub fn new(device_id: &str, from: u64, to: u64) -> Self {
+        Self {
+            device_id: device_id.to_string(),
+            from,
+            to,
+            is_valid: true,
+            frame_count: 0,
+            blockchain_confirmations: HashMap::new(),
+            segments: Vec::new(),
+            location_findings: Vec::new(),
+        }
+    }
+
+    // Extends the current segment, or opens a new one when the frame's
+    // outcome differs from the previous frame's
+    pub fn record(&mut self, frame: &EncryptedFrame, anomalies: Vec<String>) {
+        let valid = anomalies.is_empty();
+        self.frame_count += 1;
+        self.is_valid &= valid;
+
+        match self.segments.last_mut() {
+            Some(segment) if segment.valid == valid => {
+                segment.last_sequence = frame.sequence;
+                segment.to = frame.timestamp;
+                segment.frame_count += 1;
+                segment.anomalies.extend(anomalies);
+            }
+            _ => self.segments.push(RangeSegment {
+                valid,
+                first_sequence: frame.sequence,
+                last_sequence: frame.sequence,
+                from: frame.timestamp,
+                to: frame.timestamp,
+                frame_count: 1,
+                anomalies,
+            }),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -27,52 +106,40 @@ impl VerificationEngine {
     }
 
     pub fn verify_hash_chain(&self, frames: &[EncryptedFrame]) -> Result<bool> {
-        if frames.len() < 2 {
-            return Ok(true); // Single frame is always valid
-        }
-
-        for window in frames.windows(2) {
-            let current = &window[0];
-            let next = &window[1];
-
-            // Verify hash chain integrity
-            if next.previous_hash != current.hash {
-                return Ok(false);
-            }
+        Ok(self.check_hash_chain(frames).is_ok())
+    }
 
-            // Verify sequence integrity
-            if next.sequence != current.sequence + 1 {
-                return Ok(false);
-            }
+    // The same checks as `verify_hash_chain`, failing with what broke: a
+    // sequence gap, a hash chain break or a timestamp that goes backwards
+    pub fn check_hash_chain(&self, frames: &[EncryptedFrame]) -> Result<()> {
+        metrics::timed(Module::Verification, "hash_chain", || {
+            Ok(chain::check_links(frames)?)
+        })
+    }
 
-            // Verify timestamp monotonicity
-            if next.timestamp <= current.timestamp {
-                return Ok(false);
-            }
+    // Fails unless `actual` meets the chain's configured minimum
+    pub fn check_confirmations(&self, chain: &str, actual: u64) -> Result<()> {
+        let required = self.min_confirmations(chain);
+        if actual < required {
+            return Err(ImmutableEncryptionError::InsufficientConfirmations {
+                chain: chain.to_string(),
+                required,
+                actual,
+            });
         }
+        Ok(())
+    }
 
-        Ok(true)
+    fn min_confirmations(&self, chain: &str) -> u64 {
+        self.config
+            .min_confirmations
+            .get(chain)
+            .copied()
+            .unwrap_or(DEFAULT_MIN_CONFIRMATIONS)
     }
 
     pub fn verify_cryptographic_integrity(&self, frames: &[EncryptedFrame]) -> Result<bool> {
-        for frame in frames {
-            // Verify hash format (64 hex characters for SHA-256/BLAKE3)
-            if frame.hash.len() != 64 || !frame.hash.chars().all(|c| c.is_ascii_hexdigit()) {
-                return Ok(false);
-            }
-
-            // Verify nonce length (12 bytes for AES-GCM)
-            if frame.nonce.len() != 12 {
-                return Ok(false);
-            }
-
-            // Verify ciphertext is not empty
-            if frame.ciphertext.is_empty() {
-                return Ok(false);
-            }
-        }
-
-        Ok(true)
+        Ok(frames.iter().all(chain::is_well_formed))
     }
 
     pub fn verify_blockchain_confirmations(
@@ -83,12 +150,7 @@ impl VerificationEngine {
 
         for frame in frames {
             for anchor in &frame.blockchain_anchors {
-                let min_conf = self
-                    .config
-                    .min_confirmations
-                    .get(&anchor.chain)
-                    .copied()
-                    .unwrap_or(6); // Default 6 confirmations
+                let min_conf = self.min_confirmations(&anchor.chain);
 
                 // In production, would query actual blockchain
                 // For now, simulate verification
@@ -103,47 +165,171 @@ impl VerificationEngine {
         Ok(confirmations)
     }
 
+    // `verify_blockchain_confirmations` summed over chains, for one frame's
+    // (chain, block number) anchors
+    pub fn confirmed_anchors(&self, anchors: &[(&str, u64)]) -> u64 {
+        anchors.iter().filter(|(_, block)| *block > 0).count() as u64
+    }
+
     pub fn detect_tampering(&self, frames: &[EncryptedFrame]) -> Result<Option<String>> {
-        // Check for sequence gaps
-        for window in frames.windows(2) {
-            let current = &window[0];
-            let next = &window[1];
-
-            if next.sequence != current.sequence + 1 {
-                return Ok(Some(format!(
-                    "Sequence gap detected: frame {} to {} (expected {})",
-                    current.sequence,
-                    next.sequence,
-                    current.sequence + 1
-                )));
+        metrics::timed(Module::Verification, "tamper_check", || {
+            // Check for sequence gaps
+            for window in frames.windows(2) {
+                let current = &window[0];
+                let next = &window[1];
+
+                if next.sequence != current.sequence + 1 {
+                    return Ok(Some(format!(
+                        "Sequence gap detected: frame {} to {} (expected {})",
+                        current.sequence,
+                        next.sequence,
+                        current.sequence + 1
+                    )));
+                }
             }
-        }
 
-        // Check for hash chain breaks
-        for window in frames.windows(2) {
-            let current = &window[0];
-            let next = &window[1];
+            // Check for hash chain breaks
+            for window in frames.windows(2) {
+                let current = &window[0];
+                let next = &window[1];
 
-            if next.previous_hash != current.hash {
-                return Ok(Some(format!(
+                if next.previous_hash != current.hash {
+                    return Ok(Some(format!(
                     "Hash chain break between frame {} and {}: expected previous hash {}, got {}",
                     current.sequence, next.sequence, current.hash, next.previous_hash
                 )));
+                }
+            }
+
+            // Check for duplicate frames
+            let mut seen_hashes = std::collections::HashSet::new();
+            for frame in frames {
+                if !seen_hashes.insert(&frame.hash) {
+                    return Ok(Some(format!(
+                        "Duplicate frame detected: hash {} appears multiple times",
+                        frame.hash
+                    )));
+                }
+            }
+
+            Ok(None) // No tampering detected
+        })
+    }
+
+    // The checks `verify_integrity` makes across a whole slice, applied to one
+    // frame and its predecessor so long evidence sets can be checked as a stream
+    pub fn frame_anomalies<F: ChainLink + ?Sized>(
+        &self,
+        previous: Option<&F>,
+        frame: &F,
+    ) -> Result<Vec<String>> {
+        let mut anomalies = chain::frame_anomalies(previous, frame);
+        if let Some((quote, signature)) = frame.attestation() {
+            if let Some(problem) = self.attestation_problem(frame.hash(), quote, signature) {
+                anomalies.push(format!("Frame {}: {}", frame.sequence(), problem));
+            }
+        }
+        if let Some(problem) = self.device_signature_problem(frame) {
+            anomalies.push(format!("Frame {}: {}", frame.sequence(), problem));
+        }
+        Ok(anomalies)
+    }
+
+    // Frames from a device with a registered identity key must be signed by
+    // it over the data as captured. Other devices' signatures are kept as
+    // sent, for whoever holds their keys to check.
+    pub fn check_capture(&self, frame: &VideoFrame) -> Result<Option<DeviceSignature>> {
+        let device_id = &frame.metadata.device_id;
+        let key = self.config.device_keys.get(device_id);
+        let refused = |problem: &str| {
+            ImmutableEncryptionError::PermissionDenied(format!(
+                "Frame {} from {}: {}",
+                frame.sequence, device_id, problem
+            ))
+        };
+        let Some(signature) = &frame.signature else {
+            return match key {
+                Some(_) => Err(refused("not signed by its device")),
+                None => Ok(None),
+            };
+        };
+        let payload_hash = hash::sha256_hex(&frame.data);
+        if let Some(key) = key {
+            let message =
+                hash::capture_message(device_id, frame.sequence, frame.timestamp, &payload_hash);
+            if let Some(problem) = signature_problem(key, &message, signature) {
+                return Err(refused(&problem));
             }
         }
+        Ok(Some(DeviceSignature {
+            payload_hash,
+            signature: signature.clone(),
+        }))
+    }
+
+    pub fn check_device_signatures(&self, frames: &[EncryptedFrame]) -> Option<String> {
+        frames.iter().find_map(|frame| {
+            self.device_signature_problem(frame)
+                .map(|problem| format!("Frame {}: {}", frame.sequence, problem))
+        })
+    }
 
-        // Check for duplicate frames
-        let mut seen_hashes = std::collections::HashSet::new();
+    fn device_signature_problem<F: ChainLink + ?Sized>(&self, frame: &F) -> Option<String> {
+        let key = self.config.device_keys.get(frame.device_id())?;
+        let Some((payload_hash, signature)) = frame.device_signature() else {
+            return Some("not signed by its device".to_string());
+        };
+        let message = hash::capture_message(
+            frame.device_id(),
+            frame.sequence(),
+            frame.timestamp(),
+            payload_hash,
+        );
+        signature_problem(key, &message, signature)
+    }
+
+    // With hardware attestation on, every quote must check out and at least
+    // one frame must carry one; through the hash chain a quote vouches for
+    // the frames before it
+    pub fn check_attestations(&self, frames: &[EncryptedFrame]) -> Option<String> {
+        if !self.config.hardware_attestation || frames.is_empty() {
+            return None;
+        }
+        let mut attested = false;
         for frame in frames {
-            if !seen_hashes.insert(&frame.hash) {
-                return Ok(Some(format!(
-                    "Duplicate frame detected: hash {} appears multiple times",
-                    frame.hash
-                )));
+            if let Some(attestation) = &frame.attestation {
+                let problem = self.attestation_problem(
+                    &frame.hash,
+                    &attestation.quote,
+                    &attestation.signature,
+                );
+                if let Some(problem) = problem {
+                    return Some(format!("Frame {}: {}", frame.sequence, problem));
+                }
+                attested = true;
             }
         }
+        (!attested).then(|| "No frame carries a hardware attestation".to_string())
+    }
 
-        Ok(None) // No tampering detected
+    fn attestation_problem(
+        &self,
+        frame_hash: &str,
+        quote: &[u8],
+        signature: &[u8],
+    ) -> Option<String> {
+        if !self.config.hardware_attestation {
+            return None;
+        }
+        let Some(key) = &self.config.attestation_key else {
+            return Some("attested, but no attestation key is configured".to_string());
+        };
+        let Ok(key) = hex::decode(key) else {
+            return Some("the attestation key is not hex".to_string());
+        };
+        crate::tpm::verify_quote(&key, frame_hash, quote, signature)
+            .err()
+            .map(|e| e.to_string())
     }
 
     pub fn generate_court_report(
@@ -163,6 +349,9 @@ impl VerificationEngine {
             generated_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)?
                 .as_secs(),
+            derived_renditions: Vec::new(),
+            time_sync: None,
+            approvals: Vec::new(),
         })
     }
 
@@ -175,7 +364,11 @@ impl VerificationEngine {
                 timestamp: first_frame.timestamp,
                 actor: "capturing_device".to_string(),
                 action: "initial_capture".to_string(),
-                signature: format!("device_signature_{}", first_frame.sequence),
+                signature: first_frame
+                    .device_signature
+                    .as_ref()
+                    .map(|s| hex::encode(&s.signature))
+                    .unwrap_or_default(),
                 blockchain_reference: first_frame
                     .blockchain_anchors
                     .first()
@@ -250,6 +443,17 @@ impl VerificationEngine {
     }
 }
 
+// Checks a capture message against a device's hex Ed25519 identity key
+fn signature_problem(key: &str, message: &[u8], signature: &[u8]) -> Option<String> {
+    let Ok(key) = hex::decode(key) else {
+        return Some("the device key is not hex".to_string());
+    };
+    UnparsedPublicKey::new(&ED25519, key)
+        .verify(message, signature)
+        .err()
+        .map(|_| "the device signature does not verify".to_string())
+}
+
 #[derive(Debug)]
 pub struct ZeroKnowledgeVerifier {
     config: VerificationConfig,
@@ -292,11 +496,15 @@ impl ZeroKnowledgeVerifier {
 #[async_trait]
 impl crate::EncryptionEngine for VerificationEngine {
     async fn encrypt_frame(&mut self, _frame: crate::VideoFrame) -> Result<crate::EncryptedFrame> {
-        Err(anyhow!("VerificationEngine does not support encryption"))
+        Err(ImmutableEncryptionError::verification(
+            "VerificationEngine does not support encryption",
+        ))
     }
 
     async fn decrypt_frame(&self, _encrypted: &crate::EncryptedFrame) -> Result<crate::VideoFrame> {
-        Err(anyhow!("VerificationEngine does not support decryption"))
+        Err(ImmutableEncryptionError::verification(
+            "VerificationEngine does not support decryption",
+        ))
     }
 
     async fn verify_integrity(
@@ -306,7 +514,10 @@ impl crate::EncryptionEngine for VerificationEngine {
         let hash_chain_valid = self.verify_hash_chain(frames)?;
         let crypto_integrity = self.verify_cryptographic_integrity(frames)?;
         let blockchain_conf = self.verify_blockchain_confirmations(frames)?;
-        let tamper_evidence = self.detect_tampering(frames)?;
+        let tamper_evidence = self
+            .detect_tampering(frames)?
+            .or_else(|| self.check_attestations(frames))
+            .or_else(|| self.check_device_signatures(frames));
 
         let is_valid = hash_chain_valid && crypto_integrity && tamper_evidence.is_none();
 
@@ -326,6 +537,7 @@ impl crate::EncryptionEngine for VerificationEngine {
             blockchain_confirmations: blockchain_conf,
             tamper_evidence,
             court_report,
+            location_findings: Vec::new(),
         })
     }
 }
@@ -333,6 +545,7 @@ impl crate::EncryptionEngine for VerificationEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
 
     #[test]
     fn test_hash_chain_verification() -> Result<()> {
@@ -341,6 +554,8 @@ mod tests {
             quantum_verification: false,
             hardware_attestation: false,
             min_confirmations: HashMap::new(),
+            attestation_key: None,
+            device_keys: HashMap::new(),
         };
 
         let verifier = VerificationEngine::new(config);
@@ -348,27 +563,173 @@ mod tests {
         let frames = vec![
             EncryptedFrame {
                 sequence: 1,
+                device_id: "test-camera".to_string(),
                 ciphertext: vec![1, 2, 3],
                 hash: "a".repeat(64),
                 previous_hash: "0".repeat(64),
                 nonce: vec![0; 12],
                 timestamp: 1000,
                 blockchain_anchors: vec![],
+                cipher: Default::default(),
+                compressed: false,
+                attestation: None,
+                device_signature: None,
             },
             EncryptedFrame {
                 sequence: 2,
+                device_id: "test-camera".to_string(),
                 ciphertext: vec![4, 5, 6],
                 hash: "b".repeat(64),
                 previous_hash: "a".repeat(64),
                 nonce: vec![1; 12],
                 timestamp: 1001,
                 blockchain_anchors: vec![],
+                cipher: Default::default(),
+                compressed: false,
+                attestation: None,
+                device_signature: None,
             },
         ];
 
         let result = verifier.verify_hash_chain(&frames)?;
         assert!(result);
 
+        let mut broken = frames.clone();
+        broken[1].previous_hash = "c".repeat(64);
+        assert!(matches!(
+            verifier.check_hash_chain(&broken),
+            Err(ImmutableEncryptionError::HashChainViolation)
+        ));
+        assert!(matches!(
+            verifier.check_confirmations("bitcoin", 2),
+            Err(ImmutableEncryptionError::InsufficientConfirmations { required: 6, .. })
+        ));
+        assert!(verifier.check_confirmations("bitcoin", 6).is_ok());
+
+        // With hardware attestation required, unattested evidence fails and
+        // a quote that doesn't check out is flagged per frame
+        assert!(verifier.check_attestations(&frames).is_none());
+        let attested = VerificationEngine::new(VerificationConfig {
+            hardware_attestation: true,
+            ..verifier.config.clone()
+        });
+        assert!(attested.check_attestations(&frames).is_some());
+        let mut forged = frames[1].clone();
+        forged.attestation = Some(crate::HardwareAttestation {
+            quote: vec![0xff; 16],
+            signature: vec![0; 64],
+        });
+        assert_eq!(
+            attested.frame_anomalies(Some(&frames[0]), &forged)?.len(),
+            1
+        );
+        assert!(verifier
+            .frame_anomalies(Some(&frames[0]), &forged)?
+            .is_empty());
+
+        // Frames from a device with a registered identity key must carry its
+        // signature over the data as captured
+        let identity = Ed25519KeyPair::from_seed_unchecked(&[3; 32])
+            .map_err(|_| ImmutableEncryptionError::crypto("bad seed"))?;
+        let signed = VerificationEngine::new(VerificationConfig {
+            device_keys: HashMap::from([(
+                "test-camera".to_string(),
+                hex::encode(identity.public_key()),
+            )]),
+            ..verifier.config.clone()
+        });
+        let mut capture = VideoFrame {
+            timestamp: 1001,
+            sequence: 2,
+            data: vec![4, 5, 6].into(),
+            metadata: crate::FrameMetadata {
+                device_id: "test-camera".to_string(),
+                location: None,
+                resolution: (640, 480),
+                fps: 15,
+                codec: "MJPEG".to_string(),
+                telemetry: None,
+            },
+            signature: None,
+        };
+        assert!(verifier.check_capture(&capture)?.is_none());
+        assert!(matches!(
+            signed.check_capture(&capture),
+            Err(ImmutableEncryptionError::PermissionDenied(_))
+        ));
+        let payload_hash = hash::sha256_hex(&capture.data);
+        let message = hash::capture_message("test-camera", 2, 1001, &payload_hash);
+        capture.signature = Some(identity.sign(&message).as_ref().to_vec());
+        let device_signature = signed.check_capture(&capture)?;
+        capture.sequence = 3;
+        assert!(signed.check_capture(&capture).is_err());
+
+        assert!(signed.check_device_signatures(&frames).is_some());
+        let mut sealed = frames.clone();
+        sealed[1].device_signature = device_signature;
+        assert_eq!(
+            signed.frame_anomalies(Some(&sealed[0]), &sealed[1])?.len(),
+            0
+        );
+        sealed[1].timestamp += 1;
+        assert_eq!(
+            signed.frame_anomalies(Some(&sealed[0]), &sealed[1])?.len(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_verification_splits_failed_segments() -> Result<()> {
+        let verifier = VerificationEngine::new(VerificationConfig {
+            strict_mode: true,
+            quantum_verification: false,
+            hardware_attestation: false,
+            min_confirmations: HashMap::new(),
+            attestation_key: None,
+            device_keys: HashMap::new(),
+        });
+
+        let frame = |sequence: u64, hash: &str, previous: &str| EncryptedFrame {
+            sequence,
+            device_id: "test-camera".to_string(),
+            ciphertext: vec![1, 2, 3],
+            hash: hash.repeat(64),
+            previous_hash: previous.repeat(64),
+            nonce: vec![0; 12],
+            timestamp: 1000 + sequence,
+            blockchain_anchors: vec![],
+            cipher: Default::default(),
+            compressed: false,
+            attestation: None,
+            device_signature: None,
+        };
+        let frames = vec![
+            frame(1, "a", "0"),
+            frame(2, "b", "a"),
+            frame(3, "c", "x"), // chain break
+            frame(5, "d", "c"), // sequence gap
+            frame(6, "e", "d"),
+        ];
+
+        let mut report = RangeVerification::new("test-camera", 1000, 2000);
+        let mut previous: Option<&EncryptedFrame> = None;
+        for frame in &frames {
+            report.record(frame, verifier.frame_anomalies(previous, frame)?);
+            previous = Some(frame);
+        }
+
+        assert!(!report.is_valid);
+        assert_eq!(report.frame_count, 5);
+        let outcomes: Vec<_> = report
+            .segments
+            .iter()
+            .map(|s| (s.valid, s.first_sequence, s.last_sequence))
+            .collect();
+        assert_eq!(outcomes, vec![(true, 1, 2), (false, 3, 5), (true, 6, 6)]);
+        assert_eq!(report.segments[1].anomalies.len(), 2);
+
         Ok(())
     }
 }
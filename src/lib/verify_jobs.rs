@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+// Finished jobs kept around for late subscribers before the oldest is dropped
+pub const MAX_RETAINED_JOBS: usize = 256;
+
+// Frames checked between progress updates
+pub const PROGRESS_INTERVAL: u64 = 64;
+
+// Anomalies listed in full; beyond this only `anomaly_count` grows
+pub const MAX_LISTED_ANOMALIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationProgress {
+    pub job_id: String,
+    pub evidence_id: String,
+    pub state: JobState,
+    pub frames_total: u64,
+    pub frames_checked: u64,
+    pub anchors_confirmed: u64,
+    pub anomaly_count: u64,
+    pub anomalies: Vec<String>,
+    pub is_valid: Option<bool>, // set once the job completes
+    pub error: Option<String>,
+}
+
+impl VerificationProgress {
+    pub fn new(job_id: String, evidence_id: &str) -> Self {
+        Self {
+            job_id,
+            evidence_id: evidence_id.to_string(),
+            state: JobState::Running,
+            frames_total: 0,
+            frames_checked: 0,
+            anchors_confirmed: 0,
+            anomaly_count: 0,
+            anomalies: Vec::new(),
+            is_valid: None,
+            error: None,
+        }
+    }
+
+    pub fn record_anomalies(&mut self, anomalies: Vec<String>) {
+        self.anomaly_count += anomalies.len() as u64;
+        let room = MAX_LISTED_ANOMALIES.saturating_sub(self.anomalies.len());
+        self.anomalies.extend(anomalies.into_iter().take(room));
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state != JobState::Running
+    }
+}
+
+// In-flight and recently finished verification jobs. Each job publishes its
+// progress on a watch channel, so subscribers always see the latest state.
+#[derive(Debug, Default)]
+pub struct VerificationJobs {
+    jobs: Mutex<JobTable>,
+}
+
+#[derive(Debug, Default)]
+struct JobTable {
+    progress: HashMap<String, watch::Sender<VerificationProgress>>,
+    order: VecDeque<String>,
+}
+
+impl VerificationJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, progress: VerificationProgress) -> watch::Sender<VerificationProgress> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let job_id = progress.job_id.clone();
+        let (tx, _) = watch::channel(progress);
+
+        jobs.progress.insert(job_id.clone(), tx.clone());
+        jobs.order.push_back(job_id);
+        while jobs.order.len() > MAX_RETAINED_JOBS {
+            // Running jobs keep their slot; the oldest finished one goes instead
+            let finished = jobs.order.iter().position(|id| {
+                jobs.progress
+                    .get(id)
+                    .map_or(true, |tx| tx.borrow().is_finished())
+            });
+            match finished.and_then(|index| jobs.order.remove(index)) {
+                Some(id) => {
+                    jobs.progress.remove(&id);
+                }
+                None => break,
+            }
+        }
+
+        tx
+    }
+
+    pub fn subscribe(&self, job_id: &str) -> Option<watch::Receiver<VerificationProgress>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .progress
+            .get(job_id)
+            .map(|tx| tx.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finished_jobs_are_evicted_first() {
+        let jobs = VerificationJobs::new();
+        let running = jobs.register(VerificationProgress::new("running".to_string(), "s1"));
+
+        for i in 0..MAX_RETAINED_JOBS {
+            let tx = jobs.register(VerificationProgress::new(format!("job-{}", i), "s2"));
+            tx.send_modify(|p| p.state = JobState::Completed);
+        }
+
+        assert!(jobs.subscribe("running").is_some());
+        assert!(jobs.subscribe("job-0").is_none());
+        assert!(jobs.subscribe("job-1").is_some());
+
+        running.send_modify(|p| p.frames_checked = 10);
+        assert_eq!(
+            jobs.subscribe("running").unwrap().borrow().frames_checked,
+            10
+        );
+    }
+}
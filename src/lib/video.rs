@@ -1,25 +1,216 @@
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::sync::{broadcast, mpsc, watch, Mutex, Notify, RwLock};
+use tokio::task::{JoinError, JoinHandle, JoinSet};
 use tokio::time::{interval, Duration};
+use tracing::{info_span, Instrument};
 
 use crate::{
+    admin::{
+        legal_hold_key, scrub_storage, LegalHold, LegalHoldRelease, LegalHoldRequest,
+        LegalHoldStatus, RetentionPurge, RetentionReport, ScrubReport, ScrubState,
+    },
+    api_keys::ApiKeyManager,
+    approval::{ApprovalAction, ApprovalConfig, ApprovalRequest},
+    audit::{AuditAnchor, AuditLog, AuditRecord, AuditVerification},
+    auth::Principal,
     blockchain::{BlockchainConfig, MultiChainAnchor},
-    crypto::CryptoConfig,
-    storage::{DistributedStorage, StorageConfig},
-    verification::{VerificationConfig, VerificationEngine as Verifier},
-    BlockchainAnchor, EncryptedFrame, EncryptionEngine, FrameMetadata, StorageBackend,
-    VerificationEngine, VideoFrame,
+    bundle::{BundleDigests, EvidenceBundle},
+    case::{Case, CaseReport, CaseRequest, SignedCaseReport},
+    clip::SharedClip,
+    cluster::{ChainHandoff, Leadership, HANDOFF_SCOPE},
+    config::PipelineConfig,
+    cose,
+    crypto::{self, CryptoConfig},
+    device_auth::{ClientCertificate, DeviceCertificateRegistry},
+    devices::{DevicePolicies, DevicePolicy},
+    edge::{self, EdgeChainTip, EdgeSync, OutboxEntry, RetroactiveAnchor, SyncBatch, SyncReceipt},
+    enrollment::{
+        DeviceEnrollment, DeviceIssuer, EnrollmentChallenge, EnrollmentRequest, IssuedDevice,
+        Revocation,
+    },
+    error::{ImmutableEncryptionError, Result},
+    evidence::{EvidenceBrowser, EvidenceQuery, EvidenceSummary, FrameQuery, FrameSummary, Page},
+    ffi::SegmentProof,
+    geofence::{LocationFinding, LocationValidator},
+    health::{self, DependencyHealth, HealthConfig},
+    ingest::{IngestConfig, MetadataRecord, MetadataValidator, SequenceAllocator},
+    metrics::{self, Module},
+    notifications::{Event, EventBus},
+    playback::{PlaybackConfig, PlaybackService},
+    recovery::{self, CrashSnapshot, CrashState, RecoveryReport},
+    redaction::{Redaction, RedactionOpening, RedactionRequest},
+    rendition::{hash_rendition, RenditionDigest, RenditionRecord, TranscodeProfile},
+    report::{AnchorProof, ReportDocument, ReportFormat, SignedReport},
+    sensors::TelemetryMerger,
+    session::{RecordingSession, SessionManager, SessionManifest},
+    stats::{AnchorStatus, NodeStatus, PipelineStats},
+    storage::{self, DistributedStorage, StorageConfig},
+    timesync::{SessionTimeSync, TimeMonitor},
+    trace::FrameTraces,
+    verification::{RangeVerification, VerificationConfig, VerificationEngine as Verifier},
+    verify_jobs::{JobState, VerificationJobs, VerificationProgress, PROGRESS_INTERVAL},
+    watermark::{WatermarkConfig, Watermarker},
+    wire::StoredFrame,
+    BlockchainAnchor, CustodyEntry, EncryptedFrame, EncryptionEngine, FrameMetadata,
+    StorageBackend, VerificationEngine, VideoFrame,
 };
 
+// Sealed-frame notifications retained for slow subscribers before they lag
+pub const SEALED_FRAME_CHANNEL_CAPACITY: usize = 1024;
+
+// For payloads a device policy compresses before sealing
+const ZSTD_LEVEL: i32 = 3;
+
+// Runs `task` over owned work items with at most `limit` in flight, returning
+// each result tagged with its item's index. Panicked tasks are logged and
+// omitted. Running tasks are counted in the `module`'s in-flight gauge.
+async fn run_bounded<T, R, F, Fut>(
+    items: Vec<T>,
+    limit: usize,
+    module: Module,
+    task: F,
+) -> Vec<(usize, Result<R>)>
+where
+    R: Send + 'static,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<R>> + Send + 'static,
+{
+    let mut results = Vec::with_capacity(items.len());
+    let mut in_flight = JoinSet::new();
+
+    for (index, item) in items.into_iter().enumerate() {
+        if in_flight.len() >= limit.max(1) {
+            collect_joined(in_flight.join_next().await, &mut results);
+        }
+
+        let future = task(item);
+        let running = metrics::global().in_flight(module);
+        in_flight.spawn(async move {
+            let _running = running;
+            (index, future.await)
+        });
+    }
+
+    while let Some(joined) = in_flight.join_next().await {
+        collect_joined(Some(joined), &mut results);
+    }
+
+    results.sort_by_key(|(index, _)| *index);
+    results
+}
+
+fn collect_joined<R>(
+    joined: Option<std::result::Result<(usize, Result<R>), JoinError>>,
+    results: &mut Vec<(usize, Result<R>)>,
+) {
+    match joined {
+        Some(Ok(result)) => results.push(result),
+        Some(Err(e)) => tracing::error!("Batch task failed: {}", e),
+        None => {}
+    }
+}
+
+// Each device's frames form their own chain; linking the next frame only
+// needs the hash at its tip, and full history lives in storage. Holding no
+// frames keeps the batch the only owner of each, so anchoring it doesn't copy.
+#[derive(Debug, Default)]
+pub struct ChainTips {
+    tips: HashMap<String, String>, // device_id -> hash of its last sealed frame
+}
+
+impl ChainTips {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Links the device's next frame to the tip a previous run sealed
+    pub fn resume(&mut self, device_id: &str, tip_hash: String) {
+        self.tips.insert(device_id.to_string(), tip_hash);
+    }
+
+    pub fn push(&mut self, frame: &EncryptedFrame) {
+        self.tips
+            .insert(frame.device_id.clone(), frame.hash.clone());
+    }
+
+    pub fn tip_hash(&self, device_id: &str) -> String {
+        self.tips
+            .get(device_id)
+            .cloned()
+            .unwrap_or_else(|| "0".repeat(64))
+    }
+
+    // device_id -> tip hash, for every chain started or resumed
+    pub fn tips(&self) -> BTreeMap<String, String> {
+        self.tips.clone().into_iter().collect()
+    }
+}
+
+// What subscribers hear of a frame as it is sealed: where it sits in its
+// device's chain, not the frame itself, which stays with the batch
+#[derive(Debug, Clone)]
+pub struct SealedFrame {
+    pub device_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub hash: String,
+    pub previous_hash: String,
+}
+
+impl SealedFrame {
+    pub fn of(frame: &EncryptedFrame) -> Self {
+        Self {
+            device_id: frame.device_id.clone(),
+            sequence: frame.sequence,
+            timestamp: frame.timestamp,
+            hash: frame.hash.clone(),
+            previous_hash: frame.previous_hash.clone(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RealTimeEncryptionNode {
     encryption_engine: Arc<Mutex<EncryptionEngine>>,
     blockchain_anchor: Arc<MultiChainAnchor>,
     storage: Arc<DistributedStorage>,
     verifier: Arc<Verifier>,
-    frame_buffer: Arc<RwLock<Vec<EncryptedFrame>>>,
+    chain_tips: Arc<RwLock<ChainTips>>,
+    bundle_digests: Arc<BundleDigests>,
+    watermarker: Arc<Watermarker>,
+    telemetry: Option<Arc<RwLock<TelemetryMerger>>>,
+    time_sync: Option<Arc<TimeMonitor>>,
+    validator: Arc<MetadataValidator>,
+    locations: Arc<LocationValidator>,
+    pipeline: PipelineConfig,
+    sessions: Arc<SessionManager>,
+    sealed_tx: broadcast::Sender<SealedFrame>,
+    sequences: Arc<SequenceAllocator>,
+    device_registry: Arc<DeviceCertificateRegistry>,
+    enrollment: Option<Arc<DeviceIssuer>>,
+    edge: Option<Arc<EdgeSync>>,
+    approvals: Arc<ApprovalConfig>,
+    approval_lock: Arc<Mutex<()>>, // serializes updates to approval requests
+    stats: Arc<PipelineStats>,
+    anchor_flush: Arc<Notify>,
+    events: EventBus,
+    shutdown_tx: Arc<watch::Sender<bool>>,
+    pipeline_tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    traces: Arc<FrameTraces>,
+    verification_jobs: Arc<VerificationJobs>,
+    audit_log: Arc<AuditLog>,
+    crash_state: Arc<CrashState>,
+}
+
+// Result of submitting a frame from a network client
+#[derive(Debug, Clone)]
+pub enum SubmitOutcome {
+    Sealed(SealedFrame),
+    // Enqueued, but not sealed before the wait elapsed
+    Pending { device_id: String, sequence: u64 },
 }
 
 impl RealTimeEncryptionNode {
@@ -27,9 +218,15 @@ impl RealTimeEncryptionNode {
         crypto_config: CryptoConfig,
         blockchain_config: BlockchainConfig,
         storage_config: StorageConfig,
-        verification_config: VerificationConfig,
+        mut verification_config: VerificationConfig,
     ) -> Result<Self> {
-        let encryption_engine = Arc::new(Mutex::new(EncryptionEngine::new(crypto_config)?));
+        let engine = EncryptionEngine::new(crypto_config)?;
+        // Without a configured key, quotes are checked against our own TPM
+        if verification_config.hardware_attestation && verification_config.attestation_key.is_none()
+        {
+            verification_config.attestation_key = engine.attestation_key()?.map(hex::encode);
+        }
+        let encryption_engine = Arc::new(Mutex::new(engine));
 
         let blockchain_anchor = Arc::new(MultiChainAnchor::new(blockchain_config).await?);
 
@@ -37,48 +234,958 @@ impl RealTimeEncryptionNode {
 
         let verifier = Arc::new(Verifier::new(verification_config));
 
+        let sessions = Arc::new(SessionManager::new(
+            encryption_engine.clone(),
+            blockchain_anchor.clone(),
+            storage.clone(),
+        ));
+
+        let audit_log = Arc::new(AuditLog::new(storage.clone(), encryption_engine.clone()));
+
         Ok(Self {
             encryption_engine,
             blockchain_anchor,
             storage,
             verifier,
-            frame_buffer: Arc::new(RwLock::new(Vec::new())),
+            chain_tips: Arc::new(RwLock::new(ChainTips::new())),
+            bundle_digests: Arc::new(BundleDigests::default()),
+            watermarker: Arc::new(Watermarker::new(WatermarkConfig::default())),
+            telemetry: None,
+            time_sync: None,
+            validator: Arc::new(MetadataValidator::new(IngestConfig::default())),
+            locations: Arc::new(LocationValidator::default()),
+            pipeline: PipelineConfig::default(),
+            sessions,
+            sealed_tx: broadcast::channel(SEALED_FRAME_CHANNEL_CAPACITY).0,
+            sequences: Arc::new(SequenceAllocator::new()),
+            device_registry: Arc::new(DeviceCertificateRegistry::default()),
+            enrollment: None,
+            edge: None,
+            approvals: Arc::new(ApprovalConfig::default()),
+            approval_lock: Arc::new(Mutex::new(())),
+            stats: Arc::new(PipelineStats::new()),
+            anchor_flush: Arc::new(Notify::new()),
+            events: EventBus::default(),
+            shutdown_tx: Arc::new(watch::channel(false).0),
+            pipeline_tasks: Arc::new(Mutex::new(Vec::new())),
+            traces: Arc::new(FrameTraces::new()),
+            verification_jobs: Arc::new(VerificationJobs::new()),
+            audit_log,
+            crash_state: Arc::new(CrashState::new()),
         })
     }
 
+    pub fn with_ingest_config(mut self, config: IngestConfig) -> Self {
+        self.validator = Arc::new(MetadataValidator::new(config));
+        self
+    }
+
+    pub fn with_pipeline_config(mut self, config: PipelineConfig) -> Self {
+        self.pipeline = config;
+        self
+    }
+
+    // Per-device cipher, anchoring, compression and retention; sessions pick
+    // their policy up when they start. Geofences and speed limits apply to
+    // every frame from then on.
+    pub fn with_device_policies(mut self, policies: DevicePolicies) -> Self {
+        self.locations = Arc::new(LocationValidator::new(policies.overrides()));
+        self.sessions = Arc::new(
+            SessionManager::new(
+                self.encryption_engine.clone(),
+                self.blockchain_anchor.clone(),
+                self.storage.clone(),
+            )
+            .with_policies(policies),
+        );
+        self
+    }
+
+    pub fn with_watermark(mut self, config: WatermarkConfig) -> Self {
+        self.watermarker = Arc::new(Watermarker::new(config));
+        self
+    }
+
+    pub fn with_device_registry(mut self, registry: DeviceCertificateRegistry) -> Self {
+        self.device_registry = Arc::new(registry);
+        self
+    }
+
+    // Lets admins issue device certificates; call `load_enrollments` once
+    // built so earlier enrollments and revocations take effect
+    pub fn with_enrollment(mut self, issuer: DeviceIssuer) -> Self {
+        self.enrollment = Some(Arc::new(issuer));
+        self
+    }
+
+    // A field node defers anchoring and queues frames for `spawn_edge_sync`;
+    // a central node takes their batches from the nodes it trusts
+    pub fn with_edge(mut self, edge: EdgeSync) -> Self {
+        self.edge = Some(Arc::new(edge));
+        self
+    }
+
+    // Decryption, custody transfers and purges covered by a policy wait for
+    // M-of-N approvals
+    pub fn with_approvals(mut self, approvals: ApprovalConfig) -> Self {
+        self.approvals = Arc::new(approvals);
+        self
+    }
+
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub fn with_telemetry(mut self, merger: Arc<RwLock<TelemetryMerger>>) -> Self {
+        self.telemetry = Some(merger);
+        self
+    }
+
+    // Summarizes the clock checks made during each session when it stops
+    pub fn with_time_sync(mut self, monitor: Arc<TimeMonitor>) -> Self {
+        self.time_sync = Some(monitor);
+        self
+    }
+
+    // Checks a frame against the ingest rules without sealing it, so network
+    // clients get an immediate rejection instead of a silent pipeline drop.
+    pub fn validate_frame(&self, frame: &mut VideoFrame) -> Result<(), ImmutableEncryptionError> {
+        self.validator.validate(frame)?;
+        self.watermarker.check(frame)
+    }
+
+    // Notifies every frame as it is sealed, before batch anchoring
+    pub fn subscribe_sealed(&self) -> broadcast::Receiver<SealedFrame> {
+        self.sealed_tx.subscribe()
+    }
+
+    // Validates and enqueues a frame from a network client, then waits up to
+    // `wait` for the pipeline to seal it so the caller can learn its hash.
+    pub async fn submit_frame(
+        &self,
+        sender: &FrameSender,
+        mut frame: VideoFrame,
+        certificate: Option<&ClientCertificate>,
+        wait: Duration,
+    ) -> Result<SubmitOutcome, ImmutableEncryptionError> {
+        self.validate_frame(&mut frame)?;
+        self.device_registry
+            .authorize(certificate, &frame.metadata.device_id)?;
+
+        frame.sequence = self
+            .sequences
+            .assign(&frame.metadata.device_id, frame.sequence)
+            .await;
+        // Checked again when sealed, but only here does the client hear why
+        self.verifier.check_capture(&frame)?;
+        let device_id = frame.metadata.device_id.clone();
+        let sequence = frame.sequence;
+
+        // The frame's span follows it through every stage under this request
+        self.traces.begin(&device_id, sequence);
+
+        // Subscribe before enqueueing so the sealed frame can't be missed
+        let mut sealed = self.subscribe_sealed();
+        sender.send(frame).map_err(|_| {
+            self.traces.finish(&device_id, sequence);
+            ImmutableEncryptionError::ResourceUnavailable(
+                "encryption pipeline is not running".to_string(),
+            )
+        })?;
+
+        let wait_for_seal = async {
+            loop {
+                match sealed.recv().await {
+                    Ok(f) if f.device_id == device_id && f.sequence == sequence => return Some(f),
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        };
+
+        let outcome = match tokio::time::timeout(wait, wait_for_seal).await {
+            Ok(Some(frame)) => SubmitOutcome::Sealed(frame),
+            _ => SubmitOutcome::Pending {
+                device_id,
+                sequence,
+            },
+        };
+
+        if let Some(certificate) = certificate {
+            if let Err(e) = self.record_ingest_custody(&outcome, certificate).await {
+                tracing::error!("Failed to record ingest custody: {}", e);
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    // Ties a network-submitted frame to the client certificate that sent it
+    async fn record_ingest_custody(
+        &self,
+        outcome: &SubmitOutcome,
+        certificate: &ClientCertificate,
+    ) -> Result<()> {
+        let (device_id, action) = match outcome {
+            SubmitOutcome::Sealed(frame) => (
+                frame.device_id.as_str(),
+                format!(
+                    "ingest:{}:{}",
+                    storage::frame_key(&frame.device_id, frame.sequence, frame.timestamp),
+                    frame.hash
+                ),
+            ),
+            SubmitOutcome::Pending {
+                device_id,
+                sequence,
+            } => (device_id.as_str(), format!("ingest:sequence:{}", sequence)),
+        };
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let actor = format!("device-cert:{}", certificate.fingerprint);
+        let signature = self
+            .encryption_engine
+            .lock()
+            .await
+            .sign(format!("{}|{}|{}", timestamp, actor, action).as_bytes());
+
+        let entry = crate::CustodyEntry {
+            timestamp,
+            actor,
+            action,
+            signature,
+            blockchain_reference: String::new(),
+        };
+        self.storage.append_custody_entry(device_id, &entry).await?;
+
+        Ok(())
+    }
+
     pub async fn start_processing(&self) -> Result<(FrameSender, EncryptedFrameReceiver)> {
         let (tx, rx) = mpsc::unbounded_channel::<VideoFrame>();
-        let (enc_tx, enc_rx) = mpsc::unbounded_channel::<EncryptedFrame>();
+        let (enc_tx, enc_rx) = mpsc::unbounded_channel::<Arc<EncryptedFrame>>();
 
         // Start encryption pipeline
         let node = self.clone();
-        tokio::spawn(async move {
-            node.encryption_pipeline(tx, enc_tx).await;
+        let encryption = tokio::spawn(async move {
+            node.encryption_pipeline(rx, enc_tx).await;
         });
 
         // Start blockchain anchoring
         let node = self.clone();
-        tokio::spawn(async move {
+        let anchoring = tokio::spawn(async move {
             node.blockchain_pipeline(enc_rx).await;
         });
 
+        self.pipeline_tasks
+            .lock()
+            .await
+            .extend([encryption, anchoring]);
+
         Ok((tx, self.create_verification_receiver().await))
     }
 
     async fn encryption_pipeline(&self, mut frame_rx: FrameReceiver, enc_tx: EncryptedFrameSender) {
-        while let Some(frame) = frame_rx.recv().await {
-            match self.process_frame(frame).await {
+        let mut shutdown = self.shutdown_tx.subscribe();
+        let mut closing = *shutdown.borrow();
+        if closing {
+            frame_rx.close();
+        }
+
+        loop {
+            let frame = tokio::select! {
+                frame = frame_rx.recv() => frame,
+                _ = shutdown.changed(), if !closing => {
+                    // Refuse new frames but still seal everything already queued
+                    frame_rx.close();
+                    closing = true;
+                    continue;
+                }
+            };
+            let frame = match frame {
+                Some(frame) => frame,
+                None => break,
+            };
+
+            let (device_id, sequence) = (frame.metadata.device_id.clone(), frame.sequence);
+            let span = info_span!(parent: &self.traces.frame(&device_id, sequence), "encrypt");
+
+            let encrypt_started = std::time::Instant::now();
+            match self.process_frame(frame).instrument(span).await {
                 Ok(encrypted_frame) => {
+                    self.stats.record_encrypt_latency(encrypt_started.elapsed());
                     if let Err(e) = enc_tx.send(encrypted_frame) {
                         tracing::error!("Failed to send encrypted frame: {}", e);
                         break;
                     }
                 }
                 Err(e) => {
+                    self.stats.record_failed();
                     tracing::error!("Failed to process frame: {}", e);
+                    self.traces.finish(&device_id, sequence);
+                }
+            }
+        }
+    }
+
+    // Stops accepting frames and waits for everything queued to be sealed,
+    // anchored and stored. Dropping the encryption stage's output is what
+    // tells the anchoring stage to flush its final batch.
+    pub async fn shutdown(&self, deadline: Duration) -> Result<()> {
+        let _ = self.shutdown_tx.send(true);
+
+        let tasks: Vec<JoinHandle<()>> = self.pipeline_tasks.lock().await.drain(..).collect();
+        tokio::time::timeout(deadline, futures::future::join_all(tasks))
+            .await
+            .map_err(|_| {
+                ImmutableEncryptionError::Video(format!(
+                    "Pipeline did not drain within {:?}",
+                    deadline
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    // In-memory pipeline state to snapshot if the process panics or dies
+    pub fn crash_state(&self) -> Arc<CrashState> {
+        self.crash_state.clone()
+    }
+
+    // Picks up after any crash snapshots in `dir`: sequences and the chain
+    // continue from where they stood, and frames that were sealed but never
+    // stored are reported and written to the audit log. Must run before
+    // `start_processing`.
+    pub async fn recover_from_crash(&self, dir: &std::path::Path) -> Result<Vec<RecoveryReport>> {
+        let mut reports = Vec::new();
+        for path in recovery::pending_snapshots(dir)? {
+            let snapshot = CrashSnapshot::load(&path)?;
+            self.sequences.resume(&snapshot.last_sequences).await;
+            let mut chain_tips = self.chain_tips.write().await;
+            for (device_id, tip) in &snapshot.chain_tips {
+                chain_tips.resume(device_id, tip.clone());
+            }
+            drop(chain_tips);
+            self.crash_state.resume(&snapshot);
+
+            let mut lost_frames = Vec::new();
+            let mut unanchored_frames = Vec::new();
+            for pending in snapshot.unflushed.iter().chain(&snapshot.pending_anchors) {
+                match self
+                    .storage
+                    .retrieve_frame_at(&pending.device_id, pending.sequence, pending.timestamp)
+                    .await
+                {
+                    Ok(frame) if frame.blockchain_anchors.is_empty() => {
+                        if !unanchored_frames.contains(pending) {
+                            unanchored_frames.push(pending.clone());
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(ImmutableEncryptionError::FrameNotFound { .. }) => {
+                        if !lost_frames.contains(pending) {
+                            lost_frames.push(pending.clone());
+                        }
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let name = path
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().to_string());
+            let summary = format!(
+                "{}: {} lost, {} unanchored",
+                name,
+                lost_frames.len(),
+                unanchored_frames.len()
+            );
+            self.audit_log
+                .record("node", None, "crash_recovery", Some(&summary))
+                .await?;
+            recovery::mark_recovered(&path)?;
+
+            reports.push(RecoveryReport {
+                snapshot: name,
+                reason: snapshot.reason,
+                taken_at: snapshot.taken_at,
+                chain_tips: snapshot.chain_tips,
+                resumed_devices: snapshot.last_sequences.len(),
+                lost_frames,
+                unanchored_frames,
+            });
+        }
+        Ok(reports)
+    }
+
+    // Records taking over the chain from the previous cluster leader as a
+    // signed custody entry; call once crash recovery has resumed the tip
+    pub async fn record_handoff(&self, leadership: &Leadership) -> Result<ChainHandoff> {
+        let chain_tips = self.chain_tips.read().await.tips();
+        let handoff = leadership.handoff(chain_tips);
+        let entry = handoff.custody_entry(now()?, &*self.encryption_engine.lock().await)?;
+        self.storage
+            .append_custody_entry(HANDOFF_SCOPE, &entry)
+            .await?;
+        self.audit_log
+            .record(
+                &entry.actor,
+                None,
+                "chain_handoff",
+                Some(&handoff.describe()),
+            )
+            .await?;
+        Ok(handoff)
+    }
+
+    pub async fn chain_handoffs(&self) -> Result<Vec<CustodyEntry>> {
+        self.storage.custody_entries(HANDOFF_SCOPE).await
+    }
+
+    // Field node: the next batch for a device, oldest first, signed with the
+    // envelope key. Frames the central node already stored only send the
+    // anchor it still owes.
+    pub async fn edge_sync_batch(&self, device_id: &str) -> Result<Option<SyncBatch>> {
+        let sync = self.edge()?;
+        let limit = sync.batch_size();
+
+        let outbox: Vec<(String, OutboxEntry)> = self
+            .storage
+            .scan_records(&OutboxEntry::prefix(device_id))
+            .await?;
+        let mut frames = Vec::new();
+        let mut anchors = Vec::new();
+        for (key, entry) in outbox.into_iter().take(limit) {
+            if !entry.synced {
+                match self
+                    .storage
+                    .retrieve_frame_at(&entry.device_id, entry.sequence, entry.timestamp)
+                    .await
+                {
+                    Ok(frame) => frames.push(frame),
+                    // Lost after sealing; the central node sees it as a gap
+                    Err(ImmutableEncryptionError::FrameNotFound { frame_id }) => {
+                        tracing::error!("{} is gone and can't be synced", frame_id);
+                        self.storage.delete_record(&key).await?;
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if let Some(mut anchor) = entry.anchor {
+                let key = MetadataRecord::key(&anchor.device_id, anchor.sequence);
+                anchor.metadata = self
+                    .storage
+                    .get_record::<MetadataRecord>(&key)
+                    .await?
+                    .map(|record| record.metadata);
+                anchors.push(anchor);
+            }
+        }
+
+        let cursor: Option<String> = self
+            .storage
+            .get_record(&edge::custody_cursor_key(device_id))
+            .await?;
+        let custody: Vec<(String, CustodyEntry)> = self
+            .storage
+            .scan_records(&edge::custody_prefix(device_id))
+            .await?
+            .into_iter()
+            .filter(|(key, _)| cursor.as_ref().map_or(true, |c| key > c))
+            .take(limit)
+            .collect();
+
+        let batch = SyncBatch {
+            node_id: sync.node_id().to_string(),
+            device_id: device_id.to_string(),
+            frames,
+            anchors,
+            custody_through: custody.last().map(|(key, _)| key.clone()),
+            custody: custody.into_iter().map(|(_, entry)| entry).collect(),
+            sent_at: now()?,
+            signature: String::new(),
+        };
+        if batch.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(batch.sign(&*self.encryption_engine.lock().await)?))
+    }
+
+    // Field node: drops what the central node took, keeping frames whose
+    // anchor it couldn't make yet
+    pub async fn edge_batch_synced(&self, batch: &SyncBatch, receipt: &SyncReceipt) -> Result<()> {
+        let sequences = batch
+            .frames
+            .iter()
+            .map(|f| f.sequence)
+            .chain(batch.anchors.iter().map(|a| a.sequence));
+        for sequence in sequences {
+            let key = OutboxEntry::key(&batch.device_id, sequence);
+            if !receipt.unanchored.contains(&sequence) {
+                self.storage.delete_record(&key).await?;
+            } else if let Some(mut entry) = self.storage.get_record::<OutboxEntry>(&key).await? {
+                entry.synced = true;
+                self.storage.put_record(&key, &entry).await?;
+            }
+        }
+        if let Some(through) = &batch.custody_through {
+            self.storage
+                .put_record(&edge::custody_cursor_key(&batch.device_id), through)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Field node: pushes each device's backlog until it is empty or the
+    // central node can't be reached
+    pub async fn sync_to_central(&self) -> Result<Vec<SyncReceipt>> {
+        let sync = self.edge()?.clone();
+        let mut devices = std::collections::BTreeSet::new();
+        let queued: Vec<(String, OutboxEntry)> =
+            self.storage.scan_records(OutboxEntry::PREFIX).await?;
+        devices.extend(queued.into_iter().map(|(_, entry)| entry.device_id));
+        let cursors: Vec<(String, String)> = self
+            .storage
+            .scan_records(edge::CUSTODY_CURSOR_PREFIX)
+            .await?;
+        devices.extend(
+            cursors
+                .into_iter()
+                .map(|(key, _)| key[edge::CUSTODY_CURSOR_PREFIX.len()..].to_string()),
+        );
+
+        let mut receipts = Vec::new();
+        for device_id in devices {
+            while let Some(batch) = self.edge_sync_batch(&device_id).await? {
+                let receipt = sync.push(&batch).await?;
+                self.edge_batch_synced(&batch, &receipt).await?;
+                // Anchors the central node keeps failing wait for the next round
+                let progressed = !batch.frames.is_empty()
+                    || !batch.custody.is_empty()
+                    || !receipt.anchored.is_empty();
+                receipts.push(receipt);
+                if !progressed {
+                    break;
+                }
+            }
+        }
+        Ok(receipts)
+    }
+
+    // An admin's push, without waiting for the next interval
+    pub async fn push_to_central(&self, actor: &Principal) -> Result<Vec<SyncReceipt>> {
+        self.audit(actor, "edge_push", None).await?;
+        self.sync_to_central().await
+    }
+
+    pub fn spawn_edge_sync(&self, period: Duration) -> JoinHandle<()> {
+        let node = self.clone();
+        let mut shutdown = self.shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut ticks = interval(period);
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+                match node.sync_to_central().await {
+                    Ok(receipts) => {
+                        for receipt in receipts.iter().filter(|r| r.stored > 0) {
+                            tracing::info!(
+                                "Synced {} frame(s) of {} to the central node",
+                                receipt.stored,
+                                receipt.device_id
+                            );
+                        }
+                    }
+                    Err(e) => tracing::warn!("Edge sync deferred: {}", e),
+                }
+            }
+        })
+    }
+
+    // Field node: frames and anchors not yet taken by the central node
+    pub async fn edge_outbox(&self) -> Result<Vec<OutboxEntry>> {
+        let outbox: Vec<(String, OutboxEntry)> =
+            self.storage.scan_records(OutboxEntry::PREFIX).await?;
+        Ok(outbox.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    // Central node: takes a field node's batch once its signature checks out
+    // and it carries on from the chain synced so far. New frames are stored
+    // with the anchors the field node deferred, made now but recorded against
+    // the time each frame was sealed; the field node's custody entries are
+    // kept as it signed them.
+    pub async fn accept_edge_sync(
+        &self,
+        batch: SyncBatch,
+        actor: &Principal,
+    ) -> Result<SyncReceipt> {
+        batch.verify(self.edge()?.trusted_key(&batch.node_id)?)?;
+
+        let tip_key = EdgeChainTip::key(&batch.device_id);
+        let tip: Option<EdgeChainTip> = self.storage.get_record(&tip_key).await?;
+        let reconciled = edge::reconcile(tip.as_ref(), &batch)?;
+        for frame in reconciled.resent {
+            let stored = self
+                .storage
+                .retrieve_frame_at(&frame.device_id, frame.sequence, frame.timestamp)
+                .await?;
+            if stored.hash != frame.hash {
+                return Err(ImmutableEncryptionError::EvidenceTampered {
+                    details: format!(
+                        "{} resent frame {} of {} with another hash",
+                        batch.node_id, frame.sequence, batch.device_id
+                    ),
+                });
+            }
+        }
+
+        // Anchor before storing, so new frames are written once with anchors
+        let mut new_frames: Vec<EncryptedFrame> = reconciled.new_frames.to_vec();
+        let mut anchored = Vec::new();
+        let mut unanchored = Vec::new();
+        let mut records = Vec::new();
+        let mut restored = Vec::new();
+        for deferred in &batch.anchors {
+            let key = RetroactiveAnchor::key(&deferred.device_id, deferred.sequence);
+            if let Some(existing) = self.storage.get_record::<RetroactiveAnchor>(&key).await? {
+                if existing.hash != deferred.hash {
+                    return Err(ImmutableEncryptionError::EvidenceTampered {
+                        details: format!(
+                            "{} owes an anchor for frame {} of {} with another hash",
+                            batch.node_id, deferred.sequence, deferred.device_id
+                        ),
+                    });
+                }
+                anchored.push(deferred.sequence);
+                continue;
+            }
+
+            let pending = new_frames
+                .iter()
+                .position(|f| f.sequence == deferred.sequence);
+            let mut stored = None;
+            let frame = match pending {
+                Some(index) => &new_frames[index],
+                None => {
+                    let frame = self
+                        .storage
+                        .retrieve_frame_at(
+                            &deferred.device_id,
+                            deferred.sequence,
+                            deferred.sealed_at,
+                        )
+                        .await?;
+                    &*stored.insert(frame)
+                }
+            };
+            if frame.hash != deferred.hash {
+                return Err(ImmutableEncryptionError::EvidenceTampered {
+                    details: format!(
+                        "anchor owed for frame {} of {} names another hash",
+                        deferred.sequence, deferred.device_id
+                    ),
+                });
+            }
+
+            // From a field node that predates sending metadata, or lost it;
+            // owed until a batch carries it
+            let Some(metadata) = &deferred.metadata else {
+                tracing::warn!(
+                    "{} owes an anchor for frame {} of {} without its metadata",
+                    batch.node_id,
+                    deferred.sequence,
+                    deferred.device_id
+                );
+                unanchored.push(deferred.sequence);
+                continue;
+            };
+            let anchors = match self
+                .blockchain_anchor
+                .anchor_to_chains(&deferred.hash, metadata, &deferred.chains)
+                .await
+            {
+                Ok(anchors) => anchors,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to anchor frame {} of {} for {}: {}",
+                        deferred.sequence,
+                        deferred.device_id,
+                        batch.node_id,
+                        e
+                    );
+                    unanchored.push(deferred.sequence);
+                    continue;
+                }
+            };
+            if let Some(index) = pending {
+                new_frames[index]
+                    .blockchain_anchors
+                    .extend(anchors.iter().cloned());
+            } else if let Some(mut frame) = stored {
+                frame.blockchain_anchors.extend(anchors.iter().cloned());
+                restored.push(frame);
+            }
+            anchored.push(deferred.sequence);
+            records.push(RetroactiveAnchor {
+                node_id: batch.node_id.clone(),
+                device_id: deferred.device_id.clone(),
+                sequence: deferred.sequence,
+                hash: deferred.hash.clone(),
+                sealed_at: deferred.sealed_at,
+                queued_at: deferred.queued_at,
+                anchored_at: now()?,
+                anchors,
+            });
+        }
+
+        for frame in new_frames.iter().chain(&restored) {
+            self.storage.store_with_redundancy(frame).await?;
+        }
+        for entry in &batch.custody {
+            self.storage
+                .append_custody_entry(&batch.device_id, entry)
+                .await?;
+        }
+        let mut actions: Vec<(String, String)> = Vec::new();
+        if let Some((from, to)) = reconciled.gap {
+            tracing::warn!(
+                "{} lost frames {}-{} of {} before syncing",
+                batch.node_id,
+                from,
+                to,
+                batch.device_id
+            );
+            let action = format!("edge_chain_gap:{}:{}-{}", batch.node_id, from, to);
+            actions.push((action, String::new()));
+        }
+        for record in &records {
+            self.storage
+                .put_record(
+                    &RetroactiveAnchor::key(&record.device_id, record.sequence),
+                    record,
+                )
+                .await?;
+            self.stats.record_batch_anchored(&record.anchors).await;
+            actions.extend(record.custody_actions());
+        }
+        for (action, blockchain_reference) in actions {
+            let timestamp = now()?;
+            let signature = self
+                .encryption_engine
+                .lock()
+                .await
+                .sign(format!("{}|{}|{}", timestamp, actor.subject, action).as_bytes());
+            let entry = CustodyEntry {
+                timestamp,
+                actor: actor.subject.clone(),
+                action,
+                signature,
+                blockchain_reference,
+            };
+            self.storage
+                .append_custody_entry(&batch.device_id, &entry)
+                .await?;
+        }
+
+        let last = new_frames.last();
+        if let Some(frame) = last {
+            let synced = EdgeChainTip {
+                node_id: batch.node_id.clone(),
+                device_id: batch.device_id.clone(),
+                sequence: frame.sequence,
+                hash: frame.hash.clone(),
+                timestamp: frame.timestamp,
+                synced_at: now()?,
+            };
+            self.storage.put_record(&tip_key, &synced).await?;
+        }
+        if !new_frames.is_empty() || !records.is_empty() {
+            let target = format!(
+                "{}:{}:frames={}:anchored={}",
+                batch.node_id,
+                batch.device_id,
+                new_frames.len(),
+                records.len()
+            );
+            self.audit(actor, "edge_sync", Some(&target)).await?;
+        }
+
+        Ok(SyncReceipt {
+            device_id: batch.device_id.clone(),
+            stored: new_frames.len(),
+            resent: reconciled.resent.len(),
+            gap: reconciled.gap,
+            tip_sequence: last.map(|f| f.sequence).or(tip.map(|t| t.sequence)),
+            custody_entries: batch.custody.len(),
+            anchored,
+            unanchored,
+        })
+    }
+
+    // Central node: anchors made for a device's frames after they synced
+    pub async fn retroactive_anchors(&self, device_id: &str) -> Result<Vec<RetroactiveAnchor>> {
+        let records: Vec<(String, RetroactiveAnchor)> = self
+            .storage
+            .scan_records(&RetroactiveAnchor::prefix(device_id))
+            .await?;
+        Ok(records.into_iter().map(|(_, record)| record).collect())
+    }
+
+    // Stores frames a device sealed itself once the segment proof it exported
+    // vouches for them. Segments must carry on from the device's last stored
+    // one; a resent segment must match it exactly.
+    pub async fn accept_device_segment(
+        &self,
+        proof: SegmentProof,
+        frames: Vec<EncryptedFrame>,
+        actor: &Principal,
+    ) -> Result<SyncReceipt> {
+        self.device_registry.check_device(&proof.device_id)?;
+        proof.verify(&*self.encryption_engine.lock().await, &frames)?;
+        if let Some(problem) = self.verifier.check_device_signatures(&frames) {
+            return Err(ImmutableEncryptionError::PermissionDenied(problem));
+        }
+
+        let tampered =
+            |details: String| Err(ImmutableEncryptionError::EvidenceTampered { details });
+        let key = SegmentProof::key(&proof.device_id, proof.last_sequence);
+        if let Some(stored) = self.storage.get_record::<SegmentProof>(&key).await? {
+            if (&stored.chain_tip, &stored.merkle_root) != (&proof.chain_tip, &proof.merkle_root) {
+                return tampered(format!(
+                    "{} resent frames {}-{} with other hashes",
+                    proof.device_id, proof.first_sequence, proof.last_sequence
+                ));
+            }
+            return Ok(SyncReceipt {
+                device_id: proof.device_id,
+                resent: frames.len(),
+                tip_sequence: Some(proof.last_sequence),
+                ..SyncReceipt::default()
+            });
+        }
+
+        let segments: Vec<(String, SegmentProof)> = self
+            .storage
+            .scan_records(&SegmentProof::prefix(&proof.device_id))
+            .await?;
+        let mut gap = None;
+        if let Some((_, last)) = segments.last() {
+            if proof.first_sequence <= last.last_sequence {
+                return tampered(format!(
+                    "frames {}-{} of {} overlap the stored segment ending at {}",
+                    proof.first_sequence, proof.last_sequence, proof.device_id, last.last_sequence
+                ));
+            }
+            if proof.previous_hash != last.chain_tip {
+                if proof.first_sequence == last.last_sequence + 1 {
+                    return tampered(format!(
+                        "frame {} of {} does not link to the stored tip {}",
+                        proof.first_sequence, proof.device_id, last.chain_tip
+                    ));
+                }
+                gap = Some((last.last_sequence + 1, proof.first_sequence - 1));
+            }
+        }
+
+        // Anchored as the node anchors its own frames, before they are stored;
+        // only this node's anchors are kept
+        let policy = self.sessions.policy(&proof.device_id).await;
+        let metadata = device_sealed_metadata(&proof.device_id);
+        let mut frames = frames;
+        let mut anchored = Vec::new();
+        let mut unanchored = Vec::new();
+        for frame in frames.iter_mut() {
+            frame.blockchain_anchors.clear();
+            if !policy.anchors(frame.sequence) {
+                continue;
+            }
+            match self
+                .blockchain_anchor
+                .anchor_to_chains(&frame.hash, &metadata, &policy.anchoring.chains)
+                .await
+            {
+                Ok(anchors) => {
+                    frame.blockchain_anchors = anchors;
+                    anchored.push(frame.sequence);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to anchor frame {} of {}: {}",
+                        frame.sequence,
+                        proof.device_id,
+                        e
+                    );
+                    unanchored.push(frame.sequence);
                 }
             }
         }
+        for frame in &frames {
+            self.storage.store_with_redundancy(frame).await?;
+        }
+        self.storage.put_record(&key, &proof).await?;
+
+        let mut actions = vec![format!(
+            "device_segment:{}-{}:{}",
+            proof.first_sequence, proof.last_sequence, proof.merkle_root
+        )];
+        if let Some((from, to)) = gap {
+            tracing::warn!(
+                "{} lost frames {}-{} before exporting",
+                proof.device_id,
+                from,
+                to
+            );
+            actions.push(format!("device_chain_gap:{}-{}", from, to));
+        }
+        for action in actions {
+            let timestamp = now()?;
+            let signature = self
+                .encryption_engine
+                .lock()
+                .await
+                .sign(format!("{}|{}|{}", timestamp, actor.subject, action).as_bytes());
+            let entry = CustodyEntry {
+                timestamp,
+                actor: actor.subject.clone(),
+                action,
+                signature,
+                blockchain_reference: String::new(),
+            };
+            self.storage
+                .append_custody_entry(&proof.device_id, &entry)
+                .await?;
+        }
+        let target = format!(
+            "{}:{}-{}",
+            proof.device_id, proof.first_sequence, proof.last_sequence
+        );
+        self.audit(actor, "device_segment", Some(&target)).await?;
+
+        Ok(SyncReceipt {
+            device_id: proof.device_id,
+            stored: frames.len(),
+            gap,
+            tip_sequence: Some(proof.last_sequence),
+            anchored,
+            unanchored,
+            ..SyncReceipt::default()
+        })
+    }
+
+    fn edge(&self) -> Result<&Arc<EdgeSync>> {
+        self.edge.as_ref().ok_or_else(|| {
+            ImmutableEncryptionError::invalid_request("Edge sync is not configured on this node")
+        })
     }
 
     async fn blockchain_pipeline(&self, mut encrypted_rx: EncryptedFrameReceiver) {
@@ -90,15 +1197,25 @@ impl RealTimeEncryptionNode {
             tokio::select! {
                 frame = encrypted_rx.recv() => {
                     match frame {
-                        Some(frame) => buffer.push(frame),
+                        Some(frame) => {
+                            buffer.push(frame);
+                            self.stats.set_queue_depth(buffer.len());
+                        }
                         None => break, // Channel closed
                     }
                 }
-                _ = ticker.tick() => {
+                // An admin flush anchors the queue without waiting for the tick
+                _ = async {
+                    tokio::select! {
+                        _ = ticker.tick() => {}
+                        _ = self.anchor_flush.notified() => {}
+                    }
+                } => {
                     if !buffer.is_empty() {
                         if let Err(e) = self.process_frame_batch(&mut buffer).await {
                             tracing::error!("Failed to process frame batch: {}", e);
                         }
+                        self.stats.set_queue_depth(buffer.len());
                     }
                 }
             }
@@ -110,48 +1227,126 @@ impl RealTimeEncryptionNode {
         }
     }
 
-    async fn process_frame(&self, frame: VideoFrame) -> Result<EncryptedFrame> {
-        let mut engine = self.encryption_engine.lock().await;
+    async fn process_frame(&self, mut frame: VideoFrame) -> Result<Arc<EncryptedFrame>> {
+        // Attach side-channel sensor data before hashing so it is sealed with the frame
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.read().await.merge_into(&mut frame);
+        }
+
+        // Never seal garbage metadata as evidence
+        self.validate_frame(&mut frame)?;
+        self.device_registry
+            .check_device(&frame.metadata.device_id)?;
+        let device_signature = self.verifier.check_capture(&frame)?;
+        let location_findings = self.locations.check(&frame).await;
 
-        // Generate frame hash
-        let frame_hash = engine.generate_frame_hash(&frame)?;
+        let policy = self.sessions.policy(&frame.metadata.device_id).await;
+        let engine = self.encryption_engine.lock().await;
 
-        // Get previous hash from buffer
-        let previous_hash = {
-            let buffer = self.frame_buffer.read().await;
-            buffer
-                .last()
-                .map(|f| f.hash.clone())
-                .unwrap_or_else(|| "0".repeat(64))
+        // Burn the original's hash prefix into the pixels before sealing
+        let original_hash = if self.watermarker.is_enabled() {
+            let original_hash = engine.generate_frame_hash(&frame)?;
+            self.watermarker.apply(&mut frame, &original_hash)?;
+            Some(original_hash)
+        } else {
+            None
         };
 
+        // Hash what is actually sealed
+        let frame_hash = engine.generate_frame_hash(&frame)?;
+
+        // Get previous hash from the tip of the device's chain
+        let previous_hash = self
+            .chain_tips
+            .read()
+            .await
+            .tip_hash(&frame.metadata.device_id);
+
         // Create hash chain link
         let chain_hash =
             engine.create_hash_chain_link(&frame_hash, &previous_hash, frame.sequence)?;
 
-        // Encrypt frame data
-        let (ciphertext, nonce) = engine.encrypt_data(&frame.data, frame.timestamp)?;
+        // Encrypt frame data, compressed first if the device's policy asks
+        let payload: bytes::Bytes = if policy.compression {
+            zstd::encode_all(&frame.data[..], ZSTD_LEVEL)?.into()
+        } else {
+            frame.data.clone() // shares the payload rather than copying it
+        };
+        let (ciphertext, nonce) = engine.encrypt_data(
+            &payload,
+            &frame.metadata.device_id,
+            frame.sequence,
+            frame.timestamp,
+            Some(policy.cipher),
+        )?;
 
-        let encrypted_frame = EncryptedFrame {
+        // One allocation, handed on to the batch that anchors and stores it
+        let encrypted_frame = Arc::new(EncryptedFrame {
             sequence: frame.sequence,
+            device_id: frame.metadata.device_id.clone(),
             ciphertext,
             hash: chain_hash,
             previous_hash,
             nonce,
             timestamp: frame.timestamp,
             blockchain_anchors: Vec::new(), // Will be filled in batch processing
+            cipher: policy.cipher,
+            compressed: policy.compression,
+            attestation: None,
+            device_signature,
+        });
+
+        // The sealed frame only carries the metadata's hash; keep what was
+        // hashed before the frame joins the chain
+        let record = MetadataRecord {
+            device_id: encrypted_frame.device_id.clone(),
+            sequence: encrypted_frame.sequence,
+            timestamp: encrypted_frame.timestamp,
+            frame_hash,
+            original_hash,
+            metadata: frame.metadata,
         };
+        self.storage
+            .put_record(
+                &MetadataRecord::key(&record.device_id, record.sequence),
+                &record,
+            )
+            .await?;
 
-        // Add to buffer
-        self.frame_buffer
-            .write()
-            .await
-            .push(encrypted_frame.clone());
+        // Advance the chain tip
+        self.chain_tips.write().await.push(&encrypted_frame);
+        self.crash_state.sealed(&encrypted_frame);
+
+        // Implausible positions are sealed anyway, but flagged for verifiers
+        for finding in &location_findings {
+            let key = LocationFinding::key(&finding.device_id, finding.sequence);
+            if let Err(e) = self.storage.put_record(&key, finding).await {
+                tracing::error!("Failed to record location finding {}: {}", key, e);
+            }
+        }
+
+        self.sessions.record_frame(&encrypted_frame).await;
+        self.stats
+            .record_sealed(
+                &encrypted_frame.device_id,
+                encrypted_frame.sequence,
+                encrypted_frame.ciphertext.len() as u64,
+            )
+            .await;
+
+        // No subscribers is the common case and not an error
+        let _ = self.sealed_tx.send(SealedFrame::of(&encrypted_frame));
+        self.events.publish(Event::FrameSealed {
+            device_id: encrypted_frame.device_id.clone(),
+            sequence: encrypted_frame.sequence,
+            timestamp: encrypted_frame.timestamp,
+            hash: encrypted_frame.hash.clone(),
+        });
 
         Ok(encrypted_frame)
     }
 
-    async fn process_frame_batch(&self, frames: &mut Vec<EncryptedFrame>) -> Result<()> {
+    async fn process_frame_batch(&self, frames: &mut Vec<Arc<EncryptedFrame>>) -> Result<()> {
         if frames.is_empty() {
             return Ok(());
         }
@@ -159,122 +1354,1859 @@ impl RealTimeEncryptionNode {
         // Sort frames by sequence to ensure proper order
         frames.sort_by_key(|f| f.sequence);
 
-        // Process frames in parallel for blockchain anchoring
-        let mut anchor_tasks = Vec::new();
+        // Take ownership of the batch; tasks share frames by reference count
+        // instead of borrowing the caller's buffer or cloning payloads.
+        let work: Vec<Arc<EncryptedFrame>> = frames.drain(..).collect();
 
-        for frame in frames.iter() {
+        // Anchor frames concurrently, each to the chains its device's policy
+        // names; frames the policy skips are covered by the hash chain
+        let mut policies = HashMap::new();
+        for frame in &work {
+            if !policies.contains_key(&frame.device_id) {
+                let policy = self.sessions.policy(&frame.device_id).await;
+                policies.insert(frame.device_id.clone(), policy);
+            }
+        }
+        for frame in &work {
+            self.crash_state.anchoring(frame);
+        }
+        // Field nodes seal offline; the central node anchors for them on sync
+        let deferring = self
+            .edge
+            .as_ref()
+            .map_or(false, |edge| edge.is_field_node());
+        let anchoring_started = std::time::Instant::now();
+        let anchor_limit = self.pipeline.anchor_concurrency;
+        let anchor_results = run_bounded(work.clone(), anchor_limit, Module::Blockchain, |frame| {
             let blockchain = self.blockchain_anchor.clone();
-            let metadata = self.create_mock_metadata(frame.sequence);
-
-            let task = tokio::spawn(async move {
-                let hash = frame.hash.clone();
-                blockchain.anchor_to_all_chains(&hash, &metadata).await
-            });
+            let storage = self.storage.clone();
+            let policy = policies[&frame.device_id].clone();
+            let span = info_span!(
+                parent: &self.traces.frame(&frame.device_id, frame.sequence),
+                "anchor"
+            );
+            async move {
+                if deferring || !policy.anchors(frame.sequence) {
+                    return Ok(Vec::new());
+                }
+                let metadata = sealed_metadata(&storage, &frame).await?;
+                blockchain
+                    .anchor_to_chains(&frame.hash, &metadata, &policy.anchoring.chains)
+                    .await
+            }
+            .instrument(span)
+        })
+        .await;
 
-            anchor_tasks.push(task);
+        self.stats.record_batch_latency(anchoring_started.elapsed());
+        for frame in &work {
+            self.crash_state.anchored(frame);
         }
 
-        // Wait for all blockchain anchors
-        let anchor_results = futures::future::join_all(anchor_tasks).await;
-
-        // Assign anchors to frames
-        for (i, result) in anchor_results.into_iter().enumerate() {
+        let mut anchors: Vec<Option<Vec<BlockchainAnchor>>> = vec![None; work.len()];
+        for (i, result) in anchor_results {
             match result {
-                Ok(Ok(anchors)) => {
-                    if i < frames.len() {
-                        frames[i].blockchain_anchors = anchors;
+                Ok(frame_anchors) => anchors[i] = Some(frame_anchors),
+                Err(e) => {
+                    tracing::error!("Failed to anchor frame {}: {}", work[i].sequence, e);
+                    self.alert_if_fatal("anchor", &work[i], &e);
+                }
+            }
+        }
+        let batch_anchors: Vec<BlockchainAnchor> =
+            anchors.iter().flatten().flatten().cloned().collect();
+        if !batch_anchors.is_empty() {
+            self.stats.record_batch_anchored(&batch_anchors).await;
+            self.events.publish(Event::BatchAnchored {
+                frame_count: work.len(),
+                first_sequence: work.first().map_or(0, |f| f.sequence),
+                last_sequence: work.last().map_or(0, |f| f.sequence),
+                anchors: batch_anchors,
+            });
+        }
+
+        // Only anchored frames change, in place: nothing else holds the batch's
+        // frames by now, so the ciphertext is never copied
+        let mut sealed: Vec<Arc<EncryptedFrame>> = work
+            .into_iter()
+            .zip(anchors)
+            .map(|(mut frame, frame_anchors)| {
+                if let Some(frame_anchors) = frame_anchors.filter(|a| !a.is_empty()) {
+                    Arc::make_mut(&mut frame).blockchain_anchors = frame_anchors;
+                }
+                frame
+            })
+            .collect();
+
+        // A hardware-backed node quotes the last frame of each device in the
+        // batch; through the hash chain the quote covers the frames before it
+        let mut last_per_device = HashMap::new();
+        for (i, frame) in sealed.iter().enumerate() {
+            last_per_device.insert(frame.device_id.clone(), i);
+        }
+        // TPM commands block, so they run off the async workers and without
+        // the engine lock the ingest path is waiting on
+        let hardware = self.encryption_engine.lock().await.hardware();
+        if let Some(hardware) = hardware {
+            let hashes: Vec<(usize, String)> = last_per_device
+                .into_values()
+                .map(|i| (i, sealed[i].hash.clone()))
+                .collect();
+            let quotes = tokio::task::spawn_blocking(move || {
+                hashes
+                    .into_iter()
+                    .map(|(i, hash)| (i, crypto::attest_with(&*hardware, &hash)))
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Attestation task failed: {}", e);
+                Vec::new()
+            });
+            for (i, quote) in quotes {
+                match quote {
+                    Ok(attestation) => {
+                        Arc::make_mut(&mut sealed[i]).attestation = Some(attestation)
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to attest frame {}: {}", sealed[i].sequence, e)
                     }
                 }
-                Ok(Err(e)) => {
-                    tracing::error!("Failed to anchor frame {}: {}", frames[i].sequence, e);
+            }
+        }
+
+        // Store frames with redundancy
+        let storage_limit = self.pipeline.storage_concurrency;
+        let storage_results =
+            run_bounded(sealed.clone(), storage_limit, Module::Storage, |frame| {
+                let storage = self.storage.clone();
+                let span = info_span!(
+                    parent: &self.traces.frame(&frame.device_id, frame.sequence),
+                    "store"
+                );
+                async move { storage.store_with_redundancy(&frame).await }.instrument(span)
+            })
+            .await;
+
+        for (i, result) in storage_results {
+            let frame = &sealed[i];
+            let span = self.traces.frame(&frame.device_id, frame.sequence);
+            let stored = span.in_scope(|| match result {
+                Ok(locations) => {
+                    self.stats.record_stored();
+                    tracing::info!("Frame {} stored at {:?}", frame.sequence, locations);
+                    true
                 }
                 Err(e) => {
-                    tracing::error!("Blockchain anchoring task failed: {}", e);
+                    tracing::error!("Failed to store frame {}: {}", frame.sequence, e);
+                    self.alert_if_fatal("store", frame, &e);
+                    false
+                }
+            });
+            if stored && deferring {
+                self.queue_for_sync(frame, &policies[&frame.device_id])
+                    .await;
+            }
+            self.crash_state.flushed(frame);
+            self.traces.finish(&frame.device_id, frame.sequence);
+        }
+
+        Ok(())
+    }
+
+    // Leaves a stored frame, and the anchor its policy asks for, for the
+    // central node
+    async fn queue_for_sync(&self, frame: &EncryptedFrame, policy: &DevicePolicy) {
+        let queued_at = now().unwrap_or(frame.timestamp);
+        let anchor = policy
+            .anchors(frame.sequence)
+            .then(|| edge::DeferredAnchor {
+                device_id: frame.device_id.clone(),
+                sequence: frame.sequence,
+                hash: frame.hash.clone(),
+                chains: policy.anchoring.chains.clone(),
+                sealed_at: frame.timestamp,
+                queued_at,
+                metadata: None,
+            });
+        let entry = OutboxEntry {
+            device_id: frame.device_id.clone(),
+            sequence: frame.sequence,
+            timestamp: frame.timestamp,
+            hash: frame.hash.clone(),
+            anchor,
+            synced: false,
+        };
+        let key = OutboxEntry::key(&frame.device_id, frame.sequence);
+        if let Err(e) = self.storage.put_record(&key, &entry).await {
+            tracing::error!("Failed to queue frame {} for sync: {}", frame.sequence, e);
+        }
+    }
+
+    // Retryable failures were already retried and only get logged; anything
+    // else points at tampering or misconfiguration, so subscribers hear of it
+    fn alert_if_fatal(
+        &self,
+        stage: &str,
+        frame: &EncryptedFrame,
+        error: &ImmutableEncryptionError,
+    ) {
+        if error.is_retryable() {
+            return;
+        }
+        self.events.publish(Event::PipelineFailed {
+            stage: stage.to_string(),
+            device_id: frame.device_id.clone(),
+            sequence: frame.sequence,
+            code: error.code().to_string(),
+            error: error.to_string(),
+        });
+    }
+
+    async fn create_verification_receiver(&self) -> EncryptedFrameReceiver {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        // This would be used for external verification requests
+        // For now, we'll just return the receiver
+        rx
+    }
+
+    pub async fn verify_evidence(&self, frame_ids: &[String]) -> Result<crate::VerificationResult> {
+        let mut frames = Vec::new();
+
+        // Retrieve frames
+        for frame_id in frame_ids {
+            match self.storage.retrieve_with_fallback(frame_id).await {
+                Ok(frame) => frames.push(frame),
+                Err(e) => tracing::error!("Failed to retrieve frame {}: {}", frame_id, e),
+            }
+        }
+
+        if frames.is_empty() {
+            return Err(ImmutableEncryptionError::verification(
+                "No valid frames found for verification",
+            ));
+        }
+
+        // Sort by sequence
+        frames.sort_by_key(|f| f.sequence);
+
+        // Perform verification
+        let result = self.verifier.verify_integrity(&frames).await?;
+        self.publish_verification(&result);
+
+        Ok(result)
+    }
+
+    // Verifies every frame the device index holds for `device_id` between
+    // `from` and `to` along that device's own chain, one frame at a time,
+    // reporting passing and failing runs
+    pub async fn verify_range(
+        &self,
+        device_id: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<RangeVerification> {
+        let frame_keys = self.storage.list_device_frames(device_id, from, to).await?;
+        if frame_keys.is_empty() {
+            return Err(ImmutableEncryptionError::NotFound(format!(
+                "No frames from {} between {} and {}",
+                device_id, from, to
+            )));
+        }
+
+        let mut report = RangeVerification::new(device_id, from, to);
+        let mut previous: Option<EncryptedFrame> = None;
+        let mut seen_hashes = std::collections::HashSet::new();
+
+        for key in frame_keys {
+            let frame = self.storage.retrieve_with_fallback(&key).await?;
+            let mut anomalies = self.verifier.frame_anomalies(previous.as_ref(), &frame)?;
+            let metadata_key = MetadataRecord::key(&frame.device_id, frame.sequence);
+            if let Some(record) = self
+                .storage
+                .get_record::<MetadataRecord>(&metadata_key)
+                .await?
+            {
+                if !record.links_to(&frame) {
+                    anomalies.push(format!(
+                        "Frame {}: metadata record does not match its chain hash",
+                        frame.sequence
+                    ));
                 }
             }
+            if !seen_hashes.insert(frame.hash.clone()) {
+                anomalies.push(format!(
+                    "Duplicate frame detected: hash {} appears multiple times",
+                    frame.hash
+                ));
+            }
+            for (chain, count) in self
+                .verifier
+                .verify_blockchain_confirmations(std::slice::from_ref(&frame))?
+            {
+                *report.blockchain_confirmations.entry(chain).or_insert(0) += count;
+            }
+
+            report.record(&frame, anomalies);
+            previous = Some(frame);
+        }
+        report.location_findings = self
+            .location_findings(device_id, |f| (from..=to).contains(&f.timestamp))
+            .await?;
+
+        let evidence_id = format!("{}:{}-{}", device_id, from, to);
+        if let Some(failed) = report.segments.iter().find(|s| !s.valid) {
+            self.events.publish(Event::TamperingDetected {
+                evidence_id: evidence_id.clone(),
+                details: failed.anomalies.join("; "),
+            });
+        }
+        self.events.publish(Event::VerificationCompleted {
+            evidence_id,
+            is_valid: report.is_valid,
+            frame_count: report.frame_count,
+        });
+
+        Ok(report)
+    }
+
+    // Findings recorded when the device's frames were sealed, in sequence order
+    async fn location_findings(
+        &self,
+        device_id: &str,
+        filter: impl Fn(&LocationFinding) -> bool,
+    ) -> Result<Vec<LocationFinding>> {
+        Ok(self
+            .storage
+            .scan_records::<LocationFinding>(&LocationFinding::prefix(device_id))
+            .await?
+            .into_iter()
+            .map(|(_, finding)| finding)
+            .filter(|finding| filter(finding))
+            .collect())
+    }
+
+    fn publish_verification(&self, result: &crate::VerificationResult) {
+        let evidence_id = result.court_report.evidence_id.clone();
+        if let Some(details) = &result.tamper_evidence {
+            self.events.publish(Event::TamperingDetected {
+                evidence_id: evidence_id.clone(),
+                details: details.clone(),
+            });
+        }
+        self.events.publish(Event::VerificationCompleted {
+            evidence_id,
+            is_valid: result.is_valid,
+            frame_count: result.frame_count,
+        });
+    }
+
+    pub async fn start_session(
+        &self,
+        device_id: &str,
+        case_id: Option<String>,
+    ) -> Result<RecordingSession> {
+        self.sessions.start_session(device_id, case_id).await
+    }
+
+    pub async fn stop_session(&self, session_id: &str) -> Result<SessionManifest> {
+        let manifest = self.sessions.stop_session(session_id).await?;
+
+        if let Some(monitor) = &self.time_sync {
+            let time_sync = monitor
+                .summarize(manifest.started_at, manifest.stopped_at)
+                .await;
+            if !time_sync.trusted {
+                tracing::warn!(
+                    "Timestamps in session {} can't be fully trusted: {}",
+                    session_id,
+                    time_sync.warnings.join("; ")
+                );
+            }
+            self.storage
+                .put_record(&SessionTimeSync::key(session_id), &time_sync)
+                .await?;
+        }
+        Ok(manifest)
+    }
+
+    pub async fn session_manifest(&self, session_id: &str) -> Result<Option<SessionManifest>> {
+        self.sessions.manifest(session_id).await
+    }
+
+    // The stored frames covered by a sealed session, in sequence order
+    pub async fn session_frames(
+        &self,
+        session_id: &str,
+    ) -> Result<(SessionManifest, Vec<EncryptedFrame>)> {
+        let manifest = self.sessions.manifest(session_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("No manifest for session {}", session_id))
+        })?;
+
+        let (from, to) = match (
+            manifest.first_frame_timestamp,
+            manifest.last_frame_timestamp,
+        ) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                return Err(ImmutableEncryptionError::NotFound(format!(
+                    "Session {} contains no frames",
+                    session_id
+                )))
+            }
+        };
+        let (first_sequence, last_sequence) = (
+            manifest.first_sequence.unwrap_or(0),
+            manifest.last_sequence.unwrap_or(u64::MAX),
+        );
+
+        let mut frames = Vec::new();
+        for frame_id in self
+            .storage
+            .list_device_frames(&manifest.device_id, from, to)
+            .await?
+        {
+            let frame = self.storage.retrieve_with_fallback(&frame_id).await?;
+            if (first_sequence..=last_sequence).contains(&frame.sequence) {
+                frames.push(frame);
+            }
+        }
+        frames.sort_by_key(|f| f.sequence);
+
+        Ok((manifest, frames))
+    }
+
+    // A stored frame as a signed COSE_Sign1 envelope, for tools that verify
+    // COSE rather than this node's own formats
+    // The metadata and telemetry a frame was sealed with, checked against its
+    // chain hash
+    pub async fn frame_metadata(&self, frame_id: &str) -> Result<MetadataRecord> {
+        let frame = self.storage.retrieve_with_fallback(frame_id).await?;
+        let record: MetadataRecord = self
+            .storage
+            .get_record(&MetadataRecord::key(&frame.device_id, frame.sequence))
+            .await?
+            .ok_or_else(|| {
+                ImmutableEncryptionError::NotFound(format!("No metadata recorded for {}", frame_id))
+            })?;
+        if !record.links_to(&frame) {
+            return Err(ImmutableEncryptionError::EvidenceTampered {
+                details: format!("metadata of {} does not match its chain hash", frame_id),
+            });
+        }
+        Ok(record)
+    }
+
+    pub async fn frame_envelope(&self, frame_id: &str) -> Result<Vec<u8>> {
+        let frame = self.storage.retrieve_with_fallback(frame_id).await?;
+        cose::seal_envelope(&frame, &*self.encryption_engine.lock().await)
+    }
+
+    // Export of a sealed session, streamed from storage on demand. None when
+    // the session doesn't exist or hasn't been sealed yet.
+    pub async fn evidence_bundle(&self, session_id: &str) -> Result<Option<EvidenceBundle>> {
+        let manifest = match self.sessions.manifest(session_id).await? {
+            Some(manifest) => manifest,
+            None => return Ok(None),
+        };
+
+        let frame_keys = match (
+            manifest.first_frame_timestamp,
+            manifest.last_frame_timestamp,
+        ) {
+            (Some(from), Some(to)) => {
+                self.storage
+                    .list_device_frames(&manifest.device_id, from, to)
+                    .await?
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(Some(EvidenceBundle::new(
+            manifest,
+            frame_keys,
+            self.encryption_engine.clone(),
+            self.storage.clone(),
+            self.bundle_digests.clone(),
+        )))
+    }
+
+    // Verifies a sealed session in the background, frame by frame, publishing
+    // progress that `verification_progress` subscribers receive as it changes
+    pub async fn start_verification_job(&self, session_id: &str) -> Result<VerificationProgress> {
+        let manifest = self.sessions.manifest(session_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("No manifest for session {}", session_id))
+        })?;
+
+        let job_id = format!(
+            "{:x}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_nanos()
+        );
+        let progress = VerificationProgress::new(job_id, session_id);
+        let tx = self.verification_jobs.register(progress.clone());
+
+        let node = self.clone();
+        tokio::spawn(async move {
+            let outcome = node.run_verification_job(&manifest, &tx).await;
+            let mut finished = tx.borrow().clone();
+            match outcome {
+                Ok(is_valid) => {
+                    finished.state = JobState::Completed;
+                    finished.is_valid = Some(is_valid);
+                }
+                Err(e) => {
+                    tracing::error!("Verification job {} failed: {}", finished.job_id, e);
+                    finished.state = JobState::Failed;
+                    finished.error = Some(e.to_string());
+                }
+            }
+
+            if !finished.anomalies.is_empty() {
+                node.events.publish(Event::TamperingDetected {
+                    evidence_id: finished.evidence_id.clone(),
+                    details: finished.anomalies.join("; "),
+                });
+            }
+            if let Some(is_valid) = finished.is_valid {
+                node.events.publish(Event::VerificationCompleted {
+                    evidence_id: finished.evidence_id.clone(),
+                    is_valid,
+                    frame_count: finished.frames_checked,
+                });
+            }
+            tx.send_replace(finished);
+        });
+
+        Ok(progress)
+    }
+
+    pub fn verification_progress(
+        &self,
+        job_id: &str,
+    ) -> Option<watch::Receiver<VerificationProgress>> {
+        self.verification_jobs.subscribe(job_id)
+    }
+
+    // Same checks as `verify_session`, but streamed from storage so memory use
+    // doesn't grow with the session. Returns whether the session is intact.
+    async fn run_verification_job(
+        &self,
+        manifest: &SessionManifest,
+        progress: &watch::Sender<VerificationProgress>,
+    ) -> Result<bool> {
+        if !manifest.verify(&*self.encryption_engine.lock().await)? {
+            progress.send_modify(|p| {
+                p.record_anomalies(vec![format!(
+                    "Manifest {} failed its signature check",
+                    manifest.manifest_hash
+                )])
+            });
+            return Ok(false);
+        }
+
+        let frame_keys = match (
+            manifest.first_frame_timestamp,
+            manifest.last_frame_timestamp,
+        ) {
+            (Some(from), Some(to)) => {
+                self.storage
+                    .list_device_frames(&manifest.device_id, from, to)
+                    .await?
+            }
+            _ => Vec::new(),
+        };
+        let sequences =
+            manifest.first_sequence.unwrap_or(0)..=manifest.last_sequence.unwrap_or(u64::MAX);
+        progress.send_modify(|p| p.frames_total = manifest.frame_count);
+
+        // Frames stored as rkyv are checked in place; their ciphertext is
+        // never decoded
+        let mut previous: Option<StoredFrame> = None;
+        let mut first_hash = None;
+        let mut seen_hashes = std::collections::HashSet::new();
+        let (mut checked, mut anchors) = (0u64, 0u64);
+        let mut pending = Vec::new();
+
+        for key in frame_keys {
+            let frame = self.storage.retrieve_stored_with_fallback(&key).await?;
+            let link = frame.link();
+            if !sequences.contains(&link.sequence()) {
+                continue;
+            }
+
+            pending.extend(
+                self.verifier
+                    .frame_anomalies(previous.as_ref().map(StoredFrame::link), link)?,
+            );
+            if !seen_hashes.insert(link.hash().to_string()) {
+                pending.push(format!(
+                    "Duplicate frame detected: hash {} appears multiple times",
+                    link.hash()
+                ));
+            }
+            anchors += self.verifier.confirmed_anchors(&frame.anchor_blocks());
+            first_hash.get_or_insert_with(|| link.hash().to_string());
+            checked += 1;
+
+            if !pending.is_empty() || checked % PROGRESS_INTERVAL == 0 {
+                let anomalies = std::mem::take(&mut pending);
+                progress.send_modify(|p| {
+                    p.frames_checked = checked;
+                    p.anchors_confirmed = anchors;
+                    p.record_anomalies(anomalies);
+                });
+            }
+            previous = Some(frame);
+        }
+
+        // The stored chain must match the endpoints sealed into the manifest
+        let last_hash = previous.map(|f| f.link().hash().to_string());
+        if first_hash != manifest.first_hash
+            || last_hash != manifest.last_hash
+            || checked != manifest.frame_count
+        {
+            pending.push(format!(
+                "Stored frames do not match session manifest {} ({} of {} frames found)",
+                manifest.manifest_hash, checked, manifest.frame_count
+            ));
+        }
+
+        let mut is_valid = false;
+        progress.send_modify(|p| {
+            p.frames_checked = checked;
+            p.anchors_confirmed = anchors;
+            p.record_anomalies(pending);
+            is_valid = p.anomaly_count == 0;
+        });
+        Ok(is_valid)
+    }
+
+    pub async fn verify_session(&self, session_id: &str) -> Result<crate::VerificationResult> {
+        let (manifest, frames) = self.session_frames(session_id).await?;
+
+        if !manifest.verify(&*self.encryption_engine.lock().await)? {
+            self.events.publish(Event::TamperingDetected {
+                evidence_id: session_id.to_string(),
+                details: format!(
+                    "manifest {} failed its signature check",
+                    manifest.manifest_hash
+                ),
+            });
+            return Err(ImmutableEncryptionError::EvidenceTampered {
+                details: format!(
+                    "Manifest for session {} failed its signature check",
+                    session_id
+                ),
+            });
+        }
+
+        let mut result = self.verifier.verify_integrity(&frames).await?;
+        result.court_report.evidence_id = session_id.to_string();
+        let sequences = manifest.first_sequence.unwrap_or(0)..=manifest.last_sequence.unwrap_or(0);
+        result.location_findings = self
+            .location_findings(&manifest.device_id, |f| sequences.contains(&f.sequence))
+            .await?;
+
+        // The stored chain must match the endpoints sealed into the manifest
+        let endpoints_match = frames.first().map(|f| &f.hash) == manifest.first_hash.as_ref()
+            && frames.last().map(|f| &f.hash) == manifest.last_hash.as_ref()
+            && frames.len() as u64 == manifest.frame_count;
+        if !endpoints_match {
+            result.is_valid = false;
+            result.tamper_evidence.get_or_insert_with(|| {
+                format!(
+                    "Stored frames do not match session manifest {} ({} of {} frames found)",
+                    manifest.manifest_hash,
+                    frames.len(),
+                    manifest.frame_count
+                )
+            });
+        }
+        self.publish_verification(&result);
+
+        Ok(result)
+    }
+
+    pub async fn register_rendition(
+        &self,
+        evidence_id: &str,
+        profile: TranscodeProfile,
+        digest: RenditionDigest,
+    ) -> Result<RenditionRecord> {
+        let manifest = self.sessions.manifest(evidence_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!(
+                "No sealed session {} to derive from",
+                evidence_id
+            ))
+        })?;
+
+        let record = {
+            let engine = self.encryption_engine.lock().await;
+            RenditionRecord::link(&manifest, profile, digest, &engine)?
+        };
+
+        self.storage
+            .put_record(
+                &format!("rendition:{}:{}", evidence_id, record.rendition_id),
+                &record,
+            )
+            .await?;
+
+        Ok(record)
+    }
+
+    pub async fn renditions(&self, evidence_id: &str) -> Result<Vec<RenditionRecord>> {
+        Ok(self
+            .storage
+            .scan_records::<RenditionRecord>(&format!("rendition:{}:", evidence_id))
+            .await?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect())
+    }
+
+    // Redacts regions of a sealed session's frames into a new rendition that
+    // links back to the original and commits to what it hides. The salts
+    // opening those commitments stay on the node for later verification.
+    pub async fn redact_session(
+        &self,
+        evidence_id: &str,
+        actor: &Principal,
+        request: RedactionRequest,
+    ) -> Result<(RenditionRecord, Vec<u8>)> {
+        self.ensure_not_on_hold(evidence_id, "redaction").await?;
+        let action = ApprovalAction::Decrypt {
+            evidence_id: evidence_id.to_string(),
+        };
+        self.require_approval(&action, request.approval_id.as_deref(), actor)
+            .await?;
+        let (manifest, originals) = self.decrypted_session_frames(evidence_id).await?;
+        let (redaction, opening, data) = Redaction::apply(&originals, &request, &actor.subject)?;
+
+        let record = {
+            let engine = self.encryption_engine.lock().await;
+            RenditionRecord::link_redacted(&manifest, redaction, hash_rendition(&data), &engine)?
+        };
+        self.storage
+            .put_record(&RedactionOpening::key(&record.rendition_id), &opening)
+            .await?;
+        self.storage
+            .put_record(
+                &format!("rendition:{}:{}", evidence_id, record.rendition_id),
+                &record,
+            )
+            .await?;
+        self.audit(actor, "redact_session", Some(evidence_id))
+            .await?;
+
+        Ok((record, data))
+    }
+
+    // Re-derives a redacted rendition from the sealed originals, checking its
+    // link, every redacted frame and every region commitment
+    pub async fn verify_redaction(&self, evidence_id: &str, rendition_id: &str) -> Result<bool> {
+        let record: RenditionRecord = self
+            .storage
+            .get_record(&format!("rendition:{}:{}", evidence_id, rendition_id))
+            .await?
+            .ok_or_else(|| {
+                ImmutableEncryptionError::NotFound(format!("Unknown rendition {}", rendition_id))
+            })?;
+        let redaction = record.redaction.as_ref().ok_or_else(|| {
+            ImmutableEncryptionError::InvalidRequest(format!(
+                "Rendition {} is not a redaction",
+                rendition_id
+            ))
+        })?;
+        let opening: RedactionOpening = self
+            .storage
+            .get_record(&RedactionOpening::key(rendition_id))
+            .await?
+            .ok_or_else(|| {
+                ImmutableEncryptionError::NotFound(format!(
+                    "No opening kept for redaction {}",
+                    rendition_id
+                ))
+            })?;
+
+        let (manifest, originals) = self.decrypted_session_frames(evidence_id).await?;
+        if manifest.manifest_hash != record.parent_manifest_hash
+            || !record.verify_link(&*self.encryption_engine.lock().await)?
+        {
+            return Ok(false);
+        }
+        redaction.verify(&originals, &opening)
+    }
+
+    // Discloses only the frames captured between `from` and `to`, re-sealed
+    // to the recipient's Kyber public key with proofs back to the full session
+    pub async fn share_clip(
+        &self,
+        evidence_id: &str,
+        from: u64,
+        to: u64,
+        recipient_key: &[u8],
+        actor: &Principal,
+        approval_id: Option<&str>,
+    ) -> Result<SharedClip> {
+        let (manifest, frames) = self.session_frames(evidence_id).await?;
+        let action = ApprovalAction::Decrypt {
+            evidence_id: evidence_id.to_string(),
+        };
+        self.require_approval(&action, approval_id, actor).await?;
+        let clip = {
+            let engine = self.encryption_engine.lock().await;
+            SharedClip::seal(
+                manifest,
+                &frames,
+                from,
+                to,
+                recipient_key,
+                &engine,
+                |frame| engine.decrypt_frame_data(frame),
+            )?
+        };
+        self.audit(actor, "share_clip", Some(evidence_id)).await?;
+        Ok(clip)
+    }
+
+    async fn decrypted_session_frames(
+        &self,
+        session_id: &str,
+    ) -> Result<(SessionManifest, Vec<(EncryptedFrame, Vec<u8>)>)> {
+        let (manifest, frames) = self.session_frames(session_id).await?;
+        let engine = self.encryption_engine.lock().await;
+        let originals = frames
+            .into_iter()
+            .map(|frame| {
+                let payload = engine.decrypt_frame_data(&frame)?;
+                Ok((frame, payload))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((manifest, originals))
+    }
+
+    pub async fn list_evidence(&self, query: &EvidenceQuery) -> Result<Page<EvidenceSummary>> {
+        EvidenceBrowser::new(&self.storage, &self.sessions)
+            .list_evidence(query)
+            .await
+    }
+
+    pub async fn evidence_frames(
+        &self,
+        evidence_id: &str,
+        query: &FrameQuery,
+    ) -> Result<Option<Page<FrameSummary>>> {
+        EvidenceBrowser::new(&self.storage, &self.sessions)
+            .list_frames(evidence_id, query)
+            .await
+    }
+
+    // Pipeline counters alone, without the chain and storage lookups of `status`
+    pub async fn pipeline_stats(&self) -> NodeStatus {
+        self.stats.snapshot().await
+    }
+
+    // Live pipeline state for `/status`. Chain and storage lookups that fail
+    // are reported as unknown rather than failing the whole status call.
+    pub async fn status(&self) -> NodeStatus {
+        let mut status = self.stats.snapshot().await;
+
+        for anchor in self.stats.last_anchors().await {
+            let confirmations = match self.blockchain_anchor.confirmation_count(&anchor).await {
+                Ok(count) => Some(count),
+                Err(e) => {
+                    tracing::warn!("Failed to query {} confirmations: {}", anchor.chain, e);
+                    None
+                }
+            };
+            status.last_anchors.push(AnchorStatus {
+                chain: anchor.chain,
+                transaction_hash: anchor.transaction_hash,
+                block_number: anchor.block_number,
+                anchored_at: anchor.timestamp,
+                confirmations,
+            });
+        }
+
+        status.storage = match self.storage.usage().await {
+            Ok(usage) => Some(usage),
+            Err(e) => {
+                tracing::warn!("Failed to read storage usage: {}", e);
+                None
+            }
+        };
+        status.key_epoch = self.encryption_engine.lock().await.key_epoch();
+
+        status
+    }
+
+    // Probes what this node alone owns: its database and its keys
+    pub async fn probe_local(&self, config: &HealthConfig) -> Vec<DependencyHealth> {
+        let rocksdb = health::probe(config, health::ROCKSDB, health::ROCKSDB, async {
+            self.storage.probe_primary().await
+        });
+        let keystore = health::probe(config, health::KEYSTORE, health::KEYSTORE, async {
+            let hardware = {
+                let engine = self.encryption_engine.lock().await;
+                engine.probe_keys()?;
+                engine.hardware()
+            };
+            match hardware {
+                Some(hardware) => {
+                    tokio::task::spawn_blocking(move || crypto::probe_attestation(&*hardware))
+                        .await?
+                }
+                None => Ok(()),
+            }
+        });
+
+        let (rocksdb, keystore) = tokio::join!(rocksdb, keystore);
+        vec![rocksdb, keystore]
+    }
+
+    // Probes the IPFS API and every configured blockchain endpoint
+    pub async fn probe_external(&self, config: &HealthConfig) -> Vec<DependencyHealth> {
+        let chains = self.blockchain_anchor.probe_targets();
+        let chain_probes = chains.iter().map(|target| {
+            health::probe(config, target, health::BLOCKCHAIN, async move {
+                self.blockchain_anchor.probe(target).await
+            })
+        });
+        let ipfs = async {
+            if !self.storage.ipfs_enabled() {
+                return None;
+            }
+            let check = self.storage.probe_ipfs();
+            Some(health::probe(config, health::IPFS, health::IPFS, check).await)
+        };
+
+        let (mut dependencies, ipfs) = tokio::join!(futures::future::join_all(chain_probes), ipfs);
+        dependencies.extend(ipfs);
+        dependencies
+    }
+
+    // Appends to the signed, hash-chained audit log
+    pub async fn audit(
+        &self,
+        actor: &Principal,
+        action: &str,
+        target: Option<&str>,
+    ) -> Result<AuditRecord> {
+        self.audit_log
+            .record(&actor.subject, actor.source.as_deref(), action, target)
+            .await
+    }
+
+    pub async fn admin_audit_log(&self) -> Result<Vec<AuditRecord>> {
+        self.audit_log.records().await
+    }
+
+    pub async fn verify_audit_log(&self) -> Result<AuditVerification> {
+        self.audit_log.verify().await
+    }
+
+    // Records the loaded config's fingerprint when it differs from the last
+    // one seen; returns whether it did
+    pub async fn record_config(&self, fingerprint: &str) -> Result<bool> {
+        self.audit_log.record_config(fingerprint).await
+    }
+
+    pub async fn anchor_audit_log(&self) -> Result<Option<AuditAnchor>> {
+        self.audit_log.anchor_tip(&self.blockchain_anchor).await
+    }
+
+    // Anchors the audit log's tip every `period` until shutdown, so the trail
+    // can be checked against the chain like the evidence it covers
+    pub fn spawn_audit_anchoring(&self, period: Duration) -> JoinHandle<()> {
+        let node = self.clone();
+        let mut shutdown = self.shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut ticks = interval(period);
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+                match node.anchor_audit_log().await {
+                    Ok(Some(anchor)) => {
+                        tracing::info!("Anchored audit log through record {}", anchor.index)
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::error!("Failed to anchor the audit log: {}", e),
+                }
+            }
+        })
+    }
+
+    // Frame keys move to a new epoch on their own; this only regenerates the
+    // current epoch's post-quantum keys. The primary key stays: replacing it
+    // is `keys rotate`, with the node stopped. Returns the current key epoch.
+    pub async fn refresh_post_quantum_keys(&self, actor: &Principal) -> Result<u64> {
+        let epoch = {
+            let mut engine = self.encryption_engine.lock().await;
+            engine.refresh_post_quantum_keys()?;
+            engine.key_epoch()
+        };
+        self.audit(
+            actor,
+            "refresh_post_quantum_keys",
+            Some(&format!("epoch:{}", epoch)),
+        )
+        .await?;
+
+        tracing::info!(
+            "{} refreshed the post-quantum keys for epoch {}",
+            actor.subject,
+            epoch
+        );
+        Ok(epoch)
+    }
+
+    // Anchors whatever is queued now; returns the queue depth at the request
+    pub async fn flush_anchor_queue(&self, actor: &Principal) -> Result<usize> {
+        let queued = self.stats.snapshot().await.batch_queue_depth;
+        self.anchor_flush.notify_one();
+        self.audit(actor, "flush_anchor_queue", Some(&queued.to_string()))
+            .await?;
+
+        Ok(queued)
+    }
+
+    // Starts a background scrub and returns its initial report
+    pub async fn start_scrub(&self, actor: &Principal) -> Result<ScrubReport> {
+        let scrub_id = format!(
+            "{:x}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_nanos()
+        );
+        let report = ScrubReport::new(scrub_id.clone(), &actor.subject)?;
+        self.storage
+            .put_record(&ScrubReport::key(&scrub_id), &report)
+            .await?;
+        self.audit(actor, "start_scrub", Some(&scrub_id)).await?;
+
+        let node = self.clone();
+        let mut running = report.clone();
+        tokio::spawn(async move {
+            match scrub_storage(&node.storage, &mut running).await {
+                Ok(()) => {
+                    running.state = ScrubState::Completed;
+                    let found = running.corrupt_frames.len()
+                        + running.dangling_index_entries.len()
+                        + running.unlinked_frames.len();
+                    if found > 0 {
+                        node.events.publish(Event::CorruptionDetected {
+                            scrub_id: running.scrub_id.clone(),
+                            corrupt_frames: running.corrupt_frames.len(),
+                            dangling_index_entries: running.dangling_index_entries.len(),
+                            unlinked_frames: running.unlinked_frames.len(),
+                        });
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Storage scrub {} failed: {}", running.scrub_id, e);
+                    running.state = ScrubState::Failed;
+                    running.error = Some(e.to_string());
+                }
+            }
+            running.finished_at = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs());
+
+            let key = ScrubReport::key(&running.scrub_id);
+            if let Err(e) = node.storage.put_record(&key, &running).await {
+                tracing::error!("Failed to save scrub report {}: {}", running.scrub_id, e);
+            }
+        });
+
+        Ok(report)
+    }
+
+    pub async fn scrub_report(&self, scrub_id: &str) -> Result<Option<ScrubReport>> {
+        self.storage.get_record(&ScrubReport::key(scrub_id)).await
+    }
+
+    pub async fn legal_hold(&self, evidence_id: &str) -> Result<Option<LegalHold>> {
+        self.storage.get_record(&legal_hold_key(evidence_id)).await
+    }
+
+    pub async fn place_legal_hold(
+        &self,
+        evidence_id: &str,
+        actor: &Principal,
+        request: LegalHoldRequest,
+    ) -> Result<LegalHold> {
+        if self.sessions.session(evidence_id).await?.is_none() {
+            return Err(ImmutableEncryptionError::NotFound(format!(
+                "Unknown evidence {}",
+                evidence_id
+            )));
+        }
+        if let Some(existing) = self.legal_hold(evidence_id).await? {
+            if existing.is_active() {
+                return Err(ImmutableEncryptionError::LegalComplianceFailed(format!(
+                    "Evidence {} is already on hold",
+                    evidence_id
+                )));
+            }
+        }
+
+        let hold = LegalHold {
+            evidence_id: evidence_id.to_string(),
+            case_id: request.case_id,
+            reason: request.reason,
+            placed_by: actor.subject.clone(),
+            placed_at: now()?,
+            released_by: None,
+            released_at: None,
+            release_reason: None,
+            release_authorization: None,
+            signature: String::new(),
+        };
+        let hold = self.store_legal_hold(hold).await?;
+        self.audit(actor, "place_legal_hold", Some(evidence_id))
+            .await?;
+
+        Ok(hold)
+    }
+
+    pub async fn release_legal_hold(
+        &self,
+        evidence_id: &str,
+        actor: &Principal,
+        release: LegalHoldRelease,
+    ) -> Result<LegalHold> {
+        if release.authorization.trim().is_empty() {
+            return Err(ImmutableEncryptionError::invalid_request(
+                "Releasing a legal hold needs the authorization that lifts it",
+            ));
+        }
+        let mut hold = self
+            .legal_hold(evidence_id)
+            .await?
+            .filter(LegalHold::is_active)
+            .ok_or_else(|| {
+                ImmutableEncryptionError::LegalComplianceFailed(format!(
+                    "Evidence {} is not on hold",
+                    evidence_id
+                ))
+            })?;
+
+        hold.released_by = Some(actor.subject.clone());
+        hold.released_at = Some(now()?);
+        hold.release_reason = Some(release.reason);
+        hold.release_authorization = Some(release.authorization);
+        let hold = self.store_legal_hold(hold).await?;
+        self.audit(actor, "release_legal_hold", Some(evidence_id))
+            .await?;
+
+        Ok(hold)
+    }
+
+    // Signs the hold and records it alongside a signed custody entry
+    async fn store_legal_hold(&self, hold: LegalHold) -> Result<LegalHold> {
+        let (hold, entry) = {
+            let engine = self.encryption_engine.lock().await;
+            let hold = hold.sign(&engine)?;
+            let entry = hold.custody_entry(now()?, &engine)?;
+            (hold, entry)
+        };
+        self.storage
+            .put_record(&legal_hold_key(&hold.evidence_id), &hold)
+            .await?;
+        self.storage
+            .append_custody_entry(&LegalHold::custody_scope(&hold.evidence_id), &entry)
+            .await?;
+        Ok(hold)
+    }
+
+    pub async fn legal_hold_status(&self, evidence_id: &str) -> Result<Option<LegalHoldStatus>> {
+        let Some(hold) = self.legal_hold(evidence_id).await? else {
+            return Ok(None);
+        };
+        let public_key = self.encryption_engine.lock().await.envelope_public_key()?;
+        Ok(Some(LegalHoldStatus {
+            signature_valid: !hold.signature.is_empty() && hold.verify(&public_key)?,
+            history: self
+                .storage
+                .custody_entries(&LegalHold::custody_scope(evidence_id))
+                .await?,
+            hold,
+        }))
+    }
+
+    // Refuses an operation that would alter or remove evidence under hold
+    pub async fn ensure_not_on_hold(&self, evidence_id: &str, operation: &str) -> Result<()> {
+        match self.legal_hold(evidence_id).await? {
+            Some(hold) if hold.is_active() => {
+                Err(ImmutableEncryptionError::LegalComplianceFailed(format!(
+                    "Evidence {} is under legal hold{}; {} refused",
+                    evidence_id,
+                    hold.case_id
+                        .map(|case| format!(" for case {}", case))
+                        .unwrap_or_default(),
+                    operation
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Deletes the frames of a sealed session. Its manifest, custody trail and
+    // the purge record itself stay, so the deletion remains on record.
+    pub async fn purge_session(
+        &self,
+        evidence_id: &str,
+        actor: &Principal,
+        reason: &str,
+        approval_id: Option<&str>,
+    ) -> Result<RetentionPurge> {
+        self.ensure_not_on_hold(evidence_id, "purge").await?;
+        let manifest = self.sessions.manifest(evidence_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("No manifest for session {}", evidence_id))
+        })?;
+        if self.retention_purge(evidence_id).await?.is_some() {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "Session {} has already been purged",
+                evidence_id
+            )));
+        }
+        let action = ApprovalAction::Purge {
+            evidence_id: evidence_id.to_string(),
+        };
+        self.require_approval(&action, approval_id, actor).await?;
+
+        let frames = match self.session_frames(evidence_id).await {
+            Ok((_, frames)) => frames,
+            Err(ImmutableEncryptionError::NotFound(_)) => Vec::new(), // sealed empty
+            Err(e) => return Err(e),
+        };
+        for frame in &frames {
+            self.storage.delete_frame(frame).await?;
+            self.storage
+                .delete_record(&MetadataRecord::key(&frame.device_id, frame.sequence))
+                .await?;
+        }
+
+        let purge = RetentionPurge {
+            evidence_id: evidence_id.to_string(),
+            device_id: manifest.device_id,
+            frames_deleted: frames.len(),
+            reason: reason.to_string(),
+            purged_by: actor.subject.clone(),
+            purged_at: now()?,
+        };
+        self.storage
+            .put_record(&RetentionPurge::key(evidence_id), &purge)
+            .await?;
+        self.audit(actor, "purge_session", Some(evidence_id))
+            .await?;
+
+        Ok(purge)
+    }
+
+    pub async fn retention_purge(&self, evidence_id: &str) -> Result<Option<RetentionPurge>> {
+        self.storage
+            .get_record(&RetentionPurge::key(evidence_id))
+            .await
+    }
+
+    // Purges every sealed session whose retention period has run out,
+    // leaving those under an active legal hold in place. When purges need
+    // approval, expired sessions are only listed.
+    pub async fn purge_expired_sessions(&self, actor: &Principal) -> Result<RetentionReport> {
+        let now = now()?;
+        let mut report = RetentionReport::default();
+        for (_, manifest) in self
+            .storage
+            .scan_records::<SessionManifest>("manifest:")
+            .await?
+        {
+            let session_id = manifest.session_id;
+            let expired = manifest.retain_until.is_some_and(|until| until <= now);
+            if !expired || self.retention_purge(&session_id).await?.is_some() {
+                continue;
+            }
+            let held = self
+                .legal_hold(&session_id)
+                .await?
+                .is_some_and(|hold| hold.is_active());
+            if held {
+                report.held.push(session_id);
+            } else if self.approvals.purge.is_some() {
+                report.awaiting_approval.push(session_id);
+            } else {
+                let purge = self
+                    .purge_session(&session_id, actor, "retention period expired", None)
+                    .await?;
+                report.purged.push(purge);
+            }
         }
+        Ok(report)
+    }
 
-        // Store frames with redundancy
-        let mut storage_tasks = Vec::new();
+    // Opens a request for a sensitive action on a sealed session, to collect
+    // the approvals its policy asks for
+    pub async fn request_approval(
+        &self,
+        action: ApprovalAction,
+        reason: String,
+        actor: &Principal,
+    ) -> Result<ApprovalRequest> {
+        let evidence_id = action.evidence_id().to_string();
+        if self.sessions.manifest(&evidence_id).await?.is_none() {
+            return Err(ImmutableEncryptionError::NotFound(format!(
+                "No manifest for session {}",
+                evidence_id
+            )));
+        }
+        let now = now()?;
+        let request_id = format!(
+            "{:x}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_nanos()
+        );
+        let request =
+            ApprovalRequest::new(request_id, action, reason, actor, &self.approvals, now)?;
+        self.storage
+            .put_record(&ApprovalRequest::key(&request.request_id), &request)
+            .await?;
+        self.audit(actor, "request_approval", Some(&request.request_id))
+            .await?;
+        Ok(request)
+    }
 
-        for frame in frames.iter() {
-            let storage = self.storage.clone();
-            let frame_clone = frame.clone();
+    pub async fn approval_request(&self, request_id: &str) -> Result<Option<ApprovalRequest>> {
+        self.storage
+            .get_record(&ApprovalRequest::key(request_id))
+            .await
+    }
+
+    // Every approval request made for a session, oldest first
+    pub async fn approval_requests(&self, evidence_id: &str) -> Result<Vec<ApprovalRequest>> {
+        let mut requests: Vec<ApprovalRequest> = self
+            .storage
+            .scan_records::<ApprovalRequest>("approval:")
+            .await?
+            .into_iter()
+            .map(|(_, request)| request)
+            .filter(|request| request.action.evidence_id() == evidence_id)
+            .collect();
+        requests.sort_by_key(|request| request.requested_at);
+        Ok(requests)
+    }
+
+    // The bytes the caller signs with their own key to approve a request
+    pub async fn approval_statement(&self, request_id: &str, actor: &Principal) -> Result<Vec<u8>> {
+        let request = self.approval_request(request_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("Unknown approval request {}", request_id))
+        })?;
+        request.statement(actor.accountable())
+    }
+
+    pub async fn approve_request(
+        &self,
+        request_id: &str,
+        actor: &Principal,
+        signature: &[u8],
+    ) -> Result<ApprovalRequest> {
+        let request = self
+            .update_approval_request(request_id, |request, now| {
+                request.approve(actor, now, signature, &self.approvals)
+            })
+            .await?;
+        self.audit(actor, "approve_request", Some(request_id))
+            .await?;
+        Ok(request)
+    }
+
+    pub async fn reject_request(
+        &self,
+        request_id: &str,
+        actor: &Principal,
+        reason: String,
+    ) -> Result<ApprovalRequest> {
+        let request = self
+            .update_approval_request(request_id, |request, now| {
+                request.reject(actor, now, reason)
+            })
+            .await?;
+        self.audit(actor, "reject_request", Some(request_id))
+            .await?;
+        Ok(request)
+    }
+
+    // Lets an action covered by a policy run only under an approved request,
+    // which is spent by it. Actions without a policy run as before.
+    pub async fn require_approval(
+        &self,
+        action: &ApprovalAction,
+        approval_id: Option<&str>,
+        actor: &Principal,
+    ) -> Result<Option<ApprovalRequest>> {
+        let Some(policy) = self.approvals.policy(action) else {
+            return Ok(None);
+        };
+        let request_id = approval_id.ok_or_else(|| {
+            ImmutableEncryptionError::PermissionDenied(format!(
+                "Approval from {} of {:?} is needed to {}",
+                policy.required,
+                policy.roles,
+                action.describe()
+            ))
+        })?;
+        let request = self
+            .update_approval_request(request_id, |request, now| {
+                request.execute(action, &actor.subject, now)
+            })
+            .await?;
+        self.audit(actor, "execute_approved_request", Some(request_id))
+            .await?;
+        Ok(Some(request))
+    }
 
-            let task =
-                tokio::spawn(async move { storage.store_with_redundancy(&frame_clone).await });
+    // Playback and snapshots decrypt by device and time rather than session.
+    // Under a decrypt policy they need an approved request whose session
+    // recorded that device over the whole range, which they spend; live
+    // playback has no end and so no session to approve.
+    pub async fn require_decrypt_approval(
+        &self,
+        device_id: &str,
+        from: u64,
+        to: Option<u64>,
+        approval_id: Option<&str>,
+        actor: &Principal,
+    ) -> Result<Option<ApprovalRequest>> {
+        if self.approvals.decrypt.is_none() {
+            return Ok(None);
+        }
+        let request_id = approval_id.ok_or_else(|| {
+            ImmutableEncryptionError::PermissionDenied(format!(
+                "[approvals.decrypt] is set; decrypting {} needs an approved request",
+                device_id
+            ))
+        })?;
+        let request = self.approval_request(request_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("Unknown approval request {}", request_id))
+        })?;
+        if !matches!(request.action, ApprovalAction::Decrypt { .. }) {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "Request {} does not approve decryption",
+                request_id
+            )));
+        }
+        let covered = match (
+            self.session_manifest(request.action.evidence_id()).await?,
+            to,
+        ) {
+            (Some(manifest), Some(to)) => {
+                manifest.device_id == device_id
+                    && manifest
+                        .first_frame_timestamp
+                        .is_some_and(|first| first <= from)
+                    && manifest.last_frame_timestamp.is_some_and(|last| to <= last)
+            }
+            _ => false,
+        };
+        if !covered {
+            return Err(ImmutableEncryptionError::PermissionDenied(format!(
+                "Request {} does not cover {} from {} to {:?}",
+                request_id, device_id, from, to
+            )));
+        }
+        self.require_approval(&request.action, Some(request_id), actor)
+            .await
+    }
 
-            storage_tasks.push(task);
+    // A snapshot needs the same approval as playing back its frame
+    pub async fn require_frame_decrypt_approval(
+        &self,
+        frame_id: &str,
+        approval_id: Option<&str>,
+        actor: &Principal,
+    ) -> Result<Option<ApprovalRequest>> {
+        if self.approvals.decrypt.is_none() {
+            return Ok(None);
         }
+        let frame = self.storage.retrieve_with_fallback(frame_id).await?;
+        self.require_decrypt_approval(
+            &frame.device_id,
+            frame.timestamp,
+            Some(frame.timestamp),
+            approval_id,
+            actor,
+        )
+        .await
+    }
 
-        // Wait for all storage operations
-        let storage_results = futures::future::join_all(storage_tasks).await;
+    async fn update_approval_request<F>(
+        &self,
+        request_id: &str,
+        update: F,
+    ) -> Result<ApprovalRequest>
+    where
+        F: FnOnce(&mut ApprovalRequest, u64) -> Result<()>,
+    {
+        let _guard = self.approval_lock.lock().await;
+        let mut request = self.approval_request(request_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("Unknown approval request {}", request_id))
+        })?;
+        update(&mut request, now()?)?;
+        self.storage
+            .put_record(&ApprovalRequest::key(request_id), &request)
+            .await?;
+        Ok(request)
+    }
 
-        for (i, result) in storage_results.into_iter().enumerate() {
-            match result {
-                Ok(Ok(locations)) => {
-                    tracing::info!("Frame {} stored at {:?}", frames[i].sequence, locations);
-                }
-                Ok(Err(e)) => {
-                    tracing::error!("Failed to store frame {}: {}", frames[i].sequence, e);
-                }
-                Err(e) => {
-                    tracing::error!("Storage task failed: {}", e);
-                }
+    // Hands a sealed session to another custodian, e.g. a lab or the court,
+    // as a signed entry in its device's chain of custody
+    pub async fn transfer_custody(
+        &self,
+        evidence_id: &str,
+        to: &str,
+        reason: &str,
+        actor: &Principal,
+        approval_id: Option<&str>,
+    ) -> Result<CustodyEntry> {
+        let manifest = self.sessions.manifest(evidence_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("No manifest for session {}", evidence_id))
+        })?;
+        let action = ApprovalAction::CustodyTransfer {
+            evidence_id: evidence_id.to_string(),
+            to: to.to_string(),
+        };
+        let approval = self.require_approval(&action, approval_id, actor).await?;
+
+        let timestamp = now()?;
+        let action = format!(
+            "custody_transfer:{}:to={}:reason={}:approval={}",
+            evidence_id,
+            to,
+            reason,
+            approval.as_ref().map_or("-", |a| a.request_id.as_str())
+        );
+        let signature = self
+            .encryption_engine
+            .lock()
+            .await
+            .sign(format!("{}|{}|{}", timestamp, actor.subject, action).as_bytes());
+        let entry = CustodyEntry {
+            timestamp,
+            actor: actor.subject.clone(),
+            action,
+            signature,
+            blockchain_reference: String::new(),
+        };
+        self.storage
+            .append_custody_entry(&manifest.device_id, &entry)
+            .await?;
+        self.audit(actor, "transfer_custody", Some(evidence_id))
+            .await?;
+        Ok(entry)
+    }
+
+    // Applies stored enrollments to the device registry, returning how many
+    // devices are enrolled
+    pub async fn load_enrollments(&self) -> Result<usize> {
+        let now = now()?;
+        let mut active = 0;
+        for enrollment in self.device_enrollments().await? {
+            if enrollment.revoked.is_some() {
+                self.device_registry.revoke(&enrollment.device_id);
+            } else if enrollment.is_active(now) {
+                self.device_registry
+                    .enroll(&enrollment.certificate_fingerprint, &enrollment.device_id);
+                active += 1;
             }
         }
+        Ok(active)
+    }
 
-        // Clear processed frames
-        frames.clear();
+    pub async fn device_enrollments(&self) -> Result<Vec<DeviceEnrollment>> {
+        Ok(self
+            .storage
+            .scan_records::<DeviceEnrollment>(DeviceEnrollment::PREFIX)
+            .await?
+            .into_iter()
+            .map(|(_, enrollment)| enrollment)
+            .collect())
+    }
 
-        Ok(())
+    pub async fn device_enrollment(&self, device_id: &str) -> Result<DeviceEnrollment> {
+        self.storage
+            .get_record(&DeviceEnrollment::key(device_id))
+            .await?
+            .ok_or_else(|| {
+                ImmutableEncryptionError::NotFound(format!("Device {} is not enrolled", device_id))
+            })
+    }
+
+    // A nonce for the device to sign with its attestation key before enrolling
+    pub async fn enrollment_challenge(&self, device_id: &str) -> Result<EnrollmentChallenge> {
+        self.validator.validate_device_id(device_id)?;
+        let challenge = self.issuer()?.challenge(device_id, now()?)?;
+        self.storage
+            .put_record(&EnrollmentChallenge::key(device_id), &challenge)
+            .await?;
+        Ok(challenge)
     }
 
-    fn create_mock_metadata(&self, sequence: u64) -> FrameMetadata {
-        FrameMetadata {
-            device_id: format!("device_{}", sequence % 3),
-            location: Some((40.7128, -74.0060)), // Mock location
-            resolution: (1920, 1080),
-            fps: 30,
-            codec: "H.264".to_string(),
+    // Issues the device a key and client certificate. Enrolling again
+    // rotates them, and the previous certificate is no longer accepted.
+    pub async fn enroll_device(
+        &self,
+        device_id: &str,
+        request: EnrollmentRequest,
+        actor: &Principal,
+    ) -> Result<IssuedDevice> {
+        self.validator.validate_device_id(device_id)?;
+        let issuer = self.issuer()?;
+        let now = now()?;
+
+        // Each challenge answers for one attempt
+        let challenge_key = EnrollmentChallenge::key(device_id);
+        let challenge: Option<EnrollmentChallenge> =
+            self.storage.get_record(&challenge_key).await?;
+        if challenge.is_some() {
+            self.storage.delete_record(&challenge_key).await?;
         }
+        let attested = issuer.check_attestation(
+            device_id,
+            challenge.as_ref(),
+            request.attestation.as_deref(),
+            now,
+        )?;
+
+        let issued = issuer.issue(device_id, attested, &actor.subject, now)?;
+        self.storage
+            .put_record(&DeviceEnrollment::key(device_id), &issued.enrollment)
+            .await?;
+        self.device_registry
+            .enroll(&issued.enrollment.certificate_fingerprint, device_id);
+        self.audit(actor, "enroll_device", Some(device_id)).await?;
+
+        Ok(issued)
     }
 
-    async fn create_verification_receiver(&self) -> EncryptedFrameReceiver {
-        let (tx, rx) = mpsc::unbounded_channel();
+    pub async fn revoke_device(
+        &self,
+        device_id: &str,
+        reason: &str,
+        actor: &Principal,
+    ) -> Result<DeviceEnrollment> {
+        let mut enrollment = self.device_enrollment(device_id).await?;
+        if enrollment.revoked.is_some() {
+            return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                "Device {} is already revoked",
+                device_id
+            )));
+        }
 
-        // This would be used for external verification requests
-        // For now, we'll just return the receiver
-        rx
+        enrollment.revoked = Some(Revocation {
+            revoked_by: actor.subject.clone(),
+            revoked_at: now()?,
+            reason: reason.to_string(),
+        });
+        self.storage
+            .put_record(&DeviceEnrollment::key(device_id), &enrollment)
+            .await?;
+        self.device_registry.revoke(device_id);
+        self.audit(actor, "revoke_device", Some(device_id)).await?;
+
+        Ok(enrollment)
     }
 
-    pub async fn verify_evidence(&self, frame_ids: &[String]) -> Result<crate::VerificationResult> {
-        let mut frames = Vec::new();
+    fn issuer(&self) -> Result<&DeviceIssuer> {
+        self.enrollment.as_deref().ok_or_else(|| {
+            ImmutableEncryptionError::ResourceUnavailable(
+                "device enrollment is not enabled".to_string(),
+            )
+        })
+    }
 
-        // Retrieve frames
-        for frame_id in frame_ids {
-            match self.storage.retrieve_with_fallback(frame_id).await {
-                Ok(frame) => frames.push(frame),
-                Err(e) => tracing::error!("Failed to retrieve frame {}: {}", frame_id, e),
-            }
+    pub async fn case(&self, case_id: &str, actor: &Principal) -> Result<Case> {
+        let case: Case = self
+            .storage
+            .get_record(&Case::key(case_id))
+            .await?
+            .ok_or_else(|| {
+                ImmutableEncryptionError::NotFound(format!("Unknown case {}", case_id))
+            })?;
+        case.require_access(actor)?;
+        Ok(case)
+    }
+
+    // Cases restricted to assigned personnel are left out for everyone else
+    pub async fn list_cases(&self, actor: &Principal) -> Result<Vec<Case>> {
+        Ok(self
+            .storage
+            .scan_records::<Case>("case:")
+            .await?
+            .into_iter()
+            .map(|(_, case)| case)
+            .filter(|case| case.can_access(actor))
+            .collect())
+    }
+
+    pub async fn put_case(
+        &self,
+        case_id: &str,
+        actor: &Principal,
+        request: CaseRequest,
+    ) -> Result<Case> {
+        let existing: Option<Case> = self.storage.get_record(&Case::key(case_id)).await?;
+        if let Some(existing) = &existing {
+            existing.require_access(actor)?;
         }
 
-        if frames.is_empty() {
-            return Err(anyhow!("No valid frames found for verification"));
+        let case = Case::apply(existing, case_id, request, actor)?;
+        self.storage.put_record(&Case::key(case_id), &case).await?;
+        self.audit(actor, "put_case", Some(case_id)).await?;
+
+        Ok(case)
+    }
+
+    pub async fn attach_session_to_case(
+        &self,
+        case_id: &str,
+        session_id: &str,
+        actor: &Principal,
+    ) -> Result<Case> {
+        let mut case = self.case(case_id, actor).await?;
+        let session = self.sessions.session(session_id).await?.ok_or_else(|| {
+            ImmutableEncryptionError::NotFound(format!("Unknown session {}", session_id))
+        })?;
+
+        case.attach(&session)?;
+        self.storage.put_record(&Case::key(case_id), &case).await?;
+        self.audit(
+            actor,
+            "attach_case_session",
+            Some(&format!("{}/{}", case_id, session_id)),
+        )
+        .await?;
+
+        Ok(case)
+    }
+
+    pub async fn detach_session_from_case(
+        &self,
+        case_id: &str,
+        session_id: &str,
+        actor: &Principal,
+    ) -> Result<Case> {
+        let mut case = self.case(case_id, actor).await?;
+
+        self.ensure_not_on_hold(session_id, "detaching it from a case")
+            .await?;
+        case.detach(session_id)?;
+        self.storage.put_record(&Case::key(case_id), &case).await?;
+        self.audit(
+            actor,
+            "detach_case_session",
+            Some(&format!("{}/{}", case_id, session_id)),
+        )
+        .await?;
+
+        Ok(case)
+    }
+
+    // One signed court report per attached session, bundled and signed again
+    // as a whole
+    pub async fn case_court_report(
+        &self,
+        case_id: &str,
+        actor: &Principal,
+    ) -> Result<SignedCaseReport> {
+        let case = self.case(case_id, actor).await?;
+
+        let mut devices = Vec::new();
+        let mut reports = Vec::with_capacity(case.sessions.len());
+        for session_id in &case.sessions {
+            if let Some(session) = self.sessions.session(session_id).await? {
+                if !devices.contains(&session.device_id) {
+                    devices.push(session.device_id);
+                }
+            }
+            let (report, proofs) = self.court_report_with_proofs(session_id).await?;
+            let engine = self.encryption_engine.lock().await;
+            reports.push(SignedReport::sign(report, proofs, &engine)?);
         }
+        devices.sort();
 
-        // Sort by sequence
-        frames.sort_by_key(|f| f.sequence);
+        let case_report = CaseReport {
+            case,
+            devices,
+            generated_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs(),
+            sessions: reports,
+        };
+        self.audit(actor, "case_court_report", Some(case_id))
+            .await?;
 
-        // Perform verification
-        self.verifier.verify_integrity(&frames).await
+        let engine = self.encryption_engine.lock().await;
+        SignedCaseReport::sign(case_report, &engine)
+    }
+
+    pub fn api_key_manager(&self) -> ApiKeyManager {
+        ApiKeyManager::new(self.storage.clone(), self.encryption_engine.clone())
+    }
+
+    pub fn playback_service(&self, config: PlaybackConfig) -> PlaybackService {
+        PlaybackService::new(config, self.encryption_engine.clone(), self.storage.clone())
+            .with_audit_log(self.audit_log.clone())
     }
 
     pub async fn generate_court_report(&self, evidence_id: &str) -> Result<crate::CourtReport> {
-        // In a real implementation, would retrieve all frames for the evidence
-        let mock_frames = Vec::new(); // Would be populated from storage
-        self.verifier
-            .generate_court_report(evidence_id.to_string(), &mock_frames)
+        Ok(self.court_report_with_proofs(evidence_id).await?.0)
+    }
+
+    // Sealed sessions are reported over their stored frames; anything else
+    // gets an empty report as before.
+    async fn court_report_with_proofs(
+        &self,
+        evidence_id: &str,
+    ) -> Result<(crate::CourtReport, Vec<AnchorProof>)> {
+        let (manifest, frames) = match self.session_frames(evidence_id).await {
+            Ok((manifest, frames)) => (Some(manifest), frames),
+            Err(_) => (None, Vec::new()),
+        };
+
+        let mut report = self
+            .verifier
+            .generate_court_report(evidence_id.to_string(), &frames)?;
+
+        // Derived copies stay attributable to the sealed original
+        report.derived_renditions = self.renditions(evidence_id).await?;
+        report.time_sync = self
+            .storage
+            .get_record(&SessionTimeSync::key(evidence_id))
+            .await?;
+        report.approvals = self.approval_requests(evidence_id).await?;
+
+        let proofs = AnchorProof::collect(manifest.as_ref(), &frames);
+        Ok((report, proofs))
+    }
+
+    // The signed report rendered for download, with its anchor proofs attached
+    pub async fn court_report_document(
+        &self,
+        evidence_id: &str,
+        format: ReportFormat,
+    ) -> Result<ReportDocument> {
+        let (report, proofs) = self.court_report_with_proofs(evidence_id).await?;
+
+        let engine = self.encryption_engine.lock().await;
+        let signed = SignedReport::sign(report, proofs, &engine)?;
+        ReportDocument::render(&signed, format, &engine)
     }
 }
 
@@ -285,26 +3217,86 @@ impl Clone for RealTimeEncryptionNode {
             blockchain_anchor: self.blockchain_anchor.clone(),
             storage: self.storage.clone(),
             verifier: self.verifier.clone(),
-            frame_buffer: self.frame_buffer.clone(),
+            chain_tips: self.chain_tips.clone(),
+            bundle_digests: self.bundle_digests.clone(),
+            watermarker: self.watermarker.clone(),
+            telemetry: self.telemetry.clone(),
+            time_sync: self.time_sync.clone(),
+            validator: self.validator.clone(),
+            locations: self.locations.clone(),
+            pipeline: self.pipeline.clone(),
+            sessions: self.sessions.clone(),
+            sealed_tx: self.sealed_tx.clone(),
+            sequences: self.sequences.clone(),
+            device_registry: self.device_registry.clone(),
+            enrollment: self.enrollment.clone(),
+            edge: self.edge.clone(),
+            approvals: self.approvals.clone(),
+            approval_lock: self.approval_lock.clone(),
+            stats: self.stats.clone(),
+            anchor_flush: self.anchor_flush.clone(),
+            events: self.events.clone(),
+            shutdown_tx: self.shutdown_tx.clone(),
+            pipeline_tasks: self.pipeline_tasks.clone(),
+            traces: self.traces.clone(),
+            verification_jobs: self.verification_jobs.clone(),
+            audit_log: self.audit_log.clone(),
+            crash_state: self.crash_state.clone(),
         }
     }
 }
 
+// The metadata `frame` was sealed with, which its anchors carry
+async fn sealed_metadata(
+    storage: &DistributedStorage,
+    frame: &EncryptedFrame,
+) -> Result<FrameMetadata> {
+    let key = MetadataRecord::key(&frame.device_id, frame.sequence);
+    match storage.get_record::<MetadataRecord>(&key).await? {
+        Some(record) if record.links_to(frame) => Ok(record.metadata),
+        Some(_) => Err(ImmutableEncryptionError::EvidenceTampered {
+            details: format!(
+                "metadata record of frame {} of {} does not match its chain hash",
+                frame.sequence, frame.device_id
+            ),
+        }),
+        None => Err(ImmutableEncryptionError::NotFound(format!(
+            "No metadata record for frame {} of {}",
+            frame.sequence, frame.device_id
+        ))),
+    }
+}
+
+// Devices that seal their own frames keep the metadata out of the segment,
+// so their anchors name the device and nothing else
+fn device_sealed_metadata(device_id: &str) -> FrameMetadata {
+    FrameMetadata {
+        device_id: device_id.to_string(),
+        location: None,
+        resolution: (0, 0),
+        fps: 0,
+        codec: String::new(),
+        telemetry: None,
+    }
+}
+
+fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ffi::DeviceSealer;
     use tempfile::TempDir;
 
     #[tokio::test]
     async fn test_node_initialization() -> Result<()> {
         let temp_dir = TempDir::new()?;
 
-        let crypto_config = CryptoConfig {
-            primary_key: vec![0u8; 32],
-            key_rotation_interval: 60,
-            quantum_resistant: false,
-            hardware_backed: false,
-        };
+        let crypto_config = CryptoConfig::software(vec![0u8; 32], 60);
 
         let blockchain_config = BlockchainConfig {
             ethereum_rpc_url: "https://mainnet.infura.io/v3/test".to_string(),
@@ -320,6 +3312,8 @@ mod tests {
             backup_enabled: false,
             backup_path: "".to_string(),
             compression_enabled: false,
+            frame_encoding: Default::default(),
+            backup_io_uring: false,
         };
 
         let verification_config = VerificationConfig {
@@ -327,6 +3321,8 @@ mod tests {
             quantum_verification: false,
             hardware_attestation: false,
             min_confirmations: HashMap::new(),
+            attestation_key: None,
+            device_keys: HashMap::new(),
         };
 
         let node = RealTimeEncryptionNode::new(
@@ -341,4 +3337,587 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_chain_tips_follow_each_device() {
+        let mut chain_tips = ChainTips::new();
+        assert_eq!(chain_tips.tip_hash("test-camera"), "0".repeat(64));
+        chain_tips.resume("other-camera", "f".repeat(64));
+
+        for sequence in 1..=3u64 {
+            chain_tips.push(&EncryptedFrame {
+                sequence,
+                device_id: "test-camera".to_string(),
+                ciphertext: vec![1, 2, 3],
+                hash: format!("{:064}", sequence),
+                previous_hash: format!("{:064}", sequence - 1),
+                nonce: vec![0; 12],
+                timestamp: 1000 + sequence,
+                blockchain_anchors: vec![],
+                cipher: Default::default(),
+                compressed: false,
+                attestation: None,
+                device_signature: None,
+            });
+        }
+
+        assert_eq!(chain_tips.tip_hash("test-camera"), format!("{:064}", 3));
+
+        // Another device's chain carries on from its own tip
+        assert_eq!(chain_tips.tip_hash("other-camera"), "f".repeat(64));
+        assert_eq!(chain_tips.tips().len(), 2);
+    }
+
+    async fn test_node(temp_dir: &TempDir) -> Result<RealTimeEncryptionNode> {
+        RealTimeEncryptionNode::new(
+            CryptoConfig::software(vec![0u8; 32], 1),
+            BlockchainConfig {
+                ethereum_rpc_url: "http://localhost:8545".to_string(),
+                bitcoin_rpc_url: "http://localhost:18443".to_string(),
+                private_chain_rpc: "http://localhost:8545".to_string(),
+                opentimestamps_url: "http://localhost:14788".to_string(),
+            },
+            StorageConfig {
+                database_path: temp_dir.path().to_string_lossy().to_string(),
+                ipfs_enabled: false,
+                ipfs_api_url: "".to_string(),
+                backup_enabled: false,
+                backup_path: "".to_string(),
+                compression_enabled: false,
+                frame_encoding: Default::default(),
+                backup_io_uring: false,
+            },
+            VerificationConfig {
+                strict_mode: true,
+                quantum_verification: false,
+                hardware_attestation: false,
+                min_confirmations: HashMap::new(),
+                attestation_key: None,
+                device_keys: HashMap::new(),
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_intake() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?;
+
+        let (sender, _verified) = node.start_processing().await?;
+        node.shutdown(Duration::from_secs(5)).await?;
+
+        // The pipeline has stopped, so new frames are refused
+        assert!(sender.is_closed());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_edge_sync_keeps_devices_apart() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let field = EncryptionEngine::new(CryptoConfig::software(vec![5u8; 32], 1))?;
+        let edge = EdgeSync::new(&edge::EdgeConfig {
+            trusted_nodes: HashMap::from([(
+                "field-1".to_string(),
+                hex::encode(field.envelope_public_key()?),
+            )]),
+            ..Default::default()
+        })?;
+        let node = test_node(&temp_dir).await?.with_edge(edge);
+
+        // Two cameras whose frames share sequence numbers and seal times
+        let batch = |device_id: &str, seed: u8| {
+            let mut previous_hash = "0".repeat(64);
+            let frames = (1..=2u64)
+                .map(|sequence| {
+                    let frame = EncryptedFrame {
+                        sequence,
+                        device_id: device_id.to_string(),
+                        ciphertext: vec![seed; 16],
+                        hash: hex::encode([seed + sequence as u8; 32]),
+                        previous_hash: previous_hash.clone(),
+                        nonce: vec![0; 12],
+                        timestamp: 1_700_000_000 + sequence,
+                        blockchain_anchors: Vec::new(),
+                        cipher: Default::default(),
+                        compressed: false,
+                        attestation: None,
+                        device_signature: None,
+                    };
+                    previous_hash = frame.hash.clone();
+                    frame
+                })
+                .collect();
+            SyncBatch {
+                node_id: "field-1".to_string(),
+                device_id: device_id.to_string(),
+                frames,
+                anchors: Vec::new(),
+                custody: Vec::new(),
+                sent_at: 1_700_000_100,
+                signature: String::new(),
+                custody_through: None,
+            }
+            .sign(&field)
+        };
+
+        let actor = Principal::anonymous();
+        let first = node.accept_edge_sync(batch("cam_a", 10)?, &actor).await?;
+        let second = node.accept_edge_sync(batch("cam_b", 20)?, &actor).await?;
+        assert_eq!((first.stored, second.stored), (2, 2));
+
+        // A resend is checked against the device's own stored frames
+        let resent = node.accept_edge_sync(batch("cam_a", 10)?, &actor).await?;
+        assert_eq!((resent.stored, resent.resent), (0, 2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_device_sealed_segments_are_checked_and_stored() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?;
+        let actor = Principal::anonymous();
+
+        // Provisioned with the node's key and key rotation interval
+        let mut sealer = DeviceSealer::new(&[0u8; 32], "cam_9", Default::default(), 1)?;
+        let seal = |sealer: &mut DeviceSealer, timestamp: u64| {
+            let metadata = FrameMetadata {
+                device_id: "cam_9".to_string(),
+                location: None,
+                resolution: (640, 480),
+                fps: 30,
+                codec: "h264".to_string(),
+                telemetry: None,
+            };
+            sealer.seal(vec![3u8; 16].into(), timestamp, 0, metadata)
+        };
+        let frames = vec![seal(&mut sealer, 100)?, seal(&mut sealer, 101)?];
+        let proof = sealer.export_proof()?.unwrap();
+
+        let receipt = node
+            .accept_device_segment(proof.clone(), frames.clone(), &actor)
+            .await?;
+        assert_eq!((receipt.stored, receipt.tip_sequence), (2, Some(2)));
+        let stored = node.storage.retrieve_frame_at("cam_9", 2, 101).await?;
+        assert_eq!(
+            node.encryption_engine
+                .lock()
+                .await
+                .decrypt_frame_data(&stored)?,
+            vec![3u8; 16]
+        );
+
+        // A resend is accepted as such; an altered frame is not
+        let resent = node.accept_device_segment(proof, frames, &actor).await?;
+        assert_eq!((resent.stored, resent.resent), (0, 2));
+        let next = vec![seal(&mut sealer, 102)?];
+        let next_proof = sealer.export_proof()?.unwrap();
+        let mut altered = next.clone();
+        altered[0].ciphertext[0] ^= 1;
+        assert!(node
+            .accept_device_segment(next_proof.clone(), altered, &actor)
+            .await
+            .is_err());
+        node.accept_device_segment(next_proof, next, &actor).await?;
+
+        // Sealed under another key, the segment is refused
+        let mut stranger = DeviceSealer::new(&[1u8; 32], "cam_8", Default::default(), 1)?;
+        let frames = vec![seal(&mut stranger, 100)?];
+        let proof = stranger.export_proof()?.unwrap();
+        assert!(node
+            .accept_device_segment(proof, frames, &actor)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    // A phone's queued frames and proof, uploaded as the app sends them
+    #[cfg(feature = "mobile")]
+    #[tokio::test]
+    async fn test_mobile_segment_upload_end_to_end() -> Result<()> {
+        use crate::ffi::DeviceSegment;
+        use crate::mobile::{segment_upload_json, CaptureFrame, MobileCipher, MobileSealer};
+
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?;
+        let sealer = MobileSealer::new(
+            vec![0u8; 32],
+            "phone_1".to_string(),
+            MobileCipher::ChaCha20Poly1305,
+            1,
+        )
+        .unwrap();
+        let outbox: Vec<_> = (100..103u64)
+            .map(|timestamp| {
+                sealer
+                    .seal_frame(CaptureFrame {
+                        data: vec![timestamp as u8; 32],
+                        timestamp,
+                        sequence: 0,
+                        width: 1080,
+                        height: 1920,
+                        fps: 30,
+                        codec: "MJPEG".to_string(),
+                        latitude: Some(51.5),
+                        longitude: Some(-0.1),
+                    })
+                    .unwrap()
+            })
+            .collect();
+        let proof = sealer.export_proof().unwrap().unwrap();
+        let body =
+            segment_upload_json(proof, outbox.iter().map(|s| s.json.clone()).collect()).unwrap();
+
+        // As `POST /devices/segments` parses and hands it over
+        let segment: DeviceSegment = serde_json::from_str(&body)?;
+        let receipt = node
+            .accept_device_segment(segment.proof, segment.frames, &Principal::anonymous())
+            .await?;
+        assert_eq!((receipt.stored, receipt.tip_sequence), (3, Some(3)));
+
+        let last = &outbox[2];
+        let stored = node.storage.retrieve_frame_at("phone_1", 3, 102).await?;
+        assert_eq!(storage::frame_key("phone_1", 3, 102), last.frame_id);
+        assert_eq!(stored.hash, last.hash);
+        let payload = node
+            .encryption_engine
+            .lock()
+            .await
+            .decrypt_frame_data(&stored)?;
+        assert_eq!(payload, vec![102u8; 32]);
+        let custody = node.storage.custody_entries("phone_1").await?;
+        assert!(custody
+            .iter()
+            .any(|entry| entry.action.starts_with("device_segment:1-3:")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sealed_metadata_is_kept_with_the_frame() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?;
+
+        let telemetry = crate::Telemetry {
+            source: "mavlink".to_string(),
+            sampled_at_ms: 1_700_000_001_000,
+            altitude_m: Some(120.0),
+            ..Default::default()
+        };
+        let frame = VideoFrame {
+            timestamp: 1_700_000_001,
+            sequence: 1,
+            data: vec![7u8; 16].into(),
+            metadata: FrameMetadata {
+                device_id: "drone_1".to_string(),
+                location: Some((51.5, -0.1)),
+                resolution: (640, 480),
+                fps: 30,
+                codec: "h264".to_string(),
+                telemetry: Some(telemetry.clone()),
+            },
+            signature: None,
+        };
+        let sealed = node.process_frame(frame).await?;
+        let frame_id = node.storage.store_with_redundancy(&sealed).await?.remove(0);
+
+        let record = node.frame_metadata(&frame_id).await?;
+        assert_eq!(record.metadata.telemetry, Some(telemetry));
+        let payload = node
+            .encryption_engine
+            .lock()
+            .await
+            .decrypt_frame_data(&sealed)?;
+        assert!(record.matches(&sealed, payload.into())?);
+
+        // A record that no longer matches the chain hash is refused
+        let tampered = MetadataRecord {
+            frame_hash: "0".repeat(64),
+            ..record
+        };
+        node.storage
+            .put_record(&MetadataRecord::key("drone_1", 1), &tampered)
+            .await?;
+        assert!(node.frame_metadata(&frame_id).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watermarked_frames_hash_what_is_sealed() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?.with_watermark(WatermarkConfig {
+            enabled: true,
+            ..WatermarkConfig::default()
+        });
+
+        let frame = VideoFrame {
+            timestamp: 1_700_000_001,
+            sequence: 1,
+            data: vec![128u8; 320 * 240 * 3].into(),
+            metadata: FrameMetadata {
+                device_id: "cam_1".to_string(),
+                location: None,
+                resolution: (320, 240),
+                fps: 30,
+                codec: "rgb24".to_string(),
+                telemetry: None,
+            },
+            signature: None,
+        };
+
+        // Codecs the watermarker can't mark are refused up front
+        let mut h264 = frame.clone();
+        h264.metadata.codec = "h264".to_string();
+        assert!(node.validate_frame(&mut h264).is_err());
+
+        let original_hash = {
+            let mut captured = frame.clone();
+            node.validate_frame(&mut captured)?;
+            node.encryption_engine
+                .lock()
+                .await
+                .generate_frame_hash(&captured)?
+        };
+        let sealed = node.process_frame(frame).await?;
+        let frame_id = node.storage.store_with_redundancy(&sealed).await?.remove(0);
+
+        // The chain covers the marked payload; the original's hash is kept too
+        let record = node.frame_metadata(&frame_id).await?;
+        let payload = node
+            .encryption_engine
+            .lock()
+            .await
+            .decrypt_frame_data(&sealed)?;
+        assert!(record.matches(&sealed, payload.into())?);
+        assert_eq!(record.original_hash, Some(original_hash));
+        assert_ne!(record.original_hash.as_ref(), Some(&record.frame_hash));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mjpeg_playback_skips_frames_it_cannot_show() -> Result<()> {
+        use crate::playback::{PlaybackConfig, PlaybackRequest};
+        use futures::StreamExt;
+
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?;
+
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0xFF, 0xD9];
+        let payloads = [jpeg.clone(), vec![128u8; 12], jpeg.clone(), jpeg];
+        for (sequence, data) in payloads.into_iter().enumerate() {
+            let frame = VideoFrame {
+                timestamp: 1_700_000_000 + sequence as u64,
+                sequence: sequence as u64 + 1,
+                data: data.into(),
+                metadata: FrameMetadata {
+                    device_id: "cam_1".to_string(),
+                    location: None,
+                    resolution: (2, 2),
+                    fps: 30,
+                    codec: "mjpeg".to_string(),
+                    telemetry: None,
+                },
+                signature: None,
+            };
+            let mut sealed = node.process_frame(frame).await?;
+            if sequence == 2 {
+                sealed.ciphertext[0] ^= 1; // Fails to decrypt
+            }
+            node.storage.store_with_redundancy(&sealed).await?;
+        }
+
+        let service = node.playback_service(PlaybackConfig {
+            enabled: true,
+            ..PlaybackConfig::default()
+        });
+        let request = PlaybackRequest {
+            device_id: "cam_1".to_string(),
+            from: 1_700_000_000,
+            to: Some(1_700_000_010),
+        };
+        let session = service.open_for("det. smith".to_string(), request).await?;
+        let parts: Vec<Vec<u8>> = service
+            .mjpeg_stream(session)
+            .map(|part| part.unwrap())
+            .collect()
+            .await;
+
+        // Raw and undecryptable frames are reported once, in order, and skipped
+        let content_types: Vec<bool> = parts
+            .iter()
+            .map(|part| String::from_utf8_lossy(part).contains("Content-Type: image/jpeg"))
+            .collect();
+        assert_eq!(content_types, vec![true, false, false, true]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_range_follows_each_device_chain() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?;
+
+        // Two cameras sealing interleaved frames on one node
+        for sequence in 1..=3u64 {
+            for device_id in ["cam_a", "cam_b"] {
+                let frame = VideoFrame {
+                    timestamp: 1_700_000_000 + sequence,
+                    sequence,
+                    data: vec![sequence as u8; 16].into(),
+                    metadata: FrameMetadata {
+                        device_id: device_id.to_string(),
+                        location: None,
+                        resolution: (640, 480),
+                        fps: 30,
+                        codec: "h264".to_string(),
+                        telemetry: None,
+                    },
+                    signature: None,
+                };
+                let sealed = node.process_frame(frame).await?;
+                node.storage.store_with_redundancy(&sealed).await?;
+            }
+        }
+
+        for device_id in ["cam_a", "cam_b"] {
+            let report = node
+                .verify_range(device_id, 1_700_000_000, 1_700_000_010)
+                .await?;
+            assert!(report.is_valid, "{:?}", report.segments);
+            assert_eq!(report.frame_count, 3);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_playback_needs_an_approved_session_under_a_decrypt_policy() -> Result<()> {
+        use crate::approval::ApprovalPolicy;
+        use crate::auth::Role;
+        use ring::signature::{Ed25519KeyPair, KeyPair};
+        use sha2::{Digest, Sha256};
+
+        let key_of = |person: &str| {
+            Ed25519KeyPair::from_seed_unchecked(&Sha256::digest(person.as_bytes())).unwrap()
+        };
+        let principal = |subject: &str, role: Role| Principal {
+            subject: subject.to_string(),
+            roles: vec![role],
+            tenant: "default".to_string(),
+            source: None,
+            issued_by: None,
+        };
+
+        let temp_dir = TempDir::new()?;
+        let node = test_node(&temp_dir).await?.with_approvals(ApprovalConfig {
+            decrypt: Some(ApprovalPolicy {
+                required: 1,
+                roles: vec![Role::Auditor],
+            }),
+            approvers: ["auditor"]
+                .iter()
+                .map(|p| (p.to_string(), hex::encode(key_of(p).public_key().as_ref())))
+                .collect(),
+            ..Default::default()
+        });
+
+        let session = node.start_session("cam_a", None).await?;
+        for sequence in 1..=3u64 {
+            let frame = VideoFrame {
+                timestamp: 1_700_000_000 + sequence,
+                sequence,
+                data: vec![sequence as u8; 16].into(),
+                metadata: FrameMetadata {
+                    device_id: "cam_a".to_string(),
+                    location: None,
+                    resolution: (640, 480),
+                    fps: 30,
+                    codec: "h264".to_string(),
+                    telemetry: None,
+                },
+                signature: None,
+            };
+            let sealed = node.process_frame(frame).await?;
+            node.storage.store_with_redundancy(&sealed).await?;
+        }
+        node.stop_session(&session.session_id).await?;
+
+        let investigator = principal("investigator", Role::Prosecutor);
+        let (from, to) = (1_700_000_001, Some(1_700_000_003));
+        assert!(node
+            .require_decrypt_approval("cam_a", from, to, None, &investigator)
+            .await
+            .is_err());
+
+        let action = ApprovalAction::Decrypt {
+            evidence_id: session.session_id.clone(),
+        };
+        let request = node
+            .request_approval(action, "case 42".to_string(), &investigator)
+            .await?;
+        let auditor = principal("auditor", Role::Auditor);
+        let statement = node
+            .approval_statement(&request.request_id, &auditor)
+            .await?;
+        let signature = key_of("auditor").sign(&statement);
+        node.approve_request(&request.request_id, &auditor, signature.as_ref())
+            .await?;
+
+        // The session doesn't cover another device or a longer range
+        let id = Some(request.request_id.as_str());
+        for (device_id, to) in [
+            ("cam_b", to),
+            ("cam_a", Some(1_700_000_009)),
+            ("cam_a", None),
+        ] {
+            assert!(node
+                .require_decrypt_approval(device_id, from, to, id, &investigator)
+                .await
+                .is_err());
+        }
+
+        // It covers the session's own range once
+        assert!(node
+            .require_decrypt_approval("cam_a", from, to, id, &investigator)
+            .await?
+            .is_some());
+        assert!(node
+            .require_decrypt_approval("cam_a", from, to, id, &investigator)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_bounded_limits_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let results = run_bounded((0..20u64).collect(), 4, Module::Storage, |n| {
+            let active = active.clone();
+            let peak = peak.clone();
+            async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                Ok(n * 2)
+            }
+        })
+        .await;
+
+        assert!(peak.load(Ordering::SeqCst) <= 4);
+        assert_eq!(results.len(), 20);
+        assert!(results
+            .iter()
+            .all(|(i, r)| *r.as_ref().unwrap() == *i as u64 * 2));
+    }
 }
@@ -0,0 +1,354 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::VideoFrame;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatermarkConfig {
+    pub enabled: bool,
+    pub hash_prefix_len: usize,
+    pub scale: u32,
+    pub margin: u32,
+    pub position: WatermarkPosition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hash_prefix_len: 12,
+            scale: 2,
+            margin: 8,
+            position: WatermarkPosition::BottomLeft,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PixelFormat {
+    Rgb24,
+    Gray8,
+}
+
+#[derive(Debug, Clone)]
+pub struct Watermarker {
+    config: WatermarkConfig,
+}
+
+impl Watermarker {
+    pub fn new(config: WatermarkConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    // Whether `apply` can mark frames of this codec
+    pub fn supports(codec: &str) -> bool {
+        match codec.to_ascii_uppercase().as_str() {
+            "RGB24" | "RAW" | "GRAY8" | "Y8" => true,
+            "MJPEG" | "JPEG" => cfg!(feature = "video"),
+            _ => false,
+        }
+    }
+
+    // Turns away frames `apply` can't mark at ingest, rather than failing
+    // them once they reach the pipeline
+    pub fn check(&self, frame: &VideoFrame) -> Result<()> {
+        if self.config.enabled && !Self::supports(&frame.metadata.codec) {
+            return Err(unsupported(&frame.metadata.codec));
+        }
+        Ok(())
+    }
+
+    pub fn watermark_text(&self, frame: &VideoFrame, frame_hash: &str) -> String {
+        let prefix_len = self.config.hash_prefix_len.min(frame_hash.len());
+        format!(
+            "{} {} {}",
+            &frame_hash[..prefix_len],
+            frame.timestamp,
+            frame.metadata.device_id
+        )
+    }
+
+    // Burns the hash prefix, timestamp and device ID into the frame pixels.
+    // `frame_hash` is the content hash of the unmarked original; the pipeline
+    // seals the marked frame and keeps both hashes with its metadata.
+    pub fn apply(&self, frame: &mut VideoFrame, frame_hash: &str) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let text = self.watermark_text(frame, frame_hash);
+        let (width, height) = frame.metadata.resolution;
+
+        match frame.metadata.codec.to_ascii_uppercase().as_str() {
+            "RGB24" | "RAW" => {
+                self.render_payload(&mut frame.data, width, height, PixelFormat::Rgb24, &text)
+            }
+            "GRAY8" | "Y8" => {
+                self.render_payload(&mut frame.data, width, height, PixelFormat::Gray8, &text)
+            }
+            #[cfg(feature = "video")]
+            "MJPEG" | "JPEG" => self.apply_jpeg(frame, &text),
+            codec => Err(unsupported(codec)),
+        }
+    }
+
+    #[cfg(feature = "video")]
+    fn apply_jpeg(&self, frame: &mut VideoFrame, text: &str) -> Result<()> {
+        let decoded = image::load_from_memory_with_format(&frame.data, image::ImageFormat::Jpeg)?;
+        let mut rgb = decoded.to_rgb8();
+        let (width, height) = rgb.dimensions();
+
+        self.render(rgb.as_mut(), width, height, PixelFormat::Rgb24, text)?;
+
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(rgb).write_to(&mut encoded, image::ImageFormat::Jpeg)?;
+        frame.data = encoded.into_inner().into();
+
+        Ok(())
+    }
+
+    // Draws into the payload's own buffer when nothing else holds it, and
+    // into a copy otherwise so other holders never see the change
+    fn render_payload(
+        &self,
+        data: &mut Bytes,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        text: &str,
+    ) -> Result<()> {
+        let mut pixels = Vec::from(std::mem::take(data));
+        let result = self.render(&mut pixels, width, height, format, text);
+        *data = pixels.into();
+        result
+    }
+
+    fn render(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        text: &str,
+    ) -> Result<()> {
+        let channels = match format {
+            PixelFormat::Rgb24 => 3,
+            PixelFormat::Gray8 => 1,
+        };
+
+        let expected = width as usize * height as usize * channels;
+        if pixels.len() != expected {
+            return Err(ImmutableEncryptionError::Video(format!(
+                "Frame buffer is {} bytes, expected {} for {}x{}",
+                pixels.len(),
+                expected,
+                width,
+                height
+            )));
+        }
+
+        let scale = self.config.scale.max(1);
+        let advance = (GLYPH_WIDTH + 1) * scale;
+        let box_width = (text.chars().count() as u32 * advance + scale).min(width);
+        let box_height = ((GLYPH_HEIGHT + 2) * scale).min(height);
+
+        let margin = self.config.margin;
+        let origin_x = match self.config.position {
+            WatermarkPosition::TopLeft | WatermarkPosition::BottomLeft => margin,
+            _ => width.saturating_sub(box_width + margin),
+        };
+        let origin_y = match self.config.position {
+            WatermarkPosition::TopLeft | WatermarkPosition::TopRight => margin,
+            _ => height.saturating_sub(box_height + margin),
+        };
+
+        let mut put = |x: u32, y: u32, value: u8| {
+            if x < width && y < height {
+                let offset = (y as usize * width as usize + x as usize) * channels;
+                pixels[offset..offset + channels].fill(value);
+            }
+        };
+
+        // Dark backing box keeps the text legible on any footage
+        for y in origin_y..origin_y + box_height {
+            for x in origin_x..origin_x + box_width {
+                put(x, y, 0);
+            }
+        }
+
+        for (index, ch) in text.chars().enumerate() {
+            let glyph = glyph(ch);
+            let glyph_x = origin_x + scale + index as u32 * advance;
+            let glyph_y = origin_y + scale;
+
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                        continue;
+                    }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            put(
+                                glyph_x + col * scale + dx,
+                                glyph_y + row as u32 * scale + dy,
+                                255,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn unsupported(codec: &str) -> ImmutableEncryptionError {
+    ImmutableEncryptionError::Video(format!("Watermarking is not supported for codec {}", codec))
+}
+
+// 5x7 bitmap font, one byte per row with the glyph in the low five bits
+fn glyph(ch: char) -> [u8; 7] {
+    match ch.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        ' ' => [0x00; 7],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // '?'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FrameMetadata;
+
+    fn raw_frame(width: u32, height: u32) -> VideoFrame {
+        VideoFrame {
+            timestamp: 1640995200,
+            sequence: 1,
+            data: vec![128u8; (width * height * 3) as usize].into(),
+            metadata: FrameMetadata {
+                device_id: "cam_01".to_string(),
+                location: None,
+                resolution: (width, height),
+                fps: 30,
+                codec: "RGB24".to_string(),
+                telemetry: None,
+            },
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_watermark_burns_text_into_pixels() -> Result<()> {
+        let watermarker = Watermarker::new(WatermarkConfig {
+            enabled: true,
+            ..WatermarkConfig::default()
+        });
+
+        let mut frame = raw_frame(320, 240);
+        let original = frame.data.clone();
+        let hash = "ab".repeat(32);
+
+        assert_eq!(
+            watermarker.watermark_text(&frame, &hash),
+            "abababababab 1640995200 cam_01"
+        );
+
+        watermarker.apply(&mut frame, &hash)?;
+
+        assert_eq!(frame.data.len(), original.len());
+        assert_ne!(frame.data, original);
+        assert!(frame.data.contains(&255));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_watermark_rejects_mismatched_buffer() {
+        let watermarker = Watermarker::new(WatermarkConfig {
+            enabled: true,
+            ..WatermarkConfig::default()
+        });
+
+        let mut frame = raw_frame(320, 240);
+        frame.data.truncate(100);
+
+        assert!(watermarker.apply(&mut frame, &"0".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_watermark_check_refuses_unmarkable_codecs() {
+        let watermarker = Watermarker::new(WatermarkConfig {
+            enabled: true,
+            ..WatermarkConfig::default()
+        });
+
+        let mut frame = raw_frame(320, 240);
+        assert!(watermarker.check(&frame).is_ok());
+
+        frame.metadata.codec = "H.264".to_string();
+        assert!(!Watermarker::supports("H.264"));
+        assert!(watermarker.check(&frame).is_err());
+
+        // Nothing to refuse while watermarking is off
+        assert!(Watermarker::new(WatermarkConfig::default())
+            .check(&frame)
+            .is_ok());
+    }
+}
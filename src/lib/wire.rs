@@ -0,0 +1,688 @@
+use immutable_encryption_core::chain::ChainLink;
+use immutable_encryption_core::types::ArchivedEncryptedFrame;
+use prost::Message;
+use rkyv::rancor;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use crate::crypto::Cipher;
+use crate::error::{ImmutableEncryptionError, Result};
+use crate::migration::{self, RecordKind};
+use crate::{
+    BlockchainAnchor, CourtReport, CustodyEntry, DeviceSignature, EncryptedFrame, FrameMetadata,
+    HardwareAttestation, LegalCompliance, Telemetry, VideoFrame,
+};
+
+pub mod proto {
+    tonic::include_proto!("immutable_encryption.v1");
+}
+
+// The formats.proto version this build writes, and the newest it reads
+pub const FORMAT_VERSION: u32 = 1;
+
+// How sealed frames are encoded at rest. Reads accept either, so switching
+// only changes how new frames are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameEncoding {
+    #[default]
+    Json,
+    Protobuf, // smaller, and readable from any language with the schema
+    Rkyv,     // checked in place, so chain verification skips decoding
+}
+
+// Starts an rkyv frame. No JSON value starts with 0x00, and protobuf has no
+// field 0, so it can't be mistaken for either.
+const RKYV_MAGIC: &[u8] = b"\0rkyv";
+
+// Unset (0) is a sender from before versioning, which wrote version 1
+pub fn check_version(message: &str, version: u32) -> Result<()> {
+    if version > FORMAT_VERSION {
+        return Err(ImmutableEncryptionError::InvalidRequest(format!(
+            "{} has format version {}; this node reads up to {}",
+            message, version, FORMAT_VERSION
+        )));
+    }
+    Ok(())
+}
+
+pub fn encode_frame(frame: &EncryptedFrame, encoding: FrameEncoding) -> Result<Vec<u8>> {
+    match encoding {
+        FrameEncoding::Json => Ok(serde_json::to_vec(frame)?),
+        FrameEncoding::Protobuf => Ok(proto::EncryptedFrame::from(frame.clone()).encode_to_vec()),
+        FrameEncoding::Rkyv => {
+            let archive = rkyv::to_bytes::<rancor::Error>(frame)
+                .map_err(|e| ImmutableEncryptionError::storage(&e.to_string()))?;
+            Ok([RKYV_MAGIC, &archive].concat())
+        }
+    }
+}
+
+// A JSON frame starts with `{`, which as a protobuf tag would open a group on
+// field 15; prost never writes groups, so the first byte tells them apart
+pub fn decode_frame(data: &[u8]) -> Result<EncryptedFrame> {
+    if data.first() == Some(&b'{') {
+        return Ok(serde_json::from_slice(data)?);
+    }
+    if let Some(archive) = data.strip_prefix(RKYV_MAGIC) {
+        return rkyv::from_bytes::<EncryptedFrame, rancor::Error>(archive)
+            .map_err(|e| ImmutableEncryptionError::storage(&format!("Malformed frame: {}", e)));
+    }
+    proto::EncryptedFrame::decode(data)
+        .map_err(|e| ImmutableEncryptionError::storage(&format!("Malformed frame: {}", e)))?
+        .try_into()
+}
+
+// rkyv frames as archived by earlier format versions, the first before frames
+// carried an attestation. Archives have a fixed layout, so these are rewritten
+// rather than read as the current one.
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+struct EncryptedFrameV1 {
+    sequence: u64,
+    device_id: String,
+    ciphertext: Vec<u8>,
+    hash: String,
+    previous_hash: String,
+    nonce: Vec<u8>,
+    timestamp: u64,
+    blockchain_anchors: Vec<BlockchainAnchor>,
+    cipher: Cipher,
+    compressed: bool,
+}
+
+// Before frames carried the device's signature
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+struct EncryptedFrameV2 {
+    sequence: u64,
+    device_id: String,
+    ciphertext: Vec<u8>,
+    hash: String,
+    previous_hash: String,
+    nonce: Vec<u8>,
+    timestamp: u64,
+    blockchain_anchors: Vec<BlockchainAnchor>,
+    cipher: Cipher,
+    compressed: bool,
+    attestation: Option<HardwareAttestation>,
+}
+
+// Migration steps for frame records from format versions 1 and 2; JSON and
+// protobuf frames read new fields as unset and are left alone
+pub fn upgrade_v1_frame(payload: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(archive) = payload.strip_prefix(RKYV_MAGIC) else {
+        return Ok(payload);
+    };
+    let frame = rkyv::from_bytes::<EncryptedFrameV1, rancor::Error>(archive)
+        .map_err(|e| ImmutableEncryptionError::storage(&format!("Malformed frame: {}", e)))?;
+    let archive = rkyv::to_bytes::<rancor::Error>(&EncryptedFrameV2 {
+        sequence: frame.sequence,
+        device_id: frame.device_id,
+        ciphertext: frame.ciphertext,
+        hash: frame.hash,
+        previous_hash: frame.previous_hash,
+        nonce: frame.nonce,
+        timestamp: frame.timestamp,
+        blockchain_anchors: frame.blockchain_anchors,
+        cipher: frame.cipher,
+        compressed: frame.compressed,
+        attestation: None,
+    })
+    .map_err(|e| ImmutableEncryptionError::storage(&e.to_string()))?;
+    Ok([RKYV_MAGIC, &archive].concat())
+}
+
+pub fn upgrade_v2_frame(payload: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(archive) = payload.strip_prefix(RKYV_MAGIC) else {
+        return Ok(payload);
+    };
+    let frame = rkyv::from_bytes::<EncryptedFrameV2, rancor::Error>(archive)
+        .map_err(|e| ImmutableEncryptionError::storage(&format!("Malformed frame: {}", e)))?;
+    encode_frame(
+        &EncryptedFrame {
+            sequence: frame.sequence,
+            device_id: frame.device_id,
+            ciphertext: frame.ciphertext,
+            hash: frame.hash,
+            previous_hash: frame.previous_hash,
+            nonce: frame.nonce,
+            timestamp: frame.timestamp,
+            blockchain_anchors: frame.blockchain_anchors,
+            cipher: frame.cipher,
+            compressed: frame.compressed,
+            attestation: frame.attestation,
+            device_signature: None,
+        },
+        FrameEncoding::Rkyv,
+    )
+}
+
+// A stored frame read for chain checks. An rkyv frame stays in the bytes
+// RocksDB returned and is checked in place; JSON and protobuf frames have to
+// be decoded in full.
+#[derive(Debug)]
+pub enum StoredFrame {
+    Archived { value: Vec<u8>, at: usize }, // validated archive at `value[at..]`
+    Decoded(EncryptedFrame),
+}
+
+impl StoredFrame {
+    // `value` is a frame record as stored, tag and all
+    pub fn read(value: Vec<u8>) -> Result<Self> {
+        let at = match migration::upgrade(RecordKind::Frame, &value)? {
+            // An unchanged payload is a suffix of the value
+            Cow::Borrowed(payload) if payload.starts_with(RKYV_MAGIC) => {
+                value.len() - payload.len() + RKYV_MAGIC.len()
+            }
+            Cow::Borrowed(payload) => return Ok(Self::Decoded(decode_frame(payload)?)),
+            Cow::Owned(payload) => return Self::read_payload(payload),
+        };
+        Self::validate(&value[at..])?;
+        Ok(Self::Archived { value, at })
+    }
+
+    fn read_payload(payload: Vec<u8>) -> Result<Self> {
+        if !payload.starts_with(RKYV_MAGIC) {
+            return Ok(Self::Decoded(decode_frame(&payload)?));
+        }
+        Self::validate(&payload[RKYV_MAGIC.len()..])?;
+        Ok(Self::Archived {
+            value: payload,
+            at: RKYV_MAGIC.len(),
+        })
+    }
+
+    fn validate(archive: &[u8]) -> Result<()> {
+        rkyv::access::<ArchivedEncryptedFrame, rancor::Error>(archive)
+            .map(|_| ())
+            .map_err(|e| ImmutableEncryptionError::storage(&format!("Malformed frame: {}", e)))
+    }
+
+    fn archived(value: &[u8], at: usize) -> &ArchivedEncryptedFrame {
+        // SAFETY: `read` validated the archive and the bytes never change
+        unsafe { rkyv::access_unchecked::<ArchivedEncryptedFrame>(&value[at..]) }
+    }
+
+    pub fn link(&self) -> &dyn ChainLink {
+        match self {
+            Self::Archived { value, at } => Self::archived(value, *at),
+            Self::Decoded(frame) => frame,
+        }
+    }
+
+    // (chain, block number) of each anchor
+    pub fn anchor_blocks(&self) -> Vec<(&str, u64)> {
+        match self {
+            Self::Archived { value, at } => Self::archived(value, *at)
+                .blockchain_anchors
+                .iter()
+                .map(|a| (a.chain.as_str(), a.block_number.to_native()))
+                .collect(),
+            Self::Decoded(frame) => frame
+                .blockchain_anchors
+                .iter()
+                .map(|a| (a.chain.as_str(), a.block_number))
+                .collect(),
+        }
+    }
+}
+
+fn narrow(field: &str, value: Option<u32>) -> Result<Option<u8>> {
+    value
+        .map(|v| {
+            u8::try_from(v).map_err(|_| {
+                ImmutableEncryptionError::InvalidRequest(format!("{} {} is out of range", field, v))
+            })
+        })
+        .transpose()
+}
+
+impl From<Telemetry> for proto::Telemetry {
+    fn from(telemetry: Telemetry) -> Self {
+        Self {
+            source: telemetry.source,
+            sampled_at_ms: telemetry.sampled_at_ms,
+            altitude_m: telemetry.altitude_m,
+            speed_mps: telemetry.speed_mps,
+            heading_deg: telemetry.heading_deg,
+            roll_deg: telemetry.roll_deg,
+            pitch_deg: telemetry.pitch_deg,
+            yaw_deg: telemetry.yaw_deg,
+            fix_quality: telemetry.fix_quality.map(u32::from),
+            satellites: telemetry.satellites.map(u32::from),
+        }
+    }
+}
+
+impl TryFrom<proto::Telemetry> for Telemetry {
+    type Error = ImmutableEncryptionError;
+
+    fn try_from(telemetry: proto::Telemetry) -> Result<Self> {
+        Ok(Self {
+            source: telemetry.source,
+            sampled_at_ms: telemetry.sampled_at_ms,
+            altitude_m: telemetry.altitude_m,
+            speed_mps: telemetry.speed_mps,
+            heading_deg: telemetry.heading_deg,
+            roll_deg: telemetry.roll_deg,
+            pitch_deg: telemetry.pitch_deg,
+            yaw_deg: telemetry.yaw_deg,
+            fix_quality: narrow("fix_quality", telemetry.fix_quality)?,
+            satellites: narrow("satellites", telemetry.satellites)?,
+        })
+    }
+}
+
+impl From<FrameMetadata> for proto::FrameMetadata {
+    fn from(metadata: FrameMetadata) -> Self {
+        Self {
+            device_id: metadata.device_id,
+            location: metadata
+                .location
+                .map(|(latitude, longitude)| proto::Location {
+                    latitude,
+                    longitude,
+                }),
+            width: metadata.resolution.0,
+            height: metadata.resolution.1,
+            fps: metadata.fps,
+            codec: metadata.codec,
+            telemetry: metadata.telemetry.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<proto::FrameMetadata> for FrameMetadata {
+    type Error = ImmutableEncryptionError;
+
+    fn try_from(metadata: proto::FrameMetadata) -> Result<Self> {
+        Ok(Self {
+            device_id: metadata.device_id,
+            location: metadata.location.map(|l| (l.latitude, l.longitude)),
+            resolution: (metadata.width, metadata.height),
+            fps: metadata.fps,
+            codec: metadata.codec,
+            telemetry: metadata.telemetry.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<VideoFrame> for proto::VideoFrame {
+    fn from(frame: VideoFrame) -> Self {
+        Self {
+            timestamp: frame.timestamp,
+            sequence: frame.sequence,
+            data: frame.data,
+            metadata: Some(frame.metadata.into()),
+            signature: frame.signature.unwrap_or_default(),
+            format_version: FORMAT_VERSION,
+        }
+    }
+}
+
+impl TryFrom<proto::VideoFrame> for VideoFrame {
+    type Error = ImmutableEncryptionError;
+
+    fn try_from(frame: proto::VideoFrame) -> Result<Self> {
+        check_version("VideoFrame", frame.format_version)?;
+        let metadata = frame.metadata.ok_or_else(|| {
+            ImmutableEncryptionError::InvalidRequest("VideoFrame has no metadata".to_string())
+        })?;
+        Ok(Self {
+            timestamp: frame.timestamp,
+            sequence: frame.sequence,
+            data: frame.data,
+            metadata: metadata.try_into()?,
+            signature: Some(frame.signature).filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+impl From<BlockchainAnchor> for proto::BlockchainAnchor {
+    fn from(anchor: BlockchainAnchor) -> Self {
+        Self {
+            chain: anchor.chain,
+            transaction_hash: anchor.transaction_hash,
+            block_number: anchor.block_number,
+            timestamp: anchor.timestamp,
+            proof: anchor.proof,
+            format_version: FORMAT_VERSION,
+        }
+    }
+}
+
+impl TryFrom<proto::BlockchainAnchor> for BlockchainAnchor {
+    type Error = ImmutableEncryptionError;
+
+    fn try_from(anchor: proto::BlockchainAnchor) -> Result<Self> {
+        check_version("BlockchainAnchor", anchor.format_version)?;
+        Ok(Self {
+            chain: anchor.chain,
+            transaction_hash: anchor.transaction_hash,
+            block_number: anchor.block_number,
+            timestamp: anchor.timestamp,
+            proof: anchor.proof,
+        })
+    }
+}
+
+impl From<Cipher> for proto::Cipher {
+    fn from(cipher: Cipher) -> Self {
+        match cipher {
+            Cipher::Aes256Gcm => Self::Aes256Gcm,
+            Cipher::ChaCha20Poly1305 => Self::Chacha20Poly1305,
+            Cipher::XChaCha20Poly1305 => Self::Xchacha20Poly1305,
+            Cipher::Aes256GcmSiv => Self::Aes256GcmSiv,
+        }
+    }
+}
+
+impl From<EncryptedFrame> for proto::EncryptedFrame {
+    fn from(frame: EncryptedFrame) -> Self {
+        Self {
+            sequence: frame.sequence,
+            device_id: frame.device_id,
+            ciphertext: frame.ciphertext,
+            hash: frame.hash,
+            previous_hash: frame.previous_hash,
+            nonce: frame.nonce,
+            timestamp: frame.timestamp,
+            blockchain_anchors: frame
+                .blockchain_anchors
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            cipher: proto::Cipher::from(frame.cipher).into(),
+            compressed: frame.compressed,
+            attestation: frame
+                .attestation
+                .map(|attestation| proto::HardwareAttestation {
+                    quote: attestation.quote,
+                    signature: attestation.signature,
+                }),
+            device_signature: frame
+                .device_signature
+                .map(|signature| proto::DeviceSignature {
+                    payload_hash: signature.payload_hash,
+                    signature: signature.signature,
+                }),
+            format_version: FORMAT_VERSION,
+        }
+    }
+}
+
+impl TryFrom<proto::EncryptedFrame> for EncryptedFrame {
+    type Error = ImmutableEncryptionError;
+
+    fn try_from(frame: proto::EncryptedFrame) -> Result<Self> {
+        check_version("EncryptedFrame", frame.format_version)?;
+        // An unknown cipher can't be decrypted, so it is refused rather than
+        // read as the default
+        let cipher = match proto::Cipher::try_from(frame.cipher) {
+            Ok(proto::Cipher::Aes256Gcm) => Cipher::Aes256Gcm,
+            Ok(proto::Cipher::Chacha20Poly1305) => Cipher::ChaCha20Poly1305,
+            Ok(proto::Cipher::Xchacha20Poly1305) => Cipher::XChaCha20Poly1305,
+            Ok(proto::Cipher::Aes256GcmSiv) => Cipher::Aes256GcmSiv,
+            Err(_) => {
+                return Err(ImmutableEncryptionError::InvalidRequest(format!(
+                    "EncryptedFrame has unknown cipher {}",
+                    frame.cipher
+                )))
+            }
+        };
+        Ok(Self {
+            sequence: frame.sequence,
+            device_id: frame.device_id,
+            ciphertext: frame.ciphertext,
+            hash: frame.hash,
+            previous_hash: frame.previous_hash,
+            nonce: frame.nonce,
+            timestamp: frame.timestamp,
+            blockchain_anchors: frame
+                .blockchain_anchors
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+            cipher,
+            compressed: frame.compressed,
+            attestation: frame.attestation.map(|attestation| HardwareAttestation {
+                quote: attestation.quote,
+                signature: attestation.signature,
+            }),
+            device_signature: frame.device_signature.map(|signature| DeviceSignature {
+                payload_hash: signature.payload_hash,
+                signature: signature.signature,
+            }),
+        })
+    }
+}
+
+impl From<CustodyEntry> for proto::CustodyEntry {
+    fn from(entry: CustodyEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            actor: entry.actor,
+            action: entry.action,
+            signature: entry.signature,
+            blockchain_reference: entry.blockchain_reference,
+        }
+    }
+}
+
+impl From<proto::CustodyEntry> for CustodyEntry {
+    fn from(entry: proto::CustodyEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            actor: entry.actor,
+            action: entry.action,
+            signature: entry.signature,
+            blockchain_reference: entry.blockchain_reference,
+        }
+    }
+}
+
+impl From<LegalCompliance> for proto::LegalCompliance {
+    fn from(compliance: LegalCompliance) -> Self {
+        Self {
+            standards_met: compliance.standards_met,
+            certifications: compliance.certifications,
+            jurisdiction_compliance: compliance.jurisdiction_compliance,
+        }
+    }
+}
+
+impl From<proto::LegalCompliance> for LegalCompliance {
+    fn from(compliance: proto::LegalCompliance) -> Self {
+        Self {
+            standards_met: compliance.standards_met,
+            certifications: compliance.certifications,
+            jurisdiction_compliance: compliance.jurisdiction_compliance,
+        }
+    }
+}
+
+impl From<CourtReport> for proto::CourtReport {
+    fn from(report: CourtReport) -> Self {
+        Self {
+            evidence_id: report.evidence_id,
+            chain_of_custody: report
+                .chain_of_custody
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            cryptographic_proofs: report.cryptographic_proofs,
+            legal_compliance: Some(report.legal_compliance.into()),
+            generated_at: report.generated_at,
+            derived_renditions_json: report
+                .derived_renditions
+                .iter()
+                .filter_map(|r| serde_json::to_string(r).ok())
+                .collect(),
+            time_sync_json: report
+                .time_sync
+                .as_ref()
+                .and_then(|t| serde_json::to_string(t).ok())
+                .unwrap_or_default(),
+            approvals_json: report
+                .approvals
+                .iter()
+                .filter_map(|a| serde_json::to_string(a).ok())
+                .collect(),
+            format_version: FORMAT_VERSION,
+        }
+    }
+}
+
+impl TryFrom<proto::CourtReport> for CourtReport {
+    type Error = ImmutableEncryptionError;
+
+    fn try_from(report: proto::CourtReport) -> Result<Self> {
+        check_version("CourtReport", report.format_version)?;
+        Ok(Self {
+            evidence_id: report.evidence_id,
+            chain_of_custody: report
+                .chain_of_custody
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            cryptographic_proofs: report.cryptographic_proofs,
+            legal_compliance: report
+                .legal_compliance
+                .map(Into::into)
+                .unwrap_or(LegalCompliance {
+                    standards_met: Vec::new(),
+                    certifications: Vec::new(),
+                    jurisdiction_compliance: Vec::new(),
+                }),
+            generated_at: report.generated_at,
+            derived_renditions: report
+                .derived_renditions_json
+                .iter()
+                .map(|json| serde_json::from_str(json))
+                .collect::<std::result::Result<_, _>>()?,
+            time_sync: if report.time_sync_json.is_empty() {
+                None
+            } else {
+                Some(serde_json::from_str(&report.time_sync_json)?)
+            },
+            approvals: report
+                .approvals_json
+                .iter()
+                .map(|json| serde_json::from_str(json))
+                .collect::<std::result::Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_round_trip_through_either_encoding() -> Result<()> {
+        let frame = EncryptedFrame {
+            sequence: 42,
+            device_id: "cam_1".to_string(),
+            ciphertext: vec![9, 8, 7],
+            hash: "a".repeat(64),
+            previous_hash: "0".repeat(64),
+            nonce: vec![1; 12],
+            timestamp: 1_700_000_000,
+            blockchain_anchors: vec![BlockchainAnchor {
+                chain: "bitcoin".to_string(),
+                transaction_hash: "tx".to_string(),
+                block_number: 800_000,
+                timestamp: 1_700_000_100,
+                proof: "proof".to_string(),
+            }],
+            cipher: Cipher::ChaCha20Poly1305,
+            compressed: true,
+            attestation: Some(HardwareAttestation {
+                quote: vec![0xFF, 0x54, 0x43, 0x47],
+                signature: vec![5; 64],
+            }),
+            device_signature: Some(DeviceSignature {
+                payload_hash: "c".repeat(64),
+                signature: vec![6; 64],
+            }),
+        };
+
+        for encoding in [
+            FrameEncoding::Json,
+            FrameEncoding::Protobuf,
+            FrameEncoding::Rkyv,
+        ] {
+            let decoded = decode_frame(&encode_frame(&frame, encoding)?)?;
+            assert_eq!(decoded.hash, frame.hash);
+            assert_eq!(decoded.nonce, frame.nonce);
+            assert_eq!(decoded.cipher, Cipher::ChaCha20Poly1305);
+            assert!(decoded.compressed);
+            assert_eq!(decoded.blockchain_anchors[0].block_number, 800_000);
+            assert_eq!(decoded.attestation, frame.attestation);
+            assert_eq!(decoded.device_signature, frame.device_signature);
+        }
+
+        // Chain checks read a tagged rkyv record in place
+        let stored = migration::tag(encode_frame(&frame, FrameEncoding::Rkyv)?);
+        let stored = StoredFrame::read(stored)?;
+        assert!(matches!(stored, StoredFrame::Archived { .. }));
+        assert_eq!(stored.link().hash(), frame.hash);
+        assert_eq!(stored.link().sequence(), 42);
+        assert_eq!(stored.anchor_blocks(), vec![("bitcoin", 800_000)]);
+        assert_eq!(stored.link().attestation().map(|(_, s)| s.len()), Some(64));
+        assert_eq!(stored.link().device_id(), "cam_1");
+        assert_eq!(
+            stored.link().device_signature().map(|(h, _)| h.len()),
+            Some(64)
+        );
+        let json = migration::tag(encode_frame(&frame, FrameEncoding::Json)?);
+        assert!(matches!(StoredFrame::read(json)?, StoredFrame::Decoded(_)));
+
+        let frame_attestation = frame.attestation.clone();
+        let mut newer = proto::EncryptedFrame::from(frame);
+        newer.format_version = FORMAT_VERSION + 1;
+        assert!(decode_frame(&newer.encode_to_vec()).is_err());
+        newer.format_version = 0;
+        assert!(decode_frame(&newer.encode_to_vec()).is_ok());
+
+        // rkyv frames archived by earlier versions are rewritten on read
+        let legacy = EncryptedFrameV1 {
+            sequence: 7,
+            device_id: "cam_1".to_string(),
+            ciphertext: vec![1],
+            hash: "b".repeat(64),
+            previous_hash: "0".repeat(64),
+            nonce: vec![0; 12],
+            timestamp: 1_700_000_000,
+            blockchain_anchors: Vec::new(),
+            cipher: Cipher::Aes256Gcm,
+            compressed: false,
+        };
+        let archive = rkyv::to_bytes::<rancor::Error>(&legacy)
+            .map_err(|e| ImmutableEncryptionError::storage(&e.to_string()))?;
+        let stored = [&[0xFE, 1], RKYV_MAGIC, &archive].concat();
+        let stored = StoredFrame::read(stored)?;
+        assert_eq!(stored.link().sequence(), 7);
+        assert!(stored.link().attestation().is_none());
+        assert!(stored.link().device_signature().is_none());
+
+        let attested = EncryptedFrameV2 {
+            sequence: 8,
+            device_id: "cam_1".to_string(),
+            ciphertext: vec![1],
+            hash: "c".repeat(64),
+            previous_hash: "b".repeat(64),
+            nonce: vec![0; 12],
+            timestamp: 1_700_000_001,
+            blockchain_anchors: Vec::new(),
+            cipher: Cipher::Aes256Gcm,
+            compressed: false,
+            attestation: frame_attestation,
+        };
+        let archive = rkyv::to_bytes::<rancor::Error>(&attested)
+            .map_err(|e| ImmutableEncryptionError::storage(&e.to_string()))?;
+        let stored = StoredFrame::read([&[0xFE, 2], RKYV_MAGIC, &archive].concat())?;
+        assert_eq!(stored.link().attestation().map(|(q, _)| q.len()), Some(4));
+        assert!(stored.link().device_signature().is_none());
+        Ok(())
+    }
+}
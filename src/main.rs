@@ -1,38 +1,280 @@
-// Native Encryption Server
-// Complete replacement for 68+ Rust dependencies
-
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::thread;
-use std::time::Instant;
-use std::io;
-use std::path::Path;
-use std::env;
-use serde::{Deserialize, Serialize};
-
-use crate::crypto;
-use crate::server;
-
-mod crypto;
-mod error;
-mod server;
-
-use crypto::encryption_server::EncryptionServer;
-
-// Main application
-fn main() {
-    println!("🚀 Starting Native Encryption Server");
-    println!("📦 Zero Dependencies: 68 external packages eliminated");
-    println!("⚡ Performance: Native cryptographic operations");
-    println!("🔒 Security: Post-quantum cryptography ready");
-    println!("🎯 Technical Excellence: Complete Rust implementation");
-    
-    // Start the native server
-    match EncryptionServer::new().start() {
-        Ok(_) => println!("✅ Native server started successfully!"),
-        Err(e) => {
-            eprintln!("❌ Failed to start server: {}", e);
-            std::process::exit(1);
-        }
-    }
-}
\ No newline at end of file
+// Standalone crypto service: hashing, AES-256-GCM and Kyber1024 sealing over
+// HTTP, backed by the same primitives the encryption node uses.
+
+use clap::{Arg, Command};
+use ring::rand::SystemRandom;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use tracing::info;
+use warp::http::StatusCode;
+use warp::Filter;
+
+use immutable_encryption::{
+    config::{Config, LoggingConfig},
+    crypto::{self, PostQuantumCiphertext},
+    error::ImmutableEncryptionError,
+    trace,
+};
+
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct HashRequest {
+    algorithm: String,   // sha256, blake3 or hmac-sha256
+    data: String,        // hex
+    key: Option<String>, // hex, required for hmac-sha256
+}
+
+#[derive(Debug, Deserialize)]
+struct EncryptRequest {
+    data: String,        // hex
+    key: Option<String>, // hex, 32 bytes; a fresh key is generated and returned when unset
+}
+
+#[derive(Debug, Deserialize)]
+struct DecryptRequest {
+    key: String,
+    ciphertext: String,
+    nonce: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostQuantumEncryptRequest {
+    public_key: String,
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostQuantumDecryptRequest {
+    secret_key: String,
+    kem_ciphertext: String,
+    ciphertext: String,
+    nonce: String,
+}
+
+#[derive(Debug)]
+struct ApiRejection {
+    status: StatusCode,
+    error: ImmutableEncryptionError,
+}
+
+impl warp::reject::Reject for ApiRejection {}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let matches = Command::new("immutable-encryption")
+        .version("0.1.0")
+        .about("Hashing, AES-256-GCM and post-quantum encryption over HTTP")
+        .arg(
+            Arg::new("bind")
+                .short('b')
+                .long("bind")
+                .value_name("ADDR")
+                .default_value("127.0.0.1:8081")
+                .help("Address to listen on"),
+        )
+        .get_matches();
+
+    let addr: SocketAddr = matches
+        .get_one::<String>("bind")
+        .map(String::as_str)
+        .unwrap_or_default()
+        .parse()
+        .map_err(|e| format!("Invalid bind address: {}", e))?;
+
+    // Stdout only; this service has no data directory to log into
+    let _tracing = trace::init(&LoggingConfig {
+        file_path: None,
+        ..Config::default().logging
+    })?;
+
+    let api = routes().with(warp::log("immutable_encryption::crypto_server"));
+    let (bound, server) = warp::serve(api).try_bind_with_graceful_shutdown(addr, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })?;
+    info!("Crypto service listening on {}", bound);
+    server.await;
+
+    Ok(())
+}
+
+fn routes() -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone
+{
+    let health = warp::path!("health").and(warp::get()).map(|| {
+        warp::reply::json(&serde_json::json!({
+            "status": "healthy",
+            "algorithms": ["sha256", "blake3", "hmac-sha256", "aes-256-gcm", "kyber1024"],
+            "post_quantum": true
+        }))
+    });
+
+    let hash = warp::path!("hash")
+        .and(warp::post())
+        .and(json_body())
+        .and_then(|request: HashRequest| async move {
+            let data = decode_hex("data", &request.data)?;
+            let hash = match request.algorithm.as_str() {
+                "sha256" => crypto::sha256_hex(&data),
+                "blake3" => crypto::blake3_hex(&data),
+                "hmac-sha256" => {
+                    let key = request
+                        .key
+                        .as_deref()
+                        .ok_or_else(|| bad_request("hmac-sha256 requires a key"))?;
+                    crypto::hmac_sha256_hex(&decode_hex("key", key)?, &data)
+                }
+                other => return Err(bad_request(&format!("Unsupported algorithm: {}", other))),
+            };
+            Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({
+                "algorithm": request.algorithm,
+                "hash": hash
+            })))
+        });
+
+    let encrypt = warp::path!("encrypt")
+        .and(warp::post())
+        .and(json_body())
+        .and_then(|request: EncryptRequest| async move {
+            let data = decode_hex("data", &request.data)?;
+            let (key, generated) = match &request.key {
+                Some(key) => (decode_hex("key", key)?, false),
+                None => (crypto::generate_key().map_err(crypto_failure)?, true),
+            };
+            let (ciphertext, nonce) =
+                crypto::seal(&key, &data, &SystemRandom::new()).map_err(crypto_failure)?;
+
+            let mut body = serde_json::json!({
+                "algorithm": "aes-256-gcm",
+                "ciphertext": hex::encode(ciphertext),
+                "nonce": hex::encode(nonce)
+            });
+            if generated {
+                body["key"] = hex::encode(key).into();
+            }
+            Ok::<_, warp::Rejection>(warp::reply::json(&body))
+        });
+
+    let decrypt = warp::path!("decrypt")
+        .and(warp::post())
+        .and(json_body())
+        .and_then(|request: DecryptRequest| async move {
+            let plaintext = crypto::open(
+                &decode_hex("key", &request.key)?,
+                &decode_hex("ciphertext", &request.ciphertext)?,
+                &decode_hex("nonce", &request.nonce)?,
+            )
+            .map_err(crypto_failure)?;
+            Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({
+                "data": hex::encode(plaintext)
+            })))
+        });
+
+    let pq_keypair = warp::path!("post-quantum" / "keypair")
+        .and(warp::post())
+        .map(|| {
+            let (public_key, secret_key) = crypto::post_quantum_keypair();
+            warp::reply::json(&serde_json::json!({
+                "algorithm": "kyber1024",
+                "public_key": hex::encode(public_key),
+                "secret_key": hex::encode(secret_key)
+            }))
+        });
+
+    let pq_encrypt = warp::path!("post-quantum" / "encrypt")
+        .and(warp::post())
+        .and(json_body())
+        .and_then(|request: PostQuantumEncryptRequest| async move {
+            let sealed = crypto::post_quantum_seal(
+                &decode_hex("public_key", &request.public_key)?,
+                &decode_hex("data", &request.data)?,
+            )
+            .map_err(crypto_failure)?;
+            Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({
+                "algorithm": "kyber1024+aes-256-gcm",
+                "kem_ciphertext": hex::encode(sealed.kem_ciphertext),
+                "ciphertext": hex::encode(sealed.ciphertext),
+                "nonce": hex::encode(sealed.nonce)
+            })))
+        });
+
+    let pq_decrypt = warp::path!("post-quantum" / "decrypt")
+        .and(warp::post())
+        .and(json_body())
+        .and_then(|request: PostQuantumDecryptRequest| async move {
+            let sealed = PostQuantumCiphertext {
+                kem_ciphertext: decode_hex("kem_ciphertext", &request.kem_ciphertext)?,
+                ciphertext: decode_hex("ciphertext", &request.ciphertext)?,
+                nonce: decode_hex("nonce", &request.nonce)?,
+            };
+            let plaintext =
+                crypto::post_quantum_open(&decode_hex("secret_key", &request.secret_key)?, &sealed)
+                    .map_err(crypto_failure)?;
+            Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({
+                "data": hex::encode(plaintext)
+            })))
+        });
+
+    health
+        .or(hash)
+        .or(encrypt)
+        .or(decrypt)
+        .or(pq_keypair)
+        .or(pq_encrypt)
+        .or(pq_decrypt)
+        .recover(handle_rejection)
+}
+
+fn json_body<T: serde::de::DeserializeOwned + Send + 'static>(
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(MAX_BODY_BYTES).and(warp::body::json())
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, warp::Rejection> {
+    hex::decode(value).map_err(|e| bad_request(&format!("{} is not valid hex: {}", field, e)))
+}
+
+fn bad_request(message: &str) -> warp::Rejection {
+    warp::reject::custom(ApiRejection {
+        status: StatusCode::BAD_REQUEST,
+        error: ImmutableEncryptionError::invalid_request(message),
+    })
+}
+
+// Wrong key sizes and failed authentication land here, so they're the
+// caller's fault rather than the server's
+fn crypto_failure(error: ImmutableEncryptionError) -> warp::Rejection {
+    warp::reject::custom(ApiRejection {
+        status: StatusCode::UNPROCESSABLE_ENTITY,
+        error,
+    })
+}
+
+async fn handle_rejection(
+    rejection: warp::Rejection,
+) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let (status, body) = if let Some(api) = rejection.find::<ApiRejection>() {
+        (api.status, api.error.body())
+    } else if rejection.is_not_found() {
+        (
+            StatusCode::NOT_FOUND,
+            ImmutableEncryptionError::not_found("no such route").body(),
+        )
+    } else if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ImmutableEncryptionError::invalid_request("request body too large").body(),
+        )
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            ImmutableEncryptionError::invalid_request("method not allowed").body(),
+        )
+    } else {
+        (
+            StatusCode::BAD_REQUEST,
+            ImmutableEncryptionError::InvalidRequest(format!("{:?}", rejection)).body(),
+        )
+    };
+
+    Ok(warp::reply::with_status(warp::reply::json(&body), status))
+}
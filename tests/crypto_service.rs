@@ -0,0 +1,202 @@
+// End-to-end checks of the standalone crypto service (src/main.rs): starts the
+// binary on a free port and exercises each endpoint over HTTP.
+
+use serde_json::{json, Value};
+use std::net::TcpListener;
+use std::process::{Child, Command};
+use std::time::Duration;
+
+struct CryptoService {
+    child: Child,
+    base: String,
+}
+
+impl CryptoService {
+    async fn start() -> Self {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port")
+            .port();
+        let child = Command::new(env!("CARGO_BIN_EXE_immutable-encryption"))
+            .args(["--bind", &format!("127.0.0.1:{}", port)])
+            .spawn()
+            .expect("start crypto service");
+        let service = Self {
+            child,
+            base: format!("http://127.0.0.1:{}", port),
+        };
+
+        for _ in 0..100 {
+            if reqwest::get(format!("{}/health", service.base))
+                .await
+                .is_ok()
+            {
+                return service;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("crypto service didn't come up on port {}", port);
+    }
+
+    async fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        let response = reqwest::Client::new()
+            .post(format!("{}{}", self.base, path))
+            .json(&body)
+            .send()
+            .await
+            .expect("request");
+        let status = response.status().as_u16();
+        (status, response.json().await.expect("JSON body"))
+    }
+}
+
+impl Drop for CryptoService {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[tokio::test]
+async fn test_hashes_match_reference_vectors() {
+    let service = CryptoService::start().await;
+
+    let (status, body) = service
+        .post(
+            "/hash",
+            json!({"algorithm": "sha256", "data": hex::encode("abc")}),
+        )
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body["hash"],
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+
+    let (_, body) = service
+        .post(
+            "/hash",
+            json!({"algorithm": "blake3", "data": hex::encode("abc")}),
+        )
+        .await;
+    assert_eq!(
+        body["hash"],
+        "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+    );
+
+    // RFC 4231 test case 2
+    let (_, body) = service
+        .post(
+            "/hash",
+            json!({
+                "algorithm": "hmac-sha256",
+                "data": hex::encode("what do ya want for nothing?"),
+                "key": hex::encode("Jefe")
+            }),
+        )
+        .await;
+    assert_eq!(
+        body["hash"],
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+
+    let (status, _) = service
+        .post("/hash", json!({"algorithm": "hmac-sha256", "data": "00"}))
+        .await;
+    assert_eq!(status, 400);
+    let (status, _) = service
+        .post("/hash", json!({"algorithm": "md5", "data": "00"}))
+        .await;
+    assert_eq!(status, 400);
+    let (status, _) = service
+        .post("/hash", json!({"algorithm": "sha256", "data": "not hex"}))
+        .await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn test_aes_gcm_round_trip() {
+    let service = CryptoService::start().await;
+    let data = hex::encode("evidence frame");
+
+    // Without a key the service generates one and returns it
+    let (status, sealed) = service.post("/encrypt", json!({ "data": data })).await;
+    assert_eq!(status, 200);
+    let key = sealed["key"].as_str().expect("generated key");
+    assert_eq!(hex::decode(key).expect("hex key").len(), 32);
+    assert_ne!(sealed["ciphertext"], data);
+
+    let (status, opened) = service
+        .post(
+            "/decrypt",
+            json!({"key": key, "ciphertext": sealed["ciphertext"], "nonce": sealed["nonce"]}),
+        )
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(opened["data"], data);
+
+    // A supplied key isn't echoed back, and another key doesn't open it
+    let (_, sealed) = service
+        .post("/encrypt", json!({"data": data, "key": "11".repeat(32)}))
+        .await;
+    assert!(sealed.get("key").is_none());
+    let (status, _) = service
+        .post(
+            "/decrypt",
+            json!({
+                "key": "22".repeat(32),
+                "ciphertext": sealed["ciphertext"],
+                "nonce": sealed["nonce"]
+            }),
+        )
+        .await;
+    assert_eq!(status, 422);
+
+    let (status, _) = service
+        .post("/encrypt", json!({"data": data, "key": "11"}))
+        .await;
+    assert_eq!(status, 422);
+}
+
+#[tokio::test]
+async fn test_post_quantum_round_trip() {
+    let service = CryptoService::start().await;
+    let data = hex::encode("evidence frame");
+
+    let (status, keypair) = service.post("/post-quantum/keypair", json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(keypair["algorithm"], "kyber1024");
+
+    let (status, sealed) = service
+        .post(
+            "/post-quantum/encrypt",
+            json!({"public_key": keypair["public_key"], "data": data}),
+        )
+        .await;
+    assert_eq!(status, 200);
+
+    let mut request = json!({
+        "secret_key": keypair["secret_key"],
+        "kem_ciphertext": sealed["kem_ciphertext"],
+        "ciphertext": sealed["ciphertext"],
+        "nonce": sealed["nonce"]
+    });
+    let (status, opened) = service.post("/post-quantum/decrypt", request.clone()).await;
+    assert_eq!(status, 200);
+    assert_eq!(opened["data"], data);
+
+    // Another keypair's secret key doesn't open it
+    let (_, other) = service.post("/post-quantum/keypair", json!({})).await;
+    request["secret_key"] = other["secret_key"].clone();
+    let (status, _) = service.post("/post-quantum/decrypt", request).await;
+    assert_eq!(status, 422);
+}
+
+#[tokio::test]
+async fn test_unknown_routes_answer_in_json() {
+    let service = CryptoService::start().await;
+
+    let (status, body) = service.post("/sign", json!({})).await;
+    assert_eq!(status, 404);
+    assert!(body.is_object());
+}
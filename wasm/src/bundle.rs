@@ -0,0 +1,332 @@
+use hmac::{Hmac, Mac};
+use immutable_encryption_core::Cipher;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Read};
+
+// Must match the node's export layout
+const BUNDLE_ENTRY: &str = "bundle.ndjson";
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+// Same fields as the node's report, plus whether signatures were checked at
+// all: without the verification key only hashes and links can be
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleVerification {
+    pub evidence_id: Option<String>,
+    pub is_valid: bool,
+    pub manifest_valid: bool,
+    pub signature_valid: bool,
+    pub signatures_checked: bool,
+    pub frame_count: u64,
+    pub anomalies: Vec<String>,
+}
+
+// The parts of a sealed frame the chain checks read; the rest is ignored
+#[derive(Debug, Deserialize)]
+struct Frame {
+    sequence: u64,
+    hash: String,
+    previous_hash: String,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+    timestamp: u64,
+    #[serde(default)]
+    cipher: Cipher, // sets the nonce length
+}
+
+// Kept as raw JSON so the hash can be recomputed over exactly the fields the
+// node serialized, including any this verifier does not know about
+struct Manifest {
+    body: Value,
+    manifest_hash: String,
+    signature: String,
+    first_hash: Option<String>,
+    last_hash: Option<String>,
+    frame_count: u64,
+}
+
+impl Manifest {
+    fn parse(body: Value) -> Result<Self, String> {
+        let text = |field: &str| body.get(field).and_then(Value::as_str).map(str::to_string);
+        Ok(Self {
+            manifest_hash: text("manifest_hash").ok_or("Manifest has no hash")?,
+            signature: text("signature").unwrap_or_default(),
+            first_hash: text("first_hash"),
+            last_hash: text("last_hash"),
+            frame_count: body
+                .get("frame_count")
+                .and_then(Value::as_u64)
+                .ok_or("Manifest has no frame count")?,
+            body,
+        })
+    }
+
+    fn session_id(&self) -> Option<String> {
+        self.body
+            .get("session_id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    // Hash over everything except the hash itself, its signature and anchors
+    fn compute_hash(&self) -> Result<String, String> {
+        let mut body = self.body.clone();
+        let fields = body.as_object_mut().ok_or("Manifest is not an object")?;
+        fields.insert("manifest_hash".to_string(), Value::from(""));
+        fields.insert("signature".to_string(), Value::from(""));
+        fields.insert("anchors".to_string(), Value::Array(Vec::new()));
+        let serialized = serde_json::to_vec(&body).map_err(|e| e.to_string())?;
+        Ok(hex::encode(Sha256::digest(serialized)))
+    }
+}
+
+// Accepts either a bare `.ndjson` bundle or a `.tar.zst` export archive
+pub fn verify(bytes: &[u8], key: Option<&[u8]>) -> Result<BundleVerification, String> {
+    if !bytes.starts_with(&ZSTD_MAGIC) {
+        return verify_ndjson(bytes, key);
+    }
+    let decoder = ruzstd::StreamingDecoder::new(bytes).map_err(|e| e.to_string())?;
+    let mut archive = tar::Archive::new(decoder);
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if &*entry.path_bytes() == BUNDLE_ENTRY.as_bytes() {
+            return verify_ndjson(BufReader::new(entry), key);
+        }
+    }
+    Err(format!("Archive has no {}", BUNDLE_ENTRY))
+}
+
+// The node's bundle checks, line for line
+fn verify_ndjson<R: BufRead>(reader: R, key: Option<&[u8]>) -> Result<BundleVerification, String> {
+    let mut result = BundleVerification {
+        evidence_id: None,
+        is_valid: false,
+        manifest_valid: false,
+        signature_valid: false,
+        signatures_checked: key.is_some(),
+        frame_count: 0,
+        anomalies: Vec::new(),
+    };
+    let mut digest = Sha256::new();
+    let mut manifest: Option<Manifest> = None;
+    let mut previous: Option<Frame> = None;
+    let mut first_hash = None;
+    let mut trailer_seen = false;
+
+    for line in reader.split(b'\n') {
+        let mut line = line.map_err(|e| e.to_string())?;
+        if line.is_empty() {
+            continue;
+        }
+        if trailer_seen {
+            result
+                .anomalies
+                .push("Data follows the bundle signature".to_string());
+            break;
+        }
+
+        let mut record: Value = serde_json::from_slice(&line).map_err(|e| e.to_string())?;
+        line.push(b'\n');
+        match record.get("type").and_then(Value::as_str) {
+            Some("manifest") if manifest.is_none() => {
+                let m = Manifest::parse(record["manifest"].take())?;
+                result.evidence_id = m.session_id();
+                result.manifest_valid = m.compute_hash()? == m.manifest_hash
+                    && signed(key, m.manifest_hash.as_bytes(), &m.signature);
+                if !result.manifest_valid {
+                    result.anomalies.push(format!(
+                        "Manifest {} failed its signature check",
+                        m.manifest_hash
+                    ));
+                }
+                manifest = Some(m);
+            }
+            Some("manifest") => {
+                result
+                    .anomalies
+                    .push("Bundle has more than one manifest".to_string());
+            }
+            Some("frame") => {
+                let frame: Frame =
+                    serde_json::from_value(record["frame"].take()).map_err(|e| e.to_string())?;
+                result
+                    .anomalies
+                    .extend(frame_anomalies(previous.as_ref(), &frame));
+                first_hash.get_or_insert_with(|| frame.hash.clone());
+                result.frame_count += 1;
+                previous = Some(frame);
+            }
+            Some("signature") => {
+                trailer_seen = true;
+                let field = |name: &str| record.get(name).and_then(Value::as_str).unwrap_or("");
+                let (sha256, signature) = (field("sha256"), field("signature"));
+                let expected = hex::encode(digest.clone().finalize());
+                result.signature_valid =
+                    sha256 == expected && signed(key, sha256.as_bytes(), signature);
+                if !result.signature_valid {
+                    result
+                        .anomalies
+                        .push("Bundle signature does not match its contents".to_string());
+                }
+                continue;
+            }
+            other => return Err(format!("Unknown bundle record {:?}", other)),
+        }
+        digest.update(&line);
+    }
+
+    match &manifest {
+        Some(m) => {
+            let last_hash = previous.map(|f| f.hash);
+            if first_hash != m.first_hash
+                || last_hash != m.last_hash
+                || result.frame_count != m.frame_count
+            {
+                result.anomalies.push(format!(
+                    "Frames do not match session manifest {} ({} of {} frames present)",
+                    m.manifest_hash, result.frame_count, m.frame_count
+                ));
+            }
+        }
+        None => result.anomalies.push("Bundle has no manifest".to_string()),
+    }
+    if !trailer_seen {
+        result
+            .anomalies
+            .push("Bundle is truncated: no signature record".to_string());
+    }
+
+    result.is_valid = result.anomalies.is_empty();
+    Ok(result)
+}
+
+// Without a key the signature is taken on trust and reported as unchecked
+fn signed(key: Option<&[u8]>, data: &[u8], signature: &str) -> bool {
+    let key = match key {
+        Some(key) => key,
+        None => return true,
+    };
+    let (mac, tag) = match (Hmac::<Sha256>::new_from_slice(key), hex::decode(signature)) {
+        (Ok(mac), Ok(tag)) => (mac, tag),
+        _ => return false,
+    };
+    mac.chain_update(data).verify_slice(&tag).is_ok()
+}
+
+fn frame_anomalies(previous: Option<&Frame>, frame: &Frame) -> Vec<String> {
+    let mut anomalies = Vec::new();
+
+    let well_formed = frame.hash.len() == 64
+        && frame.hash.chars().all(|c| c.is_ascii_hexdigit())
+        && frame.nonce.len() == frame.cipher.nonce_len()
+        && !frame.ciphertext.is_empty();
+    if !well_formed {
+        anomalies.push(format!("Frame {} is malformed", frame.sequence));
+    }
+
+    if let Some(previous) = previous {
+        if frame.sequence != previous.sequence + 1 {
+            anomalies.push(format!(
+                "Sequence gap detected: frame {} to {} (expected {})",
+                previous.sequence,
+                frame.sequence,
+                previous.sequence + 1
+            ));
+        }
+        if frame.previous_hash != previous.hash {
+            anomalies.push(format!(
+                "Hash chain break between frame {} and {}",
+                previous.sequence, frame.sequence
+            ));
+        }
+        if frame.timestamp <= previous.timestamp {
+            anomalies.push(format!(
+                "Timestamp of frame {} does not advance past frame {}",
+                frame.sequence, previous.sequence
+            ));
+        }
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KEY: [u8; 32] = [9; 32];
+
+    fn sign(data: &str) -> String {
+        let mac = Hmac::<Sha256>::new_from_slice(&KEY).unwrap();
+        hex::encode(mac.chain_update(data.as_bytes()).finalize().into_bytes())
+    }
+
+    fn frame(sequence: u64, hash: &str, previous_hash: &str) -> Value {
+        json!({ "type": "frame", "frame": {
+            "sequence": sequence,
+            "device_id": "cam_1",
+            "ciphertext": [1, 2, 3],
+            "hash": hash,
+            "previous_hash": previous_hash,
+            "nonce": [0; 12],
+            "timestamp": 1_700_000_000 + sequence,
+            "blockchain_anchors": [],
+        }})
+    }
+
+    fn bundle(frames: &[Value]) -> Vec<u8> {
+        let (first, last) = ("a".repeat(64), "b".repeat(64));
+        let mut manifest = Manifest::parse(json!({
+            "session_id": "session-1",
+            "device_id": "cam_1",
+            "first_hash": first,
+            "last_hash": last,
+            "frame_count": 2,
+            "manifest_hash": "",
+            "signature": "",
+            "anchors": [],
+        }))
+        .unwrap();
+        let hash = manifest.compute_hash().unwrap();
+        manifest.body["manifest_hash"] = json!(hash);
+        manifest.body["signature"] = json!(sign(&hash));
+
+        let mut out = Vec::new();
+        let record = json!({ "type": "manifest", "manifest": manifest.body });
+        for line in std::iter::once(&record).chain(frames) {
+            out.extend(serde_json::to_vec(line).unwrap());
+            out.push(b'\n');
+        }
+        let sha256 = hex::encode(Sha256::digest(&out));
+        let trailer = json!({ "type": "signature", "sha256": sha256, "signature": sign(&sha256) });
+        out.extend(serde_json::to_vec(&trailer).unwrap());
+        out.push(b'\n');
+        out
+    }
+
+    #[test]
+    fn test_bundle_checks_match_the_node() {
+        let (first, last) = ("a".repeat(64), "b".repeat(64));
+        let good = bundle(&[frame(1, &first, &"0".repeat(64)), frame(2, &last, &first)]);
+
+        let result = verify(&good, Some(&KEY[..])).unwrap();
+        assert!(result.is_valid, "{:?}", result.anomalies);
+        assert_eq!(result.evidence_id.as_deref(), Some("session-1"));
+        assert_eq!(result.frame_count, 2);
+
+        let unchecked = verify(&good, None).unwrap();
+        assert!(unchecked.is_valid && !unchecked.signatures_checked);
+        assert!(!verify(&good, Some(&[1; 32][..])).unwrap().is_valid);
+
+        let broken = bundle(&[frame(1, &first, &"0".repeat(64)), frame(2, &last, &last)]);
+        let result = verify(&broken, Some(&KEY[..])).unwrap();
+        assert!(!result.is_valid);
+        assert!(result.anomalies[0].starts_with("Hash chain break"));
+
+        let truncated = &good[..good.len() - 2];
+        let truncated = &truncated[..truncated.iter().rposition(|&b| b == b'\n').unwrap() + 1];
+        assert!(!verify(truncated, None).unwrap().is_valid);
+    }
+}
@@ -0,0 +1,32 @@
+// The public-verification subset of the node, compiled to WebAssembly so an
+// exported bundle can be checked in a browser without installing anything or
+// trusting the server that produced it. Nothing here can decrypt a frame.
+mod bundle;
+
+use immutable_encryption_core::anchor;
+use wasm_bindgen::prelude::*;
+
+pub use bundle::{verify, BundleVerification};
+pub use immutable_encryption_core::merkle::{InclusionProof, ProofStep, Side};
+
+// Checks an exported `.ndjson` bundle or `.tar.zst` archive and returns a
+// `BundleVerification` as JSON. The chain, hashes and manifest are always
+// checked; signatures only when the hex key from `encryption-node keys
+// verification-key` is given.
+#[wasm_bindgen(js_name = verifyBundle)]
+pub fn verify_bundle(bytes: &[u8], verification_key: Option<String>) -> Result<String, JsError> {
+    let key = verification_key
+        .map(|key| hex::decode(key.trim()))
+        .transpose()
+        .map_err(|e| JsError::new(&format!("Verification key is not hex: {}", e)))?;
+    let result = bundle::verify(bytes, key.as_deref()).map_err(|e| JsError::new(&e))?;
+    serde_json::to_string(&result).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// Checks a Merkle inclusion proof, either bare or as written by
+// `blockchain-anchor` alongside its anchors
+#[wasm_bindgen(js_name = verifyInclusionProof)]
+pub fn verify_inclusion_proof(json: &str) -> Result<bool, JsError> {
+    let anchored = anchor::parse_proof(json).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(anchored.proof.verify())
+}